@@ -2,11 +2,12 @@
 //!
 //! Since the operator modules are crate-private, these benchmarks test the
 //! underlying operations that operators perform: glob matching on filesystems
-//! and regex-based path renaming via the path module.
+//! with compiled pattern sets, and regex-based path renaming via the path
+//! module.
 
 use common_repo::filesystem::MemoryFS;
-use common_repo::path::regex_rename;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use common_repo::path::{regex_rename, regex_rename_with, PatternSet};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
 /// Creates a MemoryFS simulating a typical project structure.
 fn create_project_fs() -> MemoryFS {
@@ -61,8 +62,8 @@ fn create_project_fs() -> MemoryFS {
 
 /// Benchmarks simulating include operator behavior.
 ///
-/// The include operator uses `list_files_glob` to find matching files,
-/// then copies them to the target filesystem.
+/// The include operator compiles its patterns into a `PatternSet`, lists
+/// the matching files, then copies them to the target filesystem.
 fn bench_include_simulation(c: &mut Criterion) {
     let mut group = c.benchmark_group("op_include");
     let source = create_project_fs();
@@ -85,13 +86,12 @@ fn bench_include_simulation(c: &mut Criterion) {
     group.bench_function("multiple_patterns", |b| {
         b.iter(|| {
             let mut target = MemoryFS::new();
-            let patterns = ["src/**/*.rs", "tests/**/*.rs", "docs/**/*.md"];
-            for pattern in patterns {
-                let matches = source.list_files_glob(black_box(pattern)).unwrap();
-                for path in &matches {
-                    if let Some(file) = source.get_file(path) {
-                        target.add_file(path, file.clone()).unwrap();
-                    }
+            let patterns =
+                PatternSet::new(black_box(&["src/**/*.rs", "tests/**/*.rs", "docs/**/*.md"]))
+                    .unwrap();
+            for path in source.list_files_matching(&patterns) {
+                if let Some(file) = source.get_file(&path) {
+                    target.add_file(&path, file.clone()).unwrap();
                 }
             }
             target
@@ -117,8 +117,8 @@ fn bench_include_simulation(c: &mut Criterion) {
 
 /// Benchmarks simulating exclude operator behavior.
 ///
-/// The exclude operator uses `list_files_glob` to find matching files,
-/// then removes them from the filesystem.
+/// The exclude operator compiles its patterns into a `PatternSet`, lists
+/// the matching files, then removes them from the filesystem.
 fn bench_exclude_simulation(c: &mut Criterion) {
     let mut group = c.benchmark_group("op_exclude");
 
@@ -142,12 +142,10 @@ fn bench_exclude_simulation(c: &mut Criterion) {
         b.iter_batched(
             create_project_fs,
             |mut fs| {
-                let patterns = ["target/**/*", "**/*.tmp", "**/*.o"];
-                for pattern in patterns {
-                    let matches = fs.list_files_glob(black_box(pattern)).unwrap();
-                    for path in &matches {
-                        fs.remove_file(path).unwrap();
-                    }
+                let patterns =
+                    PatternSet::new(black_box(&["target/**/*", "**/*.tmp", "**/*.o"])).unwrap();
+                for path in fs.list_files_matching(&patterns) {
+                    fs.remove_file(&path).unwrap();
                 }
                 fs
             },
@@ -222,13 +220,13 @@ fn bench_rename_simulation(c: &mut Criterion) {
             create_project_fs,
             |mut fs| {
                 let files: Vec<_> = fs.list_files();
-                let pattern = "src/module([0-9]+)/(.*)";
+                let pattern = regex::Regex::new(black_box("src/module([0-9]+)/(.*)")).unwrap();
                 let replacement = "lib/mod%[1]s/%[2]s";
 
                 for path in &files {
                     let path_str = path.to_string_lossy();
-                    if let Ok(Some(new_name)) =
-                        regex_rename(black_box(pattern), black_box(replacement), &path_str)
+                    if let Some(new_name) =
+                        regex_rename_with(&pattern, black_box(replacement), &path_str)
                     {
                         if new_name != path_str {
                             let new_path = std::path::Path::new(&new_name).to_path_buf();
@@ -278,6 +276,55 @@ fn bench_glob_patterns(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmarks matching several patterns against large trees, one glob at a
+/// time versus one compiled `PatternSet`.
+fn bench_pattern_set_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pattern_set_scaling");
+    let patterns = [
+        "src/**/*.rs",
+        "**/*.md",
+        "**/Cargo.toml",
+        ".github/**",
+        "**/*.tmp",
+    ];
+
+    for size in [1_000, 10_000, 30_000] {
+        let mut fs = MemoryFS::new();
+        for i in 0..size {
+            let path = match i % 4 {
+                0 => format!("src/dir{}/file{}.rs", i % 97, i),
+                1 => format!("docs/dir{}/page{}.md", i % 31, i),
+                2 => format!("assets/dir{}/image{}.png", i % 53, i),
+                _ => format!("crates/c{}/Cargo.toml", i),
+            };
+            fs.add_file_string(path, "x").unwrap();
+        }
+
+        group.bench_with_input(BenchmarkId::new("per_glob", size), &fs, |b, fs| {
+            b.iter(|| {
+                let mut matches = Vec::new();
+                for pattern in patterns {
+                    let glob = glob::Pattern::new(black_box(pattern)).unwrap();
+                    matches.extend(
+                        fs.list_files()
+                            .into_iter()
+                            .filter(|path| glob.matches(&path.to_string_lossy())),
+                    );
+                }
+                matches
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("pattern_set", size), &fs, |b, fs| {
+            b.iter(|| {
+                let set = PatternSet::new(black_box(&patterns)).unwrap();
+                fs.list_files_matching(&set)
+            })
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_include_simulation,
@@ -285,5 +332,6 @@ criterion_group!(
     bench_rename_patterns,
     bench_rename_simulation,
     bench_glob_patterns,
+    bench_pattern_set_scaling,
 );
 criterion_main!(benches);
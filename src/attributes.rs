@@ -0,0 +1,304 @@
+//! # Path Attributes
+//!
+//! Reads `.gitattributes` files so that merges can decide per path whether a
+//! file is text or binary, and which merge driver to use, instead of guessing
+//! from whether its content decodes as UTF-8.
+//!
+//! Only the attributes that matter to merging are read:
+//!
+//! - `text` / `-text`: the file is text, or binary.
+//! - `binary`: shorthand for `-text -diff -merge`.
+//! - `merge=<driver>`: `yaml`, `json`, `toml`, `ini`, `markdown` or `xml`
+//!   merge the file in a format-aware way, `union` keeps the lines of both
+//!   versions, and `binary` (like `-merge`) keeps the incoming version.
+//!
+//! Patterns follow gitattributes: one without a `/` matches the file name in
+//! any directory, one with a `/` is relative to the root. Later lines
+//! override earlier ones, attribute by attribute. Macros other than `binary`,
+//! negative patterns and `.gitattributes` files in subdirectories are not
+//! supported.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::attributes::{Attributes, MergeDriver};
+//! use std::path::Path;
+//!
+//! let attributes = Attributes::parse("*.svg text merge=xml\n*.png binary\n");
+//! assert!(attributes.is_text(Path::new("logo.svg"), b"<svg/>"));
+//! assert!(!attributes.is_text(Path::new("logo.png"), b"not really a png"));
+//! assert_eq!(attributes.merge_driver(Path::new("icons/a.svg")), Some(MergeDriver::Xml));
+//! ```
+
+use std::path::Path;
+
+use crate::filesystem::MemoryFS;
+use crate::path::PatternSet;
+
+/// The name of the attributes file, at the root of a filesystem.
+pub const ATTRIBUTES_FILENAME: &str = ".gitattributes";
+
+/// How a conflicting file is merged, from its `merge` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeDriver {
+    /// `merge=yaml`
+    Yaml,
+    /// `merge=json`
+    Json,
+    /// `merge=toml`
+    Toml,
+    /// `merge=ini`
+    Ini,
+    /// `merge=markdown`
+    Markdown,
+    /// `merge=xml`
+    Xml,
+    /// `merge=union`: the destination's lines, then the incoming lines it
+    /// does not already have.
+    Union,
+    /// `-merge`, `merge=binary` or `binary`: the incoming version replaces
+    /// the destination's, even where an auto-merge is declared.
+    Binary,
+}
+
+impl MergeDriver {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "ini" => Some(Self::Ini),
+            "markdown" | "md" => Some(Self::Markdown),
+            "xml" => Some(Self::Xml),
+            "union" => Some(Self::Union),
+            "binary" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// One attribute a line sets, in the order the line gives them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Setting {
+    Text(bool),
+    Merge(Option<MergeDriver>),
+}
+
+/// Parsed `.gitattributes` rules, in file order.
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    rules: Vec<(PatternSet, Vec<Setting>)>,
+}
+
+impl Attributes {
+    /// Parse `.gitattributes` content. Lines whose pattern is not a valid
+    /// glob, and attributes other than the ones merges use, are skipped.
+    pub fn parse(content: &str) -> Self {
+        let mut attributes = Self::default();
+        attributes.extend(content);
+        attributes
+    }
+
+    /// The attributes for merging `source` into `target`: the rules of the
+    /// incoming `.gitattributes`, overridden by those of the destination's,
+    /// so that a consumer's own file has the last word over an upstream's.
+    pub(crate) fn between(target: &MemoryFS, source: &MemoryFS) -> Self {
+        let mut attributes = Self::default();
+        for fs in [source, target] {
+            if let Some(file) = fs.get_file(ATTRIBUTES_FILENAME) {
+                attributes.extend(&String::from_utf8_lossy(&file.content));
+            }
+        }
+        attributes
+    }
+
+    fn extend(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            if pattern.starts_with('!') {
+                log::debug!("attributes: negative pattern {} is not supported", pattern);
+                continue;
+            }
+            let patterns = match pattern.strip_prefix('/') {
+                Some(anchored) => vec![anchored.to_string()],
+                None if pattern.contains('/') => vec![pattern.to_string()],
+                None => vec![pattern.to_string(), format!("**/{}", pattern)],
+            };
+            let set = match PatternSet::new(&patterns) {
+                Ok(set) => set,
+                Err(e) => {
+                    log::debug!("attributes: skipping {}: {}", pattern, e);
+                    continue;
+                }
+            };
+            let settings: Vec<Setting> = fields.flat_map(settings).collect();
+            if !settings.is_empty() {
+                self.rules.push((set, settings));
+            }
+        }
+    }
+
+    /// The `text` attribute of `path`: `Some(false)` for binary files,
+    /// `None` when no line sets it.
+    pub fn text(&self, path: &Path) -> Option<bool> {
+        self.settings(path)
+            .fold(None, |text, setting| match setting {
+                Setting::Text(value) => Some(*value),
+                Setting::Merge(_) => text,
+            })
+    }
+
+    /// Whether the file at `path` is text: its `text` attribute when one is
+    /// set, otherwise whether `content` is UTF-8 without NUL bytes.
+    pub fn is_text(&self, path: &Path, content: &[u8]) -> bool {
+        self.text(path)
+            .unwrap_or_else(|| std::str::from_utf8(content).is_ok_and(|text| !text.contains('\0')))
+    }
+
+    /// The merge driver the attributes name for `path`, if any.
+    pub fn merge_driver(&self, path: &Path) -> Option<MergeDriver> {
+        self.settings(path)
+            .fold(None, |driver, setting| match setting {
+                Setting::Merge(value) => *value,
+                Setting::Text(_) => driver,
+            })
+    }
+
+    /// The settings of every rule matching `path`, in file order.
+    fn settings<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a Setting> {
+        self.rules
+            .iter()
+            .filter(move |(set, _)| set.is_match(path))
+            .flat_map(|(_, settings)| settings)
+    }
+}
+
+/// The settings one attribute field makes. Unknown attributes and merge
+/// drivers set nothing, so they leave earlier lines in effect.
+fn settings(field: &str) -> Vec<Setting> {
+    match field {
+        "binary" => vec![
+            Setting::Text(false),
+            Setting::Merge(Some(MergeDriver::Binary)),
+        ],
+        "text" | "text=auto" => vec![Setting::Text(true)],
+        "-text" => vec![Setting::Text(false)],
+        "-merge" => vec![Setting::Merge(Some(MergeDriver::Binary))],
+        "!merge" => vec![Setting::Merge(None)],
+        _ => match field.strip_prefix("merge=") {
+            Some(value) => match MergeDriver::parse(value) {
+                Some(driver) => vec![Setting::Merge(Some(driver))],
+                None => {
+                    log::debug!("attributes: unknown merge driver {}", value);
+                    Vec::new()
+                }
+            },
+            None => Vec::new(),
+        },
+    }
+}
+
+/// Merge two text files line by line: every line of `dest`, then each line
+/// of `source` that `dest` does not already have.
+pub(crate) fn union(dest: &str, source: &str) -> String {
+    let existing: std::collections::HashSet<&str> = dest.lines().collect();
+    let mut merged = dest.to_string();
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    for line in source.lines() {
+        if !existing.contains(line) {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_without_slash_match_in_any_directory() {
+        let attributes = Attributes::parse("*.svg merge=xml\n/docs/*.md merge=markdown\n");
+        assert_eq!(
+            attributes.merge_driver(Path::new("a/b/logo.svg")),
+            Some(MergeDriver::Xml)
+        );
+        assert_eq!(
+            attributes.merge_driver(Path::new("docs/guide.md")),
+            Some(MergeDriver::Markdown)
+        );
+        assert_eq!(attributes.merge_driver(Path::new("README.md")), None);
+    }
+
+    #[test]
+    fn test_later_lines_override_earlier_ones() {
+        let attributes = Attributes::parse("*.json merge=json\nlock.json -merge\n");
+        assert_eq!(
+            attributes.merge_driver(Path::new("config.json")),
+            Some(MergeDriver::Json)
+        );
+        assert_eq!(
+            attributes.merge_driver(Path::new("lock.json")),
+            Some(MergeDriver::Binary)
+        );
+    }
+
+    #[test]
+    fn test_text_attribute_overrides_content_sniffing() {
+        let attributes = Attributes::parse("*.dat text\n*.txt -text\n");
+        assert!(attributes.is_text(Path::new("a.dat"), b"\0\x01"));
+        assert!(!attributes.is_text(Path::new("a.txt"), b"plain"));
+        assert!(attributes.is_text(Path::new("a.rs"), b"fn main() {}"));
+        assert!(!attributes.is_text(Path::new("a.bin"), b"\xff\xfe"));
+    }
+
+    #[test]
+    fn test_binary_macro_unsets_text_and_merge() {
+        let attributes = Attributes::parse("*.png binary\n");
+        assert!(!attributes.is_text(Path::new("logo.png"), b"ascii"));
+        assert_eq!(
+            attributes.merge_driver(Path::new("logo.png")),
+            Some(MergeDriver::Binary)
+        );
+    }
+
+    #[test]
+    fn test_destination_rules_override_incoming_ones() {
+        let mut target = MemoryFS::new();
+        target
+            .add_file_string(ATTRIBUTES_FILENAME, "*.cfg merge=ini\n")
+            .unwrap();
+        let mut source = MemoryFS::new();
+        source
+            .add_file_string(
+                ATTRIBUTES_FILENAME,
+                "*.cfg merge=union\n*.log merge=union\n",
+            )
+            .unwrap();
+        let attributes = Attributes::between(&target, &source);
+        assert_eq!(
+            attributes.merge_driver(Path::new("app.cfg")),
+            Some(MergeDriver::Ini)
+        );
+        assert_eq!(
+            attributes.merge_driver(Path::new("app.log")),
+            Some(MergeDriver::Union)
+        );
+    }
+
+    #[test]
+    fn test_union_appends_missing_lines() {
+        assert_eq!(union("a\nb", "b\nc\n"), "a\nb\nc\n");
+        assert_eq!(union("", "x\n"), "x\n");
+    }
+}
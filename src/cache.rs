@@ -22,19 +22,51 @@
 //! The `RepoCache` is implemented using a `HashMap` wrapped in an `Arc<Mutex>`,
 //! which allows it to be shared safely across multiple threads.
 //!
+//! ## Memory Budget
+//!
+//! A cache created with [`RepoCache::with_max_memory`] keeps the processed
+//! filesystems it holds, together with the composite being built, within a
+//! byte budget. Entries that do not fit are spilled to a temporary directory
+//! and read back from disk when they are needed again. The pipeline reports
+//! the size of its composite through [`RepoCache::enforce_max_memory`] after
+//! each upstream is integrated.
+//!
+//! ## Concurrent Runs
+//!
+//! Several runs can share one disk cache, for example parallel CI jobs in a
+//! monorepo. The `RepositoryManager` holds an [`EntryLock`] while it writes
+//! or reads a disk cache entry, and writes new entries into a staging
+//! directory that [`commit_staged`] renames into place, so a run never sees
+//! another run's partial entry. Locks left behind by a run that died are
+//! detected by age and removed.
+//!
 //! ## Key Components
 //!
 //! - **`RepoCache`**: The main struct that provides the caching functionality.
 //!
 //! - **`CacheKey`**: A struct that uniquely identifies a cached item based on
 //!   the repository's URL and Git reference.
+//!
+//! - **`EntryLock`**: An advisory lock on one entry of the disk cache.
 
 use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
 use std::hash::Hash;
-use std::sync::{Arc, Mutex};
-
-use crate::error::{Error, Result};
-use crate::filesystem::MemoryFS;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
+use crate::filesystem::{Directory, File, MemoryFS};
+use crate::messages::Message;
+
+/// Distinguishes the spill directories of caches in the same process.
+static SPILL_DIRS: AtomicUsize = AtomicUsize::new(0);
 
 /// Cache key combining URL and reference
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -70,6 +102,78 @@ impl CacheKey {
     }
 }
 
+/// A temporary directory that is removed when dropped.
+#[derive(Debug)]
+struct SpillDir(PathBuf);
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A file of a [`SpilledFS`], with its content on disk.
+#[derive(Debug)]
+struct SpilledFile {
+    path: PathBuf,
+    blob: PathBuf,
+    file: File,
+}
+
+/// A processed filesystem written to disk to free memory.
+///
+/// Only file contents are written; the rest of each [`File`] stays in
+/// memory with an empty `content`.
+#[derive(Debug)]
+struct SpilledFS {
+    files: Vec<SpilledFile>,
+    directories: Vec<(PathBuf, Directory)>,
+}
+
+impl SpilledFS {
+    /// Write the contents of `memory_fs` below `dir`.
+    fn write(memory_fs: MemoryFS, dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let (files, directories) = memory_fs.into_parts();
+        let mut spilled = Vec::with_capacity(files.len());
+        for (index, (path, mut file)) in files.into_iter().enumerate() {
+            let blob = dir.join(index.to_string());
+            fs::write(&blob, std::mem::take(&mut file.content))?;
+            spilled.push(SpilledFile { path, blob, file });
+        }
+        Ok(Self {
+            files: spilled,
+            directories: directories.into_iter().collect(),
+        })
+    }
+
+    /// Read the filesystem back, one file at a time.
+    fn load(&self) -> Result<MemoryFS> {
+        let mut memory_fs = MemoryFS::new();
+        for spilled in &self.files {
+            let mut file = spilled.file.clone();
+            file.content = fs::read(&spilled.blob)?.into();
+            memory_fs.add_file(&spilled.path, file)?;
+        }
+        for (path, directory) in &self.directories {
+            memory_fs.add_directory(path, directory.clone())?;
+        }
+        Ok(memory_fs)
+    }
+}
+
+/// Cache entries that were moved to disk to stay within the memory budget.
+#[derive(Debug, Default)]
+struct Spill {
+    /// Created on the first spill.
+    dir: Option<SpillDir>,
+    entries: HashMap<CacheKey, SpilledFS>,
+    /// Number of entries spilled so far, used to name their directories.
+    count: usize,
+    /// Whether the composite alone was reported to exceed the budget.
+    warned: bool,
+}
+
 /// A thread-safe, in-process cache for storing processed repositories as
 /// `MemoryFS` instances.
 ///
@@ -78,6 +182,8 @@ impl CacheKey {
 #[derive(Debug, Clone)]
 pub struct RepoCache {
     pub(crate) cache: Arc<Mutex<HashMap<CacheKey, MemoryFS>>>,
+    spill: Arc<Mutex<Spill>>,
+    max_memory: Option<u64>,
 }
 
 impl RepoCache {
@@ -85,9 +191,41 @@ impl RepoCache {
     pub fn new() -> Self {
         Self {
             cache: Arc::new(Mutex::new(HashMap::new())),
+            spill: Arc::new(Mutex::new(Spill::default())),
+            max_memory: None,
+        }
+    }
+
+    /// Creates a new, empty `RepoCache` that keeps cached filesystems and
+    /// the composite reported to [`RepoCache::enforce_max_memory`] within
+    /// `max_memory` bytes, spilling cached filesystems to disk as needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common_repo::cache::{CacheKey, RepoCache};
+    /// use common_repo::filesystem::MemoryFS;
+    ///
+    /// let cache = RepoCache::with_max_memory(4);
+    /// let mut fs = MemoryFS::new();
+    /// fs.add_file_string("big.txt", "too large").unwrap();
+    ///
+    /// let key = CacheKey::new("https://example.com/repo", "v1");
+    /// cache.insert(key.clone(), fs).unwrap();
+    /// assert!(cache.get(&key).unwrap().unwrap().exists("big.txt"));
+    /// ```
+    pub fn with_max_memory(max_memory: u64) -> Self {
+        Self {
+            max_memory: Some(max_memory),
+            ..Self::new()
         }
     }
 
+    /// The memory budget given to [`RepoCache::with_max_memory`], in bytes.
+    pub fn max_memory(&self) -> Option<u64> {
+        self.max_memory
+    }
+
     /// Retrieves a `MemoryFS` from the cache. If it's not present, the
     /// `processor` closure is executed to generate it, and the result is
     /// then stored in the cache before being returned.
@@ -107,17 +245,15 @@ impl RepoCache {
                 return Ok(cached.clone());
             }
         }
+        if let Some(spilled) = self.lock_spill()?.entries.get(&key) {
+            return spilled.load();
+        }
 
         // Not in cache, compute it
         let result = processor()?;
 
         // Store in cache
-        {
-            let mut cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
-                context: "Cache lock".to_string(),
-            })?;
-            cache.insert(key, result.clone());
-        }
+        self.insert(key, result.clone())?;
 
         Ok(result)
     }
@@ -125,11 +261,93 @@ impl RepoCache {
     /// Inserts a `MemoryFS` into the cache for a given `CacheKey`.
     ///
     /// If an entry for the key already exists, it will be overwritten.
+    ///
+    /// With a memory budget, an entry that does not fit next to the entries
+    /// already held in memory is spilled to disk.
     pub fn insert(&self, key: CacheKey, value: MemoryFS) -> Result<()> {
         let mut cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
             context: "Cache lock".to_string(),
         })?;
-        cache.insert(key, value);
+        let mut spill = self.lock_spill()?;
+        spill.entries.remove(&key);
+        cache.remove(&key);
+        match self.max_memory {
+            Some(max_memory)
+                if MemoryFS::total_size(cache.values().chain([&value])) > max_memory =>
+            {
+                Self::spill_entry(&mut spill, key, value)?;
+            }
+            _ => {
+                cache.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep cached filesystems and `composite` within the memory budget.
+    ///
+    /// Content the composite shares with cached filesystems is counted
+    /// once. When they do not fit, every cached filesystem still in memory
+    /// is spilled to disk. If the composite alone exceeds the budget, a
+    /// warning is reported once. Does nothing without a budget.
+    pub fn enforce_max_memory(&self, composite: &MemoryFS) -> Result<()> {
+        let Some(max_memory) = self.max_memory else {
+            return Ok(());
+        };
+        let mut cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
+            context: "Cache lock".to_string(),
+        })?;
+        let mut spill = self.lock_spill()?;
+        if MemoryFS::total_size(cache.values().chain([composite])) > max_memory {
+            for (key, value) in cache.drain() {
+                Self::spill_entry(&mut spill, key, value)?;
+            }
+        }
+        let in_use = composite.size();
+        if in_use > max_memory && !spill.warned {
+            spill.warned = true;
+            diagnostics::warn(
+                ErrorPhase::Merge,
+                Message::MemoryBudgetExceeded {
+                    size: in_use,
+                    max_memory,
+                }
+                .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// The number of entries that were spilled to disk.
+    pub fn spilled_len(&self) -> Result<usize> {
+        Ok(self.lock_spill()?.entries.len())
+    }
+
+    fn lock_spill(&self) -> Result<MutexGuard<'_, Spill>> {
+        self.spill.lock().map_err(|_| Error::LockPoisoned {
+            context: "Cache spill lock".to_string(),
+        })
+    }
+
+    fn spill_entry(spill: &mut Spill, key: CacheKey, value: MemoryFS) -> Result<()> {
+        let dir = spill.dir.get_or_insert_with(|| {
+            SpillDir(std::env::temp_dir().join(format!(
+                "common-repo-spill-{}-{}",
+                std::process::id(),
+                SPILL_DIRS.fetch_add(1, Ordering::Relaxed)
+            )))
+        });
+        let entry_dir = dir.0.join(spill.count.to_string());
+        spill.count += 1;
+        log::debug!(
+            "spilling {}@{} ({} bytes) to {}",
+            key.url,
+            key.r#ref,
+            value.size(),
+            entry_dir.display()
+        );
+        let spilled = SpilledFS::write(value, &entry_dir)?;
+        spill.entries.insert(key, spilled);
         Ok(())
     }
 
@@ -139,7 +357,14 @@ impl RepoCache {
         let cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
             context: "Cache lock".to_string(),
         })?;
-        Ok(cache.get(key).cloned())
+        if let Some(cached) = cache.get(key) {
+            return Ok(Some(cached.clone()));
+        }
+        self.lock_spill()?
+            .entries
+            .get(key)
+            .map(SpilledFS::load)
+            .transpose()
     }
 
     /// Checks if the cache contains an entry for the given `CacheKey`.
@@ -147,7 +372,7 @@ impl RepoCache {
         let cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
             context: "Cache lock".to_string(),
         })?;
-        Ok(cache.contains_key(key))
+        Ok(cache.contains_key(key) || self.lock_spill()?.entries.contains_key(key))
     }
 
     /// Removes all entries from the cache.
@@ -156,6 +381,9 @@ impl RepoCache {
             context: "Cache lock".to_string(),
         })?;
         cache.clear();
+        let mut spill = self.lock_spill()?;
+        spill.entries.clear();
+        spill.dir = None;
         Ok(())
     }
 
@@ -164,7 +392,7 @@ impl RepoCache {
         let cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
             context: "Cache lock".to_string(),
         })?;
-        Ok(cache.len())
+        Ok(cache.len() + self.lock_spill()?.entries.len())
     }
 
     /// Returns `true` if the cache contains no entries.
@@ -172,7 +400,7 @@ impl RepoCache {
         let cache = self.cache.lock().map_err(|_| Error::LockPoisoned {
             context: "Cache lock".to_string(),
         })?;
-        Ok(cache.is_empty())
+        Ok(cache.is_empty() && self.lock_spill()?.entries.is_empty())
     }
 }
 
@@ -182,6 +410,221 @@ impl Default for RepoCache {
     }
 }
 
+/// Subdirectory of the disk cache root that holds entry locks.
+const LOCK_DIR: &str = ".locks";
+
+/// Subdirectory of the disk cache root where entries are written before
+/// they are moved into place.
+const STAGING_DIR: &str = ".staging";
+
+/// Subdirectory of the disk cache root that records the commits found to
+/// have no configuration file.
+const NO_CONFIG_DIR: &str = ".no-config";
+
+/// A lock not refreshed for this long is assumed to belong to a run that
+/// died.
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// How often a waiting run checks whether a lock was released.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Distinguishes the staging directories of one process.
+static STAGING_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Advisory lock on one entry of the disk cache, held while the entry is
+/// written or read.
+///
+/// The lock is a file in `.locks/` below the cache root, created
+/// exclusively and removed when the `EntryLock` is dropped, so it works
+/// between processes and on network filesystems. Every run that shares a
+/// cache root takes the lock before touching an entry, so two `apply` runs
+/// never write the same entry at once or read one that is half written.
+///
+/// While the lock is held, a background thread refreshes the modification
+/// time of the lock file four times per [`STALE_LOCK_AGE`], however long the
+/// entry takes to write. A run that is killed leaves its lock file behind
+/// and stops refreshing it. A lock file not modified for [`STALE_LOCK_AGE`]
+/// is treated as stale and removed, and staging directories the dead run
+/// left for the entry are cleaned up.
+#[derive(Debug)]
+pub struct EntryLock {
+    path: PathBuf,
+    /// Stops the refreshing thread when dropped or sent to.
+    heartbeat: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl EntryLock {
+    /// Lock the cache entry at `entry`, waiting while another run holds it.
+    pub fn acquire(entry: &Path) -> Result<Self> {
+        Self::acquire_with_stale_age(entry, STALE_LOCK_AGE)
+    }
+
+    fn acquire_with_stale_age(entry: &Path, stale_age: Duration) -> Result<Self> {
+        let (root, name) = split_entry(entry)?;
+        let lock_dir = root.join(LOCK_DIR);
+        fs::create_dir_all(&lock_dir)?;
+        let path = lock_dir.join(format!("{}.lock", name));
+
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // The holder's process ID is only informational.
+                    let _ = writeln!(file, "{}", std::process::id());
+                    let heartbeat = heartbeat(path.clone(), stale_age / 4);
+                    let lock = Self {
+                        path,
+                        heartbeat: Some(heartbeat),
+                    };
+                    // Holding the lock, any staging directory of this entry
+                    // belongs to a run that died while writing it.
+                    let staging = root.join(STAGING_DIR).join(name);
+                    if staging.exists() {
+                        let _ = fs::remove_dir_all(&staging);
+                    }
+                    return Ok(lock);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path, stale_age) {
+                        log::warn!("{}", Message::StaleCacheLock { path: &path });
+                        remove_stale_lock(&path);
+                        continue;
+                    }
+                    if !waiting {
+                        log::info!("{}", Message::WaitingForCacheLock { path: entry });
+                        waiting = true;
+                    }
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for EntryLock {
+    fn drop(&mut self) {
+        if let Some((stop, thread)) = self.heartbeat.take() {
+            drop(stop);
+            let _ = thread.join();
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Refresh the modification time of the lock file at `path` every
+/// `interval`, so that waiting runs do not take it for stale, until the
+/// returned sender is dropped.
+fn heartbeat(path: PathBuf, interval: Duration) -> (Sender<()>, JoinHandle<()>) {
+    let (stop, stopped) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+            let touched = OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            if let Err(e) = touched {
+                log::debug!("could not refresh cache lock {}: {}", path.display(), e);
+                break;
+            }
+        }
+    });
+    (stop, thread)
+}
+
+/// A fresh directory to write the cache entry at `entry` into before it is
+/// moved into place with [`commit_staged`].
+///
+/// The caller must hold the entry's [`EntryLock`].
+pub fn staging_path(entry: &Path) -> Result<PathBuf> {
+    let (root, name) = split_entry(entry)?;
+    Ok(root.join(STAGING_DIR).join(name).join(format!(
+        "{}-{}",
+        std::process::id(),
+        STAGING_DIRS.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+/// Move the directory written at `staged` into place as the cache entry at
+/// `entry`, replacing any previous entry.
+///
+/// The rename is atomic, so a run that did not finish writing never leaves
+/// a partial entry behind. The caller must hold the entry's [`EntryLock`].
+pub fn commit_staged(staged: &Path, entry: &Path) -> Result<()> {
+    if entry.exists() {
+        fs::remove_dir_all(entry)?;
+    }
+    fs::rename(staged, entry)?;
+    if let Some(parent) = staged.parent() {
+        // Only succeeds once no other staging directory is left.
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
+
+/// Whether the disk cache at `root` records that `commit` has no
+/// configuration file.
+///
+/// A commit fixes the files of a repository, so the result never goes
+/// stale; an upstream that adds a configuration does so in a new commit.
+pub fn is_known_without_config(root: &Path, commit: &str) -> bool {
+    is_commit_id(commit) && root.join(NO_CONFIG_DIR).join(commit).exists()
+}
+
+/// Record in the disk cache at `root` that `commit` has no configuration
+/// file, so later runs skip looking for one (see
+/// [`is_known_without_config`]).
+pub fn record_without_config(root: &Path, commit: &str) -> Result<()> {
+    if !is_commit_id(commit) {
+        return Err(Error::Cache {
+            message: format!("Invalid commit id: {}", commit),
+        });
+    }
+    let dir = root.join(NO_CONFIG_DIR);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(commit), b"")?;
+    Ok(())
+}
+
+/// Whether `commit` looks like a full commit id, so it is safe as a file
+/// name.
+fn is_commit_id(commit: &str) -> bool {
+    !commit.is_empty() && commit.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Split a cache entry path into the cache root and the entry name.
+fn split_entry(entry: &Path) -> Result<(&Path, &str)> {
+    match (entry.parent(), entry.file_name().and_then(|n| n.to_str())) {
+        (Some(root), Some(name)) => Ok((root, name)),
+        _ => Err(Error::Cache {
+            message: format!("Invalid cache entry path: {}", entry.display()),
+        }),
+    }
+}
+
+/// Whether the lock file at `path` was last written longer than `stale_age`
+/// ago. A lock that is already gone is not stale; the next attempt takes it.
+fn is_stale(path: &Path, stale_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > stale_age)
+}
+
+/// Remove a stale lock file. It is renamed first so that, of several runs
+/// that find the same stale lock, only one removes it.
+fn remove_stale_lock(path: &Path) {
+    let claimed = path.with_extension(format!(
+        "stale-{}-{}",
+        std::process::id(),
+        STAGING_DIRS.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::rename(path, &claimed).is_ok() {
+        let _ = fs::remove_file(&claimed);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -335,4 +778,169 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::LockPoisoned { .. }));
     }
+
+    #[test]
+    fn test_cache_spills_entries_over_budget() {
+        let cache = RepoCache::with_max_memory(10);
+        let small = CacheKey::new("https://example.com/small", "v1");
+        let large = CacheKey::new("https://example.com/large", "v1");
+
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("a.txt", "12345").unwrap();
+        cache.insert(small.clone(), fs).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 0);
+
+        let mut fs = MemoryFS::new();
+        let mut script = crate::filesystem::File::from_string("1234567890");
+        script.permissions = 0o755;
+        fs.add_file("bin/run", script).unwrap();
+        fs.add_directory("empty", crate::filesystem::Directory::new())
+            .unwrap();
+        cache.insert(large.clone(), fs).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 1);
+        assert_eq!(cache.len().unwrap(), 2);
+
+        let loaded = cache
+            .get_or_process(large.clone(), || {
+                panic!("spilled entries are not reprocessed")
+            })
+            .unwrap();
+        let file = loaded.get_file("bin/run").unwrap();
+        assert_eq!(file.content, b"1234567890");
+        assert_eq!(file.permissions, 0o755);
+        assert!(loaded.has_directory("empty"));
+
+        // A composite that leaves no room moves the rest to disk as well.
+        let mut composite = MemoryFS::new();
+        composite.add_file_string("out.txt", "12345678").unwrap();
+        cache.enforce_max_memory(&composite).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 2);
+        assert!(cache.get(&small).unwrap().unwrap().exists("a.txt"));
+
+        cache.clear().unwrap();
+        assert!(cache.is_empty().unwrap());
+    }
+
+    #[test]
+    fn test_cache_without_budget_never_spills() {
+        let cache = RepoCache::new();
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("a.txt", "content").unwrap();
+        cache
+            .insert(CacheKey::new("https://example.com/repo", "v1"), fs)
+            .unwrap();
+        let mut composite = MemoryFS::new();
+        composite
+            .add_file_string("out.txt", &"x".repeat(4096))
+            .unwrap();
+        cache.enforce_max_memory(&composite).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_cache_budget_counts_shared_content_once() {
+        let cache = RepoCache::with_max_memory(10);
+        let key = CacheKey::new("https://example.com/repo", "v1");
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("a.txt", "1234567").unwrap();
+        fs.add_file_string("copy/a.txt", "1234567").unwrap();
+        cache.insert(key.clone(), fs.clone()).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 0);
+
+        // The composite holds the cached files themselves, not copies
+        cache.enforce_max_memory(&fs).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 0);
+
+        fs.add_file_string("b.txt", "1234").unwrap();
+        cache.enforce_max_memory(&fs).unwrap();
+        assert_eq!(cache.spilled_len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_entry_lock_waits_for_holder() {
+        let root = tempfile::TempDir::new().unwrap();
+        let entry = root.path().join("abc-main");
+
+        let lock = EntryLock::acquire(&entry).unwrap();
+        let released = Arc::new(Mutex::new(false));
+        let waiter = {
+            let (entry, released) = (entry.clone(), released.clone());
+            thread::spawn(move || {
+                let _lock = EntryLock::acquire(&entry).unwrap();
+                *released.lock().unwrap()
+            })
+        };
+        thread::sleep(LOCK_POLL_INTERVAL * 3);
+        *released.lock().unwrap() = true;
+        drop(lock);
+
+        assert!(waiter.join().unwrap(), "lock taken while still held");
+        assert!(!root.path().join(LOCK_DIR).join("abc-main.lock").exists());
+    }
+
+    #[test]
+    fn test_entry_lock_recovers_stale_lock() {
+        let root = tempfile::TempDir::new().unwrap();
+        let entry = root.path().join("abc-main");
+        let lock_dir = root.path().join(LOCK_DIR);
+        fs::create_dir_all(&lock_dir).unwrap();
+        let left_behind = fs::File::create(lock_dir.join("abc-main.lock")).unwrap();
+        left_behind
+            .set_modified(std::time::SystemTime::now() - STALE_LOCK_AGE * 2)
+            .unwrap();
+        let partial = root.path().join(STAGING_DIR).join("abc-main").join("1-0");
+        fs::create_dir_all(&partial).unwrap();
+
+        let _lock = EntryLock::acquire(&entry).unwrap();
+        assert!(!partial.exists());
+    }
+
+    #[test]
+    fn test_entry_lock_is_not_stale_while_held() {
+        let root = tempfile::TempDir::new().unwrap();
+        let entry = root.path().join("abc-main");
+        let path = root.path().join(LOCK_DIR).join("abc-main.lock");
+        let stale_age = Duration::from_millis(400);
+
+        let lock = EntryLock::acquire_with_stale_age(&entry, stale_age).unwrap();
+        thread::sleep(stale_age * 3);
+        assert!(!is_stale(&path, stale_age), "held lock went stale");
+
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_commit_staged_replaces_entry() {
+        let root = tempfile::TempDir::new().unwrap();
+        let entry = root.path().join("abc-main");
+        fs::create_dir_all(&entry).unwrap();
+        fs::write(entry.join("old.txt"), "old").unwrap();
+
+        let _lock = EntryLock::acquire(&entry).unwrap();
+        let staged = staging_path(&entry).unwrap();
+        fs::create_dir_all(&staged).unwrap();
+        fs::write(staged.join("new.txt"), "new").unwrap();
+        commit_staged(&staged, &entry).unwrap();
+
+        assert!(entry.join("new.txt").exists());
+        assert!(!entry.join("old.txt").exists());
+        assert!(!staged.exists());
+    }
+
+    #[test]
+    fn test_without_config_recorded_per_commit() {
+        let root = tempfile::TempDir::new().unwrap();
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        assert!(!is_known_without_config(root.path(), commit));
+        record_without_config(root.path(), commit).unwrap();
+        assert!(is_known_without_config(root.path(), commit));
+        assert!(!is_known_without_config(
+            root.path(),
+            "fedcba9876543210fedcba9876543210fedcba98"
+        ));
+
+        assert!(record_without_config(root.path(), "../escape").is_err());
+        assert!(!is_known_without_config(root.path(), ""));
+    }
 }
@@ -0,0 +1,357 @@
+//! # Upstream Changelogs
+//!
+//! When `update` moves an upstream from one version to another, consumers
+//! want to know what they are pulling in. This module gathers the release
+//! notes of every version after the current one, up to and including the
+//! new one, from:
+//!
+//! 1. A changelog file at the root of the upstream at the new version:
+//!    the first of [`CHANGELOG_FILENAMES`] that exists. Each release is a
+//!    Markdown heading holding its version, as in `## [1.2.0] - 2024-05-01`
+//!    or `# v1.2.0`, and its notes run to the next heading of the same level.
+//! 2. Otherwise, for repositories on `github.com`, the repository's GitHub
+//!    releases, by their tag.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::changelog::releases_between;
+//! use semver::Version;
+//!
+//! let changelog = "# Changelog\n\n## [Unreleased]\n\n## [1.2.0]\n\n- New job\n\n## [1.1.0]\n\n- Fix\n\n## [1.0.0]\n\n- First\n";
+//! let releases = releases_between(
+//!     changelog,
+//!     &Version::new(1, 0, 0),
+//!     &Version::new(1, 2, 0),
+//! );
+//! let versions: Vec<_> = releases.iter().map(|r| r.version.to_string()).collect();
+//! assert_eq!(versions, ["1.2.0", "1.1.0"]);
+//! assert_eq!(releases[0].notes, "- New job");
+//! ```
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use semver::Version;
+
+use crate::error::{Error, Result};
+use crate::git::parse_semver_tag;
+use crate::http;
+use crate::repository::RepositoryManager;
+
+/// Changelog files looked for at the root of an upstream, in order.
+pub const CHANGELOG_FILENAMES: &[&str] = &["CHANGELOG.md", "CHANGES.md", "HISTORY.md"];
+
+/// A version number in a heading, with an optional `v` prefix.
+static HEADING_VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bv?(\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?)\b")
+        .expect("valid regex")
+});
+
+/// The notes of one release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// The release's version.
+    pub version: Version,
+    /// The notes, without the release's heading.
+    pub notes: String,
+}
+
+/// Where a [`Changelog`]'s notes came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A changelog file of the upstream, by its name.
+    File(String),
+    /// The repository's GitHub releases.
+    GitHubReleases,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::File(name) => f.write_str(name),
+            Source::GitHubReleases => f.write_str("GitHub releases"),
+        }
+    }
+}
+
+/// The releases an update of one upstream pulls in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Changelog {
+    /// The upstream's URL.
+    pub url: String,
+    /// The ref the upstream is updated from.
+    pub from: String,
+    /// The ref the upstream is updated to.
+    pub to: String,
+    /// Where the notes were read.
+    pub source: Source,
+    /// The releases after `from` up to and including `to`, newest first.
+    pub releases: Vec<Release>,
+}
+
+impl Changelog {
+    /// Render the changelog as Markdown, for a job summary or a pull request
+    /// body.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("### {} ({} → {})\n", self.url, self.from, self.to);
+        for release in &self.releases {
+            markdown.push_str(&format!("\n#### {}\n", release.version));
+            if !release.notes.is_empty() {
+                markdown.push_str(&format!("\n{}\n", release.notes));
+            }
+        }
+        markdown
+    }
+}
+
+/// Gather the release notes an update of `url` from `from` to `to` pulls in.
+///
+/// Returns `None` when either ref is not a version, or when the upstream has
+/// neither a changelog file nor GitHub releases covering the range.
+///
+/// # Errors
+///
+/// Fails if the upstream cannot be fetched at `to`.
+pub fn fetch(
+    repo_manager: &RepositoryManager,
+    url: &str,
+    from: &str,
+    to: &str,
+) -> Result<Option<Changelog>> {
+    let (Some(from_version), Some(to_version)) = (parse_semver_tag(from), parse_semver_tag(to))
+    else {
+        return Ok(None);
+    };
+    let changelog = |source, releases| Changelog {
+        url: url.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+        source,
+        releases,
+    };
+
+    let fs = repo_manager.fetch_repository(url, to)?;
+    for name in CHANGELOG_FILENAMES {
+        let Some(file) = fs.get_file(name) else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&file.content);
+        let releases = releases_between(&content, &from_version, &to_version);
+        if !releases.is_empty() {
+            return Ok(Some(changelog(Source::File(name.to_string()), releases)));
+        }
+    }
+
+    let Some(api) = github_releases_url(url) else {
+        return Ok(None);
+    };
+    let releases = match http::download(&api).and_then(|body| parse_github_releases(&body)) {
+        Ok(releases) => releases,
+        Err(e) => {
+            log::debug!("no GitHub releases for {}: {}", url, e);
+            return Ok(None);
+        }
+    };
+    let mut releases: Vec<Release> = releases
+        .into_iter()
+        .filter(|release| release.version > from_version && release.version <= to_version)
+        .collect();
+    releases.sort_by(|a, b| b.version.cmp(&a.version));
+    Ok((!releases.is_empty()).then(|| changelog(Source::GitHubReleases, releases)))
+}
+
+/// The releases in a Markdown changelog after `from`, up to and including
+/// `to`, newest first.
+///
+/// Release headings are the headings that hold a version; the level of the
+/// first one is the level of them all. Headings without a version, such as
+/// `## [Unreleased]`, end the release before them.
+pub fn releases_between(content: &str, from: &Version, to: &Version) -> Vec<Release> {
+    let mut releases = Vec::new();
+    let mut level = None;
+    let mut current: Option<(Version, Vec<&str>)> = None;
+    let mut in_code = false;
+
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let heading = (!in_code).then(|| heading(line)).flatten();
+        if let Some((heading_level, text)) = heading {
+            let version = HEADING_VERSION
+                .captures(text)
+                .and_then(|caps| Version::parse(&caps[1]).ok());
+            if level.is_none() && version.is_some() {
+                level = Some(heading_level);
+            }
+            if let Some(level) = level.filter(|level| heading_level <= *level) {
+                releases.extend(current.take().map(finish));
+                if heading_level == level {
+                    current = version.map(|version| (version, Vec::new()));
+                }
+                continue;
+            }
+        }
+        if let Some((_, lines)) = &mut current {
+            lines.push(line);
+        }
+    }
+    releases.extend(current.map(finish));
+
+    let mut releases: Vec<Release> = releases
+        .into_iter()
+        .filter(|release| &release.version > from && &release.version <= to)
+        .collect();
+    releases.sort_by(|a, b| b.version.cmp(&a.version));
+    releases
+}
+
+fn finish((version, lines): (Version, Vec<&str>)) -> Release {
+    Release {
+        version,
+        notes: lines.join("\n").trim().to_string(),
+    }
+}
+
+/// The level and text of a Markdown ATX heading.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    let rest = &line[hashes..];
+    (rest.is_empty() || rest.starts_with(' ')).then(|| (hashes, rest.trim()))
+}
+
+/// The GitHub API URL of the releases of a `github.com` repository.
+fn github_releases_url(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("git@github.com:"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.split_once('/')?;
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    Some(format!(
+        "https://api.github.com/repos/{}/{}/releases?per_page=100",
+        owner, repo
+    ))
+}
+
+/// The published releases in a GitHub API response whose tag is a version.
+fn parse_github_releases(body: &[u8]) -> Result<Vec<Release>> {
+    let releases: Vec<serde_json::Value> =
+        serde_json::from_slice(body).map_err(|e| Error::Download {
+            url: "GitHub releases".to_string(),
+            message: format!("unexpected response: {}", e),
+            hint: None,
+        })?;
+    Ok(releases
+        .iter()
+        .filter(|release| release["draft"] != true)
+        .filter_map(|release| {
+            let version = parse_semver_tag(release["tag_name"].as_str()?)?;
+            let notes = release["body"].as_str().unwrap_or_default();
+            Some(Release {
+                version,
+                notes: notes.replace("\r\n", "\n").trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(releases: &[Release]) -> Vec<String> {
+        releases.iter().map(|r| r.version.to_string()).collect()
+    }
+
+    #[test]
+    fn test_releases_between_keeps_subsections_in_notes() {
+        let changelog = "\
+# Changelog
+
+## v2.0.0 - 2024-06-01
+
+### Breaking
+
+- Renamed `lint` job
+
+## v1.1.0 - 2024-05-01
+
+- Added cache
+
+## v1.0.0
+
+- First
+";
+        let releases = releases_between(changelog, &Version::new(1, 0, 0), &Version::new(2, 0, 0));
+        assert_eq!(versions(&releases), ["2.0.0", "1.1.0"]);
+        assert_eq!(releases[0].notes, "### Breaking\n\n- Renamed `lint` job");
+        assert_eq!(releases[1].notes, "- Added cache");
+    }
+
+    #[test]
+    fn test_releases_between_stops_at_headings_without_versions() {
+        let changelog = "# 1.2.0\n\n- A\n\n# Older releases\n\nSee the wiki.\n\n# 1.1.0\n\n- B\n";
+        let releases = releases_between(changelog, &Version::new(1, 0, 0), &Version::new(1, 2, 0));
+        assert_eq!(versions(&releases), ["1.2.0", "1.1.0"]);
+        assert_eq!(releases[0].notes, "- A");
+    }
+
+    #[test]
+    fn test_releases_between_ignores_headings_in_code_blocks() {
+        let changelog = "## 1.1.0\n\n```sh\n# 1.0.5 is broken\n```\n\n## 1.0.0\n";
+        let releases = releases_between(changelog, &Version::new(1, 0, 0), &Version::new(1, 1, 0));
+        assert_eq!(versions(&releases), ["1.1.0"]);
+        assert_eq!(releases[0].notes, "```sh\n# 1.0.5 is broken\n```");
+    }
+
+    #[test]
+    fn test_github_releases_url() {
+        assert_eq!(
+            github_releases_url("https://github.com/org/ci.git").as_deref(),
+            Some("https://api.github.com/repos/org/ci/releases?per_page=100")
+        );
+        assert_eq!(
+            github_releases_url("git@github.com:org/ci").as_deref(),
+            Some("https://api.github.com/repos/org/ci/releases?per_page=100")
+        );
+        assert_eq!(github_releases_url("https://gitlab.com/org/ci"), None);
+    }
+
+    #[test]
+    fn test_parse_github_releases_skips_drafts_and_other_tags() {
+        let body = br#"[
+            {"tag_name": "v1.2.0", "draft": false, "body": "- New\r\n- Fixed"},
+            {"tag_name": "v1.3.0", "draft": true, "body": "wip"},
+            {"tag_name": "nightly", "draft": false, "body": null}
+        ]"#;
+        let releases = parse_github_releases(body).unwrap();
+        assert_eq!(versions(&releases), ["1.2.0"]);
+        assert_eq!(releases[0].notes, "- New\n- Fixed");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let changelog = Changelog {
+            url: "https://github.com/org/ci".to_string(),
+            from: "v1.0.0".to_string(),
+            to: "v1.1.0".to_string(),
+            source: Source::File("CHANGELOG.md".to_string()),
+            releases: vec![Release {
+                version: Version::new(1, 1, 0),
+                notes: "- Added cache".to_string(),
+            }],
+        };
+        assert_eq!(
+            changelog.to_markdown(),
+            "### https://github.com/org/ci (v1.0.0 → v1.1.0)\n\n#### 1.1.0\n\n- Added cache\n"
+        );
+    }
+}
@@ -4,7 +4,8 @@
 //! using the `clap` library. It is responsible for:
 //!
 //! - Defining the top-level CLI structure, including global arguments like
-//!   `--color` and `--log-level`.
+//!   `--color`, `--quiet` and `--log-level`, and installing the matching
+//!   `common_repo::output` configuration.
 //! - Defining the available subcommands (e.g., `apply`, `check`, `update`).
 //! - Parsing the command-line arguments provided by the user.
 //! - Dispatching to the appropriate command implementation based on the
@@ -17,6 +18,11 @@ use anyhow::Result;
 use clap::{ArgAction, Parser, Subcommand};
 use log::LevelFilter;
 
+use common_repo::github;
+use common_repo::messages::Message;
+use common_repo::outcome::{self, Failure, Outcome, Summary};
+use common_repo::output;
+
 use crate::commands;
 
 /// Common Repository - Manage repository configuration inheritance
@@ -36,6 +42,10 @@ pub struct Cli {
     #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
     color: String,
 
+    /// Disable colors and emoji (same as --color never)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     /// Set log level (error, warn, info, debug, trace)
     #[arg(long, global = true, value_name = "LEVEL", default_value = "info")]
     log_level: String,
@@ -50,10 +60,25 @@ pub struct Cli {
 
     /// Suppress output except errors
     ///
-    /// Overrides --log-level to show only error messages.
-    /// Use for scripting or quiet operation.
+    /// Overrides --log-level to show only error messages and silences
+    /// status and warning messages. Command results (such as `ls` or
+    /// `tree` output) are still printed. Use for scripting or quiet operation.
     #[arg(long, global = true, conflicts_with = "verbose")]
     quiet: bool,
+
+    /// Format of the error report printed on failure (human, json)
+    ///
+    /// The JSON report is a single object on stderr with the error code,
+    /// phase, title, message and cause chain.
+    /// The summary that ends `apply`, `check` and `diff` is JSON too.
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        default_value = "human",
+        value_parser = ["human", "json"]
+    )]
+    error_format: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -70,12 +95,27 @@ enum Commands {
     /// Generate shell completion scripts
     Completions(commands::completions::CompletionsArgs),
 
+    /// Describe what an upstream repository offers to consumers
+    Describe(commands::describe::DescribeArgs),
+
     /// Show differences between current files and configuration result
     Diff(commands::diff::DiffArgs),
 
+    /// Check git, the cache, the configuration and access to upstream hosts
+    Doctor(commands::doctor::DoctorArgs),
+
+    /// Write the composed upstream content as a standalone template
+    ExportTemplate(commands::export_template::ExportTemplateArgs),
+
+    /// Show who applied which upstream commits, and what changed
+    History(commands::history::HistoryArgs),
+
     /// Initialize a new .common-repo.yaml configuration file
     Init(commands::init::InitArgs),
 
+    /// Create a .common-repo.yaml from copier, cruft or cookiecutter state
+    Import(commands::import::ImportArgs),
+
     /// Update repository refs to newer versions
     Update(commands::update::UpdateArgs),
 
@@ -85,14 +125,35 @@ enum Commands {
     /// List files that would be created/modified by the configuration
     Ls(commands::ls::LsArgs),
 
+    /// Rewrite a configuration file written for an older release to the current schema
+    MigrateConfig(commands::migrate_config::MigrateConfigArgs),
+
+    /// Show how the configuration will lay out files, such as where renamed files end up
+    Plan(commands::plan::PlanArgs),
+
+    /// Carry a local change to a managed file back to its upstream
+    Promote(commands::promote::PromoteArgs),
+
+    /// Print the final content of one file produced by the configuration
+    Render(commands::render::RenderArgs),
+
     /// Validate a .common-repo.yaml configuration file
     Validate(commands::validate::ValidateArgs),
 
+    /// Check an upstream repository's own operations against its files
+    VerifyUpstream(commands::verify_upstream::VerifyUpstreamArgs),
+
+    /// Check an upstream repository against best practices for templates
+    LintUpstream(commands::lint_upstream::LintUpstreamArgs),
+
     /// Manage repository cache
     Cache(commands::cache::CacheArgs),
 
     /// Display the repository inheritance tree
     Tree(commands::tree::TreeArgs),
+
+    /// Explain an error code such as E4002
+    ExplainError(commands::explain_error::ExplainErrorArgs),
 }
 
 impl Cli {
@@ -100,34 +161,53 @@ impl Cli {
     pub fn execute(self) -> Result<()> {
         // Initialize logger based on log level
         self.init_logger()?;
+        output::set_global(
+            output::OutputConfig::from_env_and_flag(self.color_choice()).with_quiet(self.quiet),
+        );
+        let color = self.color_choice().to_string();
 
         match self.command {
             Commands::Add(args) => commands::add::execute(args),
-            Commands::Apply(args) => commands::apply::execute(args),
-            Commands::Check(args) => commands::check::execute(args),
+            Commands::Apply(args) => {
+                outcome::begin("apply");
+                commands::apply::execute(args)
+            }
+            Commands::Check(args) => {
+                outcome::begin("check");
+                commands::check::execute(args)
+            }
             Commands::Completions(args) => commands::completions::execute(args),
+            Commands::Describe(args) => commands::describe::execute(args),
             Commands::Diff(args) => {
-                // Diff command uses exit code 1 to indicate changes exist
-                // (following the convention of diff(1) and git diff)
-                match commands::diff::execute(args) {
-                    Ok(()) => Ok(()),
-                    Err(e) if e.to_string() == "CHANGES_DETECTED" => {
-                        // Exit with code 1 for changes detected
-                        std::process::exit(common_repo::exit_codes::ERROR);
-                    }
-                    Err(e) => Err(e),
-                }
+                outcome::begin("diff");
+                commands::diff::execute(args)
             }
+            Commands::Doctor(args) => commands::doctor::execute(args),
+            Commands::ExportTemplate(args) => commands::export_template::execute(args),
+            Commands::History(args) => commands::history::execute(args),
             Commands::Info(args) => commands::info::execute(args),
             Commands::Init(args) => commands::init::execute(args),
+            Commands::Import(args) => commands::import::execute(args),
             Commands::Ls(args) => commands::ls::execute(args),
+            Commands::MigrateConfig(args) => commands::migrate_config::execute(args),
+            Commands::Plan(args) => commands::plan::execute(args),
+            Commands::Promote(args) => commands::promote::execute(args),
+            Commands::Render(args) => commands::render::execute(args),
             Commands::Update(args) => commands::update::execute(args),
-            Commands::Validate(args) => commands::validate::execute(args, &self.color),
+            Commands::Validate(args) => commands::validate::execute(args, &color),
+            Commands::VerifyUpstream(args) => commands::verify_upstream::execute(args),
+            Commands::LintUpstream(args) => commands::lint_upstream::execute(args),
             Commands::Cache(args) => commands::cache::execute(args),
-            Commands::Tree(args) => commands::tree::execute(args, &self.color),
+            Commands::Tree(args) => commands::tree::execute(args, &color),
+            Commands::ExplainError(args) => commands::explain_error::execute(args),
         }
     }
 
+    /// Whether the error report should be printed as JSON
+    pub fn json_errors(&self) -> bool {
+        self.error_format == "json"
+    }
+
     /// Initialize the logger with the specified log level and color settings
     fn init_logger(&self) -> Result<()> {
         let log_level = self.parse_log_level()?;
@@ -184,9 +264,18 @@ impl Cli {
         }
     }
 
+    /// The effective --color value, with --no-color applied
+    fn color_choice(&self) -> &str {
+        if self.no_color {
+            "never"
+        } else {
+            &self.color
+        }
+    }
+
     /// Determine whether to use color output based on the color setting
     fn should_use_color(&self) -> bool {
-        match self.color.to_lowercase().as_str() {
+        match self.color_choice().to_lowercase().as_str() {
             "always" => true,
             "never" => false,
             "auto" => console::Term::stdout().features().colors_supported(),
@@ -202,6 +291,82 @@ impl Cli {
     }
 }
 
+/// Report an error that ended the program as a GitHub Actions annotation.
+pub fn annotate_error(err: &anyhow::Error) {
+    let code = error_code(err);
+    github::annotate(
+        github::Annotation::new(github::Level::Error, format!("{:#}", err))
+            .title(Message::ErrorAnnotationTitle { code }),
+    );
+}
+
+/// The outcome of a command that failed with `err`: the outcome of the
+/// first [`Failure`] in the cause chain, or else that of the first
+/// `common_repo::error::Error`.
+pub fn error_outcome(err: &anyhow::Error) -> Outcome {
+    err.chain()
+        .find_map(|cause| {
+            cause
+                .downcast_ref::<Failure>()
+                .map(|failure| failure.outcome)
+                .or_else(|| {
+                    cause
+                        .downcast_ref::<common_repo::error::Error>()
+                        .map(Outcome::of_error)
+                })
+        })
+        .unwrap_or(Outcome::Error)
+}
+
+/// The code of the first `common_repo::error::Error` in the cause chain.
+pub fn error_code(err: &anyhow::Error) -> Option<&'static str> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<common_repo::error::Error>())
+        .map(|typed| typed.info().code)
+}
+
+/// Print the summary block that ends a command, as JSON with
+/// `--error-format json`.
+pub fn report_summary(summary: &Summary, json: bool) {
+    if json {
+        output::summary(summary.to_json());
+    } else {
+        output::summary(summary.to_text().trim_end());
+    }
+}
+
+/// Render the report for an error that ended the program.
+///
+/// The first `common_repo::error::Error` in the cause chain supplies the
+/// error code. The human format matches anyhow's own report, with the code
+/// added after `Error` and a pointer to `explain-error`.
+pub fn error_report(err: &anyhow::Error, json: bool) -> String {
+    let info = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<common_repo::error::Error>())
+        .map(|typed| typed.info());
+
+    if json {
+        let causes: Vec<String> = err.chain().skip(1).map(ToString::to_string).collect();
+        let report = serde_json::json!({
+            "code": info.map(|i| i.code),
+            "phase": info.map(|i| i.phase.as_str()),
+            "title": info.map(|i| i.title),
+            "message": err.to_string(),
+            "causes": causes,
+        });
+        return report.to_string();
+    }
+
+    match info {
+        Some(info) => format!(
+            "Error [{}]: {:?}\n\nFor more information about this error, run `common-repo explain-error {}`",
+            info.code, err, info.code
+        ),
+        None => format!("Error: {:?}", err),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -215,11 +380,20 @@ mod tests {
                 config: PathBuf::from("/nonexistent/config.yaml"),
                 cache_root: None,
                 updates: false,
+                vars: false,
+                templates: false,
+                locked: false,
+                assertions: false,
+                strict: false,
+                repo: None,
+                ref_: None,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "info".to_string(),
             verbose: 0,
             quiet: false,
+            error_format: "human".to_string(),
         };
 
         // This should fail because the config file doesn't exist, but it covers the match arm
@@ -239,11 +413,16 @@ mod tests {
                 yes: false,
                 dry_run: true, // Use dry run to avoid actual changes
                 filter: vec![],
+                update_vendored: false,
+                changelog: None,
+                no_changelog: false,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "info".to_string(),
             verbose: 0,
             quiet: false,
+            error_format: "human".to_string(),
         };
 
         // This should fail because the config file doesn't exist, but it covers the match arm
@@ -258,11 +437,20 @@ mod tests {
                 config: PathBuf::from("test.yaml"),
                 cache_root: None,
                 updates: false,
+                vars: false,
+                templates: false,
+                locked: false,
+                assertions: false,
+                strict: false,
+                repo: None,
+                ref_: None,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "info".to_string(),
             verbose: 1,
             quiet: false,
+            error_format: "human".to_string(),
         };
 
         // --verbose should override --log-level to debug
@@ -276,11 +464,20 @@ mod tests {
                 config: PathBuf::from("test.yaml"),
                 cache_root: None,
                 updates: false,
+                vars: false,
+                templates: false,
+                locked: false,
+                assertions: false,
+                strict: false,
+                repo: None,
+                ref_: None,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "info".to_string(),
             verbose: 2,
             quiet: false,
+            error_format: "human".to_string(),
         };
 
         // --verbose --verbose should set trace level
@@ -294,17 +491,36 @@ mod tests {
                 config: PathBuf::from("test.yaml"),
                 cache_root: None,
                 updates: false,
+                vars: false,
+                templates: false,
+                locked: false,
+                assertions: false,
+                strict: false,
+                repo: None,
+                ref_: None,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "debug".to_string(), // Would be debug without --quiet
             verbose: 0,
             quiet: true,
+            error_format: "human".to_string(),
         };
 
         // --quiet should override to error level
         assert_eq!(cli.parse_log_level().unwrap(), LevelFilter::Error);
     }
 
+    #[test]
+    fn test_no_color_overrides_color() {
+        let cli = Cli::parse_from(["common-repo", "--color", "always", "--no-color", "ls"]);
+        assert_eq!(cli.color_choice(), "never");
+        assert!(!cli.should_use_color());
+
+        let cli = Cli::parse_from(["common-repo", "--color", "always", "ls"]);
+        assert_eq!(cli.color_choice(), "always");
+    }
+
     #[test]
     fn test_parse_log_level_default() {
         let cli = Cli {
@@ -312,14 +528,55 @@ mod tests {
                 config: PathBuf::from("test.yaml"),
                 cache_root: None,
                 updates: false,
+                vars: false,
+                templates: false,
+                locked: false,
+                assertions: false,
+                strict: false,
+                repo: None,
+                ref_: None,
             }),
             color: "auto".to_string(),
+            no_color: false,
             log_level: "warn".to_string(),
             verbose: 0,
             quiet: false,
+            error_format: "human".to_string(),
         };
 
         // Without --verbose or --quiet, should use --log-level
         assert_eq!(cli.parse_log_level().unwrap(), LevelFilter::Warn);
     }
+
+    #[test]
+    fn test_error_report_includes_code() {
+        let err = anyhow::Error::from(common_repo::error::Error::CycleDetected {
+            cycle: "a -> b -> a".to_string(),
+        })
+        .context("Discovery failed");
+
+        let human = error_report(&err, false);
+        assert!(human.starts_with("Error [E1001]: Discovery failed"));
+        assert!(human.contains("common-repo explain-error E1001"));
+
+        let json: serde_json::Value = serde_json::from_str(&error_report(&err, true)).unwrap();
+        assert_eq!(json["code"], "E1001");
+        assert_eq!(json["phase"], "discovery");
+        assert_eq!(json["message"], "Discovery failed");
+        assert_eq!(
+            json["causes"][0],
+            "Cycle detected in repository dependencies: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn test_error_report_without_code() {
+        let err = anyhow::anyhow!("Something went wrong");
+
+        assert!(error_report(&err, false).starts_with("Error: Something went wrong"));
+
+        let json: serde_json::Value = serde_json::from_str(&error_report(&err, true)).unwrap();
+        assert!(json["code"].is_null());
+        assert_eq!(json["message"], "Something went wrong");
+    }
 }
@@ -18,6 +18,9 @@ use std::path::Path;
 
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
 use common_repo::git;
+use common_repo::messages::Message;
+use common_repo::migrate;
+use common_repo::output;
 use common_repo::version;
 
 /// Add a repository to the configuration file
@@ -48,22 +51,27 @@ pub fn execute(args: AddArgs) -> Result<()> {
 
     // Print warnings
     for warning in &warnings {
-        eprintln!("  ⚠️  {}", warning);
+        output::warning(Message::VersionWarning { warning });
     }
 
     // Check if config exists
     if config_path.exists() {
         // Append to existing config
         append_repo_to_config(config_path, &url, &version)?;
-        println!("✅ Added {} @ {} to .common-repo.yaml", url, version);
+        output::status(Message::RepoAdded {
+            url: &url,
+            version: &version,
+        });
     } else if args.yes {
         // Create minimal config with just this repo
         create_minimal_config(config_path, &url, &version)?;
-        println!("✅ Created .common-repo.yaml with {} @ {}", url, version);
+        output::status(Message::ConfigCreatedWith {
+            url: &url,
+            version: &version,
+        });
     } else {
         // No config exists - ask user for confirmation to create one
-        println!("No .common-repo.yaml configuration file found.");
-        println!();
+        output::status(Message::NoConfigFound);
 
         let theme = ColorfulTheme::default();
         let create_config = Confirm::with_theme(&theme)
@@ -76,10 +84,13 @@ pub fn execute(args: AddArgs) -> Result<()> {
 
         if create_config {
             create_minimal_config(config_path, &url, &version)?;
-            println!("✅ Created .common-repo.yaml with {} @ {}", url, version);
-            println!("💡 Run `common-repo apply` to fetch and apply configurations");
+            output::status(Message::ConfigCreatedWith {
+                url: &url,
+                version: &version,
+            });
+            output::status(Message::ApplyTip);
         } else {
-            println!("Aborted. Run 'common-repo init' to create a configuration interactively.");
+            output::status(Message::AddAborted);
             return Ok(());
         }
     }
@@ -88,7 +99,7 @@ pub fn execute(args: AddArgs) -> Result<()> {
 }
 
 /// Normalize a repository URL, expanding GitHub shorthand.
-fn normalize_repo_url(input: &str) -> String {
+pub(crate) fn normalize_repo_url(input: &str) -> String {
     // If it already looks like a URL, use as-is
     if input.starts_with("https://") || input.starts_with("git@") || input.starts_with("http://") {
         return input.to_string();
@@ -106,51 +117,49 @@ fn normalize_repo_url(input: &str) -> String {
 ///
 /// Returns the preferred version to use (latest semver tag or fallback) and any warnings.
 fn fetch_version_info(url: &str) -> (String, Vec<String>) {
-    print!("Fetching tags from {}... ", url);
+    output::progress(Message::FetchingTags { url, indent: false });
 
     match git::list_tags(url) {
         Ok(tags) => {
             let semver_tags = version::filter_semver_tags(&tags);
             if let Some((latest_tag, parsed_version)) = find_latest_version(&semver_tags) {
-                println!("found {}", latest_tag);
+                output::status(Message::TagsFound { tag: &latest_tag });
 
                 let mut warnings = Vec::new();
                 if parsed_version.major == 0 {
-                    warnings.push(format!(
-                        "Warning: {} is a 0.x.x version, which may indicate unstable API",
-                        latest_tag
-                    ));
+                    warnings.push(Message::UnstableVersion { tag: &latest_tag }.to_string());
                 }
                 (latest_tag, warnings)
             } else if !tags.is_empty() {
-                println!("no semver tags found");
+                output::status(Message::TagsNoSemver);
                 (
                     "main".to_string(),
                     vec![
-                        "Warning: No semantic version tags found. Using 'main' branch.".to_string(),
-                        "Consider pinning to a specific commit hash for reproducibility."
-                            .to_string(),
+                        Message::NoSemverTagsUsingMain.to_string(),
+                        Message::PinCommitHint.to_string(),
                     ],
                 )
             } else {
-                println!("no tags found");
+                output::status(Message::TagsNone);
                 (
                     "main".to_string(),
                     vec![
-                        "Warning: No tags found. Using 'main' branch.".to_string(),
-                        "Consider pinning to a specific commit hash for reproducibility."
-                            .to_string(),
+                        Message::NoTagsUsingMain.to_string(),
+                        Message::PinCommitHint.to_string(),
                     ],
                 )
             }
         }
         Err(e) => {
-            println!("failed");
+            output::status(Message::TagsFailed);
             (
                 "main".to_string(),
                 vec![
-                    format!("Warning: Error fetching tags: {}. Using 'main' branch.", e),
-                    "Consider pinning to a specific commit hash for reproducibility.".to_string(),
+                    Message::FetchTagsFailedUsingMain {
+                        error: &e.to_string(),
+                    }
+                    .to_string(),
+                    Message::PinCommitHint.to_string(),
                 ],
             )
         }
@@ -158,7 +167,7 @@ fn fetch_version_info(url: &str) -> (String, Vec<String>) {
 }
 
 /// Find the latest semantic version from a list of tags.
-fn find_latest_version(tags: &[String]) -> Option<(String, semver::Version)> {
+pub(crate) fn find_latest_version(tags: &[String]) -> Option<(String, semver::Version)> {
     let mut latest: Option<(String, semver::Version)> = None;
 
     for tag in tags {
@@ -193,9 +202,12 @@ fn create_minimal_config(config_path: &Path, url: &str, version: &str) -> Result
     Ok(())
 }
 
-/// Append a repository entry to an existing configuration file.
+/// Append a repository entry to an existing configuration file, keeping
+/// the form it is written in.
 fn append_repo_to_config(config_path: &Path, url: &str, version: &str) -> Result<()> {
-    let mut content = fs::read_to_string(config_path)?;
+    let original = fs::read_to_string(config_path)?;
+    let form = migrate::Form::of(&original);
+    let mut content = migrate::to_list(&original)?;
 
     // Find where to insert the new repo entry (before include section or at end)
     let new_entry = format!(
@@ -214,6 +226,9 @@ fn append_repo_to_config(config_path: &Path, url: &str, version: &str) -> Result
         // Append at end
         content.push_str(&new_entry);
     }
+    if form == migrate::Form::Document {
+        content = migrate::to_document(&content)?;
+    }
 
     fs::write(config_path, content)?;
     Ok(())
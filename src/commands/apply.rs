@@ -24,12 +24,227 @@
 //! The `execute` function handles argument parsing, sets up the necessary
 //! components (like the `RepositoryManager` and `RepoCache`), and invokes the
 //! main orchestrator from the `common_repo` library.
+//!
+//! ## Named Pipelines
+//!
+//! With `--pipeline <name>`, the configuration is read from
+//! `.common-repo/<name>.yaml` instead (see
+//! [`common_repo::defaults::PIPELINES_DIR`]). Each pipeline has its own apply
+//! manifest and up-to-date fingerprint, so for example CI configuration and
+//! documentation can be pulled from different upstreams on different
+//! schedules into the same repository.
+//!
+//! ## Remote Configuration
+//!
+//! With `--config-url <url>`, the configuration is downloaded and applied as
+//! the root configuration, with no local config file. The URL follows the
+//! rules of the `file:` operation, and `--config-sha256` pins the content.
+//!
+//! ## Untracked File Safety
+//!
+//! When the output directory is inside a git work tree, `apply` refuses to
+//! overwrite files that exist on disk but are not tracked by git (including
+//! ignored files) unless `--force-untracked` is passed. Such files are often
+//! local scratch files that happen to share a name with an upstream file.
+//! The refusal ends with the conflicts exit code (see
+//! [`common_repo::outcome`]). In `--dry-run` mode the conflicts are reported
+//! as warnings instead.
+//!
+//! Files that exist as symlinks or read-only files are not written through
+//! either: apply fails and lists them unless `--follow-symlinks` or
+//! `--read-only overwrite|skip` says otherwise (see
+//! [`common_repo::phases::WriteOptions`]).
+//!
+//! ## Output Formats
+//!
+//! With `--output-format tar`, the final files are written to a tar archive
+//! at `--output` instead of to a directory. With `--output-format git-tree`,
+//! the files apply would change are committed on top of `HEAD` of the
+//! output directory's repository (see [`common_repo::git::commit_files`]),
+//! and the commit id is printed; the working tree is not touched. Neither
+//! format checks for untracked files or records an up-to-date fingerprint,
+//! since nothing in the working tree is written.
+//!
+//! ## Warnings
+//!
+//! Non-fatal problems reported by the pipeline (see
+//! [`common_repo::diagnostics`]) are printed after the run. With
+//! `--deny-warnings` they make the command fail before anything is written,
+//! except those of `self:` blocks, which run after the source block is
+//! written and fail the command then. `--warnings-json` saves them for
+//! other tools. In GitHub Actions they
+//! are also reported as workflow annotations and in the job summary (see
+//! [`common_repo::github`]).
+//!
+//! ## Sweeping Renames
+//!
+//! A `rename` mapping of the configuration that moves more than
+//! [`SWEEPING_RENAME_FILES`] files, or whose pattern matches any path, is
+//! often a regex that is greedier than meant. Before writing, apply shows
+//! what such a mapping moves and asks for confirmation, or fails when there
+//! is no terminal to ask on. `--yes` skips the question. Renames declared
+//! by upstreams are not checked.
+//!
+//! ## Operation Timings
+//!
+//! Each operation is timed, with the number of files before and after it
+//! (see [`common_repo::reporting::timings`]). `--verbose` logs every
+//! operation as it runs and lists the slowest ones after the run.
+//! Operations slower than half a second are always listed, and in GitHub
+//! Actions added to the job summary, so a costly rename pattern or a huge
+//! include is easy to find.
+//!
+//! ## Managed File Map
+//!
+//! Each apply that writes to a directory also writes
+//! `.common-repo/map.json`, listing which upstream file every managed file
+//! came from (see [`common_repo::reporting::map`]), for editor plugins.
+//! When no file is managed, no map is written.
+//!
+//! ## History
+//!
+//! Each apply that writes to a directory appends an entry to
+//! `.common-repo/history.jsonl`: when and by whom, the hash of the
+//! configuration, the commit of every upstream, and the managed files it
+//! changed (see [`common_repo::reporting::history`]). `common-repo history`
+//! shows it.
+//!
+//! ## Required Variables
+//!
+//! In a terminal, the template variables upstreams require (with
+//! `requires: {vars: ...}`) that the configuration does not set are asked
+//! for before the pipeline runs, with their descriptions and defaults, and
+//! can be saved to the configuration as a `template-vars` entry. Without a
+//! terminal, or with `--yes`, the pipeline fails listing every one of them.
+//!
+//! ## Protection
+//!
+//! A `- protect:` entry in the configuration opts in to guarding managed
+//! files against direct edits: `read-only` writes them read-only and
+//! updates them regardless on the next apply, and `pre-commit` installs a
+//! git hook that rejects commits changing them (see
+//! [`common_repo::protect`]).
+//!
+//! ## Plans
+//!
+//! `--from-plan <file>` writes the files `diff --save-plan <file>` compared
+//! with instead of composing them again, so an approve-then-apply flow
+//! applies exactly what was approved even if upstream branches moved in
+//! between (see [`common_repo::phases::plan`]). It fails if the
+//! configuration changed since the plan was saved.
+//!
+//! ## Subdirectory Output
+//!
+//! `--into <dir>` runs the configuration as if the subdirectory `dir` of
+//! the output directory were its root: local files are read from there and
+//! the output is placed under it, with the apply manifest and the managed
+//! file map listing the placed paths (see
+//! [`common_repo::phases::context::RunContext::into`]).
+//!
+//! ## Sandbox
+//!
+//! `--sandbox` applies into a temporary directory instead of the output
+//! directory, and `--run <command>` then runs a command there, such as
+//! `yamllint .` or `cargo check`, failing when it fails. The sandbox is
+//! removed afterwards, so upstream authors and consumers can check that the
+//! merged output works without touching the working tree. Nothing is
+//! recorded for later applies: no fingerprint, apply manifest, checkpoint
+//! or pre-commit hook.
+//!
+//! ## Shadow Copies
+//!
+//! `--shadow-dir <dir>` composes every upstream at its latest release, or
+//! at the refs given with `--shadow-ref`, into `dir` and reports how the
+//! result differs from the live files (see
+//! [`common_repo::reporting::shadow`]). The configuration and live files
+//! are left alone, and, as with `--sandbox`, nothing is recorded.
+//!
+//! ## Up-to-Date Check
+//!
+//! After a successful apply that reported no warnings, a fingerprint of its
+//! inputs is stored (see [`common_repo::fingerprint`]). The next apply
+//! compares against it first and, when nothing changed, exits without
+//! running the pipeline. `--force`, `--no-cache`, `--resume`, `--dry-run`
+//! and `--from-plan` always run the pipeline, and applying a plan stores no
+//! fingerprint.
 
 use anyhow::Result;
-use clap::Args;
-use std::path::PathBuf;
+use clap::{Args, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
+use common_repo::config::{Operation, PathScope, Protection, RenameMapping, RequiredVar, Schema};
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::filesystem::{File, MemoryFS};
+use common_repo::github;
+use common_repo::http::sha256_hex;
+use common_repo::messages::Message;
+use common_repo::migrate;
+use common_repo::notify::{self, ApplyReport, ApplyStatus, Webhook};
+use common_repo::outcome::{self, Failure, Outcome};
+use common_repo::output;
+use common_repo::phases::manifest::ApplyManifest;
+use common_repo::phases::plan::Plan;
+use common_repo::phases::ReadOnlyPolicy;
+use common_repo::protect::{self, HookOutcome};
+use common_repo::reporting::fetches::LastRun;
+use common_repo::reporting::history::{self, HistoryEntry, UpstreamCommit};
+use common_repo::reporting::map::{self, FileMap};
+use common_repo::reporting::renames::{self, SweepingRename};
+use common_repo::reporting::shadow::{self, RefBump, ShadowReport};
+use common_repo::reporting::timings::{self, Timings};
+use common_repo::user_config::UserConfig;
+
+use super::completions;
+
+/// How many of the slowest operations `--verbose` lists after a run.
+const SLOWEST_OPERATIONS: usize = 5;
+
+/// A rename mapping of the configuration that moves more files than this
+/// asks for confirmation before apply writes.
+const SWEEPING_RENAME_FILES: usize = 100;
+
+/// How many moves of each sweeping rename are shown before asking.
+const SWEEPING_RENAME_SHOWN: usize = 10;
+
+/// What to do with read-only files that would change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum ReadOnlyFiles {
+    /// Fail before writing, listing every such file
+    #[default]
+    Error,
+    /// Leave them as they are, with a warning
+    Skip,
+    /// Write them and make them read-only again
+    Overwrite,
+}
+
+impl From<ReadOnlyFiles> for ReadOnlyPolicy {
+    fn from(files: ReadOnlyFiles) -> Self {
+        match files {
+            ReadOnlyFiles::Error => ReadOnlyPolicy::Error,
+            ReadOnlyFiles::Skip => ReadOnlyPolicy::Skip,
+            ReadOnlyFiles::Overwrite => ReadOnlyPolicy::Overwrite,
+        }
+    }
+}
+
+/// Where the final files go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum OutputFormat {
+    /// Write them to the output directory
+    #[default]
+    Files,
+    /// Write them to a tar archive at --output
+    Tar,
+    /// Commit the changed files on top of HEAD, without touching the
+    /// working tree
+    GitTree,
+}
 
 /// Arguments for the apply command
 #[derive(Args, Debug)]
@@ -38,21 +253,66 @@ pub struct ApplyArgs {
     ///
     /// If not provided, it defaults to `.common-repo.yaml` in the current directory.
     /// Can also be set with the `COMMON_REPO_CONFIG` environment variable.
-    #[arg(short, long, value_name = "PATH", env = "COMMON_REPO_CONFIG")]
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        env = "COMMON_REPO_CONFIG",
+        add = completions::config_paths()
+    )]
     pub config: Option<PathBuf>,
 
+    /// Apply the named pipeline configured in `.common-repo/<NAME>.yaml`
+    /// instead of the default configuration.
+    ///
+    /// Each pipeline keeps its own record of the files it wrote, so
+    /// pipelines into the same directory can be applied independently.
+    /// Overrides `--config`.
+    #[arg(long, value_name = "NAME", add = ArgValueCandidates::new(completions::pipelines))]
+    pub pipeline: Option<String>,
+
+    /// Download the configuration from URL and apply it as the root
+    /// configuration, without a local config file.
+    ///
+    /// The URL must use https, or http together with `--config-sha256`.
+    #[arg(long, value_name = "URL", conflicts_with_all = ["config", "pipeline"])]
+    pub config_url: Option<String>,
+
+    /// Expected SHA-256 digest of the configuration downloaded with
+    /// `--config-url`, as hex. Apply fails when the content does not match.
+    #[arg(long, value_name = "DIGEST", requires = "config_url")]
+    pub config_sha256: Option<String>,
+
     /// The directory where the final files will be written.
     ///
-    /// If not provided, it defaults to the current working directory.
-    #[arg(short, long, value_name = "PATH")]
+    /// If not provided, it defaults to the current working directory. With
+    /// `--output-format tar`, this is the archive file and is required.
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        required_if_eq("output_format", "tar")
+    )]
     pub output: Option<PathBuf>,
 
+    /// Write the final files to the output directory, to a tar archive, or
+    /// as a git commit on top of HEAD.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t)]
+    pub output_format: OutputFormat,
+
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// If set, the command will show what would be done without making any
@@ -60,8 +320,8 @@ pub struct ApplyArgs {
     #[arg(short = 'n', long)]
     pub dry_run: bool,
 
-    /// If set, the command will overwrite existing files without prompting.
-    /// (Currently, there is no prompting, so this is reserved for future use).
+    /// Run the full pipeline even when nothing changed since the last
+    /// successful apply.
     #[arg(short, long)]
     pub force: bool,
 
@@ -69,6 +329,797 @@ pub struct ApplyArgs {
     /// clones of all repositories.
     #[arg(long)]
     pub no_cache: bool,
+
+    /// Allow overwriting files that exist on disk but are untracked or
+    /// ignored by git.
+    ///
+    /// Without this flag, apply fails before writing anything if it would
+    /// change such a file.
+    #[arg(long)]
+    pub force_untracked: bool,
+
+    /// Write through symlinks in the output directory to the files they
+    /// point to.
+    ///
+    /// Without this flag, apply fails instead of writing a file that is, or
+    /// has a parent directory that is, a symlink.
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// What to do with files that exist as read-only files and would
+    /// change.
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t)]
+    pub read_only: ReadOnlyFiles,
+
+    /// Fail before writing anything if the pipeline reports any warnings.
+    #[arg(long)]
+    pub deny_warnings: bool,
+
+    /// Fail on operations this version does not know instead of skipping
+    /// them.
+    ///
+    /// Without this flag, an unknown operation is skipped with a warning,
+    /// unless its name looks like a misspelled known operation.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Apply rename mappings that move many files without asking first.
+    ///
+    /// Without this flag, apply shows a mapping of the configuration that
+    /// moves more than 100 files, or whose pattern matches any path, and
+    /// asks before writing. Without a terminal to ask on, it fails instead.
+    /// Template variables upstreams require are not asked for either.
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Write the pipeline's warnings to FILE as a JSON array.
+    ///
+    /// Each entry has a `phase` and a `message`. The file is written even
+    /// when there are no warnings.
+    #[arg(long, value_name = "FILE")]
+    pub warnings_json: Option<PathBuf>,
+
+    /// Continue a failed run from its last completed phase.
+    ///
+    /// Every run saves the discovered repository tree and the final file
+    /// set under the cache root; with this flag they are reused instead of
+    /// being computed again.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Memory budget for composing files, such as `512M` or `2G`.
+    ///
+    /// Processed upstream files that do not fit are moved to a temporary
+    /// directory and read back when needed. Suffixes are powers of 1024.
+    #[arg(long, value_name = "SIZE", value_parser = parse_memory_size)]
+    pub max_memory: Option<u64>,
+
+    /// Write the files saved by `diff --save-plan FILE` instead of
+    /// composing them again, so that what was reviewed is what is applied.
+    ///
+    /// Fails if the configuration changed since the plan was saved.
+    /// `self:` blocks still run.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["config_url", "resume"])]
+    pub from_plan: Option<PathBuf>,
+
+    /// Place every file the configuration produces under DIR, a
+    /// subdirectory of the output directory, as if DIR were the root.
+    ///
+    /// Local files are read from DIR, and the managed file map and the
+    /// record of propagated files list the paths under DIR. `self:` blocks
+    /// are not moved.
+    #[arg(long, value_name = "DIR", value_parser = parse_into)]
+    pub into: Option<PathBuf>,
+
+    /// Apply into a temporary directory, removed afterwards, instead of
+    /// the output directory.
+    ///
+    /// Checks that the configuration applies without touching the working
+    /// tree. Add `--run` to check that the output works.
+    #[arg(
+        long,
+        conflicts_with_all = ["output", "output_format", "dry_run", "resume"]
+    )]
+    pub sandbox: bool,
+
+    /// Run COMMAND in the sandbox's shell after applying, such as
+    /// `yamllint .` or `cargo check`, and fail if it fails.
+    #[arg(long = "run", value_name = "COMMAND", requires = "sandbox")]
+    pub run_command: Option<String>,
+
+    /// Compose the output of newer upstream refs into DIR, such as
+    /// `.common-repo/shadow`, and report how it differs from the live
+    /// files.
+    ///
+    /// Each upstream is composed at its latest release, or only those
+    /// given with `--shadow-ref`. The configuration and the live files are
+    /// left alone, so a major upgrade can be staged and reviewed before
+    /// `update` adopts it.
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["output", "output_format", "dry_run", "resume", "sandbox", "from_plan", "into"]
+    )]
+    pub shadow_dir: Option<PathBuf>,
+
+    /// Compose the upstream at URL at REF in the shadow directory. Can be
+    /// repeated.
+    #[arg(
+        long,
+        value_name = "URL=REF",
+        requires = "shadow_dir",
+        value_parser = parse_shadow_ref
+    )]
+    pub shadow_ref: Vec<(String, String)>,
+}
+
+/// Parse a `--shadow-ref` value, `URL=REF`. URLs may contain `=`, refs may
+/// not.
+fn parse_shadow_ref(value: &str) -> std::result::Result<(String, String), String> {
+    match value.rsplit_once('=') {
+        Some((url, ref_)) if !url.is_empty() && !ref_.is_empty() => {
+            Ok((url.to_string(), ref_.to_string()))
+        }
+        _ => Err(format!(
+            "invalid shadow ref '{}', expected URL=REF such as https://github.com/acme/ci=v2.0.0",
+            value
+        )),
+    }
+}
+
+/// The refs `--shadow-dir` composes the upstreams of `config` at: those of
+/// `shadow_refs`, or else the latest release of each upstream that has a
+/// newer one.
+fn shadow_bumps(
+    config: &Schema,
+    shadow_refs: &[(String, String)],
+    repo_manager: &common_repo::repository::RepositoryManager,
+) -> Result<Vec<RefBump>> {
+    use common_repo::version;
+
+    if !shadow_refs.is_empty() {
+        let repos = version::collect_inherited_repos(config);
+        return shadow_refs
+            .iter()
+            .map(|(url, to)| {
+                let repo = repos.iter().find(|repo| repo.url == *url).ok_or_else(|| {
+                    anyhow::anyhow!("--shadow-ref {}: no upstream has this URL", url)
+                })?;
+                Ok(RefBump {
+                    url: url.clone(),
+                    from: repo.r#ref.clone().unwrap_or_default(),
+                    to: to.clone(),
+                })
+            })
+            .collect();
+    }
+    Ok(version::check_updates(config, repo_manager)?
+        .into_iter()
+        .filter(|update| update.breaking_changes || update.compatible_updates)
+        .filter_map(|update| {
+            Some(RefBump {
+                to: update.latest_version?,
+                url: update.url,
+                from: update.current_ref,
+            })
+        })
+        .collect())
+}
+
+/// Report how the shadow output `final_fs` in `shadow_dir` differs from
+/// the live files in `live_dir`, and save the report there.
+fn report_shadow(
+    bumps: Vec<RefBump>,
+    final_fs: &MemoryFS,
+    shadow_dir: &Path,
+    live_dir: &Path,
+    previous: &ApplyManifest,
+) -> Result<()> {
+    let report = ShadowReport::compare(bumps, final_fs, live_dir, previous);
+    report.save(shadow_dir)?;
+    outcome::record_files(report.added.len() + report.changed.len() + report.removed.len());
+    for bump in &report.bumps {
+        output::status(Message::ShadowRef {
+            url: &bump.url,
+            from: &bump.from,
+            to: &bump.to,
+        });
+    }
+    for (paths, change) in [
+        (&report.added, "added"),
+        (&report.changed, "changed"),
+        (&report.removed, "removed"),
+    ] {
+        for path in paths {
+            output::status(Message::ShadowChange { path, change });
+        }
+    }
+    output::status(Message::ShadowWritten {
+        path: shadow_dir,
+        counts: &report.counts(),
+        report: &shadow_dir.join(shadow::REPORT_FILE),
+    });
+    Ok(())
+}
+
+/// The temporary directory `--sandbox` applies into, removed when dropped.
+struct Sandbox(PathBuf);
+
+impl Sandbox {
+    fn new() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("common-repo-sandbox-{}", std::process::id()));
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `command` in the sandbox at `dir`, failing when it fails.
+fn run_in_sandbox(dir: &Path, command: &str) -> Result<()> {
+    log::info!("{}", Message::SandboxRunning { command });
+    let status = common_repo::secrets::shell(command)
+        .current_dir(dir)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run `{}`: {}", command, e))?;
+    if !status.success() {
+        log::error!("{}", Message::ApplyFailed);
+        return Err(anyhow::anyhow!(
+            "{}",
+            Message::SandboxFailed {
+                command,
+                status: &status.to_string()
+            }
+        ));
+    }
+    output::status(Message::SandboxPassed { command });
+    Ok(())
+}
+
+/// Parse the subdirectory of `--into`, which must be a relative path that
+/// stays inside the output directory.
+fn parse_into(value: &str) -> std::result::Result<PathBuf, String> {
+    use std::path::Component;
+
+    let path = PathBuf::from(value);
+    let components: Vec<_> = path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+    if components.is_empty()
+        || !components
+            .iter()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!(
+            "invalid directory '{}', expected a relative path such as infra/shared",
+            value
+        ));
+    }
+    Ok(components.iter().collect())
+}
+
+/// Parse a size such as `1048576`, `512K`, `512M`, `2G` or `2GiB` into
+/// bytes.
+fn parse_memory_size(value: &str) -> std::result::Result<u64, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches(['B', 'I']);
+    let (digits, shift) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 10),
+        Some('M') => (&number[..number.len() - 1], 20),
+        Some('G') => (&number[..number.len() - 1], 30),
+        Some('T') => (&number[..number.len() - 1], 40),
+        _ => (number, 0),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .filter(|&bytes| bytes > 0)
+        .ok_or_else(|| {
+            format!(
+                "invalid size '{}', expected a number such as 512M or 2G",
+                value
+            )
+        })
+}
+
+/// Find files that the pipeline would overwrite on disk even though git does
+/// not track them.
+///
+/// `tracked` is the set returned by [`common_repo::git::tracked_files`] for
+/// `output_dir`. Only files whose on-disk content differs from the staged
+/// content count, so local files passed through unchanged are never
+/// reported. Neither are files a previous apply wrote and `manifest` still
+/// lists with their content on disk: they are generated output that was
+/// not committed yet, not files someone wrote by hand.
+fn find_untracked_overwrites(
+    final_fs: &MemoryFS,
+    output_dir: &Path,
+    tracked: &HashSet<PathBuf>,
+    manifest: &ApplyManifest,
+) -> Vec<PathBuf> {
+    let mut conflicts: Vec<PathBuf> = final_fs
+        .files()
+        .filter(|(path, _)| !tracked.contains(path.as_path()))
+        .filter(|(path, file)| {
+            let disk_path = output_dir.join(path);
+            disk_path.is_file()
+                && std::fs::read(&disk_path)
+                    .map(|on_disk| {
+                        on_disk != file.content
+                            && !manifest.is_propagated(path, &File::new(on_disk))
+                    })
+                    .unwrap_or(true)
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    conflicts.sort();
+    conflicts
+}
+
+/// The files of `final_fs` whose content or executable bit differs from
+/// the file at the same path under `output_dir`, or that are not there.
+/// These are the files a write to disk would change.
+fn changed_files<'a>(final_fs: &'a MemoryFS, output_dir: &Path) -> Vec<(&'a PathBuf, &'a File)> {
+    let mut changed: Vec<_> = final_fs
+        .files()
+        .filter(
+            |(path, file)| match File::from_path(&output_dir.join(path)) {
+                Ok(on_disk) => {
+                    on_disk.content != file.content
+                        || (on_disk.permissions & 0o111 != 0) != (file.permissions & 0o111 != 0)
+                }
+                Err(_) => true,
+            },
+        )
+        .collect();
+    changed.sort_by(|a, b| a.0.cmp(b.0));
+    changed
+}
+
+/// The managed files of `plan` that replaced a local file with other
+/// content. Files with local input are not propagated, so the manifest does
+/// not list them and [`ApplyManifest::changes`] leaves them out.
+fn replaced_local_files(plan: &Plan) -> Vec<PathBuf> {
+    let Some(local) = &plan.local else {
+        return Vec::new();
+    };
+    // The local inputs were read from the subdirectory the output is
+    // placed under, relative to it.
+    let into = plan.into.as_deref().unwrap_or(Path::new(""));
+    FileMap::placed(&plan.fs, into)
+        .files
+        .into_keys()
+        .filter(|path| {
+            let local_path = path.strip_prefix(into).unwrap_or(path);
+            match (local.get(local_path), plan.fs.get_file(path)) {
+                (Some(before), Some(file)) => *before != sha256_hex(&file.content),
+                _ => false,
+            }
+        })
+        .collect()
+}
+
+/// Write the map of the managed files of `final_fs`, placed under the
+/// subdirectory `into`, to `output_dir`, or remove a stale one when no file
+/// is managed any more.
+fn write_file_map(final_fs: &MemoryFS, output_dir: &Path, into: &Path) -> Result<()> {
+    let path = output_dir.join(map::MAP_FILE);
+    let file_map = FileMap::placed(final_fs, into);
+    if file_map.files.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, file_map.to_json())?;
+    Ok(())
+}
+
+/// Append an entry for the apply that just wrote to `output_dir` to its
+/// history, and return it. `changed` are the managed files the apply
+/// changed.
+fn record_history(
+    output_dir: &Path,
+    config: &Schema,
+    config_path: Option<&Path>,
+    pipeline: Option<&str>,
+    upstreams: Vec<UpstreamCommit>,
+    changed: Vec<PathBuf>,
+) -> Result<HistoryEntry> {
+    // A remote configuration has no file of its own to hash.
+    let config_hash = match config_path {
+        Some(path) => sha256_hex(&std::fs::read(path)?),
+        None => sha256_hex(serde_json::to_string(config)?.as_bytes()),
+    };
+    let mut entry = HistoryEntry::now(history::current_user(output_dir), config_hash);
+    entry.pipeline = pipeline.map(str::to_string);
+    entry.upstreams = upstreams;
+    entry.files = changed;
+    history::append(output_dir, &entry)?;
+    Ok(entry)
+}
+
+/// What an apply did, for the webhooks told about it.
+#[derive(Debug, Default)]
+struct Effects {
+    /// The output directory, once known.
+    directory: Option<PathBuf>,
+    /// The upstreams fetched.
+    upstreams: Vec<UpstreamCommit>,
+    /// The managed files changed.
+    files: Vec<PathBuf>,
+    /// How many warnings the pipeline reported.
+    warnings: usize,
+}
+
+/// Tell `webhooks` how the apply of `pipeline` that ended with `result`
+/// went.
+fn notify_webhooks(
+    webhooks: &[Webhook],
+    pipeline: Option<String>,
+    effects: Effects,
+    result: &Result<()>,
+) {
+    if webhooks.is_empty() {
+        return;
+    }
+    let directory = effects
+        .directory
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let report = ApplyReport {
+        status: match result {
+            Ok(()) => ApplyStatus::Success,
+            Err(_) => ApplyStatus::Failure,
+        },
+        time: history::current_time(),
+        user: history::current_user(&directory),
+        directory,
+        pipeline,
+        upstreams: effects.upstreams,
+        files: effects.files,
+        warnings: effects.warnings,
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+    notify::send(webhooks, &report);
+}
+
+/// Ask for the value of each variable in `unset`, adding them to `values`,
+/// and offer to save them to the configuration at `save_to`. Returns
+/// whether they were saved.
+fn prompt_required_vars(
+    unset: &[RequiredVar],
+    save_to: Option<&Path>,
+    values: &mut HashMap<String, String>,
+) -> Result<bool> {
+    let theme = ColorfulTheme::default();
+    output::status(Message::RequiredVarsUnset { count: unset.len() });
+    for var in unset {
+        let prompt = match &var.description {
+            Some(description) => format!("{} ({})", var.name, description),
+            None => var.name.clone(),
+        };
+        let mut input = Input::<String>::with_theme(&theme).with_prompt(prompt);
+        if let Some(default) = &var.default {
+            input = input.default(default.clone());
+        }
+        values.insert(var.name.clone(), input.interact_text()?);
+    }
+
+    let Some(path) = save_to else {
+        return Ok(false);
+    };
+    let save = Confirm::with_theme(&theme)
+        .with_prompt(Message::SaveRequiredVars { path }.to_string())
+        .default(true)
+        .interact()?;
+    if !save {
+        return Ok(false);
+    }
+    let content = std::fs::read_to_string(path)?;
+    std::fs::write(path, append_template_vars(&content, values)?)?;
+    output::status(Message::RequiredVarsSaved { path });
+    Ok(true)
+}
+
+/// `content` with a `template-vars` entry setting `values` appended, in the
+/// form `content` is written in.
+fn append_template_vars(content: &str, values: &HashMap<String, String>) -> Result<String> {
+    let sorted: BTreeMap<&String, &String> = values.iter().collect();
+    let form = migrate::Form::of(content);
+    let mut content = migrate::to_list(content)?;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("- template-vars:\n");
+    for line in serde_yaml::to_string(&sorted)?.lines() {
+        content.push_str("    ");
+        content.push_str(line);
+        content.push('\n');
+    }
+    if form == migrate::Form::Document {
+        content = migrate::to_document(&content)?;
+    }
+    Ok(content)
+}
+
+/// Install the pre-commit hook guarding the managed files of `final_fs`
+/// when `protection` asks for it, and remove one common-repo wrote when it
+/// does not.
+fn update_hook(protection: Protection, final_fs: &MemoryFS, output_dir: &Path) -> Result<()> {
+    if !protection.pre_commit {
+        protect::remove_hook(output_dir)?;
+        return Ok(());
+    }
+    match protect::install_hook(output_dir, final_fs)? {
+        HookOutcome::Installed(path) => {
+            log::info!("{}", Message::ProtectHookInstalled { path: &path })
+        }
+        HookOutcome::Foreign(path) => output::warning(Message::ProtectHookForeign { path: &path }),
+        HookOutcome::NotGit => {}
+    }
+    Ok(())
+}
+
+/// Write `final_fs` as a tar archive to the file `archive`.
+fn write_archive(final_fs: &MemoryFS, archive: &Path) -> Result<()> {
+    let file = std::fs::File::create(archive)
+        .map_err(|e| anyhow::anyhow!("Failed to create archive '{}': {}", archive.display(), e))?;
+    common_repo::phases::write_tar(final_fs, std::io::BufWriter::new(file))?;
+    Ok(())
+}
+
+/// Format the list of untracked files that would be overwritten.
+fn format_untracked_overwrites(conflicts: &[PathBuf]) -> String {
+    conflicts
+        .iter()
+        .map(|p| format!("  {}", p.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The rename mappings the configuration declares itself, including those
+/// under `repo: with:` and `self:`.
+fn own_rename_mappings(operations: &[Operation]) -> Vec<&RenameMapping> {
+    operations
+        .iter()
+        .flat_map(|op| match op {
+            Operation::Rename { rename } => rename.mappings.iter().collect(),
+            Operation::Repo { repo } => own_rename_mappings(&repo.with),
+            Operation::Self_ { self_ } => own_rename_mappings(&self_.operations),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Show what each sweeping rename moves and ask whether to write them.
+///
+/// Fails when stdin is not a terminal, since nobody can answer.
+fn confirm_sweeping_renames(sweeping: &[SweepingRename]) -> Result<bool> {
+    use std::io::Write;
+
+    for rename in sweeping {
+        output::warning(Message::SweepingRename {
+            from: &rename.mapping.from,
+            to: &rename.mapping.to,
+            count: rename.moves.len(),
+            description: rename.description.as_deref(),
+        });
+        for (from, to) in rename.moves.iter().take(SWEEPING_RENAME_SHOWN) {
+            output::warning(Message::SweepingRenameMove { from, to });
+        }
+        if rename.moves.len() > SWEEPING_RENAME_SHOWN {
+            output::warning(Message::SweepingRenameMore {
+                count: rename.moves.len() - SWEEPING_RENAME_SHOWN,
+            });
+        }
+    }
+    if !std::io::stdin().is_terminal() {
+        log::error!("{}", Message::ApplyFailed);
+        return Err(anyhow::anyhow!(
+            "{}",
+            Message::SweepingRenamesRefused {
+                count: sweeping.len()
+            }
+        ));
+    }
+    print!("{}", Message::ConfirmSweepingRenames);
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+/// The error of an apply refused under `--deny-warnings` for `count`
+/// warnings.
+fn warnings_denied(count: usize) -> anyhow::Error {
+    Failure::new(
+        Outcome::Warnings,
+        Message::WarningsDenied { count }.to_string(),
+    )
+    .into()
+}
+
+/// Print each diagnostic as a warning, followed by the total.
+fn report_diagnostics(diagnostics: &Diagnostics) {
+    if diagnostics.is_empty() {
+        return;
+    }
+    github::annotate_diagnostics(diagnostics);
+    for diagnostic in diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+    output::warning(Message::PipelineWarningCount {
+        count: diagnostics.len(),
+    });
+}
+
+/// Report operations slower than [`timings::SLOW_OPERATION`], and with
+/// `--verbose` the slowest operations of the run.
+fn report_timings(operation_timings: &Timings) {
+    let slow = operation_timings.slow();
+    if !slow.is_empty() {
+        log::info!("{}", Message::SlowOperations);
+        for timing in &slow {
+            log::info!("   {}", timing);
+        }
+    }
+    if !operation_timings.is_empty() {
+        log::debug!("Slowest operations:");
+        for timing in operation_timings.slowest(SLOWEST_OPERATIONS) {
+            log::debug!("   {}", timing);
+        }
+    }
+}
+
+/// Append the outcome of the run to the GitHub Actions job summary.
+fn write_job_summary(outcome: &str, diagnostics: &Diagnostics, operation_timings: &Timings) {
+    let mut markdown = format!("### common-repo apply\n\n{}\n", outcome);
+    if !diagnostics.is_empty() {
+        let rows: Vec<Vec<String>> = diagnostics
+            .iter()
+            .map(|d| vec![d.phase.as_str().to_string(), d.message.clone()])
+            .collect();
+        markdown.push('\n');
+        markdown.push_str(&github::summary_table(&["Phase", "Warning"], &rows));
+    }
+    let slow = operation_timings.slow();
+    if !slow.is_empty() {
+        let rows: Vec<Vec<String>> = slow
+            .iter()
+            .map(|timing| {
+                vec![
+                    timing.operation.clone(),
+                    timing
+                        .source
+                        .clone()
+                        .unwrap_or_else(|| "local config".to_string()),
+                    format!("{:.2}s", timing.elapsed.as_secs_f64()),
+                    format!("{} -> {}", timing.files_before, timing.files_after),
+                ]
+            })
+            .collect();
+        markdown.push('\n');
+        markdown.push_str(&github::summary_table(
+            &["Slow operation", "Source", "Time", "Files"],
+            &rows,
+        ));
+    }
+    github::append_summary(&markdown);
+}
+
+/// Write `diagnostics` as JSON to `path`, if one was given.
+fn write_warnings_json(path: Option<&Path>, diagnostics: &Diagnostics) -> Result<()> {
+    if let Some(path) = path {
+        std::fs::write(path, diagnostics.to_json())
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// The configuration of the pipeline `name`, relative to the current
+/// directory.
+fn pipeline_config(name: &str) -> Result<PathBuf> {
+    use common_repo::defaults::{is_valid_pipeline_name, pipeline_config_path, pipeline_names};
+
+    if !is_valid_pipeline_name(name) {
+        anyhow::bail!(
+            "Invalid pipeline name '{}': use only letters, digits, '-' and '_'",
+            name
+        );
+    }
+    let path = pipeline_config_path(Path::new(""), name);
+    if !path.exists() {
+        return Err(common_repo::suggestions::pipeline_not_found(
+            name,
+            &path,
+            &pipeline_names(Path::new(".")),
+        ));
+    }
+    Ok(path)
+}
+
+/// Download the configuration at `url` and parse it, checking the content
+/// against `sha256` when given. The URL rules are those of the `file:`
+/// operation.
+///
+/// Content pinned by `sha256` is trusted as the consumer's own
+/// configuration. Other content could change under the same URL, so it is
+/// read like an upstream's (see [`parse_remote_config`]).
+fn remote_config(url: &str, sha256: Option<&str>) -> Result<common_repo::config::Schema> {
+    use common_repo::{config, http};
+
+    config::FileOp {
+        url: url.to_string(),
+        sha256: sha256.map(str::to_string),
+        dest: Some(DEFAULT_CONFIG_FILENAME.to_string()),
+    }
+    .validate()?;
+
+    log::debug!("📋 Downloading configuration: {}", url);
+    let content = http::download(url)?;
+    if let Some(sha256) = sha256 {
+        http::verify_sha256(url, &content, sha256)?;
+    }
+    let content =
+        String::from_utf8(content).map_err(|_| common_repo::error::Error::ConfigParse {
+            message: format!("Configuration at {} is not UTF-8 text", url),
+            hint: None,
+        })?;
+    parse_remote_config(&content, sha256.is_some())
+}
+
+/// Parse a downloaded configuration. Unless its content is `pinned`, it
+/// may neither interpolate the environment nor read secret variables, since
+/// either would let whoever serves it read or run things on this machine.
+fn parse_remote_config(content: &str, pinned: bool) -> Result<common_repo::config::Schema> {
+    use common_repo::config;
+
+    if pinned {
+        return Ok(config::parse(content)?);
+    }
+    let mut schema = config::parse_upstream(content, &[])?.schema;
+    drop_secret_vars(&mut schema);
+    Ok(schema)
+}
+
+/// Remove the secret variables of the `template-vars` in `operations`,
+/// warning about each.
+fn drop_secret_vars(operations: &mut common_repo::config::Schema) {
+    use common_repo::config::Operation;
+
+    for operation in operations {
+        match operation {
+            Operation::TemplateVars { template_vars } => {
+                let mut names: Vec<_> = template_vars
+                    .secrets
+                    .drain()
+                    .map(|(name, _)| name)
+                    .collect();
+                names.sort();
+                for name in &names {
+                    output::warning(Message::UpstreamSecretIgnored { name });
+                }
+            }
+            Operation::Repo { repo } => drop_secret_vars(&mut repo.with),
+            Operation::Self_ { self_ } => drop_secret_vars(&mut self_.operations),
+            _ => {}
+        }
+    }
 }
 
 /// Execute the `apply` command.
@@ -77,84 +1128,461 @@ pub struct ApplyArgs {
 /// and setting up the environment to invoking the main pipeline and reporting
 /// the results.
 pub fn execute(args: ApplyArgs) -> Result<()> {
+    // Dry runs, sandboxes and shadow applies change nothing anyone needs
+    // to hear about.
+    let webhooks = if args.dry_run || args.sandbox || args.shadow_dir.is_some() {
+        Vec::new()
+    } else {
+        UserConfig::load()?.webhooks
+    };
+    let pipeline = args.pipeline.clone();
+    let mut effects = Effects::default();
+    let result = apply(args, &mut effects);
+    notify_webhooks(&webhooks, pipeline, effects, &result);
+    result
+}
+
+/// Run the apply described by `args`, recording what it did in `effects`.
+fn apply(args: ApplyArgs, effects: &mut Effects) -> Result<()> {
     use common_repo::cache::RepoCache;
     use common_repo::config::from_file;
+    use common_repo::fingerprint::FingerprintStore;
+    use common_repo::phases::checkpoint::RunDir;
+    use common_repo::phases::context::RunContext;
     use common_repo::phases::orchestrator;
     use common_repo::repository::RepositoryManager;
     use std::time::Instant;
 
     let start_time = Instant::now();
 
-    // Determine config file path
-    let config_path = args
-        .config
-        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILENAME));
+    // Determine config file path; a remote configuration has none
+    let config_path = match (&args.config_url, args.pipeline.as_deref()) {
+        (Some(_), _) => None,
+        (None, Some(name)) => Some(pipeline_config(name)?),
+        (None, None) => Some(common_repo::defaults::resolve_config_path(
+            args.config
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_CONFIG_FILENAME)),
+        )),
+    };
 
     // Validate config file exists
-    if !config_path.exists() {
-        return Err(common_repo::suggestions::config_not_found(&config_path));
+    if let Some(config_path) = &config_path {
+        if !config_path.exists() {
+            return Err(common_repo::suggestions::config_not_found(config_path));
+        }
     }
 
-    // Determine output directory
-    let output_dir = args
-        .output
-        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+    // Determine output directory. Other formats read it but do not write
+    // to it; a tar archive replaces it.
+    let to_disk = args.output_format == OutputFormat::Files;
+    let archive = match args.output_format {
+        OutputFormat::Tar => args.output.clone(),
+        _ => None,
+    };
+    let sandbox = args.sandbox.then(Sandbox::new).transpose()?;
+    let output_dir = match (&sandbox, &args.shadow_dir, args.output_format) {
+        (Some(sandbox), _, _) => sandbox.0.clone(),
+        (None, Some(shadow_dir), _) => {
+            std::fs::create_dir_all(shadow_dir)?;
+            std::env::current_dir()?.join(shadow_dir)
+        }
+        (None, None, OutputFormat::Tar) => {
+            std::env::current_dir().expect("Failed to get current directory")
+        }
+        (None, None, _) => args
+            .output
+            .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory")),
+    };
+    // Whether the live files are written, rather than a sandbox or shadow
+    // directory.
+    let live = sandbox.is_none() && args.shadow_dir.is_none();
+
+    effects.directory = Some(output_dir.clone());
 
     // Determine cache root
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    // A remote configuration cannot set `cache-dir` for this project.
+    let cache_root = common_repo::defaults::resolve_cache_root(
+        args.cache_root,
+        config_path.as_deref().unwrap_or(Path::new("")),
+    );
 
     // Print header
-    log::info!("🔍 Common Repository Apply");
+    log::info!("{}", Message::ApplyHeader);
 
     if args.dry_run {
-        log::info!("🔎 DRY RUN MODE - No changes will be made");
+        log::info!("{}", Message::ApplyDryRun);
     }
+    if let Some(sandbox) = &sandbox {
+        log::info!("{}", Message::ApplyingToSandbox { path: &sandbox.0 });
+    }
+
+    // Setup repository manager and cache. This also sets up the download
+    // cache used for a remote configuration.
+    let repo_manager = RepositoryManager::new(cache_root.clone());
 
     // Parse configuration
-    log::debug!("📋 Parsing configuration: {}", config_path.display());
-    let config = from_file(&config_path)?;
+    let config = match (&config_path, &args.config_url) {
+        (Some(config_path), _) => {
+            log::debug!("📋 Parsing configuration: {}", config_path.display());
+            from_file(config_path)?
+        }
+        (None, Some(url)) => remote_config(url, args.config_sha256.as_deref())?,
+        (None, None) => unreachable!("no configuration path or URL"),
+    };
+    let plan = match &args.from_plan {
+        Some(path) => {
+            let plan = Plan::load(path)?;
+            plan.check(&config)?;
+            log::info!("{}", Message::ApplyingPlan { path });
+            Some(plan)
+        }
+        None => None,
+    };
+    // A shadow apply composes newer upstream refs in place of the
+    // configured ones.
+    let (config, shadow) = match &args.shadow_dir {
+        Some(_) => {
+            let bumps = shadow_bumps(&config, &args.shadow_ref, &repo_manager)?;
+            if bumps.is_empty() {
+                output::status(Message::NoNewerRefs);
+                return Ok(());
+            }
+            let refs = bumps
+                .iter()
+                .map(|bump| (bump.url.clone(), bump.to.clone()))
+                .collect();
+            (common_repo::version::with_refs(&config, &refs), Some(bumps))
+        }
+        None => (config, None),
+    };
+    // A remote configuration cannot protect this project's files, nor
+    // bound where they are written.
+    let (protection, scope) = match &config_path {
+        Some(config_path) => {
+            let yaml = std::fs::read_to_string(config_path)?;
+            (
+                common_repo::config::protect(&yaml)?,
+                common_repo::config::path_scope(&yaml)?,
+            )
+        }
+        None => (Protection::default(), PathScope::default()),
+    };
+    let repo_cache = match args.max_memory {
+        Some(max_memory) => RepoCache::with_max_memory(max_memory),
+        None => RepoCache::new(),
+    };
+    let working_dir = std::env::current_dir().expect("Failed to get current directory");
 
-    // Setup repository manager and cache
-    let repo_manager = RepositoryManager::new(cache_root.clone());
-    let repo_cache = RepoCache::new();
+    // Skip the pipeline when its inputs match the last successful apply.
+    // An apply placed under a subdirectory has inputs of its own.
+    let fingerprint_dir = match &args.into {
+        Some(into) => output_dir.join(into),
+        None => output_dir.clone(),
+    };
+    let mut fingerprints = FingerprintStore::new(&cache_root, &working_dir, &fingerprint_dir);
+    if let Some(name) = args.pipeline.as_deref() {
+        fingerprints = fingerprints.pipeline(name);
+    }
+    let check_fingerprint = to_disk
+        && plan.is_none()
+        && live
+        && !(args.dry_run || args.force || args.no_cache || args.resume);
+    if check_fingerprint && fingerprints.is_up_to_date(&config) {
+        log::info!("{}", Message::UpToDate);
+        outcome::record_files(0);
+        write_warnings_json(args.warnings_json.as_deref(), &Diagnostics::new())?;
+        write_job_summary(
+            &Message::UpToDate.to_string(),
+            &Diagnostics::new(),
+            &Timings::default(),
+        );
+        return Ok(());
+    }
 
-    // Execute the 6-phase pipeline
-    let result = orchestrator::execute_pull(
-        &config,
-        &repo_manager,
-        &repo_cache,
-        &std::env::current_dir().expect("Failed to get current directory"),
-        if args.dry_run {
-            None
+    // Ask for the variables upstreams require that the configuration does
+    // not set, rather than failing on them. Without a terminal the pipeline
+    // fails and lists them all. A plan has its variables filled in already.
+    let mut prompted = HashMap::new();
+    let config = if !args.yes && plan.is_none() && std::io::stdin().is_terminal() {
+        let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir);
+        let unset = common_repo::phases::unset_required_vars(&config, &ctx)?;
+        let save_to = config_path.as_deref().filter(|_| !args.dry_run);
+        if unset.is_empty() {
+            config
+        } else if prompt_required_vars(&unset, save_to, &mut prompted)? {
+            from_file(save_to.expect("values are only saved to a config file"))?
         } else {
-            Some(&output_dir)
-        },
-    );
+            config
+        }
+    } else {
+        config
+    };
+
+    // Checkpoints for --resume. Dry runs neither save nor load them.
+    let run = (!args.dry_run && plan.is_none() && live)
+        .then(|| RunDir::new(&cache_root, &working_dir, &config, args.resume));
+    let resume_tip = |e: common_repo::error::Error| {
+        log::error!("{}", Message::ApplyFailed);
+        if !args.resume && run.as_ref().is_some_and(RunDir::exists) {
+            log::info!("{}", Message::ResumeTip);
+        }
+        e
+    };
+
+    // Files git tracks in the output directory. `None` when the check is
+    // disabled, the output is a shadow directory, or the output directory
+    // is not inside a git work tree.
+    let tracked = if args.force_untracked || !to_disk || args.shadow_dir.is_some() {
+        None
+    } else {
+        common_repo::git::tracked_files(&output_dir)?
+    };
+
+    // Before writing, compose the source block without output to see which
+    // files would change and what it warns about, and refuse to clobber
+    // untracked files or to write despite warnings under --deny-warnings.
+    // Sweeping renames of the configuration are confirmed from the preview
+    // too. The write then uses the composed files as a plan, so consumer
+    // operations such as `file:` downloads, patches and secret commands run
+    // once, and what was checked is what is written. `self:` blocks read
+    // the files the source block writes, so they are not previewed.
+    let own_renames = if args.yes {
+        Vec::new()
+    } else {
+        own_rename_mappings(&config)
+    };
+    let needs_preview =
+        to_disk && (tracked.is_some() || args.deny_warnings || !own_renames.is_empty());
+    let mut operation_timings = Timings::default();
+    let pull = |config: &Schema,
+                output_path: Option<&Path>,
+                plan: Option<&Plan>,
+                diagnostics: &mut Diagnostics| {
+        let mut ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir);
+        ctx.output_path = output_path;
+        ctx.run = run.as_ref();
+        ctx.pipeline = args.pipeline.as_deref();
+        ctx.follow_symlinks = args.follow_symlinks;
+        ctx.read_only = args.read_only.into();
+        ctx.protect = protection.read_only;
+        ctx.scope = scope.clone();
+        ctx.vars = prompted.clone();
+        ctx.plan = plan;
+        ctx.into = args.into.as_deref();
+        ctx.strict = args.strict;
+        orchestrator::execute_plan(config, &ctx, diagnostics)
+    };
+    // What the last apply propagated, to tell generated files from local
+    // ones and what this apply changes.
+    let previous_manifest = ApplyManifest::load(&cache_root, &output_dir, args.pipeline.as_deref());
+    let mut diagnostics = Diagnostics::new();
+    let mut composed = None;
+    if !args.dry_run && needs_preview {
+        let (_, source_config) = orchestrator::partition_self_operations(&config);
+        let mut preview_diagnostics = Diagnostics::new();
+        let ((preview, preview_timings), rename_trace) = renames::trace(|| {
+            timings::trace(|| {
+                pull(
+                    &source_config,
+                    None,
+                    plan.as_ref(),
+                    &mut preview_diagnostics,
+                )
+            })
+        });
+        operation_timings.extend(preview_timings);
+        let preview = preview.map_err(resume_tip)?;
+        if let Some(tracked) = tracked.as_ref() {
+            let conflicts =
+                find_untracked_overwrites(&preview.fs, &output_dir, tracked, &previous_manifest);
+            if !conflicts.is_empty() {
+                for path in &conflicts {
+                    github::annotate(
+                        github::Annotation::new(github::Level::Error, Message::UntrackedAnnotation)
+                            .file(output_dir.join(path)),
+                    );
+                }
+                log::error!("{}", Message::ApplyFailed);
+                return Err(Failure::new(
+                    Outcome::Conflicts,
+                    format!(
+                        "Refusing to overwrite {} file(s) not tracked by git:\n{}\n  hint: commit or move these files, or re-run with --force-untracked",
+                        conflicts.len(),
+                        format_untracked_overwrites(&conflicts)
+                    ),
+                )
+                .into());
+            }
+        }
+        if args.deny_warnings && !preview_diagnostics.is_empty() {
+            report_diagnostics(&preview_diagnostics);
+            write_warnings_json(args.warnings_json.as_deref(), &preview_diagnostics)?;
+            outcome::record_warnings(preview_diagnostics.len());
+            log::error!("{}", Message::ApplyFailed);
+            return Err(warnings_denied(preview_diagnostics.len()));
+        }
+        let sweeping = rename_trace.sweeping(&own_renames, SWEEPING_RENAME_FILES);
+        if !sweeping.is_empty() && !confirm_sweeping_renames(&sweeping)? {
+            output::status(Message::ApplyCancelled);
+            return Ok(());
+        }
+        diagnostics.extend(preview_diagnostics);
+        composed = Some(preview);
+    }
+
+    // Execute the 6-phase pipeline
+    let output_path = (!args.dry_run && to_disk).then_some(output_dir.as_path());
+    let (result, run_timings) = timings::trace(|| {
+        pull(
+            &config,
+            output_path,
+            composed.as_ref().or(plan.as_ref()),
+            &mut diagnostics,
+        )
+    });
+    operation_timings.extend(run_timings);
+    // For `info --cache`, including when the run failed.
+    LastRun::new(repo_manager.fetches()).save(&cache_root);
+    effects.upstreams = history::upstream_commits(repo_manager.fetches());
+    effects.warnings = diagnostics.len();
+    outcome::record_warnings(diagnostics.len());
+    if let Some(sandbox) = &sandbox {
+        // Nothing will be applied into the sandbox again.
+        let manifest = ApplyManifest::path(&cache_root, &sandbox.0, args.pipeline.as_deref());
+        let _ = std::fs::remove_file(manifest);
+    }
+
+    report_diagnostics(&diagnostics);
+    write_warnings_json(args.warnings_json.as_deref(), &diagnostics)?;
+
+    // Warnings of the source block stopped the write above; those of
+    // `self:` blocks are only known now.
+    if args.deny_warnings && !diagnostics.is_empty() {
+        log::error!("{}", Message::ApplyFailed);
+        return Err(warnings_denied(diagnostics.len()));
+    }
 
     match result {
-        Ok(final_fs) => {
+        Ok(output) => {
+            let replaced = replaced_local_files(&output);
+            let final_fs = output.fs;
+            if let (true, Some(tracked)) = (args.dry_run, tracked.as_ref()) {
+                let conflicts =
+                    find_untracked_overwrites(&final_fs, &output_dir, tracked, &previous_manifest);
+                if !conflicts.is_empty() {
+                    let files = format_untracked_overwrites(&conflicts);
+                    log::warn!(
+                        "{}",
+                        Message::WouldOverwriteUntracked {
+                            count: conflicts.len(),
+                            files: &files,
+                        }
+                    );
+                }
+            }
+
+            let written = if args.dry_run {
+                None
+            } else if let Some(archive) = &archive {
+                write_archive(&final_fs, archive)?;
+                Some(archive.display().to_string())
+            } else if args.output_format == OutputFormat::GitTree {
+                let changed = changed_files(&final_fs, &output_dir);
+                let commit = common_repo::git::commit_files(
+                    &output_dir,
+                    changed.iter().map(|(path, file)| (path.as_path(), *file)),
+                    "Apply common-repo configuration",
+                )?;
+                println!("{}", commit);
+                Some(format!("commit {}", commit))
+            } else {
+                Some(output_dir.display().to_string())
+            };
+
+            if !args.dry_run && to_disk {
+                write_file_map(
+                    &final_fs,
+                    &output_dir,
+                    args.into.as_deref().unwrap_or(Path::new("")),
+                )?;
+            }
+            if let Some(bumps) = shadow {
+                let live_manifest =
+                    ApplyManifest::load(&cache_root, &working_dir, args.pipeline.as_deref());
+                report_shadow(bumps, &final_fs, &output_dir, &working_dir, &live_manifest)?;
+            }
+            if !args.dry_run && to_disk && live {
+                // Before the fingerprint, which covers the history too.
+                let manifest =
+                    ApplyManifest::load(&cache_root, &output_dir, args.pipeline.as_deref());
+                let mut changed = previous_manifest.changes(&manifest);
+                changed.extend(replaced);
+                changed.sort();
+                changed.dedup();
+                let entry = record_history(
+                    &output_dir,
+                    &config,
+                    config_path.as_deref(),
+                    args.pipeline.as_deref(),
+                    effects.upstreams.clone(),
+                    changed,
+                )?;
+                outcome::record_files(entry.files.len());
+                effects.files = entry.files;
+                update_hook(protection, &final_fs, &output_dir)?;
+                // The upstreams may have moved on since the plan was saved.
+                if diagnostics.is_empty() && plan.is_none() {
+                    if let Err(e) = fingerprints.save(&config, &repo_manager) {
+                        log::debug!("failed to save fingerprint: {}", e);
+                    }
+                } else {
+                    fingerprints.clear();
+                }
+            }
+
             let duration = start_time.elapsed();
 
-            log::info!("✅ Applied successfully in {:.2}s", duration.as_secs_f64());
+            log::info!(
+                "{}",
+                Message::ApplySucceeded {
+                    seconds: duration.as_secs_f64(),
+                }
+            );
+            report_timings(&operation_timings);
 
             // Report statistics
             let file_count = final_fs.len();
+            write_job_summary(
+                &if args.dry_run {
+                    format!("Dry run: {} file(s) would be written.", file_count)
+                } else {
+                    format!(
+                        "{} file(s) written to `{}`.",
+                        file_count,
+                        match &written {
+                            Some(written) if !to_disk => written.clone(),
+                            _ => github::workspace_path(&output_dir).display().to_string(),
+                        }
+                    )
+                },
+                &diagnostics,
+                &operation_timings,
+            );
             if file_count > 0 {
                 log::info!("   {} files processed", file_count);
 
-                if !args.dry_run {
-                    log::info!("   Files written to: {}", output_dir.display());
+                if let Some(written) = &written {
+                    log::info!("   Files written to: {}", written);
                 }
             }
 
+            if let (Some(sandbox), Some(command)) = (&sandbox, args.run_command.as_deref()) {
+                run_in_sandbox(&sandbox.0, command)?;
+            }
             Ok(())
         }
-        Err(e) => {
-            log::error!("❌ Apply failed");
-            Err(e.into())
-        }
+        Err(e) => Err(resume_tip(e).into()),
     }
 }
 
@@ -166,15 +1594,72 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_unpinned_remote_config_reads_no_secrets() {
+        use common_repo::config::Operation;
+
+        let content = "- template-vars:\n    NAME: billing\n    TOKEN: { from_command: \"cat ~/.ssh/id_rsa\" }\n- self:\n    - template-vars:\n        KEY: { from_env_file: .env }\n";
+        let secrets = |schema: &common_repo::config::Schema| {
+            let mut count = 0;
+            for operation in schema {
+                match operation {
+                    Operation::TemplateVars { template_vars } => {
+                        count += template_vars.secrets.len()
+                    }
+                    Operation::Self_ { self_ } => {
+                        for operation in &self_.operations {
+                            if let Operation::TemplateVars { template_vars } = operation {
+                                count += template_vars.secrets.len();
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            count
+        };
+        assert_eq!(secrets(&parse_remote_config(content, true).unwrap()), 2);
+        let unpinned = parse_remote_config(content, false).unwrap();
+        assert_eq!(secrets(&unpinned), 0);
+        let Operation::TemplateVars { template_vars } = &unpinned[0] else {
+            panic!("expected template-vars");
+        };
+        assert_eq!(template_vars.vars["NAME"], "billing");
+
+        // PATH is set wherever the tests run
+        let content = "- exclude: ['${PATH}']\n";
+        assert!(parse_remote_config(content, true).is_ok());
+        assert!(parse_remote_config(content, false).is_err());
+    }
+
     #[test]
     fn test_execute_missing_config() {
         let args = ApplyArgs {
             config: Some(PathBuf::from("/nonexistent/config.yaml")),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: None,
             cache_root: None,
             dry_run: false,
             force: false,
             no_cache: false,
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         let result = execute(args);
@@ -195,11 +1680,30 @@ mod tests {
 
         let args = ApplyArgs {
             config: Some(config_path),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: Some(temp_dir.path().to_path_buf()),
             cache_root: Some(temp_dir.path().join("cache")),
             dry_run: true,
             force: false,
             no_cache: false,
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         let result = execute(args);
@@ -220,11 +1724,30 @@ mod tests {
 
         let args = ApplyArgs {
             config: None,
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: None,
             cache_root: None,
             dry_run: true,
             force: false,
             no_cache: false,
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         let result = execute(args);
@@ -248,11 +1771,30 @@ mod tests {
 
         let args = ApplyArgs {
             config: Some(config_path),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: Some(temp_dir.path().to_path_buf()),
             cache_root: Some(temp_dir.path().join("cache")),
             dry_run: true,
             force: false,
             no_cache: false,
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         // Dry run should succeed without making changes
@@ -272,11 +1814,30 @@ mod tests {
 
         let args = ApplyArgs {
             config: Some(config_path),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: Some(output_dir.clone()),
             cache_root: Some(temp_dir.path().join("cache")),
             dry_run: false, // Not dry run, so should print output directory
             force: false,
             no_cache: false, // Quiet to avoid console output in tests
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         let result = execute(args);
@@ -284,6 +1845,158 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn git(dir: &Path, args: &[&str]) {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_append_template_vars() {
+        let values = HashMap::from([
+            ("project_name".to_string(), "demo".to_string()),
+            ("license".to_string(), "MIT: see LICENSE".to_string()),
+        ]);
+        let content = append_template_vars("- include: ['**']", &values).unwrap();
+        assert_eq!(
+            content,
+            "- include: ['**']\n- template-vars:\n    license: 'MIT: see LICENSE'\n    project_name: demo\n"
+        );
+        let schema = common_repo::config::parse(&content).unwrap();
+        let Operation::TemplateVars { template_vars } = &schema[1] else {
+            panic!("expected a template-vars operation");
+        };
+        assert_eq!(template_vars.vars, values);
+
+        let document = "apiVersion: common-repo/v1\noperations:\n  - include: ['**']\n";
+        let content = append_template_vars(document, &values).unwrap();
+        assert!(content.starts_with(document), "{}", content);
+        assert!(content.contains("  - template-vars:\n      license:"));
+        assert_eq!(common_repo::config::parse(&content).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("1048576"), Ok(1 << 20));
+        assert_eq!(parse_memory_size("512K"), Ok(512 << 10));
+        assert_eq!(parse_memory_size("512mb"), Ok(512 << 20));
+        assert_eq!(parse_memory_size("2GiB"), Ok(2 << 30));
+        for invalid in ["", "0", "G", "-1M", "1.5G", "12X"] {
+            assert!(parse_memory_size(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_into() {
+        assert_eq!(
+            parse_into("infra/shared"),
+            Ok(PathBuf::from("infra/shared"))
+        );
+        assert_eq!(parse_into("./infra/"), Ok(PathBuf::from("infra")));
+        for invalid in ["", ".", "/infra", "../infra", "infra/../.."] {
+            assert!(parse_into(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_find_untracked_overwrites() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::write(dir.join("tracked.txt"), "old").unwrap();
+        fs::write(dir.join("scratch.txt"), "mine").unwrap();
+        fs::write(dir.join("same.txt"), "same").unwrap();
+        fs::write(dir.join("generated.txt"), "last apply").unwrap();
+        git(dir, &["add", "tracked.txt"]);
+
+        let mut final_fs = MemoryFS::new();
+        final_fs.add_file_string("tracked.txt", "new").unwrap();
+        final_fs.add_file_string("scratch.txt", "upstream").unwrap();
+        final_fs.add_file_string("same.txt", "same").unwrap();
+        final_fs.add_file_string("fresh.txt", "new file").unwrap();
+        final_fs
+            .add_file_string("generated.txt", "this apply")
+            .unwrap();
+
+        // The last apply wrote generated.txt and it was not edited since
+        let mut manifest = ApplyManifest::new();
+        manifest.insert("generated.txt", &File::from_string("last apply"));
+        manifest.insert("scratch.txt", &File::from_string("an older apply"));
+
+        let tracked = common_repo::git::tracked_files(dir).unwrap().unwrap();
+        let conflicts = find_untracked_overwrites(&final_fs, dir, &tracked, &manifest);
+        assert_eq!(conflicts, vec![PathBuf::from("scratch.txt")]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_execute_refuses_untracked_overwrite() {
+        let original_dir = env::current_dir().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        let consumer = temp_dir.path().join("consumer");
+        fs::create_dir_all(&upstream).unwrap();
+        fs::create_dir_all(&consumer).unwrap();
+        fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+        fs::write(upstream.join("notes.txt"), "upstream").unwrap();
+        fs::write(
+            consumer.join(".common-repo.yaml"),
+            "- repo:\n    url: ../upstream\n",
+        )
+        .unwrap();
+        git(&consumer, &["init", "-q"]);
+        fs::write(consumer.join("notes.txt"), "scratch").unwrap();
+
+        let make_args = |force_untracked| ApplyArgs {
+            config: Some(consumer.join(".common-repo.yaml")),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
+            output: Some(consumer.clone()),
+            cache_root: Some(temp_dir.path().join("cache")),
+            dry_run: false,
+            force: false,
+            no_cache: false,
+            force_untracked,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
+        };
+
+        env::set_current_dir(&consumer).unwrap();
+        let refused = execute(make_args(false));
+        let untouched = fs::read_to_string(consumer.join("notes.txt")).unwrap();
+        let forced = execute(make_args(true));
+        env::set_current_dir(&original_dir).unwrap();
+
+        let err = refused.unwrap_err().to_string();
+        assert!(err.contains("notes.txt"), "unexpected error: {err}");
+        assert!(err.contains("--force-untracked"));
+        assert_eq!(untouched, "scratch");
+
+        forced.unwrap();
+        assert_eq!(
+            fs::read_to_string(consumer.join("notes.txt")).unwrap(),
+            "upstream"
+        );
+    }
+
     #[test]
     fn test_execute_with_invalid_output_directory() {
         // Test execution with invalid output directory to trigger error path
@@ -305,11 +2018,30 @@ mod tests {
 
         let args = ApplyArgs {
             config: Some(config_path),
+            pipeline: None,
+            config_url: None,
+            config_sha256: None,
             output: Some(invalid_output), // Invalid path should cause failure
             cache_root: Some(temp_dir.path().join("cache")),
             dry_run: false,
             force: false,
             no_cache: false,
+            force_untracked: false,
+            follow_symlinks: false,
+            output_format: OutputFormat::Files,
+            read_only: ReadOnlyFiles::Error,
+            deny_warnings: false,
+            strict: false,
+            yes: false,
+            warnings_json: None,
+            resume: false,
+            max_memory: None,
+            from_plan: None,
+            into: None,
+            sandbox: false,
+            run_command: None,
+            shadow_dir: None,
+            shadow_ref: Vec::new(),
         };
 
         let result = execute(args);
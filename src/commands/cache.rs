@@ -7,15 +7,39 @@
 //!
 //! - **`list`**: Display all cached repositories with their information
 //! - **`clean`**: Remove cached repositories based on filters (--all, --unused, --older-than)
+//! - **`warm`**: Fetch every repository configurations inherit from
+//! - **`export`**: Bundle cached repositories into a tar archive
+//! - **`import`**: Add the repositories of an exported archive to the cache
+//!
+//! `export` and `import` move a cache to a machine without network access.
+//! The archive records the URL and ref of each entry, and `import` files
+//! every entry under the name the importing binary computes for it, so the
+//! two sides need not run the same version. Archives are written and read
+//! with the system `tar` command.
 
 use anyhow::{Context, Result};
 use clap::{Args, Subcommand};
+use clap_complete::ArgValueCandidates;
+use std::ffi::OsStr;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
+use common_repo::cache::{commit_staged, EntryLock, RepoCache};
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics;
+use common_repo::git::url_to_cache_path;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::{discover_repos, prefetch, RepoNode};
+use common_repo::repository::RepositoryManager;
+use serde::{Deserialize, Serialize};
+
+use super::completions;
+
 /// Manage repository cache
 #[derive(Args, Debug)]
 pub struct CacheArgs {
@@ -23,8 +47,15 @@ pub struct CacheArgs {
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// Subcommand to execute
@@ -38,6 +69,12 @@ pub enum CacheSubcommand {
     List(ListArgs),
     /// Clean cached repositories
     Clean(CleanArgs),
+    /// Fetch every repository configurations inherit from into the cache
+    Warm(WarmArgs),
+    /// Bundle cached repositories into an archive for another machine
+    Export(ExportArgs),
+    /// Add the repositories of an exported archive to the cache
+    Import(ImportArgs),
 }
 
 /// Arguments for the cache list command
@@ -79,6 +116,81 @@ pub struct CleanArgs {
     pub yes: bool,
 }
 
+/// Arguments for the cache warm command
+#[derive(Args, Debug)]
+pub struct WarmArgs {
+    /// Configurations to warm the cache for; repeat for several projects
+    #[arg(
+        short,
+        long = "config",
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub configs: Vec<PathBuf>,
+}
+
+/// Arguments for the cache export command
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The archive to write (a tar file)
+    #[arg(value_name = "ARCHIVE")]
+    pub archive: PathBuf,
+
+    /// Repositories to export, as `URL@REF`
+    ///
+    /// Without any, every repository the configuration inherits from is
+    /// exported, fetching those that are not cached yet.
+    #[arg(value_name = "URL@REF", add = ArgValueCandidates::new(completions::cached_repos))]
+    pub repos: Vec<String>,
+
+    /// Configuration whose repositories are exported when no `URL@REF` is given
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        conflicts_with = "repos",
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+}
+
+/// Arguments for the cache import command
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// The archive written by `cache export`
+    #[arg(value_name = "ARCHIVE")]
+    pub archive: PathBuf,
+
+    /// Replace repositories that are already cached
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Name of the index of an exported archive.
+const ARCHIVE_INDEX: &str = "common-repo-cache.json";
+
+/// Version of the archive index format.
+const ARCHIVE_VERSION: u32 = 1;
+
+/// The index of an exported archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveIndex {
+    version: u32,
+    entries: Vec<ArchiveEntry>,
+}
+
+/// One repository in an exported archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveEntry {
+    url: String,
+    #[serde(rename = "ref")]
+    ref_: String,
+    /// Directory in the archive holding the repository's files.
+    dir: String,
+}
+
 /// Cache entry information
 #[derive(Debug, Clone)]
 struct CacheEntry {
@@ -93,24 +205,38 @@ struct CacheEntry {
 
 /// Execute the `cache` command.
 pub fn execute(args: CacheArgs) -> Result<()> {
+    // The project in the current directory may keep its own cache.
+    let cache_root = |explicit| {
+        common_repo::defaults::resolve_cache_root(
+            explicit,
+            &common_repo::defaults::resolve_config_path(Path::new(DEFAULT_CONFIG_FILENAME)),
+        )
+    };
     match args.command {
-        CacheSubcommand::List(list_args) => execute_list(args.cache_root, list_args),
-        CacheSubcommand::Clean(clean_args) => execute_clean(args.cache_root, clean_args),
+        CacheSubcommand::List(list_args) => execute_list(cache_root(args.cache_root), list_args),
+        CacheSubcommand::Clean(clean_args) => {
+            execute_clean(cache_root(args.cache_root), clean_args)
+        }
+        // Each configuration warmed may keep its own cache.
+        CacheSubcommand::Warm(warm_args) => execute_warm(args.cache_root, warm_args),
+        CacheSubcommand::Export(export_args) => {
+            execute_export(cache_root(args.cache_root), export_args)
+        }
+        CacheSubcommand::Import(import_args) => {
+            execute_import(cache_root(args.cache_root), import_args)
+        }
     }
 }
 
 /// Execute the `cache list` command.
-fn execute_list(cache_root: Option<PathBuf>, args: ListArgs) -> Result<()> {
-    // Determine cache root path
-    let cache_root = cache_root.unwrap_or_else(common_repo::defaults::default_cache_root);
-
+fn execute_list(cache_root: PathBuf, args: ListArgs) -> Result<()> {
     // Check if cache directory exists
     if !cache_root.exists() {
         if args.json {
             println!("[]");
         } else {
-            println!("Cache directory does not exist: {}", cache_root.display());
-            println!("No cached repositories found.");
+            output::status(Message::CacheMissing { path: &cache_root });
+            output::status(Message::NoCachedRepos);
         }
         return Ok(());
     }
@@ -122,7 +248,7 @@ fn execute_list(cache_root: Option<PathBuf>, args: ListArgs) -> Result<()> {
         if args.json {
             println!("[]");
         } else {
-            println!("No cached repositories found in: {}", cache_root.display());
+            output::status(Message::CacheEmpty { path: &cache_root });
         }
         return Ok(());
     }
@@ -140,10 +266,7 @@ fn execute_list(cache_root: Option<PathBuf>, args: ListArgs) -> Result<()> {
 }
 
 /// Execute the `cache clean` command.
-fn execute_clean(cache_root: Option<PathBuf>, args: CleanArgs) -> Result<()> {
-    // Determine cache root path
-    let cache_root = cache_root.unwrap_or_else(common_repo::defaults::default_cache_root);
-
+fn execute_clean(cache_root: PathBuf, args: CleanArgs) -> Result<()> {
     // Validate that at least one filter is specified first
     if !args.all && !args.unused && args.older_than.is_none() {
         return Err(common_repo::suggestions::cache_clean_no_filter());
@@ -158,8 +281,8 @@ fn execute_clean(cache_root: Option<PathBuf>, args: CleanArgs) -> Result<()> {
 
     // Check if cache directory exists
     if !cache_root.exists() {
-        println!("Cache directory does not exist: {}", cache_root.display());
-        println!("No cached repositories to clean.");
+        output::status(Message::CacheMissing { path: &cache_root });
+        output::status(Message::NothingToClean);
         return Ok(());
     }
 
@@ -167,7 +290,7 @@ fn execute_clean(cache_root: Option<PathBuf>, args: CleanArgs) -> Result<()> {
     let entries = scan_cache_directory(&cache_root)?;
 
     if entries.is_empty() {
-        println!("No cached repositories found in: {}", cache_root.display());
+        output::status(Message::CacheEmpty { path: &cache_root });
         return Ok(());
     }
 
@@ -175,31 +298,29 @@ fn execute_clean(cache_root: Option<PathBuf>, args: CleanArgs) -> Result<()> {
     let entries_to_delete = filter_entries_for_cleanup(&entries, &args)?;
 
     if entries_to_delete.is_empty() {
-        println!("No cache entries match the specified criteria.");
+        output::status(Message::NoMatchingCacheEntries);
         return Ok(());
     }
 
     // Display what will be deleted
-    println!("Cache entries to be deleted:\n");
+    output::status(Message::CacheCleanHeader);
     let total_size: u64 = entries_to_delete.iter().map(|e| e.size).sum();
     for entry in &entries_to_delete {
         let path_display = entry.path.as_deref().unwrap_or("(none)");
-        println!(
-            "  {} {} {} ({})",
-            &entry.hash[..entry.hash.len().min(16)],
-            &entry.ref_name[..entry.ref_name.len().min(20)],
-            path_display,
-            format_size(entry.size)
-        );
+        output::status(Message::CacheCleanEntry {
+            hash: &entry.hash[..entry.hash.len().min(16)],
+            ref_name: &entry.ref_name[..entry.ref_name.len().min(20)],
+            path: path_display,
+            size: &format_size(entry.size),
+        });
     }
-    println!(
-        "\nTotal: {} entries ({})",
-        entries_to_delete.len(),
-        format_size(total_size)
-    );
+    output::status(Message::CacheCleanTotal {
+        count: entries_to_delete.len(),
+        size: &format_size(total_size),
+    });
 
     if args.dry_run {
-        println!("\n🔎 Dry run mode - no changes were made.");
+        output::status(Message::CacheDryRun);
         return Ok(());
     }
 
@@ -212,42 +333,301 @@ fn execute_clean(cache_root: Option<PathBuf>, args: CleanArgs) -> Result<()> {
         let input = input.trim().to_lowercase();
 
         if input != "y" && input != "yes" {
-            println!("Clean cancelled.");
+            output::status(Message::CleanCancelled);
             return Ok(());
         }
     }
 
     // Delete directories
-    println!("\n🗑️  Deleting cache entries...");
+    output::status(Message::DeletingCacheEntries);
     let mut deleted_count = 0;
     let mut failed_count = 0;
 
     for entry in &entries_to_delete {
-        match fs::remove_dir_all(&entry.dir_path) {
+        // Wait for runs that are reading or writing the entry.
+        let removed = EntryLock::acquire(&entry.dir_path).and_then(|_lock| {
+            fs::remove_dir_all(&entry.dir_path).map_err(common_repo::error::Error::from)
+        });
+        match removed {
             Ok(_) => {
                 deleted_count += 1;
                 if args.yes || deleted_count <= 3 {
                     // Show first few deletions, or all if --yes
-                    println!("  ✅ Deleted: {}", entry.dir_path.display());
+                    output::status(Message::CacheEntryDeleted {
+                        path: &entry.dir_path,
+                    });
                 }
             }
             Err(e) => {
                 failed_count += 1;
-                eprintln!("  ❌ Failed to delete {}: {}", entry.dir_path.display(), e);
+                output::error(Message::CacheEntryDeleteFailed {
+                    path: &entry.dir_path,
+                    error: &e.to_string(),
+                });
             }
         }
     }
 
     if deleted_count > 0 {
-        println!("\n✅ Successfully deleted {} cache entries.", deleted_count);
+        output::status(Message::CacheEntriesDeleted {
+            count: deleted_count,
+        });
     }
     if failed_count > 0 {
-        eprintln!("\n⚠️  Failed to delete {} cache entries.", failed_count);
+        output::error(Message::CacheEntriesDeleteFailed {
+            count: failed_count,
+        });
+    }
+
+    Ok(())
+}
+
+/// Execute the `cache warm` command.
+///
+/// Runs discovery for each configuration, which fetches every repository
+/// it inherits from into the cache, and stops there.
+fn execute_warm(explicit_cache_root: Option<PathBuf>, args: WarmArgs) -> Result<()> {
+    for config_path in &args.configs {
+        let config_path = &common_repo::defaults::resolve_config_path(config_path);
+        if !config_path.exists() {
+            return Err(common_repo::suggestions::config_not_found(config_path));
+        }
+        let schema = common_repo::config::from_file(config_path)?;
+        let cache_root =
+            common_repo::defaults::resolve_cache_root(explicit_cache_root.clone(), config_path);
+        let repo_manager = RepositoryManager::new(cache_root);
+        let repo_cache = RepoCache::new();
+        // Local paths in a configuration are relative to its directory.
+        let project_dir = match config_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => std::env::current_dir()?,
+        };
+        let ctx = RunContext::new(&repo_manager, &repo_cache, &project_dir);
+
+        let (tree, diagnostics) = diagnostics::collect(|| prefetch(&schema, &ctx));
+        for diagnostic in &diagnostics {
+            output::warning(Message::PipelineWarning {
+                message: &diagnostic.to_string(),
+            });
+        }
+        let tree = tree?;
+
+        output::status(Message::CacheWarmed {
+            count: remote_repos(&tree.root).len(),
+            config: config_path,
+        });
+    }
+    Ok(())
+}
+
+/// Execute the `cache export` command.
+fn execute_export(cache_root: PathBuf, args: ExportArgs) -> Result<()> {
+    let repos = if args.repos.is_empty() {
+        config_repos(&cache_root, &args.config)?
+    } else {
+        args.repos
+            .iter()
+            .map(|spec| parse_repo_spec(spec))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let mut entries = Vec::with_capacity(repos.len());
+    let mut locks = Vec::with_capacity(repos.len());
+    for (url, ref_) in repos {
+        let entry = url_to_cache_path(&cache_root, &url, &ref_);
+        // Held until the archive is written, so no run replaces the entry
+        // while it is read.
+        locks.push(EntryLock::acquire(&entry)?);
+        if !entry.is_dir() {
+            anyhow::bail!(
+                "{}@{} is not cached; export by configuration with --config to fetch it first",
+                url,
+                ref_
+            );
+        }
+        let dir = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("Invalid cache entry path")?
+            .to_string();
+        entries.push(ArchiveEntry { url, ref_, dir });
+    }
+
+    let work = ScratchDir::new(&cache_root, "export")?;
+    let index = ArchiveIndex {
+        version: ARCHIVE_VERSION,
+        entries,
+    };
+    fs::write(
+        work.path.join(ARCHIVE_INDEX),
+        serde_json::to_vec_pretty(&index)?,
+    )?;
+
+    let archive = std::path::absolute(&args.archive)?;
+    let mut tar_args = vec![
+        "-cf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        work.path.as_os_str(),
+        ARCHIVE_INDEX.as_ref(),
+        "-C".as_ref(),
+        cache_root.as_os_str(),
+    ];
+    tar_args.extend(index.entries.iter().map(|e| OsStr::new(&e.dir)));
+    run_tar(&tar_args)?;
+    drop(locks);
+
+    output::status(Message::CacheExported {
+        count: index.entries.len(),
+        path: &args.archive,
+    });
+    Ok(())
+}
+
+/// Execute the `cache import` command.
+fn execute_import(cache_root: PathBuf, args: ImportArgs) -> Result<()> {
+    let archive = std::path::absolute(&args.archive)?;
+    let work = ScratchDir::new(&cache_root, "import")?;
+    run_tar(&[
+        "-xf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        work.path.as_os_str(),
+    ])?;
+
+    let index: ArchiveIndex = serde_json::from_slice(
+        &fs::read(work.path.join(ARCHIVE_INDEX))
+            .with_context(|| format!("{} is not a cache archive", args.archive.display()))?,
+    )
+    .with_context(|| format!("Invalid index in {}", args.archive.display()))?;
+    if index.version != ARCHIVE_VERSION {
+        anyhow::bail!(
+            "Unsupported cache archive version {} in {}",
+            index.version,
+            args.archive.display()
+        );
+    }
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    for entry in &index.entries {
+        let mut components = Path::new(&entry.dir).components();
+        let staged = match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(dir)), None) => work.path.join(dir),
+            _ => anyhow::bail!("Invalid entry directory '{}' in archive", entry.dir),
+        };
+        if !staged.is_dir() {
+            anyhow::bail!("Entry directory '{}' is missing from archive", entry.dir);
+        }
+
+        let target = url_to_cache_path(&cache_root, &entry.url, &entry.ref_);
+        let _lock = EntryLock::acquire(&target)?;
+        if target.exists() && !args.force {
+            output::status(Message::CacheEntryAlreadyCached {
+                url: &entry.url,
+                ref_: &entry.ref_,
+            });
+            skipped += 1;
+            continue;
+        }
+        commit_staged(&staged, &target)?;
+        imported += 1;
+    }
+
+    output::status(Message::CacheImported {
+        count: imported,
+        skipped,
+    });
+    Ok(())
+}
+
+/// The repositories the configuration at `config_path` inherits from,
+/// fetched into the cache.
+fn config_repos(cache_root: &Path, config_path: &Path) -> Result<Vec<(String, String)>> {
+    let config_path = &common_repo::defaults::resolve_config_path(config_path);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
     }
+    let schema = common_repo::config::from_file(config_path)?;
+    let repo_manager = RepositoryManager::new(cache_root.to_path_buf());
+    let tree = discover_repos(&schema, &repo_manager)?;
 
+    let repos = remote_repos(&tree.root);
+    for (url, ref_) in &repos {
+        repo_manager.fetch_repository(url, ref_)?;
+    }
+    Ok(repos)
+}
+
+/// The distinct remote repositories in the tree below `root`, sorted.
+fn remote_repos(root: &RepoNode) -> Vec<(String, String)> {
+    let mut repos = Vec::new();
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        if node.url != "local" && !node.is_local() {
+            let repo = (node.url.clone(), node.ref_.clone());
+            if !repos.contains(&repo) {
+                repos.push(repo);
+            }
+        }
+        pending.extend(&node.children);
+    }
+    repos.sort();
+    repos
+}
+
+/// Split `URL@REF` at its last `@`.
+fn parse_repo_spec(spec: &str) -> Result<(String, String)> {
+    match spec.rsplit_once('@') {
+        Some((url, ref_)) if !url.is_empty() && !ref_.is_empty() && !url.ends_with(':') => {
+            Ok((url.to_string(), ref_.to_string()))
+        }
+        _ => anyhow::bail!("Expected URL@REF, got '{}'", spec),
+    }
+}
+
+/// Run the system `tar` with `args`.
+fn run_tar(args: &[&OsStr]) -> Result<()> {
+    let output = std::process::Command::new("tar")
+        .args(args)
+        .output()
+        .context("Failed to run tar; ensure it is installed")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
     Ok(())
 }
 
+/// A working directory below the cache root, removed when dropped.
+///
+/// It lives in the cache root's staging area so imported entries can be
+/// renamed into place.
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    fn new(cache_root: &Path, purpose: &str) -> Result<Self> {
+        let path = cache_root
+            .join(".staging")
+            .join(format!("{}-{}", purpose, std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
 /// Filter cache entries based on clean arguments
 fn filter_entries_for_cleanup(entries: &[CacheEntry], args: &CleanArgs) -> Result<Vec<CacheEntry>> {
     let mut filtered = Vec::new();
@@ -450,7 +830,9 @@ fn parse_cache_directory_name(dir_name: &str, dir_path: &Path) -> Option<CacheEn
 }
 
 /// Calculate directory size, file count, and last modified time
-fn calculate_directory_info(dir_path: &Path) -> (u64, usize, Option<std::time::SystemTime>) {
+pub(crate) fn calculate_directory_info(
+    dir_path: &Path,
+) -> (u64, usize, Option<std::time::SystemTime>) {
     let mut total_size = 0u64;
     let mut file_count = 0usize;
     let mut latest_mtime = None;
@@ -533,12 +915,13 @@ fn display_detailed(entries: &[CacheEntry]) {
 
 /// Display cache entries in JSON format
 fn display_json(entries: &[CacheEntry]) -> Result<()> {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
-    let json_entries: Vec<HashMap<&str, serde_json::Value>> = entries
+    // BTreeMap keeps the keys sorted so the output is identical across runs.
+    let json_entries: Vec<BTreeMap<&str, serde_json::Value>> = entries
         .iter()
         .map(|e| {
-            let mut map = HashMap::new();
+            let mut map = BTreeMap::new();
             map.insert("hash", serde_json::Value::String(e.hash.clone()));
             map.insert("ref", serde_json::Value::String(e.ref_name.clone()));
             map.insert(
@@ -594,6 +977,84 @@ fn format_size(bytes: u64) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_repo_spec_splits_at_last_at() {
+        assert_eq!(
+            parse_repo_spec("git@github.com:org/repo.git@v1.0.0").unwrap(),
+            (
+                "git@github.com:org/repo.git".to_string(),
+                "v1.0.0".to_string()
+            )
+        );
+        assert!(parse_repo_spec("https://github.com/org/repo").is_err());
+        assert!(parse_repo_spec("https://github.com/org/repo@").is_err());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = tempfile::TempDir::new().unwrap();
+        let target = tempfile::TempDir::new().unwrap();
+        let archive = source.path().join("cache.tar");
+        let url = "https://example.com/org/repo.git";
+
+        let entry = url_to_cache_path(source.path(), url, "v1");
+        fs::create_dir_all(entry.join("src")).unwrap();
+        fs::write(entry.join("src/lib.rs"), "// upstream").unwrap();
+
+        execute_export(
+            source.path().to_path_buf(),
+            ExportArgs {
+                archive: archive.clone(),
+                repos: vec![format!("{}@v1", url)],
+                config: PathBuf::from(DEFAULT_CONFIG_FILENAME),
+            },
+        )
+        .unwrap();
+        let import = || {
+            execute_import(
+                target.path().to_path_buf(),
+                ImportArgs {
+                    archive: archive.clone(),
+                    force: false,
+                },
+            )
+        };
+        import().unwrap();
+
+        let imported = url_to_cache_path(target.path(), url, "v1");
+        assert_eq!(
+            fs::read_to_string(imported.join("src/lib.rs")).unwrap(),
+            "// upstream"
+        );
+
+        // Entries already cached are kept unless --force is given.
+        fs::write(imported.join("src/lib.rs"), "// local").unwrap();
+        import().unwrap();
+        assert_eq!(
+            fs::read_to_string(imported.join("src/lib.rs")).unwrap(),
+            "// local"
+        );
+        assert!(!target
+            .path()
+            .join(format!(".staging/import-{}", std::process::id()))
+            .exists());
+    }
+
+    #[test]
+    fn test_export_fails_for_uncached_repo() {
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let err = execute_export(
+            cache_root.path().to_path_buf(),
+            ExportArgs {
+                archive: cache_root.path().join("cache.tar"),
+                repos: vec!["https://example.com/missing.git@main".to_string()],
+                config: PathBuf::from(DEFAULT_CONFIG_FILENAME),
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("is not cached"), "{err}");
+    }
+
     #[test]
     fn test_parse_cache_directory_name_simple() {
         let dir_name = "a1b2c3d4e5f6-main";
@@ -15,38 +15,136 @@
 //!   queries the remote Git repositories to check for newer versions (tags)
 //!   that are compatible with semantic versioning. It then displays a summary
 //!   of available updates, categorizing them as either compatible or containing
-//!   breaking changes.
+//!   breaking changes. In GitHub Actions each update is also reported as a
+//!   workflow annotation on the line of the config that names the repository,
+//!   and listed in the job summary (see [`common_repo::github`]). The
+//!   upstream configs are read as well, and any deprecation notices they
+//!   declare are reported as warnings.
+//!
+//! - **Template Variables**: With `--vars`, the command lists each template
+//!   variable with the repositories that define it, then the variables that
+//!   templates use but nothing defines, which would fail `apply`, and the
+//!   variables defined but never used, often a misspelling of one that is
+//!   (see [`common_repo::reporting::vars`]).
+//!
+//! - **Template Declarations**: With `--templates`, run in an upstream
+//!   repository, the `template` operations are checked against the files
+//!   the upstream ships, as `verify-upstream` checks them: files declared
+//!   under `files:` must exist and match their placeholder checksums, and
+//!   files with placeholders must be declared (see
+//!   [`super::verify_upstream`]).
+//!
+//! - **Locked Files**: With `--locked`, the configuration is run in memory
+//!   to check that no file an upstream marked `locked: true` was edited
+//!   since the last apply or is replaced by an override. `apply` fails the
+//!   same way; this reports it without writing anything.
+//!
+//! - **Assertions**: With `--assertions`, the configuration is run in memory
+//!   to check the output against the `assert` operations of the
+//!   configuration and its upstreams, which `apply` also enforces.
+//!
+//! - **Unknown Operations**: With `--strict`, the upstream configs are read
+//!   as well, and the command fails on any operation this version does not
+//!   know, as `apply --strict` does.
+//!
+//! - **Remote Consumer**: With `--repo <url> --ref <ref>`, the configuration
+//!   of a consumer repository fetched into the cache is checked instead of a
+//!   local one, for audit jobs that look after many repositories.
 //!
 //! This command is a safe, read-only operation that does not modify any files.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use common_repo::cache::RepoCache;
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics;
+use common_repo::github;
+use common_repo::messages::Message;
+use common_repo::outcome::{self, Failure, Outcome};
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator;
+use common_repo::phases::prefetch;
+use common_repo::reporting::vars;
 use common_repo::repository::RepositoryManager;
 use common_repo::version;
 
+use super::completions;
+use super::verify_upstream::{self, Severity};
+
 /// Check for repository updates and configuration validity
 #[derive(Args, Debug)]
 pub struct CheckArgs {
     /// Path to the .common-repo.yaml configuration file to check.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    ///
+    /// With `--repo`, the path is relative to the root of that repository.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// If set, the command will check for newer versions of the inherited
     /// repositories.
     #[arg(long)]
     pub updates: bool,
+
+    /// If set, the command will report template variables that are used but
+    /// never defined, or defined but never used.
+    #[arg(long)]
+    pub vars: bool,
+
+    /// If set, the command will check the configuration's template
+    /// declarations against the files of the repository it is in.
+    #[arg(long, conflicts_with_all = ["vars", "updates"])]
+    pub templates: bool,
+
+    /// If set, the command will check that no file an upstream marked
+    /// `locked: true` was modified locally.
+    #[arg(long, conflicts_with_all = ["vars", "updates", "templates"])]
+    pub locked: bool,
+
+    /// If set, the command will check the output against the `assert`
+    /// operations of the configuration and its upstreams.
+    #[arg(long, conflicts_with_all = ["vars", "updates", "templates", "locked"])]
+    pub assertions: bool,
+
+    /// Fail if the configuration or one of its upstreams has an operation
+    /// this version does not know, as `apply --strict` would.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Check the consumer repository at this URL instead of a local
+    /// configuration. Requires `--ref`.
+    ///
+    /// The repository is fetched into the cache and read from there.
+    #[arg(long, value_name = "URL", requires = "ref_")]
+    pub repo: Option<String>,
+
+    /// The git reference of the `--repo` repository to check.
+    #[arg(long = "ref", value_name = "REF", requires = "repo")]
+    pub ref_: Option<String>,
 }
 
 /// Execute the `check` command.
@@ -67,33 +165,288 @@ fn count_repos(schema: &config::Schema) -> usize {
     count
 }
 
-pub fn execute(args: CheckArgs) -> Result<()> {
-    // Load configuration
-    let config_path = &args.config;
-    println!("Loading configuration from: {}", config_path.display());
-
-    let schema = config::from_file(config_path).map_err(|e| {
-        anyhow::anyhow!(
-            "Failed to load config from {}: {}",
-            config_path.display(),
-            e
+/// The 1-based line of `config_path` that first mentions `url`.
+fn line_of(config_path: &Path, url: &str) -> Option<usize> {
+    std::fs::read_to_string(config_path)
+        .ok()?
+        .lines()
+        .position(|line| line.contains(url))
+        .map(|index| index + 1)
+}
+
+/// Annotate each available update, on its line of `config_path` if given,
+/// and list them in the GitHub Actions job summary.
+fn report_updates_to_github(config_path: Option<&Path>, updates: &[version::UpdateInfo]) {
+    let mut rows = Vec::new();
+    for update in updates {
+        let Some(latest) = update.latest_version.as_deref() else {
+            continue;
+        };
+        if !(update.breaking_changes || update.compatible_updates) {
+            continue;
+        }
+        let level = if update.breaking_changes {
+            github::Level::Warning
+        } else {
+            github::Level::Notice
+        };
+        let mut annotation = github::Annotation::new(
+            level,
+            Message::UpdateAnnotation {
+                url: &update.url,
+                current: &update.current_ref,
+                latest,
+            },
         )
-    })?;
+        .title(Message::UpdateAnnotationTitle {
+            breaking: update.breaking_changes,
+        });
+        if let Some(config_path) = config_path {
+            annotation = annotation.file(config_path);
+            if let Some(line) = line_of(config_path, &update.url) {
+                annotation = annotation.line(line);
+            }
+        }
+        github::annotate(annotation);
+        rows.push(vec![
+            update.url.clone(),
+            update.current_ref.clone(),
+            latest.to_string(),
+            if update.breaking_changes {
+                "breaking"
+            } else {
+                "compatible"
+            }
+            .to_string(),
+        ]);
+    }
 
+    let mut markdown = String::from("### common-repo check --updates\n\n");
+    if rows.is_empty() {
+        markdown.push_str("All repositories are up to date.\n");
+    } else {
+        markdown.push_str(&github::summary_table(
+            &["Repository", "Current", "Latest", "Update"],
+            &rows,
+        ));
+    }
+    github::append_summary(&markdown);
+}
+
+/// Read the upstream configs of `schema` and report the deprecation notices
+/// and other warnings found on the way.
+/// The directory local paths in the configuration are relative to.
+fn project_dir(config_path: &Path) -> Result<PathBuf> {
+    Ok(match config_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    })
+}
+
+/// Report where each template variable is defined and the variables that
+/// are undefined or unused, failing if any is undefined.
+fn report_template_vars(
+    schema: &config::Schema,
+    repo_manager: &RepositoryManager,
+    config_path: &Path,
+) -> Result<()> {
+    let repo_cache = RepoCache::new();
+    let project_dir = project_dir(config_path)?;
+    let ctx = RunContext::new(repo_manager, &repo_cache, &project_dir);
+    let audit = vars::audit(schema, &ctx)?;
+
+    let sources_of = |name: &str| {
+        let sources: Vec<&str> = audit.defined[name].iter().map(String::as_str).collect();
+        sources.join(", ")
+    };
+
+    output::status(Message::TemplateVarsHeader);
+    for name in audit.defined.keys() {
+        output::status(Message::TemplateVarDefined {
+            name,
+            sources: &sources_of(name),
+        });
+    }
+
+    let undefined = audit.undefined();
+    if !undefined.is_empty() {
+        output::status(Message::UndefinedVarsHeader);
+        for name in &undefined {
+            let paths: Vec<String> = audit.used[*name]
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            output::status(Message::TemplateVarUsedIn {
+                name,
+                paths: &paths.join(", "),
+            });
+        }
+    }
+
+    let unused = audit.unused();
+    if !unused.is_empty() {
+        output::status(Message::UnusedVarsHeader);
+        for name in &unused {
+            output::status(Message::TemplateVarDefined {
+                name,
+                sources: &sources_of(name),
+            });
+        }
+    }
+
+    if undefined.is_empty() && unused.is_empty() {
+        output::status(Message::TemplateVarsOk);
+    }
+    if !undefined.is_empty() {
+        return Err(Failure::new(
+            Outcome::ConfigError,
+            format!(
+                "{} template variable(s) are used but never defined",
+                undefined.len()
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Run `schema` in memory, which fails when a file an upstream locked was
+/// modified locally or an assertion does not hold, reporting `ok` when it
+/// succeeds.
+fn check_composed_output(
+    schema: &config::Schema,
+    repo_manager: &RepositoryManager,
+    config_path: &Path,
+    ok: Message,
+) -> Result<()> {
+    let repo_cache = RepoCache::new();
+    let project_dir = project_dir(config_path)?;
+    let ctx = RunContext::new(repo_manager, &repo_cache, &project_dir)
+        .scope(config::path_scope(&std::fs::read_to_string(config_path)?)?);
+    let mut warnings = diagnostics::Diagnostics::new();
+    orchestrator::execute_pull_with_context(schema, &ctx, &mut warnings)?;
+    for warning in &warnings {
+        output::warning(Message::PipelineWarning {
+            message: &warning.to_string(),
+        });
+    }
+    outcome::record_warnings(warnings.len());
+    output::status(ok);
+    Ok(())
+}
+
+/// Fail if `schema` or one of its upstreams has an operation this version
+/// does not know.
+fn check_unknown_operations(
+    schema: &config::Schema,
+    repo_manager: &RepositoryManager,
+    config_path: &Path,
+) -> Result<()> {
+    let repo_cache = RepoCache::new();
+    let project_dir = project_dir(config_path)?;
+    let ctx = RunContext::new(repo_manager, &repo_cache, &project_dir).strict(true);
+    common_repo::phases::check_unknown_operations(schema, &ctx)?;
+    Ok(())
+}
+
+/// Check the template declarations of `schema` against the files of the
+/// repository at the config's directory, failing on any error.
+fn check_template_declarations(schema: &config::Schema, config_path: &Path) -> Result<()> {
+    let tree = verify_upstream::load_upstream_tree(&project_dir(config_path)?)?;
+    let findings = verify_upstream::template_findings(schema, &tree);
+    for finding in &findings {
+        println!("{}: {}", config_path.display(), finding);
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(Failure::new(
+            Outcome::ConfigError,
+            format!("{} template declaration error(s)", errors),
+        )
+        .into());
+    }
+    if findings.is_empty() {
+        output::status(Message::TemplateDeclarationsOk { files: tree.len() });
+    }
+    Ok(())
+}
+
+fn report_upstream_warnings(
+    schema: &config::Schema,
+    repo_manager: &RepositoryManager,
+    config_path: &Path,
+) -> Result<()> {
+    let repo_cache = RepoCache::new();
+    let project_dir = project_dir(config_path)?;
+    let ctx = RunContext::new(repo_manager, &repo_cache, &project_dir);
+    let (tree, warnings) = diagnostics::collect(|| prefetch(schema, &ctx));
+    tree?;
+    github::annotate_diagnostics(&warnings);
+    for warning in &warnings {
+        output::warning(Message::PipelineWarning {
+            message: &warning.to_string(),
+        });
+    }
+    outcome::record_warnings(warnings.len());
+    Ok(())
+}
+
+pub fn execute(args: CheckArgs) -> Result<()> {
     // Initialize repository manager
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let local_config = common_repo::defaults::resolve_config_path(&args.config);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, &local_config);
 
     let repo_manager = RepositoryManager::new(cache_root);
 
+    let config_path = match (&args.repo, &args.ref_) {
+        (Some(url), Some(ref_)) => repo_manager
+            .fetch_repository_dir(url, ref_)
+            .with_context(|| format!("Failed to fetch {}@{}", url, ref_))
+            .map(|dir| common_repo::defaults::resolve_config_path(&dir.join(&args.config)))?,
+        _ => local_config,
+    };
+    let config_path = &config_path;
+
+    // Load configuration
+    output::status(Message::LoadingConfig { path: config_path });
+
+    let schema = config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+
+    if args.strict {
+        check_unknown_operations(&schema, &repo_manager, config_path)?;
+    }
+
+    if args.templates {
+        return check_template_declarations(&schema, config_path);
+    }
+
+    if args.locked {
+        return check_composed_output(&schema, &repo_manager, config_path, Message::LockedFilesOk);
+    }
+
+    if args.assertions {
+        return check_composed_output(&schema, &repo_manager, config_path, Message::AssertionsOk);
+    }
+
+    if args.vars {
+        return report_template_vars(&schema, &repo_manager, config_path);
+    }
+
     if args.updates {
         // Check for updates
-        println!("Checking for repository updates...");
+        output::status(Message::CheckingUpdates);
+        report_upstream_warnings(&schema, &repo_manager, config_path)?;
         let updates = version::check_updates(&schema, &repo_manager)?;
+        // A remote consumer's config has no place in this job's checkout.
+        let annotate_path = args.repo.is_none().then_some(config_path.as_path());
+        report_updates_to_github(annotate_path, &updates);
 
         if updates.is_empty() {
-            println!("✅ No repositories found that can be checked for updates.");
+            output::status(Message::NoCheckableRepos);
             return Ok(());
         }
 
@@ -110,61 +463,60 @@ pub fn execute(args: CheckArgs) -> Result<()> {
         }
 
         if !has_updates {
-            println!("✅ All repositories are up to date!");
+            output::status(Message::AllUpToDate);
             return Ok(());
         }
 
         // Display update information
-        println!("\n📦 Repository Update Summary:");
-        println!(
-            "{} repositories checked, {} have available updates\n",
-            updates.len(),
-            updates
+        output::status(Message::UpdateSummaryHeader);
+        output::status(Message::UpdateSummaryCounts {
+            checked: updates.len(),
+            outdated: updates
                 .iter()
                 .filter(|u| u.breaking_changes || u.compatible_updates)
-                .count()
-        );
+                .count(),
+        });
 
         for update in updates {
             if update.breaking_changes || update.compatible_updates {
-                println!("🔄 {} (current: {})", update.url, update.current_ref);
+                output::status(Message::RepoWithUpdate {
+                    url: &update.url,
+                    current: &update.current_ref,
+                });
 
                 if let Some(latest) = &update.latest_version {
-                    println!("   Latest: {}", latest);
+                    output::status(Message::LatestVersion { version: latest });
 
                     if update.breaking_changes {
-                        println!("   ⚠️  BREAKING CHANGES available (major version update)");
+                        output::status(Message::BreakingChangesAvailable);
                     } else if update.compatible_updates {
-                        println!("   ✅ Compatible updates available");
+                        output::status(Message::CompatibleUpdatesAvailable);
                     }
                 }
 
-                println!();
+                output::blank_line();
             }
         }
 
         if has_breaking {
-            println!(
-                "⚠️  Some repositories have breaking changes. Review carefully before updating."
-            );
-            println!(
-                "   Use 'common-repo update' to update repository refs in your configuration."
-            );
+            output::status(Message::BreakingChangesAdvice);
         }
     } else {
         // Basic configuration check
-        println!("✅ Configuration loaded successfully");
-        println!("   Operations: {}", schema.len());
+        output::status(Message::ConfigLoaded);
+        output::status(Message::OperationCount {
+            count: schema.len(),
+        });
 
         // Count repositories
         let repo_count = count_repos(&schema);
-        println!("   Repositories: {}", repo_count);
+        output::status(Message::RepositoryCount { count: repo_count });
 
         // Count other operations
         let other_ops = schema.len() - repo_count;
-        println!("   Other operations: {}", other_ops);
+        output::status(Message::OtherOperationCount { count: other_ops });
 
-        println!("\n💡 Tip: Use --updates to check for repository version updates");
+        output::status(Message::UpdatesTip);
     }
 
     Ok(())
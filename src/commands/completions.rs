@@ -26,14 +26,44 @@
 //! Note: Completions are generated for the `common-repo` command name. If you
 //! use the `cr` alias, you may need to create a separate completion file or
 //! symlink.
+//!
+//! ## Dynamic Completions
+//!
+//! With `--dynamic`, the script instead asks `common-repo` for candidates
+//! each time Tab is pressed, so values that depend on the project complete
+//! too:
+//!
+//! - `apply --pipeline`: the pipelines in `.common-repo/`
+//! - `update --filter`: the upstreams of `.common-repo.yaml`
+//! - `cache export`: the `URL@REF` pairs of those upstreams that are cached
+//! - `--config`: YAML files
+//!
+//! The script calls back into the binary, so it should be sourced when the
+//! shell starts rather than saved, to stay in step with upgrades:
+//!
+//! ```bash
+//! echo 'source <(common-repo completions bash --dynamic)' >> ~/.bashrc
+//! ```
 
 use anyhow::Result;
 use clap::{Args, CommandFactory, ValueEnum};
-use clap_complete::{generate, Shell};
+use clap_complete::env::{Bash, Elvish, EnvCompleter, Fish, Powershell, Zsh};
+use clap_complete::{generate, ArgValueCompleter, CompletionCandidate, PathCompleter, Shell};
 use std::io;
+use std::path::{Path, PathBuf};
+
+use common_repo::config;
+use common_repo::defaults::{self, DEFAULT_CONFIG_FILENAME};
+use common_repo::git::url_to_cache_path;
+use common_repo::reporting::history;
+use common_repo::version;
 
 use crate::cli::Cli;
 
+/// The environment variable that makes the binary answer a completion
+/// request instead of running a command.
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
 /// Shell types for completion generation
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum CompletionShell {
@@ -50,6 +80,19 @@ pub enum CompletionShell {
     Elvish,
 }
 
+impl CompletionShell {
+    /// The shell integration for dynamic completions.
+    fn env_completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            CompletionShell::Bash => &Bash,
+            CompletionShell::Zsh => &Zsh,
+            CompletionShell::Fish => &Fish,
+            CompletionShell::PowerShell => &Powershell,
+            CompletionShell::Elvish => &Elvish,
+        }
+    }
+}
+
 impl From<CompletionShell> for Shell {
     fn from(shell: CompletionShell) -> Self {
         match shell {
@@ -68,6 +111,11 @@ pub struct CompletionsArgs {
     /// The shell to generate completions for
     #[arg(value_enum)]
     pub shell: CompletionShell,
+
+    /// Generate a script that asks `common-repo` for candidates, completing
+    /// pipeline names, upstream URLs, cached `URL@REF` pairs and config files
+    #[arg(long)]
+    pub dynamic: bool,
 }
 
 /// Execute the `completions` command.
@@ -77,7 +125,142 @@ pub struct CompletionsArgs {
 /// shell configuration.
 pub fn execute(args: CompletionsArgs) -> Result<()> {
     let mut cmd = Cli::command();
+    if args.dynamic {
+        let completer = std::env::current_exe()
+            .map(|exe| exe.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "common-repo".to_string());
+        args.shell.env_completer().write_registration(
+            COMPLETE_ENV,
+            cmd.get_name(),
+            "common-repo",
+            &completer,
+            &mut io::stdout(),
+        )?;
+        return Ok(());
+    }
     let shell: Shell = args.shell.into();
     generate(shell, &mut cmd, "common-repo", &mut io::stdout());
     Ok(())
 }
+
+/// Completes `--config` values: directories, and YAML files in them.
+pub fn config_paths() -> ArgValueCompleter {
+    ArgValueCompleter::new(PathCompleter::any().filter(|path| {
+        path.is_dir()
+            || path
+                .extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+    }))
+}
+
+/// The pipelines configured in the current directory.
+pub fn pipelines() -> Vec<CompletionCandidate> {
+    defaults::pipeline_names(Path::new("."))
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The upstreams of the configuration in the current directory, as
+/// `update --filter` matches them.
+pub fn upstream_filters() -> Vec<CompletionCandidate> {
+    config_upstreams(&defaults::resolve_config_path(Path::new(
+        DEFAULT_CONFIG_FILENAME,
+    )))
+    .iter()
+    .filter(|repo| !repo.is_local())
+    .map(|repo| {
+        CompletionCandidate::new(version::build_match_target(repo))
+            .help(repo.r#ref.clone().map(Into::into))
+    })
+    .collect()
+}
+
+/// The `URL@REF` pairs in the cache that the configuration in the current
+/// directory inherits, or that its history records.
+pub fn cached_repos() -> Vec<CompletionCandidate> {
+    let config_path = defaults::resolve_config_path(Path::new(DEFAULT_CONFIG_FILENAME));
+    let cache_root = defaults::resolve_cache_root(
+        std::env::var_os("COMMON_REPO_CACHE_DIR").map(PathBuf::from),
+        &config_path,
+    );
+    cached_repo_specs(&cache_root, &config_path, Path::new("."))
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// The `URL@REF` pairs under `cache_root` that the configuration at
+/// `config_path` inherits, or that the history of `dir` records, sorted.
+fn cached_repo_specs(cache_root: &Path, config_path: &Path, dir: &Path) -> Vec<String> {
+    let configured = config_upstreams(config_path)
+        .into_iter()
+        .filter_map(|repo| Some((repo.url, repo.r#ref?)));
+    let applied = history::load(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|entry| entry.upstreams)
+        .map(|upstream| (upstream.url, upstream.ref_));
+    let mut specs: Vec<String> = configured
+        .chain(applied)
+        .filter(|(url, ref_)| url_to_cache_path(cache_root, url, ref_).is_dir())
+        .map(|(url, ref_)| format!("{}@{}", url, ref_))
+        .collect();
+    specs.sort();
+    specs.dedup();
+    specs
+}
+
+/// The repositories the configuration at `path` inherits from directly, or
+/// none when it cannot be read.
+fn config_upstreams(path: &Path) -> Vec<config::RepoOp> {
+    config::from_file(path)
+        .map(|schema| version::collect_inherited_repos(&schema))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cached_repo_specs() {
+        let temp = TempDir::new().unwrap();
+        let config_path = temp.path().join(DEFAULT_CONFIG_FILENAME);
+        std::fs::write(
+            &config_path,
+            "- repo:\n    url: https://github.com/acme/ci\n    ref: v2\n\
+             - repo:\n    url: https://github.com/acme/lint\n    ref: v1\n",
+        )
+        .unwrap();
+        let cache_root = temp.path().join("cache");
+        std::fs::create_dir_all(url_to_cache_path(
+            &cache_root,
+            "https://github.com/acme/ci",
+            "v2",
+        ))
+        .unwrap();
+        std::fs::create_dir_all(url_to_cache_path(
+            &cache_root,
+            "https://github.com/acme/ci",
+            "v1",
+        ))
+        .unwrap();
+        let mut entry = history::HistoryEntry::now(Some("ada".to_string()), "abc");
+        entry.upstreams.push(history::UpstreamCommit {
+            url: "https://github.com/acme/ci".to_string(),
+            ref_: "v1".to_string(),
+            commit: None,
+        });
+        history::append(temp.path(), &entry).unwrap();
+
+        assert_eq!(
+            cached_repo_specs(&cache_root, &config_path, temp.path()),
+            vec![
+                "https://github.com/acme/ci@v1".to_string(),
+                "https://github.com/acme/ci@v2".to_string(),
+            ]
+        );
+    }
+}
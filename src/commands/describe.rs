@@ -0,0 +1,358 @@
+//! # Describe Command Implementation
+//!
+//! This module implements the `describe` subcommand, which shows what an
+//! upstream repository offers to the configurations that inherit from it,
+//! so consumers can learn how to adopt a template without reading its
+//! source.
+//!
+//! ## Functionality
+//!
+//! The upstream is fetched (or read from the cache) and its
+//! `.common-repo.yaml` is parsed the way `apply` parses an inherited
+//! configuration. Nothing it inherits from is fetched. The report lists:
+//!
+//! - **Variables**: `template-vars` with their defaults, and the variables
+//!   the upstream requires consumers to set
+//! - **Operations**: each of the upstream's operations, in order
+//! - **Requirements**: the tools and files the upstream requires
+//! - **Docs**: the links from its `- docs:` entries
+//! - **Deprecations**: notices for the whole upstream or single operations
+//!
+//! Without `--ref`, the latest semantic version tag is described, falling
+//! back to `main` when there is none, as `add` does.
+//!
+//! ## Example
+//!
+//! ```bash
+//! common-repo describe common-repo/rust-cli
+//!
+//! # A specific ref, as JSON
+//! common-repo describe https://github.com/org/templates --ref v2.0.0 --json
+//! ```
+
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use common_repo::config::{Operation, Tool, Upstream};
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics;
+use common_repo::git;
+use common_repo::phases::fetch_upstream;
+use common_repo::repository::RepositoryManager;
+use common_repo::version;
+
+use super::add::{find_latest_version, normalize_repo_url};
+
+/// Describe what an upstream repository offers to consumers
+#[derive(Args, Debug)]
+pub struct DescribeArgs {
+    /// Repository URL to describe (e.g., https://github.com/org/repo or org/repo)
+    #[arg(value_name = "URI")]
+    pub uri: String,
+
+    /// Git reference to describe. Defaults to the latest semantic version
+    /// tag, or `main` when there is none.
+    #[arg(long = "ref", value_name = "REF")]
+    pub ref_: Option<String>,
+
+    /// Print the description as JSON.
+    #[arg(long)]
+    pub json: bool,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
+    /// `~/Library/Caches/common-repo` on macOS).
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+}
+
+/// What an upstream offers, as printed by `describe`.
+#[derive(Debug, Serialize)]
+struct Description {
+    url: String,
+    #[serde(rename = "ref")]
+    ref_: String,
+    variables: Vec<Variable>,
+    operations: Vec<OperationSummary>,
+    tools: Vec<Tool>,
+    files: Vec<String>,
+    docs: Vec<String>,
+    deprecations: Vec<DeprecationNotice>,
+}
+
+/// A template variable the upstream declares or requires.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Variable {
+    name: String,
+    /// The `template-vars` value, used when the consumer sets none.
+    default: Option<String>,
+    /// Whether the upstream requires consumers to set it.
+    required: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct OperationSummary {
+    kind: &'static str,
+    /// The patterns, paths or repository the operation works on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// Whether it is a merge deferred to consumers.
+    deferred: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DeprecationNotice {
+    /// The deprecated operation, or `None` for the whole upstream.
+    operation: Option<String>,
+    message: String,
+    replacement_url: Option<String>,
+}
+
+/// Execute the `describe` command.
+pub fn execute(args: DescribeArgs) -> Result<()> {
+    let url = normalize_repo_url(&args.uri);
+    let ref_ = match args.ref_ {
+        Some(ref_) => ref_,
+        None => default_ref(&url),
+    };
+    let cache_root = common_repo::defaults::resolve_cache_root(
+        args.cache_root,
+        &common_repo::defaults::resolve_config_path(Path::new(DEFAULT_CONFIG_FILENAME)),
+    );
+    let repo_manager = RepositoryManager::new(cache_root);
+
+    // The notices are part of the description, not warnings.
+    let (upstream, _) = diagnostics::collect(|| fetch_upstream(&url, &ref_, &repo_manager));
+    let description = describe(url, ref_, upstream?);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&description)?);
+    } else {
+        print!("{}", format_description(&description));
+    }
+    Ok(())
+}
+
+/// The latest semantic version tag of `url`, or `main`.
+fn default_ref(url: &str) -> String {
+    git::list_tags(url)
+        .ok()
+        .and_then(|tags| find_latest_version(&version::filter_semver_tags(&tags)))
+        .map(|(tag, _)| tag)
+        .unwrap_or_else(|| "main".to_string())
+}
+
+fn describe(url: String, ref_: String, upstream: Upstream) -> Description {
+    let requires = upstream.requires.unwrap_or_default();
+
+    let mut variables: BTreeMap<String, Variable> = BTreeMap::new();
+    let mut tools = requires.tools;
+    for op in &upstream.schema {
+        match op {
+            Operation::TemplateVars { template_vars } => {
+                for (name, value) in &template_vars.vars {
+                    variables.insert(
+                        name.clone(),
+                        Variable {
+                            name: name.clone(),
+                            default: Some(value.clone()),
+                            required: false,
+                        },
+                    );
+                }
+            }
+            Operation::Tools { tools: op } => tools.extend(op.tools.iter().cloned()),
+            _ => {}
+        }
+    }
+    for var in requires.vars {
+        let variable = variables.entry(var.name.clone()).or_insert(Variable {
+            name: var.name,
+            default: None,
+            required: false,
+        });
+        variable.required = true;
+        if var.default.is_some() {
+            variable.default = var.default;
+        }
+    }
+
+    Description {
+        url,
+        ref_,
+        variables: variables.into_values().collect(),
+        operations: upstream.schema.iter().map(summarize).collect(),
+        tools,
+        files: requires.files,
+        docs: upstream.docs,
+        deprecations: upstream
+            .deprecations
+            .into_iter()
+            .map(|d| DeprecationNotice {
+                operation: d.operation,
+                message: d.message,
+                replacement_url: d.replacement_url,
+            })
+            .collect(),
+    }
+}
+
+fn summarize(op: &Operation) -> OperationSummary {
+    let detail = match op {
+        Operation::Repo { repo } => Some(match repo.r#ref.as_deref() {
+            Some(ref_) => format!("{}@{}", repo.url, ref_),
+            None => repo.url.clone(),
+        }),
+        Operation::Include { include, .. } => Some(include.patterns.join(", ")),
+        Operation::Exclude { exclude } => Some(exclude.patterns.join(", ")),
+        Operation::Template { template } => Some(template.targets().join(", ")),
+        _ => match (op.merge_effective_source(), op.merge_effective_dest()) {
+            (Some(source), Some(dest)) if source != dest => Some(format!("{} -> {}", source, dest)),
+            (Some(path), _) | (None, Some(path)) => Some(path.to_string()),
+            (None, None) => None,
+        },
+    };
+    OperationSummary {
+        kind: op.kind(),
+        detail,
+        deferred: op.is_deferred(),
+    }
+}
+
+fn format_description(description: &Description) -> String {
+    let mut out = format!("Upstream: {} @ {}\n", description.url, description.ref_);
+
+    for notice in &description.deprecations {
+        let subject = match &notice.operation {
+            Some(operation) => format!("Deprecated operation {}", operation),
+            None => "Deprecated".to_string(),
+        };
+        out.push_str(&format!("{}: {}", subject, notice.message));
+        if let Some(url) = &notice.replacement_url {
+            out.push_str(&format!(" (replacement: {})", url));
+        }
+        out.push('\n');
+    }
+
+    if !description.variables.is_empty() {
+        out.push_str("\nVariables:\n");
+        for var in &description.variables {
+            out.push_str(&format!("  {}", var.name));
+            if let Some(default) = &var.default {
+                out.push_str(&format!(" = {}", default));
+            }
+            if var.required {
+                out.push_str(" (required)");
+            }
+            out.push('\n');
+        }
+    }
+
+    out.push_str(&format!("\nOperations: {}\n", description.operations.len()));
+    for op in &description.operations {
+        out.push_str(&format!("  {}", op.kind));
+        if let Some(detail) = &op.detail {
+            out.push_str(&format!(" {}", detail));
+        }
+        if op.deferred {
+            out.push_str(" (deferred)");
+        }
+        out.push('\n');
+    }
+
+    if !description.tools.is_empty() {
+        out.push_str("\nRequired tools:\n");
+        for tool in &description.tools {
+            out.push_str(&format!("  {} {}\n", tool.name, tool.version));
+        }
+    }
+    if !description.files.is_empty() {
+        out.push_str("\nRequired files:\n");
+        for file in &description.files {
+            out.push_str(&format!("  {}\n", file));
+        }
+    }
+    if !description.docs.is_empty() {
+        out.push_str("\nDocs:\n");
+        for link in &description.docs {
+            out.push_str(&format!("  {}\n", link));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_repo::config::parse_upstream;
+
+    #[test]
+    fn test_describe_upstream() {
+        let yaml = r#"
+- deprecated:
+    message: Moved to org/templates-v2
+- requires:
+    vars: [project_name]
+    files: [Cargo.toml]
+- docs: https://example.com/guide
+- template-vars:
+    license: MIT
+- include: ["**/*.md"]
+- yaml:
+    source: fragment.yml
+    dest: config.yml
+    defer: true
+- tools:
+    - name: rustc
+      version: ">=1.70"
+"#;
+        let upstream = parse_upstream(yaml, &[]).unwrap();
+        let description = describe("https://example.com/t".into(), "v1".into(), upstream);
+        assert_eq!(
+            description.variables,
+            [
+                Variable {
+                    name: "license".into(),
+                    default: Some("MIT".into()),
+                    required: false,
+                },
+                Variable {
+                    name: "project_name".into(),
+                    default: None,
+                    required: true,
+                },
+            ]
+        );
+        assert_eq!(description.tools[0].name, "rustc");
+
+        let text = format_description(&description);
+        assert_eq!(
+            text,
+            "Upstream: https://example.com/t @ v1\n\
+             Deprecated: Moved to org/templates-v2\n\
+             \n\
+             Variables:\n  license = MIT\n  project_name (required)\n\
+             \n\
+             Operations: 4\n  template-vars\n  include **/*.md\n  \
+             yaml fragment.yml -> config.yml (deferred)\n  tools\n\
+             \n\
+             Required tools:\n  rustc >=1.70\n\
+             \n\
+             Required files:\n  Cargo.toml\n\
+             \n\
+             Docs:\n  https://example.com/guide\n"
+        );
+    }
+}
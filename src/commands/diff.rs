@@ -9,36 +9,86 @@
 //! - **Change Detection**: Compares the composite filesystem (after applying
 //!   the configuration) with the current working directory
 //! - **Change Categories**: Shows files that would be added, modified, or deleted
-//! - **Exit Codes**: Returns 0 if no changes would occur, 1 if changes exist
+//! - **Permissions**: Files whose content matches but whose permission bits
+//!   would change (e.g. `644` to `755`) are listed as changes of their own,
+//!   modified files note a mode change, and added files note when they are
+//!   executable
+//! - **Exit Codes**: Ends with exit code 0 if no changes would occur, 2
+//!   (drift) if changes exist (see [`common_repo::outcome`])
+//! - **Patch**: With `--patch`, also prints a unified diff of each changed
+//!   text file
+//! - **Git Base**: With `--base <ref>`, compares against the files committed
+//!   at that ref (read from git) instead of the working tree, answering "what
+//!   would change relative to main" on a feature branch
+//! - **Ignored Drift**: Differences the config's `- diff-ignore:` entry
+//!   expects, such as a consumer's own badge line in README, are not
+//!   reported (see [`common_repo::drift`])
+//! - **Plans**: With `--save-plan <file>`, saves the files compared with,
+//!   so that `apply --from-plan <file>` writes exactly what was reviewed
+//!   (see [`common_repo::phases::plan`])
+//! - **Remote Consumer**: With `--repo <url> --ref <ref>`, compares a consumer
+//!   repository fetched into the cache instead of a local checkout, so an
+//!   audit job can check drift across many repositories without cloning
+//!   them itself
+//!
+//! In GitHub Actions each added or modified file is also reported as a
+//! workflow annotation on that file, and the changes are listed in the job
+//! summary (see [`common_repo::github`]).
 //!
 //! This command is a safe, read-only operation that does not modify any files.
 //! It runs phases 1-5 of the pipeline without writing to disk (phase 6).
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use common_repo::cache::RepoCache;
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::drift::DriftFilter;
+use common_repo::filesystem::{File, FsChange, MemoryFS};
+use common_repo::git;
+use common_repo::github;
+use common_repo::messages::Message;
+use common_repo::outcome::{self, Outcome};
+use common_repo::output;
+use common_repo::phases::context::RunContext;
 use common_repo::phases::orchestrator;
 use common_repo::repository::RepositoryManager;
 
+use super::completions;
+
 /// Show differences between current files and configuration result
 #[derive(Args, Debug)]
 pub struct DiffArgs {
     /// Path to the .common-repo.yaml configuration file.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    ///
+    /// With `--repo`, the path is relative to the root of that repository.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// The working directory to compare against.
@@ -50,6 +100,40 @@ pub struct DiffArgs {
     /// Show only a summary without listing individual files.
     #[arg(long)]
     pub summary: bool,
+
+    /// Compare against the files committed at this git ref (a branch, tag
+    /// or commit) instead of the working tree.
+    ///
+    /// The configuration is still read and composed from the working tree;
+    /// only the files it is compared with come from the ref.
+    #[arg(long, value_name = "REF")]
+    pub base: Option<String>,
+
+    /// Also show a unified diff of each file that would be added or
+    /// modified.
+    #[arg(short, long, conflicts_with = "summary")]
+    pub patch: bool,
+
+    /// Compare the consumer repository at this URL instead of a local
+    /// directory. Requires `--ref`.
+    ///
+    /// The repository is fetched into the cache and read from there.
+    #[arg(
+        long,
+        value_name = "URL",
+        requires = "ref_",
+        conflicts_with_all = ["working_dir", "base"]
+    )]
+    pub repo: Option<String>,
+
+    /// The git reference of the `--repo` repository to compare.
+    #[arg(long = "ref", value_name = "REF", requires = "repo")]
+    pub ref_: Option<String>,
+
+    /// Save the files compared with to FILE, for `apply --from-plan` to
+    /// write exactly those files.
+    #[arg(long, value_name = "FILE", conflicts_with = "repo")]
+    pub save_plan: Option<PathBuf>,
 }
 
 /// Result of comparing a file
@@ -61,6 +145,8 @@ pub enum ChangeType {
     Modified,
     /// File exists in working directory but not in config result
     Deleted,
+    /// File content is the same but its permission bits differ
+    ModeChanged,
 }
 
 /// A single change entry
@@ -68,6 +154,30 @@ pub enum ChangeType {
 pub struct Change {
     pub path: PathBuf,
     pub change_type: ChangeType,
+    /// Permission bits of the current file, if it exists
+    pub old_mode: Option<u32>,
+    /// Permission bits `apply` would write, unless the file is deleted
+    pub new_mode: Option<u32>,
+}
+
+impl Change {
+    /// A note on the file's permissions, if they are worth mentioning: a
+    /// mode change, or an added executable.
+    fn mode_note(&self) -> Option<String> {
+        match (self.old_mode, self.new_mode) {
+            (Some(old), Some(new)) if old != new => Some(format!("mode {:o} -> {:o}", old, new)),
+            (None, Some(new)) if new & 0o111 != 0 => Some(format!("executable, mode {:o}", new)),
+            _ => None,
+        }
+    }
+
+    /// The path, followed by the mode note if there is one.
+    fn describe(&self) -> String {
+        match self.mode_note() {
+            Some(note) => format!("{} ({})", self.path.display(), note),
+            None => self.path.display().to_string(),
+        }
+    }
 }
 
 /// Execute the `diff` command.
@@ -76,9 +186,33 @@ pub struct Change {
 /// of the pipeline to build the final filesystem, then compares it against the
 /// working directory to show what changes would be made.
 ///
-/// Returns `Ok(())` with exit code 0 if no changes, exit code 1 if changes exist.
+/// Records [`Outcome::Drift`] when changes exist, so that the command ends
+/// with exit code 2 rather than 0.
 pub fn execute(args: DiffArgs) -> Result<()> {
-    let config_path = &args.config;
+    // Initialize repository manager
+    let local_config = common_repo::defaults::resolve_config_path(&args.config);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, &local_config);
+
+    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_cache = RepoCache::new();
+
+    // Determine working directory and configuration
+    let (working_dir, config_path) = match (&args.repo, &args.ref_) {
+        (Some(url), Some(ref_)) => {
+            let dir = repo_manager
+                .fetch_repository_dir(url, ref_)
+                .with_context(|| format!("Failed to fetch {}@{}", url, ref_))?;
+            let config_path = common_repo::defaults::resolve_config_path(&dir.join(&args.config));
+            (dir, config_path)
+        }
+        _ => (
+            args.working_dir.unwrap_or_else(|| {
+                std::env::current_dir().expect("Failed to get current directory")
+            }),
+            local_config,
+        ),
+    };
+    let config_path = &config_path;
 
     // Validate config file exists
     if !config_path.exists() {
@@ -86,39 +220,40 @@ pub fn execute(args: DiffArgs) -> Result<()> {
     }
 
     // Load configuration
-    let schema = config::from_file(config_path).map_err(|e| {
-        anyhow::anyhow!(
-            "Failed to load config from {}: {}",
-            config_path.display(),
-            e
-        )
-    })?;
-
-    // Initialize repository manager
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
-
-    let repo_manager = RepositoryManager::new(cache_root);
-    let repo_cache = RepoCache::new();
-
-    // Determine working directory
-    let working_dir = args
-        .working_dir
-        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+    let schema = config::from_file(config_path)
+        .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+    let ignore = config::diff_ignore(&fs::read_to_string(config_path)?)
+        .and_then(|ignore| DriftFilter::new(&ignore))?;
 
     // Execute phases 1-5 (skip phase 6 - writing to disk)
-    let final_fs = orchestrator::execute_pull(
-        &schema,
-        &repo_manager,
-        &repo_cache,
-        &working_dir,
-        None, // Don't write to disk
-    )
-    .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    let mut diagnostics = Diagnostics::new();
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir)
+        .scope(config::path_scope(&fs::read_to_string(config_path)?)?);
+    let plan = orchestrator::execute_plan(&schema, &ctx, &mut diagnostics)
+        .context("Failed to process configuration")?;
+    if let Some(path) = &args.save_plan {
+        plan.save(path)?;
+        output::status(Message::PlanSaved { path });
+    }
+    let final_fs = plan.fs;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+    github::annotate_diagnostics(&diagnostics);
+    outcome::record_warnings(diagnostics.len());
 
     // Compare filesystems and collect changes
-    let changes = compute_changes(&final_fs, &working_dir)?;
+    let current = match &args.base {
+        Some(base) => files_at_ref(&final_fs, &working_dir, base)?,
+        None => files_on_disk(&final_fs, &working_dir)?,
+    };
+    let changes = compute_changes(&final_fs, &current, &ignore);
+    // Files of a remote consumer have no place in this job's checkout.
+    let annotate_dir = args.repo.is_none().then_some(working_dir.as_path());
+    report_to_github(&changes, annotate_dir);
+    outcome::record_files(changes.len());
 
     // Display results
     if changes.is_empty() {
@@ -139,6 +274,10 @@ pub fn execute(args: DiffArgs) -> Result<()> {
         .iter()
         .filter(|c| c.change_type == ChangeType::Deleted)
         .collect();
+    let mode_changed: Vec<_> = changes
+        .iter()
+        .filter(|c| c.change_type == ChangeType::ModeChanged)
+        .collect();
 
     if args.summary {
         // Summary mode: only show counts
@@ -152,6 +291,13 @@ pub fn execute(args: DiffArgs) -> Result<()> {
         if !deleted.is_empty() {
             println!("  {} file(s) would be deleted", deleted.len());
         }
+        if !mode_changed.is_empty() {
+            println!(
+                "  {} file(s) would be {}",
+                mode_changed.len(),
+                ChangeType::ModeChanged.verb()
+            );
+        }
         println!();
         println!("Total: {} change(s)", changes.len());
     } else {
@@ -159,7 +305,7 @@ pub fn execute(args: DiffArgs) -> Result<()> {
         if !added.is_empty() {
             println!("Files to add:");
             for change in &added {
-                println!("  + {}", change.path.display());
+                println!("  + {}", change.describe());
             }
             println!();
         }
@@ -167,7 +313,15 @@ pub fn execute(args: DiffArgs) -> Result<()> {
         if !modified.is_empty() {
             println!("Files to modify:");
             for change in &modified {
-                println!("  ~ {}", change.path.display());
+                println!("  ~ {}", change.describe());
+            }
+            println!();
+        }
+
+        if !mode_changed.is_empty() {
+            println!("Permissions to change:");
+            for change in &mode_changed {
+                println!("  * {}", change.describe());
             }
             println!();
         }
@@ -180,74 +334,175 @@ pub fn execute(args: DiffArgs) -> Result<()> {
             println!();
         }
 
+        if args.patch {
+            print_patch(&final_fs, &current, &ignore);
+            println!();
+        }
+
         // Summary line
+        let mode_summary = if mode_changed.is_empty() {
+            String::new()
+        } else {
+            format!(", {} mode change(s)", mode_changed.len())
+        };
         println!(
-            "Summary: {} added, {} modified, {} deleted",
+            "Summary: {} added, {} modified, {} deleted{}",
             added.len(),
             modified.len(),
-            deleted.len()
+            deleted.len(),
+            mode_summary
         );
     }
 
-    // Exit with code 1 to indicate changes exist
-    // Note: We use anyhow::bail! with a specific message that we can detect
-    // in the test, or we could use std::process::exit(1) but that would
-    // bypass cleanup. Instead, we return Ok(()) but the CLI wrapper should
-    // check the return value and exit accordingly.
-    //
-    // For now, we use a custom error type that indicates "changes detected"
-    // which the caller can interpret as exit code 1.
-    Err(anyhow::anyhow!("CHANGES_DETECTED"))
+    outcome::record(Outcome::Drift);
+    Ok(())
 }
 
-/// Compute the differences between the in-memory filesystem and the working directory.
-fn compute_changes(
-    final_fs: &common_repo::filesystem::MemoryFS,
-    working_dir: &Path,
-) -> Result<Vec<Change>> {
-    let mut changes = Vec::new();
+impl ChangeType {
+    /// What `apply` would do to the file, as in "would be added".
+    fn verb(&self) -> &'static str {
+        match self {
+            ChangeType::Added => "added",
+            ChangeType::Modified => "modified",
+            ChangeType::Deleted => "deleted",
+            ChangeType::ModeChanged => "given new permissions",
+        }
+    }
+}
+
+/// Annotate each change on its file in `working_dir`, if given, and list
+/// the changes in the GitHub Actions job summary.
+fn report_to_github(changes: &[Change], working_dir: Option<&Path>) {
+    if let Some(working_dir) = working_dir {
+        for change in changes {
+            github::annotate(
+                github::Annotation::new(
+                    github::Level::Warning,
+                    Message::DriftAnnotation {
+                        change: change.change_type.verb(),
+                    },
+                )
+                .file(working_dir.join(&change.path))
+                .title(Message::DriftAnnotationTitle),
+            );
+        }
+    }
 
-    // Track paths we've seen in the final filesystem
-    let mut config_paths: HashSet<PathBuf> = HashSet::new();
+    let mut markdown = String::from("### common-repo diff\n\n");
+    if changes.is_empty() {
+        markdown.push_str("No changes detected.\n");
+    } else {
+        let rows: Vec<Vec<String>> = changes
+            .iter()
+            .map(|change| {
+                let verb = change.change_type.verb();
+                vec![
+                    format!("`{}`", change.path.display()),
+                    match change.mode_note() {
+                        Some(note) => format!("{} ({})", verb, note),
+                        None => verb.to_string(),
+                    },
+                ]
+            })
+            .collect();
+        markdown.push_str(&github::summary_table(&["File", "Change"], &rows));
+    }
+    github::append_summary(&markdown);
+}
 
-    // Check each file in the final filesystem
-    for (path, file) in final_fs.files() {
-        config_paths.insert(path.clone());
+/// The files committed at `base` in the git repository containing
+/// `working_dir`, at the paths of `final_fs`.
+fn files_at_ref(final_fs: &MemoryFS, working_dir: &Path, base: &str) -> Result<MemoryFS> {
+    let committed = git::files_at_ref(
+        working_dir,
+        base,
+        final_fs.files().map(|(path, _)| path.as_path()),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to read files at '{}': {}", base, e))?;
 
-        let full_path = working_dir.join(path);
+    let mut fs = MemoryFS::new();
+    for (path, committed) in committed {
+        let mut file = File::new(committed.content);
+        file.permissions = committed.mode;
+        fs.add_file(path, file)?;
+    }
+    Ok(fs)
+}
 
+/// The files in `working_dir`, at the paths of `final_fs`.
+fn files_on_disk(final_fs: &MemoryFS, working_dir: &Path) -> Result<MemoryFS> {
+    let mut fs = MemoryFS::new();
+    for (path, _) in final_fs.files() {
+        let full_path = working_dir.join(path);
         if full_path.exists() {
-            // File exists - check if content differs
-            let existing_content = fs::read(&full_path).map_err(|e| {
+            let file = File::from_path(&full_path).map_err(|e| {
                 anyhow::anyhow!("Failed to read file {}: {}", full_path.display(), e)
             })?;
-
-            if existing_content != file.content {
-                changes.push(Change {
-                    path: path.clone(),
-                    change_type: ChangeType::Modified,
-                });
-            }
-        } else {
-            // File doesn't exist - would be added
-            changes.push(Change {
-                path: path.clone(),
-                change_type: ChangeType::Added,
-            });
+            fs.add_file(path, file)?;
         }
     }
+    Ok(fs)
+}
 
-    // Check for files in working directory that would be deleted
-    // Note: This is only relevant if the config specifies files to delete
-    // For now, we only show files that exist in working dir but not in config
-    // if they were originally managed by common-repo (we can't easily detect this)
-    //
-    // TODO: Consider adding a manifest file to track managed files
+/// The changes `apply` would make to `current`, the files at the paths of
+/// `final_fs`.
+///
+/// Only files the configuration produces are compared. Files in the
+/// working directory that it does not produce are never reported as
+/// deleted, since they may be the consumer's own. Differences `ignore`
+/// expects are left out, but a file whose permission bits would change is
+/// reported even when its content is the same or its content differences
+/// are expected, unless `ignore` ignores the whole path.
+fn compute_changes(final_fs: &MemoryFS, current: &MemoryFS, ignore: &DriftFilter) -> Vec<Change> {
+    let content_changes: HashMap<PathBuf, FsChange> = current
+        .diff(final_fs)
+        .iter()
+        .filter(|entry| !ignore.ignores(entry))
+        .map(|entry| (entry.path.clone(), entry.change))
+        .collect();
+    let mut paths: BTreeSet<&Path> = final_fs.files().map(|(path, _)| path.as_path()).collect();
+    paths.extend(current.files().map(|(path, _)| path.as_path()));
+
+    paths
+        .into_iter()
+        .filter(|path| !ignore.ignores_path(path))
+        .filter_map(|path| {
+            let old_mode = current.get_file(path).map(mode);
+            let new_mode = final_fs.get_file(path).map(mode);
+            let change_type = match content_changes.get(path) {
+                Some(FsChange::Added) => ChangeType::Added,
+                Some(FsChange::Modified) => ChangeType::Modified,
+                Some(FsChange::Removed) => ChangeType::Deleted,
+                None => match (old_mode, new_mode) {
+                    (Some(old), Some(new)) if old != new => ChangeType::ModeChanged,
+                    _ => return None,
+                },
+            };
+            Some(Change {
+                path: path.to_path_buf(),
+                change_type,
+                old_mode,
+                new_mode,
+            })
+        })
+        .collect()
+}
 
-    // Sort changes by path for consistent output
-    changes.sort_by(|a, b| a.path.cmp(&b.path));
+/// The permission bits of `file`, without its file type.
+fn mode(file: &File) -> u32 {
+    file.permissions & 0o777
+}
 
-    Ok(changes)
+/// Print a unified diff of each changed text file that `ignore` does not
+/// expect.
+fn print_patch(final_fs: &MemoryFS, current: &MemoryFS, ignore: &DriftFilter) {
+    for entry in current.diff(final_fs).iter().filter(|e| !ignore.ignores(e)) {
+        match entry.unified_diff(3) {
+            // Secret template values must not reach a CI log
+            Some(unified) => print!("{}", common_repo::secrets::mask(&unified)),
+            None => println!("Binary file {} differs", entry.path.display()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -264,6 +519,11 @@ mod tests {
             cache_root: None,
             working_dir: Some(temp_dir.path().to_path_buf()),
             summary: false,
+            base: None,
+            patch: false,
+            repo: None,
+            ref_: None,
+            save_plan: None,
         };
 
         let result = execute(args);
@@ -295,6 +555,11 @@ mod tests {
             cache_root: Some(temp_dir.path().join("cache")),
             working_dir: Some(temp_dir.path().to_path_buf()),
             summary: false,
+            base: None,
+            patch: false,
+            repo: None,
+            ref_: None,
+            save_plan: None,
         };
 
         // When files match, should return Ok(())
@@ -311,6 +576,53 @@ mod tests {
         assert_ne!(ChangeType::Added, ChangeType::Modified);
     }
 
+    #[test]
+    fn test_compute_changes_reports_mode_changes() {
+        let file = |content: &str, permissions: u32| {
+            let mut file = File::from_string(content);
+            file.permissions = permissions;
+            file
+        };
+        let mut final_fs = MemoryFS::new();
+        final_fs.add_file("run.sh", file("run", 0o755)).unwrap();
+        final_fs.add_file("new.sh", file("new", 0o100755)).unwrap();
+        final_fs.add_file("edit.sh", file("v2", 0o755)).unwrap();
+        final_fs.add_file("same.txt", file("same", 0o644)).unwrap();
+        let mut current = MemoryFS::new();
+        current.add_file("run.sh", file("run", 0o100644)).unwrap();
+        current.add_file("edit.sh", file("v1", 0o644)).unwrap();
+        current
+            .add_file("same.txt", file("same", 0o100644))
+            .unwrap();
+
+        let changes = compute_changes(
+            &final_fs,
+            &current,
+            &DriftFilter::new(&config::DiffIgnore::default()).unwrap(),
+        );
+        let described: Vec<(String, ChangeType)> = changes
+            .iter()
+            .map(|change| (change.describe(), change.change_type.clone()))
+            .collect();
+        assert_eq!(
+            described,
+            vec![
+                (
+                    "edit.sh (mode 644 -> 755)".to_string(),
+                    ChangeType::Modified
+                ),
+                (
+                    "new.sh (executable, mode 755)".to_string(),
+                    ChangeType::Added
+                ),
+                (
+                    "run.sh (mode 644 -> 755)".to_string(),
+                    ChangeType::ModeChanged
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_execute_with_summary_flag() {
         let temp_dir = TempDir::new().unwrap();
@@ -327,6 +639,11 @@ mod tests {
             cache_root: Some(temp_dir.path().join("cache")),
             working_dir: Some(temp_dir.path().to_path_buf()),
             summary: true,
+            base: None,
+            patch: false,
+            repo: None,
+            ref_: None,
+            save_plan: None,
         };
 
         let result = execute(args);
@@ -0,0 +1,526 @@
+//! # Doctor Command Implementation
+//!
+//! This module implements the `doctor` subcommand, which checks that the
+//! environment `common-repo` runs in is healthy and says how to fix what is
+//! not. It is the first thing to run, and to attach to a bug report, when
+//! an apply fails for reasons that have nothing to do with the
+//! configuration's content.
+//!
+//! ## Checks
+//!
+//! - **`git`**: git is installed, and new enough for partial clones
+//!   (`partial: true`, which needs `git sparse-checkout set --no-cone`).
+//! - **`cache`**: the cache directory can be written, and how much it holds.
+//! - **`config`**: the configuration parses.
+//! - **`host <name>`**: for each host the configuration fetches from, a
+//!   `git ls-remote` of one of its upstreams succeeds. A failure is
+//!   reported as a credentials problem or as a network problem, depending
+//!   on what git said. Skipped with `--offline`.
+//! - **`manifest`**: the files the last apply wrote (see
+//!   [`common_repo::phases::manifest`]) are still there.
+//!
+//! Each check passes, warns or fails, with a fix for the latter two. Any
+//! failure makes the command fail; `--json` prints the checks for other
+//! tools.
+
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use common_repo::config::{self, RepoOp};
+use common_repo::defaults::{self, DEFAULT_CONFIG_FILENAME};
+use common_repo::messages::Icon;
+use common_repo::output;
+use common_repo::phases::manifest::ApplyManifest;
+use common_repo::version;
+
+use super::cache::calculate_directory_info;
+use super::completions;
+use super::ls::format_size;
+
+/// The oldest git with `sparse-checkout set --no-cone`, which partial
+/// clones use.
+const PARTIAL_CLONE_GIT: (u64, u64) = (2, 35);
+
+/// Check the environment common-repo runs in
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Path to the .common-repo.yaml configuration file to check.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Resolved as `apply` resolves it.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+
+    /// Skip the checks that contact the upstream hosts.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Print the checks as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// How a check ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of one check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+    /// What to do about a warning or error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn error(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Error,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let icon = match self.status {
+            Status::Ok => Icon::Ok,
+            Status::Warning => Icon::Warning,
+            Status::Error => Icon::Error,
+        };
+        write!(
+            f,
+            "{} {}: {}",
+            icon.render(&output::global()),
+            self.name,
+            self.detail
+        )?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n   fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// The JSON report.
+#[derive(Serialize)]
+struct Report<'a> {
+    checks: &'a [Check],
+}
+
+/// Execute the `doctor` command.
+pub fn execute(args: DoctorArgs) -> Result<()> {
+    let config_path = defaults::resolve_config_path(&args.config);
+    let cache_root = defaults::resolve_cache_root(args.cache_root, &config_path);
+
+    let mut checks = vec![check_git(), check_cache(&cache_root)];
+    let schema = check_config(&config_path, &mut checks);
+    if let Some(schema) = &schema {
+        if !args.offline {
+            checks.extend(check_hosts(&version::collect_inherited_repos(schema)));
+        }
+        let dir = match config_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => std::env::current_dir()?,
+        };
+        checks.push(check_manifest(&cache_root, &dir));
+    }
+
+    if args.json {
+        let report = Report { checks: &checks };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for check in &checks {
+            println!("{}", check);
+        }
+        let count = |status| checks.iter().filter(|c| c.status == status).count();
+        println!(
+            "{} passed, {} warning(s), {} error(s)",
+            count(Status::Ok),
+            count(Status::Warning),
+            count(Status::Error)
+        );
+    }
+
+    let errors = checks.iter().filter(|c| c.status == Status::Error).count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("Doctor found {} problem(s)", errors));
+    }
+    Ok(())
+}
+
+/// Whether git runs, and is new enough for partial clones.
+fn check_git() -> Check {
+    let output = match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            return Check::error(
+                "git",
+                "git is not installed or not on PATH",
+                "install git from https://git-scm.com/downloads",
+            )
+        }
+    };
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match parse_git_version(&version) {
+        Some(found) if found < PARTIAL_CLONE_GIT => Check::warning(
+            "git",
+            format!("{} is too old for partial clones", version),
+            format!(
+                "upgrade git to {}.{} or newer to use `partial: true`",
+                PARTIAL_CLONE_GIT.0, PARTIAL_CLONE_GIT.1
+            ),
+        ),
+        _ => Check::ok("git", version),
+    }
+}
+
+/// The major and minor version in `git --version` output, such as
+/// `git version 2.39.3 (Apple Git-145)`.
+fn parse_git_version(output: &str) -> Option<(u64, u64)> {
+    let version = output.strip_prefix("git version ")?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Whether the cache directory can be written, and what it holds.
+fn check_cache(cache_root: &Path) -> Check {
+    let probe = cache_root.join(".doctor-probe");
+    let writable = fs::create_dir_all(cache_root)
+        .and_then(|()| fs::write(&probe, b""))
+        .and_then(|()| fs::remove_file(&probe));
+    if let Err(e) = writable {
+        return Check::error(
+            "cache",
+            format!("{} is not writable: {}", cache_root.display(), e),
+            "fix the directory's permissions, or choose another with --cache-dir or COMMON_REPO_CACHE_DIR",
+        );
+    }
+    // Entries are named `{hash}-{ref}`; `manifests/` and the like are not.
+    let entries = fs::read_dir(cache_root)
+        .map(|dir| {
+            dir.flatten()
+                .filter(|entry| entry.path().is_dir())
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .and_then(|name| name.split_once('-'))
+                        .is_some_and(|(hash, _)| {
+                            !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit())
+                        })
+                })
+                .count()
+        })
+        .unwrap_or(0);
+    let (size, _, _) = calculate_directory_info(cache_root);
+    Check::ok(
+        "cache",
+        format!(
+            "{} is writable ({} repositories cached, {})",
+            cache_root.display(),
+            entries,
+            format_size(size as usize)
+        ),
+    )
+}
+
+/// Whether the configuration at `path` parses. Returns it when it does.
+fn check_config(path: &Path, checks: &mut Vec<Check>) -> Option<config::Schema> {
+    if !path.exists() {
+        checks.push(Check::warning(
+            "config",
+            format!("{} not found", path.display()),
+            "run `common-repo init` to create one, or pass --config",
+        ));
+        return None;
+    }
+    match config::from_file(path) {
+        Ok(schema) => {
+            let upstreams = version::collect_inherited_repos(&schema).len();
+            checks.push(Check::ok(
+                "config",
+                format!(
+                    "{} parses ({} operations, {} upstreams)",
+                    path.display(),
+                    schema.len(),
+                    upstreams
+                ),
+            ));
+            Some(schema)
+        }
+        Err(e) => {
+            checks.push(Check::error(
+                "config",
+                format!("{}: {}", path.display(), e),
+                format!(
+                    "run `common-repo validate --config {}` for details",
+                    path.display()
+                ),
+            ));
+            None
+        }
+    }
+}
+
+/// One check per host that `repos` are fetched from, contacting the first
+/// repository of each.
+fn check_hosts(repos: &[RepoOp]) -> Vec<Check> {
+    let mut seen = Vec::new();
+    let mut checks = Vec::new();
+    for repo in repos.iter().filter(|repo| !repo.is_local()) {
+        let Some(host) = host(&repo.url) else {
+            continue;
+        };
+        if seen.contains(&host) {
+            continue;
+        }
+        seen.push(host.clone());
+        checks.push(check_host(&host, repo));
+    }
+    checks
+}
+
+/// Whether `repo`, on `host`, can be listed with `git ls-remote`.
+fn check_host(host: &str, repo: &RepoOp) -> Check {
+    let name = format!("host {}", host);
+    let mut command = Command::new("git");
+    command
+        .args(["ls-remote", &repo.url])
+        .args(repo.r#ref.as_deref())
+        // Fail instead of waiting for a password nobody will type.
+        .env("GIT_TERMINAL_PROMPT", "0");
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => {
+            return Check::error(
+                name,
+                format!("could not run git: {}", e),
+                "install git from https://git-scm.com/downloads",
+            )
+        }
+    };
+    if output.status.success() {
+        return Check::ok(name, format!("{} is reachable", repo.url));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let first_line = stderr.lines().next().unwrap_or("git ls-remote failed");
+    match classify_failure(&stderr) {
+        Failure::Credentials => Check::error(
+            name,
+            format!("access to {} was denied: {}", repo.url, first_line),
+            "check your SSH key with `ssh -T git@<host>`, or the token in your git credential helper",
+        ),
+        Failure::Network => Check::error(
+            name,
+            format!("{} could not be reached: {}", repo.url, first_line),
+            "check your network connection, DNS and HTTPS_PROXY settings",
+        ),
+        Failure::Other => Check::error(
+            name,
+            format!("could not list {}: {}", repo.url, first_line),
+            "check that the URL in the configuration is right",
+        ),
+    }
+}
+
+/// Why `git ls-remote` failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    Credentials,
+    Network,
+    Other,
+}
+
+/// Tell a credentials problem from a network problem in git's `stderr`.
+fn classify_failure(stderr: &str) -> Failure {
+    const CREDENTIALS: &[&str] = &[
+        "Authentication failed",
+        "Permission denied",
+        "could not read Username",
+        "terminal prompts disabled",
+        "Could not read from remote repository",
+        "403",
+    ];
+    const NETWORK: &[&str] = &[
+        "Could not resolve host",
+        "Failed to connect",
+        "Connection refused",
+        "Connection timed out",
+        "Network is unreachable",
+        "Operation timed out",
+    ];
+    if NETWORK.iter().any(|pattern| stderr.contains(pattern)) {
+        Failure::Network
+    } else if CREDENTIALS.iter().any(|pattern| stderr.contains(pattern)) {
+        Failure::Credentials
+    } else {
+        Failure::Other
+    }
+}
+
+/// The host of a repository URL, including scp-like SSH URLs such as
+/// `git@github.com:acme/ci.git`.
+fn host(url: &str) -> Option<String> {
+    if let Ok(parsed) = url::Url::parse(url) {
+        return parsed.host_str().map(str::to_string);
+    }
+    let (user_host, _) = url.split_once(':')?;
+    let host = user_host.rsplit('@').next()?;
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// Whether the files the last apply into `dir` wrote are still there.
+fn check_manifest(cache_root: &Path, dir: &Path) -> Check {
+    let manifest = ApplyManifest::load(cache_root, dir, None);
+    if manifest.is_empty() {
+        return Check::ok("manifest", "no apply recorded for this directory yet");
+    }
+    let missing: Vec<&Path> = manifest
+        .paths()
+        .filter(|path| !dir.join(path).exists())
+        .collect();
+    if missing.is_empty() {
+        return Check::ok(
+            "manifest",
+            format!("all {} files of the last apply are present", manifest.len()),
+        );
+    }
+    let detail = match missing.as_slice() {
+        [path] => format!("{} from the last apply is missing", path.display()),
+        _ => format!(
+            "{} of {} files of the last apply are missing, such as {}",
+            missing.len(),
+            manifest.len(),
+            missing[0].display()
+        ),
+    };
+    Check::warning(
+        "manifest",
+        detail,
+        "run `common-repo apply` to restore them, or `common-repo diff` to see the difference",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_repo::filesystem::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(parse_git_version("git version 2.43.0"), Some((2, 43)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.45.1.windows.1"),
+            Some((2, 45))
+        );
+        assert_eq!(parse_git_version("hub version 2.14.2"), None);
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(
+            host("https://github.com/acme/ci.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            host("git@gitlab.example.com:acme/ci.git").as_deref(),
+            Some("gitlab.example.com")
+        );
+        assert_eq!(
+            host("ssh://git@github.com/acme/ci").as_deref(),
+            Some("github.com")
+        );
+    }
+
+    #[test]
+    fn test_classify_failure() {
+        assert_eq!(
+            classify_failure("fatal: unable to access 'https://x/': Could not resolve host: x"),
+            Failure::Network
+        );
+        assert_eq!(
+            classify_failure(
+                "fatal: could not read Username for 'https://github.com': terminal prompts disabled"
+            ),
+            Failure::Credentials
+        );
+        assert_eq!(
+            classify_failure("fatal: repository 'https://x/y' not found"),
+            Failure::Other
+        );
+    }
+
+    #[test]
+    fn test_check_manifest_reports_missing_files() {
+        let temp = TempDir::new().unwrap();
+        let cache_root = temp.path().join("cache");
+        let dir = temp.path().join("project");
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(check_manifest(&cache_root, &dir).status, Status::Ok);
+
+        fs::write(dir.join("ci.yml"), "on: push\n").unwrap();
+        let mut manifest = ApplyManifest::new();
+        manifest.insert("ci.yml", &File::from_string("on: push\n"));
+        manifest.insert("lint.yml", &File::from_string("rules: {}\n"));
+        manifest.save(&cache_root, &dir, None);
+
+        let check = check_manifest(&cache_root, &dir);
+        assert_eq!(check.status, Status::Warning);
+        assert_eq!(check.detail, "lint.yml from the last apply is missing");
+    }
+}
@@ -0,0 +1,107 @@
+//! # Explain-Error Command Implementation
+//!
+//! This module implements the `explain-error` subcommand, which prints the
+//! long-form description of an error code such as `E4002`. Every error the
+//! tool reports carries one of these codes; see `common_repo::error` for the
+//! numbering scheme.
+//!
+//! ## Example
+//!
+//! ```bash
+//! common-repo explain-error E4002
+//!
+//! # List every code
+//! common-repo explain-error
+//! ```
+
+use anyhow::Result;
+use clap::Args;
+
+use common_repo::error::{self, ErrorInfo, ERROR_CODES};
+
+/// Explain an error code
+#[derive(Args, Debug)]
+pub struct ExplainErrorArgs {
+    /// The error code to explain (e.g. E4002). Lists all codes when omitted.
+    #[arg(value_name = "CODE")]
+    pub code: Option<String>,
+
+    /// Print the explanation as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Execute the `explain-error` command.
+pub fn execute(args: ExplainErrorArgs) -> Result<()> {
+    let Some(code) = args.code else {
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(ERROR_CODES)?);
+        } else {
+            for info in ERROR_CODES {
+                println!("{}  {:<10}  {}", info.code, info.phase.as_str(), info.title);
+            }
+        }
+        return Ok(());
+    };
+
+    let info = error::explain(&code).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown error code: {}\n\nhint: Run 'common-repo explain-error' to list all codes",
+            code
+        )
+    })?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(info)?);
+    } else {
+        print!("{}", format_explanation(info));
+    }
+    Ok(())
+}
+
+fn format_explanation(info: &ErrorInfo) -> String {
+    format!(
+        "{}: {}\nPhase: {}\n\n{}\n",
+        info.code,
+        info.title,
+        info.phase.as_str(),
+        info.explanation
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_explanation() {
+        let info = error::explain("E1001").unwrap();
+        let text = format_explanation(info);
+        assert!(text.starts_with("E1001: Repository dependency cycle\nPhase: discovery\n\n"));
+        assert!(text.contains("inherits"));
+    }
+
+    #[test]
+    fn test_execute_unknown_code() {
+        let err = execute(ExplainErrorArgs {
+            code: Some("E0000".to_string()),
+            json: false,
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown error code: E0000"));
+    }
+
+    #[test]
+    fn test_execute_known_code_and_list() {
+        execute(ExplainErrorArgs {
+            code: Some("e4002".to_string()),
+            json: true,
+        })
+        .unwrap();
+        execute(ExplainErrorArgs {
+            code: None,
+            json: false,
+        })
+        .unwrap();
+    }
+}
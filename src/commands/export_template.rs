@@ -0,0 +1,238 @@
+//! # Export-Template Command Implementation
+//!
+//! This module implements the `export-template` subcommand, which writes the
+//! content a configuration composes from its upstreams to a directory, as a
+//! standalone template repository with no inheritance chain of its own.
+//!
+//! ## Functionality
+//!
+//! - **Composite**: Runs the pipeline up to, but not including, the merge
+//!   with local files, and writes the resulting files to `--out`. Upstream
+//!   operations, the consumer's filters and merges, and templates have all
+//!   been applied; local files only appear where the configuration
+//!   `include`s them.
+//! - **Template Config**: Writes a `.common-repo.yaml` to `--out` that
+//!   includes every exported file, so the directory can be pushed and used
+//!   as an upstream as is.
+//! - **Flattened Config**: Prints, or writes to `--flat-config`, a consumer
+//!   configuration that inherits from the exported template alone. It keeps
+//!   the operations that act on the final output (`header`, `format`,
+//!   `yaml-format`, `patch`, `assert`), `tools` and `self:` blocks;
+//!   everything else is
+//!   already part of the exported files.
+//!
+//! Use it to fork away from an inheritance chain, or to archive the exact
+//! shared state at a point in time.
+
+use anyhow::Result;
+use clap::Args;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common_repo::cache::RepoCache;
+use common_repo::config::{self, Operation, RepoOp, Schema};
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator::{self, LOCAL_MERGE};
+use common_repo::repository::RepositoryManager;
+
+use super::completions;
+
+/// Configuration written into the exported template.
+const TEMPLATE_CONFIG: &str = "\
+# Exported by common-repo export-template. Consumers inherit every file.
+- include: [\"**/*\"]
+";
+
+/// Write the composed upstream content as a standalone template
+#[derive(Args, Debug)]
+pub struct ExportTemplateArgs {
+    /// Directory to write the template to.
+    #[arg(long, value_name = "DIR")]
+    pub out: PathBuf,
+
+    /// Write the flattened consumer configuration to this file instead of
+    /// printing it.
+    #[arg(long, value_name = "FILE")]
+    pub flat_config: Option<PathBuf>,
+
+    /// Path to the .common-repo.yaml configuration file.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
+    /// `~/Library/Caches/common-repo` on macOS).
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+
+    /// The working directory for local file operations.
+    ///
+    /// If not provided, it defaults to the current working directory.
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Write into `--out` even if it is not empty.
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Execute the `export-template` command.
+pub fn execute(args: ExportTemplateArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+    let schema = config::from_file(config_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load config from {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+
+    let not_empty = fs::read_dir(&args.out).is_ok_and(|mut entries| entries.next().is_some());
+    if not_empty && !args.force {
+        return Err(anyhow::anyhow!(
+            "{} is not empty. Use --force to write into it anyway.",
+            args.out.display()
+        ));
+    }
+
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
+    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_cache = RepoCache::new();
+    let working_dir = args
+        .working_dir
+        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+
+    // Stop before the composite is combined with local files; self: blocks
+    // only write local files and are carried over in the flattened config.
+    let (_, source_config) = orchestrator::partition_self_operations(&schema);
+    let mut stages = orchestrator::default_stages();
+    if let Some(at) = stages.iter().position(|stage| stage.name() == LOCAL_MERGE) {
+        stages.truncate(at);
+    }
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir).stages(stages);
+    let mut diagnostics = Diagnostics::new();
+    let composite = orchestrator::execute_pull_with_context(&source_config, &ctx, &mut diagnostics)
+        .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+
+    fs::create_dir_all(&args.out)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", args.out.display(), e))?;
+    common_repo::phases::write_fs(&composite, &args.out)?;
+    fs::write(args.out.join(DEFAULT_CONFIG_FILENAME), TEMPLATE_CONFIG)?;
+    output::status(Message::TemplateExported {
+        path: &args.out,
+        count: composite.len(),
+    });
+
+    let out = args.out.canonicalize().unwrap_or(args.out.clone());
+    let flat = flatten(&schema, &out)?;
+    match &args.flat_config {
+        Some(path) => {
+            fs::write(path, &flat)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            output::status(Message::FlatConfigWritten { path });
+        }
+        None => print!("{}", flat),
+    }
+    Ok(())
+}
+
+/// A consumer configuration that inherits from the template exported to
+/// `out` in place of the upstreams of `schema`.
+fn flatten(schema: &Schema, out: &Path) -> Result<String> {
+    let mut flat: Schema = vec![Operation::Repo {
+        repo: RepoOp {
+            url: out.display().to_string(),
+            r#ref: None,
+            path: None,
+            with: Vec::new(),
+            disable: Vec::new(),
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
+        },
+    }];
+    flat.extend(
+        schema
+            .iter()
+            .filter(|op| {
+                matches!(
+                    op,
+                    Operation::Header { .. }
+                        | Operation::Format { .. }
+                        | Operation::YamlFormat { .. }
+                        | Operation::Patch { .. }
+                        | Operation::Assert { .. }
+                        | Operation::Tools { .. }
+                        | Operation::Self_ { .. }
+                )
+            })
+            .cloned(),
+    );
+    let yaml = serde_yaml::to_string(&flat)?;
+    Ok(format!(
+        "# Flattened by common-repo export-template: inherits from the exported\n\
+         # template only. Replace the url with where the template is hosted.\n{}",
+        yaml
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_keeps_output_operations() {
+        let schema = config::parse(
+            r#"
+- repo:
+    url: https://github.com/acme/base
+    ref: v1.0.0
+- exclude: ["*.tmp"]
+- header:
+    patterns: ["src/**/*.rs"]
+    template: "Copyright Acme"
+- self:
+    - include: ["local/**"]
+"#,
+        )
+        .unwrap();
+
+        let flat = flatten(&schema, Path::new("/tmp/exported")).unwrap();
+        let parsed = config::parse(&flat).unwrap();
+        assert_eq!(parsed.len(), 3);
+        let Operation::Repo { repo } = &parsed[0] else {
+            panic!("expected repo operation, got {:?}", parsed[0]);
+        };
+        assert_eq!(repo.url, "/tmp/exported");
+        assert_eq!(parsed[1].kind(), "header");
+        assert_eq!(parsed[2].kind(), "self");
+    }
+}
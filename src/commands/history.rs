@@ -0,0 +1,140 @@
+//! # History Command Implementation
+//!
+//! This module implements the `history` subcommand, which shows the audit
+//! trail `apply` keeps in `.common-repo/history.jsonl`: who applied which
+//! upstream commits when, and which managed files each apply changed (see
+//! [`common_repo::reporting::history`]).
+//!
+//! Entries are listed newest first.
+//!
+//! ## Example
+//!
+//! ```bash
+//! common-repo history
+//!
+//! # The last three applies, as JSON
+//! common-repo history -n 3 --json
+//! ```
+
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::reporting::history::{self, HistoryEntry, HISTORY_FILE};
+
+/// Show who applied which upstream commits, and what changed
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// The directory whose history to show.
+    ///
+    /// If not provided, it defaults to the current working directory.
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Show only the last N applies.
+    #[arg(short = 'n', long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Print the entries as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Execute the `history` command.
+pub fn execute(args: HistoryArgs) -> Result<()> {
+    let dir = match args.working_dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()?,
+    };
+    let mut entries = history::load(&dir)?;
+    entries.reverse();
+    if let Some(limit) = args.limit {
+        entries.truncate(limit);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else if entries.is_empty() {
+        output::status(Message::NoHistory {
+            path: &dir.join(HISTORY_FILE),
+        });
+    } else {
+        let text: Vec<String> = entries.iter().map(format_entry).collect();
+        print!("{}", text.join("\n"));
+    }
+    Ok(())
+}
+
+/// One entry as a block of lines: when and by whom, the configuration,
+/// the upstreams and the files changed.
+fn format_entry(entry: &HistoryEntry) -> String {
+    let mut text = match &entry.user {
+        Some(user) => format!("{}  {}\n", entry.time, user),
+        None => format!("{}\n", entry.time),
+    };
+    match &entry.pipeline {
+        Some(pipeline) => text.push_str(&format!(
+            "  config   {} (pipeline {})\n",
+            short(&entry.config),
+            pipeline
+        )),
+        None => text.push_str(&format!("  config   {}\n", short(&entry.config))),
+    }
+    for upstream in &entry.upstreams {
+        text.push_str(&format!("  upstream {} @ {}", upstream.url, upstream.ref_));
+        if let Some(commit) = &upstream.commit {
+            text.push_str(&format!(" ({})", short(commit)));
+        }
+        text.push('\n');
+    }
+    text.push_str(&format!("  {} file(s) changed\n", entry.files.len()));
+    for file in &entry.files {
+        text.push_str(&format!("    {}\n", file.display()));
+    }
+    text
+}
+
+/// The first 12 characters of a hash or commit.
+fn short(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_repo::reporting::history::UpstreamCommit;
+
+    #[test]
+    fn test_format_entry() {
+        let entry = HistoryEntry {
+            time: "2026-10-17T09:12:44Z".to_string(),
+            user: Some("Ada <ada@example.com>".to_string()),
+            pipeline: None,
+            config: "5f0c1a2b3c4d5e6f".to_string(),
+            upstreams: vec![
+                UpstreamCommit {
+                    url: "https://github.com/acme/ci".to_string(),
+                    ref_: "v1".to_string(),
+                    commit: Some("9c1e2f3a4b5c6d7e".to_string()),
+                },
+                UpstreamCommit {
+                    url: "../shared".to_string(),
+                    ref_: "main".to_string(),
+                    commit: None,
+                },
+            ],
+            files: vec![PathBuf::from(".github/workflows/ci.yml")],
+        };
+        assert_eq!(
+            format_entry(&entry),
+            "2026-10-17T09:12:44Z  Ada <ada@example.com>\n\
+             \x20 config   5f0c1a2b3c4d\n\
+             \x20 upstream https://github.com/acme/ci @ v1 (9c1e2f3a4b5c)\n\
+             \x20 upstream ../shared @ main\n\
+             \x20 1 file(s) changed\n\
+             \x20   .github/workflows/ci.yml\n"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! # Import Command Implementation
+//!
+//! This module implements the `import` subcommand, which creates a
+//! `.common-repo.yaml` from the state another templating tool left in a
+//! project, for teams moving a project over to common-repo.
+//!
+//! ## Functionality
+//!
+//! - **Sources**: Reads copier's `.copier-answers.yml`, cruft's `.cruft.json`
+//!   or a cookiecutter replay file, chosen with `--from` (see
+//!   [`common_repo::import`]).
+//! - **Generation**: The template repository becomes a `repo` operation and
+//!   the answers become `template-vars`. Anything that could not be carried
+//!   over is listed. The result is parsed before it is written.
+//! - **Dry Run**: With `--dry-run`, prints the configuration instead of
+//!   writing it.
+//! - **Force Mode**: Overwrites an existing configuration file when
+//!   specified.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::path::PathBuf;
+
+use common_repo::config;
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::import::{self, Tool};
+use common_repo::messages::Message;
+use common_repo::output;
+
+use super::completions;
+
+/// A templating tool to import from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    /// copier's `.copier-answers.yml`
+    Copier,
+    /// cruft's `.cruft.json`
+    Cruft,
+    /// A cookiecutter replay file (`~/.cookiecutter_replay/<template>.json`)
+    CookiecutterReplay,
+}
+
+impl From<ImportSource> for Tool {
+    fn from(source: ImportSource) -> Self {
+        match source {
+            ImportSource::Copier => Tool::Copier,
+            ImportSource::Cruft => Tool::Cruft,
+            ImportSource::CookiecutterReplay => Tool::CookiecutterReplay,
+        }
+    }
+}
+
+/// Create a configuration from another templating tool's state
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// The tool that generated the project.
+    #[arg(long, value_enum, value_name = "TOOL")]
+    pub from: ImportSource,
+
+    /// The tool's state file. Defaults to `.copier-answers.yml` for copier
+    /// and `.cruft.json` for cruft; required for cookiecutter-replay.
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Path of the configuration file to create.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// Print the configuration instead of writing it.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Overwrite an existing configuration file.
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Execute the `import` command.
+pub fn execute(args: ImportArgs) -> Result<()> {
+    let tool = Tool::from(args.from);
+    let file = match args.file.clone().or_else(|| tool.default_file().map(PathBuf::from)) {
+        Some(file) => file,
+        None => {
+            return Err(anyhow::anyhow!(
+                "--from {} needs --file\n  hint: replay files are kept in ~/.cookiecutter_replay/<template>.json",
+                tool
+            ))
+        }
+    };
+
+    if !args.dry_run && args.config.exists() && !args.force {
+        return Err(anyhow::anyhow!(
+            "Configuration file '{}' already exists. Use --force to overwrite.",
+            args.config.display()
+        ));
+    }
+
+    let content = fs::read_to_string(&file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+    let imported = import::read(tool, &content)?;
+    let config_content = imported.to_config(tool);
+    if let Err(e) = config::parse(&config_content) {
+        return Err(anyhow::anyhow!(
+            "The imported configuration does not parse: {}\n  hint: nothing was written",
+            e
+        ));
+    }
+
+    for note in &imported.notes {
+        output::warning(Message::ImportNote { note });
+    }
+
+    if args.dry_run {
+        print!("{}", config_content);
+        return Ok(());
+    }
+
+    fs::write(&args.config, &config_content).map_err(|e| {
+        anyhow::anyhow!("Failed to write config to {}: {}", args.config.display(), e)
+    })?;
+    output::status(Message::ConfigImported {
+        path: &args.config,
+        tool: &tool.to_string(),
+        url: &imported.url,
+        vars: imported.vars.len(),
+    });
+    output::status(Message::ApplyTip);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn args(dir: &TempDir, from: ImportSource, force: bool) -> ImportArgs {
+        ImportArgs {
+            from,
+            file: None,
+            config: dir.path().join(".common-repo.yaml"),
+            dry_run: false,
+            force,
+        }
+    }
+
+    #[test]
+    fn test_import_refuses_to_overwrite() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".common-repo.yaml"), "[]\n").unwrap();
+
+        let err = execute(args(&dir, ImportSource::Copier, false)).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+
+    #[test]
+    fn test_import_replay_requires_file() {
+        let dir = TempDir::new().unwrap();
+        let err = execute(args(&dir, ImportSource::CookiecutterReplay, false)).unwrap_err();
+        assert!(err.to_string().contains("needs --file"));
+    }
+
+    #[test]
+    fn test_import_writes_config() {
+        let dir = TempDir::new().unwrap();
+        let answers = dir.path().join("answers.yml");
+        fs::write(
+            &answers,
+            "_commit: v2.0.0\n_src_path: https://github.com/acme/tpl.git\nname: demo\n",
+        )
+        .unwrap();
+        let mut args = args(&dir, ImportSource::Copier, false);
+        args.file = Some(answers);
+
+        execute(args).unwrap();
+        let config = fs::read_to_string(dir.path().join(".common-repo.yaml")).unwrap();
+        assert!(config.contains("    ref: v2.0.0\n"), "{config}");
+        assert!(config.contains("    name: demo\n"), "{config}");
+    }
+}
@@ -8,31 +8,95 @@
 //! - **Configuration Overview**: Displays the configuration file path and basic statistics
 //! - **Repository Information**: Lists all inherited repositories with their refs and cache status
 //! - **Operation Breakdown**: Counts and displays operations by type
+//! - **Upstream Statistics**: With `--stats`, runs the configuration and
+//!   shows how many files and bytes each inherited repository contributes
+//!   to the output, its largest files, and how many of its files were
+//!   overridden or dropped (see [`common_repo::reporting::stats`])
+//! - **Template Variables**: With `--vars`, shows the value each template
+//!   variable ends up with and every definition of it, from lowest to
+//!   highest precedence (see [`common_repo::reporting::vars`])
+//! - **Cache Statistics**: With `--cache`, shows the resolved cache
+//!   directory, the size and age of each inherited repository's cache entry,
+//!   and which repositories the last `apply` took from the cache or cloned,
+//!   and how long each fetch took (see [`common_repo::reporting::fetches`])
 //!
 //! This command is a safe, read-only operation that does not modify any files.
+//! With `--stats` it runs phases 1-5 of the pipeline without writing to disk
+//! (phase 6). `--vars` fetches the inherited repositories but runs no
+//! operations. `--cache` only reads the cache.
 
 use anyhow::Result;
 use clap::Args;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use common_repo::cache::RepoCache;
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator;
+use common_repo::reporting::fetches::{FetchOutcome, LastRun};
+use common_repo::reporting::stats::UpstreamStats;
+use common_repo::reporting::vars::{self, VarResolution};
 use common_repo::repository::RepositoryManager;
 
+use super::cache::calculate_directory_info;
+use super::completions;
+use super::ls::format_size;
+
+/// How many of each upstream's largest files `--stats` lists.
+const LARGEST_FILES: usize = 3;
+
 /// Show information about a repository or the current configuration
 #[derive(Args, Debug)]
 pub struct InfoArgs {
     /// Path to the .common-repo.yaml configuration file.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
+
+    /// Show how many files and bytes each inherited repository contributes
+    /// to the output.
+    ///
+    /// Runs the configuration, fetching repositories that are not cached.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Show the value each template variable resolves to and every place
+    /// that defines it, from lowest to highest precedence.
+    ///
+    /// Fetches repositories that are not cached.
+    #[arg(long)]
+    pub vars: bool,
+
+    /// Show the cache directory, the size and age of each inherited
+    /// repository's cache entry, and what the last apply fetched and how
+    /// long each fetch took.
+    #[arg(long)]
+    pub cache: bool,
 }
 
 /// Execute the `info` command.
@@ -40,7 +104,7 @@ pub struct InfoArgs {
 /// This function handles the logic for the `info` subcommand. It loads the
 /// configuration file and displays detailed information about it.
 pub fn execute(args: InfoArgs) -> Result<()> {
-    let config_path = &args.config;
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
     println!("📋 Configuration: {}", config_path.display());
 
     // Load configuration
@@ -53,11 +117,9 @@ pub fn execute(args: InfoArgs) -> Result<()> {
     })?;
 
     // Initialize repository manager for cache checking
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
 
-    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_manager = RepositoryManager::new(cache_root.clone());
 
     // Count operations by type
     let operation_counts = count_operations(&schema);
@@ -77,9 +139,280 @@ pub fn execute(args: InfoArgs) -> Result<()> {
     // Format and display output
     display_info(&operation_counts, &repo_operations, &repo_cache_status);
 
+    // Local paths in the configuration are relative to its directory.
+    let project_dir = match config_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    if args.stats {
+        let stats = upstream_stats(&schema, &repo_manager, &project_dir)?;
+        display_stats(&stats);
+    }
+    if args.vars {
+        let repo_cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &repo_cache, &project_dir);
+        let resolution = vars::resolve(&schema, &ctx)
+            .map_err(|e| anyhow::anyhow!("Failed to resolve template variables: {}", e))?;
+        print!("{}", format_vars(&resolution));
+    }
+    if args.cache {
+        let entries = cache_entries(&repo_operations, &repo_manager);
+        print!(
+            "{}",
+            format_cache(
+                &cache_root,
+                &entries,
+                LastRun::load(&cache_root).as_ref(),
+                SystemTime::now()
+            )
+        );
+    }
+
     Ok(())
 }
 
+/// The statistics of each top-level `repo:` operation of `schema`, with
+/// its label.
+///
+/// The configuration is run once for the output, and once more for each
+/// repository on its own, with the configuration's template variables, up
+/// to the local merge.
+fn upstream_stats(
+    schema: &config::Schema,
+    repo_manager: &RepositoryManager,
+    working_dir: &Path,
+) -> Result<Vec<(String, UpstreamStats)>> {
+    let repo_cache = RepoCache::new();
+    let mut diagnostics = Diagnostics::new();
+    let output = orchestrator::execute_pull(
+        schema,
+        repo_manager,
+        &repo_cache,
+        working_dir,
+        None, // Don't write to disk
+        &mut diagnostics,
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+
+    let vars: config::Schema = schema
+        .iter()
+        .filter(|op| matches!(op, config::Operation::TemplateVars { .. }))
+        .cloned()
+        .collect();
+    let mut stats = Vec::new();
+    for operation in schema {
+        let config::Operation::Repo { repo } = operation else {
+            continue;
+        };
+        let mut config = vars.clone();
+        config.push(operation.clone());
+        let mut stages = orchestrator::default_stages();
+        if let Some(at) = stages
+            .iter()
+            .position(|stage| stage.name() == orchestrator::LOCAL_MERGE)
+        {
+            stages.truncate(at);
+        }
+        let ctx = RunContext::new(repo_manager, &repo_cache, working_dir).stages(stages);
+        // Warnings were already reported for the full run.
+        let composite =
+            orchestrator::execute_pull_with_context(&config, &ctx, &mut Diagnostics::new())
+                .map_err(|e| anyhow::anyhow!("Failed to process {}: {}", repo.url, e))?;
+        let label = match repo.r#ref.as_deref() {
+            Some(ref_) if !repo.is_local() => format!("{} @ {}", repo.url, ref_),
+            _ => repo.url.clone(),
+        };
+        stats.push((label, UpstreamStats::of(&composite, &output, LARGEST_FILES)));
+    }
+    Ok(stats)
+}
+
+/// Display the statistics of each upstream.
+fn display_stats(stats: &[(String, UpstreamStats)]) {
+    println!("\nUpstream contributions:");
+    if stats.is_empty() {
+        println!("  (no inherited repositories)");
+    }
+    for (label, stats) in stats {
+        println!(
+            "  • {}: {} files, {}",
+            label,
+            stats.contributed,
+            format_size(stats.bytes as usize)
+        );
+        if stats.overridden > 0 {
+            println!("      {} overridden", stats.overridden);
+        }
+        if stats.dropped > 0 {
+            println!("      {} not in the output", stats.dropped);
+        }
+        for (path, size) in &stats.largest {
+            println!("      {} ({})", path.display(), format_size(*size as usize));
+        }
+    }
+}
+
+/// Render each variable with its value and its definitions, from lowest to
+/// highest precedence.
+fn format_vars(resolution: &VarResolution) -> String {
+    let mut out = format!("\nTemplate variables: {}\n", resolution.len());
+    if resolution.is_empty() {
+        out.push_str("  (no template variables)\n");
+    }
+    for (name, definitions) in resolution {
+        let Some(used) = definitions.last() else {
+            continue;
+        };
+        out.push_str(&format!("  {} = {:?}\n", name, used.value));
+        for (i, definition) in definitions.iter().enumerate() {
+            out.push_str(&format!(
+                "      {}. {}: {:?}{}\n",
+                i + 1,
+                definition.source,
+                definition.value,
+                if i + 1 == definitions.len() {
+                    "  (used)"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+    out
+}
+
+/// The state of a repository's on-disk cache entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EntryStatus {
+    /// A local path, which is never cached.
+    Local,
+    /// Not in the cache.
+    Missing,
+    /// In the cache, with its size in bytes, how many files it has, and when
+    /// it was last written.
+    Cached(u64, usize, Option<SystemTime>),
+}
+
+/// The label of each repository operation with the state of its cache
+/// entry.
+fn cache_entries(
+    repo_operations: &[config::RepoOp],
+    repo_manager: &RepositoryManager,
+) -> Vec<(String, EntryStatus)> {
+    repo_operations
+        .iter()
+        .map(|repo| {
+            let ref_ = repo.r#ref.as_deref().unwrap_or("");
+            let label = repo_label(&repo.url, ref_, repo.path.as_deref(), repo.is_local());
+            let status = match repo_manager.cache_path(&repo.url, ref_, repo.path.as_deref()) {
+                None => EntryStatus::Local,
+                Some(path) if path.is_dir() => {
+                    let (size, files, modified) = calculate_directory_info(&path);
+                    EntryStatus::Cached(size, files, modified)
+                }
+                Some(_) => EntryStatus::Missing,
+            };
+            (label, status)
+        })
+        .collect()
+}
+
+/// How a repository is shown: `url @ ref path:sub`, without the ref for
+/// local paths.
+fn repo_label(url: &str, ref_: &str, path: Option<&str>, local: bool) -> String {
+    let mut label = url.to_string();
+    if !ref_.is_empty() && !local {
+        label.push_str(&format!(" @ {}", ref_));
+    }
+    if let Some(path) = path {
+        label.push_str(&format!(" path:{}", path));
+    }
+    label
+}
+
+/// A duration as a short age, such as `42s`, `5m`, `3h` or `2d`.
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// How long ago `time` was, as of `now`.
+fn ago(time: SystemTime, now: SystemTime) -> String {
+    format!(
+        "{} ago",
+        format_age(now.duration_since(time).unwrap_or_default())
+    )
+}
+
+/// Render the cache directory, the cache entries of the repositories and
+/// the fetches of the last run, slowest first.
+fn format_cache(
+    cache_root: &Path,
+    entries: &[(String, EntryStatus)],
+    last_run: Option<&LastRun>,
+    now: SystemTime,
+) -> String {
+    let mut out = format!("\nCache: {}\n", cache_root.display());
+    for (label, entry) in entries {
+        let status = match entry {
+            EntryStatus::Local => "local, not cached".to_string(),
+            EntryStatus::Missing => "not cached".to_string(),
+            EntryStatus::Cached(size, files, modified) => {
+                let mut status = format!("{}, {} files", format_size(*size as usize), files);
+                if let Some(modified) = modified {
+                    status.push_str(&format!(", updated {}", ago(*modified, now)));
+                }
+                status
+            }
+        };
+        out.push_str(&format!("  • {}: {}\n", label, status));
+    }
+
+    let Some(last_run) = last_run else {
+        out.push_str("\nLast apply: (no apply recorded in this cache)\n");
+        return out;
+    };
+    out.push_str(&format!(
+        "\nLast apply: {}\n  {} fetches: {} from the cache, {} cloned, {} local\n",
+        ago(last_run.finished_at(), now),
+        last_run.fetches.len(),
+        last_run.count(FetchOutcome::Cached),
+        last_run.count(FetchOutcome::Cloned),
+        last_run.count(FetchOutcome::Local),
+    ));
+    let mut fetches: Vec<_> = last_run.fetches.iter().collect();
+    fetches.sort_by_key(|fetch| std::cmp::Reverse(fetch.millis));
+    for fetch in fetches {
+        let outcome = match fetch.outcome {
+            FetchOutcome::Cached => "from the cache",
+            FetchOutcome::Cloned => "cloned",
+            FetchOutcome::Local => "local",
+        };
+        out.push_str(&format!(
+            "  • {}: {} in {:.2}s\n",
+            repo_label(
+                &fetch.url,
+                &fetch.ref_,
+                fetch.path.as_deref(),
+                fetch.outcome == FetchOutcome::Local
+            ),
+            outcome,
+            fetch.elapsed().as_secs_f64()
+        ));
+    }
+    out
+}
+
 /// Count operations by type from the configuration schema.
 fn count_operations(schema: &config::Schema) -> OperationCounts {
     let mut counts = OperationCounts::default();
@@ -99,7 +432,16 @@ fn count_operations(schema: &config::Schema) -> OperationCounts {
             config::Operation::Ini { .. } => counts.ini += 1,
             config::Operation::Markdown { .. } => counts.markdown += 1,
             config::Operation::Xml { .. } => counts.xml += 1,
+            config::Operation::File { .. } => counts.file += 1,
+            config::Operation::Vendor { .. } => counts.vendor += 1,
+            config::Operation::Patch { .. } => counts.patch += 1,
+            config::Operation::Header { .. } => counts.header += 1,
+            config::Operation::Format { .. } => counts.format += 1,
+            config::Operation::YamlFormat { .. } => counts.yaml_format += 1,
+            config::Operation::Extract { .. } => counts.extract += 1,
+            config::Operation::Assert { .. } => counts.assert += 1,
             config::Operation::Self_ { .. } => counts.self_ += 1,
+            config::Operation::Unknown(_) => counts.unknown += 1,
         }
     }
 
@@ -172,7 +514,16 @@ fn display_info(
         + operation_counts.toml
         + operation_counts.ini
         + operation_counts.markdown
-        + operation_counts.self_;
+        + operation_counts.file
+        + operation_counts.vendor
+        + operation_counts.patch
+        + operation_counts.header
+        + operation_counts.format
+        + operation_counts.yaml_format
+        + operation_counts.extract
+        + operation_counts.assert
+        + operation_counts.self_
+        + operation_counts.unknown;
 
     println!("\nOperations: {}", total_operations);
 
@@ -218,9 +569,42 @@ fn display_info(
             operation_counts.markdown
         );
     }
+    if operation_counts.file > 0 {
+        println!("  • {} file operations", operation_counts.file);
+    }
+    if operation_counts.vendor > 0 {
+        println!("  • {} vendor operations", operation_counts.vendor);
+    }
+    if operation_counts.patch > 0 {
+        println!("  • {} patch operations", operation_counts.patch);
+    }
+    if operation_counts.header > 0 {
+        println!("  • {} header operations", operation_counts.header);
+    }
+    if operation_counts.format > 0 {
+        println!("  • {} format operations", operation_counts.format);
+    }
+    if operation_counts.yaml_format > 0 {
+        println!(
+            "  • {} yaml-format operations",
+            operation_counts.yaml_format
+        );
+    }
+    if operation_counts.extract > 0 {
+        println!("  • {} extract operations", operation_counts.extract);
+    }
+    if operation_counts.assert > 0 {
+        println!("  • {} assert operations", operation_counts.assert);
+    }
     if operation_counts.self_ > 0 {
         println!("  • {} self operations", operation_counts.self_);
     }
+    if operation_counts.unknown > 0 {
+        println!(
+            "  • {} unknown operations (skipped by this version)",
+            operation_counts.unknown
+        );
+    }
 }
 
 /// Structure to hold operation counts by type.
@@ -239,7 +623,16 @@ struct OperationCounts {
     ini: usize,
     markdown: usize,
     xml: usize,
+    file: usize,
+    vendor: usize,
+    patch: usize,
+    header: usize,
+    format: usize,
+    yaml_format: usize,
+    extract: usize,
+    assert: usize,
     self_: usize,
+    unknown: usize,
 }
 
 #[cfg(test)]
@@ -249,6 +642,103 @@ mod tests {
     use std::path::PathBuf;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_format_vars() {
+        use common_repo::reporting::vars::{VarDefinition, LOCAL_SOURCE};
+
+        let definition = |source: &str, value: &str| VarDefinition {
+            source: source.to_string(),
+            value: value.to_string(),
+        };
+        let resolution = VarResolution::from([
+            (
+                "owner".to_string(),
+                vec![
+                    definition("https://github.com/org/base @ v1", "infra"),
+                    definition(LOCAL_SOURCE, "platform"),
+                ],
+            ),
+            ("year".to_string(), vec![definition(LOCAL_SOURCE, "2026")]),
+        ]);
+
+        assert_eq!(
+            format_vars(&resolution),
+            "
+Template variables: 2
+  owner = \"platform\"
+      1. https://github.com/org/base @ v1: \"infra\"
+      2. local config: \"platform\"  (used)
+  year = \"2026\"
+      1. local config: \"2026\"  (used)
+"
+        );
+        assert_eq!(
+            format_vars(&VarResolution::new()),
+            "\nTemplate variables: 0\n  (no template variables)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_cache() {
+        use common_repo::reporting::fetches::Fetch;
+        use std::time::UNIX_EPOCH;
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let fetch = |url: &str, outcome, millis| Fetch {
+            url: url.to_string(),
+            ref_: "v1".to_string(),
+            path: None,
+            outcome,
+            commit: None,
+            millis,
+        };
+        let entries = vec![
+            (
+                "https://github.com/acme/ci @ v1".to_string(),
+                EntryStatus::Cached(2048, 3, Some(now - Duration::from_secs(7_200))),
+            ),
+            (
+                "https://github.com/acme/base @ v1".to_string(),
+                EntryStatus::Missing,
+            ),
+            ("../local".to_string(), EntryStatus::Local),
+        ];
+        let last_run = LastRun {
+            finished: 1_000_000 - 300,
+            fetches: vec![
+                fetch("https://github.com/acme/ci", FetchOutcome::Cached, 4),
+                fetch("https://github.com/acme/base", FetchOutcome::Cloned, 1250),
+                fetch("../local", FetchOutcome::Local, 1),
+            ],
+        };
+
+        assert_eq!(
+            format_cache(Path::new("/cache"), &entries, Some(&last_run), now),
+            "
+Cache: /cache
+  • https://github.com/acme/ci @ v1: 2.0K, 3 files, updated 2h ago
+  • https://github.com/acme/base @ v1: not cached
+  • ../local: local, not cached
+
+Last apply: 5m ago
+  3 fetches: 1 from the cache, 1 cloned, 1 local
+  • https://github.com/acme/base @ v1: cloned in 1.25s
+  • https://github.com/acme/ci @ v1: from the cache in 0.00s
+  • ../local: local in 0.00s
+"
+        );
+        assert!(format_cache(Path::new("/cache"), &[], None, now)
+            .ends_with("\nLast apply: (no apply recorded in this cache)\n"));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(300)), "5m");
+        assert_eq!(format_age(Duration::from_secs(3 * 3_600)), "3h");
+        assert_eq!(format_age(Duration::from_secs(2 * 86_400 + 5)), "2d");
+    }
+
     #[test]
     fn test_count_operations() {
         let schema = vec![
@@ -258,14 +748,21 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
             Operation::Include {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Exclude {
                 exclude: ExcludeOp {
@@ -276,8 +773,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.md".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
         ];
 
@@ -302,6 +801,9 @@ mod tests {
         let args = InfoArgs {
             config: PathBuf::from("/nonexistent/config.yaml"),
             cache_root: None,
+            stats: false,
+            vars: false,
+            cache: false,
         };
 
         let result = execute(args);
@@ -331,6 +833,9 @@ mod tests {
         let args = InfoArgs {
             config: config_path,
             cache_root: Some(temp_dir.path().to_path_buf()),
+            stats: false,
+            vars: false,
+            cache: false,
         };
 
         // This should succeed (though it will print output)
@@ -19,6 +19,8 @@ use std::process::Command;
 
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
 use common_repo::git;
+use common_repo::messages::Message;
+use common_repo::output;
 use common_repo::version;
 
 /// Initialize a new .common-repo.yaml configuration file
@@ -53,7 +55,7 @@ pub fn execute(args: InitArgs) -> Result<()> {
         ));
     }
 
-    println!("🎯 Initializing common-repo configuration...");
+    output::status(Message::Initializing);
 
     let config_content = if let Some(uri) = &args.uri {
         generate_config_from_uri(uri)?
@@ -64,8 +66,8 @@ pub fn execute(args: InitArgs) -> Result<()> {
 
     // Write the configuration file
     fs::write(config_path, config_content)?;
-    println!("✅ Created .common-repo.yaml");
-    println!("💡 Run `common-repo apply` to fetch and apply configurations");
+    output::status(Message::ConfigCreated);
+    output::status(Message::ApplyTip);
 
     Ok(())
 }
@@ -78,52 +80,53 @@ fn generate_config_from_uri(uri: &str) -> Result<String> {
     // Normalize URL (expand GitHub shorthand)
     let url = normalize_repo_url(uri);
 
-    print!("Fetching tags from {}... ", url);
+    output::progress(Message::FetchingTags {
+        url: &url,
+        indent: false,
+    });
     let (version, warnings) = match git::list_tags(&url) {
         Ok(tags) => {
             let semver_tags = version::filter_semver_tags(&tags);
             if let Some((latest_tag, parsed_version)) = find_latest_version(&semver_tags) {
-                println!("found {}", latest_tag);
+                output::status(Message::TagsFound { tag: &latest_tag });
 
                 // Check for 0.x.x versions
                 let mut warnings = Vec::new();
                 if parsed_version.major == 0 {
-                    warnings.push(format!(
-                        "Warning: {} is a 0.x.x version, which may indicate unstable API",
-                        latest_tag
-                    ));
+                    warnings.push(Message::UnstableVersion { tag: &latest_tag }.to_string());
                 }
                 (latest_tag, warnings)
             } else if !tags.is_empty() {
                 // No semver tags, warn and use main
-                println!("no semver tags found");
+                output::status(Message::TagsNoSemver);
                 (
                     "main".to_string(),
                     vec![
-                        "Warning: No semantic version tags found. Using 'main' branch.".to_string(),
-                        "Consider pinning to a specific commit hash for reproducibility."
-                            .to_string(),
+                        Message::NoSemverTagsUsingMain.to_string(),
+                        Message::PinCommitHint.to_string(),
                     ],
                 )
             } else {
-                println!("no tags found");
+                output::status(Message::TagsNone);
                 (
                     "main".to_string(),
                     vec![
-                        "Warning: No tags found. Using 'main' branch.".to_string(),
-                        "Consider pinning to a specific commit hash for reproducibility."
-                            .to_string(),
+                        Message::NoTagsUsingMain.to_string(),
+                        Message::PinCommitHint.to_string(),
                     ],
                 )
             }
         }
         Err(e) => {
-            println!("failed");
+            output::status(Message::TagsFailed);
             (
                 "main".to_string(),
                 vec![
-                    format!("Warning: Error fetching tags: {}. Using 'main' branch.", e),
-                    "Consider pinning to a specific commit hash for reproducibility.".to_string(),
+                    Message::FetchTagsFailedUsingMain {
+                        error: &e.to_string(),
+                    }
+                    .to_string(),
+                    Message::PinCommitHint.to_string(),
                 ],
             )
         }
@@ -131,7 +134,7 @@ fn generate_config_from_uri(uri: &str) -> Result<String> {
 
     // Print warnings
     for warning in &warnings {
-        println!("  ⚠️  {}", warning);
+        output::status(Message::VersionWarning { warning });
     }
 
     // Build the config
@@ -184,27 +187,34 @@ fn generate_interactive_config() -> Result<String> {
         let url = normalize_repo_url(input);
 
         // Fetch tags and find latest semver
-        print!("  Fetching tags from {}... ", url);
+        output::progress(Message::FetchingTags {
+            url: &url,
+            indent: true,
+        });
         match git::list_tags(&url) {
             Ok(tags) => {
                 let semver_tags = version::filter_semver_tags(&tags);
                 if let Some((latest_tag, _)) = find_latest_version(&semver_tags) {
-                    println!("found {}", latest_tag);
+                    output::status(Message::TagsFound { tag: &latest_tag });
                     repos.push(RepoEntry {
                         url: url.clone(),
                         version: latest_tag,
                     });
                 } else if !tags.is_empty() {
                     // No semver tags, warn and use first tag or main
-                    println!("no semver tags found");
-                    println!("  Warning: No semantic version tags found. Using 'main' branch.");
+                    output::status(Message::TagsNoSemver);
+                    output::status(Message::VersionWarning {
+                        warning: &Message::NoSemverTagsUsingMain.to_string(),
+                    });
                     repos.push(RepoEntry {
                         url: url.clone(),
                         version: "main".to_string(),
                     });
                 } else {
-                    println!("no tags found");
-                    println!("  Warning: No tags found. Using 'main' branch.");
+                    output::status(Message::TagsNone);
+                    output::status(Message::VersionWarning {
+                        warning: &Message::NoTagsUsingMain.to_string(),
+                    });
                     repos.push(RepoEntry {
                         url: url.clone(),
                         version: "main".to_string(),
@@ -212,8 +222,13 @@ fn generate_interactive_config() -> Result<String> {
                 }
             }
             Err(e) => {
-                println!("failed");
-                println!("  Error fetching tags: {}. Using 'main' branch.", e);
+                output::status(Message::TagsFailed);
+                output::status(Message::VersionWarning {
+                    warning: &Message::FetchTagsFailedUsingMain {
+                        error: &e.to_string(),
+                    }
+                    .to_string(),
+                });
                 repos.push(RepoEntry {
                     url: url.clone(),
                     version: "main".to_string(),
@@ -291,7 +306,7 @@ fn setup_precommit_hooks(theme: &ColorfulTheme) -> Result<()> {
     } else {
         // Generate the config file
         fs::write(config_path, generate_precommit_config())?;
-        println!("✅ Created .pre-commit-config.yaml");
+        output::status(Message::PreCommitConfigCreated);
     }
 
     // Detect available CLI
@@ -0,0 +1,402 @@
+//! # Lint-Upstream Command Implementation
+//!
+//! This module implements the `lint-upstream` subcommand, which checks an
+//! upstream (source) repository against practices that keep its consumers
+//! safe and predictable. Where `verify-upstream` looks for operations that
+//! do nothing, `lint-upstream` looks at what the upstream actually ships.
+//!
+//! ## Rules
+//!
+//! - **`include-scope`**: The configuration has no `include`, so every file
+//!   in the repository is propagated, including ones added later by
+//!   accident.
+//! - **`own-docs`**: The repository's own README, LICENSE, CHANGELOG and
+//!   similar root documents are propagated to consumers, where they
+//!   describe the wrong project. Files marked as templates are exempt.
+//! - **`unpinned-actions`**: Shipped GitHub Actions workflows and composite
+//!   actions `uses:` an action by tag or branch rather than by commit SHA,
+//!   so every consumer runs whatever the tag points to next.
+//! - **`undeclared-vars`**: Template files use variables that neither a
+//!   `template-vars` operation nor the `requires:` block declares.
+//!
+//! Files are the ones consumers receive (see
+//! [`super::verify_upstream::replay`]), at the paths they receive them.
+//! Every rule is on by default; `--disable` turns rules off. Any finding
+//! makes the command fail, so it can gate the CI of a template repository,
+//! and `--json` prints the findings for other tools.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common_repo::config::{self, Operation, Upstream};
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::filesystem::MemoryFS;
+
+use super::completions;
+use super::verify_upstream::{load_upstream_tree, replay, Replay, PLACEHOLDER};
+
+/// Root documents that describe the repository they are in.
+const OWN_DOCS: &[&str] = &[
+    "README",
+    "LICENSE",
+    "LICENCE",
+    "COPYING",
+    "CHANGELOG",
+    "CONTRIBUTING",
+    "CODE_OF_CONDUCT",
+];
+
+/// A lint rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// The configuration does not scope what it ships with `include`
+    IncludeScope,
+    /// The repository's own README, LICENSE and similar files are shipped
+    OwnDocs,
+    /// Shipped workflows use actions not pinned to a commit SHA
+    UnpinnedActions,
+    /// Templates use variables nothing declares
+    UndeclaredVars,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no rule is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// Check an upstream repository against best practices for templates
+#[derive(Args, Debug)]
+pub struct LintUpstreamArgs {
+    /// Path to the upstream's .common-repo.yaml configuration file.
+    ///
+    /// The directory containing the file is the tree that is checked.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// Turn off a rule. Can be repeated or given a comma-separated list.
+    #[arg(long, value_enum, value_name = "RULE", value_delimiter = ',')]
+    pub disable: Vec<Rule>,
+
+    /// Print the findings as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A single problem found in the upstream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Finding {
+    rule: Rule,
+    /// The shipped file the finding is about, if it is about one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    /// 1-based line in `path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.rule)?;
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{}: ", path.display(), line)?,
+            (Some(path), None) => write!(f, "{}: ", path.display())?,
+            _ => {}
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// The JSON report.
+#[derive(Serialize)]
+struct Report<'a> {
+    files_checked: usize,
+    findings: &'a [Finding],
+}
+
+/// Execute the `lint-upstream` command.
+pub fn execute(args: LintUpstreamArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+    let upstream = config::parse_upstream(&fs::read_to_string(config_path)?, &[])?;
+
+    let root = match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    let tree = load_upstream_tree(&root)?;
+
+    let disabled: HashSet<Rule> = args.disable.iter().copied().collect();
+    let findings: Vec<Finding> = lint(&upstream, &tree)
+        .into_iter()
+        .filter(|finding| !disabled.contains(&finding.rule))
+        .collect();
+
+    if args.json {
+        let report = Report {
+            files_checked: tree.len(),
+            findings: &findings,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for finding in &findings {
+            println!("{}: {}", config_path.display(), finding);
+        }
+        println!(
+            "{} file(s) checked, {} finding(s)",
+            tree.len(),
+            findings.len()
+        );
+    }
+
+    if !findings.is_empty() {
+        return Err(anyhow::anyhow!("Upstream lint failed"));
+    }
+    Ok(())
+}
+
+/// Every finding of every rule for `upstream`, whose repository holds
+/// `tree`.
+fn lint(upstream: &Upstream, tree: &MemoryFS) -> Vec<Finding> {
+    let schema = &upstream.schema;
+    let Replay {
+        composite,
+        templates,
+        ..
+    } = replay(schema, tree);
+    let mut findings = Vec::new();
+
+    if !schema
+        .iter()
+        .any(|op| matches!(op, Operation::Include { .. }))
+    {
+        findings.push(Finding {
+            rule: Rule::IncludeScope,
+            path: None,
+            line: None,
+            message: "there is no include operation, so consumers receive every file in the \
+                      repository; list what they receive with include"
+                .to_string(),
+        });
+    }
+
+    for path in composite.list_files() {
+        if path.components().count() != 1 || templates.contains_key(&path) {
+            continue;
+        }
+        let name = path.to_string_lossy();
+        let stem = name
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        if OWN_DOCS.contains(&stem.as_str()) {
+            findings.push(Finding {
+                rule: Rule::OwnDocs,
+                path: Some(path.clone()),
+                line: None,
+                message: "this repository's own document is shipped to consumers; exclude it, \
+                          or ship a template of it instead"
+                    .to_string(),
+            });
+        }
+    }
+
+    for (path, file) in composite.files() {
+        if !is_action_file(path) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&file.content);
+        for (index, line) in content.lines().enumerate() {
+            if let Some(action) = unpinned_action(line) {
+                findings.push(Finding {
+                    rule: Rule::UnpinnedActions,
+                    path: Some(path.clone()),
+                    line: Some(index + 1),
+                    message: format!("{} is not pinned to a commit SHA", action),
+                });
+            }
+        }
+    }
+
+    let mut declared: HashSet<&str> = schema
+        .iter()
+        .filter_map(|op| match op {
+            Operation::TemplateVars { template_vars } => Some(template_vars),
+            _ => None,
+        })
+        .flat_map(|tv| tv.vars.keys().map(String::as_str))
+        .collect();
+    if let Some(requires) = &upstream.requires {
+        declared.extend(requires.vars.iter().map(|var| var.name.as_str()));
+    }
+    for path in templates.keys() {
+        let Some(file) = composite.get_file(path) else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&file.content);
+        let mut reported = HashSet::new();
+        for (index, line) in content.lines().enumerate() {
+            for capture in PLACEHOLDER.captures_iter(line) {
+                let name = capture.get(1).expect("group 1").as_str();
+                if declared.contains(name) || !reported.insert(name) {
+                    continue;
+                }
+                findings.push(Finding {
+                    rule: Rule::UndeclaredVars,
+                    path: Some(path.clone()),
+                    line: Some(index + 1),
+                    message: format!(
+                        "variable {:?} is declared by no template-vars and not listed in requires",
+                        name
+                    ),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+/// Whether `path` is a GitHub Actions workflow or composite action.
+fn is_action_file(path: &Path) -> bool {
+    let yaml = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yml" | "yaml")
+    );
+    yaml && (path.starts_with(".github/workflows")
+        || matches!(
+            path.file_name().and_then(|n| n.to_str()),
+            Some("action.yml" | "action.yaml")
+        ))
+}
+
+/// The action a `uses:` line refers to, if it is not pinned to a full
+/// commit SHA. Local actions, Docker images and references filled in by a
+/// template variable are left alone.
+fn unpinned_action(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("- ").unwrap_or(line).trim_start();
+    let value = line.strip_prefix("uses:")?;
+    let value = value.split(" #").next().unwrap_or_default().trim();
+    let value = value.trim_matches(|c| c == '"' || c == '\'');
+    if value.is_empty()
+        || value.starts_with("./")
+        || value.starts_with("docker://")
+        || PLACEHOLDER.is_match(value)
+    {
+        return None;
+    }
+    let pinned = value.rsplit_once('@').is_some_and(|(_, r#ref)| {
+        r#ref.len() == 40 && r#ref.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    (!pinned).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(files: &[(&str, &str)]) -> MemoryFS {
+        let mut fs = MemoryFS::new();
+        for (path, content) in files {
+            fs.add_file_string(path, content).unwrap();
+        }
+        fs
+    }
+
+    fn lines(upstream: &str, files: &[(&str, &str)]) -> Vec<String> {
+        let upstream = config::parse_upstream(upstream, &[]).unwrap();
+        lint(&upstream, &tree(files))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_upstream() {
+        let findings = lines(
+            r#"
+- include: ["templates/**", "README.md.tmpl"]
+- template: ["README.md.tmpl"]
+- rename:
+    - "^templates/(.*)$": "$1"
+    - "^README.md.tmpl$": "README.md"
+- template-vars:
+    name: demo
+"#,
+            &[
+                (
+                    "templates/.github/workflows/ci.yml",
+                    "steps:\n  - uses: ./local\n",
+                ),
+                ("README.md.tmpl", "# __COMMON_REPO__name__\n"),
+                ("README.md", "# The template repository\n"),
+            ],
+        );
+        assert!(findings.is_empty(), "{:?}", findings);
+    }
+
+    #[test]
+    fn test_lint_reports_each_rule() {
+        let findings = lines(
+            r#"
+- requires:
+    vars: [owner]
+- template: ["*.tmpl"]
+"#,
+            &[
+                ("LICENSE", "MIT"),
+                ("docs/README.md", "nested docs are fine"),
+                (
+                    ".github/workflows/ci.yml",
+                    "steps:\n  - uses: actions/checkout@v4 # latest\n  - uses: \"actions/cache@0123456789abcdef0123456789abcdef01234567\"\n",
+                ),
+                (
+                    "notes.tmpl",
+                    "__COMMON_REPO__owner__ __COMMON_REPO__team__ __COMMON_REPO__team__\n",
+                ),
+            ],
+        );
+        assert_eq!(
+            findings,
+            vec![
+                "include-scope: there is no include operation, so consumers receive every file in the repository; list what they receive with include",
+                "own-docs: LICENSE: this repository's own document is shipped to consumers; exclude it, or ship a template of it instead",
+                "unpinned-actions: .github/workflows/ci.yml:2: actions/checkout@v4 is not pinned to a commit SHA",
+                "undeclared-vars: notes.tmpl:1: variable \"team\" is declared by no template-vars and not listed in requires",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unpinned_action() {
+        assert_eq!(unpinned_action("uses: org/action"), Some("org/action"));
+        assert_eq!(
+            unpinned_action("      - uses: 'org/action@main'"),
+            Some("org/action@main")
+        );
+        assert_eq!(unpinned_action("uses: docker://alpine:3"), None);
+        assert_eq!(
+            unpinned_action("uses: org/action@__COMMON_REPO__action_ref__"),
+            None
+        );
+        assert_eq!(unpinned_action("run: echo uses: x@v1"), None);
+    }
+}
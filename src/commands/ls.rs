@@ -20,22 +20,41 @@ use std::path::PathBuf;
 use common_repo::cache::RepoCache;
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
 use common_repo::phases::orchestrator;
 use common_repo::repository::RepositoryManager;
 
+use super::completions;
+
 /// List files that would be created/modified by the configuration
 #[derive(Args, Debug)]
 pub struct LsArgs {
     /// Path to the .common-repo.yaml configuration file.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// The working directory for local file operations.
@@ -83,7 +102,7 @@ pub enum SortOrder {
 /// of the pipeline to build the final filesystem, then lists the files without
 /// writing them to disk.
 pub fn execute(args: LsArgs) -> Result<()> {
-    let config_path = &args.config;
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
 
     // Validate config file exists
     if !config_path.exists() {
@@ -100,9 +119,7 @@ pub fn execute(args: LsArgs) -> Result<()> {
     })?;
 
     // Initialize repository manager
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
 
     let repo_manager = RepositoryManager::new(cache_root);
     let repo_cache = RepoCache::new();
@@ -115,14 +132,16 @@ pub fn execute(args: LsArgs) -> Result<()> {
     // Execute phases 1-5 without writing to disk (Phase 6).
     // Phase 5 merges local files and applies consumer-level filtering
     // (exclude/include/rename) to the final filesystem.
-    let final_fs = orchestrator::execute_pull(
-        &schema,
-        &repo_manager,
-        &repo_cache,
-        &working_dir,
-        None, // Don't write to disk
-    )
-    .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    let mut diagnostics = Diagnostics::new();
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir)
+        .scope(config::path_scope(&std::fs::read_to_string(config_path)?)?);
+    let final_fs = orchestrator::execute_pull_with_context(&schema, &ctx, &mut diagnostics)
+        .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
 
     // Collect files with their metadata
     let mut files: Vec<FileInfo> = final_fs
@@ -229,7 +248,7 @@ fn format_permissions(mode: u32) -> String {
 }
 
 /// Format file size in human-readable format
-fn format_size(size: usize) -> String {
+pub(crate) fn format_size(size: usize) -> String {
     const KB: usize = 1024;
     const MB: usize = KB * 1024;
     const GB: usize = MB * 1024;
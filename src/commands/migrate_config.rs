@@ -0,0 +1,226 @@
+//! # Migrate-Config Command Implementation
+//!
+//! This module implements the `migrate-config` subcommand, which rewrites a
+//! `.common-repo.yaml` file written for an older release so that it uses the
+//! current schema.
+//!
+//! ## Functionality
+//!
+//! - **Detection**: Finds old configuration shapes, such as `append: true` on
+//!   `yaml`/`json`/`toml` merge operations, using
+//!   [`common_repo::migrate::migrate`].
+//! - **Rewriting**: Edits only the affected lines, so comments and formatting
+//!   are preserved. The result is parsed before it is written; if it does not
+//!   parse, nothing is written.
+//! - **Check Mode**: With `--check`, lists the changes and fails if there are
+//!   any, without writing. Intended for CI.
+//! - **Forms**: With `--to`, also converts between a bare list of operations
+//!   and a versioned document with `apiVersion` and `operations`.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::path::PathBuf;
+
+use common_repo::config;
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::messages::Message;
+use common_repo::migrate;
+use common_repo::output;
+
+use super::completions;
+
+/// Rewrite a configuration file to the current schema
+#[derive(Args, Debug)]
+pub struct MigrateConfigArgs {
+    /// Path to the configuration file to migrate.
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        env = "COMMON_REPO_CONFIG",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// Report the changes that are needed without writing them, and exit
+    /// with an error if there are any.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Convert the configuration to this form: a bare list of operations,
+    /// or a document with an `apiVersion` and its `operations`.
+    #[arg(long, value_name = "FORM")]
+    pub to: Option<ConfigForm>,
+}
+
+/// The forms a configuration can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigForm {
+    /// A list of operations
+    List,
+    /// A document with `apiVersion` and `operations`
+    Document,
+}
+
+impl From<ConfigForm> for migrate::Form {
+    fn from(form: ConfigForm) -> Self {
+        match form {
+            ConfigForm::List => migrate::Form::List,
+            ConfigForm::Document => migrate::Form::Document,
+        }
+    }
+}
+
+/// Execute the `migrate-config` command.
+pub fn execute(args: MigrateConfigArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+
+    let content = fs::read_to_string(config_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read config from {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+    let mut migration = migrate::migrate(&content);
+    if let Some(form) = args.to {
+        migration = migrate::convert(migration, form.into())?;
+    }
+
+    if !migration.is_needed() {
+        output::status(Message::MigrationUpToDate { path: config_path });
+        return Ok(());
+    }
+
+    output::status(Message::MigrationNeeded {
+        path: config_path,
+        count: migration.changes.len(),
+    });
+    for change in &migration.changes {
+        output::status(Message::MigrationChange {
+            line: change.line,
+            description: &change.description,
+        });
+    }
+
+    if let Err(e) = config::parse(&migration.content) {
+        return Err(anyhow::anyhow!(
+            "The migrated configuration does not parse: {}\n  hint: fix {} by hand; nothing was written",
+            e,
+            config_path.display()
+        ));
+    }
+
+    if args.check {
+        output::status(Message::MigrateTip);
+        return Err(anyhow::anyhow!(
+            "{} is not on the current schema",
+            config_path.display()
+        ));
+    }
+
+    fs::write(config_path, &migration.content).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to write migrated config to {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+    output::status(Message::ConfigMigrated {
+        path: config_path,
+        count: migration.changes.len(),
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const OLD_CONFIG: &str = "\
+# keep this comment
+- yaml:
+    auto-merge: config.yaml
+    append: true
+";
+
+    #[test]
+    fn test_check_reports_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".common-repo.yaml");
+        fs::write(&config_path, OLD_CONFIG).unwrap();
+
+        let result = execute(MigrateConfigArgs {
+            config: config_path.clone(),
+            check: true,
+            to: None,
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), OLD_CONFIG);
+    }
+
+    #[test]
+    fn test_migrate_rewrites_and_is_idempotent() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".common-repo.yaml");
+        fs::write(&config_path, OLD_CONFIG).unwrap();
+        let args = || MigrateConfigArgs {
+            config: config_path.clone(),
+            check: false,
+            to: None,
+        };
+
+        execute(args()).unwrap();
+        let migrated = fs::read_to_string(&config_path).unwrap();
+        assert!(migrated.starts_with("# keep this comment\n"));
+        assert!(migrated.contains("    array_mode: append\n"));
+
+        execute(MigrateConfigArgs {
+            config: config_path.clone(),
+            check: true,
+            to: None,
+        })
+        .unwrap();
+        execute(args()).unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), migrated);
+    }
+
+    #[test]
+    fn test_converts_between_forms() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join(".common-repo.yaml");
+        let list = "# keep this comment\n- include: [\"**\"]\n";
+        fs::write(&config_path, list).unwrap();
+        let args = |to| MigrateConfigArgs {
+            config: config_path.clone(),
+            check: false,
+            to: Some(to),
+        };
+
+        execute(args(ConfigForm::Document)).unwrap();
+        let document = fs::read_to_string(&config_path).unwrap();
+        assert!(document.contains("apiVersion: common-repo/v1\noperations:\n"));
+        assert!(document.starts_with("# keep this comment\n"));
+
+        execute(args(ConfigForm::List)).unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), list);
+    }
+
+    #[test]
+    fn test_missing_config() {
+        let result = execute(MigrateConfigArgs {
+            config: PathBuf::from("/nonexistent/.common-repo.yaml"),
+            check: false,
+            to: None,
+        });
+        assert!(result.is_err());
+    }
+}
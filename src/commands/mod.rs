@@ -21,10 +21,22 @@ pub mod apply;
 pub mod cache;
 pub mod check;
 pub mod completions;
+pub mod describe;
 pub mod diff;
+pub mod doctor;
+pub mod explain_error;
+pub mod export_template;
+pub mod history;
+pub mod import;
 pub mod info;
 pub mod init;
+pub mod lint_upstream;
 pub mod ls;
+pub mod migrate_config;
+pub mod plan;
+pub mod promote;
+pub mod render;
 pub mod tree;
 pub mod update;
 pub mod validate;
+pub mod verify_upstream;
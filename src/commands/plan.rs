@@ -0,0 +1,282 @@
+//! # Plan Command Implementation
+//!
+//! This module implements the `plan` subcommand, which shows how the
+//! configuration will lay out files, for debugging configurations before
+//! running `apply`.
+//!
+//! ## Functionality
+//!
+//! - **Renames**: With `--renames`, lists the source and final destination
+//!   of every file the `rename` operations move, across upstreams and the
+//!   consumer's own configuration. A file renamed again by a later mapping
+//!   is flagged as chained, with each step, and mappings that matched no
+//!   file are listed as unmatched (see
+//!   [`common_repo::reporting::renames`]). A mapping whose rename
+//!   operation has a `description:` is shown with it.
+//!
+//! This command is a safe, read-only operation that does not modify any files.
+//! It runs phases 1-5 of the pipeline without writing to disk (phase 6).
+
+use anyhow::Result;
+use clap::{ArgGroup, Args};
+use std::path::PathBuf;
+
+use common_repo::cache::RepoCache;
+use common_repo::config;
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::filesystem::MemoryFS;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator;
+use common_repo::reporting::renames::{self, RenamePlan};
+use common_repo::repository::RepositoryManager;
+
+use super::completions;
+
+/// Show how the configuration will lay out files
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("view").required(true).args(["renames"])))]
+pub struct PlanArgs {
+    /// Path to the .common-repo.yaml configuration file.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
+    /// `~/Library/Caches/common-repo` on macOS).
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+
+    /// The working directory for local file operations.
+    ///
+    /// If not provided, it defaults to the current working directory.
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// List where each renamed file ends up after all rename operations.
+    #[arg(long)]
+    pub renames: bool,
+}
+
+/// Execute the `plan` command.
+pub fn execute(args: PlanArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+
+    // Validate config file exists
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+
+    // Load configuration
+    let schema = config::from_file(config_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load config from {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
+    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_cache = RepoCache::new();
+    let working_dir = args
+        .working_dir
+        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+
+    // Execute phases 1-5 (skip phase 6 - writing to disk)
+    let mut diagnostics = Diagnostics::new();
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir)
+        .scope(config::path_scope(&std::fs::read_to_string(config_path)?)?);
+    let (final_fs, trace) =
+        renames::trace(|| orchestrator::execute_pull_with_context(&schema, &ctx, &mut diagnostics));
+    let final_fs =
+        final_fs.map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+
+    if trace.is_empty() {
+        println!("No rename operations ran.");
+        return Ok(());
+    }
+    print!("{}", format_renames(&trace.plan(), &final_fs));
+    Ok(())
+}
+
+/// Render a rename plan, marking destinations that are not in `final_fs`
+/// because a later operation removed them.
+fn format_renames(plan: &RenamePlan, final_fs: &MemoryFS) -> String {
+    let mut out = String::new();
+    if !plan.files.is_empty() {
+        out.push_str("Renames:\n");
+    }
+    for file in &plan.files {
+        out.push_str(&format!(
+            "  {} -> {}",
+            file.source.display(),
+            file.destination().display()
+        ));
+        if file.is_chained() {
+            out.push_str("  (chained)");
+        }
+        if !final_fs.exists(file.destination()) {
+            out.push_str("  (not in output)");
+        }
+        out.push('\n');
+        if file.is_chained() || file.steps.iter().any(|step| step.description.is_some()) {
+            for step in &file.steps {
+                out.push_str(&format!(
+                    "      '{}' -> '{}': {}{}\n",
+                    step.mapping.from,
+                    step.mapping.to,
+                    step.path.display(),
+                    described(step.description.as_deref())
+                ));
+            }
+        }
+    }
+
+    if !plan.unmatched.is_empty() {
+        if !plan.files.is_empty() {
+            out.push('\n');
+        }
+        out.push_str("Unmatched rename patterns:\n");
+        for unmatched in &plan.unmatched {
+            out.push_str(&format!(
+                "  '{}' -> '{}'{}\n",
+                unmatched.mapping.from,
+                unmatched.mapping.to,
+                described(unmatched.description.as_deref())
+            ));
+        }
+    }
+
+    out.push_str(&format!(
+        "\nSummary: {} renamed, {} chained, {} unmatched pattern(s)\n",
+        plan.files.len(),
+        plan.files.iter().filter(|file| file.is_chained()).count(),
+        plan.unmatched.len()
+    ));
+    out
+}
+
+/// A rename operation's `description:` as a trailing comment.
+fn described(description: Option<&str>) -> String {
+    description
+        .map(|description| format!("  # {}", description))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_repo::config::RenameMapping;
+    use common_repo::reporting::renames::{RenameStep, RenamedFile, UnmatchedMapping};
+
+    fn step(from: &str, to: &str, path: &str) -> RenameStep {
+        RenameStep {
+            mapping: RenameMapping {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+            description: None,
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn test_format_renames() {
+        let plan = RenamePlan {
+            files: vec![
+                RenamedFile {
+                    source: PathBuf::from("a.rs"),
+                    steps: vec![step("^a", "b", "b.rs")],
+                },
+                RenamedFile {
+                    source: PathBuf::from("docs/x.md"),
+                    steps: vec![
+                        step("^docs/", "site/", "site/x.md"),
+                        step("^site/", "public/", "public/x.md"),
+                    ],
+                },
+            ],
+            unmatched: vec![UnmatchedMapping {
+                mapping: RenameMapping {
+                    from: "^old/".to_string(),
+                    to: "new/".to_string(),
+                },
+                description: None,
+            }],
+        };
+        let mut final_fs = MemoryFS::new();
+        final_fs.add_file_string("public/x.md", "x").unwrap();
+
+        assert_eq!(
+            format_renames(&plan, &final_fs),
+            "Renames:
+  a.rs -> b.rs  (not in output)
+  docs/x.md -> public/x.md  (chained)
+      '^docs/' -> 'site/': site/x.md
+      '^site/' -> 'public/': public/x.md
+
+Unmatched rename patterns:
+  '^old/' -> 'new/'
+
+Summary: 2 renamed, 1 chained, 1 unmatched pattern(s)
+"
+        );
+    }
+
+    #[test]
+    fn test_format_renames_shows_descriptions() {
+        let mut described_step = step("^a", "b", "b.rs");
+        described_step.description = Some("Sources live under b".to_string());
+        let plan = RenamePlan {
+            files: vec![RenamedFile {
+                source: PathBuf::from("a.rs"),
+                steps: vec![described_step],
+            }],
+            unmatched: vec![UnmatchedMapping {
+                mapping: RenameMapping {
+                    from: "^old/".to_string(),
+                    to: "new/".to_string(),
+                },
+                description: Some("Left from the 1.x layout".to_string()),
+            }],
+        };
+        let mut final_fs = MemoryFS::new();
+        final_fs.add_file_string("b.rs", "b").unwrap();
+
+        assert_eq!(
+            format_renames(&plan, &final_fs),
+            "Renames:
+  a.rs -> b.rs
+      '^a' -> 'b': b.rs  # Sources live under b
+
+Unmatched rename patterns:
+  '^old/' -> 'new/'  # Left from the 1.x layout
+
+Summary: 1 renamed, 0 chained, 1 unmatched pattern(s)
+"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! # Promote Command Implementation
+//!
+//! This module implements the `promote` subcommand, which carries a local
+//! change to a managed file back to the upstream the file came from, for
+//! when a fix made in a consumer belongs upstream.
+//!
+//! ## Functionality
+//!
+//! - **Locate**: The pipeline is run in memory to find where the file came
+//!   from: the upstream repository, its ref, and the file's path there,
+//!   whatever the operations renamed it to.
+//! - **Reverse**: The difference between what the configuration produces
+//!   and the local file is applied to the upstream's source file, turning
+//!   template values back into their variables where needed (see
+//!   [`common_repo::promote`]).
+//! - **Patch**: The change to the upstream is printed as a patch that
+//!   `git apply` accepts in a checkout of the upstream, or written to the
+//!   file given with `--output`.
+//! - **Branch**: With `--branch <name>`, the upstream is cloned at its ref
+//!   and the change is committed there on a new branch, ready to push.
+//!
+//! Nothing in the working directory is modified.
+//!
+//! ## Example
+//!
+//! ```bash
+//! common-repo promote .github/workflows/ci.yml
+//!
+//! # Commit the change on a branch of a fresh clone of the upstream
+//! common-repo promote .github/workflows/ci.yml --branch fix-ci-cache
+//! ```
+
+use anyhow::Result;
+use clap::Args;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use common_repo::cache::RepoCache;
+use common_repo::config;
+use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::filesystem::{File, MemoryFS};
+use common_repo::git;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator;
+use common_repo::promote::promote;
+use common_repo::reporting::vars;
+use common_repo::repository::RepositoryManager;
+
+use super::completions;
+
+/// Carry a local change to a managed file back to its upstream
+#[derive(Args, Debug)]
+pub struct PromoteArgs {
+    /// Path of the changed file, relative to the working directory.
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+
+    /// Path to the .common-repo.yaml configuration file.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
+    /// `~/Library/Caches/common-repo` on macOS).
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+
+    /// The working directory for local file operations.
+    ///
+    /// If not provided, it defaults to the current working directory.
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Write the patch to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Clone the upstream and commit the change on a new branch with this
+    /// name.
+    #[arg(long, value_name = "NAME")]
+    pub branch: Option<String>,
+
+    /// Where to clone the upstream for `--branch`. Must not exist yet.
+    ///
+    /// Defaults to a directory named after the branch in the system
+    /// temporary directory.
+    #[arg(long, value_name = "DIR", requires = "branch")]
+    pub clone_dir: Option<PathBuf>,
+}
+
+/// Execute the `promote` command.
+pub fn execute(args: PromoteArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+    let schema = config::from_file(config_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to load config from {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
+    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_cache = RepoCache::new();
+    let working_dir = args
+        .working_dir
+        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+    let path = args
+        .path
+        .strip_prefix(".")
+        .unwrap_or(&args.path)
+        .to_path_buf();
+
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir);
+    let final_fs = orchestrator::execute_pull_with_context(&schema, &ctx, &mut Diagnostics::new())
+        .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    let file = final_fs.get_file(&path).ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} is not in the output\n\nhint: Run 'common-repo ls' to list the files the configuration produces",
+            path.display()
+        )
+    })?;
+    let origin = file
+        .origin
+        .as_deref()
+        .filter(|origin| origin.repo.is_some())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} did not come from an upstream, so there is nowhere to promote it to",
+                path.display()
+            )
+        })?;
+    let repo = origin.repo.as_deref().unwrap_or_default();
+
+    let local = std::fs::read(working_dir.join(&path))
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    if local == file.content[..] {
+        anyhow::bail!(
+            "{} is unchanged from what the configuration produces; there is nothing to promote",
+            path.display()
+        );
+    }
+    let upstream_dir = repo_manager.fetch_repository_dir(repo, &origin.ref_)?;
+    let source = std::fs::read(upstream_dir.join(&origin.source)).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read {} in {}: {}",
+            origin.source.display(),
+            repo,
+            e
+        )
+    })?;
+    let text = |content: &[u8], what: &Path| {
+        String::from_utf8(content.to_vec())
+            .map_err(|_| anyhow::anyhow!("{} is not text and cannot be promoted", what.display()))
+    };
+    let source = text(&source, &origin.source)?;
+    let values: HashMap<String, String> = vars::resolve(&schema, &ctx)?
+        .into_iter()
+        .filter_map(|(name, definitions)| Some((name, definitions.last()?.value.clone())))
+        .collect();
+    let promoted = promote(
+        &source,
+        &text(&file.content, &path)?,
+        &text(&local, &path)?,
+        &values,
+    )
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "The change to {} does not apply to {} in {}\n\nhint: The changed lines were produced by a merge or formatting; make the change upstream by hand",
+            path.display(),
+            origin.source.display(),
+            repo
+        )
+    })?;
+    if promoted == source {
+        output::status(Message::AlreadyUpstream {
+            source: &origin.source,
+            repo,
+        });
+        return Ok(());
+    }
+
+    let mut before = MemoryFS::new();
+    before.add_file_string(&origin.source, &source)?;
+    let mut after = MemoryFS::new();
+    after.add_file_string(&origin.source, &promoted)?;
+    let patch = before
+        .diff(&after)
+        .iter()
+        .find_map(|entry| entry.unified_diff(3))
+        .unwrap_or_default();
+    match &args.output {
+        Some(output_path) => {
+            std::fs::write(output_path, &patch)?;
+            output::status(Message::PromotePatchWritten {
+                source: &origin.source,
+                path: output_path,
+            });
+        }
+        None if args.branch.is_none() => io::stdout().lock().write_all(patch.as_bytes())?,
+        None => {}
+    }
+
+    if let Some(branch) = &args.branch {
+        let clone_dir = args.clone_dir.unwrap_or_else(|| {
+            std::env::temp_dir()
+                .join("common-repo-promote")
+                .join(branch.replace('/', "-"))
+        });
+        // Keeps the source's mode, such as an executable bit
+        let mut file = File::from_path(&upstream_dir.join(&origin.source))?;
+        file.content = promoted.into_bytes().into();
+        commit_on_branch(
+            repo,
+            &origin.ref_,
+            &clone_dir,
+            branch,
+            &origin.source,
+            &file,
+        )?;
+        output::status(Message::PromoteCommitted {
+            source: &origin.source,
+            branch,
+            dir: &clone_dir,
+        });
+    }
+    Ok(())
+}
+
+/// Clone `url` at `ref_` into `dir` and commit `file` at `source` on a new
+/// branch `branch`, which is checked out.
+fn commit_on_branch(
+    url: &str,
+    ref_: &str,
+    dir: &Path,
+    branch: &str,
+    source: &Path,
+    file: &File,
+) -> Result<()> {
+    if dir.exists() {
+        anyhow::bail!(
+            "{} already exists\n\nhint: Remove it, or choose another directory with --clone-dir",
+            dir.display()
+        );
+    }
+    if ref_.is_empty() {
+        // A local upstream has no ref; clone what is checked out there.
+        let status = Command::new("git")
+            .args(["clone", "--quiet", url])
+            .arg(dir)
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("git clone of {} failed", url);
+        }
+    } else {
+        git::clone_shallow(url, ref_, dir)?;
+    }
+    let message = format!(
+        "Update {}\n\nPromoted from a consumer with common-repo promote.\n",
+        source.display()
+    );
+    let commit = git::commit_files(dir, [(source, file)], &message)?;
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["checkout", "--quiet", "-b", branch, &commit])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("Failed to create branch {} in {}", branch, dir.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! # Render Command Implementation
+//!
+//! This module implements the `render` subcommand, which runs the pipeline
+//! and prints the final content of a single file to stdout, for shell
+//! scripts and editor integrations that want a quick preview of one merged
+//! file.
+//!
+//! ## Functionality
+//!
+//! - **Single File**: Prints the file exactly as `apply` would write it
+//! - **Provenance**: With `--provenance`, prepends a comment saying whether
+//!   the content came from upstream, the local file, or both (see
+//!   [`common_repo::reporting::provenance`]). Files with no known comment
+//!   syntax get the provenance on stderr instead.
+//! - **Stdin**: `--config -` reads the configuration from stdin, so an
+//!   editor can preview an unsaved configuration buffer
+//!
+//! This command is a safe, read-only operation that does not modify any files.
+//! It runs phases 1-5 of the pipeline without writing to disk (phase 6).
+//!
+//! ## Example
+//!
+//! ```bash
+//! common-repo render --file .github/workflows/ci.yml
+//!
+//! # From an unsaved buffer, with provenance
+//! cat .common-repo.yaml | common-repo render --config - --file Cargo.toml --provenance
+//! ```
+
+use anyhow::Result;
+use clap::Args;
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use common_repo::cache::RepoCache;
+use common_repo::config;
+use common_repo::defaults::{DEFAULT_CONFIG_FILENAME, OVERRIDES_DIR};
+use common_repo::diagnostics::Diagnostics;
+use common_repo::error;
+use common_repo::filesystem::File;
+use common_repo::messages::Message;
+use common_repo::output;
+use common_repo::phases::context::RunContext;
+use common_repo::phases::orchestrator::{self, BlockState, Stage, LOCAL_MERGE};
+use common_repo::reporting::provenance::{self, Origin};
+use common_repo::repository::RepositoryManager;
+
+use super::completions;
+
+/// Print the final content of one file
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// Path of the file in the output, relative to the working directory.
+    #[arg(short, long, value_name = "PATH")]
+    pub file: PathBuf,
+
+    /// Path to the .common-repo.yaml configuration file, or `-` to read it
+    /// from stdin.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// The root directory for the repository cache.
+    ///
+    /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
+    /// `~/Library/Caches/common-repo` on macOS).
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
+    pub cache_root: Option<PathBuf>,
+
+    /// The working directory for local file operations.
+    ///
+    /// If not provided, it defaults to the current working directory.
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Prepend a comment saying where the content came from.
+    #[arg(long)]
+    pub provenance: bool,
+}
+
+/// The upstream and local versions of the rendered file, as the source
+/// block saw them before local files were merged in.
+#[derive(Debug, Default)]
+struct Sides {
+    composite: Option<File>,
+    local: Option<File>,
+}
+
+/// Records [`Sides`] for one path, just before [`LOCAL_MERGE`].
+struct SnapshotStage {
+    path: PathBuf,
+    sides: Rc<RefCell<Sides>>,
+}
+
+impl Stage for SnapshotStage {
+    fn name(&self) -> &str {
+        "render-snapshot"
+    }
+
+    fn run(&self, state: &mut BlockState<'_>) -> error::Result<()> {
+        if state.is_self_block() {
+            return Ok(());
+        }
+        let local = state
+            .source_fs
+            .as_ref()
+            .and_then(|fs| fs.get_file(&self.path));
+        *self.sides.borrow_mut() = Sides {
+            composite: state.fs.get_file(&self.path).cloned(),
+            local: local.cloned(),
+        };
+        Ok(())
+    }
+}
+
+/// Execute the `render` command.
+pub fn execute(args: RenderArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    let schema = if config_path == Path::new("-") {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        config::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse config from stdin: {}", e))?
+    } else {
+        if !config_path.exists() {
+            return Err(common_repo::suggestions::config_not_found(config_path));
+        }
+        config::from_file(config_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load config from {}: {}",
+                config_path.display(),
+                e
+            )
+        })?
+    };
+
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
+    let repo_manager = RepositoryManager::new(cache_root);
+    let repo_cache = RepoCache::new();
+    let working_dir = args
+        .working_dir
+        .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
+    let path = args
+        .file
+        .strip_prefix(".")
+        .unwrap_or(&args.file)
+        .to_path_buf();
+
+    let sides = Rc::new(RefCell::new(Sides::default()));
+    let mut stages = orchestrator::default_stages();
+    if args.provenance {
+        let at = stages
+            .iter()
+            .position(|stage| stage.name() == LOCAL_MERGE)
+            .unwrap_or(stages.len());
+        stages.insert(
+            at,
+            Box::new(SnapshotStage {
+                path: path.clone(),
+                sides: Rc::clone(&sides),
+            }),
+        );
+    }
+    let ctx = RunContext::new(&repo_manager, &repo_cache, &working_dir).stages(stages);
+
+    // Execute phases 1-5 (skip phase 6 - writing to disk)
+    let mut diagnostics = Diagnostics::new();
+    let final_fs = orchestrator::execute_pull_with_context(&schema, &ctx, &mut diagnostics)
+        .map_err(|e| anyhow::anyhow!("Failed to process configuration: {}", e))?;
+    for diagnostic in &diagnostics {
+        output::warning(Message::PipelineWarning {
+            message: &diagnostic.to_string(),
+        });
+    }
+
+    let file = final_fs.get_file(&path).ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} is not in the output\n\nhint: Run 'common-repo ls' to list the files the configuration produces",
+            path.display()
+        )
+    })?;
+
+    let mut stdout = io::stdout().lock();
+    if args.provenance {
+        let sides = sides.borrow();
+        let origin = if working_dir.join(OVERRIDES_DIR).join(&path).is_file() {
+            Origin::Override
+        } else {
+            Origin::of(
+                sides.composite.as_ref().map(|f| &f.content[..]),
+                sides.local.as_ref().map(|f| &f.content[..]),
+                &file.content,
+            )
+        };
+        if let Some(annotated) = provenance::annotate(&path, &file.content, origin) {
+            stdout.write_all(annotated.as_bytes())?;
+            return Ok(());
+        }
+        for line in provenance::describe(&path, origin) {
+            eprintln!("{}", line);
+        }
+    }
+    stdout.write_all(&file.content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn args(dir: &TempDir, file: &str) -> RenderArgs {
+        RenderArgs {
+            file: PathBuf::from(file),
+            config: dir.path().join(".common-repo.yaml"),
+            cache_root: Some(dir.path().join("cache")),
+            working_dir: Some(dir.path().to_path_buf()),
+            provenance: false,
+        }
+    }
+
+    #[test]
+    fn test_execute_missing_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join(".common-repo.yaml"),
+            "- include: [\"*.txt\"]\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+
+        assert!(execute(args(&dir, "./a.txt")).is_ok());
+        let err = execute(args(&dir, "b.txt")).unwrap_err();
+        assert!(err.to_string().contains("b.txt is not in the output"));
+    }
+}
@@ -8,11 +8,16 @@
 //! - **Repository Tree Visualization**: Displays the inheritance hierarchy of repositories
 //! - **Depth Control**: Supports `--depth` flag to limit tree depth
 //! - **URL and Ref Display**: Shows repository URLs and their references
+//! - **Descriptions**: Shows the `description:` of each `repo:` operation
+//!   next to its repository
+//! - **Graph Export**: `--format dot|mermaid` prints the tree as a Graphviz
+//!   or Mermaid graph, with repositories pinned to conflicting refs marked
+//!   (see `common_repo::reporting::graph`)
 //!
 //! This command is a safe, read-only operation that does not modify any files.
 
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use ptree::{print_tree, TreeItem};
 use std::path::PathBuf;
 
@@ -20,21 +25,37 @@ use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
 use common_repo::output::{emoji, OutputConfig};
 use common_repo::phases::{discover_repos, RepoNode};
+use common_repo::reporting::graph;
 use common_repo::repository::RepositoryManager;
 
+use super::completions;
+
 /// Display the repository inheritance tree
 #[derive(Args, Debug)]
 pub struct TreeArgs {
     /// Path to the .common-repo.yaml configuration file.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// Maximum depth to display in the tree.
@@ -43,6 +64,22 @@ pub struct TreeArgs {
     /// Use 0 to show only the root level, 1 to show one level of inheritance, etc.
     #[arg(long, value_name = "NUM")]
     pub depth: Option<usize>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: TreeFormat,
+}
+
+/// Output formats for the inheritance tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum TreeFormat {
+    /// Indented tree for the terminal
+    #[default]
+    Text,
+    /// Graphviz DOT graph
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
 }
 
 /// Execute the `tree` command.
@@ -56,12 +93,14 @@ pub struct TreeArgs {
 /// * `color_flag` - The value of the global --color flag ("always", "never", or "auto")
 pub fn execute(args: TreeArgs, color_flag: &str) -> Result<()> {
     let out = OutputConfig::from_env_and_flag(color_flag);
-    let config_path = &args.config;
-    println!(
-        "{} Repository inheritance tree for: {}",
-        emoji(&out, "🌳", "[TREE]"),
-        config_path.display()
-    );
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if args.format == TreeFormat::Text {
+        println!(
+            "{} Repository inheritance tree for: {}",
+            emoji(&out, "🌳", "[TREE]"),
+            config_path.display()
+        );
+    }
 
     // Load configuration
     let schema = config::from_file(config_path).map_err(|e| {
@@ -73,9 +112,7 @@ pub fn execute(args: TreeArgs, color_flag: &str) -> Result<()> {
     })?;
 
     // Initialize repository manager
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
 
     let repo_manager = RepositoryManager::new(cache_root);
 
@@ -83,6 +120,18 @@ pub fn execute(args: TreeArgs, color_flag: &str) -> Result<()> {
     let repo_tree = discover_repos(&schema, &repo_manager)
         .map_err(|e| anyhow::anyhow!("Failed to discover repository tree: {}", e))?;
 
+    match args.format {
+        TreeFormat::Dot => {
+            print!("{}", graph::to_dot(&repo_tree, args.depth));
+            return Ok(());
+        }
+        TreeFormat::Mermaid => {
+            print!("{}", graph::to_mermaid(&repo_tree, args.depth));
+            return Ok(());
+        }
+        TreeFormat::Text => {}
+    }
+
     // Build and display tree
     let tree_root = build_tree_node(&repo_tree.root, args.depth.unwrap_or(usize::MAX), 0);
     print_tree(&tree_root).map_err(|e| anyhow::anyhow!("Failed to display tree: {}", e))?;
@@ -92,7 +141,10 @@ pub fn execute(args: TreeArgs, color_flag: &str) -> Result<()> {
 
 /// Build a tree node from a repository node
 fn build_tree_node(repo_node: &RepoNode, max_depth: usize, current_depth: usize) -> TreeNode {
-    let label = format!("{} @ {}", repo_node.url, repo_node.ref_);
+    let mut label = format!("{} @ {}", repo_node.url, repo_node.ref_);
+    if let Some(description) = &repo_node.description {
+        label.push_str(&format!("  # {}", description));
+    }
 
     if current_depth >= max_depth || repo_node.children.is_empty() {
         TreeNode {
@@ -144,6 +196,7 @@ mod tests {
             config: PathBuf::from("/nonexistent/config.yaml"),
             cache_root: None,
             depth: None,
+            format: TreeFormat::Text,
         };
 
         let result = execute(args, "auto");
@@ -154,6 +207,25 @@ mod tests {
             .contains("Failed to load config"));
     }
 
+    #[test]
+    fn test_build_tree_node_shows_description() {
+        let mut root = RepoNode::new("local".to_string(), "HEAD".to_string(), vec![]);
+        let mut child = RepoNode::new(
+            "https://github.com/org/ci".to_string(),
+            "v1".to_string(),
+            vec![],
+        );
+        child.description = Some("Shared CI workflows".to_string());
+        root.add_child(child);
+
+        let node = build_tree_node(&root, usize::MAX, 0);
+        assert_eq!(node.label, "local @ HEAD");
+        assert_eq!(
+            node.children[0].label,
+            "https://github.com/org/ci @ v1  # Shared CI workflows"
+        );
+    }
+
     /// Test tree command with repository configuration.
     /// This test requires network access to fetch repositories.
     #[test]
@@ -181,6 +253,7 @@ mod tests {
             config: config_path,
             cache_root: Some(temp_dir.path().to_path_buf()),
             depth: Some(1),
+            format: TreeFormat::Text,
         };
 
         // This should succeed (though it will print output)
@@ -24,32 +24,64 @@
 //!   `.common-repo.yaml` file in place, updating the `ref` for each repository
 //!   to the selected newer version.
 //!
+//! - **Vendored Repositories**: The repositories of `vendor` operations are
+//!   pinned on purpose and left alone unless `--update-vendored` is given,
+//!   in which case their `ref` is updated the same way. Run `apply`
+//!   afterwards to replace the vendored files and their provenance file.
+//!
+//! - **Changelogs**: For each update, the release notes of the versions it
+//!   pulls in are read from the upstream's changelog file or GitHub releases
+//!   (see [`common_repo::changelog`]) and shown with the update. They are
+//!   added to the GitHub Actions job summary, and `--changelog` writes them
+//!   to a Markdown file, for example to use as a pull request body.
+//!   `--no-changelog` skips them.
+//!
 //! - **Dry Run**: A `--dry-run` mode is available to show what would be updated
 //!   without actually modifying the configuration file.
 
 use anyhow::Result;
 use clap::Args;
+use clap_complete::ArgValueCandidates;
 use std::fs;
 use std::path::PathBuf;
 
+use common_repo::changelog::{self, Changelog};
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
+use common_repo::github;
+use common_repo::messages::Message;
+use common_repo::output;
 use common_repo::repository::RepositoryManager;
 use common_repo::version;
 
+use super::completions;
+
 /// Update repository refs to newer versions
 #[derive(Args, Debug)]
 pub struct UpdateArgs {
     /// Path to the .common-repo.yaml configuration file to update.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// If set, the command will update to the latest compatible versions
@@ -80,8 +112,27 @@ pub struct UpdateArgs {
     /// Examples:
     ///   --filter "github.com/org/*"
     ///   --filter "*/*/ci-*" --filter "*/*/linter-*"
-    #[arg(long, value_name = "GLOB")]
+    #[arg(
+        long,
+        value_name = "GLOB",
+        add = ArgValueCandidates::new(completions::upstream_filters)
+    )]
     pub filter: Vec<String>,
+
+    /// Also update the refs of vendored repositories (`vendor` operations).
+    #[arg(long)]
+    pub update_vendored: bool,
+
+    /// Write the release notes of the versions the updates pull in to FILE,
+    /// as Markdown, for example to use as a pull request body.
+    ///
+    /// Written with `--dry-run` too.
+    #[arg(long, value_name = "FILE", conflicts_with = "no_changelog")]
+    pub changelog: Option<PathBuf>,
+
+    /// Do not read the release notes of the versions the updates pull in.
+    #[arg(long)]
+    pub no_changelog: bool,
 }
 
 /// Execute the `update` command.
@@ -91,8 +142,8 @@ pub struct UpdateArgs {
 /// `.common-repo.yaml` file to update the repository references.
 pub fn execute(args: UpdateArgs) -> Result<()> {
     // Load configuration
-    let config_path = &args.config;
-    println!("Loading configuration from: {}", config_path.display());
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    output::status(Message::LoadingConfig { path: config_path });
 
     let schema = config::from_file(config_path).map_err(|e| {
         anyhow::anyhow!(
@@ -103,32 +154,35 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
     })?;
 
     // Initialize repository manager
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
 
     let repo_manager = RepositoryManager::new(cache_root);
 
     // Show filter status if filters are active
     if !args.filter.is_empty() {
         let patterns = args.filter.join(", ");
-        println!("Filtering upstreams matching: {}", patterns);
+        output::status(Message::FilteringUpstreams {
+            patterns: &patterns,
+        });
     }
 
     // Check for updates (with optional filtering)
-    println!("Checking for repository updates...");
-    let update_result = version::check_updates_filtered(&schema, &repo_manager, &args.filter)?;
+    output::status(Message::CheckingUpdates);
+    let mut update_result = version::check_updates_filtered(&schema, &repo_manager, &args.filter)?;
+    if args.update_vendored {
+        let vendored =
+            version::check_vendored_updates_filtered(&schema, &repo_manager, &args.filter)?;
+        update_result.updates.extend(vendored.updates);
+        update_result.filtered_out_count += vendored.filtered_out_count;
+    }
     let updates = update_result.updates;
     let filtered_out = update_result.filtered_out_count;
 
     if updates.is_empty() {
         if filtered_out > 0 {
-            println!(
-                "✅ No repositories found that match the filter ({} filtered out).",
-                filtered_out
-            );
+            output::status(Message::NoMatchingRepos { filtered_out });
         } else {
-            println!("✅ No repositories found that can be checked for updates.");
+            output::status(Message::NoCheckableRepos);
         }
         return Ok(());
     }
@@ -152,43 +206,66 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
 
     if relevant_updates.is_empty() {
         if args.latest {
-            println!("✅ All repositories are already at the latest versions!");
+            output::status(Message::AllAtLatest);
         } else {
-            println!("✅ No compatible updates available. Use --latest to see breaking changes.");
+            output::status(Message::NoCompatibleUpdates);
         }
         return Ok(());
     }
 
     // Display available updates
-    println!("\n📦 Available Updates:");
-    if filtered_out > 0 {
-        println!(
-            "{} repositories can be updated ({} filtered out)\n",
-            relevant_updates.len(),
-            filtered_out
-        );
-    } else {
-        println!("{} repositories can be updated\n", relevant_updates.len());
-    }
+    output::status(Message::AvailableUpdatesHeader);
+    output::status(Message::UpdatableCount {
+        count: relevant_updates.len(),
+        filtered_out,
+    });
 
+    let mut changelogs = Vec::new();
     for update in &relevant_updates {
-        println!("🔄 {} (current: {})", update.url, update.current_ref);
+        output::status(Message::RepoWithUpdate {
+            url: &update.url,
+            current: &update.current_ref,
+        });
 
         if let Some(latest) = &update.latest_version {
-            println!("   Latest: {}", latest);
+            output::status(Message::LatestVersion { version: latest });
 
             if update.breaking_changes {
-                println!("   ⚠️  BREAKING CHANGES (major version update)");
+                output::status(Message::BreakingChange);
             } else if update.compatible_updates {
-                println!("   ✅ Compatible update");
+                output::status(Message::CompatibleUpdate);
+            }
+
+            if !args.no_changelog {
+                match changelog::fetch(&repo_manager, &update.url, &update.current_ref, latest) {
+                    Ok(Some(changelog)) => {
+                        show_changelog(&changelog);
+                        changelogs.push(changelog);
+                    }
+                    Ok(None) => {}
+                    Err(e) => output::warning(Message::ChangelogUnavailable {
+                        error: &e.to_string(),
+                    }),
+                }
             }
         }
 
-        println!();
+        output::blank_line();
+    }
+
+    if !changelogs.is_empty() {
+        let markdown = changelog_markdown(&changelogs);
+        github::append_summary(&markdown);
+        if let Some(path) = &args.changelog {
+            fs::write(path, &markdown).map_err(|e| {
+                anyhow::anyhow!("Failed to write changelog to {}: {}", path.display(), e)
+            })?;
+            output::status(Message::ChangelogWritten { path });
+        }
     }
 
     if args.dry_run {
-        println!("ℹ️  Dry run mode - no changes will be made.");
+        output::status(Message::UpdateDryRun);
         return Ok(());
     }
 
@@ -200,7 +277,7 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
         let input = input.trim().to_lowercase();
 
         if input != "y" && input != "yes" {
-            println!("Update cancelled.");
+            output::status(Message::UpdateCancelled);
             return Ok(());
         }
     }
@@ -217,7 +294,7 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
         )
     })?;
 
-    println!("\n🔄 Updating repositories...");
+    output::status(Message::UpdatingRepos);
     let mut updated_count = 0;
 
     for update in relevant_updates {
@@ -236,14 +313,12 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
                     &update.current_ref,
                     &latest_version,
                 );
-                println!(
-                    "✅ Updated {} ({} occurrence{}): {} → {}",
-                    update.url,
-                    replacements,
-                    if replacements == 1 { "" } else { "s" },
-                    update.current_ref,
-                    latest_version
-                );
+                output::status(Message::RepoUpdated {
+                    url: &update.url,
+                    occurrences: replacements,
+                    from: &update.current_ref,
+                    to: &latest_version,
+                });
                 updated_count += 1;
             }
         }
@@ -258,19 +333,39 @@ pub fn execute(args: UpdateArgs) -> Result<()> {
             )
         })?;
 
-        println!(
-            "\n✅ Successfully updated {} repositories in {}",
-            updated_count,
-            config_path.display()
-        );
-        println!("💡 Run 'common-repo apply' to apply the updated configuration.");
+        output::status(Message::ReposUpdated {
+            count: updated_count,
+            path: config_path,
+        });
+        output::status(Message::ApplyUpdatedTip);
     } else {
-        println!("\nℹ️  No repositories were updated.");
+        output::status(Message::NoReposUpdated);
     }
 
     Ok(())
 }
 
+/// Print the releases an update pulls in, under the update.
+fn show_changelog(changelog: &Changelog) {
+    output::status(Message::ChangelogHeader {
+        source: &changelog.source.to_string(),
+    });
+    for release in &changelog.releases {
+        output::status(Message::ChangelogRelease {
+            version: &release.version.to_string(),
+        });
+        for line in release.notes.lines() {
+            output::status(Message::ChangelogNote { line });
+        }
+    }
+}
+
+/// The Markdown report of every update's releases.
+fn changelog_markdown(changelogs: &[Changelog]) -> String {
+    let sections: Vec<String> = changelogs.iter().map(Changelog::to_markdown).collect();
+    format!("## Upstream changes\n\n{}", sections.join("\n"))
+}
+
 /// Count how many ref occurrences would be updated for a given repo URL.
 fn update_ref_in_text(content: &str, url: &str, current_ref: &str, _new_ref: &str) -> usize {
     let lines: Vec<&str> = content.lines().collect();
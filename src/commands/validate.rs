@@ -21,23 +21,40 @@ use std::path::PathBuf;
 
 use common_repo::config;
 use common_repo::defaults::DEFAULT_CONFIG_FILENAME;
-use common_repo::output::{emoji, OutputConfig};
+use common_repo::messages::Message;
+use common_repo::outcome::{Failure, Outcome};
+use common_repo::output::{self, OutputConfig};
 use common_repo::phases;
 use common_repo::repository::RepositoryManager;
 
+use super::completions;
+
 /// Validate a .common-repo.yaml configuration file
 #[derive(Args, Debug)]
 pub struct ValidateArgs {
     /// Path to the .common-repo.yaml configuration file to validate.
-    #[arg(short, long, value_name = "FILE", default_value = DEFAULT_CONFIG_FILENAME)]
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
     pub config: PathBuf,
 
     /// The root directory for the repository cache.
     ///
     /// Defaults to the system cache directory (`~/.cache/common-repo` on Linux,
     /// `~/Library/Caches/common-repo` on macOS).
-    /// Can also be set with the `COMMON_REPO_CACHE` environment variable.
-    #[arg(long, value_name = "DIR", env = "COMMON_REPO_CACHE")]
+    /// Can also be set with `--cache-dir`, the `COMMON_REPO_CACHE_DIR`
+    /// environment variable (or the older `COMMON_REPO_CACHE`), or a
+    /// `cache-dir:` entry in the configuration.
+    #[arg(
+        long,
+        visible_alias = "cache-dir",
+        value_name = "DIR",
+        env = "COMMON_REPO_CACHE_DIR"
+    )]
     pub cache_root: Option<PathBuf>,
 
     /// If set, also validate that referenced repositories are accessible.
@@ -59,29 +76,25 @@ pub struct ValidateArgs {
 /// * `color_flag` - The value of the global --color flag ("always", "never", or "auto")
 pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
     let out = OutputConfig::from_env_and_flag(color_flag);
-    let config_path = &args.config;
-    println!(
-        "{} Validating configuration: {}",
-        emoji(&out, "🔍", "[SCAN]"),
-        config_path.display()
-    );
+    let say = |message: Message| output::status(message.render(&out));
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    say(Message::Validating { path: config_path });
 
     // Load and parse configuration
     let schema = match config::from_file(config_path) {
         Ok(schema) => {
-            println!(
-                "{} Configuration file parsed successfully",
-                emoji(&out, "✅", "[OK]")
-            );
+            say(Message::ConfigParsed);
             schema
         }
         Err(e) => {
-            println!(
-                "{} Configuration parsing failed: {}",
-                emoji(&out, "❌", "[ERR]"),
-                e
-            );
-            return Err(anyhow::anyhow!("Configuration parsing failed: {}", e));
+            say(Message::ConfigParseFailed {
+                error: &e.to_string(),
+            });
+            return Err(Failure::new(
+                Outcome::ConfigError,
+                format!("Configuration parsing failed: {}", e),
+            )
+            .into());
         }
     };
 
@@ -89,55 +102,44 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
     let mut has_errors = false;
 
     // Determine cache root (used for both cycle detection and repository checks)
-    let cache_root = args
-        .cache_root
-        .unwrap_or_else(common_repo::defaults::default_cache_root);
+    let cache_root = common_repo::defaults::resolve_cache_root(args.cache_root, config_path);
 
     // Basic configuration statistics
-    println!("\n{} Configuration Summary:", emoji(&out, "📊", "[INFO]"));
-    println!("   Total operations: {}", schema.len());
+    say(Message::ConfigSummaryHeader);
+    say(Message::TotalOperationCount {
+        count: schema.len(),
+    });
 
     let repo_count = schema
         .iter()
         .filter(|op| matches!(op, config::Operation::Repo { .. }))
         .count();
-    println!("   Repository operations: {}", repo_count);
+    say(Message::RepoOperationCount { count: repo_count });
 
     let other_ops = schema.len() - repo_count;
-    println!("   Other operations: {}", other_ops);
+    say(Message::OtherOperationCount { count: other_ops });
 
     // Cycle detection
-    println!(
-        "\n{} Checking for circular dependencies...",
-        emoji(&out, "🔄", "[CHECK]")
-    );
+    say(Message::CheckingCycles);
     let repo_tree_result =
         phases::discover_repos(&schema, &RepositoryManager::new(cache_root.clone()));
     match &repo_tree_result {
         Ok(repo_tree) => {
-            println!(
-                "{} No circular dependencies detected",
-                emoji(&out, "✅", "[OK]")
-            );
-            println!(
-                "   Discovered {} repositories in dependency tree",
-                repo_tree.all_repos.len()
-            );
+            say(Message::NoCycles);
+            say(Message::DiscoveredRepos {
+                count: repo_tree.all_repos.len(),
+            });
         }
         Err(e) => {
             if e.to_string().contains("cycle detected") {
-                println!(
-                    "{} Circular dependency detected: {}",
-                    emoji(&out, "❌", "[ERR]"),
-                    e
-                );
+                say(Message::CycleDetected {
+                    error: &e.to_string(),
+                });
                 has_errors = true;
             } else {
-                println!(
-                    "{} Warning during dependency discovery: {}",
-                    emoji(&out, "⚠️", "[WARN]"),
-                    e
-                );
+                say(Message::DiscoveryWarning {
+                    error: &e.to_string(),
+                });
                 has_warnings = true;
             }
         }
@@ -149,27 +151,19 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
         let provenance_warnings = config::check_merge_provenance(&schema, &upstream_deferred);
 
         if !provenance_warnings.is_empty() {
-            println!(
-                "\n{} Merge operation provenance:",
-                emoji(&out, "🔍", "[SCAN]")
-            );
+            say(Message::ProvenanceHeader);
             for w in &provenance_warnings {
-                println!(
-                    "{} Merge operation {} uses source \"{}\" not declared by any upstream",
-                    emoji(&out, "⚠️", "[WARN]"),
-                    w.operation_index,
-                    w.source_path
-                );
+                say(Message::UndeclaredMergeSource {
+                    operation: w.operation_index,
+                    source: &w.source_path,
+                });
                 has_warnings = true;
             }
         }
     }
 
     // Validate operation-specific patterns
-    println!(
-        "\n{} Validating operation patterns...",
-        emoji(&out, "🔍", "[SCAN]")
-    );
+    say(Message::ValidatingPatterns);
 
     for (idx, operation) in schema.iter().enumerate() {
         match operation {
@@ -177,12 +171,11 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
                 // Validate regex patterns
                 for mapping in &rename.mappings {
                     if let Err(e) = regex::Regex::new(&mapping.from) {
-                        println!(
-                            "{} Invalid regex pattern in rename operation {}: {}",
-                            emoji(&out, "❌", "[ERR]"),
-                            idx,
-                            e
-                        );
+                        say(Message::InvalidRegex {
+                            kind: "rename",
+                            operation: idx,
+                            error: &e.to_string(),
+                        });
                         has_errors = true;
                     }
                 }
@@ -191,12 +184,11 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
                 // Validate glob patterns
                 for pattern in &include.patterns {
                     if let Err(e) = glob::Pattern::new(pattern) {
-                        println!(
-                            "{} Invalid glob pattern in include operation {}: {}",
-                            emoji(&out, "❌", "[ERR]"),
-                            idx,
-                            e
-                        );
+                        say(Message::InvalidGlob {
+                            kind: "include",
+                            operation: idx,
+                            error: &e.to_string(),
+                        });
                         has_errors = true;
                     }
                 }
@@ -205,89 +197,138 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
                 // Validate glob patterns
                 for pattern in &exclude.patterns {
                     if let Err(e) = glob::Pattern::new(pattern) {
-                        println!(
-                            "{} Invalid glob pattern in exclude operation {}: {}",
-                            emoji(&out, "❌", "[ERR]"),
-                            idx,
-                            e
-                        );
+                        say(Message::InvalidGlob {
+                            kind: "exclude",
+                            operation: idx,
+                            error: &e.to_string(),
+                        });
+                        has_errors = true;
+                    }
+                }
+            }
+            config::Operation::Header { header } => {
+                // Validate glob patterns
+                for pattern in &header.patterns {
+                    if let Err(e) = glob::Pattern::new(pattern) {
+                        say(Message::InvalidGlob {
+                            kind: "header",
+                            operation: idx,
+                            error: &e.to_string(),
+                        });
+                        has_errors = true;
+                    }
+                }
+            }
+            config::Operation::YamlFormat { yaml_format } => {
+                // Validate glob patterns
+                for pattern in &yaml_format.patterns {
+                    if let Err(e) = glob::Pattern::new(pattern) {
+                        say(Message::InvalidGlob {
+                            kind: "yaml-format",
+                            operation: idx,
+                            error: &e.to_string(),
+                        });
                         has_errors = true;
                     }
                 }
             }
             // Basic validation - tools array should not be empty
             config::Operation::Tools { tools } if tools.tools.is_empty() => {
-                println!(
-                    "{} Tools operation {} has no tools defined",
-                    emoji(&out, "⚠️", "[WARN]"),
-                    idx
-                );
+                say(Message::EmptyTools { operation: idx });
                 has_warnings = true;
             }
+            // The diff is read from the consumer repo when the pipeline runs
+            config::Operation::Patch { patch } => {
+                let config_dir = config_path.parent().unwrap_or(std::path::Path::new(""));
+                if common_repo::path::validate_relative_path(std::path::Path::new(&patch.patch))
+                    .is_err()
+                {
+                    say(Message::PatchOutsideRepo {
+                        operation: idx,
+                        path: &patch.patch,
+                    });
+                    has_errors = true;
+                } else if !config_dir.join(&patch.patch).is_file() {
+                    say(Message::MissingPatch {
+                        operation: idx,
+                        path: &patch.patch,
+                    });
+                    has_errors = true;
+                }
+            }
+            // Operations from a newer release are skipped when the
+            // pipeline runs; flag them so --strict can reject them. A
+            // misspelled operation fails the pipeline, so it is an error.
+            config::Operation::Unknown(unknown) => {
+                say(Message::UnrecognizedOperation {
+                    operation: idx,
+                    name: &unknown.name,
+                    suggestion: unknown.suggestion(),
+                });
+                if unknown.suggestion().is_some() {
+                    has_errors = true;
+                } else {
+                    has_warnings = true;
+                }
+            }
             // Validate merge operations (source/dest requirements, auto-merge conflicts)
             config::Operation::Yaml { yaml } => {
                 if let Err(e) = yaml.validate() {
-                    println!(
-                        "{} Invalid yaml merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "yaml",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
             config::Operation::Json { json } => {
                 if let Err(e) = json.validate() {
-                    println!(
-                        "{} Invalid json merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "json",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
             config::Operation::Toml { toml } => {
                 if let Err(e) = toml.validate() {
-                    println!(
-                        "{} Invalid toml merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "toml",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
             config::Operation::Ini { ini } => {
                 if let Err(e) = ini.validate() {
-                    println!(
-                        "{} Invalid ini merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "ini",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
             config::Operation::Markdown { markdown } => {
                 if let Err(e) = markdown.validate() {
-                    println!(
-                        "{} Invalid markdown merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "markdown",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
             config::Operation::Xml { xml } => {
                 if let Err(e) = xml.validate() {
-                    println!(
-                        "{} Invalid xml merge operation {}: {}",
-                        emoji(&out, "❌", "[ERR]"),
-                        idx,
-                        e
-                    );
+                    say(Message::InvalidMerge {
+                        kind: "xml",
+                        operation: idx,
+                        error: &e.to_string(),
+                    });
                     has_errors = true;
                 }
             }
@@ -298,36 +339,27 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
     }
 
     if !has_errors {
-        println!(
-            "{} All operation patterns are valid",
-            emoji(&out, "✅", "[OK]")
-        );
+        say(Message::PatternsValid);
     }
 
     // Optional repository accessibility check
     if args.check_repos {
-        println!(
-            "\n{} Checking repository accessibility...",
-            emoji(&out, "🌐", "[NET]")
-        );
+        say(Message::CheckingAccess);
 
         let repo_manager = RepositoryManager::new(cache_root);
 
         for operation in &schema {
             if let config::Operation::Repo { repo } = operation {
-                if repo.is_local() {
-                    print!("   Checking {}... ", repo.url);
+                let target = if repo.is_local() {
+                    repo.url.clone()
                 } else {
-                    print!(
-                        "   Checking {}@{}... ",
-                        repo.url,
-                        repo.r#ref.as_deref().unwrap_or("")
-                    );
-                }
+                    format!("{}@{}", repo.url, repo.r#ref.as_deref().unwrap_or(""))
+                };
+                output::progress(Message::CheckingRepo { target: &target });
 
                 // Local filesystem repos don't need a network check
                 if repo.is_local() {
-                    println!("local, skipping network check");
+                    say(Message::RepoLocal);
                     continue;
                 }
 
@@ -335,21 +367,16 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
                 match repo_manager.list_repository_tags(&repo.url) {
                     Ok(tags) => {
                         if tags.is_empty() {
-                            println!(
-                                "{} accessible but no tags found",
-                                emoji(&out, "⚠️", "[WARN]")
-                            );
+                            say(Message::RepoNoTags);
                             has_warnings = true;
                         } else {
-                            println!(
-                                "{} accessible ({} tags)",
-                                emoji(&out, "✅", "[OK]"),
-                                tags.len()
-                            );
+                            say(Message::RepoAccessible { tags: tags.len() });
                         }
                     }
                     Err(e) => {
-                        println!("{} not accessible: {}", emoji(&out, "❌", "[ERR]"), e);
+                        say(Message::RepoInaccessible {
+                            error: &e.to_string(),
+                        });
                         has_errors = true;
                     }
                 }
@@ -358,40 +385,30 @@ pub fn execute(args: ValidateArgs, color_flag: &str) -> Result<()> {
     }
 
     // Final result
-    println!("\n{} Validation Result:", emoji(&out, "🎯", "[RESULT]"));
+    say(Message::ResultHeader);
 
     if has_errors {
-        println!(
-            "{} Configuration has errors that must be fixed",
-            emoji(&out, "❌", "[ERR]")
-        );
-        return Err(anyhow::anyhow!("Configuration validation failed"));
+        say(Message::HasErrors);
+        return Err(Failure::new(Outcome::ConfigError, "Configuration validation failed").into());
     }
 
     if has_warnings && args.strict {
-        println!(
-            "{} Configuration has warnings (strict mode enabled)",
-            emoji(&out, "❌", "[ERR]")
-        );
-        return Err(anyhow::anyhow!(
-            "Configuration validation failed in strict mode"
-        ));
+        say(Message::HasWarningsStrict);
+        return Err(Failure::new(
+            Outcome::Warnings,
+            "Configuration validation failed in strict mode",
+        )
+        .into());
     }
 
     if has_warnings {
-        println!(
-            "{} Configuration is valid but has warnings",
-            emoji(&out, "⚠️", "[WARN]")
-        );
+        say(Message::ValidWithWarnings);
     } else {
-        println!("{} Configuration is valid", emoji(&out, "✅", "[OK]"));
+        say(Message::Valid);
     }
 
     if !args.check_repos {
-        println!(
-            "\n{} Tip: Use --check-repos to also validate repository accessibility",
-            emoji(&out, "💡", "[TIP]")
-        );
+        say(Message::CheckReposTip);
     }
 
     Ok(())
@@ -0,0 +1,538 @@
+//! # Verify-Upstream Command Implementation
+//!
+//! This module implements the `verify-upstream` subcommand, which is meant to
+//! run in an upstream (source) repository, typically in CI. Where `validate`
+//! checks that a configuration is well-formed, `verify-upstream` checks the
+//! upstream's own filtering operations against the files it actually contains.
+//!
+//! ## Functionality
+//!
+//! The top-level `include`, `exclude`, `rename` and `template` operations are
+//! replayed in order against the repository tree, the same way they are
+//! applied when a consumer inherits the repository. Along the way it reports:
+//!
+//! - **Dead patterns**: `include`, `exclude` and `template` patterns that
+//!   match no files.
+//! - **Dead renames**: `rename` mappings whose regex matches no file path.
+//! - **Undeclared template variables**: `__COMMON_REPO__VAR__` placeholders
+//!   in template files with no matching `template-vars` entry.
+//! - **Stale template declarations**: files listed under `template: files:`
+//!   that are missing, are not text, or whose placeholders no longer match
+//!   the declared checksum (see [`config::placeholder_checksum`]), and,
+//!   once an upstream declares its templates this way, files with
+//!   placeholders that no declaration covers.
+//! - **Invalid patterns**: glob or regex patterns that fail to compile.
+//!
+//! When the repository is a git work tree, only files tracked by git are
+//! considered, since those are the files consumers receive. `self:` blocks
+//! and `repo:` operations are not checked.
+//!
+//! Each finding is printed on its own line, prefixed with `error:` or
+//! `warning:`. Errors make the command fail; warnings do too with `--strict`.
+
+use anyhow::Result;
+use clap::Args;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use common_repo::config::{self, Operation, Schema};
+use common_repo::defaults::{ConfigFiles, DEFAULT_CONFIG_FILENAME};
+use common_repo::filesystem::MemoryFS;
+use common_repo::git::{self, LoadOptions};
+use common_repo::path::regex_rename;
+
+use super::completions;
+
+/// A `__COMMON_REPO__VAR__` placeholder, capturing the variable name.
+pub(crate) static PLACEHOLDER: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"__COMMON_REPO__([A-Za-z_][A-Za-z0-9_]*?)__").expect("valid regex")
+});
+
+/// Verify an upstream repository's own operations against its files
+#[derive(Args, Debug)]
+pub struct VerifyUpstreamArgs {
+    /// Path to the upstream's .common-repo.yaml configuration file.
+    ///
+    /// The directory containing the file is the tree that is checked.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = DEFAULT_CONFIG_FILENAME,
+        add = completions::config_paths()
+    )]
+    pub config: PathBuf,
+
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found in the upstream configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Finding {
+    pub(crate) severity: Severity,
+    /// 1-based index of the operation in the configuration.
+    operation: usize,
+    kind: &'static str,
+    message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}: operation {} ({}): {}",
+            severity, self.operation, self.kind, self.message
+        )
+    }
+}
+
+/// Execute the `verify-upstream` command.
+pub fn execute(args: VerifyUpstreamArgs) -> Result<()> {
+    let config_path = &common_repo::defaults::resolve_config_path(&args.config);
+    if !config_path.exists() {
+        return Err(common_repo::suggestions::config_not_found(config_path));
+    }
+    let schema = config::from_file(config_path)?;
+
+    let root = match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    let tree = load_upstream_tree(&root)?;
+
+    let findings = verify(&schema, &tree);
+    for finding in &findings {
+        println!("{}: {}", config_path.display(), finding);
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    println!(
+        "{} file(s) checked, {} error(s), {} warning(s)",
+        tree.len(),
+        errors,
+        warnings
+    );
+
+    if errors > 0 || (args.strict && warnings > 0) {
+        return Err(anyhow::anyhow!("Upstream verification failed"));
+    }
+    Ok(())
+}
+
+/// Load the files a consumer would receive from the upstream at `root`.
+///
+/// Inside a git work tree only tracked files are kept. Config files are
+/// always dropped because they are never copied to consumers.
+pub(crate) fn load_upstream_tree(root: &Path) -> Result<MemoryFS> {
+    let mut tree = git::load_directory_into_memfs(root, LoadOptions::default())?;
+
+    if let Some(tracked) = git::tracked_files(root)? {
+        for path in tree.list_files() {
+            if !tracked.contains(&path) {
+                tree.remove_file(&path)?;
+            }
+        }
+    }
+    for name in ConfigFiles::load().names() {
+        let _ = tree.remove_file(name);
+    }
+    Ok(tree)
+}
+
+/// The files an upstream exposes, as found by [`replay`].
+pub(crate) struct Replay {
+    /// Problems found along the way.
+    findings: Vec<Finding>,
+    /// The files consumers receive, at the paths they receive them.
+    pub(crate) composite: MemoryFS,
+    /// Template files, mapped to the index of the operation that marked
+    /// them.
+    pub(crate) templates: BTreeMap<PathBuf, usize>,
+}
+
+/// Replay the upstream filtering operations of `schema` against `tree`.
+///
+/// Mirrors how the operations run when a consumer inherits the repository:
+/// `include` copies matching files from `tree` into an initially empty
+/// composite, and `exclude`, `rename` and `template` act on the composite.
+/// A configuration without any `include` exposes the whole tree.
+pub(crate) fn replay(schema: &Schema, tree: &MemoryFS) -> Replay {
+    let mut findings = Vec::new();
+    let has_include = schema
+        .iter()
+        .any(|op| matches!(op, Operation::Include { .. }));
+    let mut composite = if has_include {
+        MemoryFS::new()
+    } else {
+        tree.clone()
+    };
+    let mut templates: BTreeMap<PathBuf, usize> = BTreeMap::new();
+
+    for (index, op) in schema.iter().enumerate() {
+        let mut report = |severity, message| {
+            findings.push(Finding {
+                severity,
+                operation: index + 1,
+                kind: op.kind(),
+                message,
+            })
+        };
+
+        match op {
+            Operation::Include { include, .. } => {
+                for pattern in &include.patterns {
+                    match tree.list_files_glob(pattern) {
+                        Ok(matches) if matches.is_empty() => report(
+                            Severity::Warning,
+                            format!("pattern {:?} matches no files", pattern),
+                        ),
+                        Ok(matches) => {
+                            for path in matches {
+                                if let Some(file) = tree.get_file(&path) {
+                                    let _ = composite.add_file(&path, file.clone());
+                                }
+                            }
+                        }
+                        Err(e) => report(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Operation::Exclude { exclude } => {
+                for pattern in &exclude.patterns {
+                    match composite.list_files_glob(pattern) {
+                        Ok(matches) if matches.is_empty() => report(
+                            Severity::Warning,
+                            format!("pattern {:?} matches no files", pattern),
+                        ),
+                        Ok(matches) => {
+                            for path in matches {
+                                let _ = composite.remove_file(&path);
+                                templates.remove(&path);
+                            }
+                        }
+                        Err(e) => report(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            Operation::Rename { rename } => {
+                for mapping in &rename.mappings {
+                    if let Err(e) = regex::Regex::new(&mapping.from) {
+                        report(Severity::Error, e.to_string());
+                        continue;
+                    }
+                    let mut renames = Vec::new();
+                    let mut matched = false;
+                    for path in composite.list_files() {
+                        let path_str = path.to_string_lossy().into_owned();
+                        if let Ok(Some(new_name)) =
+                            regex_rename(&mapping.from, &mapping.to, &path_str)
+                        {
+                            matched = true;
+                            if new_name != path_str {
+                                renames.push((path, PathBuf::from(new_name)));
+                            }
+                        }
+                    }
+                    if !matched {
+                        report(
+                            Severity::Warning,
+                            format!("rename from {:?} matches no files", mapping.from),
+                        );
+                    }
+                    for (from, to) in renames {
+                        if let Err(e) = composite.rename_file(&from, &to) {
+                            report(Severity::Error, e.to_string());
+                            continue;
+                        }
+                        if let Some(marked_by) = templates.remove(&from) {
+                            templates.insert(to, marked_by);
+                        }
+                    }
+                }
+            }
+            Operation::Template { template } => {
+                for pattern in &template.patterns {
+                    match composite.list_files_glob(pattern) {
+                        Ok(matches) if matches.is_empty() => report(
+                            Severity::Warning,
+                            format!("pattern {:?} matches no files", pattern),
+                        ),
+                        Ok(matches) => templates.extend(matches.into_iter().map(|p| (p, index))),
+                        Err(e) => report(Severity::Error, e.to_string()),
+                    }
+                }
+                for (path, declared) in &template.files {
+                    let Some(file) = composite.get_file(path) else {
+                        report(
+                            Severity::Error,
+                            format!("declared template {} is not in the tree", path),
+                        );
+                        continue;
+                    };
+                    match config::placeholder_checksum(&file.content) {
+                        None => report(
+                            Severity::Error,
+                            format!("declared template {} is not UTF-8 text", path),
+                        ),
+                        Some(actual) if &actual != declared => report(
+                            Severity::Error,
+                            format!(
+                                "placeholders in {} do not match the declaration; it is now {}",
+                                path, actual
+                            ),
+                        ),
+                        Some(_) => {}
+                    }
+                    templates.insert(PathBuf::from(path), index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Replay {
+        findings,
+        composite,
+        templates,
+    }
+}
+
+/// The findings of [`verify`] about `template` operations: dead patterns,
+/// stale declarations and undeclared variables.
+pub(crate) fn template_findings(schema: &Schema, tree: &MemoryFS) -> Vec<Finding> {
+    verify(schema, tree)
+        .into_iter()
+        .filter(|finding| finding.kind == "template")
+        .collect()
+}
+
+/// Check the upstream operations of `schema` against `tree`: [`replay`]
+/// them, then look for template variables nothing declares.
+fn verify(schema: &Schema, tree: &MemoryFS) -> Vec<Finding> {
+    let Replay {
+        mut findings,
+        composite,
+        templates,
+    } = replay(schema, tree);
+    let declared_vars: HashSet<&str> = schema
+        .iter()
+        .filter_map(|op| match op {
+            Operation::TemplateVars { template_vars } => Some(template_vars),
+            _ => None,
+        })
+        .flat_map(|tv| tv.vars.keys().map(String::as_str))
+        .collect();
+
+    // Once templates are declared file by file, every file with
+    // placeholders should be among them.
+    let declared_by = schema.iter().rposition(
+        |op| matches!(op, Operation::Template { template } if !template.files.is_empty()),
+    );
+    if let Some(declared_by) = declared_by {
+        for (path, file) in composite.files() {
+            if templates.contains_key(path) {
+                continue;
+            }
+            if PLACEHOLDER.is_match(&String::from_utf8_lossy(&file.content)) {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    operation: declared_by + 1,
+                    kind: schema[declared_by].kind(),
+                    message: format!(
+                        "{} has placeholders but is not declared; its checksum is {}",
+                        path.display(),
+                        config::placeholder_checksum(&file.content).unwrap_or_default()
+                    ),
+                });
+            }
+        }
+    }
+
+    for (path, &marked_by) in &templates {
+        let Some(file) = composite.get_file(path) else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&file.content);
+        let mut reported = HashSet::new();
+        for capture in PLACEHOLDER.captures_iter(&content) {
+            let name = &capture[1];
+            if declared_vars.contains(name) || !reported.insert(name.to_string()) {
+                continue;
+            }
+            findings.push(Finding {
+                severity: Severity::Warning,
+                operation: marked_by + 1,
+                kind: schema[marked_by].kind(),
+                message: format!(
+                    "{} uses variable {:?} with no template-vars declaration",
+                    path.display(),
+                    name
+                ),
+            });
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(files: &[(&str, &str)]) -> MemoryFS {
+        let mut fs = MemoryFS::new();
+        for (path, content) in files {
+            fs.add_file_string(path, content).unwrap();
+        }
+        fs
+    }
+
+    fn messages(findings: &[Finding]) -> Vec<String> {
+        findings.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_verify_clean_config_has_no_findings() {
+        let schema = config::parse(
+            r#"
+- include: ["templates/**"]
+- rename:
+    - "^templates/(.*)$": "$1"
+"#,
+        )
+        .unwrap();
+        let tree = tree(&[("templates/ci.yml", "on: push"), ("internal/notes", "x")]);
+
+        assert!(verify(&schema, &tree).is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_dead_patterns_in_order() {
+        let schema = config::parse(
+            r#"
+- include: ["templates/**", "configs/**"]
+- exclude: ["internal/**"]
+- rename:
+    - "^docs/(.*)$": "$1"
+"#,
+        )
+        .unwrap();
+        let tree = tree(&[("templates/ci.yml", "on: push"), ("internal/notes", "x")]);
+
+        assert_eq!(
+            messages(&verify(&schema, &tree)),
+            vec![
+                "warning: operation 1 (include): pattern \"configs/**\" matches no files",
+                "warning: operation 2 (exclude): pattern \"internal/**\" matches no files",
+                "warning: operation 3 (rename): rename from \"^docs/(.*)$\" matches no files",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_without_include_checks_whole_tree() {
+        let schema = config::parse("- exclude: [\"internal/**\"]\n").unwrap();
+        let tree = tree(&[("templates/ci.yml", "on: push"), ("internal/notes", "x")]);
+
+        assert!(verify(&schema, &tree).is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_undeclared_template_vars() {
+        let schema = config::parse(
+            r#"
+- include: ["**"]
+- template: ["*.tmpl"]
+- rename:
+    - "^(.*)\\.tmpl$": "$1"
+- template-vars:
+    project: demo
+"#,
+        )
+        .unwrap();
+        let tree = tree(&[(
+            "README.md.tmpl",
+            "# __COMMON_REPO__project__ by __COMMON_REPO__owner__ (__COMMON_REPO__owner__)",
+        )]);
+
+        assert_eq!(
+            messages(&verify(&schema, &tree)),
+            vec![
+                "warning: operation 2 (template): README.md uses variable \"owner\" with no template-vars declaration",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_checks_template_declarations() {
+        let name_checksum = config::placeholder_checksum(b"__COMMON_REPO__name__").unwrap();
+        let schema = config::parse(&format!(
+            r#"
+- template:
+    files:
+      ci.yml: {name_checksum}
+      stale.yml: {name_checksum}
+      gone.yml: {name_checksum}
+- template-vars:
+    name: demo
+    project: demo
+"#
+        ))
+        .unwrap();
+        let tree = tree(&[
+            ("ci.yml", "name: __COMMON_REPO__name__"),
+            ("stale.yml", "name: __COMMON_REPO__project__"),
+            ("missed.yml", "name: __COMMON_REPO__name__"),
+        ]);
+
+        let stale = config::placeholder_checksum(b"__COMMON_REPO__project__").unwrap();
+        assert_eq!(
+            messages(&template_findings(&schema, &tree)),
+            vec![
+                "error: operation 1 (template): declared template gone.yml is not in the tree"
+                    .to_string(),
+                format!(
+                    "error: operation 1 (template): placeholders in stale.yml do not match the declaration; it is now {stale}"
+                ),
+                format!(
+                    "error: operation 1 (template): missed.yml has placeholders but is not declared; its checksum is {name_checksum}"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_reports_invalid_patterns_as_errors() {
+        let schema = config::parse(
+            r#"
+- include: ["[unclosed"]
+- rename:
+    - "(unclosed": "$1"
+"#,
+        )
+        .unwrap();
+        let tree = tree(&[("a.txt", "a")]);
+
+        let findings = verify(&schema, &tree);
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().all(|f| f.severity == Severity::Error));
+    }
+}
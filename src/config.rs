@@ -31,10 +31,25 @@
 //! The parser will first attempt to parse the input using the current format, and
 //! if that fails, it will fall back to the original format parser. This ensures
 //! that older configuration files continue to work without modification.
+//!
+//! Either format can also be wrapped in a versioned document, so that later
+//! schema versions can be told apart from this one:
+//!
+//! ```yaml
+//! apiVersion: common-repo/v1
+//! operations:
+//!   - include: ["**"]
+//! ```
+//!
+//! A document is read as its `operations` list (see [`API_VERSION`]), and
+//! [`crate::migrate`] converts between the two forms.
 
-use crate::error::{Error, Result};
+use crate::error::{Error, ErrorPhase, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
 
 /// Represents a tool requirement with version constraint
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -50,6 +65,69 @@ pub struct Tool {
 pub struct TemplateVars {
     /// Variable name to value mapping
     pub vars: HashMap<String, String>,
+    /// Variables whose values are read from a secret source when the
+    /// variables are collected. Only the consumer's own configuration may
+    /// declare them.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, SecretSource>,
+}
+
+/// Where a secret template variable's value is read from
+///
+/// Exactly one of the `from_*` fields is set:
+/// ```yaml
+/// - template-vars:
+///     project: my-project
+///     npm_token: { from_env_file: .env }
+///     db_password: { from_sops: secrets.enc.yaml, key: database.password }
+///     api_token: { from_command: "vault kv get -field=token secret/ci" }
+/// ```
+///
+/// The values are masked in output; see [`crate::secrets`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretSource {
+    /// A dotenv file to read the variable from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_env_file: Option<String>,
+    /// A SOPS-encrypted YAML or JSON file, decrypted with `sops --decrypt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_sops: Option<String>,
+    /// A shell command whose output, without its trailing newline, is the
+    /// value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_command: Option<String>,
+    /// The name in the env file, or the path in the SOPS file in the merge
+    /// operators' path syntax. Defaults to the variable's name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl SecretSource {
+    /// Check that exactly one source is set, and that `key` goes with a
+    /// file.
+    pub fn validate(&self, name: &str) -> Result<()> {
+        let sources = [&self.from_env_file, &self.from_sops, &self.from_command];
+        if sources.iter().filter(|source| source.is_some()).count() != 1 {
+            return Err(Error::ConfigParse {
+                message: format!(
+                    "Secret template variable '{}' needs exactly one of 'from_env_file', \
+                     'from_sops' or 'from_command'",
+                    name
+                ),
+                hint: None,
+            });
+        }
+        if self.from_command.is_some() && self.key.is_some() {
+            return Err(Error::ConfigParse {
+                message: format!(
+                    "Secret template variable '{}' sets 'key' with 'from_command'",
+                    name
+                ),
+                hint: Some("'key' names the value to read from an env or SOPS file".to_string()),
+            });
+        }
+        Ok(())
+    }
 }
 
 /// Self operator configuration
@@ -86,6 +164,376 @@ pub struct RepoOp {
     /// content.
     #[serde(default)]
     pub with: Vec<Operation>,
+    /// Ids of upstream operations to drop before the upstream's config is
+    /// applied. An upstream gives an operation an id with an `id:` key next
+    /// to the operation; see [`parse_disabling`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// Signers the fetched tag or commit must be signed by. When set, apply
+    /// fails unless the signature verifies against one of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<VerifyOp>,
+    /// What to do when the repository cannot be fetched. `None` uses the
+    /// config's `- on-fetch-error:` entry, or [`OnFetchError::UseCache`].
+    #[serde(
+        default,
+        rename = "on-fetch-error",
+        alias = "on_fetch_error",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub on_fetch_error: Option<OnFetchError>,
+    /// Fetch the repository as a partial clone that downloads only the files
+    /// the `include` operations of `with:` can match, for a large upstream
+    /// of which little is used. Their patterns must name files as the
+    /// repository stores them, before its own operations rename anything.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// The index that `ref` is looked up in, so that an alias such as
+    /// `stable` resolves to the ref the index currently maps it to. `None`
+    /// uses the config's `- ref-index:` entry, if any.
+    #[serde(
+        default,
+        rename = "ref-index",
+        alias = "ref_index",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ref_index: Option<RefIndex>,
+}
+
+/// Where the ref aliases of `repo:` operations are published
+///
+/// An index is a repository, often one per organization, holding a YAML
+/// file that maps each upstream URL to its aliases:
+///
+/// ```yaml
+/// https://github.com/acme/ci-config:
+///   stable: v2.3.1
+///   next: v3.0.0-rc.1
+/// ```
+///
+/// A `repo:` operation whose `ref` is an alias there inherits the ref it
+/// maps to, so a platform team promotes a version to every consumer by
+/// updating the index. A ref the index does not list is used as written.
+/// See [`crate::ref_index`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RefIndex {
+    /// URL of the index repository, or a local path.
+    pub url: String,
+    /// Branch or tag of the index to read. Defaults to `main`.
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<String>,
+    /// Path of the index file in the repository. Defaults to
+    /// [`crate::ref_index::DEFAULT_INDEX_FILE`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// What an upstream requires of the configurations that inherit from it
+///
+/// Declared with a `requires:` block in the upstream's configuration, which
+/// may also carry the `version` constraint on common-repo itself:
+///
+/// ```yaml
+/// - requires:
+///     version: ">=0.30"
+///     vars:
+///       - project_name
+///       - name: license
+///         description: SPDX identifier of the project's license
+///         default: MIT
+///     files: [Cargo.toml]
+///     tools:
+///       - name: rustc
+///         version: ">=1.70"
+/// ```
+///
+/// A consumer that does not meet them fails before anything is processed,
+/// with every unmet requirement listed, unless `apply` can ask for the
+/// missing variables. The block is ignored in the configuration being
+/// applied, which is nobody's upstream.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConsumerRequirements {
+    /// Template variables the consumer must set, with `template-vars` or
+    /// `--var`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<RequiredVar>,
+    /// Files that must exist in the consumer's directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// Tools that must be installed, as in a `tools` operation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+}
+
+/// A template variable an upstream requires of its consumers
+///
+/// Written as just the name, or as a mapping with a `description` and a
+/// suggested `default`, which `apply` shows when it asks for the value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RequiredVarRepr", into = "RequiredVarRepr")]
+pub struct RequiredVar {
+    /// Name of the variable.
+    pub name: String,
+    /// What the variable is for.
+    pub description: Option<String>,
+    /// The value to suggest.
+    pub default: Option<String>,
+}
+
+/// The two YAML forms of a [`RequiredVar`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RequiredVarRepr {
+    Name(String),
+    Described(DescribedVar),
+}
+
+/// The mapping form of a [`RequiredVar`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DescribedVar {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+}
+
+impl From<RequiredVarRepr> for RequiredVar {
+    fn from(repr: RequiredVarRepr) -> Self {
+        match repr {
+            RequiredVarRepr::Name(name) => RequiredVar {
+                name,
+                description: None,
+                default: None,
+            },
+            RequiredVarRepr::Described(DescribedVar {
+                name,
+                description,
+                default,
+            }) => RequiredVar {
+                name,
+                description,
+                default,
+            },
+        }
+    }
+}
+
+impl From<RequiredVar> for RequiredVarRepr {
+    fn from(var: RequiredVar) -> Self {
+        if var.description.is_none() && var.default.is_none() {
+            RequiredVarRepr::Name(var.name)
+        } else {
+            RequiredVarRepr::Described(DescribedVar {
+                name: var.name,
+                description: var.description,
+                default: var.default,
+            })
+        }
+    }
+}
+
+impl ConsumerRequirements {
+    /// Add the requirements of another block.
+    pub fn extend(&mut self, other: ConsumerRequirements) {
+        self.vars.extend(other.vars);
+        self.files.extend(other.files);
+        self.tools.extend(other.tools);
+    }
+}
+
+/// Expected local deviations that `diff` should not report as drift
+///
+/// ```yaml
+/// - diff-ignore:
+///     paths: ["docs/local/**"]     # never compared
+///     lines: ['^\[!\[.*\]\(']      # regexes; matching lines are skipped
+///     rules: [yaml-quoting]        # compare YAML files by value
+/// ```
+///
+/// A plain list is taken as `paths`. The entry is not an operation and is
+/// left out of the parsed schema, and in upstream configs it is ignored;
+/// see [`diff_ignore`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiffIgnore {
+    /// Glob patterns of files whose differences are never reported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Regular expressions; lines matching any of them are left out when
+    /// text files are compared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<String>,
+    /// Per-format differences to overlook.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<DiffIgnoreRule>,
+}
+
+/// A per-format rule of a `diff-ignore:` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffIgnoreRule {
+    /// YAML files that parse to the same value are equal, whatever their
+    /// quoting, indentation or comments.
+    YamlQuoting,
+    /// JSON files that parse to the same value are equal, whatever their
+    /// whitespace.
+    JsonFormatting,
+    /// Text files that differ only in trailing whitespace are equal.
+    TrailingWhitespace,
+}
+
+/// How `apply` guards the files it manages against direct edits
+///
+/// ```yaml
+/// - protect: read-only              # or pre-commit, or [read-only, pre-commit]
+/// ```
+///
+/// The entry is not an operation and is left out of the parsed schema, and
+/// in upstream configs it is ignored; see [`protect`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Protection {
+    /// Write managed files read-only, and overwrite them when they already
+    /// are.
+    pub read_only: bool,
+    /// Install a git pre-commit hook that rejects commits changing managed
+    /// files.
+    pub pre_commit: bool,
+}
+
+/// Where the files a configuration produces may be written, a boundary
+/// no upstream can cross
+///
+/// ```yaml
+/// - allow-paths: [".github/**", "docs/**"]   # nothing outside these
+/// - deny-paths: [".github/CODEOWNERS"]      # and never these
+/// ```
+///
+/// The patterns match paths in the output, after every rename, so an
+/// upstream cannot rename its way out. The entries are not operations and
+/// are left out of the parsed schema, and in upstream configs they are
+/// ignored; see [`path_scope`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathScope {
+    /// Glob patterns of the paths that may be written. Empty allows every
+    /// path.
+    pub allow: Vec<String>,
+    /// Glob patterns of the paths that may never be written.
+    pub deny: Vec<String>,
+}
+
+impl PathScope {
+    /// Whether the scope allows every path.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether a file may be written at `path`.
+    pub fn allows(&self, path: &Path) -> Result<bool> {
+        use crate::path::PatternSet;
+
+        if !self.allow.is_empty() && !PatternSet::cached(&self.allow)?.is_match(path) {
+            return Ok(false);
+        }
+        Ok(self.deny.is_empty() || !PatternSet::cached(&self.deny)?.is_match(path))
+    }
+}
+
+/// One mode of a `protect:` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ProtectionMode {
+    ReadOnly,
+    PreCommit,
+}
+
+/// A deprecation notice from an upstream configuration
+///
+/// See [`parse_upstream`] for where upstreams write them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Deprecation {
+    /// What consumers should know, usually what to migrate to and when.
+    pub message: String,
+    /// Where the replacement lives.
+    #[serde(
+        default,
+        rename = "replacement-url",
+        alias = "replacement_url",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replacement_url: Option<String>,
+    /// The deprecated operation, by id or else by name, or `None` when the
+    /// whole configuration is deprecated.
+    #[serde(skip)]
+    pub operation: Option<String>,
+}
+
+/// What a run does when an upstream repository cannot be fetched
+///
+/// ```yaml
+/// - on-fetch-error: fail        # default for the repos in this file
+/// - repo:
+///     url: https://github.com/org/optional-extras
+///     ref: main
+///     on-fetch-error: skip      # this upstream is not critical
+/// ```
+///
+/// Only failures to fetch the repository are affected; an invalid upstream
+/// config or a failed signature check always fails the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnFetchError {
+    /// Abort the run.
+    Fail,
+    /// Leave the repository, and everything it inherits from, out of the
+    /// run with a warning.
+    Skip,
+    /// Use the cached copy of the repository with a warning, and abort the
+    /// run when there is none (default, matches pre-feature behavior).
+    #[default]
+    UseCache,
+}
+
+/// Trusted signers for a repo operation's `verify:` block
+///
+/// ```yaml
+/// - repo:
+///     url: https://github.com/org/ci-config
+///     ref: v1.2.0
+///     verify:
+///       gpg: ["3AA5C34371567BD2..."]
+///       sigstore:
+///         - identity: release@example.com
+///           issuer: https://accounts.google.com
+/// ```
+///
+/// An annotated tag must carry the signature itself; otherwise the checked
+/// out commit must be signed. A signature from any listed signer is
+/// accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyOp {
+    /// Full fingerprints of GPG keys allowed to sign. The keys must be in
+    /// the local GPG keyring.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gpg: Vec<String>,
+    /// Sigstore identities allowed to sign with gitsign.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sigstore: Vec<SigstoreIdentity>,
+}
+
+/// A keyless signing identity, as recorded in a Sigstore certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigstoreIdentity {
+    /// Certificate identity, usually an email address or a CI workflow URL.
+    pub identity: String,
+    /// OIDC issuer that vouched for the identity.
+    pub issuer: String,
 }
 
 impl RepoOp {
@@ -122,6 +570,9 @@ pub struct IncludeOp {
     pub patterns: Vec<String>,
     /// Behavior when the destination of an included file exists locally.
     pub if_exists: IfExists,
+    /// How relative links in included Markdown files are rewritten. Only
+    /// [`LinkRewrite::Upstream`] applies to an include.
+    pub rewrite_links: Option<LinkRewrite>,
 }
 
 impl<'de> serde::Deserialize<'de> for IncludeOp {
@@ -133,6 +584,7 @@ impl<'de> serde::Deserialize<'de> for IncludeOp {
         Ok(IncludeOp {
             patterns,
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         })
     }
 }
@@ -168,11 +620,96 @@ pub struct ExcludeOp {
 /// - template:
 ///     - "templates/**"
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(transparent)]
+///
+/// or from an explicit list of template files, each with the
+/// [`placeholder_checksum`] of its content, which `verify-upstream` and
+/// `check --templates` compare against the files:
+/// ```yaml
+/// - template:
+///     files:
+///       ci.yml: sha256:0c5f...
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "TemplateOpRepr", into = "TemplateOpRepr")]
 pub struct TemplateOp {
     /// A list of glob patterns specifying the files to mark as templates.
+    /// Only matching files that contain a placeholder are marked.
     pub patterns: Vec<String>,
+    /// Files marked as templates whatever their content, mapped to the
+    /// declared checksum of their placeholders.
+    pub files: BTreeMap<String, String>,
+}
+
+impl TemplateOp {
+    /// The patterns, then the declared files, for display.
+    pub fn targets(&self) -> Vec<&str> {
+        self.patterns
+            .iter()
+            .chain(self.files.keys())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// The two YAML forms of a [`TemplateOp`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum TemplateOpRepr {
+    Patterns(Vec<String>),
+    Files(TemplateFiles),
+}
+
+/// The `files:` form of a [`TemplateOp`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFiles {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    patterns: Vec<String>,
+    files: BTreeMap<String, String>,
+}
+
+impl From<TemplateOpRepr> for TemplateOp {
+    fn from(repr: TemplateOpRepr) -> Self {
+        match repr {
+            TemplateOpRepr::Patterns(patterns) => TemplateOp {
+                patterns,
+                files: BTreeMap::new(),
+            },
+            TemplateOpRepr::Files(TemplateFiles { patterns, files }) => {
+                TemplateOp { patterns, files }
+            }
+        }
+    }
+}
+
+impl From<TemplateOp> for TemplateOpRepr {
+    fn from(op: TemplateOp) -> Self {
+        if op.files.is_empty() {
+            TemplateOpRepr::Patterns(op.patterns)
+        } else {
+            TemplateOpRepr::Files(TemplateFiles {
+                patterns: op.patterns,
+                files: op.files,
+            })
+        }
+    }
+}
+
+/// The checksum of the placeholders in a template file, as declared in
+/// [`TemplateOp::files`]: `sha256:` and the digest of the placeholder
+/// variable names in order of appearance, one per line.
+///
+/// Edits that leave the placeholders alone keep the checksum. Returns
+/// `None` when `content` is not UTF-8.
+pub fn placeholder_checksum(content: &[u8]) -> Option<String> {
+    let content = std::str::from_utf8(content).ok()?;
+    let names: String = crate::operators::template::variables(content)
+        .map(|name| format!("{}\n", name))
+        .collect();
+    Some(format!(
+        "sha256:{}",
+        crate::http::sha256_hex(names.as_bytes())
+    ))
 }
 
 /// Rename operation mapping
@@ -190,6 +727,14 @@ pub struct RenameMapping {
 pub struct RenameOp {
     /// List of rename mappings
     pub mappings: Vec<RenameMapping>,
+    /// How relative links in renamed Markdown files, and links to them,
+    /// are rewritten.
+    #[serde(
+        default,
+        rename = "rewrite-links",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rewrite_links: Option<LinkRewrite>,
 }
 
 /// Tools operator configuration
@@ -199,103 +744,583 @@ pub struct ToolsOp {
     pub tools: Vec<Tool>,
 }
 
-////// CONVERSION IMPLEMENTATIONS //////
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
-#[serde(rename_all = "lowercase")]
-pub enum ArrayMergeMode {
-    #[default]
-    Replace,
-    Append,
-    #[serde(rename = "append_unique")]
-    AppendUnique,
+/// Patch operator configuration
+///
+/// Applies a unified diff stored in the consumer repository to a file in the
+/// composed output, after everything else has been merged:
+/// ```yaml
+/// - patch:
+///     file: README.md
+///     patch: patches/readme.diff
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchOp {
+    /// The file to patch, relative to the output root.
+    pub file: String,
+    /// The unified diff to apply, relative to the consumer's working
+    /// directory.
+    pub patch: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
-#[serde(rename_all = "lowercase")]
-pub enum InsertPosition {
-    Start,
-    #[default]
-    End,
+/// Header operator configuration
+///
+/// Prepends a license or copyright header, written as a comment in the
+/// syntax of each file's type, to the matching files:
+/// ```yaml
+/// - header:
+///     patterns: ["src/**/*.rs"]
+///     template: |
+///       Copyright 2026 Example Corp.
+///       SPDX-License-Identifier: Apache-2.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderOp {
+    /// Glob patterns selecting the files to add the header to.
+    pub patterns: Vec<String>,
+    /// The header text, without comment markers.
+    pub template: String,
 }
 
-/// Behavior when an `include`-tagged file's destination already exists
-/// in the consumer's working tree at write time.
+/// Format operator configuration
 ///
-/// `Overwrite` is the default and matches pre-`if-exists:` behavior:
-/// the include result clobbers any local content at the destination.
-/// `Preserve` skips the write so consumer-authored content survives.
-/// `Error` fails the propagation with a clear message naming the path.
+/// Normalizes the formatting of propagated text files to the consumer's
+/// conventions once the output is composed:
+/// ```yaml
+/// - format: editorconfig
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FormatOp {
+    /// Apply the indentation, line ending, final newline and trailing
+    /// whitespace rules of the output's `.editorconfig`.
+    #[serde(rename = "editorconfig")]
+    EditorConfig,
+}
+
+/// Quotes used for YAML strings that cannot be written plain
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
-pub enum IfExists {
-    /// Overwrite the destination (default, matches pre-feature behavior).
+pub enum QuoteStyle {
+    /// `'single'` quotes, falling back to double quotes for strings with
+    /// characters that need escaping.
     #[default]
-    Overwrite,
-    /// Skip the write when the destination exists in the local working tree.
-    Preserve,
-    /// Fail propagation when the destination exists in the local working tree.
-    Error,
+    Single,
+    /// `"double"` quotes.
+    Double,
 }
 
-/// YAML merge operator configuration
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct YamlMergeOp {
-    /// Source fragment file (required unless auto_merge is set)
-    #[serde(default)]
-    pub source: Option<String>,
-    /// Destination file to merge into (required unless auto_merge is set)
-    #[serde(default)]
-    pub dest: Option<String>,
-    /// Path within the destination to merge at (optional - merges at root if omitted)
+/// YAML format operator configuration
+///
+/// Rewrites the matching YAML files in a canonical layout, once merges have
+/// run:
+/// ```yaml
+/// - yaml-format:
+///     patterns: [".github/workflows/*.yml"]
+///     sort-keys: ["env", "jobs.*.env"]
+///     indent: 2
+///     quote: double
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YamlFormatOp {
+    /// Glob patterns selecting the files to format.
+    pub patterns: Vec<String>,
+    /// Paths of mappings whose keys are sorted, in the path syntax of the
+    /// merge operators. An empty path is the document root and a `*`
+    /// segment matches every key or item.
+    #[serde(default, rename = "sort-keys")]
+    pub sort_keys: Vec<String>,
+    /// Spaces per indentation level.
+    #[serde(default = "default_yaml_indent")]
+    pub indent: usize,
+    /// Quotes for strings that cannot be written plain.
     #[serde(default)]
+    pub quote: QuoteStyle,
+}
+
+/// Get the default indentation width for `yaml-format` operations
+pub fn default_yaml_indent() -> usize {
+    2
+}
+
+/// Extract operator configuration
+///
+/// Copies one part of a YAML, JSON or Markdown file into a file of its own,
+/// the inverse of a merge. The format follows the source's extension:
+/// ```yaml
+/// - extract:
+///     source: .github/workflows/shared.yml
+///     dest: .github/workflows/lint.yml
+///     path: jobs.lint
+///     keep-path: true
+/// - extract:
+///     source: docs/HANDBOOK.md
+///     dest: docs/releasing.md
+///     section: Releasing
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractOp {
+    /// The file to extract from.
+    pub source: String,
+    /// The file to write the extracted part to. An existing file is
+    /// replaced.
+    pub dest: String,
+    /// For YAML and JSON, the path of the value to extract, in the path
+    /// syntax of the merge operators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
-    /// Array merge mode: replace, append, or append_unique
-    #[serde(default, rename = "array_mode")]
-    pub array_mode: ArrayMergeMode,
-    /// Position for array insertion (start or end)
-    #[serde(default)]
-    pub position: InsertPosition,
-    /// Mark this operation as deferred (applies when repo is used as an upstream)
-    #[serde(default)]
-    pub defer: Option<bool>,
-    /// Shorthand: sets source=dest to this value and implies defer=true
-    #[serde(default, rename = "auto-merge")]
-    pub auto_merge: Option<String>,
+    /// For Markdown, the heading of the section to extract, such as
+    /// `Releasing`, or `## Releasing` to give its level too. The section runs
+    /// to the next heading of the same or a higher level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// Heading level of `section` (1-6).
+    #[serde(default = "default_header_level")]
+    pub level: u8,
+    /// For YAML and JSON, nest the value under the keys of `path` in the
+    /// destination instead of writing it at the root.
+    #[serde(default, rename = "keep-path")]
+    pub keep_path: bool,
 }
 
-impl YamlMergeOp {
-    /// Validate the merge operation configuration
+impl ExtractOp {
+    /// Check that the operation names either a path or a section, not
+    /// both.
     pub fn validate(&self) -> Result<()> {
-        // If auto_merge is set, source/dest must not be set
-        if self.auto_merge.is_some() && (self.source.is_some() || self.dest.is_some()) {
-            return Err(Error::ConfigParse {
-                message: "Cannot use auto-merge with explicit source or dest".to_string(),
-                hint: Some(
-                    "Use either 'auto-merge: file.yaml' OR 'source' and 'dest', not both"
-                        .to_string(),
+        match (&self.path, &self.section) {
+            (Some(_), Some(_)) | (None, None) => Err(Error::ConfigParse {
+                message: format!(
+                    "Extract from '{}' needs exactly one of 'path' or 'section'",
+                    self.source
                 ),
-            });
-        }
-        // If auto_merge is not set, both source and dest are required
-        if self.auto_merge.is_none() && (self.source.is_none() || self.dest.is_none()) {
-            return Err(Error::ConfigParse {
-                message: "YAML merge requires source and dest (or use auto-merge)".to_string(),
                 hint: Some(
-                    "Add 'source:' and 'dest:' fields, or use 'auto-merge:' for same-name files"
-                        .to_string(),
+                    "Use 'path:' for YAML and JSON files, 'section:' for Markdown".to_string(),
                 ),
-            });
+            }),
+            (None, Some(section)) if !(1..=6).contains(&self.level) => Err(Error::ConfigParse {
+                message: format!(
+                    "Extract of section '{}' has heading level {}",
+                    section, self.level
+                ),
+                hint: Some("Use a level from 1 to 6".to_string()),
+            }),
+            _ => Ok(()),
         }
-        Ok(())
-    }
-
-    /// Get the effective source path (from auto_merge or source field)
-    pub fn get_source(&self) -> Option<&str> {
-        self.auto_merge.as_deref().or(self.source.as_deref())
     }
+}
 
-    /// Get the effective dest path (from auto_merge or dest field)
+/// Assert operator configuration
+///
+/// States what the composed output must hold, checked once everything else
+/// has run, so an upstream can enforce a policy on the repositories that
+/// inherit from it:
+/// ```yaml
+/// - assert:
+///     path: LICENSE
+///     contains: Apache License
+/// - assert:
+///     path: .travis.yml
+///     absent: true
+///     message: CI runs on GitHub Actions
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssertOp {
+    /// Glob pattern selecting the files the assertion is about, relative
+    /// to the output root.
+    pub path: String,
+    /// Text every matching file must contain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    /// Regular expression every matching file must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+    /// Require that no file matches `path`, rather than at least one.
+    #[serde(default)]
+    pub absent: bool,
+    /// Why the assertion exists, shown when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl AssertOp {
+    /// Check that the path and regex compile, and that `absent` is not
+    /// combined with content checks.
+    pub fn validate(&self) -> Result<()> {
+        crate::path::PatternSet::cached(std::slice::from_ref(&self.path))?;
+        if let Some(pattern) = &self.matches {
+            regex::Regex::new(pattern)?;
+        }
+        if self.absent && (self.contains.is_some() || self.matches.is_some()) {
+            return Err(Error::ConfigParse {
+                message: format!(
+                    "Assertion on '{}' is 'absent' but also checks content",
+                    self.path
+                ),
+                hint: Some(
+                    "Use either 'absent: true' or 'contains:'/'matches:', not both".to_string(),
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// File operator configuration
+///
+/// Downloads a single file into the composite filesystem:
+/// ```yaml
+/// - file:
+///     url: https://example.com/policies/CODE_OF_CONDUCT.md
+///     sha256: 5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOp {
+    /// The URL to download. Must be `https://`, or `http://` with `sha256`.
+    pub url: String,
+    /// Expected SHA-256 digest of the content, as hex. The download fails
+    /// when the content does not match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Path to write the file to. Defaults to the last segment of the URL
+    /// path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dest: Option<String>,
+}
+
+impl FileOp {
+    /// Check the URL scheme and the digest format.
+    pub fn validate(&self) -> Result<()> {
+        let url = url::Url::parse(&self.url).map_err(|e| Error::ConfigParse {
+            message: format!("Invalid file URL '{}': {}", self.url, e),
+            hint: None,
+        })?;
+        match url.scheme() {
+            "https" => {}
+            "http" if self.sha256.is_some() => {}
+            "http" => {
+                return Err(Error::ConfigParse {
+                    message: format!("File URL '{}' uses http without a sha256", self.url),
+                    hint: Some("Use https, or pin the content with 'sha256:'".to_string()),
+                })
+            }
+            scheme => {
+                return Err(Error::ConfigParse {
+                    message: format!("Unsupported file URL scheme '{}': {}", scheme, self.url),
+                    hint: Some("Use an https:// URL".to_string()),
+                })
+            }
+        }
+        if let Some(sha256) = &self.sha256 {
+            if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(Error::ConfigParse {
+                    message: format!("Invalid sha256 '{}' for {}", sha256, self.url),
+                    hint: Some("Use the 64-character hex digest, e.g. from sha256sum".to_string()),
+                });
+            }
+        }
+        self.dest_path().map(|_| ())
+    }
+
+    /// The path the file is written to: `dest`, or the last segment of the
+    /// URL path.
+    pub fn dest_path(&self) -> Result<String> {
+        if let Some(dest) = &self.dest {
+            return Ok(dest.clone());
+        }
+        url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| {
+                url.path_segments()
+                    .and_then(|mut segments| segments.next_back().map(str::to_string))
+            })
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| Error::ConfigParse {
+                message: format!("Cannot derive a file name from '{}'", self.url),
+                hint: Some("Add 'dest: <path>' to the file operation".to_string()),
+            })
+    }
+}
+
+/// Vendor operator configuration
+///
+/// Copies every file of a repository, unfiltered, under a directory of the
+/// consumer, with a provenance file listing where they came from:
+/// ```yaml
+/// - vendor:
+///     url: https://github.com/acme/shared-scripts.git
+///     ref: v1.4.0
+///     path: scripts
+///     dest: tools/scripts
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VendorOp {
+    /// The URL of the repository to vendor.
+    pub url: String,
+    /// The Git reference (branch, tag, or commit hash) to vendor.
+    #[serde(rename = "ref")]
+    pub r#ref: String,
+    /// An optional sub-path within the repository. Only the files under it
+    /// are vendored, relative to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Name of the vendored directory. Defaults to the last segment of the
+    /// URL, without `.git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Directory to place the files under. Defaults to `vendor/<name>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dest: Option<String>,
+}
+
+impl VendorOp {
+    /// Name of the provenance file written in the vendored directory.
+    pub const PROVENANCE_FILE: &'static str = ".vendored.yaml";
+
+    /// Check that a name can be derived and that the directory stays inside
+    /// the consumer.
+    pub fn validate(&self) -> Result<()> {
+        if self.r#ref.trim().is_empty() {
+            return Err(Error::ConfigParse {
+                message: format!("Vendor operation for '{}' has an empty ref", self.url),
+                hint: Some("Pin the vendored code with 'ref: <tag or commit>'".to_string()),
+            });
+        }
+        let dest = self.dest_dir()?;
+        let path = std::path::Path::new(&dest);
+        if dest.is_empty()
+            || path.is_absolute()
+            || path
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            return Err(Error::ConfigParse {
+                message: format!("Invalid vendor directory '{}' for {}", dest, self.url),
+                hint: Some(
+                    "Use a relative path inside the repository, e.g. vendor/<name>".to_string(),
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// The name of the vendored directory: `name`, or the last segment of
+    /// the URL without `.git`.
+    pub fn name(&self) -> Result<String> {
+        if let Some(name) = &self.name {
+            return Ok(name.clone());
+        }
+        self.url
+            .trim_end_matches('/')
+            .rsplit(['/', ':'])
+            .next()
+            .map(|segment| segment.trim_end_matches(".git"))
+            .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+            .map(str::to_string)
+            .ok_or_else(|| Error::ConfigParse {
+                message: format!("Cannot derive a vendor name from '{}'", self.url),
+                hint: Some("Add 'name: <name>' to the vendor operation".to_string()),
+            })
+    }
+
+    /// The directory the files are placed under: `dest`, or `vendor/<name>`.
+    pub fn dest_dir(&self) -> Result<String> {
+        match &self.dest {
+            Some(dest) => Ok(dest.trim_end_matches('/').to_string()),
+            None => Ok(format!("vendor/{}", self.name()?)),
+        }
+    }
+}
+
+////// CONVERSION IMPLEMENTATIONS //////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrayMergeMode {
+    #[default]
+    Replace,
+    Append,
+    #[serde(rename = "append_unique")]
+    AppendUnique,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertPosition {
+    Start,
+    #[default]
+    End,
+}
+
+/// Where a `yaml` merge puts what it adds
+///
+/// Written as `start`, `end`, `after:<key>` or an index. New keys of the
+/// mapping merged into go at that position among its existing keys, and
+/// appended sequence items at that position in the sequence. For a
+/// sequence, `after:<key>` names the item that is the string `<key>`, or
+/// with `array_key`, the mapping whose key is `<key>`. An anchor that is
+/// not there, or an index past the end, puts them at the end.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(try_from = "YamlPositionRepr", into = "YamlPositionRepr")]
+pub enum YamlPosition {
+    Start,
+    #[default]
+    End,
+    /// After the key, or item, with this name.
+    After(String),
+    /// At this index, counting from 0.
+    Index(usize),
+}
+
+/// The YAML forms of a [`YamlPosition`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum YamlPositionRepr {
+    Index(usize),
+    Name(String),
+}
+
+impl TryFrom<YamlPositionRepr> for YamlPosition {
+    type Error = String;
+
+    fn try_from(repr: YamlPositionRepr) -> std::result::Result<Self, Self::Error> {
+        match repr {
+            YamlPositionRepr::Index(index) => Ok(YamlPosition::Index(index)),
+            YamlPositionRepr::Name(name) => match name.as_str() {
+                "start" => Ok(YamlPosition::Start),
+                "end" => Ok(YamlPosition::End),
+                _ => match name.strip_prefix("after:").map(str::trim) {
+                    Some(key) if !key.is_empty() => Ok(YamlPosition::After(key.to_string())),
+                    _ => Err(format!(
+                        "invalid position '{}': expected start, end, after:<key> or an index",
+                        name
+                    )),
+                },
+            },
+        }
+    }
+}
+
+impl From<YamlPosition> for YamlPositionRepr {
+    fn from(position: YamlPosition) -> Self {
+        match position {
+            YamlPosition::Start => YamlPositionRepr::Name("start".to_string()),
+            YamlPosition::End => YamlPositionRepr::Name("end".to_string()),
+            YamlPosition::After(key) => YamlPositionRepr::Name(format!("after:{}", key)),
+            YamlPosition::Index(index) => YamlPositionRepr::Index(index),
+        }
+    }
+}
+
+impl From<InsertPosition> for YamlPosition {
+    fn from(position: InsertPosition) -> Self {
+        match position {
+            InsertPosition::Start => YamlPosition::Start,
+            InsertPosition::End => YamlPosition::End,
+        }
+    }
+}
+
+/// Behavior when an `include`-tagged file's destination already exists
+/// in the consumer's working tree at write time.
+///
+/// `Overwrite` is the default and matches pre-`if-exists:` behavior:
+/// the include result clobbers any local content at the destination.
+/// `Preserve` skips the write so consumer-authored content survives.
+/// `Error` fails the propagation with a clear message naming the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IfExists {
+    /// Overwrite the destination (default, matches pre-feature behavior).
+    #[default]
+    Overwrite,
+    /// Skip the write when the destination exists in the local working tree.
+    Preserve,
+    /// Fail propagation when the destination exists in the local working tree.
+    Error,
+}
+
+/// How relative Markdown links and image references are rewritten when
+/// files are included or renamed, set with a `rewrite-links:` key:
+/// ```yaml
+/// - include: ["docs/**"]
+///   rewrite-links: upstream
+/// - rename:
+///     - from: "^docs/(.*)$"
+///       to: "handbook/$1"
+///   rewrite-links: relative
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkRewrite {
+    /// Follow the renames, so links keep pointing at the same files from
+    /// their new paths. Only applies to `rename`.
+    Relative,
+    /// Point links at the files in the upstream repository, as absolute
+    /// URLs of its web interface at the ref that was fetched.
+    Upstream,
+}
+
+/// YAML merge operator configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct YamlMergeOp {
+    /// Source fragment file (required unless auto_merge is set)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Destination file to merge into (required unless auto_merge is set)
+    #[serde(default)]
+    pub dest: Option<String>,
+    /// Path within the destination to merge at (optional - merges at root if omitted)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Array merge mode: replace, append, or append_unique
+    #[serde(default, rename = "array_mode")]
+    pub array_mode: ArrayMergeMode,
+    /// Merge sequences of mappings item by item, matching items on this key
+    /// (e.g. `id`) instead of applying `array_mode`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array_key: Option<String>,
+    /// Position for new keys and appended array items: start, end,
+    /// after:<key> or an index
+    #[serde(default)]
+    pub position: YamlPosition,
+    /// Mark this operation as deferred (applies when repo is used as an upstream)
+    #[serde(default)]
+    pub defer: Option<bool>,
+    /// Shorthand: sets source=dest to this value and implies defer=true
+    #[serde(default, rename = "auto-merge")]
+    pub auto_merge: Option<String>,
+}
+
+impl YamlMergeOp {
+    /// Validate the merge operation configuration
+    pub fn validate(&self) -> Result<()> {
+        // If auto_merge is set, source/dest must not be set
+        if self.auto_merge.is_some() && (self.source.is_some() || self.dest.is_some()) {
+            return Err(Error::ConfigParse {
+                message: "Cannot use auto-merge with explicit source or dest".to_string(),
+                hint: Some(
+                    "Use either 'auto-merge: file.yaml' OR 'source' and 'dest', not both"
+                        .to_string(),
+                ),
+            });
+        }
+        // If auto_merge is not set, both source and dest are required
+        if self.auto_merge.is_none() && (self.source.is_none() || self.dest.is_none()) {
+            return Err(Error::ConfigParse {
+                message: "YAML merge requires source and dest (or use auto-merge)".to_string(),
+                hint: Some(
+                    "Add 'source:' and 'dest:' fields, or use 'auto-merge:' for same-name files"
+                        .to_string(),
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the effective source path (from auto_merge or source field)
+    pub fn get_source(&self) -> Option<&str> {
+        self.auto_merge.as_deref().or(self.source.as_deref())
+    }
+
+    /// Get the effective dest path (from auto_merge or dest field)
     pub fn get_dest(&self) -> Option<&str> {
         self.auto_merge.as_deref().or(self.dest.as_deref())
     }
@@ -348,9 +1373,15 @@ impl YamlMergeOp {
         self
     }
 
-    /// Set the position for array insertion
-    pub fn position(mut self, position: InsertPosition) -> Self {
-        self.position = position;
+    /// Set the key that sequences of mappings are merged by
+    pub fn array_key(mut self, key: impl Into<String>) -> Self {
+        self.array_key = Some(key.into());
+        self
+    }
+
+    /// Set the position for new keys and appended array items
+    pub fn position(mut self, position: impl Into<YamlPosition>) -> Self {
+        self.position = position.into();
         self
     }
 
@@ -1064,6 +2095,12 @@ pub enum Operation {
         include: IncludeOp,
         #[serde(default, rename = "if-exists")]
         if_exists: IfExists,
+        #[serde(
+            default,
+            rename = "rewrite-links",
+            skip_serializing_if = "Option::is_none"
+        )]
+        rewrite_links: Option<LinkRewrite>,
     },
     /// Exclude a set of files from the final output.
     Exclude { exclude: ExcludeOp },
@@ -1088,15 +2125,139 @@ pub enum Operation {
     Markdown { markdown: MarkdownMergeOp },
     /// Merge the content of two XML files.
     Xml { xml: XmlMergeOp },
+    /// Download a single file over HTTPS.
+    File { file: FileOp },
+    /// Copy a repository's files as-is under a directory.
+    Vendor { vendor: VendorOp },
+    /// Apply a consumer-side patch to a composed file.
+    Patch { patch: PatchOp },
+    /// Prepend a license or copyright header to files.
+    Header { header: HeaderOp },
+    /// Normalize the formatting of propagated files.
+    Format { format: FormatOp },
+    /// Rewrite YAML files in a canonical layout.
+    YamlFormat { yaml_format: YamlFormatOp },
+    /// Copy part of a YAML, JSON or Markdown file into a new file.
+    Extract { extract: ExtractOp },
+    /// Check the composed output against an expectation.
+    Assert { assert: AssertOp },
     /// Operations for this repo itself (local-only, isolated pipeline).
     /// Uses `Self_` because `Self` is a Rust keyword.
     Self_ {
         #[serde(rename = "self")]
         self_: SelfOp,
     },
+    /// An operation key this version does not recognize, typically one added
+    /// in a newer release. It is skipped by the pipeline and reported; see
+    /// [`UnknownOp`].
+    #[serde(skip_deserializing)]
+    Unknown(UnknownOp),
+}
+
+/// Operation keys this version understands, in documentation order.
+pub const KNOWN_OPERATIONS: &[&str] = &[
+    "repo",
+    "include",
+    "exclude",
+    "template",
+    "template-vars",
+    "rename",
+    "tools",
+    "yaml",
+    "json",
+    "toml",
+    "ini",
+    "markdown",
+    "xml",
+    "file",
+    "vendor",
+    "patch",
+    "header",
+    "format",
+    "yaml-format",
+    "extract",
+    "assert",
+    "self",
+];
+
+/// An operation whose key is not in [`KNOWN_OPERATIONS`].
+///
+/// Parsing keeps it instead of failing so that a config written for a newer
+/// release still loads in an older one. The pipeline skips it and reports a
+/// diagnostic; `validate --strict` turns it into an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOp {
+    /// The operation key, e.g. `vendor`.
+    pub name: String,
+    /// The operation body, kept as written.
+    pub value: serde_yaml::Value,
+}
+
+impl UnknownOp {
+    /// A known operation whose name is close to this one, if the key looks
+    /// like a typo.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        crate::suggestions::find_similar(&self.name, KNOWN_OPERATIONS)
+    }
+
+    /// Report this operation as skipped.
+    ///
+    /// Lands in the active [`crate::diagnostics`] collector when a pipeline
+    /// is running.
+    pub fn report(&self) {
+        crate::diagnostics::warn(
+            ErrorPhase::Config,
+            crate::messages::Message::UnknownOperation {
+                name: &self.name,
+                suggestion: self.suggestion(),
+            }
+            .to_string(),
+        );
+    }
+}
+
+impl Serialize for UnknownOp {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.name, &self.value)?;
+        map.end()
+    }
 }
 
 impl Operation {
+    /// Returns the YAML key that introduces this operation (e.g. `"include"`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Operation::Repo { .. } => "repo",
+            Operation::Include { .. } => "include",
+            Operation::Exclude { .. } => "exclude",
+            Operation::Template { .. } => "template",
+            Operation::Rename { .. } => "rename",
+            Operation::Tools { .. } => "tools",
+            Operation::TemplateVars { .. } => "template-vars",
+            Operation::Yaml { .. } => "yaml",
+            Operation::Json { .. } => "json",
+            Operation::Toml { .. } => "toml",
+            Operation::Ini { .. } => "ini",
+            Operation::Markdown { .. } => "markdown",
+            Operation::Xml { .. } => "xml",
+            Operation::File { .. } => "file",
+            Operation::Vendor { .. } => "vendor",
+            Operation::Patch { .. } => "patch",
+            Operation::Header { .. } => "header",
+            Operation::Format { .. } => "format",
+            Operation::YamlFormat { .. } => "yaml-format",
+            Operation::Extract { .. } => "extract",
+            Operation::Assert { .. } => "assert",
+            Operation::Self_ { .. } => "self",
+            Operation::Unknown(_) => "unknown",
+        }
+    }
+
     /// Check if this operation is deferred (applies when repo is used as an upstream)
     ///
     /// Deferred operations have `defer: true` or `auto-merge` set.
@@ -1217,7 +2378,7 @@ pub fn check_merge_provenance(
 pub type Schema = Vec<Operation>;
 
 /// After deserialization, copy each `Operation::Include` variant's
-/// `if_exists` into its inner `IncludeOp.if_exists`. Recurses into
+/// `if_exists` and `rewrite_links` into its inner `IncludeOp`. Recurses into
 /// `Operation::Repo`'s `with:` clauses and `Operation::Self_`'s
 /// inner operations.
 ///
@@ -1225,11 +2386,16 @@ pub type Schema = Vec<Operation>;
 /// sibling key in standard-format YAML); the inner `IncludeOp.if_exists`
 /// is the canonical location every downstream consumer (`include::apply`,
 /// `filter_if_exists`, etc.) reads from.
-fn normalize_include_if_exists(schema: &mut Schema) {
+pub(crate) fn normalize_include_if_exists(schema: &mut Schema) {
     for op in schema.iter_mut() {
         match op {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include,
+                if_exists,
+                rewrite_links,
+            } => {
                 include.if_exists = *if_exists;
+                include.rewrite_links = *rewrite_links;
             }
             Operation::Repo { repo } => {
                 normalize_include_if_exists(&mut repo.with);
@@ -1242,532 +2408,2562 @@ fn normalize_include_if_exists(schema: &mut Schema) {
     }
 }
 
-/// Parses a YAML string into a `Schema`.
+/// Move a tag or digest written at the end of an `oci://` repo URL into
+/// its `ref`, so `url: oci://ghcr.io/org/config:1.2.0` reads the same as
+/// `url: oci://ghcr.io/org/config` with `ref: 1.2.0`.
 ///
-/// This function supports both the current, more structured format and the
-/// original, more concise format for backward compatibility. It will first
-/// attempt to parse as the current format, and if that fails, it will fall back
-/// to the original format parser.
-pub fn parse(yaml_content: &str) -> Result<Schema> {
-    // First try parsing as the current format
-    let mut schema = match serde_yaml::from_str::<Schema>(yaml_content) {
-        Ok(schema) => schema,
-        Err(_) => {
-            // If that fails, try parsing as the original user-friendly format
-            parse_original_format(yaml_content)?
+/// URLs that also have a `ref` are left alone and rejected by
+/// [`validate_repo_ref`].
+fn normalize_oci_references(schema: &mut Schema) {
+    for op in schema.iter_mut() {
+        if let Operation::Repo { repo } = op {
+            if repo.r#ref.is_none() {
+                if let Some((url, reference)) = crate::oci::split_reference(&repo.url) {
+                    repo.r#ref = Some(reference.to_string());
+                    repo.url = url.to_string();
+                }
+            }
+            normalize_oci_references(&mut repo.with);
         }
-    };
-    normalize_include_if_exists(&mut schema);
-    validate_self_operations(&schema)?;
-    validate_repo_ref(&schema)?;
-    Ok(schema)
+    }
 }
 
-/// Validate repo operations' ref requirement.
+/// The `apiVersion` of the configuration documents this release reads.
+pub const API_VERSION: &str = "common-repo/v1";
+
+/// Whether `yaml_content` is a configuration document, a mapping with an
+/// `apiVersion`, rather than a bare list of operations.
+pub fn is_document(yaml_content: &str) -> bool {
+    matches!(
+        serde_yaml::from_str::<serde_yaml::Value>(yaml_content),
+        Ok(serde_yaml::Value::Mapping(map)) if map.contains_key("apiVersion")
+    )
+}
+
+/// The operations of `yaml_content` as a bare list: the `operations` of a
+/// configuration document, or `yaml_content` itself when it is not one.
 ///
-/// Git URLs must have a ref; local filesystem URLs (starting with `./`,
-/// `../`, or `/`) may omit ref. A local URL with an explicit ref logs a
-/// warning and the ref is ignored downstream.
-pub fn validate_repo_ref(schema: &Schema) -> Result<()> {
-    for op in schema {
-        if let Operation::Repo { repo } = op {
-            if repo.is_local() {
-                if let Some(ref_value) = repo.r#ref.as_deref() {
-                    log::warn!(
-                        "ref '{}' ignored on local-path repo {}",
-                        ref_value,
-                        repo.url
-                    );
-                }
-            } else if repo.r#ref.is_none() {
-                return Err(Error::ConfigParse {
-                    message: "Repo operation missing ref".to_string(),
-                    hint: Some(
-                        "Add 'ref: main' or 'ref: v1.0.0' to the repo block, \
-                         or use a local path URL (./foo, ../foo, /foo)"
-                            .to_string(),
-                    ),
-                });
-            }
-            // Recurse into with: clauses
-            validate_repo_ref(&repo.with)?;
+/// # Errors
+///
+/// Returns `Error::ConfigParse` for a document with an `apiVersion` other
+/// than [`API_VERSION`], a key other than `apiVersion` and `operations`, or
+/// `operations` that are not a list.
+pub fn operations_text(yaml_content: &str) -> Result<Cow<'_, str>> {
+    use serde_yaml::Value;
+
+    let Ok(Value::Mapping(mut document)) = serde_yaml::from_str::<Value>(yaml_content) else {
+        return Ok(Cow::Borrowed(yaml_content));
+    };
+    let Some(version) = document.remove("apiVersion") else {
+        return Ok(Cow::Borrowed(yaml_content));
+    };
+    if version.as_str() != Some(API_VERSION) {
+        return Err(Error::ConfigParse {
+            message: format!(
+                "Unsupported apiVersion {}",
+                serde_yaml::to_string(&version)
+                    .unwrap_or_default()
+                    .trim_end()
+            ),
+            hint: Some(format!(
+                "This release of common-repo reads apiVersion: {}; a newer release may read this one",
+                API_VERSION
+            )),
+        });
+    }
+    let operations = match document.remove("operations") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Sequence(operations)) => operations,
+        Some(_) => {
+            return Err(Error::ConfigParse {
+                message: "`operations` must be a list of operations".to_string(),
+                hint: None,
+            })
         }
+    };
+    if let Some((key, _)) = document.iter().next() {
+        return Err(Error::ConfigParse {
+            message: format!(
+                "Unknown key '{}' in configuration document",
+                key.as_str().unwrap_or("?")
+            ),
+            hint: Some(
+                "A configuration document has only `apiVersion` and `operations`".to_string(),
+            ),
+        });
     }
-    Ok(())
+    serde_yaml::to_string(&Value::Sequence(operations))
+        .map(Cow::Owned)
+        .map_err(Error::Yaml)
 }
 
-/// Validate self: operations in a schema.
+/// Parses a YAML string into a `Schema`.
 ///
-/// Rules:
-/// - self: blocks must not be empty
-/// - self: blocks must not contain nested self: blocks
-pub fn validate_self_operations(schema: &Schema) -> Result<()> {
-    for op in schema {
-        if let Operation::Self_ { self_ } = op {
-            if self_.operations.is_empty() {
-                return Err(Error::ConfigParse {
-                    message: "Self operator must contain at least one operation".to_string(),
-                    hint: Some("Add operations inside the self: block".to_string()),
-                });
-            }
-            for inner in &self_.operations {
-                if matches!(inner, Operation::Self_ { .. }) {
-                    return Err(Error::ConfigParse {
-                        message: "Self operator cannot be nested".to_string(),
-                        hint: Some("Move inner self: operations to the top level".to_string()),
-                    });
+/// This function supports both the current, more structured format and the
+/// original, more concise format for backward compatibility. It will first
+/// attempt to parse as the current format, and if that fails, it will fall back
+/// to the original format parser.
+///
+/// `- requires: "<constraint>"` entries are checked against the running
+/// version before anything else is interpreted (see
+/// [`crate::version::check_requirement`]) and are not part of the returned
+/// schema. `id:` keys on top-level operations are accepted and dropped; see
+/// [`parse_disabling`]. So are deprecation notices; see [`parse_upstream`].
+///
+/// A configuration document is read as its `operations` (see
+/// [`operations_text`]).
+///
+/// This is the parser for the consumer's own configuration, whose `${NAME}`
+/// references may come from the environment (see [`crate::interpolation`]).
+/// Upstream configurations are read with [`parse_upstream`].
+pub fn parse(yaml_content: &str) -> Result<Schema> {
+    parse_interpolated(yaml_content, &[], crate::interpolation::interpolate)
+        .map(|upstream| upstream.schema)
+}
+
+/// Parses a YAML string into a `Schema`, leaving out the top-level operations
+/// whose `id:` is listed in `disable`.
+///
+/// This is how a consumer's `repo: { disable: [...] }` is applied to the
+/// upstream's configuration. An id is a string key written next to the
+/// operation:
+///
+/// ```yaml
+/// - yaml:
+///     auto-merge: .pre-commit-config.yaml
+///   id: pre-commit
+/// ```
+///
+/// Returns the schema along with the ids in `disable` that matched no
+/// operation, so the caller can report them. Ids must be unique within a
+/// file.
+pub fn parse_disabling(yaml_content: &str, disable: &[String]) -> Result<(Schema, Vec<String>)> {
+    parse_upstream(yaml_content, disable).map(|upstream| (upstream.schema, upstream.unmatched))
+}
+
+/// An upstream's configuration, as read by a consumer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upstream {
+    /// The operations, without those the consumer disabled.
+    pub schema: Schema,
+    /// Ids in the consumer's `disable` list that matched no operation.
+    pub unmatched: Vec<String>,
+    /// The upstream's deprecation notices, for the whole configuration and
+    /// for the operations the consumer did not disable.
+    pub deprecations: Vec<Deprecation>,
+    /// What the upstream requires of its consumers.
+    pub requires: Option<ConsumerRequirements>,
+    /// Links to the upstream's documentation, from its `- docs:` entries.
+    pub docs: Vec<String>,
+    /// Patterns of the `include` operations marked `locked: true`, whose
+    /// files consumers may not modify.
+    pub locked: Vec<String>,
+}
+
+/// Parses an upstream's configuration like [`parse_disabling`], keeping its
+/// deprecation notices.
+///
+/// An upstream that is being retired or replaced says so with a top-level
+/// entry, and can mark single operations with a `deprecated:` key next to
+/// the operation:
+///
+/// ```yaml
+/// - deprecated:
+///     message: This template moved to org/templates-v2
+///     replacement-url: https://github.com/org/templates-v2
+/// - include: [".github/**"]
+///   deprecated:
+///     message: Workflows now come from org/ci-workflows
+/// ```
+///
+/// The notices are not operations and are left out of the schema, as are
+/// the upstream's requirements of its consumers (see
+/// [`ConsumerRequirements`]) and its `- docs:` links, which take one URL or
+/// a list of them.
+///
+/// An `include` can also be marked `locked: true`, so that consumers may
+/// not modify the files it propagates:
+///
+/// ```yaml
+/// - include: [SECURITY.md, .github/CODEOWNERS]
+///   locked: true
+/// ```
+///
+/// Its patterns are returned in [`Upstream::locked`].
+///
+/// `${NAME}` references in the operations are filled in first, from the
+/// file's own `template-vars` only: the consumer's environment is not the
+/// upstream's to read (see [`crate::interpolation::interpolate_inline`]).
+pub fn parse_upstream(yaml_content: &str, disable: &[String]) -> Result<Upstream> {
+    parse_interpolated(
+        yaml_content,
+        disable,
+        crate::interpolation::interpolate_inline,
+    )
+}
+
+fn parse_interpolated(
+    yaml_content: &str,
+    disable: &[String],
+    interpolate: fn(&str) -> Result<std::borrow::Cow<'_, str>>,
+) -> Result<Upstream> {
+    let yaml_content = &*operations_text(yaml_content)?;
+    let yaml_content = &*interpolate(yaml_content)?;
+    let (mut schema, unmatched, deprecations, requires, docs, locked) =
+        match take_entries(yaml_content, disable)? {
+            Some(entries) => {
+                let sequence = serde_yaml::Value::Sequence(entries.operations.clone());
+                let mut schema = match serde_yaml::from_value::<Schema>(sequence) {
+                    Ok(schema) => schema,
+                    Err(_) => parse_original_values(entries.operations)?,
+                };
+                if let Some(policy) = entries.on_fetch_error {
+                    default_on_fetch_error(&mut schema, policy);
+                }
+                if let Some(index) = entries.ref_index {
+                    default_ref_index(&mut schema, &index);
                 }
+                (
+                    schema,
+                    entries.unmatched,
+                    entries.deprecations,
+                    entries.requires,
+                    entries.docs,
+                    entries.locked,
+                )
             }
+            None => {
+                // First try parsing as the current format
+                let schema = match serde_yaml::from_str::<Schema>(yaml_content) {
+                    Ok(schema) => schema,
+                    Err(_) => {
+                        // If that fails, try parsing as the original user-friendly format
+                        parse_original_format(yaml_content)?
+                    }
+                };
+                (
+                    schema,
+                    disable.to_vec(),
+                    Vec::new(),
+                    None,
+                    Vec::new(),
+                    Vec::new(),
+                )
+            }
+        };
+    normalize_include_if_exists(&mut schema);
+    normalize_oci_references(&mut schema);
+    validate_self_operations(&schema)?;
+    validate_repo_ref(&schema)?;
+    validate_file_operations(&schema)?;
+    validate_vendor_operations(&schema)?;
+    validate_extract_operations(&schema)?;
+    validate_assert_operations(&schema)?;
+    validate_link_rewrites(&schema)?;
+    Ok(Upstream {
+        schema,
+        unmatched,
+        deprecations,
+        requires,
+        docs,
+        locked,
+    })
+}
+
+/// Give the repo operations of `schema` that have no `ref-index:` of their
+/// own the config's `- ref-index:` entry.
+fn default_ref_index(schema: &mut Schema, index: &RefIndex) {
+    for op in schema {
+        if let Operation::Repo { repo } = op {
+            repo.ref_index.get_or_insert_with(|| index.clone());
         }
     }
-    Ok(())
 }
 
-/// Parse a YAML string using only the original user-friendly format
-pub fn parse_original_format(yaml_content: &str) -> Result<Schema> {
-    use serde_yaml::Value;
+/// Give the repo operations of `schema` that have no `on-fetch-error:` of
+/// their own the config's default.
+fn default_on_fetch_error(schema: &mut Schema, policy: OnFetchError) {
+    for op in schema {
+        if let Operation::Repo { repo } = op {
+            repo.on_fetch_error.get_or_insert(policy);
+        }
+    }
+}
 
-    // Parse as raw YAML values first
-    let raw_values: Vec<Value> = serde_yaml::from_str(yaml_content).map_err(Error::Yaml)?;
+/// Returns the directory of the `- cache-dir: <path>` entry in a config, if
+/// any.
+///
+/// The setting lets a project keep its repository cache with the project,
+/// for example `.common-repo/cache`, so CI can cache it next to the build.
+/// A relative path is relative to the directory of the config file; see
+/// [`crate::defaults::resolve_cache_root`]. `cache_dir` is accepted as
+/// another spelling of the key. The entry is not an operation and is left
+/// out of the parsed schema, and in upstream configs it is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::PathBuf;
+///
+/// let yaml = "- cache-dir: .common-repo/cache\n- include: ['**']\n";
+/// let cache_dir = common_repo::config::cache_dir(yaml).unwrap();
+/// assert_eq!(cache_dir, Some(PathBuf::from(".common-repo/cache")));
+/// assert_eq!(common_repo::config::parse(yaml).unwrap().len(), 1);
+/// ```
+pub fn cache_dir(yaml_content: &str) -> Result<Option<PathBuf>> {
+    let yaml_content = &*operations_text(yaml_content)?;
+    let Ok(serde_yaml::Value::Sequence(items)) =
+        serde_yaml::from_str::<serde_yaml::Value>(yaml_content)
+    else {
+        return Ok(None);
+    };
+    items
+        .iter()
+        .find_map(cache_dir_of)
+        .map(cache_dir_path)
+        .transpose()
+}
 
-    let mut operations = Vec::new();
+/// Returns the `- diff-ignore:` entry of a config, or an empty
+/// [`DiffIgnore`] when there is none.
+///
+/// Like `cache-dir`, the entry is read before the pipeline and is not an
+/// operation. More than one entry is combined.
+///
+/// # Examples
+///
+/// ```
+/// let yaml = "- diff-ignore: ['README.md']\n- include: ['**']\n";
+/// let ignore = common_repo::config::diff_ignore(yaml).unwrap();
+/// assert_eq!(ignore.paths, vec!["README.md".to_string()]);
+/// assert_eq!(common_repo::config::parse(yaml).unwrap().len(), 1);
+/// ```
+pub fn diff_ignore(yaml_content: &str) -> Result<DiffIgnore> {
+    let yaml_content = &*operations_text(yaml_content)?;
+    let Ok(serde_yaml::Value::Sequence(items)) =
+        serde_yaml::from_str::<serde_yaml::Value>(yaml_content)
+    else {
+        return Ok(DiffIgnore::default());
+    };
+    let mut ignore = DiffIgnore::default();
+    for value in items.iter().filter_map(diff_ignore_of) {
+        let entry = diff_ignore_entry(value)?;
+        ignore.paths.extend(entry.paths);
+        ignore.lines.extend(entry.lines);
+        ignore.rules.extend(entry.rules);
+    }
+    Ok(ignore)
+}
 
-    for value in raw_values {
-        if let Value::Mapping(map) = value {
-            // Convert the mapping to an Operation
-            let operation = convert_yaml_mapping_to_operation(map)?;
-            operations.push(operation);
-        } else {
-            return Err(Error::ConfigParse {
-                message: "Expected YAML mapping for operation".to_string(),
-                hint: None,
-            });
+/// Returns the `- protect:` entry of a config, or no protection when there
+/// is none.
+///
+/// Like `cache-dir`, the entry is read before the pipeline and is not an
+/// operation. More than one entry is combined.
+///
+/// # Examples
+///
+/// ```
+/// let yaml = "- protect: [read-only, pre-commit]\n- include: ['**']\n";
+/// let protection = common_repo::config::protect(yaml).unwrap();
+/// assert!(protection.read_only && protection.pre_commit);
+/// assert_eq!(common_repo::config::parse(yaml).unwrap().len(), 1);
+/// ```
+pub fn protect(yaml_content: &str) -> Result<Protection> {
+    let yaml_content = &*operations_text(yaml_content)?;
+    let Ok(serde_yaml::Value::Sequence(items)) =
+        serde_yaml::from_str::<serde_yaml::Value>(yaml_content)
+    else {
+        return Ok(Protection::default());
+    };
+    let mut protection = Protection::default();
+    for value in items.iter().filter_map(protect_of) {
+        let entry = protection_entry(value)?;
+        protection.read_only |= entry.read_only;
+        protection.pre_commit |= entry.pre_commit;
+    }
+    Ok(protection)
+}
+
+/// Returns the `- allow-paths:` and `- deny-paths:` entries of a config,
+/// or a scope that allows everything when there are none.
+///
+/// Like `protect`, the entries are read before the pipeline and are not
+/// operations. More than one of each is combined.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+///
+/// let yaml = "- allow-paths: ['.github/**', 'docs/**']\n- deny-paths: [docs/internal/**]\n- include: ['**']\n";
+/// let scope = common_repo::config::path_scope(yaml).unwrap();
+/// assert!(scope.allows(Path::new(".github/workflows/ci.yml")).unwrap());
+/// assert!(!scope.allows(Path::new("Cargo.toml")).unwrap());
+/// assert!(!scope.allows(Path::new("docs/internal/notes.md")).unwrap());
+/// assert_eq!(common_repo::config::parse(yaml).unwrap().len(), 1);
+/// ```
+pub fn path_scope(yaml_content: &str) -> Result<PathScope> {
+    let yaml_content = &*operations_text(yaml_content)?;
+    let Ok(serde_yaml::Value::Sequence(items)) =
+        serde_yaml::from_str::<serde_yaml::Value>(yaml_content)
+    else {
+        return Ok(PathScope::default());
+    };
+    let mut scope = PathScope::default();
+    for item in &items {
+        if let Some(patterns) = allow_paths_of(item) {
+            scope.allow.extend(scope_patterns("allow-paths", patterns)?);
+        }
+        if let Some(patterns) = deny_paths_of(item) {
+            scope.deny.extend(scope_patterns("deny-paths", patterns)?);
         }
     }
+    Ok(scope)
+}
 
-    Ok(operations)
+/// The value of a `- allow-paths: ...` entry.
+fn allow_paths_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["allow-paths", "allow_paths"])
 }
 
-/// Extracts the `if-exists:` value from the operator-level sibling
-/// mapping. Returns `IfExists::Overwrite` (the default) when the key is
-/// absent. A malformed value (e.g. `if-exists: garbage`) is a parse
-/// error.
-fn extract_if_exists_sibling(siblings: &serde_yaml::Mapping) -> Result<IfExists> {
-    siblings
-        .get("if-exists")
-        .map_or(Ok(IfExists::Overwrite), |value| {
-            serde_yaml::from_value(value.clone()).map_err(Error::Yaml)
-        })
+/// The value of a `- deny-paths: ...` entry.
+fn deny_paths_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["deny-paths", "deny_paths"])
 }
 
-/// Emit [`log::warn!`] for any keys in `siblings` that are not in the
-/// allow-list `recognized`. Used at the operator-dispatch layer only —
-/// inner-struct fields (e.g. `RepoOp.url`) are not currently subject to
-/// this warning.
-fn warn_unknown_siblings(op_type: &str, siblings: &serde_yaml::Mapping, recognized: &[&str]) {
-    for (key, _) in siblings {
-        if let Some(key_str) = key.as_str() {
-            if !recognized.contains(&key_str) {
-                // Sanitize control characters (newlines, ANSI escapes, etc.)
-                // so that a malicious or malformed YAML key cannot inject fake
-                // log lines or corrupt terminal output.
-                let key_display: String = key_str
-                    .chars()
-                    .map(|c| if c.is_control() { '\u{FFFD}' } else { c })
-                    .collect();
-                log::warn!(
-                    "unknown key '{}' in {} operation, ignoring",
-                    key_display,
-                    op_type
-                );
-            }
+/// Read an `allow-paths` or `deny-paths` value: one glob or a list of
+/// them, checked.
+fn scope_patterns(key: &str, value: &serde_yaml::Value) -> Result<Vec<String>> {
+    let patterns: Vec<String> = match value {
+        serde_yaml::Value::String(pattern) => vec![pattern.clone()],
+        serde_yaml::Value::Sequence(_) => {
+            serde_yaml::from_value(value.clone()).map_err(|_| Error::ConfigParse {
+                message: format!("{} must be a list of glob patterns", key),
+                hint: Some(format!("Use '{}: [\".github/**\"]'", key)),
+            })?
+        }
+        _ => {
+            return Err(Error::ConfigParse {
+                message: format!("{} must be a list of glob patterns", key),
+                hint: Some(format!("Use '{}: [\".github/**\"]'", key)),
+            })
         }
-    }
+    };
+    crate::path::PatternSet::new(&patterns)?;
+    Ok(patterns)
 }
 
-/// Convert a YAML mapping to an Operation (handles original format)
-fn convert_yaml_mapping_to_operation(map: serde_yaml::Mapping) -> Result<Operation> {
-    let mut iter = map.into_iter();
-    let (key, value) = iter.next().ok_or_else(|| Error::ConfigParse {
-        message: "Empty operation mapping".to_string(),
-        hint: None,
+/// The value of a `- protect: ...` entry.
+fn protect_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["protect"])
+}
+
+/// Read a `protect` value: one mode or a list of them.
+fn protection_entry(value: &serde_yaml::Value) -> Result<Protection> {
+    let modes: Vec<ProtectionMode> = match value {
+        serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value.clone()),
+        _ => serde_yaml::from_value(value.clone()).map(|mode| vec![mode]),
+    }
+    .map_err(|e| Error::ConfigParse {
+        message: format!("Invalid protect entry: {}", e),
+        hint: Some("Use 'protect: read-only', 'protect: pre-commit' or both in a list".to_string()),
     })?;
+    Ok(Protection {
+        read_only: modes.contains(&ProtectionMode::ReadOnly),
+        pre_commit: modes.contains(&ProtectionMode::PreCommit),
+    })
+}
 
-    let op_type = key
-        .as_str()
-        .ok_or_else(|| Error::ConfigParse {
-            message: "Operation key must be string".to_string(),
+/// The value of a `- diff-ignore: ...` entry.
+fn diff_ignore_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["diff-ignore", "diff_ignore"])
+}
+
+/// Read a `diff-ignore` value, checking its globs and regexes.
+fn diff_ignore_entry(value: &serde_yaml::Value) -> Result<DiffIgnore> {
+    let ignore = match value {
+        serde_yaml::Value::Sequence(_) => DiffIgnore {
+            paths: serde_yaml::from_value(value.clone()).map_err(Error::Yaml)?,
+            ..DiffIgnore::default()
+        },
+        serde_yaml::Value::Mapping(_) => {
+            serde_yaml::from_value(value.clone()).map_err(Error::Yaml)?
+        }
+        _ => {
+            return Err(Error::ConfigParse {
+                message: "diff-ignore must be a list of globs or a mapping".to_string(),
+                hint: Some("Use 'diff-ignore: [\"README.md\"]'".to_string()),
+            })
+        }
+    };
+    crate::path::PatternSet::new(&ignore.paths)?;
+    for line in &ignore.lines {
+        regex::Regex::new(line).map_err(|e| Error::ConfigParse {
+            message: format!("Invalid diff-ignore line pattern '{}': {}", line, e),
             hint: None,
-        })?
-        .to_string();
+        })?;
+    }
+    Ok(ignore)
+}
 
-    // Collect remaining sibling keys so individual op arms can read them
-    // (e.g. `if-exists:` on `include:`) and so unknown siblings can be
-    // warned on (see Task 6).
-    let siblings: serde_yaml::Mapping = iter.collect();
+/// The value of a `- cache-dir: <path>` entry.
+fn cache_dir_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["cache-dir", "cache_dir"])
+}
 
-    match op_type.as_str() {
-        "repo" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            // Special handling for repo operations since they may contain 'with' operations
-            // that are also in the original format
-            let mut repo_map = match value {
-                serde_yaml::Value::Mapping(m) => m,
-                _ => {
-                    return Err(Error::ConfigParse {
-                        message: "Repo operation must be a mapping".to_string(),
-                        hint: Some("Use 'repo: {url: ..., ref: ...}' format".to_string()),
-                    });
-                }
-            };
+/// The value of a `- on-fetch-error: <policy>` entry.
+fn on_fetch_error_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["on-fetch-error", "on_fetch_error"])
+}
 
-            let url = repo_map
-                .remove(serde_yaml::Value::String("url".to_string()))
-                .and_then(|v| v.as_str().map(|s| s.to_string()))
-                .ok_or_else(|| Error::ConfigParse {
-                    message: "Repo operation missing url".to_string(),
-                    hint: Some("Add 'url: https://github.com/...' to the repo block".to_string()),
-                })?;
+/// The value of a `- ref-index: {url, ref, path}` entry.
+fn ref_index_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["ref-index", "ref_index"])
+}
 
-            let r#ref = repo_map
-                .remove(serde_yaml::Value::String("ref".to_string()))
-                .and_then(|v| v.as_str().map(|s| s.to_string()));
+/// Read a `- requires:` block, checking its `version` constraint against
+/// the running version.
+fn consumer_requirements(mut block: serde_yaml::Mapping) -> Result<ConsumerRequirements> {
+    if let Some(version) = block.remove("version") {
+        let version = version.as_str().ok_or_else(|| Error::ConfigParse {
+            message: "requires version must be a version constraint string".to_string(),
+            hint: Some("Quote the constraint, e.g. 'version: \">=0.30\"'".to_string()),
+        })?;
+        crate::version::check_requirement(version)?;
+    }
+    serde_yaml::from_value(serde_yaml::Value::Mapping(block)).map_err(Error::Yaml)
+}
 
-            let path = repo_map
-                .remove(serde_yaml::Value::String("path".to_string()))
-                .and_then(|v| v.as_str().map(|s| s.to_string()));
+/// The value of a `- deprecated: {message, replacement-url}` entry.
+fn deprecated_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["deprecated"])
+}
 
-            let with = if let Some(with_value) =
-                repo_map.remove(serde_yaml::Value::String("with".to_string()))
-            {
-                match with_value {
-                    serde_yaml::Value::Sequence(seq) => {
-                        let mut with_ops = Vec::new();
-                        for item in seq {
-                            if let serde_yaml::Value::Mapping(map) = item {
-                                let op = convert_yaml_mapping_to_operation(map)?;
-                                with_ops.push(op);
-                            } else {
-                                return Err(Error::ConfigParse {
-                                    message: "With clause items must be mappings".to_string(),
-                                    hint: None,
-                                });
-                            }
-                        }
-                        with_ops
-                    }
-                    _ => {
-                        return Err(Error::ConfigParse {
-                            message: "With clause must be a sequence".to_string(),
-                            hint: Some("Use 'with: [{ include: [...] }, ...]' format".to_string()),
-                        })
-                    }
-                }
-            } else {
-                Vec::new()
-            };
+/// The value of a `- docs: <url or list of urls>` entry.
+fn docs_of(item: &serde_yaml::Value) -> Option<&serde_yaml::Value> {
+    setting_of(item, &["docs"])
+}
 
-            Ok(Operation::Repo {
-                repo: RepoOp {
-                    url,
-                    r#ref,
-                    path,
-                    with,
-                },
-            })
+/// Read the links of a `- docs:` entry.
+fn docs_links(value: &serde_yaml::Value) -> Result<Vec<String>> {
+    let invalid = || Error::ConfigParse {
+        message: "docs must be a URL or a list of URLs".to_string(),
+        hint: Some("Use 'docs: https://example.com/guide'".to_string()),
+    };
+    match value {
+        serde_yaml::Value::String(url) => Ok(vec![url.clone()]),
+        serde_yaml::Value::Sequence(urls) => urls
+            .iter()
+            .map(|url| url.as_str().map(str::to_string).ok_or_else(invalid))
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+/// The value of a top-level `- <key>: <value>` setting spelled as one of
+/// `keys`.
+fn setting_of<'a>(item: &'a serde_yaml::Value, keys: &[&str]) -> Option<&'a serde_yaml::Value> {
+    item.as_mapping()
+        .filter(|map| map.len() == 1)
+        .and_then(|map| map.iter().next())
+        .filter(|(key, _)| key.as_str().is_some_and(|key| keys.contains(&key)))
+        .map(|(_, value)| value)
+}
+
+/// Check that a `cache-dir` value is a non-empty path.
+fn cache_dir_path(value: &serde_yaml::Value) -> Result<PathBuf> {
+    match value.as_str() {
+        Some(path) if !path.trim().is_empty() => Ok(PathBuf::from(path)),
+        _ => Err(Error::ConfigParse {
+            message: "cache-dir must be a directory path".to_string(),
+            hint: Some("Use 'cache-dir: .common-repo/cache'".to_string()),
+        }),
+    }
+}
+
+/// The top-level entries of a config that are operations, with what was
+/// taken out of them.
+struct Entries {
+    operations: Vec<serde_yaml::Value>,
+    /// Ids in `disable` that matched no operation.
+    unmatched: Vec<String>,
+    /// The `- on-fetch-error:` entry.
+    on_fetch_error: Option<OnFetchError>,
+    /// The `- ref-index:` entry.
+    ref_index: Option<RefIndex>,
+    /// The `- deprecated:` entry and `deprecated:` keys.
+    deprecations: Vec<Deprecation>,
+    /// The `- requires:` blocks, combined.
+    requires: Option<ConsumerRequirements>,
+    /// The `- docs:` links.
+    docs: Vec<String>,
+    /// The patterns of the includes marked `locked: true`.
+    locked: Vec<String>,
+}
+
+/// Take the `- requires: "<constraint>"` entries, the `- cache-dir: <path>`,
+/// `- diff-ignore: ...`, `- protect: ...`, `- allow-paths: ...`, `- deny-paths: ...`,
+/// `- on-fetch-error: <policy>`, `- ref-index: ...`, `- deprecated: ...`
+/// and `- docs: ...` entries and the `id:`, `deprecated:` and `locked:` keys out of a config's
+/// top-level entries, and the `description:` keys out of its operations (see
+/// [`description`]).
+///
+/// Requirements are checked first. Entries whose id is in `disable` are
+/// dropped. Returns `None` when the config has none of these (or is not a
+/// YAML sequence) and should be parsed as written.
+fn take_entries(yaml_content: &str, disable: &[String]) -> Result<Option<Entries>> {
+    use serde_yaml::Value;
+
+    let Ok(Value::Sequence(items)) = serde_yaml::from_str::<Value>(yaml_content) else {
+        return Ok(None);
+    };
+    let requirement_of = |item: &Value| {
+        item.as_mapping()
+            .and_then(|map| map.iter().next())
+            .filter(|(key, _)| key.as_str() == Some("requires"))
+            .map(|(_, value)| value.clone())
+    };
+    let id_key = Value::String("id".to_string());
+    let deprecated_key = Value::String("deprecated".to_string());
+    let locked_key = Value::String("locked".to_string());
+    let has_sibling = |item: &Value| {
+        item.as_mapping().is_some_and(|map| {
+            map.len() > 1
+                && (map.contains_key(&id_key)
+                    || map.contains_key(&deprecated_key)
+                    || map.contains_key(&locked_key))
+        }) || has_description(item)
+    };
+    if !items.iter().any(|item| {
+        requirement_of(item).is_some()
+            || cache_dir_of(item).is_some()
+            || diff_ignore_of(item).is_some()
+            || protect_of(item).is_some()
+            || allow_paths_of(item).is_some()
+            || deny_paths_of(item).is_some()
+            || on_fetch_error_of(item).is_some()
+            || ref_index_of(item).is_some()
+            || deprecated_of(item).is_some()
+            || docs_of(item).is_some()
+            || has_sibling(item)
+    }) {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::with_capacity(items.len());
+    let mut on_fetch_error = None;
+    let mut ref_index = None;
+    let mut deprecations = Vec::new();
+    let mut requires = None;
+    let mut docs = Vec::new();
+    for item in items {
+        if let Some(deprecated) = deprecated_of(&item) {
+            // The whole config is deprecated.
+            deprecations.push(serde_yaml::from_value(deprecated.clone()).map_err(Error::Yaml)?);
+            continue;
         }
-        "include" => {
-            warn_unknown_siblings(&op_type, &siblings, &["if-exists"]);
-            let patterns: Vec<String> = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            let if_exists = extract_if_exists_sibling(&siblings)?;
-            Ok(Operation::Include {
-                include: IncludeOp {
-                    patterns,
-                    if_exists,
-                },
-                if_exists,
-            })
+        if let Some(links) = docs_of(&item) {
+            docs.extend(docs_links(links)?);
+            continue;
         }
-        "exclude" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let patterns: Vec<String> = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Exclude {
-                exclude: ExcludeOp { patterns },
-            })
+        if let Some(cache_dir) = cache_dir_of(&item) {
+            // Read by `cache_dir` before the pipeline; not an operation.
+            cache_dir_path(cache_dir)?;
+            continue;
         }
-        "template" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let patterns: Vec<String> = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Template {
-                template: TemplateOp { patterns },
-            })
+        if let Some(ignore) = diff_ignore_of(&item) {
+            // Read by `diff_ignore` before comparing; not an operation.
+            diff_ignore_entry(ignore)?;
+            continue;
         }
-        "rename" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            // Try parsing as the current format first (Vec<RenameMapping>)
-            match serde_yaml::from_value::<Vec<RenameMapping>>(value.clone()) {
-                Ok(mappings) => Ok(Operation::Rename {
-                    rename: RenameOp { mappings },
-                }),
-                Err(_) => {
-                    // Fall back to original format: Vec<HashMap<String, String>>
-                    // where each hashmap represents {"regex": "replacement"}
-                    let original_mappings: Vec<std::collections::HashMap<String, String>> =
-                        serde_yaml::from_value(value).map_err(Error::Yaml)?;
-
-                    let mappings: Vec<RenameMapping> = original_mappings
-                        .into_iter()
-                        .map(|map| {
-                            let mut iter = map.into_iter();
-                            let (from, to) = iter.next().ok_or_else(|| Error::ConfigParse {
-                                message: "Empty rename mapping".to_string(),
-                                hint: Some("Use 'rename: [{ from: \"pattern\", to: \"replacement\" }]' format".to_string()),
-                            })?;
-                            Ok(RenameMapping { from, to })
-                        })
-                        .collect::<Result<Vec<_>>>()?;
-
-                    Ok(Operation::Rename {
-                        rename: RenameOp { mappings },
-                    })
+        if let Some(protection) = protect_of(&item) {
+            // Read by `protect` before writing; not an operation.
+            protection_entry(protection)?;
+            continue;
+        }
+        if let Some(patterns) = allow_paths_of(&item) {
+            // Read by `path_scope` before the pipeline; not an operation.
+            scope_patterns("allow-paths", patterns)?;
+            continue;
+        }
+        if let Some(patterns) = deny_paths_of(&item) {
+            scope_patterns("deny-paths", patterns)?;
+            continue;
+        }
+        if let Some(policy) = on_fetch_error_of(&item) {
+            on_fetch_error = Some(serde_yaml::from_value(policy.clone()).map_err(Error::Yaml)?);
+            continue;
+        }
+        if let Some(index) = ref_index_of(&item) {
+            ref_index = Some(serde_yaml::from_value(index.clone()).map_err(Error::Yaml)?);
+            continue;
+        }
+        let Some(requirement) = requirement_of(&item) else {
+            entries.push(item);
+            continue;
+        };
+        if let Some(map) = item.as_mapping() {
+            let siblings: serde_yaml::Mapping = map
+                .iter()
+                .skip(1)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            warn_unknown_siblings("requires", &siblings, &[]);
+        }
+        if let Value::Mapping(block) = requirement {
+            let block = consumer_requirements(block)?;
+            requires
+                .get_or_insert_with(ConsumerRequirements::default)
+                .extend(block);
+            continue;
+        }
+        let requirement = requirement.as_str().ok_or_else(|| Error::ConfigParse {
+            message: "requires must be a version constraint string or a requirements block"
+                .to_string(),
+            hint: Some("Quote the constraint, e.g. 'requires: \">=0.30\"'".to_string()),
+        })?;
+        crate::version::check_requirement(requirement)?;
+    }
+
+    let mut ids = std::collections::HashSet::new();
+    let mut operations = Vec::with_capacity(entries.len());
+    let mut locked = Vec::new();
+    for mut entry in entries {
+        let (id, deprecated, lock) = match entry.as_mapping_mut() {
+            Some(map) if map.len() > 1 => (
+                map.remove(&id_key),
+                map.remove(&deprecated_key),
+                map.remove(&locked_key),
+            ),
+            _ => (None, None, None),
+        };
+        let id = match id {
+            Some(id) => {
+                let id = id.as_str().ok_or_else(|| Error::ConfigParse {
+                    message: "Operation id must be a string".to_string(),
+                    hint: Some("Use 'id: my-operation'".to_string()),
+                })?;
+                if !ids.insert(id.to_string()) {
+                    return Err(Error::ConfigParse {
+                        message: format!("Duplicate operation id '{}'", id),
+                        hint: Some("Give each operation a unique id".to_string()),
+                    });
                 }
+                if disable.iter().any(|d| d == id) {
+                    continue;
+                }
+                Some(id.to_string())
             }
+            None => None,
+        };
+        if let Some(deprecated) = deprecated {
+            let mut deprecation: Deprecation =
+                serde_yaml::from_value(deprecated).map_err(Error::Yaml)?;
+            deprecation.operation = id.or_else(|| {
+                entry
+                    .as_mapping()
+                    .and_then(|map| map.keys().next())
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            deprecations.push(deprecation);
         }
-        "tools" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            // Try parsing as the current format first (Vec<Tool>)
-            match serde_yaml::from_value::<Vec<Tool>>(value.clone()) {
-                Ok(tools) => Ok(Operation::Tools {
-                    tools: ToolsOp { tools },
-                }),
-                Err(_) => {
-                    // Fall back to original format: Vec<HashMap<String, String>>
-                    // where each hashmap represents {"tool_name": "version"}
-                    let original_tools: Vec<std::collections::HashMap<String, String>> =
-                        serde_yaml::from_value(value).map_err(Error::Yaml)?;
-
-                    let tools: Vec<Tool> = original_tools
-                        .into_iter()
-                        .map(|map| {
-                            let mut iter = map.into_iter();
-                            let (name, version) =
-                                iter.next().ok_or_else(|| Error::ConfigParse {
-                                    message: "Empty tool specification".to_string(),
-                                    hint: Some("Use 'tools: [{ name: \"tool\", version: \">=1.0\" }]' format".to_string()),
-                                })?;
-                            Ok(Tool { name, version })
-                        })
-                        .collect::<Result<Vec<_>>>()?;
+        if let Some(lock) = lock {
+            locked.extend(locked_patterns(&entry, &lock)?);
+        }
+        take_descriptions(&mut entry)?;
+        operations.push(entry);
+    }
+    let unmatched = disable
+        .iter()
+        .filter(|d| !ids.contains(d.as_str()))
+        .cloned()
+        .collect();
+    Ok(Some(Entries {
+        operations,
+        unmatched,
+        on_fetch_error,
+        ref_index,
+        deprecations,
+        requires,
+        docs,
+        locked,
+    }))
+}
 
-                    Ok(Operation::Tools {
-                        tools: ToolsOp { tools },
-                    })
-                }
-            }
+/// The patterns of an `include` entry whose `locked:` key is `lock`: all
+/// of them when it is `true`, none when it is `false`.
+fn locked_patterns(entry: &serde_yaml::Value, lock: &serde_yaml::Value) -> Result<Vec<String>> {
+    let lock = lock.as_bool().ok_or_else(|| Error::ConfigParse {
+        message: "locked must be true or false".to_string(),
+        hint: Some("Use 'locked: true'".to_string()),
+    })?;
+    let patterns = match entry.get("include") {
+        Some(serde_yaml::Value::Sequence(patterns)) => patterns
+            .iter()
+            .filter_map(|pattern| pattern.as_str().map(str::to_string))
+            .collect(),
+        Some(serde_yaml::Value::String(pattern)) => vec![pattern.clone()],
+        _ => {
+            return Err(Error::ConfigParse {
+                message: "locked is only supported on include operations".to_string(),
+                hint: Some("Move 'locked: true' next to an 'include:' list".to_string()),
+            })
         }
-        "template-vars" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            // Try parsing as the current format first (TemplateVars with vars field)
-            match serde_yaml::from_value::<TemplateVars>(value.clone()) {
-                Ok(template_vars) => Ok(Operation::TemplateVars { template_vars }),
-                Err(_) => {
-                    // Fall back to original format: direct HashMap<String, String>
-                    let vars: std::collections::HashMap<String, String> =
-                        serde_yaml::from_value(value).map_err(Error::Yaml)?;
+    };
+    Ok(if lock { patterns } else { Vec::new() })
+}
 
-                    Ok(Operation::TemplateVars {
-                        template_vars: TemplateVars { vars },
-                    })
+/// Descriptions of the operations parsed so far, keyed by the operation
+/// serialized as YAML.
+static DESCRIPTIONS: LazyLock<RwLock<HashMap<String, String>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// The `description:` an operation was written with, if any.
+///
+/// Any operation, including one in a `repo: { with: [...] }` or `self:`
+/// list, can say why it exists with a `description:` key next to the
+/// operation:
+///
+/// ```yaml
+/// - yaml:
+///     auto-merge: .pre-commit-config.yaml
+///   description: Every repo runs the shared hooks; add your own below them
+/// ```
+///
+/// The description is documentation for whoever debugs the configuration
+/// and does not change what the operation does, so it is not part of
+/// [`Operation`]. Parsing records it here instead, and reports look it up
+/// by the operation. Identical operations share one description, the last
+/// one parsed.
+///
+/// # Examples
+///
+/// ```
+/// let schema = common_repo::config::parse(
+///     "- exclude: ['*.tmp']\n  description: Editors leave these behind\n",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     common_repo::config::description(&schema[0]).as_deref(),
+///     Some("Editors leave these behind")
+/// );
+/// ```
+pub fn description(operation: &Operation) -> Option<String> {
+    let descriptions = DESCRIPTIONS.read().unwrap();
+    if descriptions.is_empty() {
+        return None;
+    }
+    let key = serde_yaml::to_string(operation).ok()?;
+    descriptions.get(&key).cloned()
+}
+
+/// The operation lists nested in an operation entry: `repo: { with: [...] }`
+/// and `self: [...]`.
+fn nested_operations(entry: &mut serde_yaml::Value) -> Vec<&mut serde_yaml::Value> {
+    let Some(map) = entry.as_mapping_mut() else {
+        return Vec::new();
+    };
+    map.iter_mut()
+        .filter_map(|(key, value)| match (key.as_str(), value) {
+            (Some("repo"), serde_yaml::Value::Mapping(repo)) => repo.get_mut("with"),
+            (Some("self"), serde_yaml::Value::Mapping(self_)) => self_.get_mut("operations"),
+            (Some("self"), value @ serde_yaml::Value::Sequence(_)) => Some(value),
+            _ => None,
+        })
+        .filter_map(serde_yaml::Value::as_sequence_mut)
+        .flat_map(|items| items.iter_mut())
+        .collect()
+}
+
+/// Whether an operation entry, or one nested in it, has a `description:`.
+fn has_description(entry: &serde_yaml::Value) -> bool {
+    let is_described = entry
+        .as_mapping()
+        .is_some_and(|map| map.len() > 1 && map.contains_key("description"));
+    is_described
+        || nested_operations(&mut entry.clone())
+            .into_iter()
+            .any(|e| has_description(e))
+}
+
+/// Take the `description:` keys out of an operation entry and the entries
+/// nested in it, recording each for [`description`].
+fn take_descriptions(entry: &mut serde_yaml::Value) -> Result<()> {
+    for nested in nested_operations(entry) {
+        take_descriptions(nested)?;
+    }
+    let description = match entry.as_mapping_mut() {
+        Some(map) if map.len() > 1 => map.remove("description"),
+        _ => None,
+    };
+    let Some(description) = description else {
+        return Ok(());
+    };
+    let description = description.as_str().ok_or_else(|| Error::ConfigParse {
+        message: "Operation description must be a string".to_string(),
+        hint: Some("Use 'description: Why this operation exists'".to_string()),
+    })?;
+    // Parse the entry the way the whole config is parsed. One that does not
+    // parse on its own is reported when the whole config is parsed.
+    let operations = match serde_yaml::from_value::<Operation>(entry.clone()) {
+        Ok(operation) => Ok(vec![operation]),
+        Err(_) => parse_original_values(vec![entry.clone()]),
+    };
+    let Ok(mut operations) = operations else {
+        return Ok(());
+    };
+    if operations.len() != 1 {
+        return Ok(());
+    }
+    normalize_include_if_exists(&mut operations);
+    normalize_oci_references(&mut operations);
+    if let Ok(key) = serde_yaml::to_string(&operations[0]) {
+        DESCRIPTIONS
+            .write()
+            .unwrap()
+            .insert(key, description.trim().to_string());
+    }
+    Ok(())
+}
+
+/// Validate repo operations' ref requirement.
+///
+/// Git URLs must have a ref; local filesystem URLs (starting with `./`,
+/// `../`, or `/`) may omit ref. A local URL with an explicit ref logs a
+/// warning and the ref is ignored downstream. The ref of an `oci://` URL
+/// must be a valid tag or digest (see [`crate::oci::Reference`]), and a
+/// `verify:` block is only allowed on git URLs.
+pub fn validate_repo_ref(schema: &Schema) -> Result<()> {
+    for op in schema {
+        if let Operation::Repo { repo } = op {
+            if repo.is_local() {
+                if let Some(ref_value) = repo.r#ref.as_deref() {
+                    log::warn!(
+                        "ref '{}' ignored on local-path repo {}",
+                        ref_value,
+                        repo.url
+                    );
                 }
+            } else if repo.r#ref.is_none() {
+                return Err(Error::ConfigParse {
+                    message: "Repo operation missing ref".to_string(),
+                    hint: Some(
+                        "Add 'ref: main' or 'ref: v1.0.0' to the repo block, \
+                         or use a local path URL (./foo, ../foo, /foo)"
+                            .to_string(),
+                    ),
+                });
+            }
+            if crate::oci::is_oci_url(&repo.url) {
+                crate::oci::Reference::parse(&repo.url, repo.r#ref.as_deref().unwrap_or(""))?;
+            }
+            if let Some(verify) = &repo.verify {
+                validate_verify(repo, verify)?;
             }
+            // Recurse into with: clauses
+            validate_repo_ref(&repo.with)?;
         }
-        "yaml" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let yaml: YamlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Yaml { yaml })
+    }
+    Ok(())
+}
+
+/// Validate a repo operation's `verify:` block.
+fn validate_verify(repo: &RepoOp, verify: &VerifyOp) -> Result<()> {
+    let invalid = |message: String, hint: &str| Error::ConfigParse {
+        message,
+        hint: Some(hint.to_string()),
+    };
+    if repo.is_local() || crate::oci::is_oci_url(&repo.url) {
+        return Err(invalid(
+            format!(
+                "verify is only supported for git repositories, not {}",
+                repo.url
+            ),
+            "Pin OCI artifacts by digest instead",
+        ));
+    }
+    if verify.gpg.is_empty() && verify.sigstore.is_empty() {
+        return Err(invalid(
+            format!("verify for {} lists no signers", repo.url),
+            "Add 'gpg: [<fingerprint>]' or 'sigstore: [{identity: ..., issuer: ...}]'",
+        ));
+    }
+    for key in &verify.gpg {
+        let fingerprint = key.replace(' ', "");
+        if !matches!(fingerprint.len(), 40 | 64)
+            || !fingerprint.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(invalid(
+                format!(
+                    "Invalid GPG fingerprint '{}' in verify for {}",
+                    key, repo.url
+                ),
+                "Use the full fingerprint from 'gpg --fingerprint', not a key id",
+            ));
         }
-        "json" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let json: JsonMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Json { json })
+    }
+    Ok(())
+}
+
+/// Validate the URL and digest of every `file:` operation, including those
+/// in `with:` clauses and `self:` blocks.
+pub fn validate_file_operations(schema: &Schema) -> Result<()> {
+    for op in schema {
+        match op {
+            Operation::File { file } => file.validate()?,
+            Operation::Repo { repo } => validate_file_operations(&repo.with)?,
+            Operation::Self_ { self_ } => validate_file_operations(&self_.operations)?,
+            _ => {}
         }
-        "toml" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let toml: TomlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Toml { toml })
+    }
+    Ok(())
+}
+
+/// Validate every `extract:` operation, including those in `with:` clauses
+/// and `self:` blocks.
+pub fn validate_extract_operations(schema: &Schema) -> Result<()> {
+    for op in schema {
+        match op {
+            Operation::Extract { extract } => extract.validate()?,
+            Operation::Repo { repo } => validate_extract_operations(&repo.with)?,
+            Operation::Self_ { self_ } => validate_extract_operations(&self_.operations)?,
+            _ => {}
         }
-        "ini" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let ini: IniMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Ini { ini })
+    }
+    Ok(())
+}
+
+/// Validate every `assert:` operation, including those in `with:` clauses
+/// and `self:` blocks.
+pub fn validate_assert_operations(schema: &Schema) -> Result<()> {
+    for op in schema {
+        match op {
+            Operation::Assert { assert } => assert.validate()?,
+            Operation::Repo { repo } => validate_assert_operations(&repo.with)?,
+            Operation::Self_ { self_ } => validate_assert_operations(&self_.operations)?,
+            _ => {}
         }
-        "markdown" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let markdown: MarkdownMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Markdown { markdown })
+    }
+    Ok(())
+}
+
+/// Check that no `include:` asks for `rewrite-links: relative`, which only
+/// applies to `rename:`, including in `with:` clauses and `self:` blocks.
+pub fn validate_link_rewrites(schema: &Schema) -> Result<()> {
+    for op in schema {
+        match op {
+            Operation::Include { include, .. }
+                if include.rewrite_links == Some(LinkRewrite::Relative) =>
+            {
+                return Err(Error::ConfigParse {
+                    message: format!(
+                        "Include of {:?} has 'rewrite-links: relative', which only applies to rename",
+                        include.patterns
+                    ),
+                    hint: Some(
+                        "Use 'rewrite-links: upstream', or set 'relative' on the rename that moves the files"
+                            .to_string(),
+                    ),
+                });
+            }
+            Operation::Repo { repo } => validate_link_rewrites(&repo.with)?,
+            Operation::Self_ { self_ } => validate_link_rewrites(&self_.operations)?,
+            _ => {}
         }
-        "xml" => {
-            warn_unknown_siblings(&op_type, &siblings, &[]);
-            let xml: XmlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
-            Ok(Operation::Xml { xml })
+    }
+    Ok(())
+}
+
+/// Validate the reference and directory of every `vendor:` operation,
+/// including those in `self:` blocks.
+pub fn validate_vendor_operations(schema: &Schema) -> Result<()> {
+    for op in schema {
+        match op {
+            Operation::Vendor { vendor } => vendor.validate()?,
+            Operation::Self_ { self_ } => validate_vendor_operations(&self_.operations)?,
+            _ => {}
         }
-        "self" => {
+    }
+    Ok(())
+}
+
+/// Validate self: operations in a schema.
+///
+/// Rules:
+/// - self: blocks must not be empty
+/// - self: blocks must not contain nested self: blocks
+pub fn validate_self_operations(schema: &Schema) -> Result<()> {
+    for op in schema {
+        if let Operation::Self_ { self_ } = op {
+            if self_.operations.is_empty() {
+                return Err(Error::ConfigParse {
+                    message: "Self operator must contain at least one operation".to_string(),
+                    hint: Some("Add operations inside the self: block".to_string()),
+                });
+            }
+            for inner in &self_.operations {
+                if matches!(inner, Operation::Self_ { .. }) {
+                    return Err(Error::ConfigParse {
+                        message: "Self operator cannot be nested".to_string(),
+                        hint: Some("Move inner self: operations to the top level".to_string()),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a YAML string using only the original user-friendly format
+pub fn parse_original_format(yaml_content: &str) -> Result<Schema> {
+    use serde_yaml::Value;
+
+    // Parse as raw YAML values first
+    let yaml_content = &*operations_text(yaml_content)?;
+    let raw_values: Vec<Value> = serde_yaml::from_str(yaml_content).map_err(Error::Yaml)?;
+    parse_original_values(raw_values)
+}
+
+/// Convert already-parsed YAML entries using the original format.
+fn parse_original_values(raw_values: Vec<serde_yaml::Value>) -> Result<Schema> {
+    use serde_yaml::Value;
+
+    let mut operations = Vec::new();
+
+    for value in raw_values {
+        if let Value::Mapping(map) = value {
+            // Convert the mapping to an Operation
+            let operation = convert_yaml_mapping_to_operation(map)?;
+            operations.push(operation);
+        } else {
+            return Err(Error::ConfigParse {
+                message: "Expected YAML mapping for operation".to_string(),
+                hint: None,
+            });
+        }
+    }
+
+    Ok(operations)
+}
+
+/// Extracts the `if-exists:` value from the operator-level sibling
+/// mapping. Returns `IfExists::Overwrite` (the default) when the key is
+/// absent. A malformed value (e.g. `if-exists: garbage`) is a parse
+/// error.
+fn extract_if_exists_sibling(siblings: &serde_yaml::Mapping) -> Result<IfExists> {
+    siblings
+        .get("if-exists")
+        .map_or(Ok(IfExists::Overwrite), |value| {
+            serde_yaml::from_value(value.clone()).map_err(Error::Yaml)
+        })
+}
+
+/// Extracts the `rewrite-links:` value from the operator-level sibling
+/// mapping, if any. A malformed value is a parse error.
+fn extract_rewrite_links_sibling(siblings: &serde_yaml::Mapping) -> Result<Option<LinkRewrite>> {
+    siblings
+        .get("rewrite-links")
+        .map(|value| serde_yaml::from_value(value.clone()).map_err(Error::Yaml))
+        .transpose()
+}
+
+/// Emit [`log::warn!`] for any keys in `siblings` that are not in the
+/// allow-list `recognized`. Used at the operator-dispatch layer only —
+/// inner-struct fields (e.g. `RepoOp.url`) are not currently subject to
+/// this warning.
+fn warn_unknown_siblings(op_type: &str, siblings: &serde_yaml::Mapping, recognized: &[&str]) {
+    for (key, _) in siblings {
+        if let Some(key_str) = key.as_str() {
+            if !recognized.contains(&key_str) {
+                // Sanitize control characters (newlines, ANSI escapes, etc.)
+                // so that a malicious or malformed YAML key cannot inject fake
+                // log lines or corrupt terminal output.
+                let key_display: String = key_str
+                    .chars()
+                    .map(|c| if c.is_control() { '\u{FFFD}' } else { c })
+                    .collect();
+                log::warn!(
+                    "unknown key '{}' in {} operation, ignoring",
+                    key_display,
+                    op_type
+                );
+            }
+        }
+    }
+}
+
+/// Convert a YAML mapping to an Operation (handles original format)
+fn convert_yaml_mapping_to_operation(map: serde_yaml::Mapping) -> Result<Operation> {
+    let mut iter = map.into_iter();
+    let (key, value) = iter.next().ok_or_else(|| Error::ConfigParse {
+        message: "Empty operation mapping".to_string(),
+        hint: None,
+    })?;
+
+    let op_type = key
+        .as_str()
+        .ok_or_else(|| Error::ConfigParse {
+            message: "Operation key must be string".to_string(),
+            hint: None,
+        })?
+        .to_string();
+
+    // Collect remaining sibling keys so individual op arms can read them
+    // (e.g. `if-exists:` on `include:`) and so unknown siblings can be
+    // warned on (see Task 6).
+    let siblings: serde_yaml::Mapping = iter.collect();
+
+    match op_type.as_str() {
+        "repo" => {
             warn_unknown_siblings(&op_type, &siblings, &[]);
-            // Self operations contain a sub-list of operations
-            match value {
-                serde_yaml::Value::Sequence(seq) => {
-                    let mut operations = Vec::new();
-                    for item in seq {
-                        if let serde_yaml::Value::Mapping(map) = item {
-                            let op = convert_yaml_mapping_to_operation(map)?;
-                            operations.push(op);
-                        } else {
-                            return Err(Error::ConfigParse {
-                                message: "Self clause items must be mappings".to_string(),
-                                hint: None,
-                            });
+            // Special handling for repo operations since they may contain 'with' operations
+            // that are also in the original format
+            let mut repo_map = match value {
+                serde_yaml::Value::Mapping(m) => m,
+                _ => {
+                    return Err(Error::ConfigParse {
+                        message: "Repo operation must be a mapping".to_string(),
+                        hint: Some("Use 'repo: {url: ..., ref: ...}' format".to_string()),
+                    });
+                }
+            };
+
+            let url = repo_map
+                .remove(serde_yaml::Value::String("url".to_string()))
+                .and_then(|v| v.as_str().map(|s| s.to_string()))
+                .ok_or_else(|| Error::ConfigParse {
+                    message: "Repo operation missing url".to_string(),
+                    hint: Some("Add 'url: https://github.com/...' to the repo block".to_string()),
+                })?;
+
+            let r#ref = repo_map
+                .remove(serde_yaml::Value::String("ref".to_string()))
+                .and_then(|v| v.as_str().map(|s| s.to_string()));
+
+            let path = repo_map
+                .remove(serde_yaml::Value::String("path".to_string()))
+                .and_then(|v| v.as_str().map(|s| s.to_string()));
+
+            let disable = match repo_map.remove(serde_yaml::Value::String("disable".to_string())) {
+                Some(value) => serde_yaml::from_value(value).map_err(Error::Yaml)?,
+                None => Vec::new(),
+            };
+
+            let verify = repo_map
+                .remove(serde_yaml::Value::String("verify".to_string()))
+                .map(serde_yaml::from_value)
+                .transpose()
+                .map_err(Error::Yaml)?;
+
+            let on_fetch_error = repo_map
+                .remove(serde_yaml::Value::String("on-fetch-error".to_string()))
+                .or_else(|| {
+                    repo_map.remove(serde_yaml::Value::String("on_fetch_error".to_string()))
+                })
+                .map(serde_yaml::from_value)
+                .transpose()
+                .map_err(Error::Yaml)?;
+
+            let partial = repo_map
+                .remove(serde_yaml::Value::String("partial".to_string()))
+                .map(serde_yaml::from_value)
+                .transpose()
+                .map_err(Error::Yaml)?
+                .unwrap_or(false);
+
+            let ref_index = repo_map
+                .remove(serde_yaml::Value::String("ref-index".to_string()))
+                .or_else(|| repo_map.remove(serde_yaml::Value::String("ref_index".to_string())))
+                .map(serde_yaml::from_value)
+                .transpose()
+                .map_err(Error::Yaml)?;
+
+            let with = if let Some(with_value) =
+                repo_map.remove(serde_yaml::Value::String("with".to_string()))
+            {
+                match with_value {
+                    serde_yaml::Value::Sequence(seq) => {
+                        let mut with_ops = Vec::new();
+                        for item in seq {
+                            if let serde_yaml::Value::Mapping(map) = item {
+                                let op = convert_yaml_mapping_to_operation(map)?;
+                                with_ops.push(op);
+                            } else {
+                                return Err(Error::ConfigParse {
+                                    message: "With clause items must be mappings".to_string(),
+                                    hint: None,
+                                });
+                            }
                         }
+                        with_ops
+                    }
+                    _ => {
+                        return Err(Error::ConfigParse {
+                            message: "With clause must be a sequence".to_string(),
+                            hint: Some("Use 'with: [{ include: [...] }, ...]' format".to_string()),
+                        })
                     }
-                    Ok(Operation::Self_ {
-                        self_: SelfOp { operations },
-                    })
                 }
-                _ => Err(Error::ConfigParse {
-                    message: "Self operator must be a sequence of operations".to_string(),
-                    hint: Some(
-                        "Use 'self: [{ include: [...] }, ...]' format".to_string(),
-                    ),
-                }),
-            }
+            } else {
+                Vec::new()
+            };
+
+            Ok(Operation::Repo {
+                repo: RepoOp {
+                    url,
+                    r#ref,
+                    path,
+                    with,
+                    disable,
+                    verify,
+                    on_fetch_error,
+                    partial,
+                    ref_index,
+                },
+            })
+        }
+        "include" => {
+            warn_unknown_siblings(&op_type, &siblings, &["if-exists", "rewrite-links"]);
+            let patterns: Vec<String> = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            let if_exists = extract_if_exists_sibling(&siblings)?;
+            let rewrite_links = extract_rewrite_links_sibling(&siblings)?;
+            Ok(Operation::Include {
+                include: IncludeOp {
+                    patterns,
+                    if_exists,
+                    rewrite_links,
+                },
+                if_exists,
+                rewrite_links,
+            })
+        }
+        "exclude" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let patterns: Vec<String> = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Exclude {
+                exclude: ExcludeOp { patterns },
+            })
+        }
+        "template" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let template: TemplateOp =
+                serde_yaml::from_value(value).map_err(|e| Error::ConfigParse {
+                    message: format!("Invalid template operation: {}", e),
+                    hint: Some(
+                        "use a list of glob patterns, or `files:` mapping each template file to its placeholder checksum"
+                            .to_string(),
+                    ),
+                })?;
+            Ok(Operation::Template { template })
+        }
+        "rename" => {
+            warn_unknown_siblings(&op_type, &siblings, &["rewrite-links"]);
+            let rewrite_links = extract_rewrite_links_sibling(&siblings)?;
+            // Try parsing as the current format first (Vec<RenameMapping>)
+            match serde_yaml::from_value::<Vec<RenameMapping>>(value.clone()) {
+                Ok(mappings) => Ok(Operation::Rename {
+                    rename: RenameOp {
+                        mappings,
+                        rewrite_links,
+                    },
+                }),
+                Err(_) => {
+                    // Fall back to original format: Vec<HashMap<String, String>>
+                    // where each hashmap represents {"regex": "replacement"}
+                    let original_mappings: Vec<std::collections::HashMap<String, String>> =
+                        serde_yaml::from_value(value).map_err(Error::Yaml)?;
+
+                    let mappings: Vec<RenameMapping> = original_mappings
+                        .into_iter()
+                        .map(|map| {
+                            let mut iter = map.into_iter();
+                            let (from, to) = iter.next().ok_or_else(|| Error::ConfigParse {
+                                message: "Empty rename mapping".to_string(),
+                                hint: Some("Use 'rename: [{ from: \"pattern\", to: \"replacement\" }]' format".to_string()),
+                            })?;
+                            Ok(RenameMapping { from, to })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                    Ok(Operation::Rename {
+                        rename: RenameOp {
+                            mappings,
+                            rewrite_links,
+                        },
+                    })
+                }
+            }
+        }
+        "tools" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            // Try parsing as the current format first (Vec<Tool>)
+            match serde_yaml::from_value::<Vec<Tool>>(value.clone()) {
+                Ok(tools) => Ok(Operation::Tools {
+                    tools: ToolsOp { tools },
+                }),
+                Err(_) => {
+                    // Fall back to original format: Vec<HashMap<String, String>>
+                    // where each hashmap represents {"tool_name": "version"}
+                    let original_tools: Vec<std::collections::HashMap<String, String>> =
+                        serde_yaml::from_value(value).map_err(Error::Yaml)?;
+
+                    let tools: Vec<Tool> = original_tools
+                        .into_iter()
+                        .map(|map| {
+                            let mut iter = map.into_iter();
+                            let (name, version) =
+                                iter.next().ok_or_else(|| Error::ConfigParse {
+                                    message: "Empty tool specification".to_string(),
+                                    hint: Some("Use 'tools: [{ name: \"tool\", version: \">=1.0\" }]' format".to_string()),
+                                })?;
+                            Ok(Tool { name, version })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                    Ok(Operation::Tools {
+                        tools: ToolsOp { tools },
+                    })
+                }
+            }
+        }
+        "template-vars" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            // Try parsing as the current format first (TemplateVars with vars field)
+            let template_vars = match serde_yaml::from_value::<TemplateVars>(value.clone()) {
+                Ok(template_vars) => template_vars,
+                Err(_) => {
+                    // Fall back to original format: a direct mapping, where
+                    // a mapping value is a secret source
+                    let serde_yaml::Value::Mapping(map) = value else {
+                        return Err(Error::ConfigParse {
+                            message: "template-vars must be a mapping of names to values"
+                                .to_string(),
+                            hint: None,
+                        });
+                    };
+                    let (secrets, vars): (serde_yaml::Mapping, serde_yaml::Mapping) =
+                        map.into_iter().partition(|(_, v)| v.is_mapping());
+                    TemplateVars {
+                        vars: serde_yaml::from_value(serde_yaml::Value::Mapping(vars))
+                            .map_err(Error::Yaml)?,
+                        secrets: serde_yaml::from_value(serde_yaml::Value::Mapping(secrets))
+                            .map_err(Error::Yaml)?,
+                    }
+                }
+            };
+            for (name, source) in &template_vars.secrets {
+                source.validate(name)?;
+            }
+            Ok(Operation::TemplateVars { template_vars })
+        }
+        "yaml" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let yaml: YamlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Yaml { yaml })
+        }
+        "json" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let json: JsonMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Json { json })
+        }
+        "toml" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let toml: TomlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Toml { toml })
+        }
+        "ini" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let ini: IniMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Ini { ini })
+        }
+        "markdown" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let markdown: MarkdownMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Markdown { markdown })
+        }
+        "xml" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let xml: XmlMergeOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Xml { xml })
+        }
+        "file" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let file: FileOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::File { file })
+        }
+        "vendor" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let vendor: VendorOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Vendor { vendor })
+        }
+        "patch" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let patch: PatchOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Patch { patch })
+        }
+        "header" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let header: HeaderOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Header { header })
+        }
+        "format" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let format: FormatOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Format { format })
+        }
+        // `yaml_format` is the serialized form of the operation
+        "yaml-format" | "yaml_format" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let yaml_format: YamlFormatOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::YamlFormat { yaml_format })
+        }
+        "extract" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let extract: ExtractOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Extract { extract })
+        }
+        "assert" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            let assert: AssertOp = serde_yaml::from_value(value).map_err(Error::Yaml)?;
+            Ok(Operation::Assert { assert })
+        }
+        "self" => {
+            warn_unknown_siblings(&op_type, &siblings, &[]);
+            // Self operations contain a sub-list of operations
+            match value {
+                serde_yaml::Value::Sequence(seq) => {
+                    let mut operations = Vec::new();
+                    for item in seq {
+                        if let serde_yaml::Value::Mapping(map) = item {
+                            let op = convert_yaml_mapping_to_operation(map)?;
+                            operations.push(op);
+                        } else {
+                            return Err(Error::ConfigParse {
+                                message: "Self clause items must be mappings".to_string(),
+                                hint: None,
+                            });
+                        }
+                    }
+                    Ok(Operation::Self_ {
+                        self_: SelfOp { operations },
+                    })
+                }
+                _ => Err(Error::ConfigParse {
+                    message: "Self operator must be a sequence of operations".to_string(),
+                    hint: Some("Use 'self: [{ include: [...] }, ...]' format".to_string()),
+                }),
+            }
+        }
+        _ => {
+            // Keep the whole mapping so nothing written for a newer release is lost.
+            let unknown = UnknownOp {
+                name: op_type,
+                value,
+            };
+            log::debug!("parsed unknown operation '{}'", unknown.name);
+            Ok(Operation::Unknown(unknown))
+        }
+    }
+}
+
+/// Parse a Schema from a YAML file path
+pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Schema> {
+    let content = std::fs::read_to_string(path).map_err(Error::Io)?;
+    parse(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interpolates_operation_fields() {
+        let yaml = r#"
+- template-vars:
+    SERVICE: billing
+- repo:
+    url: https://github.com/org/shared-config
+    ref: ${SERVICE}-v1
+- rename:
+    - "^ci\\.yaml$": ".github/workflows/${SERVICE}.yaml"
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Repo { repo } = &schema[1] else {
+            panic!("expected a repo operation");
+        };
+        assert_eq!(repo.r#ref.as_deref(), Some("billing-v1"));
+        let Operation::Rename { rename } = &schema[2] else {
+            panic!("expected a rename operation");
+        };
+        assert_eq!(rename.mappings[0].to, ".github/workflows/billing.yaml");
+
+        let err = parse("- exclude: ['${COMMON_REPO_TEST_UNDEFINED_VAR}']\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("Undefined variable 'COMMON_REPO_TEST_UNDEFINED_VAR'"),
+            "Error was: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_upstream_does_not_interpolate_the_environment() {
+        // PATH is set wherever the tests run
+        let yaml = "- template-vars:\n    DIR: docs\n- exclude: ['${DIR}/**']\n";
+        assert!(parse_upstream(yaml, &[]).is_ok());
+        let yaml = "- rename:\n    - \"^a$\": \"${PATH}/a\"\n";
+        let err = parse_upstream(yaml, &[]).unwrap_err().to_string();
+        assert!(
+            err.contains("Undefined variable 'PATH'"),
+            "Error was: {}",
+            err
+        );
+        assert!(parse(yaml).is_ok());
+    }
+
+    #[test]
+    fn test_parse_simple_config() {
+        let yaml = r#"
+- repo:
+    url: https://github.com/example/repo
+    ref: main
+- include:
+    - "**/*"
+    - "*.md"
+- exclude:
+    - ".git/**"
+    - "*.tmp"
+"#;
+
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 3);
+
+        match &schema[0] {
+            Operation::Repo { repo } => {
+                assert_eq!(repo.url, "https://github.com/example/repo");
+                assert_eq!(repo.r#ref.as_deref(), Some("main"));
+                assert!(repo.with.is_empty());
+            }
+            _ => panic!("Expected Repo operation"),
+        }
+
+        match &schema[1] {
+            Operation::Include { include, .. } => {
+                assert_eq!(include.patterns, vec!["**/*", "*.md"]);
+            }
+            _ => panic!("Expected Include operation"),
+        }
+
+        match &schema[2] {
+            Operation::Exclude { exclude } => {
+                assert_eq!(exclude.patterns, vec![".git/**", "*.tmp"]);
+            }
+            _ => panic!("Expected Exclude operation"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rename_operation() {
+        let yaml = r#"
+- rename:
+    mappings:
+      - from: "badname/(.*)"
+        to: "goodname/$1"
+      - from: "^files/(.*)"
+        to: "$1"
+"#;
+
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 1);
+
+        match &schema[0] {
+            Operation::Rename { rename } => {
+                assert_eq!(rename.mappings.len(), 2);
+                assert_eq!(rename.mappings[0].from, "badname/(.*)");
+                assert_eq!(rename.mappings[0].to, "goodname/$1");
+                assert_eq!(rename.mappings[1].from, "^files/(.*)");
+                assert_eq!(rename.mappings[1].to, "$1");
+            }
+            _ => panic!("Expected Rename operation"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rewrite_links() {
+        let yaml = r#"
+- include: ["docs/**"]
+  rewrite-links: upstream
+- rename:
+    - "^docs/(.*)$": "handbook/$1"
+  rewrite-links: relative
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Include { include, .. } = &schema[0] else {
+            panic!("expected include operation");
+        };
+        assert_eq!(include.rewrite_links, Some(LinkRewrite::Upstream));
+        let Operation::Rename { rename } = &schema[1] else {
+            panic!("expected rename operation");
+        };
+        assert_eq!(rename.rewrite_links, Some(LinkRewrite::Relative));
+
+        // The serialized form parses back to the same operations
+        let serialized = serde_yaml::to_string(&schema).unwrap();
+        assert_eq!(parse(&serialized).unwrap(), schema);
+
+        let err = parse("- include: [\"docs/**\"]\n  rewrite-links: relative\n").unwrap_err();
+        assert!(err.to_string().contains("only applies to rename"));
+        assert!(parse("- include: [\"docs/**\"]\n  rewrite-links: sideways\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_yaml() {
+        // A repo operation without a url is invalid (url is required).
+        let invalid_yaml = r#"
+- repo:
+    ref: main
+"#;
+
+        let result = parse(invalid_yaml);
+        match result {
+            Err(crate::error::Error::ConfigParse { message, .. }) => {
+                assert!(
+                    message.contains("missing url"),
+                    "unexpected message: {message}"
+                );
+            }
+            other => panic!("expected ConfigParse for missing url, got: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_empty_config() {
+        let empty_yaml = "";
+        let result = parse(empty_yaml);
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_malformed_operation() {
+        let malformed_yaml = r#"
+- yaml: 5
+"#;
+
+        let result = parse(malformed_yaml);
+        // A known operation with the wrong shape still fails; only unknown
+        // operation keys are kept as Operation::Unknown
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_from_file_nonexistent() {
+        let result = from_file("nonexistent_file.yaml");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_requires_is_checked_and_removed() {
+        let yaml = r#"
+- requires: ">=0.1"
+- include: ["**/*"]
+- exclude: [".git/**"]
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 2);
+        assert!(matches!(schema[0], Operation::Include { .. }));
+
+        // Original-format entries still parse after the requirement is removed.
+        let schema = parse("- requires: \">=0.1\"\n- template-vars:\n    name: demo\n").unwrap();
+        assert!(matches!(schema[0], Operation::TemplateVars { .. }));
+    }
+
+    #[test]
+    fn test_parse_requires_unmet_wins_over_unknown_operations() {
+        let yaml = r#"
+- requires: ">=999.0"
+- operation-from-the-future: {}
+"#;
+        let err = parse(yaml).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::VersionRequirement { ref required, origin: None, .. } if required == ">=999.0"
+        ));
+        assert_eq!(err.code(), "E0005");
+    }
+
+    #[test]
+    fn test_parse_keeps_unknown_operations() {
+        let yaml = r#"
+- include: ["**/*"]
+- archive:
+    source: third_party
+    lock: archive.lock
+- exlude: [".git/**"]
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 3);
+        let Operation::Unknown(archive) = &schema[1] else {
+            panic!("expected unknown operation, got {:?}", schema[1]);
+        };
+        assert_eq!(archive.name, "archive");
+        assert_eq!(archive.suggestion(), None);
+        assert_eq!(schema[1].kind(), "unknown");
+
+        let Operation::Unknown(typo) = &schema[2] else {
+            panic!("expected unknown operation, got {:?}", schema[2]);
+        };
+        assert_eq!(typo.suggestion(), Some("exclude"));
+
+        // Serializing writes the operation back as it was read.
+        let round_trip: serde_yaml::Value =
+            serde_yaml::from_str(&serde_yaml::to_string(&schema[1]).unwrap()).unwrap();
+        let original: serde_yaml::Value =
+            serde_yaml::from_str("archive: {source: third_party, lock: archive.lock}").unwrap();
+        assert_eq!(round_trip, original);
+    }
+
+    #[test]
+    fn test_unknown_operation_reports_diagnostic() {
+        let schema = parse("- archive: {source: third_party}\n").unwrap();
+        let Operation::Unknown(unknown) = &schema[0] else {
+            panic!("expected unknown operation");
+        };
+        let ((), diagnostics) = crate::diagnostics::collect(|| unknown.report());
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.phase, ErrorPhase::Config);
+        assert!(diagnostic
+            .message
+            .contains("Unknown operation 'archive' skipped"));
+    }
+
+    #[test]
+    fn test_parse_disabling_drops_operations_by_id() {
+        let yaml = r#"
+- id: everything
+  include: ["**/*"]
+- exclude: [".git/**"]
+  id: no-git
+- yaml:
+    auto-merge: config.yaml
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 3);
+        assert!(matches!(schema[0], Operation::Include { .. }));
+
+        let disable = vec!["no-git".to_string(), "missing".to_string()];
+        let (schema, unmatched) = parse_disabling(yaml, &disable).unwrap();
+        assert_eq!(schema.len(), 2);
+        assert!(matches!(schema[0], Operation::Include { .. }));
+        assert!(matches!(schema[1], Operation::Yaml { .. }));
+        assert_eq!(unmatched, vec!["missing".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_upstream_deprecations() {
+        let yaml = r#"
+- deprecated:
+    message: Use org/templates-v2
+    replacement_url: https://github.com/org/templates-v2
+- include: ["**/*"]
+  deprecated:
+    message: Include less
+- exclude: [".git/**"]
+  id: no-git
+  deprecated:
+    message: Going away
+"#;
+        let upstream = parse_upstream(yaml, &[]).unwrap();
+        assert_eq!(upstream.schema.len(), 2);
+        let notices: Vec<_> = upstream
+            .deprecations
+            .iter()
+            .map(|d| (d.operation.as_deref(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            notices,
+            [
+                (None, "Use org/templates-v2"),
+                (Some("include"), "Include less"),
+                (Some("no-git"), "Going away"),
+            ]
+        );
+        assert_eq!(
+            upstream.deprecations[0].replacement_url.as_deref(),
+            Some("https://github.com/org/templates-v2")
+        );
+
+        // Disabled operations no longer concern the consumer.
+        let upstream = parse_upstream(yaml, &["no-git".to_string()]).unwrap();
+        assert_eq!(upstream.deprecations.len(), 2);
+
+        assert!(parse("- deprecated:\n    replacement-url: x\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_upstream_requires_block() {
+        let yaml = r#"
+- requires: ">=0.1"
+- requires:
+    version: ">=0.1"
+    vars: [project_name]
+    files: [Cargo.toml]
+- requires:
+    tools:
+      - name: rustc
+        version: ">=1.70"
+- include: ["**"]
+"#;
+        let upstream = parse_upstream(yaml, &[]).unwrap();
+        assert_eq!(upstream.schema.len(), 1);
+        let requires = upstream.requires.unwrap();
+        assert_eq!(requires.vars.len(), 1);
+        assert_eq!(requires.vars[0].name, "project_name");
+        assert_eq!(requires.files, ["Cargo.toml"]);
+        assert_eq!(requires.tools[0].name, "rustc");
+
+        let err = parse("- requires:\n    version: \">=999\"\n").unwrap_err();
+        assert!(matches!(err, Error::VersionRequirement { .. }));
+        assert!(parse("- requires:\n    varz: [a]\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_upstream_docs_links() {
+        let yaml = r#"
+- docs: https://example.com/guide
+- docs:
+    - https://example.com/adopting
+- include: ["**"]
+"#;
+        let upstream = parse_upstream(yaml, &[]).unwrap();
+        assert_eq!(upstream.schema.len(), 1);
+        assert_eq!(
+            upstream.docs,
+            ["https://example.com/guide", "https://example.com/adopting"]
+        );
+        assert!(parse("- docs: {url: x}\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_upstream_locked_includes() {
+        let yaml = r#"
+- include: [SECURITY.md, ".github/CODEOWNERS"]
+  locked: true
+- include: [README.md]
+  locked: false
+- include: ["docs/**"]
+  id: docs
+  locked: true
+"#;
+        let upstream = parse_upstream(yaml, &["docs".to_string()]).unwrap();
+        assert_eq!(upstream.schema.len(), 2);
+        assert_eq!(upstream.locked, ["SECURITY.md", ".github/CODEOWNERS"]);
+        assert!(parse("- exclude: [a]\n  locked: true\n").is_err());
+        assert!(parse("- include: [a]\n  locked: yes please\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_duplicate_ids() {
+        let yaml = "- include: [a]\n  id: x\n- include: [b]\n  id: x\n";
+        let err = parse(yaml).unwrap_err();
+        assert!(err.to_string().contains("Duplicate operation id 'x'"));
+
+        let err = parse("- include: [a]\n  id: 3\n").unwrap_err();
+        assert!(err.to_string().contains("id must be a string"));
+    }
+
+    #[test]
+    fn test_parse_operation_descriptions() {
+        let yaml = r#"
+- repo:
+    url: https://github.com/example/descriptions
+    ref: v1.0.0
+    with:
+      - exclude: [".github/CODEOWNERS"]
+        description: Each team keeps its own owners file
+  description: Shared CI for every service
+- include: ["docs/**"]
+  if-exists: overwrite
+  id: docs
+  description: Docs are published from upstream
+- self:
+    - rename:
+        - from: "^tmpl/"
+          to: ""
+      description: Templates live under tmpl/ in this repo
+- toml:
+    auto-merge: pyproject.toml
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(schema.len(), 4);
+        let Operation::Repo { repo } = &schema[0] else {
+            panic!("expected repo operation");
+        };
+        assert_eq!(
+            description(&schema[0]).as_deref(),
+            Some("Shared CI for every service")
+        );
+        assert_eq!(
+            description(&repo.with[0]).as_deref(),
+            Some("Each team keeps its own owners file")
+        );
+        assert_eq!(
+            description(&schema[1]).as_deref(),
+            Some("Docs are published from upstream")
+        );
+        let Operation::Self_ { self_ } = &schema[2] else {
+            panic!("expected self operation");
+        };
+        assert_eq!(
+            description(&self_.operations[0]).as_deref(),
+            Some("Templates live under tmpl/ in this repo")
+        );
+        assert_eq!(description(&schema[3]), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_non_string_description() {
+        let err = parse("- include: [a]\n  description: [x]\n").unwrap_err();
+        assert!(err.to_string().contains("description must be a string"));
+    }
+
+    #[test]
+    fn test_parse_repo_disable() {
+        let yaml = r#"
+- repo:
+    url: https://github.com/example/upstream
+    ref: v1.0.0
+    disable: [pre-commit]
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Repo { repo } = &schema[0] else {
+            panic!("expected repo operation");
+        };
+        assert_eq!(repo.disable, vec!["pre-commit".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_repo_verify() {
+        let yaml = r#"
+- repo:
+    url: https://github.com/example/upstream
+    ref: v1.0.0
+    verify:
+      gpg: ["1111 2222 3333 4444 5555  6666 7777 8888 9999 0000"]
+      sigstore:
+        - identity: release@example.com
+          issuer: https://accounts.google.com
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Repo { repo } = &schema[0] else {
+            panic!("expected repo operation");
+        };
+        let verify = repo.verify.as_ref().unwrap();
+        assert_eq!(verify.gpg.len(), 1);
+        assert_eq!(verify.sigstore[0].identity, "release@example.com");
+
+        for (repo, verify, expected) in [
+            ("url: ./local", "gpg: []", "only supported for git"),
+            (
+                "url: https://github.com/a/b\n    ref: v1",
+                "gpg: []",
+                "no signers",
+            ),
+            (
+                "url: https://github.com/a/b\n    ref: v1",
+                "gpg: [ABCD1234]",
+                "Invalid GPG fingerprint",
+            ),
+        ] {
+            let yaml = format!("- repo:\n    {}\n    verify:\n      {}\n", repo, verify);
+            let err = parse(&yaml).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
         }
-        _ => Err(Error::ConfigParse {
-            message: format!("Unknown operation type: {}", op_type),
-            hint: Some("Valid operations: repo, include, exclude, template, template-vars, rename, tools, yaml, json, toml, ini, markdown, xml, self".to_string()),
-        }),
     }
-}
 
-/// Parse a Schema from a YAML file path
-pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Schema> {
-    let content = std::fs::read_to_string(path).map_err(Error::Io)?;
-    parse(&content)
-}
+    #[test]
+    fn test_parse_document_form() {
+        let document = r#"
+apiVersion: common-repo/v1
+operations:
+  - cache-dir: .cache
+  - include: ["**"]
+  - exclude: ["*.tmp"]
+"#;
+        let list = "- cache-dir: .cache\n- include: [\"**\"]\n- exclude: [\"*.tmp\"]\n";
+        assert!(is_document(document));
+        assert!(!is_document(list));
+        assert_eq!(parse(document).unwrap(), parse(list).unwrap());
+        assert_eq!(cache_dir(document).unwrap(), Some(PathBuf::from(".cache")));
+        assert!(parse("apiVersion: common-repo/v1\n").unwrap().is_empty());
+
+        let err = parse("apiVersion: common-repo/v2\noperations: []\n").unwrap_err();
+        assert!(
+            err.to_string().contains("Unsupported apiVersion"),
+            "{}",
+            err
+        );
+        let err = parse("apiVersion: common-repo/v1\nops: []\n").unwrap_err();
+        assert!(err.to_string().contains("Unknown key 'ops'"), "{}", err);
+        let err = parse("apiVersion: common-repo/v1\noperations: {include: []}\n").unwrap_err();
+        assert!(err.to_string().contains("must be a list"), "{}", err);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_parse_repo_ref_index() {
+        let yaml = r#"
+- ref-index:
+    url: https://github.com/acme/index
+- repo:
+    url: https://github.com/acme/ci-config
+    ref: stable
+- repo:
+    url: https://github.com/acme/lint-config
+    ref: stable
+    ref-index:
+      url: ../index
+      path: aliases.yaml
+"#;
+        let schema = parse(yaml).unwrap();
+        let indexes: Vec<_> = schema
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Repo { repo } => repo.ref_index.clone(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            indexes,
+            [
+                RefIndex {
+                    url: "https://github.com/acme/index".to_string(),
+                    r#ref: None,
+                    path: None,
+                },
+                RefIndex {
+                    url: "../index".to_string(),
+                    r#ref: None,
+                    path: Some("aliases.yaml".to_string()),
+                },
+            ]
+        );
+        assert_eq!(schema.len(), 2);
+
+        let yaml = "- repo:\n    url: https://github.com/a/b\n    ref-index: {url: x, branch: y}\n";
+        assert!(parse(yaml).is_err());
+    }
 
     #[test]
-    fn test_parse_simple_config() {
+    fn test_parse_repo_on_fetch_error() {
         let yaml = r#"
+- on-fetch-error: fail
 - repo:
-    url: https://github.com/example/repo
+    url: https://github.com/example/required
+    ref: v1.0.0
+- repo:
+    url: https://github.com/example/optional
     ref: main
-- include:
-    - "**/*"
-    - "*.md"
-- exclude:
-    - ".git/**"
-    - "*.tmp"
+    on_fetch_error: skip
+- include: ["**"]
 "#;
-
         let schema = parse(yaml).unwrap();
+        let policies: Vec<_> = schema
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Repo { repo } => Some(repo.on_fetch_error),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            policies,
+            [Some(OnFetchError::Fail), Some(OnFetchError::Skip)]
+        );
         assert_eq!(schema.len(), 3);
 
-        match &schema[0] {
-            Operation::Repo { repo } => {
-                assert_eq!(repo.url, "https://github.com/example/repo");
-                assert_eq!(repo.r#ref.as_deref(), Some("main"));
-                assert!(repo.with.is_empty());
-            }
-            _ => panic!("Expected Repo operation"),
-        }
+        let yaml = "- repo:\n    url: https://github.com/a/b\n    ref: v1\n";
+        let Operation::Repo { repo } = &parse(yaml).unwrap()[0] else {
+            panic!("expected repo operation");
+        };
+        assert_eq!(repo.on_fetch_error, None);
 
-        match &schema[1] {
-            Operation::Include { include, .. } => {
-                assert_eq!(include.patterns, vec!["**/*", "*.md"]);
-            }
-            _ => panic!("Expected Include operation"),
-        }
+        assert!(parse("- on-fetch-error: retry\n").is_err());
+    }
 
-        match &schema[2] {
-            Operation::Exclude { exclude } => {
-                assert_eq!(exclude.patterns, vec![".git/**", "*.tmp"]);
-            }
-            _ => panic!("Expected Exclude operation"),
-        }
+    #[test]
+    fn test_parse_oci_repo_reference_in_url() {
+        let yaml = r#"
+- repo:
+    url: oci://ghcr.io/org/common-config:1.2.0
+    with:
+      - repo:
+          url: oci://localhost:5000/org/nested
+          ref: v2
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Repo { repo } = &schema[0] else {
+            panic!("expected repo operation");
+        };
+        assert_eq!(repo.url, "oci://ghcr.io/org/common-config");
+        assert_eq!(repo.r#ref.as_deref(), Some("1.2.0"));
+        let Operation::Repo { repo: nested } = &repo.with[0] else {
+            panic!("expected nested repo operation");
+        };
+        assert_eq!(nested.url, "oci://localhost:5000/org/nested");
+        assert_eq!(nested.r#ref.as_deref(), Some("v2"));
+
+        let err = parse("- repo:\n    url: oci://ghcr.io/org/config:1.2.0\n    ref: 1.3.0\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("as well as a ref"));
+        assert!(parse("- repo:\n    url: oci://ghcr.io/org/config\n    ref: sha256:00\n").is_err());
     }
 
     #[test]
-    fn test_parse_rename_operation() {
+    fn test_parse_patch_operation() {
         let yaml = r#"
-- rename:
-    mappings:
-      - from: "badname/(.*)"
-        to: "goodname/$1"
-      - from: "^files/(.*)"
-        to: "$1"
+- patch:
+    file: README.md
+    patch: patches/readme.diff
 "#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(
+            schema,
+            vec![Operation::Patch {
+                patch: PatchOp {
+                    file: "README.md".to_string(),
+                    patch: "patches/readme.diff".to_string(),
+                },
+            }]
+        );
+        assert_eq!(schema[0].kind(), "patch");
+    }
 
+    #[test]
+    fn test_parse_header_operation() {
+        let yaml = r#"
+- header:
+    patterns: ["src/**/*.rs"]
+    template: |
+      Copyright 2026 Example Corp.
+      SPDX-License-Identifier: Apache-2.0
+"#;
         let schema = parse(yaml).unwrap();
-        assert_eq!(schema.len(), 1);
+        assert_eq!(
+            schema,
+            vec![Operation::Header {
+                header: HeaderOp {
+                    patterns: vec!["src/**/*.rs".to_string()],
+                    template: "Copyright 2026 Example Corp.\nSPDX-License-Identifier: Apache-2.0\n"
+                        .to_string(),
+                },
+            }]
+        );
+        assert_eq!(schema[0].kind(), "header");
+    }
 
-        match &schema[0] {
-            Operation::Rename { rename } => {
-                assert_eq!(rename.mappings.len(), 2);
-                assert_eq!(rename.mappings[0].from, "badname/(.*)");
-                assert_eq!(rename.mappings[0].to, "goodname/$1");
-                assert_eq!(rename.mappings[1].from, "^files/(.*)");
-                assert_eq!(rename.mappings[1].to, "$1");
-            }
-            _ => panic!("Expected Rename operation"),
-        }
+    #[test]
+    fn test_parse_format_operation() {
+        let schema = parse("- format: editorconfig\n").unwrap();
+        assert_eq!(
+            schema,
+            vec![Operation::Format {
+                format: FormatOp::EditorConfig,
+            }]
+        );
+        assert_eq!(schema[0].kind(), "format");
+
+        assert!(parse("- format: prettier\n").is_err());
     }
 
     #[test]
-    fn test_parse_invalid_yaml() {
-        // A repo operation without a url is invalid (url is required).
-        let invalid_yaml = r#"
-- repo:
-    ref: main
+    fn test_parse_yaml_format_operation() {
+        let yaml = r#"
+- yaml-format:
+    patterns: [".github/workflows/*.yml"]
+    sort-keys: ["jobs.*.env"]
+    quote: double
 "#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(
+            schema,
+            vec![Operation::YamlFormat {
+                yaml_format: YamlFormatOp {
+                    patterns: vec![".github/workflows/*.yml".to_string()],
+                    sort_keys: vec!["jobs.*.env".to_string()],
+                    indent: 2,
+                    quote: QuoteStyle::Double,
+                },
+            }]
+        );
+        assert_eq!(schema[0].kind(), "yaml-format");
 
-        let result = parse(invalid_yaml);
-        match result {
-            Err(crate::error::Error::ConfigParse { message, .. }) => {
-                assert!(
-                    message.contains("missing url"),
-                    "unexpected message: {message}"
-                );
+        // The serialized form parses back to the same operation
+        let serialized = serde_yaml::to_string(&schema).unwrap();
+        assert_eq!(parse(&serialized).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_parse_template_vars_with_secret_sources() {
+        let schema = parse(
+            r#"
+- template-vars:
+    project: demo
+    npm_token: { from_env_file: .env, key: NPM_TOKEN }
+    api_token:
+      from_command: vault kv get -field=token secret/ci
+"#,
+        )
+        .unwrap();
+        let Operation::TemplateVars { template_vars } = &schema[0] else {
+            panic!("Expected TemplateVars operation");
+        };
+        assert_eq!(template_vars.vars.len(), 1);
+        assert_eq!(
+            template_vars.secrets["npm_token"].from_env_file.as_deref(),
+            Some(".env")
+        );
+        assert_eq!(
+            template_vars.secrets["api_token"].from_command.as_deref(),
+            Some("vault kv get -field=token secret/ci")
+        );
+
+        let err = parse("- template-vars:\n    token: { from_sops: s.yaml, from_command: cat }\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("exactly one of"));
+        let err =
+            parse("- template-vars:\n    token: { from_command: cat, key: token }\n").unwrap_err();
+        assert!(err.to_string().contains("'key' with 'from_command'"));
+    }
+
+    #[test]
+    fn test_parse_extract_operation() {
+        let yaml = r#"
+- extract:
+    source: .github/workflows/shared.yml
+    dest: .github/workflows/lint.yml
+    path: jobs.lint
+    keep-path: true
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(
+            schema,
+            vec![Operation::Extract {
+                extract: ExtractOp {
+                    source: ".github/workflows/shared.yml".to_string(),
+                    dest: ".github/workflows/lint.yml".to_string(),
+                    path: Some("jobs.lint".to_string()),
+                    section: None,
+                    level: 2,
+                    keep_path: true,
+                },
+            }]
+        );
+        assert_eq!(schema[0].kind(), "extract");
+
+        // The serialized form parses back to the same operation
+        let serialized = serde_yaml::to_string(&schema).unwrap();
+        assert_eq!(parse(&serialized).unwrap(), schema);
+
+        let err = parse("- extract:\n    source: a.md\n    dest: b.md\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("exactly one of 'path' or 'section'"));
+        let err = parse(
+            "- repo:\n    url: ./up\n    with:\n      - extract:\n          source: a.md\n          dest: b.md\n          section: Usage\n          level: 7\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("heading level 7"));
+    }
+
+    #[test]
+    fn test_parse_assert_operation() {
+        let yaml = r#"
+- assert:
+    path: LICENSE
+    contains: Apache License
+    message: Projects are Apache-2.0 licensed
+- assert:
+    path: .travis.yml
+    absent: true
+"#;
+        let schema = parse(yaml).unwrap();
+        assert_eq!(
+            schema[0],
+            Operation::Assert {
+                assert: AssertOp {
+                    path: "LICENSE".to_string(),
+                    contains: Some("Apache License".to_string()),
+                    matches: None,
+                    absent: false,
+                    message: Some("Projects are Apache-2.0 licensed".to_string()),
+                },
             }
-            other => panic!("expected ConfigParse for missing url, got: {other:?}"),
-        }
+        );
+        assert_eq!(schema[0].kind(), "assert");
+        let Operation::Assert { assert } = &schema[1] else {
+            panic!("expected assert operation");
+        };
+        assert!(assert.absent);
+
+        // The serialized form parses back to the same operations
+        let serialized = serde_yaml::to_string(&schema).unwrap();
+        assert_eq!(parse(&serialized).unwrap(), schema);
+
+        let err = parse("- assert:\n    path: LICENSE\n    absent: true\n    contains: MIT\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("also checks content"));
+        assert!(parse("- assert:\n    path: LICENSE\n    matches: '(unclosed'\n").is_err());
     }
 
     #[test]
-    fn test_parse_empty_config() {
-        let empty_yaml = "";
-        let result = parse(empty_yaml);
-        assert!(result.is_ok());
-        assert!(result.unwrap().is_empty());
+    fn test_parse_file_operation() {
+        let yaml = r#"
+- file:
+    url: https://example.com/policies/CODE_OF_CONDUCT.md?raw=1
+    sha256: 5891B5B522D5DF086D0FF0B110FBD9D21BB4FC7163AF34D08286A2E846F6BE03
+"#;
+        let schema = parse(yaml).unwrap();
+        let Operation::File { file } = &schema[0] else {
+            panic!("expected file operation");
+        };
+        assert_eq!(file.dest_path().unwrap(), "CODE_OF_CONDUCT.md");
+        assert_eq!(schema[0].kind(), "file");
     }
 
     #[test]
-    fn test_parse_malformed_operation() {
-        let malformed_yaml = r#"
-- invalid_operation:
-    some_field: value
+    fn test_parse_vendor_operation() {
+        let yaml = r#"
+- vendor:
+    url: https://github.com/acme/shared-scripts.git
+    ref: v1.4.0
 "#;
+        let schema = parse(yaml).unwrap();
+        let Operation::Vendor { vendor } = &schema[0] else {
+            panic!("expected vendor operation");
+        };
+        assert_eq!(vendor.name().unwrap(), "shared-scripts");
+        assert_eq!(vendor.dest_dir().unwrap(), "vendor/shared-scripts");
+        assert_eq!(schema[0].kind(), "vendor");
 
-        let result = parse(malformed_yaml);
-        // This should fail because the YAML doesn't match any known operation variant
-        assert!(result.is_err());
+        let yaml = "- vendor:\n    url: https://example.com/x.git\n    ref: v1\n    dest: ../x\n";
+        let err = parse(yaml).unwrap_err();
+        assert!(err.to_string().contains("Invalid vendor directory"));
     }
 
     #[test]
-    fn test_from_file_nonexistent() {
-        let result = from_file("nonexistent_file.yaml");
-        assert!(result.is_err());
+    fn test_file_operation_validation() {
+        let file = |url: &str, sha256: Option<&str>| FileOp {
+            url: url.to_string(),
+            sha256: sha256.map(str::to_string),
+            dest: None,
+        };
+        let digest = "0".repeat(64);
+
+        assert!(file("https://example.com/a.md", None).validate().is_ok());
+        assert!(file("http://example.com/a.md", Some(&digest))
+            .validate()
+            .is_ok());
+
+        let err = file("http://example.com/a.md", None)
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("without a sha256"));
+        let err = file("ftp://example.com/a.md", None).validate().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported file URL scheme 'ftp'"));
+        let err = file("https://example.com/a.md", Some("abc"))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid sha256"));
+        let err = file("https://example.com/", None).validate().unwrap_err();
+        assert!(err.to_string().contains("Cannot derive a file name"));
+
+        // Validation runs as part of parsing
+        assert!(parse("- file:\n    url: http://example.com/a.md\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_diff_ignore_is_read_and_removed() {
+        let yaml = "- diff-ignore: ['README.md']\n\
+                    - diff_ignore:\n    lines: ['badge']\n    rules: [yaml-quoting]\n\
+                    - include: ['**']\n";
+        assert_eq!(
+            diff_ignore(yaml).unwrap(),
+            DiffIgnore {
+                paths: vec!["README.md".to_string()],
+                lines: vec!["badge".to_string()],
+                rules: vec![DiffIgnoreRule::YamlQuoting],
+            }
+        );
+        assert_eq!(parse(yaml).unwrap().len(), 1);
+        assert_eq!(
+            diff_ignore("- include: ['**']\n").unwrap(),
+            DiffIgnore::default()
+        );
+
+        assert!(parse("- diff-ignore: 3\n").is_err());
+        assert!(parse("- diff-ignore:\n    lines: ['(']\n").is_err());
+        assert!(parse("- diff-ignore:\n    rules: [sorting]\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_protect_is_read_and_removed() {
+        let yaml = "- protect: read-only\n- include: ['**']\n- protect: [pre-commit]\n";
+        assert_eq!(
+            protect(yaml).unwrap(),
+            Protection {
+                read_only: true,
+                pre_commit: true,
+            }
+        );
+        assert_eq!(parse(yaml).unwrap().len(), 1);
+        assert_eq!(
+            protect("- include: ['**']\n").unwrap(),
+            Protection::default()
+        );
+
+        let err = parse("- protect: locked\n").unwrap_err();
+        assert!(err.to_string().contains("Invalid protect entry"));
+    }
+
+    #[test]
+    fn test_parse_path_scope_is_read_and_removed() {
+        let yaml = "- allow-paths: ['.github/**']\n- include: ['**']\n- allow_paths: docs/**\n- deny-paths: ['.github/CODEOWNERS']\n";
+        let scope = path_scope(yaml).unwrap();
+        assert_eq!(scope.allow, [".github/**", "docs/**"]);
+        assert_eq!(scope.deny, [".github/CODEOWNERS"]);
+        assert_eq!(parse(yaml).unwrap().len(), 1);
+        assert!(path_scope("- include: ['**']\n").unwrap().is_empty());
+
+        // An empty allow list allows everything the deny list does not deny
+        let scope = path_scope("- deny-paths: ['*.lock']\n").unwrap();
+        assert!(scope.allows(Path::new("src/main.rs")).unwrap());
+        assert!(!scope.allows(Path::new("Cargo.lock")).unwrap());
+
+        let err = parse("- allow-paths: {docs: true}\n").unwrap_err();
+        assert!(err.to_string().contains("allow-paths must be a list"));
+        assert!(parse("- deny-paths: ['[']\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_cache_dir_is_read_and_removed() {
+        let yaml = "- cache_dir: build/cache\n- template-vars:\n    name: demo\n";
+        assert_eq!(cache_dir(yaml).unwrap(), Some(PathBuf::from("build/cache")));
+        assert_eq!(parse(yaml).unwrap().len(), 1);
+        assert_eq!(cache_dir("- include: ['**']\n").unwrap(), None);
+
+        let err = parse("- cache-dir: 3\n").unwrap_err();
+        assert!(matches!(err, Error::ConfigParse { .. }), "{err:?}");
+        assert!(cache_dir("- cache-dir: ''\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_requires_must_be_string() {
+        let err = parse("- requires: 0.30\n").unwrap_err();
+        assert!(err.to_string().contains("version constraint string"));
     }
 
     #[test]
@@ -3129,8 +6325,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
             assert!(!op.is_deferred());
         }
@@ -3155,6 +6353,11 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             };
             assert!(!op.is_deferred());
@@ -3329,6 +6532,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_template_files_declaration() {
+        let schema = parse(
+            r#"
+- template:
+    files:
+      ci.yml: sha256:abc
+- template: ["*.tmpl"]
+"#,
+        )
+        .unwrap();
+        let Operation::Template { template } = &schema[0] else {
+            panic!("Expected Template operation");
+        };
+        assert!(template.patterns.is_empty());
+        assert_eq!(template.files["ci.yml"], "sha256:abc");
+        assert_eq!(template.targets(), vec!["ci.yml"]);
+
+        // Each form serializes back to itself.
+        let yaml = serde_yaml::to_string(&schema).unwrap();
+        assert_eq!(parse(&yaml).unwrap(), schema);
+        assert!(yaml.contains("- '*.tmpl'"), "{yaml}");
+
+        let err = parse("- template:\n    file: {ci.yml: x}\n").unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid template operation"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_placeholder_checksum_follows_placeholders_only() {
+        let a = placeholder_checksum(b"name: __COMMON_REPO__name__\n").unwrap();
+        let b = placeholder_checksum(b"# edited\nname:  __COMMON_REPO__name__").unwrap();
+        let c = placeholder_checksum(b"name: __COMMON_REPO__project__\n").unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with("sha256:"));
+        assert_eq!(placeholder_checksum(b"\xff"), None);
+    }
+
     #[test]
     fn test_parse_template_vars_in_with_clause() {
         let yaml = r#"
@@ -3453,6 +6697,25 @@ mod tests {
         assert_eq!(end, InsertPosition::End);
     }
 
+    #[test]
+    fn test_yaml_position_deserialize() {
+        let parse = |yaml: &str| serde_yaml::from_str::<YamlPosition>(yaml);
+        assert_eq!(parse("start").unwrap(), YamlPosition::Start);
+        assert_eq!(parse("end").unwrap(), YamlPosition::End);
+        assert_eq!(
+            parse("after:checkout").unwrap(),
+            YamlPosition::After("checkout".to_string())
+        );
+        assert_eq!(parse("2").unwrap(), YamlPosition::Index(2));
+        let err = parse("middle").unwrap_err().to_string();
+        assert!(err.contains("after:<key>"), "Error was: {}", err);
+        assert!(parse("'after:'").is_err());
+        assert_eq!(
+            serde_yaml::to_string(&YamlPosition::After("on".to_string())).unwrap(),
+            "after:on\n"
+        );
+    }
+
     #[test]
     fn test_insert_position_default() {
         let pos = InsertPosition::default();
@@ -3538,8 +6801,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
             ];
             let upstream_deferred_ops: Vec<Operation> = vec![];
@@ -3657,6 +6922,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
             assert_eq!(op.is_local(), expected, "url={url}");
         }
@@ -3670,6 +6940,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         let result = validate_repo_ref(&schema);
@@ -3687,6 +6962,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         assert!(validate_repo_ref(&schema).is_ok());
@@ -3701,6 +6981,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         assert!(validate_repo_ref(&schema).is_ok());
@@ -3720,6 +7005,11 @@ mod tests {
                 r#ref: Some("v1.0.0".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         assert!(validate_repo_ref(&schema).is_ok());
@@ -3760,6 +7050,7 @@ mod tests {
         let op = IncludeOp {
             patterns: vec!["src/**".to_string()],
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         };
         assert_eq!(op.if_exists, IfExists::Overwrite);
         assert_eq!(op.patterns, vec!["src/**".to_string()]);
@@ -3781,6 +7072,7 @@ mod tests {
         let op = IncludeOp {
             patterns: vec!["src/**".to_string(), "*.md".to_string()],
             if_exists: IfExists::Preserve, // intentionally non-default to prove if_exists is NOT serialized
+            rewrite_links: None,
         };
         let yaml = serde_yaml::to_string(&op).unwrap();
         // Round-trip: parse it back and confirm we get a bare list (and if_exists drops back to Overwrite default)
@@ -3815,7 +7107,9 @@ mod tests {
 "#;
         let schema = parse_original_format(yaml).unwrap();
         match &schema[0] {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include, if_exists, ..
+            } => {
                 assert_eq!(include.patterns, vec!["src/**".to_string()]);
                 assert_eq!(*if_exists, IfExists::Preserve);
                 assert_eq!(include.if_exists, IfExists::Preserve);
@@ -3842,7 +7136,9 @@ mod tests {
 "#;
         let schema = parse_original_format(yaml).unwrap();
         match &schema[0] {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include, if_exists, ..
+            } => {
                 assert_eq!(*if_exists, IfExists::Overwrite);
                 assert_eq!(include.if_exists, IfExists::Overwrite);
             }
@@ -3858,7 +7154,9 @@ mod tests {
 "#;
         let schema = parse_original_format(yaml).unwrap();
         match &schema[0] {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include, if_exists, ..
+            } => {
                 assert_eq!(*if_exists, IfExists::Error);
                 assert_eq!(include.if_exists, IfExists::Error);
             }
@@ -3896,7 +7194,10 @@ mod tests {
 "#;
         let schema = parse(yaml).unwrap();
         if let Operation::Repo { repo } = &schema[0] {
-            if let Operation::Include { include, if_exists } = &repo.with[0] {
+            if let Operation::Include {
+                include, if_exists, ..
+            } = &repo.with[0]
+            {
                 assert_eq!(*if_exists, IfExists::Preserve);
                 assert_eq!(include.if_exists, IfExists::Preserve);
             } else {
@@ -3916,7 +7217,10 @@ mod tests {
 "#;
         let schema = parse(yaml).unwrap();
         if let Operation::Self_ { self_ } = &schema[0] {
-            if let Operation::Include { include, if_exists } = &self_.operations[0] {
+            if let Operation::Include {
+                include, if_exists, ..
+            } = &self_.operations[0]
+            {
                 assert_eq!(*if_exists, IfExists::Preserve);
                 assert_eq!(include.if_exists, IfExists::Preserve);
             } else {
@@ -3937,7 +7241,9 @@ mod tests {
         // Parse succeeds — warning is non-fatal.
         let schema = parse_original_format(yaml).unwrap();
         match &schema[0] {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include, if_exists, ..
+            } => {
                 assert_eq!(include.patterns, vec!["src/**".to_string()]);
                 assert_eq!(*if_exists, IfExists::Overwrite);
                 assert_eq!(include.if_exists, IfExists::Overwrite);
@@ -4054,7 +7360,9 @@ mod tests {
         // drops unknown fields. Parsing succeeds with default if_exists.
         let schema = parse(yaml).unwrap();
         match &schema[0] {
-            Operation::Include { include, if_exists } => {
+            Operation::Include {
+                include, if_exists, ..
+            } => {
                 assert_eq!(
                     *if_exists,
                     IfExists::Overwrite,
@@ -3,7 +3,7 @@
 //! This module provides centralized default values used across commands,
 //! ensuring consistency and avoiding duplication.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// The default configuration filename.
 pub const DEFAULT_CONFIG_FILENAME: &str = ".common-repo.yaml";
@@ -11,6 +11,155 @@ pub const DEFAULT_CONFIG_FILENAME: &str = ".common-repo.yaml";
 /// Alternate configuration filename (without hyphen).
 pub const ALT_CONFIG_FILENAME: &str = ".commonrepo.yaml";
 
+/// The names a project configuration is looked up under, and which of them
+/// a directory or repository uses.
+///
+/// The order of preference is the `config-file:` of the user configuration,
+/// if any, then [`DEFAULT_CONFIG_FILENAME`] and [`ALT_CONFIG_FILENAME`].
+/// Discovery reads upstream configurations with it, the pipeline leaves the
+/// files it names out of upstream and local trees, and commands find the
+/// configuration of the current directory with [`resolve_config_path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFiles {
+    names: Vec<String>,
+}
+
+impl ConfigFiles {
+    /// The names for the user configuration at [`user_config_path`].
+    ///
+    /// A user configuration that cannot be read adds no name; `apply`
+    /// reports it when it sends notifications.
+    pub fn load() -> Self {
+        let config = crate::user_config::UserConfig::load().unwrap_or_default();
+        Self::from_user_config(&config)
+    }
+
+    /// The names for `config`.
+    pub fn from_user_config(config: &crate::user_config::UserConfig) -> Self {
+        let mut names: Vec<String> = config.config_file.iter().cloned().collect();
+        for name in [DEFAULT_CONFIG_FILENAME, ALT_CONFIG_FILENAME] {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        Self { names }
+    }
+
+    /// The names, most preferred first.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Whether `path`, relative to a repository root, is a configuration
+    /// file.
+    pub fn is_config_file(&self, path: &Path) -> bool {
+        self.names.iter().any(|name| path == Path::new(name))
+    }
+
+    /// The name and content of the configuration that `read` finds, trying
+    /// the names in order of preference.
+    ///
+    /// When a less preferred file also exists with different content, a
+    /// warning names both, since edits to the ignored one have no effect.
+    /// `location` names the directory or repository in that warning.
+    pub fn find(
+        &self,
+        location: &str,
+        read: impl Fn(&str) -> Option<Vec<u8>>,
+    ) -> Option<(&str, Vec<u8>)> {
+        let mut found = self
+            .names
+            .iter()
+            .filter_map(|name| Some((name.as_str(), read(name)?)));
+        let (used, content) = found.next()?;
+        for (ignored, other) in found {
+            if other != content {
+                crate::diagnostics::warn(
+                    crate::error::ErrorPhase::Config,
+                    crate::messages::Message::DuplicateConfigFiles {
+                        location,
+                        used,
+                        ignored,
+                    }
+                    .to_string(),
+                );
+            }
+        }
+        Some((used, content))
+    }
+
+    /// The configuration file in `dir`, if it has one.
+    pub fn find_in_dir(&self, dir: &Path) -> Option<PathBuf> {
+        let location = match dir.as_os_str().is_empty() {
+            true => ".".to_string(),
+            false => dir.display().to_string(),
+        };
+        let (name, _) = self.find(&location, |name| {
+            let path = dir.join(name);
+            path.is_file().then(|| std::fs::read(path).ok()).flatten()
+        })?;
+        Some(dir.join(name))
+    }
+}
+
+/// The configuration file a command reads for the `--config` it was given.
+///
+/// A path whose file name is [`DEFAULT_CONFIG_FILENAME`], the default of
+/// `--config`, stands for the configuration of its directory, whichever of
+/// the [`ConfigFiles`] names it has. Any other path is read as given, as is
+/// a directory without a configuration so the command reports it missing.
+pub fn resolve_config_path(requested: &Path) -> PathBuf {
+    if requested.file_name() != Some(DEFAULT_CONFIG_FILENAME.as_ref()) {
+        return requested.to_path_buf();
+    }
+    let dir = requested.parent().unwrap_or(Path::new(""));
+    ConfigFiles::load()
+        .find_in_dir(dir)
+        .unwrap_or_else(|| requested.to_path_buf())
+}
+
+/// The directory that holds the configurations of named pipelines, one
+/// `<name>.yaml` each, applied independently with `apply --pipeline <name>`.
+pub const PIPELINES_DIR: &str = ".common-repo";
+
+/// The directory whose files replace the output at the same paths, after
+/// everything else is merged. Relative to the project directory.
+pub const OVERRIDES_DIR: &str = ".common-repo/overrides";
+
+/// The configuration of the pipeline `name` in `project_dir`.
+pub fn pipeline_config_path(project_dir: &Path, name: &str) -> PathBuf {
+    project_dir
+        .join(PIPELINES_DIR)
+        .join(format!("{}.yaml", name))
+}
+
+/// The names of the pipelines configured in `project_dir`, sorted.
+pub fn pipeline_names(project_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(project_dir.join(PIPELINES_DIR)) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "yaml"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| is_valid_pipeline_name(name))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Whether `name` can name a pipeline: ASCII letters, digits, `-` and `_`.
+///
+/// The name is part of the file names of the pipeline's manifest and
+/// fingerprint, so it must not contain path separators.
+pub fn is_valid_pipeline_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 /// Returns the default cache root directory.
 ///
 /// Uses the platform-appropriate cache directory:
@@ -21,17 +170,154 @@ pub const ALT_CONFIG_FILENAME: &str = ".commonrepo.yaml";
 /// Falls back to `.common-repo-cache` in the current directory if the
 /// platform cache directory cannot be determined.
 ///
-/// This can be overridden by the `--cache-root` CLI flag or the
-/// `COMMON_REPO_CACHE` environment variable.
+/// This can be overridden per project or per run; see
+/// [`resolve_cache_root`].
 pub fn default_cache_root() -> PathBuf {
     dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from(".common-repo-cache"))
         .join("common-repo")
 }
 
+/// The environment variable that names the user configuration file in
+/// place of [`default_user_config_path`].
+pub const USER_CONFIG_ENV: &str = "COMMON_REPO_USER_CONFIG";
+
+/// Returns where the user configuration is read from by default, the
+/// settings of the person running common-repo rather than of a project:
+///
+/// - Linux: `~/.config/common-repo/config.yaml`
+/// - macOS: `~/Library/Application Support/common-repo/config.yaml`
+/// - Windows: `{FOLDERID_RoamingAppData}\common-repo\config.yaml`
+pub fn default_user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("common-repo").join("config.yaml"))
+}
+
+/// Returns the user configuration file: the one [`USER_CONFIG_ENV`] names,
+/// or [`default_user_config_path`].
+pub fn user_config_path() -> Option<PathBuf> {
+    std::env::var_os(USER_CONFIG_ENV)
+        .map(PathBuf::from)
+        .or_else(default_user_config_path)
+}
+
+/// The environment variable that set the cache root before
+/// `COMMON_REPO_CACHE_DIR`, still honored.
+pub const LEGACY_CACHE_ENV: &str = "COMMON_REPO_CACHE";
+
+/// Returns the cache root for a run using the configuration at `config_path`.
+///
+/// In order of precedence:
+///
+/// 1. `explicit`, from the `--cache-dir` flag (or `--cache-root`) or the
+///    `COMMON_REPO_CACHE_DIR` environment variable.
+/// 2. The `COMMON_REPO_CACHE` environment variable.
+/// 3. The `cache-dir:` entry of the configuration, relative to the directory
+///    of the configuration file (see [`crate::config::cache_dir`]).
+/// 4. [`default_cache_root`].
+///
+/// A configuration that is missing or cannot be read is skipped here; the
+/// command reports it when it parses the configuration.
+pub fn resolve_cache_root(explicit: Option<PathBuf>, config_path: &Path) -> PathBuf {
+    explicit
+        .or_else(|| std::env::var_os(LEGACY_CACHE_ENV).map(PathBuf::from))
+        .or_else(|| project_cache_dir(config_path))
+        .unwrap_or_else(default_cache_root)
+}
+
+/// The `cache-dir:` of the configuration at `config_path`, made relative to
+/// its directory.
+fn project_cache_dir(config_path: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(config_path).ok()?;
+    let cache_dir = crate::config::cache_dir(&content).ok()??;
+    let project_dir = config_path.parent().unwrap_or(Path::new(""));
+    Some(project_dir.join(cache_dir))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::user_config::UserConfig;
+
+    #[test]
+    fn test_config_files_names_and_preference() {
+        let default = ConfigFiles::from_user_config(&UserConfig::default());
+        assert_eq!(
+            default.names(),
+            [DEFAULT_CONFIG_FILENAME, ALT_CONFIG_FILENAME]
+        );
+        assert!(default.is_config_file(Path::new(ALT_CONFIG_FILENAME)));
+        assert!(!default.is_config_file(Path::new("docs/.common-repo.yaml")));
+
+        let custom = ConfigFiles::from_user_config(&UserConfig {
+            config_file: Some(".common-repo.yml".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            custom.names(),
+            [
+                ".common-repo.yml",
+                DEFAULT_CONFIG_FILENAME,
+                ALT_CONFIG_FILENAME
+            ]
+        );
+
+        let project = tempfile::TempDir::new().unwrap();
+        assert_eq!(default.find_in_dir(project.path()), None);
+        std::fs::write(
+            project.path().join(ALT_CONFIG_FILENAME),
+            "- include: ['*']\n",
+        )
+        .unwrap();
+        assert_eq!(
+            default.find_in_dir(project.path()),
+            Some(project.path().join(ALT_CONFIG_FILENAME))
+        );
+        std::fs::write(
+            project.path().join(DEFAULT_CONFIG_FILENAME),
+            "- exclude: ['*']\n",
+        )
+        .unwrap();
+        std::fs::write(project.path().join(".common-repo.yml"), "[]\n").unwrap();
+        assert_eq!(
+            default.find_in_dir(project.path()),
+            Some(project.path().join(DEFAULT_CONFIG_FILENAME))
+        );
+        assert_eq!(
+            custom.find_in_dir(project.path()),
+            Some(project.path().join(".common-repo.yml"))
+        );
+    }
+
+    #[test]
+    fn test_config_files_warns_when_both_differ() {
+        let files = ConfigFiles::from_user_config(&UserConfig::default());
+        let read = |same: bool| {
+            move |name: &str| match name {
+                DEFAULT_CONFIG_FILENAME => Some(b"- include: ['*']\n".to_vec()),
+                _ if same => Some(b"- include: ['*']\n".to_vec()),
+                _ => Some(b"- exclude: ['*']\n".to_vec()),
+            }
+        };
+
+        let (found, diagnostics) = crate::diagnostics::collect(|| {
+            files
+                .find("org/repo", read(true))
+                .map(|(n, _)| n.to_string())
+        });
+        assert_eq!(found.as_deref(), Some(DEFAULT_CONFIG_FILENAME));
+        assert!(diagnostics.is_empty());
+
+        let (found, diagnostics) = crate::diagnostics::collect(|| {
+            files
+                .find("org/repo", read(false))
+                .map(|(n, _)| n.to_string())
+        });
+        assert_eq!(found.as_deref(), Some(DEFAULT_CONFIG_FILENAME));
+        let warnings: Vec<String> = diagnostics.iter().map(|d| d.message.clone()).collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("org/repo"), "{:?}", warnings);
+        assert!(warnings[0].contains(ALT_CONFIG_FILENAME), "{:?}", warnings);
+    }
 
     #[test]
     fn test_default_cache_root_returns_path() {
@@ -40,6 +326,47 @@ mod tests {
         assert!(cache_root.ends_with("common-repo"));
     }
 
+    #[test]
+    fn test_resolve_cache_root_from_config_is_relative_to_project() {
+        let project = tempfile::TempDir::new().unwrap();
+        let config_path = project.path().join(DEFAULT_CONFIG_FILENAME);
+        std::fs::write(&config_path, "- cache-dir: .common-repo/cache\n").unwrap();
+
+        let explicit = PathBuf::from("/tmp/explicit");
+        assert_eq!(
+            resolve_cache_root(Some(explicit.clone()), &config_path),
+            explicit
+        );
+        if std::env::var_os(LEGACY_CACHE_ENV).is_none() {
+            assert_eq!(
+                resolve_cache_root(None, &config_path),
+                project.path().join(".common-repo/cache")
+            );
+            assert_eq!(
+                resolve_cache_root(None, &project.path().join("missing.yaml")),
+                default_cache_root()
+            );
+        }
+    }
+
+    #[test]
+    fn test_pipeline_names() {
+        let project = tempfile::TempDir::new().unwrap();
+        assert!(pipeline_names(project.path()).is_empty());
+
+        let dir = project.path().join(PIPELINES_DIR);
+        std::fs::create_dir_all(dir.join("cache")).unwrap();
+        for file in ["docs.yaml", "ci.yaml", "notes.txt", "bad name.yaml"] {
+            std::fs::write(dir.join(file), "- include: [\"**/*\"]\n").unwrap();
+        }
+        assert_eq!(pipeline_names(project.path()), vec!["ci", "docs"]);
+        assert_eq!(
+            pipeline_config_path(project.path(), "ci"),
+            dir.join("ci.yaml")
+        );
+        assert!(!is_valid_pipeline_name("../ci"));
+    }
+
     #[test]
     fn test_default_cache_root_is_absolute_or_fallback() {
         let cache_root = default_cache_root();
@@ -0,0 +1,252 @@
+//! # Pipeline Diagnostics
+//!
+//! Non-fatal problems found while running the pipeline, such as a merge that
+//! replaces a table with a scalar or a `repo:` reference that was skipped,
+//! are reported as [`Diagnostic`]s rather than printed. The caller of
+//! [`crate::phases::orchestrator::execute_pull`] passes a [`Diagnostics`]
+//! collector and decides what to do with the result: render it, count it,
+//! serialize it to JSON, or fail.
+//!
+//! ## Reporting
+//!
+//! Code anywhere in the pipeline reports with [`warn`]. While a pipeline runs,
+//! [`collect`] installs a collector for the current thread and every report
+//! lands in it. Outside of a collector, for example when a merge function is
+//! called directly, reports fall back to [`log::warn!`].
+//!
+//! Work handed to other threads (parallel cloning in discovery) must pass its
+//! findings back to the calling thread before reporting them.
+//!
+//! A report made while an operation with a `description:` runs (see
+//! [`crate::config::description`]) ends with that description, so whoever
+//! debugs a merge warning sees why the operation exists.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::diagnostics;
+//!
+//! let (value, diagnostics) = diagnostics::collect(|| 42);
+//! assert_eq!(value, 42);
+//! assert!(diagnostics.is_empty());
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::error::ErrorPhase;
+
+/// A single non-fatal problem reported by the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Pipeline phase that reported the problem.
+    pub phase: ErrorPhase,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.phase.as_str(), self.message)
+    }
+}
+
+/// Ordered collection of diagnostics from one or more pipeline runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Create an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic);
+    }
+
+    /// Append every diagnostic in `other`.
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.items.extend(other.items);
+    }
+
+    /// Number of diagnostics.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether no diagnostics were reported.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterate over the diagnostics in the order they were reported.
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+
+    /// Serialize as a pretty-printed JSON array.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("diagnostics serialize to JSON")
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<Vec<Diagnostic>>> = const { RefCell::new(Vec::new()) };
+    static DESCRIPTIONS: RefCell<Vec<Option<String>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Pops the collector frame even if the closure panics.
+struct Frame;
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| {
+            active.borrow_mut().pop();
+        });
+    }
+}
+
+/// Pops the description pushed by [`described`] even if the closure panics.
+struct Described;
+
+impl Drop for Described {
+    fn drop(&mut self) {
+        DESCRIPTIONS.with(|descriptions| {
+            descriptions.borrow_mut().pop();
+        });
+    }
+}
+
+/// Run `f` for an operation with `description`, which is added to every
+/// diagnostic `f` reports on this thread.
+///
+/// Calls nest: an inner operation without a description hides the outer
+/// one's.
+pub(crate) fn described<T>(description: Option<String>, f: impl FnOnce() -> T) -> T {
+    DESCRIPTIONS.with(|descriptions| descriptions.borrow_mut().push(description));
+    let _described = Described;
+    f()
+}
+
+/// The description of the operation running on this thread, if it has one.
+pub(crate) fn current_description() -> Option<String> {
+    DESCRIPTIONS.with(|descriptions| descriptions.borrow().last().cloned().flatten())
+}
+
+/// Run `f`, collecting every diagnostic it reports on this thread.
+///
+/// Calls nest: diagnostics go to the innermost collector only.
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, Diagnostics) {
+    ACTIVE.with(|active| active.borrow_mut().push(Vec::new()));
+    let frame = Frame;
+    let value = f();
+    let items = ACTIVE.with(|active| std::mem::take(active.borrow_mut().last_mut().unwrap()));
+    drop(frame);
+    (value, Diagnostics { items })
+}
+
+/// Report a warning from `phase`.
+///
+/// Goes to the innermost active collector, or to [`log::warn!`] if there is
+/// none.
+pub fn warn(phase: ErrorPhase, message: impl Into<String>) {
+    let mut message = message.into();
+    if let Some(description) = current_description() {
+        message = format!("{} (operation: {})", message, description);
+    }
+    let diagnostic = Diagnostic { phase, message };
+    let unhandled = ACTIVE.with(|active| match active.borrow_mut().last_mut() {
+        Some(frame) => {
+            log::debug!("diagnostic: {}", diagnostic);
+            frame.push(diagnostic);
+            None
+        }
+        None => Some(diagnostic),
+    });
+    if let Some(diagnostic) = unhandled {
+        log::warn!("{}", diagnostic.message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_captures_warnings_in_order() {
+        let ((), diagnostics) = collect(|| {
+            warn(ErrorPhase::Merge, "first");
+            warn(ErrorPhase::Discovery, "second");
+        });
+
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, vec!["merge: first", "discovery: second"]);
+    }
+
+    #[test]
+    fn test_collect_nests_and_unwinds() {
+        let ((inner, ()), outer) = collect(|| {
+            let (_, inner) = collect(|| warn(ErrorPhase::Merge, "inner"));
+            warn(ErrorPhase::Merge, "outer");
+            (inner, ())
+        });
+        assert_eq!(inner.len(), 1);
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer.iter().next().unwrap().message, "outer");
+
+        // A panicking closure still removes its frame.
+        let _ = std::panic::catch_unwind(|| collect(|| panic!("boom")));
+        ACTIVE.with(|active| assert!(active.borrow().is_empty()));
+    }
+
+    #[test]
+    fn test_described_adds_the_operation_description() {
+        let ((), diagnostics) = collect(|| {
+            described(Some("Shared lint settings".to_string()), || {
+                warn(ErrorPhase::Merge, "replaced table");
+                described(None, || warn(ErrorPhase::Merge, "inner"));
+            });
+            warn(ErrorPhase::Merge, "after");
+        });
+
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "replaced table (operation: Shared lint settings)",
+                "inner",
+                "after"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic {
+            phase: ErrorPhase::Merge,
+            message: "replaced array".to_string(),
+        });
+        let value: serde_json::Value = serde_json::from_str(&diagnostics.to_json()).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([{ "phase": "merge", "message": "replaced array" }])
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! # Drift Filtering
+//!
+//! Decides which differences between a consumer's files and the
+//! configuration's output are expected, according to the config's
+//! `- diff-ignore:` entry (see [`crate::config::DiffIgnore`]), so that
+//! `diff` reports only real drift.
+//!
+//! A difference is expected when the file matches one of the `paths`
+//! globs, or when both versions are equal once the `lines` patterns and the
+//! format `rules` are taken into account. Files that are only on one side
+//! are expected only when their path is ignored.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::config::DiffIgnore;
+//! use common_repo::drift::DriftFilter;
+//! use std::path::Path;
+//!
+//! let filter = DriftFilter::new(&DiffIgnore {
+//!     lines: vec![r"^\[!\[".to_string()],
+//!     ..DiffIgnore::default()
+//! })
+//! .unwrap();
+//! let ours = b"# Demo\n[![ci](badge.svg)](ci)\nText\n";
+//! assert!(filter.is_equivalent(Path::new("README.md"), ours, b"# Demo\nText\n"));
+//! ```
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::config::{DiffIgnore, DiffIgnoreRule};
+use crate::error::{Error, Result};
+use crate::filesystem::FsDiffEntry;
+use crate::path::PatternSet;
+
+/// A compiled `diff-ignore` entry.
+#[derive(Debug, Clone)]
+pub struct DriftFilter {
+    paths: PatternSet,
+    lines: Vec<Regex>,
+    rules: Vec<DiffIgnoreRule>,
+}
+
+impl DriftFilter {
+    /// Compiles the globs and regexes of `ignore`.
+    pub fn new(ignore: &DiffIgnore) -> Result<Self> {
+        let lines = ignore
+            .lines
+            .iter()
+            .map(|line| {
+                Regex::new(line).map_err(|e| Error::ConfigParse {
+                    message: format!("Invalid diff-ignore line pattern '{}': {}", line, e),
+                    hint: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            paths: PatternSet::new(&ignore.paths)?,
+            lines,
+            rules: ignore.rules.clone(),
+        })
+    }
+
+    /// Whether the difference `entry` is expected and should not be
+    /// reported.
+    pub fn ignores(&self, entry: &FsDiffEntry) -> bool {
+        if self.ignores_path(&entry.path) {
+            return true;
+        }
+        match (&entry.old, &entry.new) {
+            (Some(old), Some(new)) => self.is_equivalent(&entry.path, old, new),
+            _ => false,
+        }
+    }
+
+    /// Whether every difference in the file at `path` is expected, content
+    /// and permissions alike.
+    pub fn ignores_path(&self, path: &Path) -> bool {
+        self.paths.is_match(path)
+    }
+
+    /// Whether two versions of the file at `path` are equal, once ignored
+    /// lines and the format rules are taken into account.
+    pub fn is_equivalent(&self, path: &Path, a: &[u8], b: &[u8]) -> bool {
+        if a == b {
+            return true;
+        }
+        let (Ok(a), Ok(b)) = (std::str::from_utf8(a), std::str::from_utf8(b)) else {
+            return false;
+        };
+        let (a, b) = (self.kept_lines(a), self.kept_lines(b));
+        if a == b {
+            return true;
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.rules.iter().any(|rule| match rule {
+            DiffIgnoreRule::YamlQuoting => {
+                matches!(extension, "yml" | "yaml")
+                    && same_value(
+                        serde_yaml::from_str::<serde_yaml::Value>(&a),
+                        serde_yaml::from_str(&b),
+                    )
+            }
+            DiffIgnoreRule::JsonFormatting => {
+                extension == "json"
+                    && same_value(
+                        serde_json::from_str::<serde_json::Value>(&a),
+                        serde_json::from_str(&b),
+                    )
+            }
+            DiffIgnoreRule::TrailingWhitespace => trimmed(&a) == trimmed(&b),
+        })
+    }
+
+    /// `text` without the lines that match a `lines` pattern.
+    fn kept_lines(&self, text: &str) -> String {
+        if self.lines.is_empty() {
+            return text.to_string();
+        }
+        text.split_inclusive('\n')
+            .filter(|line| {
+                let line = line.trim_end_matches(['\r', '\n']);
+                !self.lines.iter().any(|pattern| pattern.is_match(line))
+            })
+            .collect()
+    }
+}
+
+/// Whether `a` and `b` both parsed, to the same value.
+fn same_value<T: PartialEq, E>(a: std::result::Result<T, E>, b: std::result::Result<T, E>) -> bool {
+    match (a, b) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The lines of `text` without trailing whitespace, and without trailing
+/// blank lines.
+fn trimmed(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFS;
+
+    fn filter(rules: &[DiffIgnoreRule]) -> DriftFilter {
+        DriftFilter::new(&DiffIgnore {
+            rules: rules.to_vec(),
+            ..DiffIgnore::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rules_apply_to_their_format() {
+        let yaml = filter(&[DiffIgnoreRule::YamlQuoting]);
+        assert!(yaml.is_equivalent(Path::new("ci.yml"), b"a: 'x'\n", b"a: \"x\" # c\n"));
+        assert!(!yaml.is_equivalent(Path::new("ci.yml"), b"a: x\n", b"a: y\n"));
+        assert!(!yaml.is_equivalent(Path::new("ci.txt"), b"a: 'x'\n", b"a: x\n"));
+
+        let json = filter(&[DiffIgnoreRule::JsonFormatting]);
+        assert!(json.is_equivalent(Path::new("a.json"), b"{\"a\":1}", b"{\n  \"a\": 1\n}\n"));
+        assert!(!json.is_equivalent(Path::new("a.json"), b"{\"a\":1}", b"{\"a\":2}"));
+
+        let ws = filter(&[DiffIgnoreRule::TrailingWhitespace]);
+        assert!(ws.is_equivalent(Path::new("a.txt"), b"a  \nb\n\n", b"a\nb"));
+        assert!(!ws.is_equivalent(Path::new("a.txt"), b" a\n", b"a\n"));
+    }
+
+    #[test]
+    fn test_ignores_paths_and_lines() {
+        let filter = DriftFilter::new(&DiffIgnore {
+            paths: vec!["local/**".to_string()],
+            lines: vec!["badge".to_string()],
+            rules: Vec::new(),
+        })
+        .unwrap();
+        let mut ours = MemoryFS::new();
+        ours.add_file_string("README.md", "# A\nbadge here\n")
+            .unwrap();
+        ours.add_file_string("local/notes.md", "mine").unwrap();
+        ours.add_file_string("other.md", "mine").unwrap();
+        let mut theirs = MemoryFS::new();
+        theirs.add_file_string("README.md", "# A\n").unwrap();
+        theirs.add_file_string("other.md", "theirs").unwrap();
+
+        let kept: Vec<_> = ours
+            .diff(&theirs)
+            .iter()
+            .filter(|entry| !filter.ignores(entry))
+            .map(|entry| entry.path.clone())
+            .collect();
+        assert_eq!(kept, vec![Path::new("other.md").to_path_buf()]);
+    }
+}
@@ -28,6 +28,7 @@
 //! - Filesystem operations.
 //! - Path operations.
 //! - Tool validation errors.
+//! - Unmet upstream requirements.
 //! - Template processing errors.
 //! - Merge operation errors.
 //! - Network errors.
@@ -48,7 +49,26 @@
 //! The `Result` type alias is used to return `Result<T, Error>` from
 //! functions, making it easy to handle errors and propagate them up the
 //! call stack.
+//!
+//! ## Error Codes
+//!
+//! Every variant maps to a stable code (see [`Error::code`]) that wrapper
+//! tooling and documentation can refer to. The leading digit names the
+//! pipeline phase the failure belongs to:
+//!
+//! | Range   | Phase                                     |
+//! |---------|-------------------------------------------|
+//! | `E0xxx` | Configuration parsing                     |
+//! | `E1xxx` | Discovery and fetching (phase 1)          |
+//! | `E2xxx` | Operator processing (phase 2)             |
+//! | `E4xxx` | Merging into the composite (phase 4)      |
+//! | `E6xxx` | Filesystem and output (phase 6)           |
+//! | `E9xxx` | Internal errors                           |
+//!
+//! Codes are never reused or renumbered. [`explain`] looks up the long-form
+//! description for a code, which is what `common-repo explain-error` prints.
 
+use serde::Serialize;
 use thiserror::Error;
 
 /// Main error type for common-repo operations
@@ -65,6 +85,18 @@ pub enum Error {
         hint: Option<String>,
     },
 
+    /// A configuration declares `requires:` with a version constraint that
+    /// this build of common-repo does not satisfy.
+    ///
+    /// `origin` names the upstream repository whose configuration declared
+    /// it, or is `None` for the local configuration.
+    #[error("{} requires common-repo {required}, but this is {current}\n  hint: upgrade common-repo to a release matching {required}", origin.as_ref().map(|o| format!("Upstream configuration {}", o)).unwrap_or_else(|| "This configuration".to_string()))]
+    VersionRequirement {
+        required: String,
+        current: String,
+        origin: Option<String>,
+    },
+
     /// An error occurred while cloning a Git repository.
     ///
     /// Includes the repository URL, ref (branch/tag), error message, and an
@@ -90,6 +122,50 @@ pub enum Error {
     #[error("Cache operation error: {message}")]
     Cache { message: String },
 
+    /// A single file could not be downloaded for a `file:` operation.
+    #[error("Download error for {url}: {message}{}", hint.as_ref().map(|h| format!("\n  hint: {}", h)).unwrap_or_default())]
+    Download {
+        url: String,
+        message: String,
+        /// Optional hint for how to resolve the download issue
+        hint: Option<String>,
+    },
+
+    /// A downloaded file does not match its pinned `sha256`.
+    #[error("Checksum mismatch for {url}: expected sha256 {expected}, got {actual}\n  hint: the file changed upstream; review it and update the pinned sha256")]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// An OCI artifact could not be pulled for an `oci://` repo.
+    #[error("OCI pull error for {reference}: {message}{}", hint.as_ref().map(|h| format!("\n  hint: {}", h)).unwrap_or_default())]
+    OciPull {
+        reference: String,
+        message: String,
+        /// Optional hint for how to resolve the pull issue
+        hint: Option<String>,
+    },
+
+    /// The tag of an `oci://` repo no longer resolves to its pinned digest.
+    #[error("Digest mismatch for {reference}: expected {expected}, got {actual}\n  hint: the tag was moved; review the new artifact and update the pinned digest")]
+    DigestMismatch {
+        reference: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// A repo with `verify:` is not signed by any of its trusted signers.
+    #[error("Signature verification failed for {url}@{r#ref}: {message}{}", hint.as_ref().map(|h| format!("\n  hint: {}", h)).unwrap_or_default())]
+    SignatureVerification {
+        url: String,
+        r#ref: String,
+        message: String,
+        /// Optional hint for how to resolve the verification issue
+        hint: Option<String>,
+    },
+
     /// An error occurred during the execution of an operator.
     #[error("Operator execution error: {operator} - {message}")]
     Operator { operator: String, message: String },
@@ -126,6 +202,16 @@ pub enum Error {
         message: String,
     },
 
+    /// Files an upstream marked `locked: true` were modified locally, by an
+    /// edit or an override. Lists every such file with its upstream.
+    #[error("Refusing to override locked files:{}\n  hint: restore the upstream's content and remove any overrides of these files; ask the upstream to change them instead", paths.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    LockedFilesModified { paths: Vec<String> },
+
+    /// Local files a plan was merged with, or that it would write over,
+    /// changed since the plan was computed.
+    #[error("Local files changed since the plan was computed: {}\n  hint: run `common-repo diff --save-plan` again so the plan includes the changes", paths.join(", "))]
+    StalePlan { paths: Vec<String> },
+
     /// An error occurred with an in-memory filesystem operation.
     #[error("Filesystem operation error: {message}")]
     Filesystem { message: String },
@@ -134,10 +220,45 @@ pub enum Error {
     #[error("Path operation error: {message}")]
     Path { message: String },
 
+    /// Writing the output would go through symlinks on disk, changing the
+    /// files they point to. Lists each symlink with its target.
+    #[error("Refusing to write through symlinks:{}\n  hint: replace the links with regular files, or re-run with --follow-symlinks to write to their targets", paths.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    SymlinkedOutput { paths: Vec<String> },
+
+    /// Files to be written exist on disk as read-only files with different
+    /// content. Lists every such file.
+    #[error("Refusing to overwrite read-only files:{}\n  hint: make the files writable, or re-run with --read-only overwrite or --read-only skip", paths.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    ReadOnlyOutput { paths: Vec<String> },
+
+    /// Paths to be written cannot be stored on the host: they are not valid
+    /// UTF-8, contain control characters, or are too long. Lists each path
+    /// with the problem.
+    #[error("Cannot write paths the filesystem does not allow:{}\n  hint: rename the files with a `rename` operation, exclude them, or use a shorter output path", paths.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    UnsafeOutputPaths { paths: Vec<String> },
+
+    /// A `patch` operation's diff does not apply to the file it targets,
+    /// usually because the upstream changed the lines the patch touches.
+    #[error("Patch {patch} does not apply to {file}: {message}\n  hint: regenerate the patch against the current {file}")]
+    Patch {
+        file: String,
+        patch: String,
+        message: String,
+    },
+
     /// An error occurred during tool validation.
     #[error("Tool validation error: {tool} - {message}")]
     ToolValidation { tool: String, message: String },
 
+    /// The consumer does not meet what its upstreams require of it with
+    /// `requires:` blocks. Lists every unmet requirement.
+    #[error("Upstream requirements not met:{}", problems.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    RequirementsNotMet { problems: Vec<String> },
+
+    /// The composed output does not hold what `assert` operations expect of
+    /// it. Lists every failed assertion with where it was declared.
+    #[error("Assertions failed:{}\n  hint: change the output so it meets them, or ask the upstream that declares them", failures.iter().map(|f| format!("\n  - {}", f)).collect::<String>())]
+    AssertionsFailed { failures: Vec<String> },
+
     /// An error occurred during template processing.
     ///
     /// May include the name of the problematic variable when applicable.
@@ -152,6 +273,11 @@ pub enum Error {
     #[error("Merge operation error: {operation} - {message}")]
     Merge { operation: String, message: String },
 
+    /// A merge path ran into a value of the wrong type, such as a key lookup
+    /// on a sequence.
+    #[error("Merge type mismatch: {operation} - {message}")]
+    MergeTypeMismatch { operation: String, message: String },
+
     /// An error occurred during a network operation.
     #[error("Network operation error: {url} - {message}")]
     Network { url: String, message: String },
@@ -196,6 +322,431 @@ pub enum Error {
 /// A convenient type alias for `Result<T, Error>`.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The pipeline phase an error code belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPhase {
+    /// Reading and parsing configuration (`E0xxx`).
+    Config,
+    /// Discovering and fetching repositories (`E1xxx`).
+    Discovery,
+    /// Applying operators to repository contents (`E2xxx`).
+    Processing,
+    /// Merging intermediate results into the composite (`E4xxx`).
+    Merge,
+    /// Filesystem access and writing output (`E6xxx`).
+    Output,
+    /// Internal failures that indicate a bug (`E9xxx`).
+    Internal,
+}
+
+impl ErrorPhase {
+    /// Lowercase name of the phase, as used in JSON output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorPhase::Config => "config",
+            ErrorPhase::Discovery => "discovery",
+            ErrorPhase::Processing => "processing",
+            ErrorPhase::Merge => "merge",
+            ErrorPhase::Output => "output",
+            ErrorPhase::Internal => "internal",
+        }
+    }
+}
+
+/// Documentation for a single error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorInfo {
+    /// Stable code, e.g. `E4002`.
+    pub code: &'static str,
+    /// Phase the code belongs to.
+    pub phase: ErrorPhase,
+    /// One-line summary.
+    pub title: &'static str,
+    /// What usually causes the error and how to fix it.
+    pub explanation: &'static str,
+}
+
+/// Every error code, in ascending order.
+pub const ERROR_CODES: &[ErrorInfo] = &[
+    ErrorInfo {
+        code: "E0001",
+        phase: ErrorPhase::Config,
+        title: "Invalid configuration",
+        explanation: "The .common-repo.yaml file could not be interpreted. The message names the \
+            offending operation or field. Run `common-repo validate` for a full report.",
+    },
+    ErrorInfo {
+        code: "E0002",
+        phase: ErrorPhase::Config,
+        title: "YAML syntax error",
+        explanation: "A YAML document is not well-formed. Check indentation, unclosed brackets \
+            and quoting at the reported line and column.",
+    },
+    ErrorInfo {
+        code: "E0003",
+        phase: ErrorPhase::Config,
+        title: "Invalid URL",
+        explanation: "A repository URL could not be parsed. Use an https:// or git@ URL, a \
+            GitHub shorthand such as org/repo, or a local path starting with ./, ../ or /.",
+    },
+    ErrorInfo {
+        code: "E0004",
+        phase: ErrorPhase::Config,
+        title: "Invalid version",
+        explanation: "A ref or version constraint is not valid semantic versioning. Tags are \
+            compared as semver, optionally with a leading `v`.",
+    },
+    ErrorInfo {
+        code: "E0005",
+        phase: ErrorPhase::Config,
+        title: "Newer common-repo required",
+        explanation: "A configuration, local or upstream, declares `requires:` with a version \
+            constraint that this build does not satisfy, usually because it uses operations \
+            added in a later release. Upgrade common-repo; `common-repo --version` shows the \
+            installed version.",
+    },
+    ErrorInfo {
+        code: "E1001",
+        phase: ErrorPhase::Discovery,
+        title: "Repository dependency cycle",
+        explanation: "A repository inherits, directly or through other upstreams, from itself. \
+            The message shows the cycle. Remove one of the `repo:` references that closes it.",
+    },
+    ErrorInfo {
+        code: "E1002",
+        phase: ErrorPhase::Discovery,
+        title: "Clone failed",
+        explanation: "A repository could not be cloned at the requested ref. Check that the URL \
+            is correct, the ref exists, and your git credentials grant access.",
+    },
+    ErrorInfo {
+        code: "E1003",
+        phase: ErrorPhase::Discovery,
+        title: "Git command failed",
+        explanation: "A git command exited with an error. The message includes git's stderr, \
+            which usually names the problem.",
+    },
+    ErrorInfo {
+        code: "E1004",
+        phase: ErrorPhase::Discovery,
+        title: "Network error",
+        explanation: "A remote could not be reached. Check connectivity and proxy settings, or \
+            retry later if the host is rate limiting.",
+    },
+    ErrorInfo {
+        code: "E1005",
+        phase: ErrorPhase::Discovery,
+        title: "Local repository path not found",
+        explanation: "A local `repo:` path does not exist. Relative paths are resolved against \
+            the directory of the config file that declares them.",
+    },
+    ErrorInfo {
+        code: "E1006",
+        phase: ErrorPhase::Discovery,
+        title: "Local repository path is not a directory",
+        explanation: "A local `repo:` path points at a file. It must point at the root directory \
+            of the upstream repository.",
+    },
+    ErrorInfo {
+        code: "E1007",
+        phase: ErrorPhase::Discovery,
+        title: "Cache error",
+        explanation: "The repository cache could not be read or written. Check permissions on \
+            the cache directory, or clear it with `common-repo cache clean --all`.",
+    },
+    ErrorInfo {
+        code: "E1008",
+        phase: ErrorPhase::Discovery,
+        title: "Download failed",
+        explanation: "A `file:` operation could not download its URL. Check the URL and \
+            network access; credentials for private hosts are read from ~/.netrc by curl.",
+    },
+    ErrorInfo {
+        code: "E1009",
+        phase: ErrorPhase::Discovery,
+        title: "Checksum mismatch",
+        explanation: "A file downloaded by a `file:` operation does not match its pinned \
+            `sha256`, so it changed since it was pinned. Review the new content and update \
+            the digest, or point the URL at the pinned version.",
+    },
+    ErrorInfo {
+        code: "E1010",
+        phase: ErrorPhase::Discovery,
+        title: "OCI pull failed",
+        explanation: "An `oci://` repo could not be pulled with the oras CLI. Check that oras \
+            is installed, the reference exists, and you are logged in to the registry with \
+            `oras login`.",
+    },
+    ErrorInfo {
+        code: "E1011",
+        phase: ErrorPhase::Discovery,
+        title: "Digest mismatch",
+        explanation: "The tag of an `oci://` repo pinned as `<tag>@<digest>` now points at a \
+            different artifact. Review the new artifact and update the digest, or pin the \
+            digest alone.",
+    },
+    ErrorInfo {
+        code: "E1012",
+        phase: ErrorPhase::Discovery,
+        title: "Signature verification failed",
+        explanation: "A repo with `verify:` was fetched, but its tag or commit is not signed by \
+            any of the listed GPG keys or Sigstore identities. Check that the publisher signed \
+            the release, that GPG keys are imported with `gpg --import`, and that gitsign is \
+            installed for Sigstore identities.",
+    },
+    ErrorInfo {
+        code: "E2001",
+        phase: ErrorPhase::Processing,
+        title: "Operator failed",
+        explanation: "An operator could not be applied. The message names the operator and the \
+            reason, such as an operation that is not allowed in a `with:` clause.",
+    },
+    ErrorInfo {
+        code: "E2002",
+        phase: ErrorPhase::Processing,
+        title: "Template error",
+        explanation: "Template substitution failed, most often because a __COMMON_REPO__VAR__ \
+            placeholder has no matching `template-vars` entry. Declare the variable in the \
+            consumer or the upstream config.",
+    },
+    ErrorInfo {
+        code: "E2003",
+        phase: ErrorPhase::Processing,
+        title: "Required tool missing",
+        explanation: "A tool declared in a `tools:` operation is not installed or does not \
+            satisfy the version constraint.",
+    },
+    ErrorInfo {
+        code: "E2004",
+        phase: ErrorPhase::Processing,
+        title: "Invalid regex",
+        explanation: "A `rename` pattern is not a valid regular expression. Remember that \
+            backslashes must be doubled inside double-quoted YAML strings.",
+    },
+    ErrorInfo {
+        code: "E2005",
+        phase: ErrorPhase::Processing,
+        title: "Invalid glob pattern",
+        explanation: "An `include`, `exclude` or `template` pattern is not a valid glob. Check \
+            for unbalanced `[` brackets.",
+    },
+    ErrorInfo {
+        code: "E2006",
+        phase: ErrorPhase::Processing,
+        title: "Patch does not apply",
+        explanation: "A `patch` operation's diff no longer matches the file it targets, usually \
+            because an upstream update changed the lines the patch touches. Regenerate the \
+            patch against the current file, or remove it if the change is now upstream.",
+    },
+    ErrorInfo {
+        code: "E2007",
+        phase: ErrorPhase::Processing,
+        title: "Upstream requirements not met",
+        explanation: "An upstream declares in a `requires:` block what configurations that \
+            inherit from it must provide: template variables, files in the consumer's \
+            directory, or installed tools. The message lists each one that is missing and the \
+            upstream that requires it. Nothing is processed until all of them are met.",
+    },
+    ErrorInfo {
+        code: "E2008",
+        phase: ErrorPhase::Processing,
+        title: "Assertions failed",
+        explanation: "An `assert` operation, in the configuration or in an upstream's, expects \
+            the composed output to contain a file, to leave one out, or to have certain \
+            content in it, and the output does not. The message lists each failed assertion \
+            with the repository that declares it and the reason it exists, when given. \
+            Nothing is written.",
+    },
+    ErrorInfo {
+        code: "E4001",
+        phase: ErrorPhase::Merge,
+        title: "Merge failed",
+        explanation: "A merge operator could not complete, for example because the source file \
+            does not exist or a file could not be parsed in the declared format.",
+    },
+    ErrorInfo {
+        code: "E4002",
+        phase: ErrorPhase::Merge,
+        title: "Merge type mismatch",
+        explanation: "The merge `path` runs through a value of the wrong type, such as a key \
+            lookup on a list or an index into a mapping. Fix the path, or change the \
+            destination file so the structure matches.",
+    },
+    ErrorInfo {
+        code: "E4003",
+        phase: ErrorPhase::Merge,
+        title: "Merge conflict",
+        explanation: "Two sources write to the same file in ways that cannot be combined.",
+    },
+    ErrorInfo {
+        code: "E4004",
+        phase: ErrorPhase::Merge,
+        title: "Locked files modified",
+        explanation: "An upstream marked the files it propagates with an `include` as \
+            `locked: true`, so consumers may not change them. The message lists each locked \
+            file that was edited since the last apply, differs from the upstream's copy, or is \
+            replaced by a file in `.common-repo/overrides/`. Restore the upstream's content, or \
+            delete the local copy and re-apply.",
+    },
+    ErrorInfo {
+        code: "E4005",
+        phase: ErrorPhase::Merge,
+        title: "Plan is stale",
+        explanation: "`apply --from-plan` writes the files `diff --save-plan` merged with the \
+            local files as they were then. Since then, a local file the plan was merged with \
+            was edited or removed, or a file was added, or overridden, at a path the plan \
+            writes, so writing it would lose the change. The message lists those files. Run \
+            `common-repo diff --save-plan` again and review the new plan.",
+    },
+    ErrorInfo {
+        code: "E6001",
+        phase: ErrorPhase::Output,
+        title: "Filesystem error",
+        explanation: "An in-memory or on-disk filesystem operation failed. The message names \
+            the path involved.",
+    },
+    ErrorInfo {
+        code: "E6002",
+        phase: ErrorPhase::Output,
+        title: "Invalid path",
+        explanation: "A path is absolute or escapes the output root, typically as the result \
+            of a `rename`. Output paths must stay inside the target directory.",
+    },
+    ErrorInfo {
+        code: "E6003",
+        phase: ErrorPhase::Output,
+        title: "I/O error",
+        explanation: "The operating system reported an error reading or writing a file. Check \
+            permissions and free disk space.",
+    },
+    ErrorInfo {
+        code: "E6004",
+        phase: ErrorPhase::Output,
+        title: "Output path is a symlink",
+        explanation: "A file to be written, or one of its parent directories, is a symlink in \
+            the output directory. Writing through it would change the link's target, which \
+            may be outside the repository. Replace the link with a regular file, exclude the \
+            path, or pass `--follow-symlinks` to write to the targets.",
+    },
+    ErrorInfo {
+        code: "E6005",
+        phase: ErrorPhase::Output,
+        title: "Output file is read-only",
+        explanation: "Files to be written exist in the output directory as read-only files \
+            with different content. Make them writable, or pass `--read-only overwrite` to \
+            write them and keep them read-only, or `--read-only skip` to leave them as they \
+            are.",
+    },
+    ErrorInfo {
+        code: "E6006",
+        phase: ErrorPhase::Output,
+        title: "Output path not allowed",
+        explanation: "Paths to be written are not valid UTF-8, contain control characters, \
+            have a name longer than 255 bytes, or are longer than the OS allows once joined \
+            to the output directory (260 characters on Windows). All of them are listed \
+            before anything is written. Rename or exclude the files, or write to a shorter \
+            output path.",
+    },
+    ErrorInfo {
+        code: "E9001",
+        phase: ErrorPhase::Internal,
+        title: "Lock poisoned",
+        explanation: "A worker thread panicked while holding a lock. This is a bug; please \
+            report it with the output of `--verbose`.",
+    },
+    ErrorInfo {
+        code: "E9002",
+        phase: ErrorPhase::Internal,
+        title: "Serialization error",
+        explanation: "Internal data could not be serialized. This is a bug; please report it.",
+    },
+    ErrorInfo {
+        code: "E9003",
+        phase: ErrorPhase::Internal,
+        title: "Not implemented",
+        explanation: "The requested feature is not implemented yet.",
+    },
+];
+
+/// Look up the documentation for an error code.
+///
+/// Matching ignores ASCII case, so `e4002` finds `E4002`.
+///
+/// ```
+/// use common_repo::error::{explain, ErrorPhase};
+///
+/// let info = explain("E4002").unwrap();
+/// assert_eq!(info.phase, ErrorPhase::Merge);
+/// assert!(explain("E0000").is_none());
+/// ```
+pub fn explain(code: &str) -> Option<&'static ErrorInfo> {
+    ERROR_CODES
+        .iter()
+        .find(|info| info.code.eq_ignore_ascii_case(code))
+}
+
+impl Error {
+    /// The stable error code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ConfigParse { .. } => "E0001",
+            Error::Yaml(_) => "E0002",
+            Error::UrlParse(_) => "E0003",
+            Error::Semver(_) => "E0004",
+            Error::VersionRequirement { .. } => "E0005",
+            Error::CycleDetected { .. } => "E1001",
+            Error::GitClone { .. } => "E1002",
+            Error::GitCommand { .. } => "E1003",
+            Error::Network { .. } => "E1004",
+            Error::LocalPathNotFound { .. } => "E1005",
+            Error::LocalPathNotDirectory { .. } => "E1006",
+            Error::Cache { .. } => "E1007",
+            Error::Download { .. } => "E1008",
+            Error::ChecksumMismatch { .. } => "E1009",
+            Error::OciPull { .. } => "E1010",
+            Error::DigestMismatch { .. } => "E1011",
+            Error::SignatureVerification { .. } => "E1012",
+            Error::Operator { .. } => "E2001",
+            Error::Template { .. } => "E2002",
+            Error::ToolValidation { .. } => "E2003",
+            Error::Regex(_) => "E2004",
+            Error::Glob(_) => "E2005",
+            Error::Patch { .. } => "E2006",
+            Error::RequirementsNotMet { .. } => "E2007",
+            Error::AssertionsFailed { .. } => "E2008",
+            Error::Merge { .. } => "E4001",
+            Error::MergeTypeMismatch { .. } => "E4002",
+            Error::MergeConflict { .. } => "E4003",
+            Error::LockedFilesModified { .. } => "E4004",
+            Error::StalePlan { .. } => "E4005",
+            Error::Filesystem { .. } => "E6001",
+            Error::Path { .. } => "E6002",
+            Error::Io(_) => "E6003",
+            Error::SymlinkedOutput { .. } => "E6004",
+            Error::ReadOnlyOutput { .. } => "E6005",
+            Error::UnsafeOutputPaths { .. } => "E6006",
+            Error::LockPoisoned { .. } => "E9001",
+            Error::Serialization { .. } => "E9002",
+            Error::NotImplemented { .. } => "E9003",
+        }
+    }
+
+    /// Documentation for this error's code.
+    pub fn info(&self) -> &'static ErrorInfo {
+        explain(self.code()).expect("every error code is listed in ERROR_CODES")
+    }
+
+    /// Whether this is a failure to fetch a repository from its host, which
+    /// a cached copy can stand in for.
+    pub fn is_fetch_failure(&self) -> bool {
+        matches!(
+            self,
+            Error::GitClone { .. } | Error::Network { .. } | Error::OciPull { .. }
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -404,4 +955,43 @@ mod tests {
         assert!(display.contains("dest.txt"));
         assert!(display.contains("File already exists"));
     }
+
+    #[test]
+    fn test_error_codes_are_sorted_unique_and_match_phase() {
+        for pair in ERROR_CODES.windows(2) {
+            assert!(
+                pair[0].code < pair[1].code,
+                "{} >= {}",
+                pair[0].code,
+                pair[1].code
+            );
+        }
+        for info in ERROR_CODES {
+            let digit = match info.phase {
+                ErrorPhase::Config => '0',
+                ErrorPhase::Discovery => '1',
+                ErrorPhase::Processing => '2',
+                ErrorPhase::Merge => '4',
+                ErrorPhase::Output => '6',
+                ErrorPhase::Internal => '9',
+            };
+            assert_eq!(info.code.chars().nth(1), Some(digit), "{}", info.code);
+        }
+    }
+
+    #[test]
+    fn test_error_code_lookup() {
+        let error = Error::CycleDetected {
+            cycle: "a -> b -> a".to_string(),
+        };
+        assert_eq!(error.code(), "E1001");
+        assert_eq!(error.info().title, "Repository dependency cycle");
+
+        let error = Error::MergeTypeMismatch {
+            operation: "yaml merge".to_string(),
+            message: "Expected mapping".to_string(),
+        };
+        assert_eq!(error.info().phase, ErrorPhase::Merge);
+        assert_eq!(explain("e4002"), Some(error.info()));
+    }
 }
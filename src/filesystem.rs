@@ -9,13 +9,24 @@
 //! ## Key Components
 //!
 //! - **`MemoryFS`**: The main struct that represents the in-memory filesystem.
-//!   It stores files in a `HashMap` where the keys are `PathBuf`s representing
-//!   the file paths and the values are `File` structs.
+//!   It stores files in a `BTreeMap` where the keys are `PathBuf`s representing
+//!   the file paths and the values are `File` structs. Every listing and
+//!   iterator yields paths in sorted order, so anything derived from a
+//!   `MemoryFS` (written files, reports, diffs) is the same on every run.
 //!
 //! - **`File`**: A struct that represents a single file, containing its content
-//!   as a `Vec<u8>` and associated metadata like permissions and modification
+//!   as a [`Content`] and associated metadata like permissions and modification
 //!   time.
 //!
+//! - **`Content`**: Immutable, shared file content. Identical content is
+//!   stored once no matter how many files, repositories or copies of a
+//!   filesystem hold it, so a wide tree that inherits the same base
+//!   repository at several points does not keep a copy per repository.
+//!
+//! - **`Directory`**: An explicit directory entry with its permissions. Parent
+//!   directories of files are implied by their paths; explicit entries exist
+//!   so that empty directories survive loading, composition, and writing.
+//!
 //! ## Functionality
 //!
 //! The `MemoryFS` provides a full set of methods for file manipulation,
@@ -26,6 +37,9 @@
 //! - Listing all files or a subset of files that match a glob pattern.
 //! - Renaming and copying files.
 //! - Merging one filesystem into another.
+//! - Comparing two filesystems ([`MemoryFS::diff`]), with unified diffs of
+//!   changed text files.
+//! - Tracking explicit (possibly empty) directories.
 //!
 //! This in-memory representation is a crucial component of the multi-phase
 //! pipeline, as it allows each phase to operate on a consistent and isolated
@@ -33,9 +47,15 @@
 
 use crate::config::IfExists;
 use crate::error::{Error, Result};
-use glob::Pattern;
-use std::collections::HashMap;
+use crate::path::{validate_relative_path, PatternSet};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
 use std::time::SystemTime;
 
 /// Read the Unix permission mode from a file's metadata.
@@ -55,11 +75,165 @@ fn permission_mode_from_metadata(metadata: &std::fs::Metadata) -> u32 {
     }
 }
 
+/// Content shared by every [`Content`] with the same bytes, keyed by hash.
+///
+/// Entries are weak, so content is freed once no file refers to it. Dead
+/// entries are pruned whenever the store doubles in size.
+struct ContentStore {
+    entries: HashMap<u64, Weak<[u8]>>,
+    prune_at: usize,
+}
+
+static CONTENT_STORE: LazyLock<Mutex<ContentStore>> = LazyLock::new(|| {
+    Mutex::new(ContentStore {
+        entries: HashMap::new(),
+        prune_at: 1024,
+    })
+});
+
+/// The raw bytes of a [`File`].
+///
+/// Content is immutable and cheap to clone: clones share one allocation.
+/// Content created from bytes that are already held elsewhere shares that
+/// allocation too, so identical files are stored once. To change a file,
+/// assign new content to it.
+///
+/// # Examples
+///
+/// ```
+/// use common_repo::filesystem::Content;
+///
+/// let a = Content::from("shared\n");
+/// let b = Content::from(b"shared\n".to_vec());
+/// assert_eq!(a, b);
+/// assert!(Content::ptr_eq(&a, &b));
+/// assert_eq!(a.len(), 7);
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Content(Arc<[u8]>);
+
+impl Content {
+    /// Wrap `bytes`, sharing the allocation of identical content that is
+    /// already in use.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut store = CONTENT_STORE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = store.entries.get(&key).and_then(Weak::upgrade) {
+            if *existing == *bytes {
+                return Self(existing);
+            }
+            // A hash collision: keep the first content interned.
+            return Self(Arc::from(bytes));
+        }
+        if store.entries.len() >= store.prune_at {
+            store.entries.retain(|_, weak| weak.strong_count() > 0);
+            store.prune_at = (store.entries.len() * 2).max(1024);
+        }
+        let content: Arc<[u8]> = Arc::from(bytes);
+        store.entries.insert(key, Arc::downgrade(&content));
+        Self(content)
+    }
+
+    /// Whether `a` and `b` share one allocation.
+    pub fn ptr_eq(a: &Content, b: &Content) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Self(Arc::from(Vec::new()))
+    }
+}
+
+impl Deref for Content {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Content {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Content {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Vec<u8>> for Content {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&[u8]> for Content {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Self::new(text.into_bytes())
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Self::new(text.as_bytes().to_vec())
+    }
+}
+
+impl PartialEq<[u8]> for Content {
+    fn eq(&self, other: &[u8]) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&[u8]> for Content {
+    fn eq(&self, other: &&[u8]) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for Content {
+    fn eq(&self, other: &[u8; N]) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl<const N: usize> PartialEq<&[u8; N]> for Content {
+    fn eq(&self, other: &&[u8; N]) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Vec<u8>> for Content {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Content> for Vec<u8> {
+    fn eq(&self, other: &Content) -> bool {
+        **self == *other.0
+    }
+}
+
 /// Represents a file with content and metadata
 #[derive(Debug, Clone)]
 pub struct File {
     /// The raw byte content of the file.
-    pub content: Vec<u8>,
+    pub content: Content,
     /// The file's permissions, represented in a Unix-like mode format.
     pub permissions: u32,
     /// The last modification time of the file.
@@ -71,6 +245,33 @@ pub struct File {
     /// time; flows passively through every other operator. The
     /// `filter_if_exists` pass at the tail of the pipeline acts on it.
     pub if_exists: IfExists,
+    /// Where the file came from upstream, if it was included from a
+    /// repository. Set at `include::apply` time, stamped with the
+    /// repository once it has been resolved, and shared by copies.
+    pub origin: Option<Arc<FileOrigin>>,
+    /// Whether relative Markdown links in the file are rewritten to the
+    /// URLs of its upstream once the repository is known. Set by `include`
+    /// and `rename` with `rewrite-links: upstream`.
+    pub upstream_links: bool,
+}
+
+/// The upstream a file came from, and what was done to it on the way.
+///
+/// Reported in `.common-repo/map.json` so that editors can tell which
+/// upstream file a managed file came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOrigin {
+    /// URL of the repository the file was included from. `None` until
+    /// the repository's operations have finished.
+    pub repo: Option<String>,
+    /// The ref of that repository.
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    /// The file's path in that repository.
+    pub source: PathBuf,
+    /// Operations that changed the file's content after it was included,
+    /// in order, such as `template` or `merge`.
+    pub operations: Vec<String>,
 }
 
 impl File {
@@ -91,13 +292,15 @@ impl File {
     /// assert_eq!(file.permissions, 0o644);
     /// assert_eq!(file.content, vec![72, 101, 108, 108, 111]);
     /// ```
-    pub fn new(content: Vec<u8>) -> Self {
+    pub fn new(content: impl Into<Content>) -> Self {
         Self {
-            content,
+            content: content.into(),
             permissions: 0o644, // Default to standard file permissions
             modified_time: SystemTime::now(),
             is_template: false,
             if_exists: IfExists::Overwrite,
+            origin: None,
+            upstream_links: false,
         }
     }
 
@@ -115,11 +318,13 @@ impl File {
             message: format!("Failed to read metadata for '{}': {}", path.display(), e),
         })?;
         Ok(Self {
-            content,
+            content: content.into(),
             permissions: permission_mode_from_metadata(&metadata),
             modified_time: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
             is_template: false,
             if_exists: IfExists::Overwrite,
+            origin: None,
+            upstream_links: false,
         })
     }
 
@@ -141,6 +346,24 @@ impl File {
         Self::new(content.as_bytes().to_vec())
     }
 
+    /// Whether the file came from an upstream repository.
+    pub fn is_managed(&self) -> bool {
+        self.origin
+            .as_ref()
+            .is_some_and(|origin| origin.repo.is_some())
+    }
+
+    /// Note that `operation` changed the content of a file that came from
+    /// upstream. Files without an origin are left as they are.
+    pub fn record(&mut self, operation: &str) {
+        if let Some(origin) = &mut self.origin {
+            let origin = Arc::make_mut(origin);
+            if origin.operations.last().map(String::as_str) != Some(operation) {
+                origin.operations.push(operation.to_string());
+            }
+        }
+    }
+
     /// Returns the size of the file's content in bytes.
     ///
     /// # Examples
@@ -159,6 +382,49 @@ impl File {
     }
 }
 
+/// Represents an explicit directory entry.
+///
+/// Directories that contain files are implied by the file paths and do not
+/// need an entry. Explicit entries are used for directories that must exist
+/// even when empty, such as a `migrations/` directory some tools expect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directory {
+    /// The directory's permissions, represented in a Unix-like mode format.
+    pub permissions: u32,
+}
+
+impl Directory {
+    /// Creates a new `Directory` with `0o755` permissions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common_repo::filesystem::Directory;
+    ///
+    /// let dir = Directory::new();
+    /// assert_eq!(dir.permissions, 0o755);
+    /// ```
+    pub fn new() -> Self {
+        Self { permissions: 0o755 }
+    }
+
+    /// Creates a new `Directory` using the permissions of a directory on disk.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path).map_err(|e| Error::Filesystem {
+            message: format!("Failed to read metadata for '{}': {}", path.display(), e),
+        })?;
+        Ok(Self {
+            permissions: permission_mode_from_metadata(&metadata),
+        })
+    }
+}
+
+impl Default for Directory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// An in-memory filesystem that stores files and their content.
 ///
 /// This struct provides a virtual filesystem that can be manipulated without
@@ -166,8 +432,10 @@ impl File {
 /// performing dry runs.
 #[derive(Debug, Clone, Default)]
 pub struct MemoryFS {
-    /// Files stored as path -> content mapping
-    files: HashMap<PathBuf, File>,
+    /// Files stored as path -> content mapping, sorted by path
+    files: BTreeMap<PathBuf, File>,
+    /// Explicit directory entries (typically empty directories), sorted by path
+    directories: BTreeMap<PathBuf, Directory>,
 }
 
 impl MemoryFS {
@@ -189,9 +457,13 @@ impl MemoryFS {
     /// Adds a file to the filesystem.
     ///
     /// If a file already exists at the given path, it will be overwritten.
+    ///
+    /// Returns an error if the path is absolute or uses `..` to escape the
+    /// filesystem root (see [`validate_relative_path`]).
     pub fn add_file<P: AsRef<Path>>(&mut self, path: P, file: File) -> Result<()> {
-        let path = path.as_ref().to_path_buf();
-        self.files.insert(path, file);
+        let path = path.as_ref();
+        validate_relative_path(path)?;
+        self.files.insert(path.to_path_buf(), file);
         Ok(())
     }
 
@@ -253,31 +525,31 @@ impl MemoryFS {
         self.files.contains_key(path.as_ref())
     }
 
-    /// Returns a list of all file paths in the filesystem.
+    /// Returns a list of all file paths in the filesystem, in sorted order.
     pub fn list_files(&self) -> Vec<PathBuf> {
         self.files.keys().cloned().collect()
     }
 
     /// Returns a list of file paths that match the given glob pattern.
     pub fn list_files_glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
-        let pattern = Pattern::new(pattern).map_err(Error::Glob)?;
-        let mut matches = Vec::new();
-
-        for path in self.files.keys() {
-            if let Some(path_str) = path.to_str() {
-                if pattern.matches(path_str) {
-                    matches.push(path.clone());
-                }
-            }
-        }
+        Ok(self.list_files_matching(&*PatternSet::cached(&[pattern.to_string()])?))
+    }
 
-        Ok(matches)
+    /// Returns a list of file paths that match any pattern of `patterns`, in
+    /// sorted order.
+    pub fn list_files_matching(&self, patterns: &PatternSet) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .filter(|path| patterns.is_match(path))
+            .cloned()
+            .collect()
     }
 
     /// Renames a file from one path to another.
     ///
-    /// Returns an error if the `from` path does not exist. If the `to` path
-    /// already exists, it will be overwritten.
+    /// Returns an error if the `from` path does not exist or if `to` is not a
+    /// safe relative path. If the `to` path already exists, it will be
+    /// overwritten.
     ///
     /// # Examples
     ///
@@ -295,6 +567,7 @@ impl MemoryFS {
     pub fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
         let from_path = from.as_ref();
         let to_path = to.as_ref();
+        validate_relative_path(to_path)?;
 
         if let Some(file) = self.files.remove(from_path) {
             self.files.insert(to_path.to_path_buf(), file);
@@ -308,11 +581,13 @@ impl MemoryFS {
 
     /// Copies a file from one path to another.
     ///
-    /// Returns an error if the `from` path does not exist. If the `to` path
-    /// already exists, it will be overwritten.
+    /// Returns an error if the `from` path does not exist or if `to` is not a
+    /// safe relative path. If the `to` path already exists, it will be
+    /// overwritten.
     pub fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
         let from_path = from.as_ref();
         let to_path = to.as_ref();
+        validate_relative_path(to_path)?;
 
         if let Some(file) = self.files.get(from_path) {
             self.files.insert(to_path.to_path_buf(), file.clone());
@@ -334,15 +609,49 @@ impl MemoryFS {
         self.files.is_empty()
     }
 
-    /// Removes all files from the filesystem.
+    /// Returns the total size of all file contents, in bytes.
+    ///
+    /// Content shared by several files is held once in memory and counted
+    /// once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common_repo::filesystem::MemoryFS;
+    ///
+    /// let mut fs = MemoryFS::new();
+    /// fs.add_file_string("a.txt", "abc").unwrap();
+    /// fs.add_file_string("b.txt", "de").unwrap();
+    /// fs.add_file_string("c.txt", "de").unwrap();
+    /// assert_eq!(fs.size(), 5);
+    /// ```
+    pub fn size(&self) -> u64 {
+        Self::total_size([self])
+    }
+
+    /// Returns the total size of the file contents of `filesystems`, in
+    /// bytes, counting content they share once.
+    pub fn total_size<'a>(filesystems: impl IntoIterator<Item = &'a MemoryFS>) -> u64 {
+        let mut seen = HashSet::new();
+        filesystems
+            .into_iter()
+            .flat_map(|fs| fs.files.values())
+            .filter(|file| seen.insert(file.content.as_ptr()))
+            .map(|file| file.size() as u64)
+            .sum()
+    }
+
+    /// Removes all files and directory entries from the filesystem.
     pub fn clear(&mut self) {
         self.files.clear();
+        self.directories.clear();
     }
 
     /// Merges another `MemoryFS` into this one.
     ///
     /// If a file exists in both filesystems, the one from `other` will
-    /// overwrite the one in `self` (last-write-wins).
+    /// overwrite the one in `self` (last-write-wins). Directory entries are
+    /// merged the same way.
     ///
     /// # Examples
     ///
@@ -366,12 +675,407 @@ impl MemoryFS {
         for (path, file) in &other.files {
             self.files.insert(path.clone(), file.clone());
         }
+        for (path, dir) in &other.directories {
+            self.directories.insert(path.clone(), dir.clone());
+        }
     }
 
-    /// Returns an iterator over the `(path, file)` pairs in the filesystem.
+    /// Returns an iterator over the `(path, file)` pairs in the filesystem,
+    /// in sorted path order.
     pub fn files(&self) -> impl Iterator<Item = (&PathBuf, &File)> {
         self.files.iter()
     }
+
+    /// Adds an explicit directory entry.
+    ///
+    /// If an entry already exists at the given path, it will be overwritten.
+    /// The same path restrictions as [`MemoryFS::add_file`] apply.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common_repo::filesystem::{Directory, MemoryFS};
+    ///
+    /// let mut fs = MemoryFS::new();
+    /// fs.add_directory("migrations", Directory::new()).unwrap();
+    ///
+    /// assert!(fs.has_directory("migrations"));
+    /// assert!(fs.is_empty()); // no files
+    /// ```
+    pub fn add_directory<P: AsRef<Path>>(&mut self, path: P, dir: Directory) -> Result<()> {
+        let path = path.as_ref();
+        validate_relative_path(path)?;
+        self.directories.insert(path.to_path_buf(), dir);
+        Ok(())
+    }
+
+    /// Retrieves a reference to an explicit directory entry.
+    pub fn get_directory<P: AsRef<Path>>(&self, path: P) -> Option<&Directory> {
+        self.directories.get(path.as_ref())
+    }
+
+    /// Removes an explicit directory entry, returning it if it existed.
+    ///
+    /// Files below the directory are not affected.
+    pub fn remove_directory<P: AsRef<Path>>(&mut self, path: P) -> Result<Option<Directory>> {
+        Ok(self.directories.remove(path.as_ref()))
+    }
+
+    /// Checks if an explicit directory entry exists at the given path.
+    ///
+    /// Directories implied by file paths are not reported.
+    pub fn has_directory<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.directories.contains_key(path.as_ref())
+    }
+
+    /// Returns a list of all explicit directory paths, in sorted order.
+    pub fn list_directories(&self) -> Vec<PathBuf> {
+        self.directories.keys().cloned().collect()
+    }
+
+    /// Returns a list of explicit directory paths that match the given glob
+    /// pattern.
+    pub fn list_directories_glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        Ok(self.list_directories_matching(&*PatternSet::cached(&[pattern.to_string()])?))
+    }
+
+    /// Returns a list of explicit directory paths that match any pattern of
+    /// `patterns`, in sorted order.
+    pub fn list_directories_matching(&self, patterns: &PatternSet) -> Vec<PathBuf> {
+        self.directories
+            .keys()
+            .filter(|path| patterns.is_match(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Renames an explicit directory entry.
+    ///
+    /// Returns an error if no entry exists at `from`. Files below the
+    /// directory are not moved.
+    pub fn rename_directory<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> Result<()> {
+        let from_path = from.as_ref();
+        validate_relative_path(to.as_ref())?;
+        if let Some(dir) = self.directories.remove(from_path) {
+            self.directories.insert(to.as_ref().to_path_buf(), dir);
+            Ok(())
+        } else {
+            Err(Error::Filesystem {
+                message: format!("Directory not found: {}", from_path.display()),
+            })
+        }
+    }
+
+    /// Returns an iterator over the `(path, directory)` pairs of explicit
+    /// directory entries, in sorted path order.
+    pub fn directories(&self) -> impl Iterator<Item = (&PathBuf, &Directory)> {
+        self.directories.iter()
+    }
+
+    /// Consumes the filesystem, returning its files and explicit directory
+    /// entries so they can be moved elsewhere without copying.
+    pub fn into_parts(self) -> (BTreeMap<PathBuf, File>, BTreeMap<PathBuf, Directory>) {
+        (self.files, self.directories)
+    }
+
+    /// Compares this filesystem with `other`, taking `self` as the old side.
+    ///
+    /// Files only in `other` are added, files only in `self` are removed, and
+    /// files in both whose content differs are modified. Entries are in
+    /// sorted path order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use common_repo::filesystem::{FsChange, MemoryFS};
+    ///
+    /// let mut old = MemoryFS::new();
+    /// old.add_file_string("kept.txt", "same").unwrap();
+    /// old.add_file_string("gone.txt", "bye").unwrap();
+    ///
+    /// let mut new = MemoryFS::new();
+    /// new.add_file_string("kept.txt", "same").unwrap();
+    /// new.add_file_string("new.txt", "hi").unwrap();
+    ///
+    /// let diff = old.diff(&new);
+    /// let changes: Vec<_> = diff.iter().map(|e| (e.path.to_str().unwrap(), e.change)).collect();
+    /// assert_eq!(changes, [("gone.txt", FsChange::Removed), ("new.txt", FsChange::Added)]);
+    /// ```
+    pub fn diff(&self, other: &MemoryFS) -> FsDiff {
+        let mut entries = Vec::new();
+        let mut old = self.files.iter().peekable();
+        let mut new = other.files.iter().peekable();
+        loop {
+            let order = match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some((a, _)), Some((b, _))) => a.cmp(b),
+            };
+            let entry = match order {
+                std::cmp::Ordering::Less => {
+                    let (path, file) = old.next().expect("peeked");
+                    FsDiffEntry::new(path, FsChange::Removed, Some(file), None)
+                }
+                std::cmp::Ordering::Greater => {
+                    let (path, file) = new.next().expect("peeked");
+                    FsDiffEntry::new(path, FsChange::Added, None, Some(file))
+                }
+                std::cmp::Ordering::Equal => {
+                    let (path, a) = old.next().expect("peeked");
+                    let (_, b) = new.next().expect("peeked");
+                    if a.content == b.content {
+                        continue;
+                    }
+                    FsDiffEntry::new(path, FsChange::Modified, Some(a), Some(b))
+                }
+            };
+            entries.push(entry);
+        }
+        FsDiff { entries }
+    }
+}
+
+/// How a file differs between two filesystems; see [`MemoryFS::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsChange {
+    /// The file is only in the new filesystem.
+    Added,
+    /// The file is only in the old filesystem.
+    Removed,
+    /// The file is in both, with different content.
+    Modified,
+}
+
+/// One file that differs between two filesystems.
+#[derive(Debug, Clone)]
+pub struct FsDiffEntry {
+    /// Path of the file, relative to the filesystem root.
+    pub path: PathBuf,
+    /// How the file differs.
+    pub change: FsChange,
+    /// The old content, or `None` for an added file.
+    pub old: Option<Content>,
+    /// The new content, or `None` for a removed file.
+    pub new: Option<Content>,
+}
+
+/// Size of the comparison table beyond which [`FsDiffEntry::unified_diff`]
+/// replaces the whole file rather than searching for common lines.
+const MAX_DIFF_CELLS: usize = 16 * 1024 * 1024;
+
+impl FsDiffEntry {
+    fn new(path: &Path, change: FsChange, old: Option<&File>, new: Option<&File>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            change,
+            old: old.map(|file| file.content.clone()),
+            new: new.map(|file| file.content.clone()),
+        }
+    }
+
+    /// A unified diff of the content change, with `context` unchanged lines
+    /// around each hunk, or `None` when either side is not UTF-8 text.
+    ///
+    /// The output can be applied with `patch -p1` or the `patch` operation.
+    ///
+    /// ```
+    /// use common_repo::filesystem::MemoryFS;
+    ///
+    /// let mut old = MemoryFS::new();
+    /// old.add_file_string("a.txt", "one\ntwo\n").unwrap();
+    /// let mut new = MemoryFS::new();
+    /// new.add_file_string("a.txt", "one\n2\n").unwrap();
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(
+    ///     diff.iter().next().unwrap().unified_diff(3).unwrap(),
+    ///     "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n"
+    /// );
+    /// ```
+    pub fn unified_diff(&self, context: usize) -> Option<String> {
+        fn text(content: &Option<Content>) -> Option<&str> {
+            match content {
+                Some(content) => std::str::from_utf8(content).ok(),
+                None => Some(""),
+            }
+        }
+        let (old, new) = (text(&self.old)?, text(&self.new)?);
+        let label = |side: &str, content: &Option<Content>| match content {
+            Some(_) => format!("{}/{}", side, self.path.display()),
+            None => "/dev/null".to_string(),
+        };
+
+        let mut out = format!(
+            "--- {}\n+++ {}\n",
+            label("a", &self.old),
+            label("b", &self.new)
+        );
+        let old: Vec<&str> = old.split_inclusive('\n').collect();
+        let new: Vec<&str> = new.split_inclusive('\n').collect();
+        let ops = diff_lines(&old, &new);
+
+        // Group changed lines, with their context, into hunks.
+        let changed: Vec<usize> = (0..ops.len())
+            .filter(|&i| !matches!(ops[i], LineOp::Same(..)))
+            .collect();
+        let mut i = 0;
+        while i < changed.len() {
+            let start = changed[i].saturating_sub(context);
+            let mut end = changed[i];
+            while i < changed.len() && changed[i] <= end + 2 * context + 1 {
+                end = changed[i];
+                i += 1;
+            }
+            let end = (end + context + 1).min(ops.len());
+
+            let (mut old_start, mut new_start) = (0, 0);
+            for op in &ops[..start] {
+                match op {
+                    LineOp::Same(..) => (old_start, new_start) = (old_start + 1, new_start + 1),
+                    LineOp::Removed(_) => old_start += 1,
+                    LineOp::Added(_) => new_start += 1,
+                }
+            }
+            let hunk = &ops[start..end];
+            let old_len = hunk
+                .iter()
+                .filter(|op| !matches!(op, LineOp::Added(_)))
+                .count();
+            let new_len = hunk
+                .iter()
+                .filter(|op| !matches!(op, LineOp::Removed(_)))
+                .count();
+            // An empty side starts at the line before the hunk.
+            let position = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+            out.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                position(old_start, old_len),
+                old_len,
+                position(new_start, new_len),
+                new_len
+            ));
+            for op in hunk {
+                let (prefix, line) = match op {
+                    LineOp::Same(line) => (' ', *line),
+                    LineOp::Removed(line) => ('-', *line),
+                    LineOp::Added(line) => ('+', *line),
+                };
+                out.push(prefix);
+                out.push_str(line);
+                if !line.ends_with('\n') {
+                    out.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+        Some(out)
+    }
+}
+
+/// One line of a line diff.
+enum LineOp<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A shortest line diff from `old` to `new`, by longest common subsequence.
+///
+/// Inputs too large to compare line by line are diffed as a removal of
+/// every old line followed by an addition of every new line.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<LineOp<'a>> {
+    // Common prefix and suffix are matched directly, which keeps the table
+    // small for the usual few-line edit.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops: Vec<LineOp> = old[..prefix].iter().map(|l| LineOp::Same(l)).collect();
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_DIFF_CELLS {
+        ops.extend(a.iter().map(|l| LineOp::Removed(l)));
+        ops.extend(b.iter().map(|l| LineOp::Added(l)));
+    } else {
+        // lcs[i][j]: length of the longest common subsequence of a[i..], b[j..]
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(LineOp::Same(a[i]));
+                (i, j) = (i + 1, j + 1);
+            } else if j == b.len()
+                || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                ops.push(LineOp::Removed(a[i]));
+                i += 1;
+            } else {
+                ops.push(LineOp::Added(b[j]));
+                j += 1;
+            }
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| LineOp::Same(l)));
+    ops
+}
+
+/// The files that differ between two filesystems, from [`MemoryFS::diff`].
+#[derive(Debug, Clone, Default)]
+pub struct FsDiff {
+    entries: Vec<FsDiffEntry>,
+}
+
+impl FsDiff {
+    /// All differing files, in sorted path order.
+    pub fn iter(&self) -> impl Iterator<Item = &FsDiffEntry> {
+        self.entries.iter()
+    }
+
+    /// The differing files with the given kind of change.
+    pub fn changes(&self, change: FsChange) -> impl Iterator<Item = &FsDiffEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.change == change)
+    }
+
+    /// Number of differing files.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the filesystems have the same files with the same content.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a FsDiff {
+    type Item = &'a FsDiffEntry;
+    type IntoIter = std::slice::Iter<'a, FsDiffEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
 }
 
 #[cfg(test)]
@@ -379,6 +1083,34 @@ mod tests {
     use super::*;
     use std::time::SystemTime;
 
+    #[test]
+    fn test_content_is_shared_across_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for repo in ["base-a", "base-b"] {
+            std::fs::create_dir(temp_dir.path().join(repo)).unwrap();
+            std::fs::write(temp_dir.path().join(repo).join("ci.yml"), "on: push\n").unwrap();
+        }
+
+        let a = File::from_path(&temp_dir.path().join("base-a/ci.yml")).unwrap();
+        let b = File::from_path(&temp_dir.path().join("base-b/ci.yml")).unwrap();
+        assert!(Content::ptr_eq(&a.content, &b.content));
+
+        let other = File::from_string("on: pull_request\n");
+        assert!(!Content::ptr_eq(&a.content, &other.content));
+        assert_ne!(a.content, other.content);
+    }
+
+    #[test]
+    fn test_content_comparisons() {
+        let content = Content::from("abc");
+        assert_eq!(content, b"abc");
+        assert_eq!(content, b"abc".to_vec());
+        assert_eq!(b"abc".to_vec(), content);
+        assert_eq!(&content[..], b"abc");
+        assert_eq!(format!("{:?}", content), format!("{:?}", b"abc".to_vec()));
+        assert!(Content::default().is_empty());
+    }
+
     #[test]
     fn test_file_new() {
         let content = vec![1, 2, 3, 4, 5];
@@ -478,7 +1210,7 @@ mod tests {
         assert!(fs.exists("hello.txt"));
         let retrieved = fs.get_file("hello.txt").unwrap();
         assert_eq!(
-            String::from_utf8(retrieved.content.clone()).unwrap(),
+            String::from_utf8(retrieved.content.to_vec()).unwrap(),
             "Hello, World!"
         );
     }
@@ -489,7 +1221,7 @@ mod tests {
         fs.add_file_string("test.txt", "content").unwrap();
 
         let file = fs.get_file("test.txt").unwrap();
-        assert_eq!(String::from_utf8(file.content.clone()).unwrap(), "content");
+        assert_eq!(String::from_utf8(file.content.to_vec()).unwrap(), "content");
 
         assert!(fs.get_file("nonexistent.txt").is_none());
     }
@@ -502,7 +1234,10 @@ mod tests {
         assert!(fs.exists("test.txt"));
 
         let removed = fs.remove_file("test.txt").unwrap().unwrap();
-        assert_eq!(String::from_utf8(removed.content).unwrap(), "content");
+        assert_eq!(
+            String::from_utf8(removed.content.to_vec()).unwrap(),
+            "content"
+        );
 
         assert!(!fs.exists("test.txt"));
         assert!(fs.remove_file("nonexistent.txt").unwrap().is_none());
@@ -571,7 +1306,7 @@ mod tests {
         assert!(fs.exists("new.txt"));
         let content = fs.get_file("new.txt").unwrap();
         assert_eq!(
-            String::from_utf8(content.content.clone()).unwrap(),
+            String::from_utf8(content.content.to_vec()).unwrap(),
             "content"
         );
     }
@@ -655,13 +1390,13 @@ mod tests {
 
         let file1 = fs1.get_file("file1.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file1.content.clone()).unwrap(),
+            String::from_utf8(file1.content.to_vec()).unwrap(),
             "overwritten"
         );
 
         let file2 = fs1.get_file("file2.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file2.content.clone()).unwrap(),
+            String::from_utf8(file2.content.to_vec()).unwrap(),
             "content2"
         );
     }
@@ -672,23 +1407,47 @@ mod tests {
         fs.add_file_string("file1.txt", "content1").unwrap();
         fs.add_file_string("file2.txt", "content2").unwrap();
 
-        let mut files: Vec<_> = fs.files().collect();
-        files.sort_by(|a, b| a.0.cmp(b.0));
+        let files: Vec<_> = fs.files().collect();
 
         assert_eq!(files.len(), 2);
         assert_eq!(files[0].0, &PathBuf::from("file1.txt"));
         assert_eq!(files[1].0, &PathBuf::from("file2.txt"));
 
         assert_eq!(
-            String::from_utf8(files[0].1.content.clone()).unwrap(),
+            String::from_utf8(files[0].1.content.to_vec()).unwrap(),
             "content1"
         );
         assert_eq!(
-            String::from_utf8(files[1].1.content.clone()).unwrap(),
+            String::from_utf8(files[1].1.content.to_vec()).unwrap(),
             "content2"
         );
     }
 
+    #[test]
+    fn test_memory_fs_iterates_in_sorted_order() {
+        let mut fs = MemoryFS::new();
+        for path in ["z.txt", "a/b.txt", "m.txt", "a.txt"] {
+            fs.add_file_string(path, "x").unwrap();
+        }
+        for path in ["zz", "aa"] {
+            fs.add_directory(path, Directory::new()).unwrap();
+        }
+
+        let expected: Vec<PathBuf> = ["a/b.txt", "a.txt", "m.txt", "z.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(fs.list_files(), expected);
+        assert_eq!(
+            fs.files().map(|(p, _)| p.clone()).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            fs.list_directories(),
+            vec![PathBuf::from("aa"), PathBuf::from("zz")]
+        );
+    }
+
     #[test]
     fn test_file_modified_time() {
         let file = File::new(vec![1, 2, 3]);
@@ -812,7 +1571,7 @@ mod tests {
 
         let file = fs.get_file("test.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file.content.clone()).unwrap(),
+            String::from_utf8(file.content.to_vec()).unwrap(),
             "new content"
         );
         assert_eq!(file.permissions, 0o644);
@@ -828,11 +1587,33 @@ mod tests {
         assert!(fs.exists("deep/nested/path/file.txt"));
         let retrieved = fs.get_file("deep/nested/path/file.txt").unwrap();
         assert_eq!(
-            String::from_utf8(retrieved.content.clone()).unwrap(),
+            String::from_utf8(retrieved.content.to_vec()).unwrap(),
             "nested content"
         );
     }
 
+    #[test]
+    fn test_add_file_rejects_unsafe_paths() {
+        let mut fs = MemoryFS::new();
+
+        assert!(fs.add_file_string("../escape.txt", "x").is_err());
+        assert!(fs.add_file_string("/etc/passwd", "x").is_err());
+        assert!(fs.add_file_string("a/../../escape.txt", "x").is_err());
+        assert!(fs.is_empty());
+    }
+
+    #[test]
+    fn test_rename_and_copy_reject_escaping_targets() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("file.txt", "content").unwrap();
+
+        assert!(fs.rename_file("file.txt", "../file.txt").is_err());
+        assert!(fs.copy_file("file.txt", "/tmp/file.txt").is_err());
+        // The source is untouched when the target is rejected
+        assert!(fs.exists("file.txt"));
+        assert_eq!(fs.len(), 1);
+    }
+
     #[test]
     fn test_copy_file_overwrite_behavior() {
         let mut fs = MemoryFS::new();
@@ -848,7 +1629,7 @@ mod tests {
         let dest = fs.get_file("dest.txt").unwrap();
         assert_eq!(source.content, dest.content);
         assert_eq!(
-            String::from_utf8(dest.content.clone()).unwrap(),
+            String::from_utf8(dest.content.to_vec()).unwrap(),
             "source content"
         );
     }
@@ -868,6 +1649,75 @@ mod tests {
     }
 }
 
+#[cfg(test)]
+mod directory_tests {
+    use super::*;
+
+    #[test]
+    fn directory_new_defaults_to_0o755() {
+        assert_eq!(Directory::new().permissions, 0o755);
+    }
+
+    #[test]
+    fn directories_do_not_count_as_files() {
+        let mut fs = MemoryFS::new();
+        fs.add_directory("empty", Directory::new()).unwrap();
+
+        assert!(fs.has_directory("empty"));
+        assert!(!fs.exists("empty"));
+        assert!(fs.is_empty());
+        assert_eq!(fs.list_directories(), vec![PathBuf::from("empty")]);
+    }
+
+    #[test]
+    fn merge_carries_directories() {
+        let mut fs1 = MemoryFS::new();
+        fs1.add_directory("a", Directory::new()).unwrap();
+
+        let mut fs2 = MemoryFS::new();
+        fs2.add_directory("a", Directory { permissions: 0o700 })
+            .unwrap();
+        fs2.add_directory("b", Directory::new()).unwrap();
+
+        fs1.merge(&fs2);
+
+        assert_eq!(fs1.get_directory("a").unwrap().permissions, 0o700);
+        assert!(fs1.has_directory("b"));
+    }
+
+    #[test]
+    fn list_directories_glob_matches_patterns() {
+        let mut fs = MemoryFS::new();
+        fs.add_directory("db/migrations", Directory::new()).unwrap();
+        fs.add_directory("logs", Directory::new()).unwrap();
+
+        let matches = fs.list_directories_glob("db/**").unwrap();
+        assert_eq!(matches, vec![PathBuf::from("db/migrations")]);
+    }
+
+    #[test]
+    fn rename_and_remove_directory() {
+        let mut fs = MemoryFS::new();
+        fs.add_directory("old", Directory::new()).unwrap();
+
+        fs.rename_directory("old", "new").unwrap();
+        assert!(!fs.has_directory("old"));
+        assert!(fs.has_directory("new"));
+        assert!(fs.rename_directory("missing", "x").is_err());
+
+        assert!(fs.remove_directory("new").unwrap().is_some());
+        assert!(!fs.has_directory("new"));
+    }
+
+    #[test]
+    fn clear_removes_directories() {
+        let mut fs = MemoryFS::new();
+        fs.add_directory("empty", Directory::new()).unwrap();
+        fs.clear();
+        assert!(fs.list_directories().is_empty());
+    }
+}
+
 #[cfg(test)]
 mod if_exists_tests {
     use super::*;
@@ -884,3 +1734,83 @@ mod if_exists_tests {
         assert_eq!(file.if_exists, IfExists::Overwrite);
     }
 }
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::config::PatchOp;
+
+    fn fs_with(files: &[(&str, &str)]) -> MemoryFS {
+        let mut fs = MemoryFS::new();
+        for (path, content) in files {
+            fs.add_file_string(path, content).unwrap();
+        }
+        fs
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_modified_in_path_order() {
+        let old = fs_with(&[("b.txt", "b"), ("c.txt", "c"), ("same.txt", "s")]);
+        let new = fs_with(&[("a.txt", "a"), ("c.txt", "C"), ("same.txt", "s")]);
+
+        let diff = old.diff(&new);
+        let changes: Vec<_> = diff
+            .iter()
+            .map(|entry| (entry.path.to_str().unwrap(), entry.change))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("a.txt", FsChange::Added),
+                ("b.txt", FsChange::Removed),
+                ("c.txt", FsChange::Modified),
+            ]
+        );
+        assert_eq!(diff.changes(FsChange::Modified).count(), 1);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn unified_diff_applies_with_the_patch_operation() {
+        let before: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        let after = before
+            .replace("line 2\n", "line two\n")
+            .replace("line 15\n", "")
+            + "tail";
+        let old = fs_with(&[("f.txt", &before)]);
+        let new = fs_with(&[("f.txt", &after)]);
+
+        let unified = old
+            .diff(&new)
+            .iter()
+            .next()
+            .unwrap()
+            .unified_diff(3)
+            .unwrap();
+        assert_eq!(unified.matches("@@ -").count(), 2);
+        assert!(unified.ends_with("+tail\n\\ No newline at end of file\n"));
+
+        let mut patched = old.clone();
+        let op = PatchOp {
+            file: "f.txt".to_string(),
+            patch: "f.patch".to_string(),
+        };
+        crate::operators::patch::apply(&op, &unified, &mut patched).unwrap();
+        assert_eq!(patched.get_file("f.txt").unwrap().content, after.as_bytes());
+    }
+
+    #[test]
+    fn unified_diff_of_added_file_and_binary_content() {
+        let old = MemoryFS::new();
+        let mut new = fs_with(&[("new.txt", "hello\n")]);
+        new.add_file_content("bin", vec![0xff, 0xfe]).unwrap();
+
+        let diff = old.diff(&new);
+        let entries: Vec<_> = diff.iter().collect();
+        assert_eq!(entries[0].unified_diff(3), None);
+        assert_eq!(
+            entries[1].unified_diff(3).unwrap(),
+            "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,1 @@\n+hello\n"
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! # Up-to-Date Checks
+//!
+//! A fingerprint records everything an `apply` depends on, so that a later
+//! run can tell nothing changed and skip the pipeline entirely:
+//!
+//! - the configuration, by hash of its parsed form;
+//! - the version of `common-repo`;
+//! - every file in the working directory (and the output directory, when
+//!   different), by hash of its path, mode and content, including the
+//!   overrides directory (see [`crate::defaults::OVERRIDES_DIR`]);
+//! - every upstream in the inheritance tree, by the commit its ref resolves
+//!   to (a digest for OCI artifacts, a hash of the paths, modes and content
+//!   of the files for local paths);
+//! - every ref index that ref aliases were looked up in (see
+//!   [`crate::ref_index`]), the same way.
+//!
+//! The fingerprint of the last successful `apply` is stored under the cache
+//! root in `fingerprints/`, separately for each named pipeline. Checking it re-reads the local files and asks
+//! each git remote where its ref points with `git ls-remote`, which is much
+//! cheaper than fetching, composing and comparing. Refs that are already a
+//! commit SHA or a digest are not looked up.
+//!
+//! A configuration with a `file:` operation that is not pinned with `sha256`
+//! has no fingerprint, since its content can change without any of the above
+//! changing.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Operation, Schema};
+use crate::error::{Error, Result};
+use crate::http::sha256_hex;
+use crate::phases::orchestrator::partition_self_operations;
+use crate::phases::{RepoNode, RepoTree};
+use crate::repository::RepositoryManager;
+
+/// An upstream and the commit its ref pointed to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Upstream {
+    /// Repository URL, or the canonical path of a local repository.
+    pub url: String,
+    /// The ref as written in the configuration.
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    /// Commit SHA, OCI digest, or `sha256:<hash>` of a local repository's
+    /// files.
+    pub revision: String,
+}
+
+/// The inputs of a successful `apply`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Version of `common-repo` that applied.
+    pub version: String,
+    /// Hash of the configuration.
+    pub config: String,
+    /// Hash of the local files after the apply.
+    pub local: String,
+    /// Every upstream in the inheritance tree.
+    pub upstreams: Vec<Upstream>,
+}
+
+/// Where the fingerprints for a working directory and output directory are
+/// stored.
+#[derive(Debug, Clone)]
+pub struct FingerprintStore {
+    path: PathBuf,
+    working_dir: PathBuf,
+    output_dir: PathBuf,
+    cache_root: PathBuf,
+}
+
+impl FingerprintStore {
+    /// The store for applying into `output_dir` from `working_dir`.
+    pub fn new(cache_root: &Path, working_dir: &Path, output_dir: &Path) -> Self {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let working_dir = canonical(working_dir);
+        let output_dir = canonical(output_dir);
+        let key =
+            sha256_hex(format!("{}\n{}", working_dir.display(), output_dir.display()).as_bytes());
+        Self {
+            path: cache_root
+                .join("fingerprints")
+                .join(format!("{}.json", &key[..16])),
+            working_dir,
+            output_dir,
+            cache_root: canonical(cache_root),
+        }
+    }
+
+    /// The store for applying the named pipeline `name` instead of the
+    /// default configuration.
+    pub fn pipeline(mut self, name: &str) -> Self {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let file = format!("{}-{}.json", stem, name);
+        self.path.set_file_name(file);
+        self
+    }
+
+    /// Whether the last successful apply had the same inputs as `config`
+    /// and the current files and upstreams.
+    ///
+    /// Any failure to read or compute the fingerprint counts as changed.
+    pub fn is_up_to_date(&self, config: &Schema) -> bool {
+        let Some(stored) = self.load() else {
+            return false;
+        };
+        if stored.version != env!("CARGO_PKG_VERSION")
+            || Some(&stored.config) != config_hash(config).as_ref()
+            || self.local_hash().ok().as_ref() != Some(&stored.local)
+        {
+            return false;
+        }
+        stored.upstreams.iter().all(|upstream| {
+            resolve(&upstream.url, &upstream.ref_, upstream.ref_.is_empty())
+                .is_ok_and(|revision| revision == upstream.revision)
+        })
+    }
+
+    /// Record the inputs of a successful apply of `config`.
+    ///
+    /// Discovers the inheritance tree again, from the cache, to list the
+    /// upstreams. Does nothing when the configuration has no fingerprint.
+    pub fn save(&self, config: &Schema, repo_manager: &RepositoryManager) -> Result<()> {
+        let Some(fingerprint) = self.compute(config, repo_manager)? else {
+            self.clear();
+            return Ok(());
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_vec_pretty(&fingerprint).map_err(|e| Error::Filesystem {
+            message: format!("Failed to serialize fingerprint: {}", e),
+        })?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Forget the stored fingerprint, so the next apply runs in full.
+    pub fn clear(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    fn load(&self) -> Option<Fingerprint> {
+        let content = fs::read(&self.path).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    fn compute(
+        &self,
+        config: &Schema,
+        repo_manager: &RepositoryManager,
+    ) -> Result<Option<Fingerprint>> {
+        let Some(config_hash) = config_hash(config) else {
+            return Ok(None);
+        };
+
+        let (self_ops, source_config) = partition_self_operations(config);
+        let mut upstreams = BTreeSet::new();
+        for block in std::iter::once(&source_config).chain(self_ops.iter().map(|s| &s.operations)) {
+            let tree: RepoTree = crate::phases::discovery::discover_repos_with_parent(
+                block,
+                &self.working_dir,
+                repo_manager,
+            )?;
+            if !collect_upstreams(&tree.root, &mut upstreams)? {
+                return Ok(None);
+            }
+        }
+        // A promotion in a ref index changes what the next apply fetches
+        for index in crate::ref_index::loaded() {
+            upstreams.insert(Upstream {
+                revision: resolve(&index.url, &index.ref_, index.ref_.is_empty())?,
+                url: index.url,
+                ref_: index.ref_,
+            });
+        }
+
+        Ok(Some(Fingerprint {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config_hash,
+            local: self.local_hash()?,
+            upstreams: upstreams.into_iter().collect(),
+        }))
+    }
+
+    /// Hash of the files in the working and output directories and the
+    /// overrides, leaving out the cache when it lives inside them.
+    fn local_hash(&self) -> Result<String> {
+        let mut lines = Vec::new();
+        let mut roots = vec![&self.working_dir];
+        if self.output_dir != self.working_dir {
+            roots.push(&self.output_dir);
+        }
+        for root in roots {
+            let cache = self.cache_root.strip_prefix(root).ok();
+            let local_fs = crate::phases::local_merge::load_local_fs(root)?;
+            for (path, file) in local_fs.files() {
+                if cache.is_some_and(|cache| path.starts_with(cache)) {
+                    continue;
+                }
+                lines.push(format!(
+                    "{}\t{}\t{:o}\t{}",
+                    root.display(),
+                    path.display(),
+                    file.permissions,
+                    sha256_hex(&file.content)
+                ));
+            }
+        }
+        let overrides = crate::phases::local_merge::load_overrides(&self.working_dir)?;
+        for (path, file) in overrides.files() {
+            lines.push(format!(
+                "{}\t{}\t{:o}\t{}",
+                crate::defaults::OVERRIDES_DIR,
+                path.display(),
+                file.permissions,
+                sha256_hex(&file.content)
+            ));
+        }
+        Ok(sha256_hex(lines.join("\n").as_bytes()))
+    }
+}
+
+/// Hash of `config`, or `None` when it downloads a file that is not pinned
+/// by `sha256`.
+fn config_hash(config: &Schema) -> Option<String> {
+    if has_unpinned_file(config) {
+        return None;
+    }
+    let serialized = serde_json::to_string(config).ok()?;
+    Some(sha256_hex(serialized.as_bytes()))
+}
+
+fn has_unpinned_file(config: &Schema) -> bool {
+    config.iter().any(|operation| match operation {
+        Operation::File { file } => file.sha256.is_none(),
+        Operation::Repo { repo } => has_unpinned_file(&repo.with),
+        Operation::Self_ { self_ } => has_unpinned_file(&self_.operations),
+        _ => false,
+    })
+}
+
+/// Add every upstream below `node` to `upstreams`. Returns false when one
+/// of them downloads an unpinned file.
+fn collect_upstreams(node: &RepoNode, upstreams: &mut BTreeSet<Upstream>) -> Result<bool> {
+    if has_unpinned_file(&node.operations) {
+        return Ok(false);
+    }
+    for operation in &node.operations {
+        if let Operation::Vendor { vendor } = operation {
+            upstreams.insert(Upstream {
+                url: vendor.url.clone(),
+                ref_: vendor.r#ref.clone(),
+                revision: resolve(
+                    &vendor.url,
+                    &vendor.r#ref,
+                    crate::repository::is_local_url(&vendor.url),
+                )?,
+            });
+        }
+    }
+    if node.url != "local" {
+        upstreams.insert(Upstream {
+            url: node.url.clone(),
+            ref_: node.ref_.clone(),
+            revision: resolve(&node.url, &node.ref_, node.is_local())?,
+        });
+    }
+    for child in &node.children {
+        if !collect_upstreams(child, upstreams)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The revision `ref_name` of the repository at `url` currently points to.
+fn resolve(url: &str, ref_name: &str, local: bool) -> Result<String> {
+    if local {
+        let files = crate::git::load_directory_into_memfs(
+            Path::new(url),
+            crate::git::LoadOptions {
+                skip_symlinks: true,
+                skip_git_dirs: true,
+            },
+        )?;
+        let lines: Vec<String> = files
+            .files()
+            .map(|(path, file)| {
+                format!(
+                    "{}\t{:o}\t{}",
+                    path.display(),
+                    file.permissions,
+                    sha256_hex(&file.content)
+                )
+            })
+            .collect();
+        return Ok(format!(
+            "sha256:{}",
+            sha256_hex(lines.join("\n").as_bytes())
+        ));
+    }
+    if crate::oci::is_oci_url(url) {
+        return crate::oci::resolve(url, ref_name);
+    }
+    if is_commit(ref_name) {
+        return Ok(ref_name.to_ascii_lowercase());
+    }
+    crate::git::resolve_ref(url, ref_name)
+}
+
+/// Whether `ref_name` is a full commit SHA, which cannot move.
+fn is_commit(ref_name: &str) -> bool {
+    matches!(ref_name.len(), 40 | 64) && ref_name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FileOp, IncludeOp, RepoOp};
+    use tempfile::TempDir;
+
+    fn file_op(sha256: Option<&str>) -> Operation {
+        Operation::File {
+            file: FileOp {
+                url: "https://example.com/ci.yml".to_string(),
+                sha256: sha256.map(str::to_string),
+                dest: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_config_hash_requires_pinned_files() {
+        let pinned = vec![file_op(Some(&"a".repeat(64)))];
+        assert!(config_hash(&pinned).is_some());
+        assert_ne!(config_hash(&pinned), config_hash(&vec![]));
+
+        assert!(config_hash(&vec![file_op(None)]).is_none());
+        let nested = vec![Operation::Repo {
+            repo: RepoOp {
+                url: "https://github.com/org/shared".to_string(),
+                r#ref: Some("v1".to_string()),
+                path: None,
+                with: vec![file_op(None)],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
+            },
+        }];
+        assert!(config_hash(&nested).is_none());
+    }
+
+    #[test]
+    fn test_local_hash_tracks_files_but_not_cache() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("README.md"), "# Project\n").unwrap();
+        let store = FingerprintStore::new(&dir.path().join(".cache"), dir.path(), dir.path());
+        let before = store.local_hash().unwrap();
+
+        fs::create_dir(dir.path().join(".cache")).unwrap();
+        fs::write(dir.path().join(".cache/state"), "anything").unwrap();
+        assert_eq!(store.local_hash().unwrap(), before);
+
+        fs::write(dir.path().join("README.md"), "# Changed\n").unwrap();
+        let changed = store.local_hash().unwrap();
+        assert_ne!(changed, before);
+
+        let overrides = dir.path().join(crate::defaults::OVERRIDES_DIR);
+        fs::create_dir_all(&overrides).unwrap();
+        fs::write(overrides.join("ci.yml"), "mine: true\n").unwrap();
+        assert_ne!(store.local_hash().unwrap(), changed);
+    }
+
+    #[test]
+    fn test_up_to_date_requires_matching_fingerprint() {
+        let dir = TempDir::new().unwrap();
+        let cache = TempDir::new().unwrap();
+        fs::write(dir.path().join("README.md"), "# Project\n").unwrap();
+        let config = vec![Operation::Include {
+            include: IncludeOp {
+                patterns: vec!["**".to_string()],
+                if_exists: Default::default(),
+                rewrite_links: None,
+            },
+            if_exists: Default::default(),
+            rewrite_links: None,
+        }];
+        let store = FingerprintStore::new(cache.path(), dir.path(), dir.path());
+        assert!(!store.is_up_to_date(&config));
+
+        let manager = RepositoryManager::new(cache.path().to_path_buf());
+        store.save(&config, &manager).unwrap();
+        assert!(store.is_up_to_date(&config));
+        assert!(!store.is_up_to_date(&vec![]));
+
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        assert!(!store.is_up_to_date(&config));
+
+        store.clear();
+        fs::remove_file(dir.path().join("new.txt")).unwrap();
+        assert!(!store.is_up_to_date(&config));
+    }
+
+    #[test]
+    fn test_is_commit() {
+        assert!(is_commit(&"0123456789abcdef".repeat(4)[..40]));
+        assert!(!is_commit("v1.0.0"));
+        assert!(!is_commit("main"));
+    }
+}
@@ -11,6 +11,10 @@
 //!   branch, tag, or commit. This is tuned for speed and disk space by only
 //!   fetching the most recent commit.
 //!
+//! - **`clone_partial`**: Performs a shallow, blobless clone that downloads
+//!   the contents of only the files matching a set of patterns, for large
+//!   upstreams of which a consumer includes little.
+//!
 //! - **`load_from_cache`**: Loads the contents of a cached repository from the
 //!   filesystem into an in-memory `MemoryFS`, which can then be manipulated by
 //!   the application.
@@ -27,15 +31,37 @@
 //! - **`parse_semver_tag`**: A utility function for parsing Git tags into
 //!   semantic versions, which is crucial for the update-checking functionality.
 //!
+//! - **`tracked_files`**: Lists the files git tracks in a consumer working
+//!   tree, used by `apply` to avoid clobbering untracked or ignored files.
+//!
+//! - **`head_commit`**: Reads the commit checked out in a cached clone, which
+//!   results that hold for a commit are cached by.
+//!
+//! - **`user_identity`**: Reads who git commits as, recorded in the
+//!   history of applies.
+//!
+//! - **`files_at_ref`**: Reads files of the consumer repository, with their
+//!   modes, as committed at a given ref, used by `diff --base`.
+//!
+//! - **`commit_files`**: Commits files on top of `HEAD` of the consumer
+//!   repository without touching its working tree or index, used by
+//!   `apply --output-format git-tree`.
+//!
+//! - **`verify_signature`**: Checks that a cloned tag or commit is signed by a
+//!   trusted GPG key or Sigstore identity, for repos with `verify:`.
+//!
 //! By using the system's `git` command, this module avoids the need to
 //! re-implement complex Git logic and ensures that it can handle a wide variety
 //! of repository configurations and authentication setups.
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
+use crate::config::VerifyOp;
 use crate::error::Error;
-use crate::filesystem::{File, MemoryFS};
+use crate::filesystem::{Directory, File, MemoryFS};
 use semver::Version;
 
 /// Clone a repository at a specific ref using shallow clone
@@ -69,37 +95,174 @@ pub fn clone_shallow(url: &str, ref_name: &str, target_dir: &Path) -> Result<(),
         })?;
 
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(clone_error(url, ref_name, &output.stderr));
+    }
 
-        // Provide helpful error message for common auth failures
-        let (message, hint) = if stderr.contains("Authentication failed")
-            || stderr.contains("Permission denied")
-            || stderr.contains("Could not read from remote repository")
-        {
-            (
-                format!("Authentication failed: {}", stderr),
-                Some("Check SSH keys, git credentials, or personal access token".to_string()),
-            )
-        } else if stderr.contains("not found") || stderr.contains("does not exist") {
-            (
-                stderr.to_string(),
-                Some("Verify the repository URL and ref (branch/tag) are correct".to_string()),
-            )
-        } else {
-            (stderr.to_string(), None)
-        };
+    Ok(())
+}
 
-        return Err(Error::GitClone {
-            url: url.to_string(),
-            r#ref: ref_name.to_string(),
-            message,
-            hint,
-        });
+/// Clone a repository at a specific ref, downloading only the files that
+/// `include` patterns can match
+///
+/// The clone is shallow like [`clone_shallow`], and also partial: it is
+/// made with `--filter=blob:none` and without a checkout, so only the
+/// commit and its trees are downloaded. A sparse checkout (see
+/// [`sparse_patterns`]) then fetches the contents of just the files the
+/// patterns can match, and the repository's configuration file. A server
+/// that does not support filtering sends every blob, and the checkout is
+/// still restricted to those files.
+pub fn clone_partial(
+    url: &str,
+    ref_name: &str,
+    target_dir: &Path,
+    include: &[String],
+) -> Result<(), Error> {
+    if target_dir.exists() {
+        fs::remove_dir_all(target_dir)?;
+    }
+    if let Some(parent) = target_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let git_error = |e: std::io::Error| Error::GitClone {
+        url: url.to_string(),
+        r#ref: ref_name.to_string(),
+        message: e.to_string(),
+        hint: Some("Ensure git is installed and accessible".to_string()),
+    };
+    let output = Command::new("git")
+        .args([
+            "clone",
+            "--depth=1",
+            "--filter=blob:none",
+            "--no-checkout",
+            "--branch",
+            ref_name,
+            url,
+        ])
+        .arg(target_dir)
+        .output()
+        .map_err(git_error)?;
+    if !output.status.success() {
+        return Err(clone_error(url, ref_name, &output.stderr));
+    }
+
+    let mut sparse = Command::new("git")
+        .arg("-C")
+        .arg(target_dir)
+        .args(["sparse-checkout", "set", "--no-cone", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(git_error)?;
+    if let Some(mut stdin) = sparse.stdin.take() {
+        stdin.write_all(sparse_patterns(include).join("\n").as_bytes())?;
+    }
+    let output = sparse.wait_with_output().map_err(git_error)?;
+    if !output.status.success() {
+        return Err(clone_error(url, ref_name, &output.stderr));
+    }
+
+    // Fetches the blobs of the files in the sparse checkout
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(target_dir)
+        .args(["checkout", "--quiet"])
+        .output()
+        .map_err(git_error)?;
+    if !output.status.success() {
+        return Err(clone_error(url, ref_name, &output.stderr));
     }
 
     Ok(())
 }
 
+/// The sparse-checkout patterns, in the gitignore-style syntax of
+/// `git sparse-checkout set --no-cone`, for the files that the `include`
+/// glob patterns can match, and the configuration files
+///
+/// The globs are anchored at the repository root and their `*` matches `/`,
+/// unlike gitignore patterns, so each is widened to something git can
+/// match: the directory before its first wildcard, or any file with its
+/// extension for a pattern such as `**/*.md`, or, when neither applies,
+/// everything.
+///
+/// # Examples
+///
+/// ```
+/// use common_repo::git::sparse_patterns;
+///
+/// let include = ["docs/**/*.md".to_string(), "**/*.rs".to_string(), "LICENSE".to_string()];
+/// assert_eq!(
+///     sparse_patterns(&include),
+///     ["/.common-repo.yaml", "/.commonrepo.yaml", "/docs/", "*.rs", "/LICENSE"]
+/// );
+/// ```
+pub fn sparse_patterns(include: &[String]) -> Vec<String> {
+    let mut patterns: Vec<String> = crate::defaults::ConfigFiles::load()
+        .names()
+        .iter()
+        .map(|name| format!("/{}", name))
+        .collect();
+    for glob in include {
+        let glob = glob.trim_start_matches("./");
+        let sparse = match glob.find(['*', '?', '[', '{']) {
+            None => format!("/{}", glob),
+            Some(wild) => match glob[..wild].rfind('/') {
+                Some(slash) => format!("/{}/", &glob[..slash]),
+                None => {
+                    let name = glob.strip_prefix("**/").unwrap_or(glob);
+                    match name.strip_prefix('*') {
+                        Some(suffix)
+                            if !suffix.is_empty()
+                                && !suffix.contains(['*', '?', '[', '{', '/']) =>
+                        {
+                            name.to_string()
+                        }
+                        _ => return vec!["/*".to_string()],
+                    }
+                }
+            },
+        };
+        if !patterns.contains(&sparse) {
+            patterns.push(sparse);
+        }
+    }
+    patterns
+}
+
+/// The error for a failed clone of `url` at `ref_name`, with a hint for
+/// the common failures in git's `stderr`.
+fn clone_error(url: &str, ref_name: &str, stderr: &[u8]) -> Error {
+    let stderr = String::from_utf8_lossy(stderr);
+
+    // Provide helpful error message for common auth failures
+    let (message, hint) = if stderr.contains("Authentication failed")
+        || stderr.contains("Permission denied")
+        || stderr.contains("Could not read from remote repository")
+    {
+        (
+            format!("Authentication failed: {}", stderr),
+            Some("Check SSH keys, git credentials, or personal access token".to_string()),
+        )
+    } else if stderr.contains("not found") || stderr.contains("does not exist") {
+        (
+            stderr.to_string(),
+            Some("Verify the repository URL and ref (branch/tag) are correct".to_string()),
+        )
+    } else {
+        (stderr.to_string(), None)
+    };
+
+    Error::GitClone {
+        url: url.to_string(),
+        r#ref: ref_name.to_string(),
+        message,
+        hint,
+    }
+}
+
 /// Options controlling how a directory is loaded into a `MemoryFS`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LoadOptions {
@@ -147,6 +310,9 @@ pub fn load_from_cache_with_path(cache_dir: &Path, path: Option<&str>) -> Result
 ///
 /// `skip_symlinks` uses `fs::symlink_metadata` to detect symlinks without
 /// following them. `skip_git_dirs` skips any directory named `.git`.
+///
+/// Empty directories are recorded as explicit [`Directory`] entries so they
+/// can be recreated when the filesystem is written out.
 pub fn load_directory_into_memfs(root: &Path, opts: LoadOptions) -> Result<MemoryFS, Error> {
     load_directory_with_filter(root, None, opts)
 }
@@ -186,26 +352,34 @@ fn load_directory_with_filter(
                 if opts.skip_git_dirs && path.ends_with(".git") {
                     continue;
                 }
+                if fs::read_dir(&path)?.next().is_none() {
+                    if let Some(dest) = remap(relative_path, filter_path) {
+                        fs.add_directory(dest, Directory::from_path(&path)?)?;
+                    }
+                    continue;
+                }
                 load_directory(&path, base_path, fs, filter_path, opts)?;
             } else {
                 // Apply path filtering if specified
-                if let Some(filter) = filter_path {
-                    if !relative_path.starts_with(filter) {
-                        continue;
-                    }
-                    let remapped_path = relative_path.strip_prefix(filter).unwrap_or(relative_path);
-                    if remapped_path.as_os_str().is_empty() {
-                        continue;
-                    }
-                    add_file(fs, &path, remapped_path)?;
-                } else {
-                    add_file(fs, &path, relative_path)?;
+                if let Some(dest) = remap(relative_path, filter_path) {
+                    add_file(fs, &path, dest)?;
                 }
             }
         }
         Ok(())
     }
 
+    /// Remap a path relative to the load root into the MemoryFS, honouring
+    /// the optional sub-path filter. Returns `None` when the path falls
+    /// outside the filter.
+    fn remap<'a>(relative_path: &'a Path, filter_path: Option<&PathBuf>) -> Option<&'a Path> {
+        let Some(filter) = filter_path else {
+            return Some(relative_path);
+        };
+        let remapped = relative_path.strip_prefix(filter).ok()?;
+        (!remapped.as_os_str().is_empty()).then_some(remapped)
+    }
+
     load_directory(cache_dir, cache_dir, &mut fs, filter_path.as_ref(), opts)?;
     Ok(fs)
 }
@@ -246,6 +420,20 @@ pub fn save_to_cache(cache_dir: &Path, fs: &MemoryFS) -> Result<(), Error> {
         }
     }
 
+    for (path, dir) in fs.directories() {
+        let full_path = cache_dir.join(path);
+        fs::create_dir_all(&full_path)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = std::fs::Permissions::from_mode(dir.permissions);
+            std::fs::set_permissions(&full_path, perms)?;
+        }
+        #[cfg(not(unix))]
+        let _ = dir;
+    }
+
     Ok(())
 }
 
@@ -274,8 +462,9 @@ pub fn url_to_cache_path_with_path(
     url.hash(&mut hasher);
     let url_hash = format!("{:x}", hasher.finish());
 
-    // Sanitize ref name for filesystem (replace / with -)
-    let safe_ref = ref_name.replace('/', "-");
+    // Sanitize ref name for filesystem (replace /, and the : and @ of OCI
+    // digests, with -)
+    let safe_ref = ref_name.replace(['/', ':', '@'], "-");
 
     // Include path in cache key if present and non-empty
     let cache_key = if let Some(path) = path {
@@ -336,6 +525,521 @@ pub fn list_tags(url: &str) -> Result<Vec<String>, Error> {
     Ok(tags)
 }
 
+/// Returns the commit SHA that `ref_name` points to in the remote
+/// repository at `url`, without fetching it.
+///
+/// Annotated tags resolve to the commit they tag. Fails when the remote has
+/// no branch or tag named `ref_name`.
+pub fn resolve_ref(url: &str, ref_name: &str) -> Result<String, Error> {
+    let command = format!("ls-remote {}", ref_name);
+    let output = Command::new("git")
+        .args(["ls-remote", url, ref_name])
+        .output()
+        .map_err(|e| Error::GitCommand {
+            command: command.clone(),
+            url: url.to_string(),
+            stderr: e.to_string(),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::GitCommand {
+            command,
+            url: url.to_string(),
+            stderr: stderr.to_string(),
+        });
+    }
+
+    // Git ls-remote output format: <hash>\t<ref>. An annotated tag is listed
+    // twice, the second time peeled to its commit as refs/tags/<tag>^{}.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let refs: Vec<(&str, &str)> = stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    refs.iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or_else(|| refs.first())
+        .map(|(hash, _)| hash.to_string())
+        .ok_or_else(|| Error::GitCommand {
+            command,
+            url: url.to_string(),
+            stderr: format!("no branch or tag named '{}'", ref_name),
+        })
+}
+
+/// The commit checked out in the clone at `repo_dir`, or `None` when
+/// `repo_dir` is not the top of a clone.
+///
+/// A directory without its own `.git`, such as a pulled OCI artifact, has
+/// no commit even when it is inside some other repository.
+pub fn head_commit(repo_dir: &Path) -> Option<String> {
+    if !repo_dir.join(".git").exists() {
+        return None;
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD^{commit}"])
+        .output()
+        .ok()?;
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !commit.is_empty()).then_some(commit)
+}
+
+/// The name and email git commits as in `dir`, as `Name <email>`, or
+/// `None` when git has no identity configured there.
+pub fn user_identity(dir: &Path) -> Option<String> {
+    let value = |key: &str| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["config", "--get", key])
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    };
+    match (value("user.name"), value("user.email")) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (Some(name), None) => Some(name),
+        (None, Some(email)) => Some(format!("<{}>", email)),
+        (None, None) => None,
+    }
+}
+
+/// Lists the files tracked by git in the working tree at `dir`.
+///
+/// Paths are relative to `dir`. Returns `Ok(None)` when `dir` is not inside
+/// a git work tree (or git is unavailable), in which case callers have no
+/// tracked/untracked distinction to act on.
+pub fn tracked_files(dir: &Path) -> Result<Option<HashSet<PathBuf>>, Error> {
+    let inside = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output();
+    match inside {
+        Ok(output) if output.status.success() => {}
+        _ => return Ok(None),
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "-z"])
+        .output()
+        .map_err(|e| Error::GitCommand {
+            command: "ls-files".to_string(),
+            url: dir.display().to_string(),
+            stderr: e.to_string(),
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::GitCommand {
+            command: "ls-files".to_string(),
+            url: dir.display().to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    Ok(Some(
+        output
+            .stdout
+            .split(|b| *b == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| PathBuf::from(String::from_utf8_lossy(entry).as_ref()))
+            .collect(),
+    ))
+}
+
+/// A file as committed in a git repository; see [`files_at_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedFile {
+    /// The permission bits git records for the file: `0o644` or `0o755`.
+    pub mode: u32,
+    /// The committed content.
+    pub content: Vec<u8>,
+}
+
+/// Reads the content and mode of `paths` as committed at `ref_name` in the
+/// git repository containing `dir`.
+///
+/// Paths are relative to `dir`, like those of [`tracked_files`]. Paths that
+/// do not exist at `ref_name` (or are not regular files there) are left out
+/// of the result. Fails if `dir` is not in a git repository or `ref_name`
+/// does not name a commit.
+pub fn files_at_ref<'a>(
+    dir: &Path,
+    ref_name: &str,
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> Result<HashMap<PathBuf, CommittedFile>, Error> {
+    let git_error = |command: &str, stderr: String| Error::GitCommand {
+        command: command.to_string(),
+        url: dir.display().to_string(),
+        stderr,
+    };
+
+    // `ls-tree` run from `dir` lists the tree under `dir`, with paths
+    // relative to it.
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-tree", "-r", "-z"])
+        .arg(format!("{}^{{commit}}", ref_name))
+        .output()
+        .map_err(|e| git_error("ls-tree", e.to_string()))?;
+    if !output.status.success() {
+        return Err(git_error(
+            "ls-tree",
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    // Each entry is "<mode> <type> <object>\t<path>".
+    // Symlinks (mode 120000) and submodules are not regular files.
+    let mut blobs: HashMap<PathBuf, (u32, String)> = HashMap::new();
+    for entry in output.stdout.split(|b| *b == 0) {
+        let entry = String::from_utf8_lossy(entry);
+        let Some((info, path)) = entry.split_once('\t') else {
+            continue;
+        };
+        let mut fields = info.split(' ');
+        if let (Some(mode), Some("blob"), Some(object)) =
+            (fields.next(), fields.next(), fields.next())
+        {
+            if let Some(mode) = mode
+                .strip_prefix("100")
+                .and_then(|bits| u32::from_str_radix(bits, 8).ok())
+            {
+                blobs.insert(PathBuf::from(path), (mode, object.to_string()));
+            }
+        }
+    }
+
+    let wanted: Vec<(PathBuf, u32, String)> = paths
+        .into_iter()
+        .filter_map(|path| {
+            blobs
+                .get(path)
+                .map(|(mode, object)| (path.to_path_buf(), *mode, object.clone()))
+        })
+        .collect();
+    if wanted.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // Read all blobs through one `cat-file --batch`, which answers each
+    // object name with "<object> <type> <size>\n<content>\n".
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| git_error("cat-file", e.to_string()))?;
+    let request: String = wanted
+        .iter()
+        .map(|(_, _, object)| format!("{}\n", object))
+        .collect();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| git_error("cat-file", e.to_string()))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(git_error(
+            "cat-file",
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let mut files = HashMap::new();
+    let mut rest = output.stdout.as_slice();
+    for (path, mode, _) in wanted {
+        let malformed = || git_error("cat-file", "unexpected output".to_string());
+        let newline = rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(malformed)?;
+        let header = String::from_utf8_lossy(&rest[..newline]).to_string();
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(malformed)?;
+        let start = newline + 1;
+        let content = rest.get(start..start + size).ok_or_else(malformed)?;
+        files.insert(
+            path,
+            CommittedFile {
+                mode,
+                content: content.to_vec(),
+            },
+        );
+        rest = rest.get(start + size + 1..).unwrap_or_default();
+    }
+    Ok(files)
+}
+
+/// The ref [`commit_files`] points at the commit it creates, so that the
+/// commit is not garbage collected before it is pushed or merged.
+pub const APPLY_REF: &str = "refs/common-repo/apply";
+
+/// Creates a commit of `files` on top of `HEAD` in the git repository
+/// containing `dir`, and returns its id.
+///
+/// Paths are relative to `dir`, like those of [`tracked_files`]. Files not
+/// listed stay as they are in `HEAD`; when the repository has no commits
+/// yet, the commit has no parent and contains only `files`. The commit is
+/// written with `git fast-import` and recorded at [`APPLY_REF`]; the
+/// working tree, the index and the current branch are left alone.
+pub fn commit_files<'a>(
+    dir: &Path,
+    files: impl IntoIterator<Item = (&'a Path, &'a File)>,
+    message: &str,
+) -> Result<String, Error> {
+    let git_error = |command: &str, stderr: String| Error::GitCommand {
+        command: command.to_string(),
+        url: dir.display().to_string(),
+        stderr,
+    };
+    let git = |args: &[&str]| -> Result<(bool, String), Error> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .map_err(|e| git_error(args[0], e.to_string()))?;
+        if output.status.success() {
+            Ok((
+                true,
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ))
+        } else {
+            Ok((
+                false,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    };
+    let required = |args: &[&str]| match git(args)? {
+        (true, stdout) => Ok(stdout),
+        (false, stderr) => Err(git_error(args[0], stderr)),
+    };
+
+    // Paths in the stream are relative to the top of the repository.
+    let prefix = required(&["rev-parse", "--show-prefix"])?;
+    let parent = match git(&["rev-parse", "--verify", "--quiet", "HEAD^{commit}"])? {
+        (true, parent) => Some(parent),
+        (false, _) => None,
+    };
+    let committer = required(&["var", "GIT_COMMITTER_IDENT"])?;
+
+    let mut stream = Vec::new();
+    let _ = write!(
+        stream,
+        "reset {ref_}\ncommit {ref_}\ncommitter {}\ndata {}\n{}\n",
+        committer,
+        message.len(),
+        message,
+        ref_ = APPLY_REF,
+    );
+    if let Some(parent) = &parent {
+        let _ = writeln!(stream, "from {}", parent);
+    }
+    for (path, file) in files {
+        let relative: Vec<_> = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let path = format!("{}{}", prefix, relative.join("/"));
+        let mode = if file.permissions & 0o111 != 0 {
+            "100755"
+        } else {
+            "100644"
+        };
+        let _ = write!(
+            stream,
+            "M {} inline {}\ndata {}\n",
+            mode,
+            quote_fast_import_path(&path),
+            file.content.len()
+        );
+        stream.extend_from_slice(&file.content);
+        stream.push(b'\n');
+    }
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["fast-import", "--quiet", "--force"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| git_error("fast-import", e.to_string()))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&stream));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| git_error("fast-import", e.to_string()))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(git_error(
+            "fast-import",
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    required(&["rev-parse", APPLY_REF])
+}
+
+/// `path` as a C-style quoted string, which `git fast-import` accepts for
+/// any path.
+fn quote_fast_import_path(path: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Checks that the tag or commit checked out at `repo_dir` is signed by one
+/// of the signers in `verify`.
+///
+/// When `ref_name` is an annotated tag, the tag's own signature is checked;
+/// otherwise the checked-out commit's. GPG signatures are checked with
+/// `git verify-tag`/`git verify-commit` against the local keyring, and must
+/// come from a key whose fingerprint (or primary key fingerprint) is listed.
+/// Sigstore identities are checked with `gitsign`.
+pub fn verify_signature(
+    repo_dir: &Path,
+    url: &str,
+    ref_name: &str,
+    verify: &VerifyOp,
+) -> Result<(), Error> {
+    let tag = format!("refs/tags/{}", ref_name);
+    let annotated = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["cat-file", "-t", &tag])
+        .output()
+        .is_ok_and(|output| output.status.success() && output.stdout.trim_ascii() == b"tag");
+    let (object, target) = if annotated {
+        ("tag", tag.as_str())
+    } else {
+        ("commit", "HEAD")
+    };
+
+    let mut failures = Vec::new();
+    let mut hint = None;
+    if !verify.gpg.is_empty() {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo_dir)
+            .args([&format!("verify-{}", object), "--raw", target])
+            .output()
+            .map_err(|e| Error::SignatureVerification {
+                url: url.to_string(),
+                r#ref: ref_name.to_string(),
+                message: e.to_string(),
+                hint: Some("Ensure git is installed and accessible".to_string()),
+            })?;
+        let status = String::from_utf8_lossy(&output.stderr);
+        let signers = gpg_signers(&status);
+        if signers
+            .iter()
+            .any(|signer| verify.gpg.iter().any(|key| same_fingerprint(key, signer)))
+        {
+            return Ok(());
+        }
+        if let Some(signer) = signers.first() {
+            failures.push(format!(
+                "{} is signed by untrusted GPG key {}",
+                object, signer
+            ));
+        } else if status.contains("NO_PUBKEY") {
+            failures.push(format!(
+                "{} is signed by a GPG key that is not in the keyring",
+                object
+            ));
+            hint = Some("Import the publisher's key with 'gpg --import'".to_string());
+        } else {
+            failures.push(format!("{} has no valid GPG signature", object));
+        }
+    }
+
+    for signer in &verify.sigstore {
+        let output = Command::new("gitsign")
+            .current_dir(repo_dir)
+            .arg(if annotated { "verify-tag" } else { "verify" })
+            .arg(format!("--certificate-identity={}", signer.identity))
+            .arg(format!("--certificate-oidc-issuer={}", signer.issuer))
+            .arg(target)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(_) => failures.push(format!(
+                "{} is not signed by Sigstore identity {} ({})",
+                object, signer.identity, signer.issuer
+            )),
+            Err(_) => {
+                failures.push("gitsign is not available".to_string());
+                hint = Some("Install gitsign to verify Sigstore identities".to_string());
+                break;
+            }
+        }
+    }
+
+    Err(Error::SignatureVerification {
+        url: url.to_string(),
+        r#ref: ref_name.to_string(),
+        message: failures.join("; "),
+        hint,
+    })
+}
+
+/// Fingerprints of the keys behind good signatures in `git verify-* --raw`
+/// output: the signing key and, for subkeys, the primary key.
+fn gpg_signers(status: &str) -> Vec<String> {
+    if !status
+        .lines()
+        .any(|line| line.starts_with("[GNUPG:] GOODSIG "))
+    {
+        return Vec::new();
+    }
+    status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|fields| {
+            let fields: Vec<&str> = fields.split_whitespace().collect();
+            [fields.first().copied(), fields.get(9).copied()]
+        })
+        .flatten()
+        .fold(Vec::new(), |mut signers, fingerprint| {
+            if !signers.iter().any(|s| s == fingerprint) {
+                signers.push(fingerprint.to_string());
+            }
+            signers
+        })
+}
+
+/// Whether two GPG fingerprints are equal, ignoring case and spaces.
+fn same_fingerprint(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| s.replace(' ', "").to_ascii_uppercase();
+    normalize(a) == normalize(b)
+}
+
 /// Parses a Git tag string into a `semver::Version`.
 ///
 /// This function is designed to handle common tag formats, such as `v1.2.3`
@@ -388,6 +1092,31 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_gpg_signers() {
+        let status = "\
+[GNUPG:] NEWSIG
+[GNUPG:] GOODSIG 5C3BB8B4 Publisher <pub@example.com>
+[GNUPG:] VALIDSIG 1111222233334444555566667777888899990000 2024-01-01 1704067200 0 4 0 22 10 00 AAAABBBBCCCCDDDDEEEEFFFF0000111122223333
+[GNUPG:] TRUST_UNDEFINED 0 pgp
+";
+        assert_eq!(
+            gpg_signers(status),
+            vec![
+                "1111222233334444555566667777888899990000".to_string(),
+                "AAAABBBBCCCCDDDDEEEEFFFF0000111122223333".to_string(),
+            ]
+        );
+        assert!(same_fingerprint(
+            "aaaa bbbb cccc dddd eeee ffff 0000 1111 2222 3333",
+            "AAAABBBBCCCCDDDDEEEEFFFF0000111122223333"
+        ));
+
+        // A VALIDSIG without GOODSIG (expired or revoked key) is not trusted
+        let expired = status.replace("GOODSIG", "EXPKEYSIG");
+        assert!(gpg_signers(&expired).is_empty());
+    }
+
     #[test]
     fn test_url_to_cache_path() {
         let cache_root = PathBuf::from("/tmp/cache");
@@ -595,13 +1324,13 @@ mod tests {
 
         let file1 = loaded_fs.get_file("file1.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file1.content.clone()).unwrap(),
+            String::from_utf8(file1.content.to_vec()).unwrap(),
             "content1"
         );
 
         let file2 = loaded_fs.get_file("subdir/file2.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file2.content.clone()).unwrap(),
+            String::from_utf8(file2.content.to_vec()).unwrap(),
             "content2"
         );
     }
@@ -894,6 +1623,161 @@ mod tests {
         assert_eq!(fs.len(), 0);
     }
 
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(status.status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_tracked_files_outside_work_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(tracked_files(temp_dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tracked_files_lists_only_tracked() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/tracked.txt"), b"t").unwrap();
+        fs::write(dir.join("untracked.txt"), b"u").unwrap();
+        git(dir, &["add", "sub/tracked.txt"]);
+
+        let tracked = tracked_files(dir).unwrap().unwrap();
+        assert!(tracked.contains(Path::new("sub/tracked.txt")));
+        assert!(!tracked.contains(Path::new("untracked.txt")));
+    }
+
+    #[test]
+    fn test_files_at_ref_reads_committed_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.txt"), b"committed\n").unwrap();
+        fs::write(dir.join("sub/b.txt"), b"").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "init"]);
+        fs::write(dir.join("sub/a.txt"), b"changed").unwrap();
+
+        let files = files_at_ref(
+            &dir.join("sub"),
+            "HEAD",
+            [
+                Path::new("a.txt"),
+                Path::new("b.txt"),
+                Path::new("missing.txt"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[Path::new("a.txt")].content, b"committed\n");
+        assert_eq!(files[Path::new("a.txt")].mode, 0o644);
+        assert!(files[Path::new("b.txt")].content.is_empty());
+    }
+
+    #[test]
+    fn test_head_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        assert_eq!(head_commit(dir), None);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "init"]);
+        let commit = head_commit(dir).unwrap();
+        assert_eq!(commit.len(), 40);
+        // A directory inside the clone is not a clone of its own
+        fs::create_dir(dir.join("sub")).unwrap();
+        assert_eq!(head_commit(&dir.join("sub")), None);
+    }
+
+    #[test]
+    fn test_user_identity() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        git(dir, &["init", "-q"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "user.name", "Test"]);
+        assert_eq!(
+            user_identity(dir).as_deref(),
+            Some("Test <test@example.com>")
+        );
+    }
+
+    #[test]
+    fn test_files_at_ref_unknown_ref() {
+        let temp_dir = TempDir::new().unwrap();
+        git(temp_dir.path(), &["init", "-q"]);
+        let result = files_at_ref(temp_dir.path(), "no-such-ref", [Path::new("a.txt")]);
+        assert!(matches!(result, Err(Error::GitCommand { .. })));
+    }
+
+    #[test]
+    fn test_clone_partial_checks_out_matching_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        fs::create_dir_all(upstream.join("docs")).unwrap();
+        fs::create_dir_all(upstream.join("assets")).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["config", "user.email", "test@example.com"]);
+        git(&upstream, &["config", "user.name", "Test"]);
+        fs::write(upstream.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+        fs::write(upstream.join("docs/guide.md"), b"guide\n").unwrap();
+        fs::write(upstream.join("assets/logo.bin"), b"large\n").unwrap();
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "-q", "-m", "init"]);
+        git(&upstream, &["tag", "v1.0.0"]);
+
+        let target = temp_dir.path().join("clone");
+        let patterns = ["docs/**".to_string()];
+        let url = format!("file://{}", upstream.display());
+        clone_partial(&url, "v1.0.0", &target, &patterns).unwrap();
+
+        let fs = load_from_cache(&target).unwrap();
+        let mut files = fs.list_files();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from(".common-repo.yaml"),
+                PathBuf::from("docs/guide.md")
+            ]
+        );
+        assert_eq!(fs.get_file("docs/guide.md").unwrap().content, b"guide\n");
+
+        let result = clone_partial(&url, "no-such-tag", &target, &patterns);
+        assert!(matches!(result, Err(Error::GitClone { .. })));
+    }
+
+    #[test]
+    fn test_sparse_patterns_widen_globs() {
+        let patterns = |include: &[&str]| {
+            let include: Vec<String> = include.iter().map(|p| p.to_string()).collect();
+            sparse_patterns(&include)
+        };
+        let config = ["/.common-repo.yaml", "/.commonrepo.yaml"];
+        assert_eq!(patterns(&["src/*.rs", "src/*.toml"])[..2], config);
+        assert_eq!(patterns(&["src/*.rs", "src/*.toml"])[2..], ["/src/"]);
+        assert_eq!(
+            patterns(&["./Cargo.toml", "*.md"])[2..],
+            ["/Cargo.toml", "*.md"]
+        );
+        assert_eq!(patterns(&["a/b?/c"])[2..], ["/a/"]);
+        // Nothing narrower than the whole repository can be matched
+        assert_eq!(patterns(&["docs/**", "*"]), ["/*"]);
+        assert_eq!(patterns(&["**/docs/*.md"]), ["/*"]);
+        assert_eq!(patterns(&["{a,b}/x"]), ["/*"]);
+    }
+
     // Note: Integration tests for clone_shallow and list_tags would require
     // actual git repositories and network access, so they're omitted for now
     // Unit tests for list_tags would require mocking the Command output
@@ -935,6 +1819,44 @@ mod loader_tests {
         assert!(fs.exists("link.txt"), "default options preserve symlinks");
     }
 
+    #[test]
+    fn load_directory_into_memfs_records_empty_dirs() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("db/migrations")).unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), b"").unwrap();
+
+        let fs = load_directory_into_memfs(dir.path(), LoadOptions::default()).unwrap();
+
+        assert!(fs.has_directory("db/migrations"));
+        assert!(!fs.has_directory("db"), "non-empty parents are implied");
+        assert!(!fs.has_directory("src"));
+    }
+
+    #[test]
+    fn load_from_cache_with_path_remaps_empty_dirs() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub/empty")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other/empty")).unwrap();
+
+        let fs = load_from_cache_with_path(dir.path(), Some("sub")).unwrap();
+
+        assert_eq!(fs.list_directories(), vec![PathBuf::from("empty")]);
+    }
+
+    #[test]
+    fn save_to_cache_round_trips_empty_dirs() {
+        let dir = TempDir::new().unwrap();
+        let mut fs = MemoryFS::new();
+        fs.add_directory("logs", Directory::new()).unwrap();
+
+        save_to_cache(dir.path(), &fs).unwrap();
+        assert!(dir.path().join("logs").is_dir());
+
+        let loaded = load_from_cache(dir.path()).unwrap();
+        assert!(loaded.has_directory("logs"));
+    }
+
     #[test]
     fn load_directory_into_memfs_skips_git_dir() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,265 @@
+//! # GitHub Actions Output
+//!
+//! When a command runs in a GitHub Actions workflow (`GITHUB_ACTIONS=true`),
+//! it also reports what it found in the two forms GitHub understands:
+//!
+//! - [`Annotation`]s, printed to stdout as `::error`, `::warning` or
+//!   `::notice` workflow commands. An annotation that names a file is shown
+//!   next to that file in the pull request diff.
+//! - A job summary: Markdown appended to the file named by
+//!   `GITHUB_STEP_SUMMARY`, which GitHub renders on the run's summary page.
+//!
+//! Outside of GitHub Actions [`annotate`] and [`append_summary`] do nothing,
+//! so commands call them unconditionally. Annotations go through
+//! [`crate::output::annotation`] and are written even in quiet mode.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::github::{Annotation, Level};
+//!
+//! let annotation = Annotation::new(Level::Warning, "Differs from the configuration")
+//!     .file(".github/workflows/ci.yml")
+//!     .title("Configuration drift");
+//! assert_eq!(
+//!     annotation.to_string(),
+//!     "::warning file=.github/workflows/ci.yml,title=Configuration drift::Differs from the configuration"
+//! );
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::Diagnostics;
+use crate::messages::Message;
+use crate::output;
+
+/// Whether the process runs in a GitHub Actions workflow.
+pub fn is_enabled() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Severity of an [`Annotation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// `::error`
+    Error,
+    /// `::warning`
+    Warning,
+    /// `::notice`
+    Notice,
+}
+
+impl Level {
+    fn command(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Notice => "notice",
+        }
+    }
+}
+
+/// A workflow command that attaches a message to the run, and optionally
+/// to a line of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Severity.
+    pub level: Level,
+    /// Message text. May span lines.
+    pub message: String,
+    /// Short title shown above the message.
+    pub title: Option<String>,
+    /// File the annotation belongs to, relative to the repository root.
+    pub file: Option<PathBuf>,
+    /// 1-based line in `file`.
+    pub line: Option<usize>,
+}
+
+impl Annotation {
+    /// An annotation with `message` that names no file.
+    pub fn new(level: Level, message: impl fmt::Display) -> Self {
+        Self {
+            level,
+            message: message.to_string(),
+            title: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    /// Set the title.
+    pub fn title(mut self, title: impl fmt::Display) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Attach the annotation to `file`, made relative to the workspace.
+    pub fn file(mut self, file: impl AsRef<Path>) -> Self {
+        self.file = Some(workspace_path(file.as_ref()));
+        self
+    }
+
+    /// Attach the annotation to a 1-based line of its file.
+    pub fn line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut properties = Vec::new();
+        if let Some(file) = &self.file {
+            properties.push(format!("file={}", escape_property(&file.to_string_lossy())));
+        }
+        if let Some(line) = self.line {
+            properties.push(format!("line={}", line));
+        }
+        if let Some(title) = &self.title {
+            properties.push(format!("title={}", escape_property(title)));
+        }
+        write!(f, "::{}", self.level.command())?;
+        if !properties.is_empty() {
+            write!(f, " {}", properties.join(","))?;
+        }
+        write!(f, "::{}", escape_data(&self.message))
+    }
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a `key=value` property of a workflow command.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// `path` relative to `GITHUB_WORKSPACE`, which is how annotations name
+/// files. Paths outside the workspace are returned unchanged.
+pub fn workspace_path(path: &Path) -> PathBuf {
+    let Some(workspace) = env::var_os("GITHUB_WORKSPACE").map(PathBuf::from) else {
+        return path.to_path_buf();
+    };
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match env::current_dir() {
+            Ok(dir) => dir.join(path),
+            Err(_) => return path.to_path_buf(),
+        }
+    };
+    let workspace = workspace.canonicalize().unwrap_or(workspace);
+    let absolute = absolute
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .zip(absolute.file_name())
+        .map_or(absolute.clone(), |(parent, name)| parent.join(name));
+    match absolute.strip_prefix(&workspace) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Print `annotation` when running in GitHub Actions.
+pub fn annotate(annotation: Annotation) {
+    if is_enabled() {
+        output::annotation(annotation);
+    }
+}
+
+/// Annotate each pipeline warning in `diagnostics`.
+pub fn annotate_diagnostics(diagnostics: &Diagnostics) {
+    for diagnostic in diagnostics {
+        annotate(Annotation::new(Level::Warning, &diagnostic.message).title(
+            Message::WarningAnnotationTitle {
+                phase: diagnostic.phase.as_str(),
+            },
+        ));
+    }
+}
+
+/// Render a Markdown table for a job summary.
+pub fn summary_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut table = row(headers.iter().map(|h| escape_cell(h)).collect());
+    table.push_str(&row(headers.iter().map(|_| "---".to_string()).collect()));
+    for cells in rows {
+        table.push_str(&row(cells.iter().map(|c| escape_cell(c)).collect()));
+    }
+    table
+}
+
+/// Escape a table cell so it stays on one row.
+fn escape_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Append `markdown` to the job summary when running in GitHub Actions.
+///
+/// The summary is best effort: a failure to write it is logged and does not
+/// fail the command.
+pub fn append_summary(markdown: &str) {
+    if !is_enabled() {
+        return;
+    }
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") else {
+        return;
+    };
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", markdown));
+    if let Err(e) = result {
+        log::debug!(
+            "failed to write job summary {}: {}",
+            Path::new(&path).display(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_escaping() {
+        let annotation = Annotation::new(Level::Error, "100% broken\nsee log")
+            .title("a: b, c")
+            .line(7);
+        assert_eq!(
+            annotation.to_string(),
+            "::error line=7,title=a%3A b%2C c::100%25 broken%0Asee log"
+        );
+        assert_eq!(
+            Annotation::new(Level::Notice, "ok").to_string(),
+            "::notice::ok"
+        );
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let table = summary_table(
+            &["File", "Change"],
+            &[vec!["a|b.txt".to_string(), "added".to_string()]],
+        );
+        assert_eq!(
+            table,
+            "| File | Change |\n| --- | --- |\n| a\\|b.txt | added |\n"
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! # Single-File Downloads
+//!
+//! Fetches individual files over HTTP(S) for the `file:` operation. Like
+//! [`crate::git`], this is a thin wrapper around a system tool, here `curl`,
+//! so proxy settings, `.netrc` credentials and custom CA bundles configured
+//! for it apply unchanged.
+//!
+//! ## Caching and Rate Limits
+//!
+//! Once [`set_cache_dir`] is called, as [`crate::repository::RepositoryManager`]
+//! does, downloads are cached under `http/` in that directory:
+//!
+//! - A response with an `ETag` is stored, and the next download of the URL
+//!   asks for it with `If-None-Match`, so an unchanged file costs a `304`
+//!   instead of a full response against the host's quota. The ETag and the
+//!   body are kept in one file, replaced atomically, so a download cut short
+//!   never pairs a body with the ETag of another.
+//! - A `429`, or a `403` with `X-RateLimit-Remaining: 0`, is retried once
+//!   when `Retry-After` (or `X-RateLimit-Reset`) asks for a short wait. A
+//!   longer wait is recorded per host in `http/rate-limits.json`, and until
+//!   it has passed the host is not contacted: the cached copy is used with a
+//!   warning, or the download fails without another request.
+//!
+//! ## Integrity
+//!
+//! A download can be pinned to a SHA-256 digest with [`verify_sha256`]. The
+//! `file:` operation only accepts plain `http://` URLs when a digest is
+//! given, and redirects are only followed to `https://` URLs.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::http;
+//!
+//! assert_eq!(
+//!     http::sha256_hex(b"hello\n"),
+//!     "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+//! );
+//! assert!(http::verify_sha256("https://example.com/x", b"hello\n", "5891B5B5").is_err());
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
+use crate::messages::Message;
+
+/// Longest rate-limit wait, in seconds, that is waited out before retrying.
+const MAX_RETRY_WAIT: u64 = 30;
+
+/// Wait assumed when a rate-limited response does not say how long to wait.
+const DEFAULT_RETRY_WAIT: u64 = 60;
+
+/// Root of the download cache, set by [`set_cache_dir`].
+static CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Number of cache files this process has started writing, to name each
+/// temporary file uniquely.
+static CACHE_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Cache downloads and rate-limit state under `dir/http`.
+pub fn set_cache_dir(dir: PathBuf) {
+    *CACHE_DIR.write().unwrap_or_else(PoisonError::into_inner) = Some(dir);
+}
+
+/// Download `url` and return its body.
+///
+/// Fails on HTTP error statuses as well as on network errors. See the module
+/// docs for how responses are cached and rate limits are handled.
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    let cache = CACHE_DIR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_deref()
+        .map(|root| Cache::new(root, url));
+    let host = host(url);
+    let (etag, cached) = cache.as_ref().and_then(Cache::response).unzip();
+
+    if let Some(wait) = cache.as_ref().and_then(|cache| cache.blocked_for(&host)) {
+        return rate_limited(url, &host, wait, cached);
+    }
+
+    let mut retried = false;
+    loop {
+        let response = fetch(url, etag.as_deref())?;
+
+        if let Some(wait) = response.rate_limit_wait() {
+            if matches!(response.status, 403 | 429) {
+                if !retried && wait <= MAX_RETRY_WAIT {
+                    log::debug!("{} is rate limited; retrying in {}s", host, wait);
+                    thread::sleep(Duration::from_secs(wait));
+                    retried = true;
+                    continue;
+                }
+                if let Some(cache) = &cache {
+                    cache.block(&host, wait);
+                }
+                return rate_limited(url, &host, wait, cached);
+            }
+            // The request went through, but the quota is used up
+            if let Some(cache) = &cache {
+                cache.block(&host, wait);
+            }
+        }
+
+        return match (response.status, cached) {
+            (304, Some(body)) => Ok(body),
+            (200..=299, _) => {
+                if let Some(cache) = &cache {
+                    cache.store(response.header("etag"), &response.body);
+                }
+                Ok(response.body)
+            }
+            (status, _) => Err(Error::Download {
+                url: url.to_string(),
+                message: format!("the server returned HTTP {}", status),
+                hint: match status {
+                    404 => Some("Verify the URL points to the raw file content".to_string()),
+                    401 | 403 => Some("Configure credentials for the host in ~/.netrc".to_string()),
+                    _ => None,
+                },
+            }),
+        };
+    }
+}
+
+/// Run curl for `url`, sending `If-None-Match` when an ETag is known.
+fn fetch(url: &str, etag: Option<&str>) -> Result<Response> {
+    let mut command = Command::new("curl");
+    command.args([
+        "--silent",
+        "--show-error",
+        "--include",
+        "--suppress-connect-headers",
+        "--location",
+        "--proto",
+        "=https,http",
+        "--proto-redir",
+        "=https",
+        "--connect-timeout",
+        "30",
+    ]);
+    if let Some(etag) = etag {
+        command
+            .arg("--header")
+            .arg(format!("If-None-Match: {}", etag));
+    }
+    let output = command
+        .args(["--", url])
+        .output()
+        .map_err(|e| Error::Download {
+            url: url.to_string(),
+            message: e.to_string(),
+            hint: Some("Ensure curl is installed and accessible".to_string()),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Download {
+            url: url.to_string(),
+            message: stderr.trim().trim_start_matches("curl: ").to_string(),
+            hint: None,
+        });
+    }
+
+    Response::parse(&output.stdout).ok_or_else(|| Error::Download {
+        url: url.to_string(),
+        message: "malformed HTTP response".to_string(),
+        hint: None,
+    })
+}
+
+/// Use the cached copy of a rate-limited URL, or fail.
+fn rate_limited(url: &str, host: &str, wait: u64, cached: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    match cached {
+        Some(body) => {
+            diagnostics::warn(
+                ErrorPhase::Processing,
+                Message::RateLimitedCachedFile {
+                    url,
+                    host,
+                    seconds: wait,
+                }
+                .to_string(),
+            );
+            Ok(body)
+        }
+        None => Err(Error::Download {
+            url: url.to_string(),
+            message: format!("{} is rate limiting requests for another {}s", host, wait),
+            hint: Some(
+                "Retry later, or configure credentials for the host in ~/.netrc to raise the limit"
+                    .to_string(),
+            ),
+        }),
+    }
+}
+
+/// The host part of `url`, used to key rate limits.
+pub(crate) fn host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// The final response in curl's `--include` output.
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    /// Header names are lowercase.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Split `raw` into the headers and body of the last response, skipping
+    /// the header blocks of interim and followed redirect responses.
+    fn parse(raw: &[u8]) -> Option<Self> {
+        let mut rest = raw;
+        loop {
+            let end = rest.windows(4).position(|window| window == b"\r\n\r\n")?;
+            let head = String::from_utf8_lossy(&rest[..end]);
+            rest = &rest[end + 4..];
+
+            let mut lines = head.lines();
+            let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+            let followed = (100..200).contains(&status)
+                || ((300..400).contains(&status) && rest.starts_with(b"HTTP/"));
+            if !followed {
+                let headers = lines
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| {
+                        (name.trim().to_ascii_lowercase(), value.trim().to_string())
+                    })
+                    .collect();
+                return Some(Response {
+                    status,
+                    headers,
+                    body: rest.to_vec(),
+                });
+            }
+        }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Seconds to wait before the next request to this host, when the
+    /// response says the rate limit is used up.
+    fn rate_limit_wait(&self) -> Option<u64> {
+        let exhausted = self.status == 429
+            || self
+                .header("x-ratelimit-remaining")
+                .is_some_and(|remaining| remaining.trim() == "0");
+        if !exhausted {
+            return None;
+        }
+        let retry_after = self
+            .header("retry-after")
+            .and_then(|value| value.parse::<u64>().ok());
+        let reset = self
+            .header("x-ratelimit-reset")
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|reset| reset.saturating_sub(now()));
+        Some(retry_after.or(reset).unwrap_or(DEFAULT_RETRY_WAIT))
+    }
+}
+
+/// Cached state for one URL under `<cache root>/http`.
+struct Cache {
+    dir: PathBuf,
+    key: String,
+}
+
+impl Cache {
+    fn new(root: &Path, url: &str) -> Self {
+        Cache {
+            dir: root.join("http"),
+            key: sha256_hex(url.as_bytes()),
+        }
+    }
+
+    /// The kept ETag and body: the ETag on the first line, then the body.
+    fn response(&self) -> Option<(String, Vec<u8>)> {
+        let mut content = fs::read(self.dir.join(format!("{}.response", self.key))).ok()?;
+        let newline = content.iter().position(|byte| *byte == b'\n')?;
+        let body = content.split_off(newline + 1);
+        content.pop();
+        Some((String::from_utf8(content).ok()?, body))
+    }
+
+    /// Keep `body` for the next conditional request. Responses without an
+    /// ETag are not kept. Failures only cost the cache, so they are logged.
+    fn store(&self, etag: Option<&str>, body: &[u8]) {
+        let Some(etag) = etag.filter(|etag| !etag.contains('\n')) else {
+            return;
+        };
+        let mut content = Vec::with_capacity(etag.len() + 1 + body.len());
+        content.extend_from_slice(etag.as_bytes());
+        content.push(b'\n');
+        content.extend_from_slice(body);
+        if let Err(e) = self.write(&format!("{}.response", self.key), &content) {
+            log::debug!("could not cache download in {}: {}", self.dir.display(), e);
+        }
+    }
+
+    /// Replace the file `name` with `content`, through a temporary file
+    /// renamed into place, so that a concurrent reader sees either the old
+    /// or the new content in full.
+    fn write(&self, name: &str, content: &[u8]) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let temp = self.dir.join(format!(
+            ".{}.{}-{}",
+            name,
+            std::process::id(),
+            CACHE_WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written =
+            fs::write(&temp, content).and_then(|_| fs::rename(&temp, self.dir.join(name)));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written
+    }
+
+    fn rate_limits(&self) -> HashMap<String, u64> {
+        fs::read(self.dir.join("rate-limits.json"))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Seconds left before `host` may be contacted again, if it is blocked.
+    fn blocked_for(&self, host: &str) -> Option<u64> {
+        let until = *self.rate_limits().get(host)?;
+        until.checked_sub(now()).filter(|wait| *wait > 0)
+    }
+
+    /// Record that `host` should not be contacted for `wait` seconds.
+    fn block(&self, host: &str, wait: u64) {
+        let now = now();
+        let mut limits = self.rate_limits();
+        limits.retain(|_, until| *until > now);
+        limits.insert(host.to_string(), now + wait);
+        let content = serde_json::to_vec_pretty(&limits).unwrap_or_default();
+        if let Err(e) = self.write("rate-limits.json", &content) {
+            log::debug!("could not record rate limit for {}: {}", host, e);
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 digest of `content`.
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Check `content` downloaded from `url` against an expected SHA-256 digest.
+///
+/// The comparison ignores ASCII case.
+pub fn verify_sha256(url: &str, content: &[u8], expected: &str) -> Result<()> {
+    let actual = sha256_hex(content);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(Error::ChecksumMismatch {
+            url: url.to_string(),
+            expected: expected.to_ascii_lowercase(),
+            actual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_verify_sha256() {
+        let digest = sha256_hex(b"content");
+        verify_sha256("https://example.com/f", b"content", &digest).unwrap();
+        verify_sha256("https://example.com/f", b"content", &digest.to_uppercase()).unwrap();
+
+        let err = verify_sha256("https://example.com/f", b"changed", &digest).unwrap_err();
+        assert_eq!(err.code(), "E1009");
+        assert!(err.to_string().contains(&digest));
+    }
+
+    #[test]
+    fn test_parse_response_skips_followed_redirects() {
+        let raw = b"HTTP/1.1 302 Found\r\nLocation: https://cdn.example.com/f\r\n\r\n\
+HTTP/2 200\r\nETag: \"abc\"\r\nX-RateLimit-Remaining: 59\r\n\r\nbody\r\n\r\nmore";
+        let response = Response::parse(raw).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("etag"), Some("\"abc\""));
+        assert_eq!(response.body, b"body\r\n\r\nmore");
+        assert_eq!(response.rate_limit_wait(), None);
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let response =
+            Response::parse(b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 7\r\n\r\n").unwrap();
+        assert_eq!(response.rate_limit_wait(), Some(7));
+
+        let reset = now() + 120;
+        let raw = format!(
+            "HTTP/1.1 403 Forbidden\r\nX-RateLimit-Remaining: 0\r\nX-RateLimit-Reset: {}\r\n\r\n",
+            reset
+        );
+        let wait = Response::parse(raw.as_bytes())
+            .unwrap()
+            .rate_limit_wait()
+            .unwrap();
+        assert!((118..=120).contains(&wait));
+
+        let forbidden = Response::parse(b"HTTP/1.1 403 Forbidden\r\n\r\n").unwrap();
+        assert_eq!(forbidden.rate_limit_wait(), None);
+    }
+
+    #[test]
+    fn test_cache_blocks_host_until_reset() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let cache = Cache::new(temp.path(), "https://example.com/f");
+        assert_eq!(cache.blocked_for("example.com"), None);
+
+        cache.block("example.com", 300);
+        let wait = cache.blocked_for("example.com").unwrap();
+        assert!((299..=300).contains(&wait));
+        assert_eq!(cache.blocked_for("other.example.com"), None);
+
+        cache.store(Some("\"v1\""), b"content\nwith lines\n");
+        let (etag, body) = cache.response().unwrap();
+        assert_eq!(etag, "\"v1\"");
+        assert_eq!(body, b"content\nwith lines\n");
+
+        // Only the response file is left, without temporary files
+        let names: Vec<_> = fs::read_dir(temp.path().join("http"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names.len(), 2, "{:?}", names);
+    }
+
+    #[test]
+    fn test_download_reports_failure() {
+        // Port 9 (discard) is not served on loopback, so the connection fails
+        let err = download("http://127.0.0.1:9/file.txt").unwrap_err();
+        assert_eq!(err.code(), "E1008");
+    }
+}
@@ -0,0 +1,324 @@
+//! # Importing From Other Templating Tools
+//!
+//! Reads the answer and state files that other project templating tools
+//! leave in a generated project and turns them into an equivalent
+//! `.common-repo.yaml`. This backs the `import` command.
+//!
+//! ## Recognized Tools
+//!
+//! | Tool | File | Template | Ref | Answers |
+//! |------|------|----------|-----|---------|
+//! | copier | `.copier-answers.yml` | `_src_path` | `_commit` | top-level keys |
+//! | cruft | `.cruft.json` | `template` (and `directory`) | `commit`, else `checkout` | `context.cookiecutter` |
+//! | cookiecutter replay | `~/.cookiecutter_replay/<name>.json` | `cookiecutter._template` | `cookiecutter._checkout` | `cookiecutter` |
+//!
+//! The template becomes a `repo` operation and the answers become
+//! `template-vars`. Keys starting with `_` are the tools' own bookkeeping
+//! and are left out. Answers that are lists or maps have no
+//! `template-vars` equivalent and are reported instead.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::import::{self, Tool};
+//!
+//! let answers = "_commit: v1.2.0\n_src_path: gh:acme/python-template\nproject_name: demo\n";
+//! let imported = import::read(Tool::Copier, answers).unwrap();
+//!
+//! assert_eq!(imported.url, "https://github.com/acme/python-template.git");
+//! assert_eq!(imported.r#ref, "v1.2.0");
+//! assert_eq!(imported.vars["project_name"], "demo");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// A templating tool whose state can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// copier, from `.copier-answers.yml`.
+    Copier,
+    /// cruft, from `.cruft.json`.
+    Cruft,
+    /// A cookiecutter replay file.
+    CookiecutterReplay,
+}
+
+impl Tool {
+    /// The file the tool keeps in a generated project, if it keeps one
+    /// there. Replay files live in the user's home directory instead.
+    pub fn default_file(self) -> Option<&'static str> {
+        match self {
+            Tool::Copier => Some(".copier-answers.yml"),
+            Tool::Cruft => Some(".cruft.json"),
+            Tool::CookiecutterReplay => None,
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Tool::Copier => "copier",
+            Tool::Cruft => "cruft",
+            Tool::CookiecutterReplay => "cookiecutter-replay",
+        })
+    }
+}
+
+/// What was read from a tool's state file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// The template repository URL.
+    pub url: String,
+    /// The Git reference the project was generated from.
+    pub r#ref: String,
+    /// The sub-directory of the template repository, if any.
+    pub path: Option<String>,
+    /// The answers that become template variables.
+    pub vars: BTreeMap<String, String>,
+    /// Things the configuration could not carry over, for the user to
+    /// review.
+    pub notes: Vec<String>,
+}
+
+impl Imported {
+    /// The `.common-repo.yaml` content for this template and its answers.
+    pub fn to_config(&self, tool: Tool) -> String {
+        let mut config = String::from("# common-repo configuration\n");
+        config.push_str(&format!("# Imported from {}\n\n", tool));
+        config.push_str("- repo:\n");
+        config.push_str(&format!("    url: {}\n", scalar(&self.url)));
+        config.push_str(&format!("    ref: {}\n", scalar(&self.r#ref)));
+        if let Some(path) = &self.path {
+            config.push_str(&format!("    path: {}\n", scalar(path)));
+        }
+        if !self.vars.is_empty() {
+            config.push_str("\n- template-vars:\n");
+            for (name, value) in &self.vars {
+                config.push_str(&format!("    {}: {}\n", scalar(name), scalar(value)));
+            }
+        }
+        config
+    }
+}
+
+/// Read the state file `content` written by `tool`.
+pub fn read(tool: Tool, content: &str) -> Result<Imported> {
+    let parse_error = |e: &dyn fmt::Display| Error::ConfigParse {
+        message: format!("Failed to parse the {} file: {}", tool, e),
+        hint: None,
+    };
+    let document: Value = match tool {
+        Tool::Copier => serde_yaml::from_str(content).map_err(|e| parse_error(&e))?,
+        Tool::Cruft | Tool::CookiecutterReplay => {
+            serde_json::from_str(content).map_err(|e| parse_error(&e))?
+        }
+    };
+    let (template, r#ref, path, answers) = match tool {
+        Tool::Copier => (
+            document.get("_src_path"),
+            document.get("_commit"),
+            None,
+            Some(&document),
+        ),
+        Tool::Cruft => (
+            document.get("template"),
+            document
+                .get("commit")
+                .filter(|commit| !commit.is_null())
+                .or_else(|| document.get("checkout")),
+            document.get("directory"),
+            document.pointer("/context/cookiecutter"),
+        ),
+        Tool::CookiecutterReplay => {
+            let answers = document.get("cookiecutter");
+            (
+                answers.and_then(|answers| answers.get("_template")),
+                answers.and_then(|answers| answers.get("_checkout")),
+                None,
+                answers,
+            )
+        }
+    };
+
+    let template = template
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::ConfigParse {
+            message: format!("The {} file does not name the template it came from", tool),
+            hint: Some("Check that the file was written by the tool given with --from".to_string()),
+        })?;
+    let mut notes = Vec::new();
+    let r#ref = match r#ref.and_then(Value::as_str) {
+        Some(r#ref) => describe_base(r#ref, &mut notes),
+        None => {
+            notes.push("No ref was recorded; using 'main'. Pin a tag or commit.".to_string());
+            "main".to_string()
+        }
+    };
+
+    let mut vars = BTreeMap::new();
+    if let Some(Value::Object(answers)) = answers {
+        for (name, value) in answers {
+            if name.starts_with('_') {
+                continue;
+            }
+            match value {
+                Value::String(value) => {
+                    vars.insert(name.clone(), value.clone());
+                }
+                Value::Bool(_) | Value::Number(_) => {
+                    vars.insert(name.clone(), value.to_string());
+                }
+                Value::Null => {
+                    vars.insert(name.clone(), String::new());
+                }
+                Value::Array(_) | Value::Object(_) => notes.push(format!(
+                    "Answer '{}' is a list or map and was not imported",
+                    name
+                )),
+            }
+        }
+    }
+
+    Ok(Imported {
+        url: template_url(template),
+        r#ref,
+        path: path
+            .and_then(Value::as_str)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string),
+        vars,
+        notes,
+    })
+}
+
+/// Expand the `gh:` and `gl:` shorthands the tools accept into clone URLs.
+pub fn template_url(template: &str) -> String {
+    let expand = |host: &str, repo: &str| {
+        let repo = repo.trim_end_matches('/');
+        if repo.ends_with(".git") {
+            format!("https://{}/{}", host, repo)
+        } else {
+            format!("https://{}/{}.git", host, repo)
+        }
+    };
+    if let Some(repo) = template.strip_prefix("gh:") {
+        expand("github.com", repo)
+    } else if let Some(repo) = template.strip_prefix("gl:") {
+        expand("gitlab.com", repo)
+    } else if let Some(url) = template.strip_prefix("git+") {
+        url.to_string()
+    } else {
+        template.to_string()
+    }
+}
+
+/// The tag of a `git describe` ref such as `v1.2.0-3-gabc1234`, which copier
+/// records for projects generated from an untagged commit.
+fn describe_base(r#ref: &str, notes: &mut Vec<String>) -> String {
+    let mut parts = r#ref.rsplitn(3, '-');
+    if let (Some(hash), Some(count), Some(tag)) = (parts.next(), parts.next(), parts.next()) {
+        if hash.starts_with('g') && count.chars().all(|c| c.is_ascii_digit()) {
+            notes.push(format!(
+                "The project was generated {} commit(s) after {}; using {}",
+                count, tag, tag
+            ));
+            return tag.to_string();
+        }
+    }
+    r#ref.to_string()
+}
+
+/// `value` as a YAML scalar, quoted when it would not read back as the same
+/// string.
+fn scalar(value: &str) -> String {
+    let plain = serde_yaml::from_str::<serde_yaml::Value>(value)
+        .ok()
+        .and_then(|parsed| parsed.as_str().map(|parsed| parsed == value))
+        .unwrap_or(false);
+    if plain && !value.contains(": ") && !value.contains(" #") {
+        value.to_string()
+    } else {
+        serde_json::to_string(value).expect("strings serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_copier_answers() {
+        let imported = read(
+            Tool::Copier,
+            "# Changes here will be overwritten by Copier\n_commit: v1.2.0-3-gabc1234\n\
+             _src_path: gh:acme/template\nproject_name: demo\nuse_ci: true\nlicenses: [MIT]\n",
+        )
+        .unwrap();
+
+        assert_eq!(imported.url, "https://github.com/acme/template.git");
+        assert_eq!(imported.r#ref, "v1.2.0");
+        assert_eq!(imported.vars.len(), 2);
+        assert_eq!(imported.vars["use_ci"], "true");
+        assert_eq!(imported.notes.len(), 2);
+        assert!(imported.notes[1].contains("'licenses'"));
+    }
+
+    #[test]
+    fn test_read_cruft_state() {
+        let imported = read(
+            Tool::Cruft,
+            r#"{
+  "template": "https://github.com/acme/cookiecutter-lib",
+  "commit": "0123456789abcdef0123456789abcdef01234567",
+  "checkout": null,
+  "directory": "python",
+  "context": {
+    "cookiecutter": {
+      "project_slug": "demo",
+      "version": 1,
+      "_template": "https://github.com/acme/cookiecutter-lib"
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(imported.r#ref, "0123456789abcdef0123456789abcdef01234567");
+        assert_eq!(imported.path.as_deref(), Some("python"));
+        assert_eq!(
+            imported.vars,
+            BTreeMap::from([
+                ("project_slug".to_string(), "demo".to_string()),
+                ("version".to_string(), "1".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_cookiecutter_replay_without_ref() {
+        let imported = read(
+            Tool::CookiecutterReplay,
+            r#"{"cookiecutter": {"name": "a: b", "_template": "gl:acme/tpl"}}"#,
+        )
+        .unwrap();
+        assert_eq!(imported.url, "https://gitlab.com/acme/tpl.git");
+        assert_eq!(imported.r#ref, "main");
+
+        let config = imported.to_config(Tool::CookiecutterReplay);
+        assert!(config.contains("    name: \"a: b\"\n"), "{config}");
+        let schema = crate::config::parse(&config).unwrap();
+        assert_eq!(schema.len(), 2);
+    }
+
+    #[test]
+    fn test_read_without_template_fails() {
+        let err = read(Tool::Cruft, r#"{"commit": "abc"}"#).unwrap_err();
+        assert!(err.to_string().contains("does not name the template"));
+    }
+}
@@ -0,0 +1,319 @@
+//! # Configuration Interpolation
+//!
+//! String values of a configuration's operations can use `${NAME}` to take
+//! a value that differs between consumers or environments:
+//!
+//! ```yaml
+//! - template-vars:
+//!     SERVICE: billing
+//! - repo:
+//!     url: https://github.com/org/shared-config
+//!     ref: ${SHARED_CONFIG_REF}
+//! - rename:
+//!     - "^ci\\.yaml$": ".github/workflows/${SERVICE}.yaml"
+//! ```
+//!
+//! A name is looked up in the inline values of the file's own top-level
+//! `template-vars` entries, a later entry overriding an earlier one, and
+//! then, in the consumer's own configuration only, in the environment. An
+//! upstream's configuration is not given the environment (see
+//! [`interpolate_inline`]), since it could carry a consumer's tokens off the
+//! machine in a URL. Secret variables are not read, since that would run
+//! commands whenever the file is parsed. An undefined name fails the parse,
+//! naming the variable and the operation it is used in.
+//!
+//! Values are interpolated when the configuration is parsed, before any
+//! repository is fetched, so an upstream's `template-vars` cannot set them.
+//! The values under `template-vars` are never interpolated, since they are
+//! templated into files. Only names of letters, digits and underscores are
+//! recognized, so `${{ github.sha }}` in a workflow passes through, and
+//! `$${NAME}` writes a literal `${NAME}`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde_yaml::Value;
+
+use crate::error::{Error, Result};
+
+/// The key of the entries whose values are not interpolated.
+const TEMPLATE_VARS: &str = "template-vars";
+
+/// `content`, a configuration, with the `${NAME}` references in its
+/// operations replaced from its `template-vars` and the environment.
+///
+/// Content without references is returned as it is.
+///
+/// # Errors
+///
+/// Returns `Error::ConfigParse` naming the first variable that is defined
+/// in neither.
+pub fn interpolate(content: &str) -> Result<Cow<'_, str>> {
+    interpolate_with(content, |name| std::env::var(name).ok())
+}
+
+/// Like [`interpolate`], from the `template-vars` of `content` alone.
+///
+/// This is how an upstream's configuration is interpolated: the consumer's
+/// environment holds its secrets, and a reference to one is undefined.
+pub fn interpolate_inline(content: &str) -> Result<Cow<'_, str>> {
+    interpolate_with(content, |_| None)
+}
+
+/// Like [`interpolate`], with `env` in place of the environment.
+///
+/// # Examples
+///
+/// ```
+/// use common_repo::interpolation::interpolate_with;
+///
+/// let yaml = "- template-vars:\n    SERVICE: billing\n- exclude: ['${SERVICE}/**']\n";
+/// let interpolated = interpolate_with(yaml, |_| None).unwrap();
+/// assert!(interpolated.contains("billing/**"));
+/// ```
+pub fn interpolate_with(
+    content: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'_, str>> {
+    if !content.contains("${") {
+        return Ok(Cow::Borrowed(content));
+    }
+    // Content that is not a list of operations fails to parse later.
+    let Ok(Value::Sequence(mut items)) = serde_yaml::from_str::<Value>(content) else {
+        return Ok(Cow::Borrowed(content));
+    };
+    let vars = declared_vars(&items);
+    let lookup = |name: &str| vars.get(name).cloned().or_else(|| env(name));
+    let mut changed = false;
+    for (index, item) in items.iter_mut().enumerate() {
+        changed |= interpolate_value(item, &lookup, index + 1, "")?;
+    }
+    if !changed {
+        return Ok(Cow::Borrowed(content));
+    }
+    serde_yaml::to_string(&Value::Sequence(items))
+        .map(Cow::Owned)
+        .map_err(Error::Yaml)
+}
+
+/// The inline values of the top-level `template-vars` entries of `items`,
+/// in either of their forms.
+fn declared_vars(items: &[Value]) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    for item in items {
+        let Some(Value::Mapping(entry)) = item.get(TEMPLATE_VARS) else {
+            continue;
+        };
+        let values = match entry.get("vars") {
+            Some(Value::Mapping(values)) => values,
+            _ => entry,
+        };
+        for (name, value) in values {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Number(value) => value.to_string(),
+                Value::Bool(value) => value.to_string(),
+                // Secret sources and anything else
+                _ => continue,
+            };
+            if let Some(name) = name.as_str() {
+                vars.insert(name.to_string(), value);
+            }
+        }
+    }
+    vars
+}
+
+/// Interpolate the strings in `value`, found at `path` in the operation
+/// numbered `operation`. Returns whether anything changed.
+fn interpolate_value(
+    value: &mut Value,
+    lookup: &impl Fn(&str) -> Option<String>,
+    operation: usize,
+    path: &str,
+) -> Result<bool> {
+    match value {
+        Value::String(text) => {
+            let interpolated =
+                interpolate_str(text, lookup).map_err(|name| undefined(&name, operation, path))?;
+            if let Cow::Owned(interpolated) = interpolated {
+                *text = interpolated;
+                return Ok(true);
+            }
+            Ok(false)
+        }
+        Value::Sequence(values) => {
+            let mut changed = false;
+            for (index, value) in values.iter_mut().enumerate() {
+                let path = format!("{}[{}]", path, index);
+                changed |= interpolate_value(value, lookup, operation, &path)?;
+            }
+            Ok(changed)
+        }
+        Value::Mapping(entries) => {
+            let mut changed = false;
+            for (key, value) in entries.iter_mut() {
+                let key = key.as_str().unwrap_or("?");
+                if key == TEMPLATE_VARS {
+                    continue;
+                }
+                let path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                changed |= interpolate_value(value, lookup, operation, &path)?;
+            }
+            Ok(changed)
+        }
+        Value::Tagged(tagged) => interpolate_value(&mut tagged.value, lookup, operation, path),
+        Value::Null | Value::Bool(_) | Value::Number(_) => Ok(false),
+    }
+}
+
+/// `text` with its references replaced, or the name of the first one
+/// `lookup` does not know.
+fn interpolate_str<'a>(
+    text: &'a str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> std::result::Result<Cow<'a, str>, String> {
+    if !text.contains("${") {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find("${") {
+        if rest[..at].ends_with('$') {
+            // `$${NAME}` is a literal `${NAME}`
+            out.push_str(&rest[..at - 1]);
+            out.push_str("${");
+            rest = &rest[at + 2..];
+            continue;
+        }
+        out.push_str(&rest[..at]);
+        let after = &rest[at + 2..];
+        match after.find('}').map(|end| (&after[..end], end)) {
+            Some((name, end)) if is_name(name) => {
+                out.push_str(&lookup(name).ok_or_else(|| name.to_string())?);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    if out == text {
+        return Ok(Cow::Borrowed(text));
+    }
+    Ok(Cow::Owned(out))
+}
+
+/// Whether `name` can be interpolated: letters, digits and underscores,
+/// not starting with a digit.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn undefined(name: &str, operation: usize, path: &str) -> Error {
+    Error::ConfigParse {
+        message: format!(
+            "Undefined variable '{}' in operation {} at {}",
+            name, operation, path
+        ),
+        hint: Some(format!(
+            "set it under template-vars (or, in the consumer's own configuration, in the environment), or write $${{{}}} for a literal ${{{}}}",
+            name, name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        (name == "SHARED_CONFIG_REF").then(|| "v2.1.0".to_string())
+    }
+
+    #[test]
+    fn test_interpolates_from_vars_and_env() {
+        let yaml = r#"
+- template-vars:
+    SERVICE: api
+- template-vars:
+    vars:
+      SERVICE: billing
+      label: ${NOT_INTERPOLATED}
+- repo:
+    url: https://github.com/org/shared
+    ref: ${SHARED_CONFIG_REF}
+- rename:
+    - "^ci\\.yaml$": ".github/workflows/${SERVICE}.yaml"
+"#;
+        let interpolated = interpolate_with(yaml, env).unwrap();
+        let items: Vec<Value> = serde_yaml::from_str(&interpolated).unwrap();
+        assert_eq!(items[2]["repo"]["ref"], "v2.1.0");
+        assert_eq!(
+            items[3]["rename"][0]["^ci\\.yaml$"],
+            ".github/workflows/billing.yaml"
+        );
+        assert_eq!(
+            items[1]["template-vars"]["vars"]["label"],
+            "${NOT_INTERPOLATED}"
+        );
+    }
+
+    #[test]
+    fn test_undefined_variable_names_the_operation() {
+        let yaml = "- include: ['**']\n- repo:\n    url: ../shared\n    ref: ${MISSING}\n";
+        let err = interpolate_with(yaml, env).unwrap_err().to_string();
+        assert!(
+            err.contains("'MISSING' in operation 2 at repo.ref"),
+            "Error was: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_inline_interpolation_ignores_the_environment() {
+        let yaml = "- template-vars:\n    SERVICE: billing\n- exclude: ['${SERVICE}/**']\n";
+        assert!(interpolate_inline(yaml).unwrap().contains("billing/**"));
+
+        // Set in every environment the tests run in
+        let yaml = "- exclude: ['${PATH}/**']\n";
+        let err = interpolate_inline(yaml).unwrap_err().to_string();
+        assert!(err.contains("'PATH'"), "{}", err);
+        assert!(interpolate(yaml).is_ok());
+    }
+
+    #[test]
+    fn test_interpolate_str_passes_other_dollars_through() {
+        let lookup = |name: &str| (name == "A").then(|| "x".to_string());
+        assert_eq!(interpolate_str("${A}-${A}", &lookup).unwrap(), "x-x");
+        assert_eq!(interpolate_str("$${A}", &lookup).unwrap(), "${A}");
+        assert_eq!(
+            interpolate_str("${{ github.sha }} $1 ${ A", &lookup).unwrap(),
+            "${{ github.sha }} $1 ${ A"
+        );
+        assert_eq!(interpolate_str("${B}", &lookup).unwrap_err(), "B");
+        assert!(matches!(
+            interpolate_str("plain", &lookup).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_content_without_references_is_borrowed() {
+        let yaml = "- include: ['**']\n";
+        assert!(matches!(
+            interpolate_with(yaml, env).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+}
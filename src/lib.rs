@@ -45,7 +45,36 @@
 //!   process, from repository discovery and cloning to applying operators and
 //!   writing the final result.
 //! - **Repository Management (`repository`, `git`, `cache`)**: Handles cloning,
-//!   caching, and loading Git repositories.
+//!   caching, and loading Git repositories. OCI artifacts are pulled by `oci`
+//!   and single files are downloaded by `http`.
+//! - **Changelogs (`changelog`)**: The release notes an upstream update pulls
+//!   in, from the upstream's changelog file or its GitHub releases.
+//! - **Diagnostics (`diagnostics`)**: Non-fatal problems reported by the
+//!   pipeline, collected for the caller instead of printed.
+//! - **Attributes (`attributes`)**: Text, binary and merge-driver hints for
+//!   paths, read from `.gitattributes` when files are merged.
+//! - **Drift (`drift`)**: Which differences from the configuration's output
+//!   a consumer expects, from its `diff-ignore` entry.
+//! - **Import (`import`)**: Turns the state files of other templating tools,
+//!   such as copier and cruft, into a configuration.
+//! - **Interpolation (`interpolation`)**: `${NAME}` references in a
+//!   configuration's operations, filled in from its `template-vars` and the
+//!   environment when it is parsed.
+//! - **Secrets (`secrets`)**: Template variables read from env files, SOPS
+//!   files or commands, and masked wherever output is written.
+//! - **Protection (`protect`)**: A git pre-commit hook that rejects direct
+//!   edits to managed files.
+//! - **Ref Aliases (`ref_index`)**: Refs such as `stable` looked up in an
+//!   organization's index repository, so one commit there promotes a version
+//!   to every consumer.
+//! - **Promotion (`promote`)**: Carries a consumer's change to a managed
+//!   file back to the file's source in its upstream.
+//! - **Notifications (`notify`)**: Webhooks told the result of every apply,
+//!   configured in the user configuration (`user_config`).
+//! - **Outcomes (`outcome`)**: How a command ended, and the exit code and
+//!   summary block that say so.
+//! - **Reporting (`reporting`)**: Renders what the pipeline discovered, such
+//!   as the inheritance graph, for documentation and reviews.
 //!
 //! ## Execution Flow
 //!
@@ -65,70 +94,128 @@
 //! By separating the logic into these distinct modules and phases, the library
 //! provides a flexible and extensible system for managing shared configurations.
 
+pub mod attributes;
 pub mod cache;
+pub mod changelog;
 pub mod config;
 pub mod defaults;
+pub mod diagnostics;
+pub mod drift;
 pub mod error;
 pub mod filesystem;
+pub mod fingerprint;
 pub mod git;
+pub mod github;
+pub mod http;
+pub mod import;
+pub mod interpolation;
 pub mod merge;
+pub mod messages;
+pub mod migrate;
+pub mod notify;
+pub mod oci;
 pub mod operators;
+pub mod outcome;
 pub mod output;
 pub mod path;
 pub mod phases;
+pub mod promote;
+pub mod protect;
+pub mod ref_index;
+pub mod reporting;
 pub mod repository;
+pub mod secrets;
 pub mod suggestions;
+#[cfg(feature = "test_support")]
+pub mod test_support;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod user_config;
 pub mod version;
 
 /// Standard exit codes for the CLI.
 ///
-/// These constants define the exit codes returned by the `common-repo` binary:
+/// Every command ends with one of these, so scripts can branch on what
+/// happened (see [`outcome`] for how a run is classified):
 ///
-/// - [`SUCCESS`]: Normal successful execution (exit code 0)
-/// - [`ERROR`]: General errors, including configuration errors, network failures,
-///   and processing errors (exit code 1)
+/// - [`SUCCESS`]: Done, nothing to report (exit code 0)
+/// - [`WARNINGS`]: Done, with warnings; or refused to write because of
+///   them under `--deny-warnings` (exit code 1)
+/// - [`DRIFT`]: Local files differ from what the configuration produces,
+///   as `diff` and `check --locked` report (exit code 2)
+/// - [`CONFLICTS`]: Files could not be merged or written as configured,
+///   such as a merge conflict, a read-only destination, or an untracked
+///   file in the way (exit code 3)
+/// - [`FETCH_FAILED`]: An upstream could not be fetched or verified
+///   (exit code 4)
+/// - [`CONFIG_ERROR`]: The configuration is missing or invalid (exit code 5)
+/// - [`ERROR`]: Any other error, such as an I/O failure (exit code 6)
 /// - [`USAGE`]: Invalid command-line usage, such as unknown flags or missing
-///   required arguments (exit code 2, handled by clap)
-///
-/// ## Special Cases
-///
-/// The `diff` command uses exit code 1 to indicate that changes were detected
-/// (files differ from the configuration result). This follows the convention
-/// established by `diff(1)` and `git diff`, where a non-zero exit indicates
-/// differences exist.
+///   required arguments (exit code 64)
 ///
 /// ## Examples
 ///
 /// ```bash
-/// # Success (exit code 0)
-/// common-repo validate && echo "Config is valid"
-///
-/// # Error (exit code 1)
-/// common-repo apply --config nonexistent.yaml || echo "Failed"
-///
-/// # Usage error (exit code 2)
-/// common-repo --unknown-flag  # Prints help and exits with 2
+/// common-repo apply
+/// case $? in
+///   0|1) echo "Applied" ;;
+///   3) echo "Resolve the conflicts" ;;
+///   4) echo "Upstream unreachable, retry later" ;;
+///   *) echo "Failed" ;;
+/// esac
 ///
-/// # Diff with changes (exit code 1)
+/// # Drift (exit code 2)
 /// common-repo diff && echo "No changes" || echo "Changes detected"
 /// ```
+///
+/// [`outcome`]: crate::outcome
 pub mod exit_codes {
-    /// Successful execution (exit code 0).
+    /// Done, nothing to report (exit code 0).
     pub const SUCCESS: i32 = 0;
 
-    /// General error (exit code 1).
+    /// Done, with warnings (exit code 1).
+    ///
+    /// The pipeline reported non-fatal problems, such as a merge that
+    /// replaced a table with a scalar. `apply --deny-warnings` exits with
+    /// this code too when it refuses to write because of them.
+    pub const WARNINGS: i32 = 1;
+
+    /// Drift detected (exit code 2).
     ///
-    /// This includes configuration errors, network failures, file I/O errors,
-    /// and any other runtime errors. For the `diff` command, this exit code
-    /// also indicates that changes were detected.
-    pub const ERROR: i32 = 1;
+    /// Local files differ from what the configuration produces: `diff`
+    /// found changes, or `check --locked` found a locked file edited. Also
+    /// returned when `apply --from-plan` finds local files changed since
+    /// the plan was computed.
+    pub const DRIFT: i32 = 2;
+
+    /// Conflicts (exit code 3).
+    ///
+    /// Files could not be merged or written as configured: a merge conflict
+    /// or type mismatch, or a destination that is read-only, a symlink, or
+    /// an untracked file.
+    pub const CONFLICTS: i32 = 3;
+
+    /// Fetch failure (exit code 4).
+    ///
+    /// An upstream repository, artifact or file could not be fetched, or
+    /// did not match its pinned digest or signature.
+    pub const FETCH_FAILED: i32 = 4;
+
+    /// Configuration error (exit code 5).
+    ///
+    /// The configuration is missing, cannot be parsed, or asks for
+    /// something the upstreams cannot provide.
+    pub const CONFIG_ERROR: i32 = 5;
+
+    /// Any other error (exit code 6).
+    pub const ERROR: i32 = 6;
 
-    /// Invalid command-line usage (exit code 2).
+    /// Invalid command-line usage (exit code 64, `EX_USAGE`).
     ///
-    /// Returned by clap when the user provides invalid arguments, unknown flags,
-    /// or fails to provide required arguments. The CLI will print a help message
+    /// Returned when the user provides invalid arguments, unknown flags, or
+    /// fails to provide required arguments. The CLI prints a usage message
     /// along with this exit code.
-    pub const USAGE: i32 = 2;
+    pub const USAGE: i32 = 64;
 }
 
 #[cfg(test)]
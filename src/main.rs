@@ -3,10 +3,13 @@
 //! This is the binary entry point for the `common-repo` command-line tool.
 //!
 //! Its primary responsibilities are:
-//! - Parsing command-line arguments using `clap`.
+//! - Parsing command-line arguments using `clap`, or answering a dynamic
+//!   completion request from the shell (see `commands::completions`).
 //! - Executing the appropriate command based on the parsed arguments.
 //! - Handling top-level application errors and translating them into user-friendly
 //!   output.
+//! - Ending with the exit code of the command's outcome (see
+//!   `common_repo::exit_codes`), after its summary block.
 //!
 //! The core application logic is defined in the `lib.rs` library crate, ensuring
 //! that the binary is a thin wrapper around the reusable library functionality.
@@ -14,10 +17,48 @@
 mod cli;
 mod commands;
 
-use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use common_repo::exit_codes;
+use common_repo::outcome;
 
-fn main() -> Result<()> {
-    let cli = cli::Cli::parse();
-    cli.execute()
+fn main() {
+    clap_complete::CompleteEnv::with_factory(cli::Cli::command)
+        .var(commands::completions::COMPLETE_ENV)
+        .bin("common-repo")
+        .complete();
+
+    let cli = match cli::Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            // --help and --version end here too.
+            std::process::exit(match err.exit_code() {
+                0 => exit_codes::SUCCESS,
+                _ => exit_codes::USAGE,
+            });
+        }
+    };
+    let json_errors = cli.json_errors();
+    let result = cli.execute();
+    let mut summary = outcome::finish();
+    let code = match result {
+        Ok(()) => summary
+            .as_ref()
+            .map_or(exit_codes::SUCCESS, |summary| summary.exit_code),
+        Err(err) => {
+            cli::annotate_error(&err);
+            eprintln!("{}", cli::error_report(&err, json_errors));
+            let failed = cli::error_outcome(&err);
+            if let Some(summary) = summary.as_mut() {
+                summary.outcome = failed;
+                summary.exit_code = failed.exit_code();
+                summary.error_code = cli::error_code(&err);
+            }
+            failed.exit_code()
+        }
+    };
+    if let Some(summary) = summary {
+        cli::report_summary(&summary, json_errors);
+    }
+    std::process::exit(code);
 }
@@ -0,0 +1,358 @@
+//! Extract operation
+//!
+//! The inverse of a merge: copies one part of a YAML, JSON or Markdown file
+//! into a file of its own (see [`ExtractOp`]), for consumers that want one
+//! job of a large shared workflow or one section of a long document.
+//!
+//! The format follows the source's extension. YAML and JSON values are
+//! found with the merge operators' path syntax (see [`parse_path`]) and
+//! Markdown sections by their heading, as the `markdown` merge does. The
+//! destination keeps the source file's permissions and template marking,
+//! so placeholders in the extracted part are still substituted.
+
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+
+use super::markdown::{find_section_bounds, split_lines_preserve};
+use super::{ensure_trailing_newline, parse_path, read_file_as_string, PathSegment};
+use crate::config::ExtractOp;
+use crate::error::{Error, Result};
+use crate::filesystem::MemoryFS;
+
+/// The formats a part can be extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Yaml,
+    Json,
+    Markdown,
+}
+
+impl Format {
+    fn of(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "yml" | "yaml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "md" | "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Apply an extract operation to the filesystem
+///
+/// Reads `source`, takes the value at `path` or the Markdown `section`, and
+/// writes it to `dest`, replacing any file there.
+///
+/// # Errors
+///
+/// Returns `Error::Merge` if the source is missing or not UTF-8, its format
+/// is not YAML, JSON or Markdown, the option its format needs is missing, or
+/// nothing is at the path or section.
+pub fn apply_extract_operation(fs: &mut MemoryFS, op: &ExtractOp) -> Result<()> {
+    let content = read_file_as_string(fs, &op.source)?;
+    let extracted = match Format::of(&op.source) {
+        Some(Format::Yaml) => {
+            let document: YamlValue = serde_yaml::from_str(&content)
+                .map_err(|e| fail(op, format!("could not parse {} as YAML: {}", op.source, e)))?;
+            let value = extract_yaml(&document, op)?;
+            serde_yaml::to_string(&value)
+                .map_err(|e| fail(op, format!("could not write YAML: {}", e)))?
+        }
+        Some(Format::Json) => {
+            let document: JsonValue = serde_json::from_str(&content)
+                .map_err(|e| fail(op, format!("could not parse {} as JSON: {}", op.source, e)))?;
+            let value = extract_json(&document, op)?;
+            serde_json::to_string_pretty(&value)
+                .map_err(|e| fail(op, format!("could not write JSON: {}", e)))?
+        }
+        Some(Format::Markdown) => extract_markdown(&content, op)?,
+        None => {
+            return Err(fail(
+                op,
+                "only YAML, JSON and Markdown files are supported".to_string(),
+            ))
+        }
+    };
+
+    // Keep the source's permissions and template marking
+    let mut file = fs
+        .get_file(&op.source)
+        .cloned()
+        .expect("the source was read above");
+    file.content = ensure_trailing_newline(extracted).into();
+    fs.add_file(&op.dest, file)
+}
+
+fn fail(op: &ExtractOp, message: String) -> Error {
+    let part = match (&op.path, &op.section) {
+        (Some(path), _) => format!(" {}", path),
+        (None, Some(section)) => format!(" '{}'", section),
+        (None, None) => String::new(),
+    };
+    Error::Merge {
+        operation: format!("extract{} from {}", part, op.source),
+        message,
+    }
+}
+
+/// The segments of `op.path`, which YAML and JSON sources require.
+fn path_segments(op: &ExtractOp) -> Result<Vec<PathSegment>> {
+    match &op.path {
+        Some(path) => Ok(parse_path(path)),
+        None => Err(fail(
+            op,
+            format!("`path` is required to extract from {}", op.source),
+        )),
+    }
+}
+
+/// The keys of `segments`, for `keep-path`, innermost last.
+fn keys(op: &ExtractOp, segments: &[PathSegment]) -> Result<Vec<String>> {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => Ok(key.clone()),
+            PathSegment::Index(index) => Err(fail(
+                op,
+                format!("`keep-path` cannot nest under the index [{}]", index),
+            )),
+        })
+        .collect()
+}
+
+fn extract_yaml(document: &YamlValue, op: &ExtractOp) -> Result<YamlValue> {
+    let segments = path_segments(op)?;
+    let mut current = document;
+    for segment in &segments {
+        let next = match segment {
+            PathSegment::Key(key) => current
+                .as_mapping()
+                .and_then(|map| map.get(YamlValue::String(key.clone()))),
+            PathSegment::Index(index) => current.as_sequence().and_then(|seq| seq.get(*index)),
+        };
+        current = next.ok_or_else(|| fail(op, "nothing is at this path".to_string()))?;
+    }
+    let mut value = current.clone();
+    if op.keep_path {
+        for key in keys(op, &segments)?.into_iter().rev() {
+            let mut map = serde_yaml::Mapping::new();
+            map.insert(YamlValue::String(key), value);
+            value = YamlValue::Mapping(map);
+        }
+    }
+    Ok(value)
+}
+
+fn extract_json(document: &JsonValue, op: &ExtractOp) -> Result<JsonValue> {
+    let segments = path_segments(op)?;
+    let mut current = document;
+    for segment in &segments {
+        let next = match segment {
+            PathSegment::Key(key) => current.get(key),
+            PathSegment::Index(index) => current.get(*index),
+        };
+        current = next.ok_or_else(|| fail(op, "nothing is at this path".to_string()))?;
+    }
+    let mut value = current.clone();
+    if op.keep_path {
+        for key in keys(op, &segments)?.into_iter().rev() {
+            let mut map = serde_json::Map::new();
+            map.insert(key, value);
+            value = JsonValue::Object(map);
+        }
+    }
+    Ok(value)
+}
+
+fn extract_markdown(content: &str, op: &ExtractOp) -> Result<String> {
+    let Some(section) = &op.section else {
+        return Err(fail(
+            op,
+            format!("`section` is required to extract from {}", op.source),
+        ));
+    };
+    // `## Releasing` gives the level with the heading
+    let hashes = section
+        .trim_start()
+        .chars()
+        .take_while(|c| *c == '#')
+        .count();
+    let (level, section) = match hashes {
+        0 => (op.level, section.trim()),
+        _ => (hashes as u8, section.trim_start()[hashes..].trim()),
+    };
+    let lines = split_lines_preserve(content);
+    let (start, end) = find_section_bounds(&lines, level, section).ok_or_else(|| {
+        fail(
+            op,
+            format!("no level {} heading '{}' in the file", level, section),
+        )
+    })?;
+    let mut section_lines = &lines[start..end];
+    while let [rest @ .., last] = section_lines {
+        if !last.trim().is_empty() {
+            break;
+        }
+        section_lines = rest;
+    }
+    Ok(section_lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(source: &str, dest: &str) -> ExtractOp {
+        ExtractOp {
+            source: source.to_string(),
+            dest: dest.to_string(),
+            path: None,
+            section: None,
+            level: 2,
+            keep_path: false,
+        }
+    }
+
+    fn read(fs: &MemoryFS, path: &str) -> String {
+        read_file_as_string(fs, path).unwrap()
+    }
+
+    const WORKFLOW: &str = "on: push\njobs:\n  lint:\n    runs-on: ubuntu-latest\n  test:\n    runs-on: macos-latest\n";
+
+    #[test]
+    fn test_extract_yaml_value() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("ci.yml", WORKFLOW).unwrap();
+        let op = ExtractOp {
+            path: Some("jobs.lint".to_string()),
+            ..op("ci.yml", "lint.yml")
+        };
+        apply_extract_operation(&mut fs, &op).unwrap();
+        assert_eq!(read(&fs, "lint.yml"), "runs-on: ubuntu-latest\n");
+        // The source is left as it is
+        assert_eq!(read(&fs, "ci.yml"), WORKFLOW);
+    }
+
+    #[test]
+    fn test_extract_yaml_keep_path() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("ci.yml", WORKFLOW).unwrap();
+        let op = ExtractOp {
+            path: Some("jobs.lint".to_string()),
+            keep_path: true,
+            ..op("ci.yml", "lint.yml")
+        };
+        apply_extract_operation(&mut fs, &op).unwrap();
+        assert_eq!(
+            read(&fs, "lint.yml"),
+            "jobs:\n  lint:\n    runs-on: ubuntu-latest\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_json_array_item() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string(
+            "tasks.json",
+            r#"{"tasks": [{"label": "a"}, {"label": "b"}]}"#,
+        )
+        .unwrap();
+        let op = ExtractOp {
+            path: Some("tasks[1]".to_string()),
+            ..op("tasks.json", "b.json")
+        };
+        apply_extract_operation(&mut fs, &op).unwrap();
+        assert_eq!(read(&fs, "b.json"), "{\n  \"label\": \"b\"\n}\n");
+    }
+
+    #[test]
+    fn test_extract_markdown_section_with_subsections() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string(
+            "HANDBOOK.md",
+            "# Handbook\n\n## Releasing\n\nTag it.\n\n### Hotfixes\n\nBranch.\n\n## Support\n\nAsk.\n",
+        )
+        .unwrap();
+        let releasing = ExtractOp {
+            section: Some("Releasing".to_string()),
+            ..op("HANDBOOK.md", "RELEASING.md")
+        };
+        apply_extract_operation(&mut fs, &releasing).unwrap();
+        assert_eq!(
+            read(&fs, "RELEASING.md"),
+            "## Releasing\n\nTag it.\n\n### Hotfixes\n\nBranch.\n"
+        );
+
+        let hotfixes = ExtractOp {
+            section: Some("### Hotfixes".to_string()),
+            ..op("HANDBOOK.md", "HOTFIXES.md")
+        };
+        apply_extract_operation(&mut fs, &hotfixes).unwrap();
+        assert_eq!(read(&fs, "HOTFIXES.md"), "### Hotfixes\n\nBranch.\n");
+    }
+
+    #[test]
+    fn test_extract_keeps_template_marking() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("ci.yml", WORKFLOW).unwrap();
+        fs.get_file_mut("ci.yml").unwrap().is_template = true;
+        let op = ExtractOp {
+            path: Some("jobs".to_string()),
+            ..op("ci.yml", "jobs.yml")
+        };
+        apply_extract_operation(&mut fs, &op).unwrap();
+        assert!(fs.get_file("jobs.yml").unwrap().is_template);
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("ci.yml", WORKFLOW).unwrap();
+        fs.add_file_string("README.md", "# Readme\n").unwrap();
+        fs.add_file_string("Cargo.toml", "[package]\n").unwrap();
+
+        let missing = ExtractOp {
+            path: Some("jobs.deploy".to_string()),
+            ..op("ci.yml", "deploy.yml")
+        };
+        let err = apply_extract_operation(&mut fs, &missing).unwrap_err();
+        assert!(err.to_string().contains("extract jobs.deploy from ci.yml"));
+        assert!(err.to_string().contains("nothing is at this path"));
+
+        let no_path = op("ci.yml", "x.yml");
+        assert!(apply_extract_operation(&mut fs, &no_path)
+            .unwrap_err()
+            .to_string()
+            .contains("`path` is required"));
+
+        let no_section = ExtractOp {
+            section: Some("Usage".to_string()),
+            ..op("README.md", "USAGE.md")
+        };
+        assert!(apply_extract_operation(&mut fs, &no_section)
+            .unwrap_err()
+            .to_string()
+            .contains("no level 2 heading 'Usage'"));
+
+        let index = ExtractOp {
+            path: Some("jobs[0]".to_string()),
+            keep_path: true,
+            ..op("ci.yml", "x.yml")
+        };
+        assert!(apply_extract_operation(&mut fs, &index).is_err());
+
+        let toml = ExtractOp {
+            path: Some("package".to_string()),
+            ..op("Cargo.toml", "package.toml")
+        };
+        assert!(apply_extract_operation(&mut fs, &toml)
+            .unwrap_err()
+            .to_string()
+            .contains("only YAML, JSON and Markdown"));
+        assert!(!fs.exists("deploy.yml"));
+    }
+}
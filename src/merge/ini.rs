@@ -26,12 +26,11 @@
 
 use std::collections::HashSet;
 
-use log::warn;
-
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file};
 use crate::config::IniMergeOp;
 use crate::error::Result;
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use super::ensure_trailing_newline;
@@ -206,11 +205,7 @@ pub fn apply_ini_merge_operation(fs: &mut MemoryFS, op: &IniMergeOp) -> Result<(
 
     // MissingSourceAutoMerge: auto-merge where neither side has the file -> warn and skip
     if op.auto_merge.is_some() && !fs.exists(source_path) && !fs.exists(dest_path) {
-        warn!(
-            "Auto-merge skipped, file not found on either side: {}. \
-             Was the file possibly renamed or excluded by a preceding operation?",
-            source_path
-        );
+        super::warn(Message::AutoMergeSkipped { path: source_path });
         return Ok(());
     }
 
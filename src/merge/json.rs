@@ -22,13 +22,14 @@
 //! apply_json_merge_operation(&mut fs, &op)?;
 //! ```
 
-use log::warn;
 use serde_json::Value as JsonValue;
 
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file, PathSegment};
 use crate::config::{ArrayMergeMode, InsertPosition, JsonMergeOp};
-use crate::error::{Error, Result};
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use super::ensure_trailing_newline;
@@ -54,7 +55,7 @@ use crate::filesystem::File;
 ///
 /// # Errors
 ///
-/// Returns `Error::Merge` if the path cannot be navigated due to type mismatches.
+/// Returns `Error::MergeTypeMismatch` if the path runs through a value of the wrong type.
 pub fn navigate_json_value<'a>(
     value: &'a mut JsonValue,
     path: &[PathSegment],
@@ -64,7 +65,7 @@ pub fn navigate_json_value<'a>(
         match segment {
             PathSegment::Key(key) => {
                 if !current.is_object() && !current.is_null() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "json merge".to_string(),
                         message: format!("Expected object while navigating to '{}'", key),
                     });
@@ -81,7 +82,7 @@ pub fn navigate_json_value<'a>(
             }
             PathSegment::Index(idx) => {
                 if !current.is_array() && !current.is_null() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "json merge".to_string(),
                         message: format!("Expected array while navigating to index {}", idx),
                     });
@@ -147,6 +148,20 @@ impl<'a> JsonMergeContext<'a> {
         }
     }
 
+    /// Record a type mismatch at `path` and report it as a diagnostic.
+    fn type_mismatch(&mut self, path: &str, from: &str, to: &str) {
+        let msg = Message::MergeTypeMismatch {
+            source: self.src_file,
+            dest: self.dst_file,
+            path,
+            from,
+            to,
+        }
+        .to_string();
+        diagnostics::warn(ErrorPhase::Merge, msg.clone());
+        self.warnings.push(msg);
+    }
+
     fn display_path(&self) -> &str {
         if self.path.is_empty() {
             "<root>"
@@ -227,25 +242,13 @@ pub fn merge_json_values(
                                     }
                                 }
                             } else {
-                                let msg =
-                                    format!(
-                                    "{} -> {}: Type mismatch at path '{}': replacing {} with Array",
-                                    ctx.src_file, ctx.dst_file, new_path, json_type_name(existing)
-                                );
-                                warn!("{}", msg);
-                                ctx.warnings.push(msg);
+                                ctx.type_mismatch(&new_path, json_type_name(existing), "Array");
                                 *existing = JsonValue::Array(source_array.clone());
                             }
                         } else {
                             // Check for type mismatch before scalar overwrite
                             if existing.is_array() && !value.is_array() {
-                                let msg =
-                                    format!(
-                                    "{} -> {}: Type mismatch at path '{}': replacing Array with {}",
-                                    ctx.src_file, ctx.dst_file, new_path, json_type_name(value)
-                                );
-                                warn!("{}", msg);
-                                ctx.warnings.push(msg);
+                                ctx.type_mismatch(&new_path, "Array", json_type_name(value));
                             }
                             // Scalars: source always wins
                             *existing = value.clone();
@@ -255,15 +258,8 @@ pub fn merge_json_values(
                     }
                 }
             } else {
-                let msg = format!(
-                    "{} -> {}: Type mismatch at path '{}': replacing Object with {}",
-                    ctx.src_file,
-                    ctx.dst_file,
-                    ctx.display_path(),
-                    json_type_name(source)
-                );
-                warn!("{}", msg);
-                ctx.warnings.push(msg);
+                let path = ctx.display_path().to_string();
+                ctx.type_mismatch(&path, "Object", json_type_name(source));
                 *target = source.clone();
             }
         }
@@ -302,15 +298,8 @@ pub fn merge_json_values(
                     }
                 }
             } else {
-                let msg = format!(
-                    "{} -> {}: Type mismatch at path '{}': replacing Array with {}",
-                    ctx.src_file,
-                    ctx.dst_file,
-                    ctx.display_path(),
-                    json_type_name(source)
-                );
-                warn!("{}", msg);
-                ctx.warnings.push(msg);
+                let path = ctx.display_path().to_string();
+                ctx.type_mismatch(&path, "Array", json_type_name(source));
                 *target = source.clone();
             }
         }
@@ -337,8 +326,9 @@ pub fn merge_json_values(
 /// Returns `Error::Merge` if:
 /// - Source file cannot be read
 /// - Source JSON is invalid
-/// - Path navigation fails
 /// - Result cannot be serialized
+///
+/// Returns `Error::MergeTypeMismatch` if path navigation fails.
 pub fn apply_json_merge_operation(fs: &mut MemoryFS, op: &JsonMergeOp) -> Result<()> {
     op.validate()?;
     let source_path = op.get_source().expect("source validated");
@@ -346,11 +336,7 @@ pub fn apply_json_merge_operation(fs: &mut MemoryFS, op: &JsonMergeOp) -> Result
 
     // MissingSourceAutoMerge: auto-merge where neither side has the file -> warn and skip
     if op.auto_merge.is_some() && !fs.exists(source_path) && !fs.exists(dest_path) {
-        warn!(
-            "Auto-merge skipped, file not found on either side: {}. \
-             Was the file possibly renamed or excluded by a preceding operation?",
-            source_path
-        );
+        super::warn(Message::AutoMergeSkipped { path: source_path });
         return Ok(());
     }
 
@@ -1519,7 +1505,7 @@ mod tests {
             write_string_to_file(&mut fs, "test.txt", "content".to_string()).unwrap();
 
             let file = fs.get_file("test.txt").unwrap();
-            let content = String::from_utf8(file.content.clone()).unwrap();
+            let content = String::from_utf8(file.content.to_vec()).unwrap();
             assert!(content.ends_with('\n'));
         }
     }
@@ -24,12 +24,11 @@
 //! apply_markdown_merge_operation(&mut fs, &op)?;
 //! ```
 
-use log::warn;
-
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file};
 use crate::config::{InsertPosition, MarkdownMergeOp};
 use crate::error::{Error, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use super::ensure_trailing_newline;
@@ -48,7 +47,7 @@ use crate::filesystem::File;
 /// # Returns
 ///
 /// A vector of lines, with an empty string appended if content ends with newline
-fn split_lines_preserve(content: &str) -> Vec<String> {
+pub(super) fn split_lines_preserve(content: &str) -> Vec<String> {
     let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
     if content.ends_with('\n') {
         lines.push(String::new());
@@ -90,7 +89,11 @@ fn heading_for(level: u8, section: &str) -> String {
 ///
 /// `Some((start, end))` if found, where `start` is the heading line index
 /// and `end` is one past the last line of the section. `None` if not found.
-fn find_section_bounds(lines: &[String], level: u8, section: &str) -> Option<(usize, usize)> {
+pub(super) fn find_section_bounds(
+    lines: &[String],
+    level: u8,
+    section: &str,
+) -> Option<(usize, usize)> {
     let heading = heading_for(level, section);
     let mut start_index = None;
 
@@ -146,11 +149,7 @@ pub fn apply_markdown_merge_operation(fs: &mut MemoryFS, op: &MarkdownMergeOp) -
 
     // MissingSourceAutoMerge: auto-merge where neither side has the file -> warn and skip
     if op.auto_merge.is_some() && !fs.exists(source_path) && !fs.exists(dest_path) {
-        warn!(
-            "Auto-merge skipped, file not found on either side: {}. \
-             Was the file possibly renamed or excluded by a preceding operation?",
-            source_path
-        );
+        super::warn(Message::AutoMergeSkipped { path: source_path });
         return Ok(());
     }
 
@@ -12,16 +12,22 @@
 //! - INI (ini.rs) - Simple key-value sections
 //! - Markdown (markdown.rs) - Documentation with sections
 //!
+//! The `extract` operation (extract.rs) works the other way, copying one
+//! part of a YAML, JSON or Markdown file into a file of its own.
+//!
 //! ## Common Types
 //!
 //! The `PathSegment` enum and path parsing functions are shared across formats
 //! to navigate nested data structures during merge operations.
 
-use crate::error::{Error, Result};
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
 use crate::filesystem::{File, MemoryFS};
+use crate::messages::Message;
 
 // Merge format modules - internal implementations
 // These are called by the phases module during merge operations
+pub(crate) mod extract;
 pub(crate) mod ini;
 pub(crate) mod json;
 pub(crate) mod markdown;
@@ -30,6 +36,7 @@ pub(crate) mod xml;
 pub(crate) mod yaml;
 
 // Public re-exports of merge functions for integration testing and external use
+pub use extract::apply_extract_operation;
 pub use ini::apply_ini_merge_operation;
 pub use json::apply_json_merge_operation;
 pub use markdown::apply_markdown_merge_operation;
@@ -38,6 +45,11 @@ pub use toml::parse_toml_path;
 pub use xml::apply_xml_merge_operation;
 pub use yaml::apply_yaml_merge_operation;
 
+/// Report a non-fatal merge problem to the pipeline's diagnostics.
+pub(crate) fn warn(message: Message<'_>) {
+    diagnostics::warn(ErrorPhase::Merge, message.to_string());
+}
+
 /// Represents a segment in a path expression for navigating nested structures
 ///
 /// Path expressions like `servers[0].host` or `database.connection.timeout`
@@ -157,7 +169,7 @@ pub fn parse_path(path: &str) -> Vec<PathSegment> {
 
 pub(super) fn read_file_as_string(fs: &MemoryFS, path: &str) -> Result<String> {
     match fs.get_file(path) {
-        Some(file) => String::from_utf8(file.content.clone()).map_err(|_| Error::Merge {
+        Some(file) => String::from_utf8(file.content.to_vec()).map_err(|_| Error::Merge {
             operation: format!("read {}", path),
             message: "File content is not valid UTF-8".to_string(),
         }),
@@ -168,9 +180,14 @@ pub(super) fn read_file_as_string(fs: &MemoryFS, path: &str) -> Result<String> {
     }
 }
 
+/// The content of the file at `path`, or `None` when there is none.
+///
+/// A zero-length file is an empty document and reads as `None` too, so
+/// every format merges into it as into a missing file rather than failing
+/// to parse it.
 pub(super) fn read_file_as_string_optional(fs: &MemoryFS, path: &str) -> Result<Option<String>> {
-    if let Some(file) = fs.get_file(path) {
-        Ok(Some(String::from_utf8(file.content.clone()).map_err(
+    if let Some(file) = fs.get_file(path).filter(|file| !file.content.is_empty()) {
+        Ok(Some(String::from_utf8(file.content.to_vec()).map_err(
             |_| Error::Merge {
                 operation: format!("read {}", path),
                 message: "File content is not valid UTF-8".to_string(),
@@ -23,13 +23,13 @@
 //! apply_toml_merge_operation(&mut fs, &op)?;
 //! ```
 
-use log::warn;
 use toml::Value as TomlValue;
 
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file, PathSegment};
 use crate::config::TomlMergeOp;
 use crate::error::{Error, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use super::ensure_trailing_newline;
@@ -149,7 +149,7 @@ pub fn parse_toml_path(path: &str) -> Vec<PathSegment> {
 ///
 /// # Errors
 ///
-/// Returns `Error::Merge` if the path cannot be navigated due to type mismatches.
+/// Returns `Error::MergeTypeMismatch` if the path runs through a value of the wrong type.
 pub fn navigate_toml_value<'a>(
     value: &'a mut TomlValue,
     path: &[PathSegment],
@@ -159,7 +159,7 @@ pub fn navigate_toml_value<'a>(
         match segment {
             PathSegment::Key(key) => {
                 if !current.is_table() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "toml merge".to_string(),
                         message: format!("Expected table while navigating to '{}'", key),
                     });
@@ -172,7 +172,7 @@ pub fn navigate_toml_value<'a>(
             }
             PathSegment::Index(idx) => {
                 if !current.is_array() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "toml merge".to_string(),
                         message: format!("Expected array while navigating to index {}", idx),
                     });
@@ -247,10 +247,13 @@ pub fn merge_toml_values(
                                         }
                                     },
                                     ArrayMergeMode::Replace => {
-                                        warn!(
-                                            "{} -> {}: Replacing array at path '{}' (old size: {}, new size: {})",
-                                            src_file, dst_file, new_path, target_array.len(), source_array.len()
-                                        );
+                                        super::warn(Message::MergeReplaceArray {
+                                            source: src_file,
+                                            dest: dst_file,
+                                            path: &new_path,
+                                            old_len: target_array.len(),
+                                            new_len: source_array.len(),
+                                        });
                                         *existing = TomlValue::Array(source_array.clone());
                                     }
                                     ArrayMergeMode::AppendUnique => {
@@ -272,21 +275,23 @@ pub fn merge_toml_values(
                                     }
                                 }
                             } else {
-                                warn!(
-                                    "{} -> {}: Type mismatch at path '{}': replacing {:?} with Array",
-                                    src_file, dst_file, new_path, get_toml_type_name(existing)
-                                );
+                                super::warn(Message::MergeTypeMismatch {
+                                    source: src_file,
+                                    dest: dst_file,
+                                    path: &new_path,
+                                    from: get_toml_type_name(existing),
+                                    to: "Array",
+                                });
                                 *existing = TomlValue::Array(source_array.clone());
                             }
                         } else {
-                            eprintln!(
-                                "Warning: {} -> {}: Overwriting value at path '{}': {:?} -> {:?}",
-                                src_file,
-                                dst_file,
-                                new_path,
-                                get_toml_type_name(existing),
-                                get_toml_type_name(value)
-                            );
+                            super::warn(Message::MergeOverwriteValue {
+                                source: src_file,
+                                dest: dst_file,
+                                path: &new_path,
+                                from: get_toml_type_name(existing),
+                                to: get_toml_type_name(value),
+                            });
                             *existing = value.clone();
                         }
                     } else {
@@ -294,13 +299,13 @@ pub fn merge_toml_values(
                     }
                 }
             } else {
-                eprintln!(
-                    "Warning: {} -> {}: Type mismatch at path '{}': replacing Table with {:?}",
-                    src_file,
-                    dst_file,
+                super::warn(Message::MergeTypeMismatch {
+                    source: src_file,
+                    dest: dst_file,
                     path,
-                    get_toml_type_name(source)
-                );
+                    from: "Table",
+                    to: get_toml_type_name(source),
+                });
                 *target = source.clone();
             }
         }
@@ -318,10 +323,13 @@ pub fn merge_toml_values(
                         }
                     },
                     ArrayMergeMode::Replace => {
-                        eprintln!(
-                            "Warning: {} -> {}: Replacing array at path '{}' (old size: {}, new size: {})",
-                            src_file, dst_file, path, target_array.len(), source_array.len()
-                        );
+                        super::warn(Message::MergeReplaceArray {
+                            source: src_file,
+                            dest: dst_file,
+                            path,
+                            old_len: target_array.len(),
+                            new_len: source_array.len(),
+                        });
                         *target = TomlValue::Array(source_array.clone());
                     }
                     ArrayMergeMode::AppendUnique => {
@@ -343,25 +351,24 @@ pub fn merge_toml_values(
                     }
                 }
             } else {
-                eprintln!(
-                    "Warning: {} -> {}: Type mismatch at path '{}': replacing Array with {:?}",
-                    src_file,
-                    dst_file,
+                super::warn(Message::MergeTypeMismatch {
+                    source: src_file,
+                    dest: dst_file,
                     path,
-                    get_toml_type_name(source)
-                );
+                    from: "Array",
+                    to: get_toml_type_name(source),
+                });
                 *target = source.clone();
             }
         }
         _ => {
-            eprintln!(
-                "Warning: {} -> {}: Overwriting scalar at path '{}': {:?} -> {:?}",
-                src_file,
-                dst_file,
+            super::warn(Message::MergeOverwriteScalar {
+                source: src_file,
+                dest: dst_file,
                 path,
-                get_toml_type_name(target),
-                get_toml_type_name(source)
-            );
+                from: get_toml_type_name(target),
+                to: get_toml_type_name(source),
+            });
             *target = source.clone();
         }
     }
@@ -401,8 +408,9 @@ pub fn get_toml_type_name(value: &TomlValue) -> &'static str {
 /// Returns `Error::Merge` if:
 /// - Source file cannot be read
 /// - Source TOML is invalid
-/// - Path navigation fails
 /// - Result cannot be serialized
+///
+/// Returns `Error::MergeTypeMismatch` if path navigation fails.
 pub fn apply_toml_merge_operation(fs: &mut MemoryFS, op: &TomlMergeOp) -> Result<()> {
     op.validate()?;
     let source_path = op.get_source().expect("source validated");
@@ -410,11 +418,7 @@ pub fn apply_toml_merge_operation(fs: &mut MemoryFS, op: &TomlMergeOp) -> Result
 
     // MissingSourceAutoMerge: auto-merge where neither side has the file -> warn and skip
     if op.auto_merge.is_some() && !fs.exists(source_path) && !fs.exists(dest_path) {
-        warn!(
-            "Auto-merge skipped, file not found on either side: {}. \
-             Was the file possibly renamed or excluded by a preceding operation?",
-            source_path
-        );
+        super::warn(Message::AutoMergeSkipped { path: source_path });
         return Ok(());
     }
 
@@ -608,7 +612,7 @@ serde = "1.0"
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("Cargo.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
 
             // Should contain both original and merged content
             assert!(result_str.contains("serde = \"1.0\""));
@@ -648,7 +652,7 @@ name = "mydb"
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("merged.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
 
             // Should have server section with new fields
             assert!(result_str.contains("[server]"));
@@ -686,7 +690,7 @@ items = ["old1", "old2"]
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("dest.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
             let value: toml::Value = result_str.parse().unwrap();
             let items = value["package"]["items"].as_array().unwrap();
             assert_eq!(items.len(), 2);
@@ -720,7 +724,7 @@ items = ["old1", "old2"]
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("dest.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
             let value: toml::Value = result_str.parse().unwrap();
             let items = value["package"]["items"].as_array().unwrap();
             assert_eq!(items.len(), 4);
@@ -756,7 +760,7 @@ items = ["item1", "item4"]
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("dest.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
             let value: toml::Value = result_str.parse().unwrap();
             let items = value["package"]["items"].as_array().unwrap();
             assert_eq!(items.len(), 4);
@@ -792,7 +796,7 @@ items = ["old1"]
             apply_toml_merge_operation(&mut fs, &toml_op).unwrap();
 
             let result = fs.get_file("dest.toml").unwrap();
-            let result_str = String::from_utf8(result.content.clone()).unwrap();
+            let result_str = String::from_utf8(result.content.to_vec()).unwrap();
             let value: toml::Value = result_str.parse().unwrap();
             let items = value["package"]["items"].as_array().unwrap();
             assert_eq!(items.len(), 2);
@@ -1308,6 +1312,28 @@ items = [{name = "a"}, {name = "c"}]
             assert_eq!(target.as_str(), Some("replaced"));
         }
 
+        #[test]
+        fn test_overwrite_reports_diagnostic() {
+            let mut target = TomlValue::Table(toml::map::Map::new());
+            let source = TomlValue::Integer(1);
+
+            let ((), diagnostics) = crate::diagnostics::collect(|| {
+                merge_toml_values(
+                    &mut target,
+                    &source,
+                    ArrayMergeMode::Replace,
+                    InsertPosition::End,
+                    "test",
+                    "src",
+                    "dst",
+                );
+            });
+
+            assert_eq!(diagnostics.len(), 1);
+            let diagnostic = diagnostics.iter().next().unwrap();
+            assert_eq!(diagnostic.phase, crate::error::ErrorPhase::Merge);
+        }
+
         #[test]
         fn test_overwrite_table_with_array() {
             let mut target = TomlValue::Table(toml::map::Map::new());
@@ -1456,8 +1482,11 @@ items = 42
 
             let result = navigate_toml_value(&mut value, &path);
             assert!(result.is_err());
-            if let Err(Error::Merge { message, .. }) = result {
-                assert!(message.contains("array"));
+            match result {
+                Err(Error::MergeTypeMismatch { message, .. }) => {
+                    assert!(message.contains("array"))
+                }
+                other => panic!("expected MergeTypeMismatch, got {:?}", other.map(|_| ())),
             }
         }
 
@@ -1468,8 +1497,11 @@ items = 42
 
             let result = navigate_toml_value(&mut value, &path);
             assert!(result.is_err());
-            if let Err(Error::Merge { message, .. }) = result {
-                assert!(message.contains("table"));
+            match result {
+                Err(Error::MergeTypeMismatch { message, .. }) => {
+                    assert!(message.contains("table"))
+                }
+                other => panic!("expected MergeTypeMismatch, got {:?}", other.map(|_| ())),
             }
         }
 
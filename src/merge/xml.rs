@@ -3,13 +3,13 @@
 //! Merges XML documents with support for path navigation, recursive deep
 //! merging, namespace-aware element matching, and repeated element handling.
 
-use log::warn;
 use xot::Xot;
 
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file, PathSegment};
 use crate::config::{ArrayMergeMode, InsertPosition, XmlMergeOp};
 use crate::error::{Error, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use crate::filesystem::File;
@@ -123,10 +123,13 @@ pub fn merge_xml_elements(
     // Step 3: handle text content (last-write-wins from source)
     if let Some(src_text) = &source_text_content {
         if target_has_elements && !target_has_text {
-            warn!(
-                "{} -> {}: Type mismatch at path '{}': replacing child elements with text content",
-                ctx.src_file, ctx.dst_file, ctx.path
-            );
+            super::warn(Message::MergeTypeMismatch {
+                source: ctx.src_file,
+                dest: ctx.dst_file,
+                path: ctx.path,
+                from: "child elements",
+                to: "text content",
+            });
         }
         apply_text_content(xot, target, src_text)?;
     }
@@ -137,10 +140,13 @@ pub fn merge_xml_elements(
     }
 
     if source_has_elements && target_has_text && !target_has_elements {
-        warn!(
-            "{} -> {}: Type mismatch at path '{}': replacing text content with child elements",
-            ctx.src_file, ctx.dst_file, ctx.path
-        );
+        super::warn(Message::MergeTypeMismatch {
+            source: ctx.src_file,
+            dest: ctx.dst_file,
+            path: ctx.path,
+            from: "text content",
+            to: "child elements",
+        });
     }
 
     // Step 5: merge element children
@@ -451,6 +457,23 @@ mod tests {
         assert!(result.contains("<key>value</key>"));
     }
 
+    #[test]
+    fn empty_dest_is_treated_as_missing() {
+        let mut fs = MemoryFS::new();
+        fs.add_file(
+            "source.xml",
+            File::from_string("<config><key>value</key></config>"),
+        )
+        .unwrap();
+        fs.add_file("dest.xml", File::from_string("")).unwrap();
+
+        let op = XmlMergeOp::new().source("source.xml").dest("dest.xml");
+        apply_xml_merge_operation(&mut fs, &op).unwrap();
+
+        let result = read_file_as_string(&fs, "dest.xml").unwrap();
+        assert!(result.contains("<key>value</key>"), "{}", result);
+    }
+
     #[test]
     fn auto_created_dest_includes_xml_declaration() {
         let mut fs = MemoryFS::new();
@@ -8,6 +8,8 @@
 //! - Deep merging of YAML mappings with recursive descent
 //! - Array merge modes: append, replace, append-unique
 //! - Path-based targeting to merge at specific locations
+//! - Positioning of new keys and appended items: start, end, after a key,
+//!   or at an index
 //! - Type mismatch handling with warnings
 //!
 //! ## Example
@@ -23,13 +25,14 @@
 //! apply_yaml_merge_operation(&mut fs, &op)?;
 //! ```
 
-use log::{trace, warn};
-use serde_yaml::Value as YamlValue;
+use log::trace;
+use serde_yaml::{Mapping, Value as YamlValue};
 
 use super::{read_file_as_string, read_file_as_string_optional, write_string_to_file, PathSegment};
-use crate::config::{ArrayMergeMode, InsertPosition, YamlMergeOp};
+use crate::config::{ArrayMergeMode, YamlMergeOp, YamlPosition};
 use crate::error::{Error, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
 
 #[cfg(test)]
 use super::ensure_trailing_newline;
@@ -55,7 +58,7 @@ use crate::filesystem::File;
 ///
 /// # Errors
 ///
-/// Returns `Error::Merge` if the path cannot be navigated due to type mismatches.
+/// Returns `Error::MergeTypeMismatch` if the path runs through a value of the wrong type.
 pub fn navigate_yaml_value<'a>(
     value: &'a mut YamlValue,
     path: &[PathSegment],
@@ -65,7 +68,7 @@ pub fn navigate_yaml_value<'a>(
         match segment {
             PathSegment::Key(key) => {
                 if !current.is_mapping() && !current.is_null() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "yaml merge".to_string(),
                         message: format!("Expected mapping while navigating to '{}'", key),
                     });
@@ -82,7 +85,7 @@ pub fn navigate_yaml_value<'a>(
             }
             PathSegment::Index(idx) => {
                 if !current.is_sequence() && !current.is_null() {
-                    return Err(Error::Merge {
+                    return Err(Error::MergeTypeMismatch {
                         operation: "yaml merge".to_string(),
                         message: format!("Expected sequence while navigating to index {}", idx),
                     });
@@ -115,16 +118,15 @@ pub fn navigate_yaml_value<'a>(
 ///
 /// * `target` - The target value to merge into (modified in place)
 /// * `source` - The source value to merge from
-/// * `mode` - How to handle array merging
-/// * `position` - Where to insert array items (start or end)
+/// * `arrays` - How to merge sequences: array merge mode, insert position
+///   and the key items are matched on
 /// * `path` - Current path for logging purposes
 /// * `src_file` - Source file path for logging
 /// * `dst_file` - Destination file path for logging
 pub fn merge_yaml_values(
     target: &mut YamlValue,
     source: &YamlValue,
-    mode: ArrayMergeMode,
-    position: InsertPosition,
+    arrays: &ArrayMerge,
     path: &str,
     src_file: &str,
     dst_file: &str,
@@ -146,62 +148,31 @@ pub fn merge_yaml_values(
                     if let Some(existing) = target_map.get_mut(key) {
                         if existing.as_mapping().is_some() && value.as_mapping().is_some() {
                             merge_yaml_values(
-                                existing, value, mode, position, &new_path, src_file, dst_file,
+                                existing, value, arrays, &new_path, src_file, dst_file,
                             );
                         } else if let Some(source_seq) = value.as_sequence() {
                             if let Some(target_seq) = existing.as_sequence_mut() {
-                                match mode {
-                                    ArrayMergeMode::Append => match position {
-                                        InsertPosition::Start => {
-                                            let mut new_seq = source_seq.clone();
-                                            new_seq.append(target_seq);
-                                            *target_seq = new_seq;
-                                        }
-                                        InsertPosition::End => {
-                                            target_seq.extend(source_seq.iter().cloned());
-                                        }
-                                    },
-                                    ArrayMergeMode::Replace => {
-                                        warn!(
-                                            "{} -> {}: Replacing array at path '{}' (old size: {}, new size: {})",
-                                            src_file, dst_file, new_path, target_seq.len(), source_seq.len()
-                                        );
-                                        *existing = YamlValue::Sequence(source_seq.clone());
-                                    }
-                                    ArrayMergeMode::AppendUnique => {
-                                        let unique_items: Vec<_> = source_seq
-                                            .iter()
-                                            .filter(|item| !target_seq.contains(item))
-                                            .cloned()
-                                            .collect();
-                                        match position {
-                                            InsertPosition::Start => {
-                                                let mut new_seq = unique_items;
-                                                new_seq.append(target_seq);
-                                                *target_seq = new_seq;
-                                            }
-                                            InsertPosition::End => {
-                                                target_seq.extend(unique_items);
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                warn!(
-                                    "{} -> {}: Type mismatch at path '{}': replacing {:?} with Sequence",
-                                    src_file, dst_file, new_path, get_yaml_type_name(existing)
+                                merge_sequences(
+                                    target_seq, source_seq, arrays, &new_path, src_file, dst_file,
                                 );
+                            } else {
+                                super::warn(Message::MergeTypeMismatch {
+                                    source: src_file,
+                                    dest: dst_file,
+                                    path: &new_path,
+                                    from: get_yaml_type_name(existing),
+                                    to: "Sequence",
+                                });
                                 *existing = YamlValue::Sequence(source_seq.clone());
                             }
                         } else {
-                            warn!(
-                                "{} -> {}: Overwriting value at path '{}': {:?} -> {:?}",
-                                src_file,
-                                dst_file,
-                                new_path,
-                                get_yaml_type_name(existing),
-                                get_yaml_type_name(value)
-                            );
+                            super::warn(Message::MergeOverwriteValue {
+                                source: src_file,
+                                dest: dst_file,
+                                path: &new_path,
+                                from: get_yaml_type_name(existing),
+                                to: get_yaml_type_name(value),
+                            });
                             *existing = value.clone();
                         }
                     } else {
@@ -209,83 +180,209 @@ pub fn merge_yaml_values(
                     }
                 }
             } else {
-                warn!(
-                    "{} -> {}: Type mismatch at path '{}': replacing Mapping with {:?}",
-                    src_file,
-                    dst_file,
+                super::warn(Message::MergeTypeMismatch {
+                    source: src_file,
+                    dest: dst_file,
                     path,
-                    get_yaml_type_name(source)
-                );
+                    from: "Mapping",
+                    to: get_yaml_type_name(source),
+                });
                 *target = source.clone();
             }
         }
         YamlValue::Sequence(target_seq) => {
             if let YamlValue::Sequence(source_seq) = source {
-                match mode {
-                    ArrayMergeMode::Append => match position {
-                        InsertPosition::Start => {
-                            let mut new_seq = source_seq.clone();
-                            new_seq.append(target_seq);
-                            *target_seq = new_seq;
-                        }
-                        InsertPosition::End => {
-                            target_seq.extend(source_seq.clone());
-                        }
-                    },
-                    ArrayMergeMode::Replace => {
-                        warn!(
-                            "{} -> {}: Replacing array at path '{}' (old size: {}, new size: {})",
-                            src_file,
-                            dst_file,
-                            path,
-                            target_seq.len(),
-                            source_seq.len()
-                        );
-                        *target = YamlValue::Sequence(source_seq.clone());
-                    }
-                    ArrayMergeMode::AppendUnique => {
-                        let unique_items: Vec<_> = source_seq
-                            .iter()
-                            .filter(|item| !target_seq.contains(item))
-                            .cloned()
-                            .collect();
-                        match position {
-                            InsertPosition::Start => {
-                                let mut new_seq = unique_items;
-                                new_seq.append(target_seq);
-                                *target_seq = new_seq;
-                            }
-                            InsertPosition::End => {
-                                target_seq.extend(unique_items);
-                            }
-                        }
-                    }
-                }
+                merge_sequences(target_seq, source_seq, arrays, path, src_file, dst_file);
             } else {
-                warn!(
-                    "{} -> {}: Type mismatch at path '{}': replacing Sequence with {:?}",
-                    src_file,
-                    dst_file,
+                super::warn(Message::MergeTypeMismatch {
+                    source: src_file,
+                    dest: dst_file,
                     path,
-                    get_yaml_type_name(source)
-                );
+                    from: "Sequence",
+                    to: get_yaml_type_name(source),
+                });
                 *target = source.clone();
             }
         }
         _ => {
-            warn!(
-                "{} -> {}: Overwriting scalar at path '{}': {:?} -> {:?}",
-                src_file,
-                dst_file,
+            super::warn(Message::MergeOverwriteScalar {
+                source: src_file,
+                dest: dst_file,
                 path,
-                get_yaml_type_name(target),
-                get_yaml_type_name(source)
-            );
+                from: get_yaml_type_name(target),
+                to: get_yaml_type_name(source),
+            });
             *target = source.clone();
         }
     }
 }
 
+/// How sequences are merged.
+pub struct ArrayMerge<'a> {
+    pub mode: ArrayMergeMode,
+    pub position: YamlPosition,
+    /// Key that items of sequences of mappings are matched on, if any.
+    pub key: Option<&'a str>,
+}
+
+impl ArrayMerge<'_> {
+    /// The index in `target_seq` that appended items are inserted at.
+    fn insert_index(&self, target_seq: &[YamlValue]) -> usize {
+        match &self.position {
+            YamlPosition::Start => 0,
+            YamlPosition::End => target_seq.len(),
+            YamlPosition::Index(index) => (*index).min(target_seq.len()),
+            YamlPosition::After(anchor) => target_seq
+                .iter()
+                .position(|item| {
+                    let name = match self.key {
+                        Some(key) => item_key(item, key).unwrap_or(item),
+                        None => item,
+                    };
+                    name.as_str() == Some(anchor)
+                })
+                .map_or(target_seq.len(), |index| index + 1),
+        }
+    }
+}
+
+/// Merge the items of `source_seq` into `target_seq`.
+///
+/// With a key, and source items that are mappings carrying it, items are
+/// matched on that key: see [`merge_keyed_sequences`]. Otherwise the array
+/// merge mode applies.
+fn merge_sequences(
+    target_seq: &mut Vec<YamlValue>,
+    source_seq: &[YamlValue],
+    arrays: &ArrayMerge,
+    path: &str,
+    src_file: &str,
+    dst_file: &str,
+) {
+    if let Some(key) = arrays.key {
+        if source_seq.iter().any(|item| item_key(item, key).is_some()) {
+            merge_keyed_sequences(
+                target_seq, source_seq, key, arrays, path, src_file, dst_file,
+            );
+            return;
+        }
+    }
+    match arrays.mode {
+        ArrayMergeMode::Append => {
+            let index = arrays.insert_index(target_seq);
+            target_seq.splice(index..index, source_seq.iter().cloned());
+        }
+        ArrayMergeMode::Replace => {
+            super::warn(Message::MergeReplaceArray {
+                source: src_file,
+                dest: dst_file,
+                path,
+                old_len: target_seq.len(),
+                new_len: source_seq.len(),
+            });
+            *target_seq = source_seq.to_vec();
+        }
+        ArrayMergeMode::AppendUnique => {
+            let unique_items: Vec<_> = source_seq
+                .iter()
+                .filter(|item| !target_seq.contains(item))
+                .cloned()
+                .collect();
+            let index = arrays.insert_index(target_seq);
+            target_seq.splice(index..index, unique_items);
+        }
+    }
+}
+
+/// The value of `key` in `item`, if `item` is a mapping that has it.
+fn item_key<'v>(item: &'v YamlValue, key: &str) -> Option<&'v YamlValue> {
+    item.as_mapping()?.get(key)
+}
+
+/// Merge sequences of mappings item by item, as for GitHub issue forms
+/// whose `body` fields carry an `id`.
+///
+/// A source item whose key matches a target item is merged into it, where
+/// the target has it. A source item with no match is inserted after the
+/// target position of the source item before it, so new items keep their
+/// place relative to the source's order; a source item without the key that
+/// equals a target item is matched to it. Target items the source does not
+/// have are kept where they are.
+fn merge_keyed_sequences(
+    target_seq: &mut Vec<YamlValue>,
+    source_seq: &[YamlValue],
+    key: &str,
+    arrays: &ArrayMerge,
+    path: &str,
+    src_file: &str,
+    dst_file: &str,
+) {
+    // Target index the next unmatched source item goes after
+    let mut previous: Option<usize> = None;
+    for item in source_seq {
+        let matched = match item_key(item, key) {
+            Some(id) => target_seq
+                .iter()
+                .position(|existing| item_key(existing, key) == Some(id)),
+            None => target_seq.iter().position(|existing| existing == item),
+        };
+        match matched {
+            Some(index) => {
+                let item_path = format!("{}[{}]", path, index);
+                merge_yaml_values(
+                    &mut target_seq[index],
+                    item,
+                    arrays,
+                    &item_path,
+                    src_file,
+                    dst_file,
+                );
+                previous = Some(index);
+            }
+            None => {
+                let index = previous.map_or(0, |previous| previous + 1);
+                target_seq.insert(index, item.clone());
+                previous = Some(index);
+            }
+        }
+    }
+}
+
+/// The keys of `source` that `target` does not have, if both are mappings.
+fn new_keys(target: &YamlValue, source: &YamlValue) -> Vec<YamlValue> {
+    match (target.as_mapping(), source.as_mapping()) {
+        (Some(target_map), Some(source_map)) => source_map
+            .keys()
+            .filter(|key| !target_map.contains_key(*key))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Move `added`, keys just inserted at the end of `mapping`, to `position`
+/// among its other keys. With `after:<key>`, they go after that key, or at
+/// the end if the mapping does not have it.
+fn place_new_keys(mapping: &mut Mapping, added: &[YamlValue], position: &YamlPosition) {
+    if added.is_empty() || *position == YamlPosition::End {
+        return;
+    }
+    let (new, mut existing): (Vec<_>, Vec<_>) = std::mem::take(mapping)
+        .into_iter()
+        .partition(|(key, _)| added.contains(key));
+    let index = match position {
+        YamlPosition::Start => 0,
+        YamlPosition::End => existing.len(),
+        YamlPosition::Index(index) => (*index).min(existing.len()),
+        YamlPosition::After(anchor) => existing
+            .iter()
+            .position(|(key, _)| key.as_str() == Some(anchor))
+            .map_or(existing.len(), |index| index + 1),
+    };
+    let rest = existing.split_off(index);
+    *mapping = existing.into_iter().chain(new).chain(rest).collect();
+}
+
 /// Get a human-readable type name for a YAML value
 ///
 /// Used for logging and error messages to describe the type of a value.
@@ -320,8 +417,9 @@ pub fn get_yaml_type_name(value: &YamlValue) -> &'static str {
 /// Returns `Error::Merge` if:
 /// - Source file cannot be read
 /// - Source YAML is invalid
-/// - Path navigation fails
 /// - Result cannot be serialized
+///
+/// Returns `Error::MergeTypeMismatch` if path navigation fails.
 pub fn apply_yaml_merge_operation(fs: &mut MemoryFS, op: &YamlMergeOp) -> Result<()> {
     op.validate()?;
     let source_path = op.get_source().expect("source validated");
@@ -341,11 +439,7 @@ pub fn apply_yaml_merge_operation(fs: &mut MemoryFS, op: &YamlMergeOp) -> Result
 
     // MissingSourceAutoMerge: auto-merge where neither side has the file -> warn and skip
     if op.auto_merge.is_some() && !fs.exists(source_path) && !fs.exists(dest_path) {
-        warn!(
-            "Auto-merge skipped, file not found on either side: {}. \
-             Was the file possibly renamed or excluded by a preceding operation?",
-            source_path
-        );
+        super::warn(Message::AutoMergeSkipped { path: source_path });
         return Ok(());
     }
 
@@ -364,16 +458,23 @@ pub fn apply_yaml_merge_operation(fs: &mut MemoryFS, op: &YamlMergeOp) -> Result
     let path_str = op.path.as_deref().unwrap_or("");
     let path = super::parse_path(path_str);
     let target = navigate_yaml_value(&mut dest_value, &path)?;
-    let mode = op.array_mode;
+    let arrays = ArrayMerge {
+        mode: op.array_mode,
+        position: op.position.clone(),
+        key: op.array_key.as_deref(),
+    };
+    let added = new_keys(target, &source_value);
     merge_yaml_values(
         target,
         &source_value,
-        mode,
-        op.position,
+        &arrays,
         path_str,
         source_path,
         dest_path,
     );
+    if let Some(mapping) = target.as_mapping_mut() {
+        place_new_keys(mapping, &added, &op.position);
+    }
 
     let serialized = serde_yaml::to_string(&dest_value).map_err(|err| Error::Merge {
         operation: "yaml merge".to_string(),
@@ -582,6 +683,30 @@ mod tests {
             assert_eq!(parsed["key"], YamlValue::String("value".to_string()));
         }
 
+        #[test]
+        fn test_yaml_merge_into_empty_dest() {
+            let mut fs = MemoryFS::new();
+            fs.add_file("source.yaml", File::from_string("key: value"))
+                .unwrap();
+            fs.add_file("dest.yaml", File::from_string("")).unwrap();
+
+            let op = YamlMergeOp {
+                source: Some("source.yaml".to_string()),
+                dest: Some("dest.yaml".to_string()),
+                path: Some("settings".to_string()),
+                ..Default::default()
+            };
+
+            apply_yaml_merge_operation(&mut fs, &op).unwrap();
+
+            let result = read_file_as_string(&fs, "dest.yaml").unwrap();
+            let parsed: YamlValue = serde_yaml::from_str(&result).unwrap();
+            assert_eq!(
+                parsed["settings"]["key"],
+                YamlValue::String("value".to_string())
+            );
+        }
+
         #[test]
         fn test_yaml_merge_array_mode_replace() {
             let mut fs = MemoryFS::new();
@@ -1127,8 +1252,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "",
                 "src",
                 "dst",
@@ -1690,8 +1818,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "root",
                 "src.yaml",
                 "dst.yaml",
@@ -1710,8 +1841,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "root",
                 "src.yaml",
                 "dst.yaml",
@@ -1730,8 +1864,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "field",
                 "src.yaml",
                 "dst.yaml",
@@ -1748,8 +1885,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "field",
                 "src.yaml",
                 "dst.yaml",
@@ -1766,8 +1906,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "field",
                 "src.yaml",
                 "dst.yaml",
@@ -1785,8 +1928,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Replace,
-                InsertPosition::End,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Replace,
+                    position: YamlPosition::End,
+                    key: None,
+                },
                 "",
                 "src.yaml",
                 "dst.yaml",
@@ -1805,8 +1951,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::Append,
-                InsertPosition::Start,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::Append,
+                    position: YamlPosition::Start,
+                    key: None,
+                },
                 "",
                 "src.yaml",
                 "dst.yaml",
@@ -1829,8 +1978,11 @@ mod tests {
             merge_yaml_values(
                 &mut target,
                 &source,
-                ArrayMergeMode::AppendUnique,
-                InsertPosition::Start,
+                &ArrayMerge {
+                    mode: ArrayMergeMode::AppendUnique,
+                    position: YamlPosition::Start,
+                    key: None,
+                },
                 "",
                 "src.yaml",
                 "dst.yaml",
@@ -1846,4 +1998,237 @@ mod tests {
             assert_eq!(seq[4], YamlValue::String("c".to_string()));
         }
     }
+
+    mod keyed_array_tests {
+        use super::*;
+
+        const UPSTREAM_FORM: &str = r#"name: Bug report
+body:
+  - type: markdown
+    attributes:
+      value: Thanks for reporting!
+  - type: input
+    id: version
+    attributes:
+      label: Version (required)
+  - type: input
+    id: os
+    attributes:
+      label: Operating system
+  - type: textarea
+    id: logs
+    attributes:
+      label: Logs
+"#;
+
+        const CONSUMER_FORM: &str = r#"name: Bug report
+body:
+  - type: markdown
+    attributes:
+      value: Thanks for reporting!
+  - type: input
+    id: version
+    attributes:
+      label: Version
+      placeholder: "1.2.3"
+  - type: dropdown
+    id: team
+    attributes:
+      label: Team
+  - type: textarea
+    id: logs
+    attributes:
+      label: Logs
+"#;
+
+        fn merge(array_key: Option<&str>) -> YamlValue {
+            let mut fs = MemoryFS::new();
+            fs.add_file("upstream.yml", File::from_string(UPSTREAM_FORM))
+                .unwrap();
+            fs.add_file("bug.yml", File::from_string(CONSUMER_FORM))
+                .unwrap();
+            let mut op = YamlMergeOp::new().source("upstream.yml").dest("bug.yml");
+            if let Some(key) = array_key {
+                op = op.array_key(key);
+            }
+            apply_yaml_merge_operation(&mut fs, &op).unwrap();
+            serde_yaml::from_str(&read_file_as_string(&fs, "bug.yml").unwrap()).unwrap()
+        }
+
+        fn ids(form: &YamlValue) -> Vec<Option<&str>> {
+            form["body"]
+                .as_sequence()
+                .unwrap()
+                .iter()
+                .map(|item| item.get("id").and_then(YamlValue::as_str))
+                .collect()
+        }
+
+        #[test]
+        fn test_merges_items_by_key_and_keeps_consumer_items() {
+            let form = merge(Some("id"));
+            assert_eq!(
+                ids(&form),
+                vec![
+                    None,
+                    Some("version"),
+                    Some("os"),
+                    Some("team"),
+                    Some("logs")
+                ]
+            );
+            // Matched items are merged: upstream wins, consumer additions stay
+            let version = &form["body"][1]["attributes"];
+            assert_eq!(version["label"], "Version (required)");
+            assert_eq!(version["placeholder"], "1.2.3");
+        }
+
+        #[test]
+        fn test_without_key_uses_array_mode() {
+            // The default replace mode drops the consumer's fields
+            let form = merge(None);
+            assert_eq!(
+                ids(&form),
+                vec![None, Some("version"), Some("os"), Some("logs")]
+            );
+        }
+
+        #[test]
+        fn test_new_first_item_goes_first() {
+            let mut target: YamlValue = serde_yaml::from_str("- id: b\n- id: c").unwrap();
+            let source: YamlValue = serde_yaml::from_str("- id: a\n- id: b").unwrap();
+            let arrays = ArrayMerge {
+                mode: ArrayMergeMode::Replace,
+                position: YamlPosition::End,
+                key: Some("id"),
+            };
+            merge_yaml_values(&mut target, &source, &arrays, "", "src", "dst");
+            let expected: YamlValue = serde_yaml::from_str("- id: a\n- id: b\n- id: c").unwrap();
+            assert_eq!(target, expected);
+        }
+    }
+
+    mod position_tests {
+        use super::*;
+
+        fn merge(dest: &str, source: &str, op: YamlMergeOp) -> String {
+            let mut fs = MemoryFS::new();
+            fs.add_file("source.yml", File::from_string(source))
+                .unwrap();
+            fs.add_file("dest.yml", File::from_string(dest)).unwrap();
+            let op = op.source("source.yml").dest("dest.yml");
+            apply_yaml_merge_operation(&mut fs, &op).unwrap();
+            read_file_as_string(&fs, "dest.yml").unwrap()
+        }
+
+        #[test]
+        fn test_new_keys_go_at_position() {
+            let dest = "name: ci\non: push\njobs: {}\n";
+            let source = "permissions: read-all\non: pull_request\n";
+            let at =
+                |position: YamlPosition| merge(dest, source, YamlMergeOp::new().position(position));
+            assert_eq!(
+                at(YamlPosition::End),
+                "name: ci\non: pull_request\njobs: {}\npermissions: read-all\n"
+            );
+            assert_eq!(
+                at(YamlPosition::Start),
+                "permissions: read-all\nname: ci\non: pull_request\njobs: {}\n"
+            );
+            assert_eq!(
+                at(YamlPosition::After("on".to_string())),
+                "name: ci\non: pull_request\npermissions: read-all\njobs: {}\n"
+            );
+            assert_eq!(
+                at(YamlPosition::Index(1)),
+                "name: ci\npermissions: read-all\non: pull_request\njobs: {}\n"
+            );
+            // A missing anchor puts them at the end
+            assert_eq!(
+                at(YamlPosition::After("env".to_string())),
+                at(YamlPosition::End)
+            );
+        }
+
+        #[test]
+        fn test_new_keys_at_path() {
+            let dest = "jobs:\n  build:\n    runs-on: ubuntu-latest\n  test:\n    runs-on: ubuntu-latest\n";
+            let source = "lint:\n  runs-on: ubuntu-latest\n";
+            let merged = merge(
+                dest,
+                source,
+                YamlMergeOp::new()
+                    .path("jobs")
+                    .position(YamlPosition::After("build".to_string())),
+            );
+            let value: YamlValue = serde_yaml::from_str(&merged).unwrap();
+            let jobs: Vec<_> = value["jobs"]
+                .as_mapping()
+                .unwrap()
+                .keys()
+                .filter_map(YamlValue::as_str)
+                .collect();
+            assert_eq!(jobs, ["build", "lint", "test"]);
+        }
+
+        #[test]
+        fn test_appended_items_go_after_named_step() {
+            let dest = "steps:\n  - name: checkout\n  - name: test\n";
+            let source = "steps:\n  - name: cache\n";
+            let merged = merge(
+                dest,
+                source,
+                YamlMergeOp::new()
+                    .array_mode(ArrayMergeMode::Append)
+                    .array_key("name")
+                    .position(YamlPosition::After("checkout".to_string())),
+            );
+            // Source items with the key are merged by key instead
+            assert_eq!(
+                merged,
+                "steps:\n- name: cache\n- name: checkout\n- name: test\n"
+            );
+
+            let mut target: YamlValue = serde_yaml::from_str(dest).unwrap();
+            let source: YamlValue = serde_yaml::from_str("steps:\n  - run: make lint\n").unwrap();
+            let arrays = ArrayMerge {
+                mode: ArrayMergeMode::Append,
+                position: YamlPosition::After("checkout".to_string()),
+                key: Some("name"),
+            };
+            merge_yaml_values(&mut target, &source, &arrays, "", "src", "dst");
+            let expected: YamlValue = serde_yaml::from_str(
+                "steps:\n  - name: checkout\n  - run: make lint\n  - name: test\n",
+            )
+            .unwrap();
+            assert_eq!(target, expected);
+        }
+
+        #[test]
+        fn test_appended_items_go_at_index() {
+            let mut target: YamlValue = serde_yaml::from_str("[a, b, c]").unwrap();
+            let source: YamlValue = serde_yaml::from_str("[x, b]").unwrap();
+            let arrays = ArrayMerge {
+                mode: ArrayMergeMode::AppendUnique,
+                position: YamlPosition::Index(1),
+                key: None,
+            };
+            merge_yaml_values(&mut target, &source, &arrays, "", "src", "dst");
+            assert_eq!(
+                target,
+                serde_yaml::from_str::<YamlValue>("[a, x, b, c]").unwrap()
+            );
+
+            let arrays = ArrayMerge {
+                mode: ArrayMergeMode::Append,
+                position: YamlPosition::After("c".to_string()),
+                key: None,
+            };
+            merge_yaml_values(&mut target, &source, &arrays, "", "src", "dst");
+            assert_eq!(
+                target,
+                serde_yaml::from_str::<YamlValue>("[a, x, b, c, x, b]").unwrap()
+            );
+        }
+    }
 }
@@ -0,0 +1,1577 @@
+//! # Message Catalog
+//!
+//! Every status and warning message the tool prints is defined here, as a
+//! variant of [`Message`]. Commands and library code build a `Message` and
+//! hand it to one of the [`crate::output`] channel functions; none of them
+//! carry their own text. Keeping the wording in one place keeps it
+//! consistent between commands and gives a single table to translate.
+//!
+//! Command results such as `ls` listings, `tree` output or `info` reports
+//! are data rather than messages and are printed by the commands directly.
+//!
+//! ## Icons
+//!
+//! Messages may start with an [`Icon`], which renders as an emoji when colors
+//! are enabled and as a plain-text tag such as `[OK]` otherwise. Rendering
+//! uses the process-wide configuration from [`crate::output::global`] unless
+//! [`Message::render`] is given one.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use common_repo::messages::Message;
+//! use common_repo::output;
+//!
+//! output::status(Message::CheckingUpdates);
+//! assert_eq!(
+//!     Message::CheckingUpdates.to_string(),
+//!     "Checking for repository updates..."
+//! );
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use crate::output::{self, emoji, OutputConfig};
+
+/// A marker at the start of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    /// Success: `✅` or `[OK]`.
+    Ok,
+    /// Failure: `❌` or `[ERR]`.
+    Error,
+    /// Warning: `⚠️` or `[WARN]`.
+    Warning,
+    /// Summary header: `📊` or `[INFO]`.
+    Info,
+    /// Inspection step: `🔍` or `[SCAN]`.
+    Scan,
+    /// Dependency check: `🔄` or `[CHECK]`.
+    Check,
+    /// Network access: `🌐` or `[NET]`.
+    Network,
+    /// Final result: `🎯` or `[RESULT]`.
+    Result,
+    /// Hint: `💡` or `[TIP]`.
+    Tip,
+    /// Repository or package listing: `📦` or `[PKG]`.
+    Package,
+    /// Note or edited file: `📝` or `[NOTE]`.
+    Note,
+    /// Removal: `🗑️` or `[DEL]`.
+    Delete,
+    /// Relocation: `🚚` or `[MOVE]`.
+    Move,
+    /// Sandboxed or trial run: `🧪` or `[TEST]`.
+    Sandbox,
+    /// Lock file or pinned state: `🔒` or `[LOCK]`.
+    Lock,
+    /// Slow step: `🐢` or `[SLOW]`.
+    Slow,
+}
+
+impl Icon {
+    /// Render the icon for `config`.
+    pub fn render(self, config: &OutputConfig) -> &'static str {
+        let (emoji_str, plain) = match self {
+            Icon::Ok => ("✅", "[OK]"),
+            Icon::Error => ("❌", "[ERR]"),
+            Icon::Warning => ("⚠️", "[WARN]"),
+            Icon::Info => ("📊", "[INFO]"),
+            Icon::Scan => ("🔍", "[SCAN]"),
+            Icon::Check => ("🔄", "[CHECK]"),
+            Icon::Network => ("🌐", "[NET]"),
+            Icon::Result => ("🎯", "[RESULT]"),
+            Icon::Tip => ("💡", "[TIP]"),
+            Icon::Package => ("📦", "[PKG]"),
+            Icon::Note => ("📝", "[NOTE]"),
+            Icon::Delete => ("🗑️", "[DEL]"),
+            Icon::Move => ("🚚", "[MOVE]"),
+            Icon::Sandbox => ("🧪", "[TEST]"),
+            Icon::Lock => ("🔒", "[LOCK]"),
+            Icon::Slow => ("🐢", "[SLOW]"),
+        };
+        emoji(config, emoji_str, plain)
+    }
+}
+
+/// A user-facing status or warning message.
+///
+/// Variants are grouped by the command that prints them. Messages shared by
+/// several commands are listed under the first.
+#[derive(Debug, Clone)]
+pub enum Message<'a> {
+    // Shared
+    /// `Loading configuration from: <path>`
+    LoadingConfig { path: &'a Path },
+    /// `Checking for repository updates...`
+    CheckingUpdates,
+    /// No repository in the configuration can be checked for updates.
+    NoCheckableRepos,
+    /// Hint to run `apply` after creating a configuration.
+    ApplyTip,
+    /// Start of a tag lookup, finished by one of the `Tags*` messages.
+    FetchingTags { url: &'a str, indent: bool },
+    /// Tag lookup found `tag`.
+    TagsFound { tag: &'a str },
+    /// Tag lookup found tags, none of them semver.
+    TagsNoSemver,
+    /// Tag lookup found no tags.
+    TagsNone,
+    /// Tag lookup failed.
+    TagsFailed,
+    /// The latest tag is a 0.x.x version.
+    UnstableVersion { tag: &'a str },
+    /// No semver tag was found, so `main` is used.
+    NoSemverTagsUsingMain,
+    /// No tag was found, so `main` is used.
+    NoTagsUsingMain,
+    /// Listing tags failed, so `main` is used.
+    FetchTagsFailedUsingMain { error: &'a str },
+    /// Follow-up to the `UsingMain` warnings.
+    PinCommitHint,
+    /// A version warning, indented under the tag lookup.
+    VersionWarning { warning: &'a str },
+
+    // add
+    /// The repository was appended to an existing configuration.
+    RepoAdded { url: &'a str, version: &'a str },
+    /// A configuration was created holding the repository.
+    ConfigCreatedWith { url: &'a str, version: &'a str },
+    /// No configuration exists and one will be offered.
+    NoConfigFound,
+    /// The user declined to create a configuration.
+    AddAborted,
+
+    // init
+    /// `init` is starting.
+    Initializing,
+    /// `init` wrote the configuration.
+    ConfigCreated,
+    /// `init` wrote the pre-commit configuration.
+    PreCommitConfigCreated,
+
+    // export-template
+    /// `export-template` wrote the composite.
+    TemplateExported { path: &'a Path, count: usize },
+    /// `export-template` wrote the flattened configuration.
+    FlatConfigWritten { path: &'a Path },
+
+    // import
+    /// `import` wrote the configuration.
+    ConfigImported {
+        path: &'a Path,
+        tool: &'a str,
+        url: &'a str,
+        vars: usize,
+    },
+    /// Something the imported state could not carry over.
+    ImportNote { note: &'a str },
+
+    // check
+    /// Every checked repository is up to date.
+    AllUpToDate,
+    /// Header of the `check --updates` summary.
+    UpdateSummaryHeader,
+    /// `<checked> repositories checked, <outdated> have available updates`
+    UpdateSummaryCounts { checked: usize, outdated: usize },
+    /// A repository with an update available.
+    RepoWithUpdate { url: &'a str, current: &'a str },
+    /// The latest version of the repository above.
+    LatestVersion { version: &'a str },
+    /// The update above crosses a major version (`check`).
+    BreakingChangesAvailable,
+    /// The update above is semver compatible (`check`).
+    CompatibleUpdatesAvailable,
+    /// Closing advice when any update is breaking.
+    BreakingChangesAdvice,
+    /// The configuration parsed.
+    ConfigLoaded,
+    /// `   Operations: <count>`
+    OperationCount { count: usize },
+    /// `   Repositories: <count>`
+    RepositoryCount { count: usize },
+    /// `   Other operations: <count>`
+    OtherOperationCount { count: usize },
+    /// Hint to pass `--updates`.
+    UpdatesTip,
+    /// Header of the `check --vars` report.
+    TemplateVarsHeader,
+    /// `   <name>: <sources>`, a variable and what defines it.
+    TemplateVarDefined { name: &'a str, sources: &'a str },
+    /// Header of the variables templates use but nothing defines.
+    UndefinedVarsHeader,
+    /// `   <name> (used in <paths>)`
+    TemplateVarUsedIn { name: &'a str, paths: &'a str },
+    /// Header of the variables defined but used by no template.
+    UnusedVarsHeader,
+    /// Every template variable is both defined and used.
+    TemplateVarsOk,
+    /// `check --templates` found nothing wrong.
+    TemplateDeclarationsOk { files: usize },
+    /// `check --locked` found no locked file modified.
+    LockedFilesOk,
+    /// `check --assertions` found every assertion met.
+    AssertionsOk,
+
+    // update
+    /// `--filter` patterns in effect.
+    FilteringUpstreams { patterns: &'a str },
+    /// Every repository was excluded by `--filter`.
+    NoMatchingRepos { filtered_out: usize },
+    /// Every repository is at its latest version.
+    AllAtLatest,
+    /// Only breaking updates exist and `--latest` was not given.
+    NoCompatibleUpdates,
+    /// Header of the update list.
+    AvailableUpdatesHeader,
+    /// `<count> repositories can be updated`
+    UpdatableCount { count: usize, filtered_out: usize },
+    /// The update above crosses a major version (`update`).
+    BreakingChange,
+    /// The update above is semver compatible (`update`).
+    CompatibleUpdate,
+    /// Header of the release notes the update above pulls in.
+    ChangelogHeader { source: &'a str },
+    /// `     <version>`, one release in the notes above.
+    ChangelogRelease { version: &'a str },
+    /// One line of the release's notes.
+    ChangelogNote { line: &'a str },
+    /// The release notes of an update could not be read.
+    ChangelogUnavailable { error: &'a str },
+    /// `--changelog` was written.
+    ChangelogWritten { path: &'a Path },
+    /// `--dry-run` is in effect.
+    UpdateDryRun,
+    /// The user declined the update.
+    UpdateCancelled,
+    /// Refs are about to be rewritten.
+    UpdatingRepos,
+    /// One repository's ref was rewritten.
+    RepoUpdated {
+        url: &'a str,
+        occurrences: usize,
+        from: &'a str,
+        to: &'a str,
+    },
+    /// The configuration was written back.
+    ReposUpdated { count: usize, path: &'a Path },
+    /// Hint to run `apply` after updating.
+    ApplyUpdatedTip,
+    /// Nothing was rewritten.
+    NoReposUpdated,
+
+    // validate
+    /// `validate` is starting.
+    Validating { path: &'a Path },
+    /// The configuration file parsed.
+    ConfigParsed,
+    /// The configuration file did not parse.
+    ConfigParseFailed { error: &'a str },
+    /// Header of the configuration summary.
+    ConfigSummaryHeader,
+    /// `   Total operations: <count>`
+    TotalOperationCount { count: usize },
+    /// `   Repository operations: <count>`
+    RepoOperationCount { count: usize },
+    /// Cycle detection is starting.
+    CheckingCycles,
+    /// No cycle was found.
+    NoCycles,
+    /// Size of the discovered repository tree.
+    DiscoveredRepos { count: usize },
+    /// A cycle was found.
+    CycleDetected { error: &'a str },
+    /// Discovery failed for a reason other than a cycle.
+    DiscoveryWarning { error: &'a str },
+    /// Header of the merge provenance findings.
+    ProvenanceHeader,
+    /// A merge operation reads a file no upstream declares.
+    UndeclaredMergeSource { operation: usize, source: &'a str },
+    /// Pattern validation is starting.
+    ValidatingPatterns,
+    /// A rename regex does not compile.
+    InvalidRegex {
+        kind: &'a str,
+        operation: usize,
+        error: &'a str,
+    },
+    /// An include or exclude glob does not compile.
+    InvalidGlob {
+        kind: &'a str,
+        operation: usize,
+        error: &'a str,
+    },
+    /// A `tools` operation lists no tools.
+    EmptyTools { operation: usize },
+    /// A `patch` operation names a diff file that does not exist.
+    MissingPatch { operation: usize, path: &'a str },
+    /// A `patch` operation's patch file is outside the repository.
+    PatchOutsideRepo { operation: usize, path: &'a str },
+    /// An operation key this version does not recognize.
+    UnrecognizedOperation {
+        operation: usize,
+        name: &'a str,
+        suggestion: Option<&'a str>,
+    },
+    /// A merge operation is invalid.
+    InvalidMerge {
+        kind: &'a str,
+        operation: usize,
+        error: &'a str,
+    },
+    /// No pattern errors were found.
+    PatternsValid,
+    /// Repository accessibility checks are starting.
+    CheckingAccess,
+    /// Start of a repository check, finished by one of the `Repo*` messages.
+    CheckingRepo { target: &'a str },
+    /// The repository is local and not checked.
+    RepoLocal,
+    /// The repository is reachable but has no tags.
+    RepoNoTags,
+    /// The repository is reachable.
+    RepoAccessible { tags: usize },
+    /// The repository could not be reached.
+    RepoInaccessible { error: &'a str },
+    /// Header of the validation result.
+    ResultHeader,
+    /// Validation found errors.
+    HasErrors,
+    /// Validation found warnings under `--strict`.
+    HasWarningsStrict,
+    /// Validation found warnings only.
+    ValidWithWarnings,
+    /// Validation found nothing.
+    Valid,
+    /// Hint to pass `--check-repos`.
+    CheckReposTip,
+
+    // cache
+    /// The cache directory is missing.
+    CacheMissing { path: &'a Path },
+    /// Follow-up to `CacheMissing` for `cache list`.
+    NoCachedRepos,
+    /// Follow-up to `CacheMissing` for `cache clean`.
+    NothingToClean,
+    /// The cache directory holds no entries.
+    CacheEmpty { path: &'a Path },
+    /// No entry matches the `cache clean` filters.
+    NoMatchingCacheEntries,
+    /// Header of the entries `cache clean` will delete.
+    CacheCleanHeader,
+    /// One entry `cache clean` will delete.
+    CacheCleanEntry {
+        hash: &'a str,
+        ref_name: &'a str,
+        path: &'a str,
+        size: &'a str,
+    },
+    /// Totals of the entries `cache clean` will delete.
+    CacheCleanTotal { count: usize, size: &'a str },
+    /// `--dry-run` is in effect.
+    CacheDryRun,
+    /// The user declined the deletion.
+    CleanCancelled,
+    /// Deletion is starting.
+    DeletingCacheEntries,
+    /// One entry was deleted.
+    CacheEntryDeleted { path: &'a Path },
+    /// One entry could not be deleted.
+    CacheEntryDeleteFailed { path: &'a Path, error: &'a str },
+    /// Deletion finished.
+    CacheEntriesDeleted { count: usize },
+    /// Deletion finished with failures.
+    CacheEntriesDeleteFailed { count: usize },
+    /// `cache warm` fetched the repositories of a configuration.
+    CacheWarmed { count: usize, config: &'a Path },
+    /// `cache export` wrote an archive.
+    CacheExported { count: usize, path: &'a Path },
+    /// `cache import` left an entry that is already cached alone.
+    CacheEntryAlreadyCached { url: &'a str, ref_: &'a str },
+    /// `cache import` finished.
+    CacheImported { count: usize, skipped: usize },
+
+    // migrate-config
+    /// The config uses no old shapes.
+    MigrationUpToDate { path: &'a Path },
+    /// `<count> change(s)` are needed, followed by one line per change.
+    MigrationNeeded { path: &'a Path, count: usize },
+    /// One rewrite, with its line number in the original file.
+    MigrationChange { line: usize, description: &'a str },
+    /// The rewritten config was saved.
+    ConfigMigrated { path: &'a Path, count: usize },
+    /// Shown after `--check` finds old shapes.
+    MigrateTip,
+
+    // promote
+    /// The upstream's source already has the local change.
+    AlreadyUpstream { source: &'a Path, repo: &'a str },
+    /// The patch for the upstream was written to `path`.
+    PromotePatchWritten { source: &'a Path, path: &'a Path },
+    /// The change was committed on `branch` in a clone of the upstream.
+    PromoteCommitted {
+        source: &'a Path,
+        branch: &'a str,
+        dir: &'a Path,
+    },
+
+    // history
+    /// No apply has been recorded in the history at `path`.
+    NoHistory { path: &'a Path },
+
+    // notify
+    /// A webhook at `host` could not be sent the apply report.
+    WebhookFailed { host: &'a str, error: &'a str },
+
+    // shadow
+    /// No upstream has a newer release to compose in a shadow directory.
+    NoNewerRefs,
+    /// The upstream at `url` was composed at `to` rather than `from`.
+    ShadowRef {
+        url: &'a str,
+        from: &'a str,
+        to: &'a str,
+    },
+    /// A file the shadow output `change`s ("added", "changed", "removed").
+    ShadowChange { path: &'a Path, change: &'a str },
+    /// The shadow output was written to `path`, with its report.
+    ShadowWritten {
+        path: &'a Path,
+        counts: &'a str,
+        report: &'a Path,
+    },
+
+    // Pipeline
+    /// A warning reported by the pipeline, as collected in
+    /// [`crate::diagnostics::Diagnostics`].
+    PipelineWarning { message: &'a str },
+    /// `<count> warning(s)` reported by the pipeline.
+    PipelineWarningCount { count: usize },
+    /// Warnings made the command fail under `--deny-warnings`.
+    WarningsDenied { count: usize },
+    /// A fetch failed and the cached clone is used instead.
+    CachedFallback { url: &'a str, ref_: &'a str },
+    /// An upstream declared itself, or one of its operations, deprecated.
+    UpstreamDeprecated {
+        repo: &'a str,
+        operation: Option<&'a str>,
+        message: &'a str,
+        replacement_url: Option<&'a str>,
+    },
+    /// An upstream marked `on-fetch-error: skip` could not be fetched and
+    /// is left out of the run.
+    SkippedUpstream {
+        url: &'a str,
+        ref_: &'a str,
+        error: &'a str,
+    },
+    /// A `ref-index:` could not be fetched and its cached copy is used.
+    StaleRefIndex { index: &'a str, error: &'a str },
+    /// A `repo:` ref was an alias in its `ref-index:`.
+    ResolvedRefAlias {
+        url: &'a str,
+        alias: &'a str,
+        ref_: &'a str,
+        index: &'a str,
+    },
+    /// A `file:` host is rate limiting and the cached download is used.
+    RateLimitedCachedFile {
+        url: &'a str,
+        host: &'a str,
+        seconds: u64,
+    },
+    /// The composite alone is larger than `--max-memory`.
+    MemoryBudgetExceeded { size: u64, max_memory: u64 },
+    /// A pipeline block was resumed from a checkpoint of a failed run.
+    ResumedFromCheckpoint { what: &'a str },
+    /// Files from the overrides directory replaced the output.
+    OverridesApplied { count: usize },
+    /// Another run is writing a repository cache entry.
+    WaitingForCacheLock { path: &'a Path },
+    /// A cache entry lock left by a run that died was removed.
+    StaleCacheLock { path: &'a Path },
+    /// The old copy of a file the upstream renamed was removed.
+    RenamedUpstream { from: &'a Path, to: &'a Path },
+    /// Shown after a failed apply that left checkpoints behind.
+    ResumeTip,
+    /// Nothing changed since the last successful apply.
+    UpToDate,
+    /// `diff --save-plan` saved the output to this file.
+    PlanSaved { path: &'a Path },
+    /// `apply --from-plan` writes the output saved in this file.
+    ApplyingPlan { path: &'a Path },
+    /// `apply` is starting.
+    ApplyHeader,
+    /// `apply --dry-run` writes nothing.
+    ApplyDryRun,
+    /// `apply` finished after `seconds`.
+    ApplySucceeded { seconds: f64 },
+    /// `apply` failed; the error follows.
+    ApplyFailed,
+    /// Header above the operations slower than the slow-operation threshold.
+    SlowOperations,
+    /// `apply --dry-run` would overwrite `count` untracked files, listed in
+    /// `files`.
+    WouldOverwriteUntracked { count: usize, files: &'a str },
+    /// `apply --sandbox` writes to this temporary directory.
+    ApplyingToSandbox { path: &'a Path },
+    /// `apply --run` runs this command in the sandbox.
+    SandboxRunning { command: &'a str },
+    /// The command run in the sandbox succeeded.
+    SandboxPassed { command: &'a str },
+    /// The command run in the sandbox failed with `status`.
+    SandboxFailed { command: &'a str, status: &'a str },
+    /// Upstreams require `count` template variables the configuration
+    /// does not set, which apply asks for.
+    RequiredVarsUnset { count: usize },
+    /// Offer to save the variables given to the configuration.
+    SaveRequiredVars { path: &'a Path },
+    /// The variables given were saved to the configuration.
+    RequiredVarsSaved { path: &'a Path },
+    /// The pre-commit hook guarding managed files was written.
+    ProtectHookInstalled { path: &'a Path },
+    /// A pre-commit hook common-repo did not write is in the way.
+    ProtectHookForeign { path: &'a Path },
+    /// A rename mapping of the configuration moves `count` files, or
+    /// matches any path.
+    SweepingRename {
+        from: &'a str,
+        to: &'a str,
+        count: usize,
+        description: Option<&'a str>,
+    },
+    /// One file a sweeping rename moves.
+    SweepingRenameMove { from: &'a Path, to: &'a Path },
+    /// `count` more moves of a sweeping rename are not shown.
+    SweepingRenameMore { count: usize },
+    /// Asks whether to apply the sweeping renames.
+    ConfirmSweepingRenames,
+    /// The user declined the sweeping renames.
+    ApplyCancelled,
+    /// Sweeping renames need `--yes` when there is no terminal to ask on.
+    SweepingRenamesRefused { count: usize },
+    /// More than one cloned repository matches a `repo:` operation.
+    AmbiguousRepo {
+        url: &'a str,
+        ref_: &'a str,
+        candidates: usize,
+    },
+    /// An operation key this version does not recognize was skipped.
+    UnknownOperation {
+        name: &'a str,
+        suggestion: Option<&'a str>,
+    },
+    /// A `disable:` id matches no operation in the upstream config.
+    UnmatchedDisable { id: &'a str, repo: &'a str },
+    /// A directory has two configuration files that differ; only `used`
+    /// is read.
+    DuplicateConfigFiles {
+        location: &'a str,
+        used: &'a str,
+        ignored: &'a str,
+    },
+    /// A `repo:` operation has no cloned repository.
+    RepoNotCloned { url: &'a str, ref_: &'a str },
+    /// A child in the repository tree has no cloned repository.
+    TreeChildNotCloned { key: &'a str },
+    /// An upstream with files and `include` operations contributed none of
+    /// them.
+    EmptyUpstream {
+        repo: &'a str,
+        include: &'a str,
+        exclude: &'a str,
+    },
+    /// An upstream declared a secret template variable, which only the
+    /// consumer's configuration may do.
+    UpstreamSecretIgnored { name: &'a str },
+    /// A local path could not be read.
+    InaccessiblePath { error: &'a str },
+    /// A read-only file that would change was left as it is.
+    ReadOnlySkipped { path: &'a str },
+    /// Auto-merge found the file on neither side.
+    AutoMergeSkipped { path: &'a str },
+    /// A file the configuration produced is outside `allow-paths:` or in
+    /// `deny-paths:`, and was not written.
+    OutsidePathScope { path: &'a str },
+    /// A value is overwritten by one of another type.
+    MergeOverwriteValue {
+        source: &'a str,
+        dest: &'a str,
+        path: &'a str,
+        from: &'a str,
+        to: &'a str,
+    },
+    /// A container is replaced by a value of another type.
+    MergeTypeMismatch {
+        source: &'a str,
+        dest: &'a str,
+        path: &'a str,
+        from: &'a str,
+        to: &'a str,
+    },
+    /// An array is replaced in `replace` mode.
+    MergeReplaceArray {
+        source: &'a str,
+        dest: &'a str,
+        path: &'a str,
+        old_len: usize,
+        new_len: usize,
+    },
+    /// A scalar is overwritten.
+    MergeOverwriteScalar {
+        source: &'a str,
+        dest: &'a str,
+        path: &'a str,
+        from: &'a str,
+        to: &'a str,
+    },
+
+    // GitHub Actions annotations
+    /// Title of an annotation for a pipeline warning.
+    WarningAnnotationTitle { phase: &'a str },
+    /// Title of an annotation for a repository with a newer version.
+    UpdateAnnotationTitle { breaking: bool },
+    /// A repository can be updated to a newer version.
+    UpdateAnnotation {
+        url: &'a str,
+        current: &'a str,
+        latest: &'a str,
+    },
+    /// Title of an annotation for a file that differs from the
+    /// configuration.
+    DriftAnnotationTitle,
+    /// A file that `apply` would add or modify.
+    DriftAnnotation { change: &'a str },
+    /// Title of an annotation for a failed command.
+    ErrorAnnotationTitle { code: Option<&'a str> },
+    /// A file `apply` refuses to overwrite because git does not track it.
+    UntrackedAnnotation,
+}
+
+impl Message<'_> {
+    /// Render the message text for `config`.
+    pub fn render(&self, config: &OutputConfig) -> String {
+        let icon = |icon: Icon| icon.render(config);
+        match self {
+            Message::LoadingConfig { path } => {
+                format!("Loading configuration from: {}", path.display())
+            }
+            Message::CheckingUpdates => "Checking for repository updates...".to_string(),
+            Message::NoCheckableRepos => {
+                format!("{} No repositories found that can be checked for updates.", icon(Icon::Ok))
+            }
+            Message::ApplyTip => format!(
+                "{} Run `common-repo apply` to fetch and apply configurations",
+                icon(Icon::Tip)
+            ),
+            Message::FetchingTags { url, indent } => format!(
+                "{}Fetching tags from {}... ",
+                if *indent { "  " } else { "" },
+                url
+            ),
+            Message::TagsFound { tag } => format!("found {}", tag),
+            Message::TagsNoSemver => "no semver tags found".to_string(),
+            Message::TagsNone => "no tags found".to_string(),
+            Message::TagsFailed => "failed".to_string(),
+            Message::UnstableVersion { tag } => format!(
+                "Warning: {} is a 0.x.x version, which may indicate unstable API",
+                tag
+            ),
+            Message::NoSemverTagsUsingMain => {
+                "Warning: No semantic version tags found. Using 'main' branch.".to_string()
+            }
+            Message::NoTagsUsingMain => "Warning: No tags found. Using 'main' branch.".to_string(),
+            Message::FetchTagsFailedUsingMain { error } => format!(
+                "Warning: Error fetching tags: {}. Using 'main' branch.",
+                error
+            ),
+            Message::PinCommitHint => {
+                "Consider pinning to a specific commit hash for reproducibility.".to_string()
+            }
+            Message::VersionWarning { warning } => {
+                format!("  {}  {}", icon(Icon::Warning), warning)
+            }
+
+            Message::RepoAdded { url, version } => {
+                format!("{} Added {} @ {} to .common-repo.yaml", icon(Icon::Ok), url, version)
+            }
+            Message::ConfigCreatedWith { url, version } => {
+                format!("{} Created .common-repo.yaml with {} @ {}", icon(Icon::Ok), url, version)
+            }
+            Message::NoConfigFound => {
+                "No .common-repo.yaml configuration file found.\n".to_string()
+            }
+            Message::AddAborted => {
+                "Aborted. Run 'common-repo init' to create a configuration interactively."
+                    .to_string()
+            }
+
+            Message::Initializing => {
+                format!("{} Initializing common-repo configuration...", icon(Icon::Result))
+            }
+            Message::ConfigCreated => format!("{} Created .common-repo.yaml", icon(Icon::Ok)),
+            Message::PreCommitConfigCreated => {
+                format!("{} Created .pre-commit-config.yaml", icon(Icon::Ok))
+            }
+
+            Message::TemplateExported { path, count } => format!(
+                "{} Exported {} file(s) to {}",
+                icon(Icon::Ok),
+                count,
+                path.display()
+            ),
+            Message::FlatConfigWritten { path } => format!(
+                "{} Wrote the flattened configuration to {}",
+                icon(Icon::Ok),
+                path.display()
+            ),
+
+            Message::ConfigImported {
+                path,
+                tool,
+                url,
+                vars,
+            } => format!(
+                "{} Created {} from {} state: {} with {} template variable(s)",
+                icon(Icon::Ok),
+                path.display(),
+                tool,
+                url,
+                vars
+            ),
+            Message::ImportNote { note } => format!("{} {}", icon(Icon::Warning), note),
+
+            Message::AllUpToDate => format!("{} All repositories are up to date!", icon(Icon::Ok)),
+            Message::UpdateSummaryHeader => {
+                format!("\n{} Repository Update Summary:", icon(Icon::Package))
+            }
+            Message::UpdateSummaryCounts { checked, outdated } => format!(
+                "{} repositories checked, {} have available updates\n",
+                checked, outdated
+            ),
+            Message::RepoWithUpdate { url, current } => {
+                format!("{} {} (current: {})", icon(Icon::Check), url, current)
+            }
+            Message::LatestVersion { version } => format!("   Latest: {}", version),
+            Message::BreakingChangesAvailable => format!(
+                "   {}  BREAKING CHANGES available (major version update)",
+                icon(Icon::Warning)
+            ),
+            Message::CompatibleUpdatesAvailable => {
+                format!("   {} Compatible updates available", icon(Icon::Ok))
+            }
+            Message::BreakingChangesAdvice => format!(
+                concat!(
+                    "{}  Some repositories have breaking changes. Review carefully before updating.\n",
+                    "   Use 'common-repo update' to update repository refs in your configuration."
+                ),
+                icon(Icon::Warning)
+            ),
+            Message::ConfigLoaded => {
+                format!("{} Configuration loaded successfully", icon(Icon::Ok))
+            }
+            Message::OperationCount { count } => format!("   Operations: {}", count),
+            Message::RepositoryCount { count } => format!("   Repositories: {}", count),
+            Message::OtherOperationCount { count } => format!("   Other operations: {}", count),
+            Message::UpdatesTip => format!(
+                "\n{} Tip: Use --updates to check for repository version updates",
+                icon(Icon::Tip)
+            ),
+            Message::TemplateVarsHeader => {
+                format!("\n{} Template variables:", icon(Icon::Info))
+            }
+            Message::TemplateVarDefined { name, sources } => {
+                format!("   {}: {}", name, sources)
+            }
+            Message::UndefinedVarsHeader => format!(
+                "\n{} Used but never defined (rendering fails):",
+                icon(Icon::Error)
+            ),
+            Message::TemplateVarUsedIn { name, paths } => {
+                format!("   {} (used in {})", name, paths)
+            }
+            Message::UnusedVarsHeader => {
+                format!("\n{} Defined but never used:", icon(Icon::Warning))
+            }
+            Message::TemplateVarsOk => format!(
+                "\n{} Every template variable is defined and used",
+                icon(Icon::Ok)
+            ),
+            Message::TemplateDeclarationsOk { files } => format!(
+                "{} Template declarations match the {} file(s) consumers receive",
+                icon(Icon::Ok),
+                files
+            ),
+            Message::LockedFilesOk => {
+                format!("{} No locked file is modified locally", icon(Icon::Ok))
+            }
+            Message::AssertionsOk => {
+                format!("{} The output meets every assertion", icon(Icon::Ok))
+            }
+
+            Message::FilteringUpstreams { patterns } => {
+                format!("Filtering upstreams matching: {}", patterns)
+            }
+            Message::NoMatchingRepos { filtered_out } => format!(
+                "{} No repositories found that match the filter ({} filtered out).",
+                icon(Icon::Ok),
+                filtered_out
+            ),
+            Message::AllAtLatest => {
+                format!("{} All repositories are already at the latest versions!", icon(Icon::Ok))
+            }
+            Message::NoCompatibleUpdates => format!(
+                "{} No compatible updates available. Use --latest to see breaking changes.",
+                icon(Icon::Ok)
+            ),
+            Message::AvailableUpdatesHeader => {
+                format!("\n{} Available Updates:", icon(Icon::Package))
+            }
+            Message::UpdatableCount {
+                count,
+                filtered_out: 0,
+            } => format!("{} repositories can be updated\n", count),
+            Message::UpdatableCount {
+                count,
+                filtered_out,
+            } => format!(
+                "{} repositories can be updated ({} filtered out)\n",
+                count, filtered_out
+            ),
+            Message::BreakingChange => {
+                format!("   {}  BREAKING CHANGES (major version update)", icon(Icon::Warning))
+            }
+            Message::CompatibleUpdate => format!("   {} Compatible update", icon(Icon::Ok)),
+            Message::ChangelogHeader { source } => format!("   Changes (from {}):", source),
+            Message::ChangelogRelease { version } => format!("     {}", version),
+            Message::ChangelogNote { line: "" } => String::new(),
+            Message::ChangelogNote { line } => format!("       {}", line),
+            Message::ChangelogUnavailable { error } => {
+                format!("   {}  Could not read the changelog: {}", icon(Icon::Warning), error)
+            }
+            Message::ChangelogWritten { path } => {
+                format!("{} Wrote the changelog to {}", icon(Icon::Note), path.display())
+            }
+            Message::UpdateDryRun => "ℹ️  Dry run mode - no changes will be made.".to_string(),
+            Message::UpdateCancelled => "Update cancelled.".to_string(),
+            Message::UpdatingRepos => format!("\n{} Updating repositories...", icon(Icon::Check)),
+            Message::RepoUpdated {
+                url,
+                occurrences,
+                from,
+                to,
+            } => format!(
+                "{} Updated {} ({} occurrence{}): {} → {}",
+                icon(Icon::Ok),
+                url,
+                occurrences,
+                if *occurrences == 1 { "" } else { "s" },
+                from,
+                to
+            ),
+            Message::ReposUpdated { count, path } => format!(
+                "\n{} Successfully updated {} repositories in {}",
+                icon(Icon::Ok),
+                count,
+                path.display()
+            ),
+            Message::ApplyUpdatedTip => format!(
+                "{} Run 'common-repo apply' to apply the updated configuration.",
+                icon(Icon::Tip)
+            ),
+            Message::NoReposUpdated => "\nℹ️  No repositories were updated.".to_string(),
+
+            Message::Validating { path } => format!(
+                "{} Validating configuration: {}",
+                icon(Icon::Scan),
+                path.display()
+            ),
+            Message::ConfigParsed => {
+                format!("{} Configuration file parsed successfully", icon(Icon::Ok))
+            }
+            Message::ConfigParseFailed { error } => format!(
+                "{} Configuration parsing failed: {}",
+                icon(Icon::Error),
+                error
+            ),
+            Message::ConfigSummaryHeader => {
+                format!("\n{} Configuration Summary:", icon(Icon::Info))
+            }
+            Message::TotalOperationCount { count } => format!("   Total operations: {}", count),
+            Message::RepoOperationCount { count } => {
+                format!("   Repository operations: {}", count)
+            }
+            Message::CheckingCycles => format!(
+                "\n{} Checking for circular dependencies...",
+                icon(Icon::Check)
+            ),
+            Message::NoCycles => format!("{} No circular dependencies detected", icon(Icon::Ok)),
+            Message::DiscoveredRepos { count } => {
+                format!("   Discovered {} repositories in dependency tree", count)
+            }
+            Message::CycleDetected { error } => format!(
+                "{} Circular dependency detected: {}",
+                icon(Icon::Error),
+                error
+            ),
+            Message::DiscoveryWarning { error } => format!(
+                "{} Warning during dependency discovery: {}",
+                icon(Icon::Warning),
+                error
+            ),
+            Message::ProvenanceHeader => {
+                format!("\n{} Merge operation provenance:", icon(Icon::Scan))
+            }
+            Message::UndeclaredMergeSource { operation, source } => format!(
+                "{} Merge operation {} uses source \"{}\" not declared by any upstream",
+                icon(Icon::Warning),
+                operation,
+                source
+            ),
+            Message::ValidatingPatterns => {
+                format!("\n{} Validating operation patterns...", icon(Icon::Scan))
+            }
+            Message::InvalidRegex {
+                kind,
+                operation,
+                error,
+            } => format!(
+                "{} Invalid regex pattern in {} operation {}: {}",
+                icon(Icon::Error),
+                kind,
+                operation,
+                error
+            ),
+            Message::InvalidGlob {
+                kind,
+                operation,
+                error,
+            } => format!(
+                "{} Invalid glob pattern in {} operation {}: {}",
+                icon(Icon::Error),
+                kind,
+                operation,
+                error
+            ),
+            Message::EmptyTools { operation } => format!(
+                "{} Tools operation {} has no tools defined",
+                icon(Icon::Warning),
+                operation
+            ),
+            Message::MissingPatch { operation, path } => format!(
+                "{} Patch operation {}: patch file not found: {}",
+                icon(Icon::Error),
+                operation,
+                path
+            ),
+            Message::PatchOutsideRepo { operation, path } => format!(
+                "{} Patch operation {}: patch file is outside the repository: {}",
+                icon(Icon::Error),
+                operation,
+                path
+            ),
+            Message::UnrecognizedOperation {
+                operation,
+                name,
+                suggestion,
+            } => match suggestion {
+                Some(similar) => format!(
+                    "{} Operation {} has unknown type '{}' (did you mean '{}'?)",
+                    icon(Icon::Warning),
+                    operation,
+                    name,
+                    similar
+                ),
+                None => format!(
+                    "{} Operation {} has unknown type '{}'; it is skipped by this version",
+                    icon(Icon::Warning),
+                    operation,
+                    name
+                ),
+            },
+            Message::InvalidMerge {
+                kind,
+                operation,
+                error,
+            } => format!(
+                "{} Invalid {} merge operation {}: {}",
+                icon(Icon::Error),
+                kind,
+                operation,
+                error
+            ),
+            Message::PatternsValid => {
+                format!("{} All operation patterns are valid", icon(Icon::Ok))
+            }
+            Message::CheckingAccess => format!(
+                "\n{} Checking repository accessibility...",
+                icon(Icon::Network)
+            ),
+            Message::CheckingRepo { target } => format!("   Checking {}... ", target),
+            Message::RepoLocal => "local, skipping network check".to_string(),
+            Message::RepoNoTags => {
+                format!("{} accessible but no tags found", icon(Icon::Warning))
+            }
+            Message::RepoAccessible { tags } => {
+                format!("{} accessible ({} tags)", icon(Icon::Ok), tags)
+            }
+            Message::RepoInaccessible { error } => {
+                format!("{} not accessible: {}", icon(Icon::Error), error)
+            }
+            Message::ResultHeader => format!("\n{} Validation Result:", icon(Icon::Result)),
+            Message::HasErrors => format!(
+                "{} Configuration has errors that must be fixed",
+                icon(Icon::Error)
+            ),
+            Message::HasWarningsStrict => format!(
+                "{} Configuration has warnings (strict mode enabled)",
+                icon(Icon::Error)
+            ),
+            Message::ValidWithWarnings => format!(
+                "{} Configuration is valid but has warnings",
+                icon(Icon::Warning)
+            ),
+            Message::Valid => format!("{} Configuration is valid", icon(Icon::Ok)),
+            Message::CheckReposTip => format!(
+                "\n{} Tip: Use --check-repos to also validate repository accessibility",
+                icon(Icon::Tip)
+            ),
+
+            Message::CacheMissing { path } => {
+                format!("Cache directory does not exist: {}", path.display())
+            }
+            Message::NoCachedRepos => "No cached repositories found.".to_string(),
+            Message::NothingToClean => "No cached repositories to clean.".to_string(),
+            Message::CacheEmpty { path } => {
+                format!("No cached repositories found in: {}", path.display())
+            }
+            Message::NoMatchingCacheEntries => {
+                "No cache entries match the specified criteria.".to_string()
+            }
+            Message::CacheCleanHeader => "Cache entries to be deleted:\n".to_string(),
+            Message::CacheCleanEntry {
+                hash,
+                ref_name,
+                path,
+                size,
+            } => format!("  {} {} {} ({})", hash, ref_name, path, size),
+            Message::CacheCleanTotal { count, size } => {
+                format!("\nTotal: {} entries ({})", count, size)
+            }
+            Message::CacheDryRun => {
+                format!("\n{} Dry run mode - no changes were made.", icon(Icon::Scan))
+            }
+            Message::CleanCancelled => "Clean cancelled.".to_string(),
+            Message::DeletingCacheEntries => {
+                format!("\n{}  Deleting cache entries...", icon(Icon::Delete))
+            }
+            Message::CacheEntryDeleted { path } => {
+                format!("  {} Deleted: {}", icon(Icon::Ok), path.display())
+            }
+            Message::CacheEntryDeleteFailed { path, error } => {
+                format!("  {} Failed to delete {}: {}", icon(Icon::Error), path.display(), error)
+            }
+            Message::CacheEntriesDeleted { count } => {
+                format!("\n{} Successfully deleted {} cache entries.", icon(Icon::Ok), count)
+            }
+            Message::CacheEntriesDeleteFailed { count } => {
+                format!("\n{}  Failed to delete {} cache entries.", icon(Icon::Warning), count)
+            }
+            Message::CacheWarmed { count, config } => format!(
+                "{} {} repositories cached for {}",
+                icon(Icon::Ok),
+                count,
+                config.display()
+            ),
+            Message::CacheExported { count, path } => format!(
+                "{} Exported {} cache entries to {}",
+                icon(Icon::Ok),
+                count,
+                path.display()
+            ),
+            Message::CacheEntryAlreadyCached { url, ref_ } => format!(
+                "  {}@{} is already cached, skipped (use --force to replace it)",
+                url, ref_
+            ),
+            Message::CacheImported { count, skipped } => format!(
+                "{} Imported {} cache entries ({} already cached)",
+                icon(Icon::Ok),
+                count,
+                skipped
+            ),
+
+            Message::MigrationUpToDate { path } => format!(
+                "{} {} already uses the current schema",
+                icon(Icon::Ok),
+                path.display()
+            ),
+            Message::MigrationNeeded { path, count } => format!(
+                "{} {} needs {} change(s) to use the current schema:",
+                icon(Icon::Warning),
+                path.display(),
+                count
+            ),
+            Message::MigrationChange { line, description } => {
+                format!("   line {}: {}", line, description)
+            }
+            Message::ConfigMigrated { path, count } => format!(
+                "{} Migrated {} ({} change(s))",
+                icon(Icon::Ok),
+                path.display(),
+                count
+            ),
+            Message::MigrateTip => format!(
+                "\n{} Run 'common-repo migrate-config' to rewrite the file",
+                icon(Icon::Tip)
+            ),
+
+            Message::AlreadyUpstream { source, repo } => format!(
+                "{} {} in {} already has this change",
+                icon(Icon::Ok),
+                source.display(),
+                repo
+            ),
+            Message::PromotePatchWritten { source, path } => format!(
+                "{} Wrote the change to {} as a patch to {}",
+                icon(Icon::Note),
+                source.display(),
+                path.display()
+            ),
+            Message::PromoteCommitted {
+                source,
+                branch,
+                dir,
+            } => format!(
+                "{} Committed the change to {} on branch {} in {}\n   Push it with: git -C {} push origin {}",
+                icon(Icon::Ok),
+                source.display(),
+                branch,
+                dir.display(),
+                dir.display(),
+                branch
+            ),
+
+            Message::NoHistory { path } => format!(
+                "{} No applies recorded in {} yet",
+                icon(Icon::Info),
+                path.display()
+            ),
+
+            Message::WebhookFailed { host, error } => format!(
+                "{} Could not notify the webhook at {}: {}",
+                icon(Icon::Warning),
+                host,
+                error
+            ),
+
+            Message::NoNewerRefs => format!(
+                "{} All upstreams are at their latest release; nothing to shadow",
+                icon(Icon::Ok)
+            ),
+            Message::ShadowRef { url, from, to } => format!(
+                "{} Shadowing {} at {} (live: {})",
+                icon(Icon::Scan),
+                url,
+                to,
+                if from.is_empty() { "no ref" } else { from }
+            ),
+            Message::ShadowChange { path, change } => {
+                format!("   {:<8} {}", change, path.display())
+            }
+            Message::ShadowWritten {
+                path,
+                counts,
+                report,
+            } => format!(
+                "{} Shadow output written to {}: {}\n   Report: {}",
+                icon(Icon::Result),
+                path.display(),
+                counts,
+                report.display()
+            ),
+
+            Message::PipelineWarning { message } => format!("Warning: {}", message),
+            Message::PipelineWarningCount { count } => format!("   {} warning(s)", count),
+            Message::WarningsDenied { count } => format!(
+                "{} warning(s) reported and --deny-warnings is set; nothing was written",
+                count
+            ),
+            Message::CachedFallback { url, ref_ } => format!(
+                "Network fetch failed for {}@{}, falling back to cached version",
+                url, ref_
+            ),
+            Message::UpstreamDeprecated {
+                repo,
+                operation,
+                message,
+                replacement_url,
+            } => {
+                let subject = match operation {
+                    Some(operation) => format!("Operation '{}' of {}", operation, repo),
+                    None => repo.to_string(),
+                };
+                match replacement_url {
+                    Some(url) => format!(
+                        "DEPRECATED: {}: {} (replacement: {})",
+                        subject, message, url
+                    ),
+                    None => format!("DEPRECATED: {}: {}", subject, message),
+                }
+            }
+            Message::SkippedUpstream { url, ref_, error } => format!(
+                "Skipping {}@{} and everything it inherits from, it could not be fetched: {}",
+                url, ref_, error
+            ),
+            Message::StaleRefIndex { index, error } => format!(
+                "Could not fetch the ref index {}, using the cached copy: {}",
+                index, error
+            ),
+            Message::ResolvedRefAlias {
+                url,
+                alias,
+                ref_,
+                index,
+            } => format!("{}@{} resolves to {} in {}", url, alias, ref_, index),
+            Message::RateLimitedCachedFile { url, host, seconds } => format!(
+                "{} is rate limiting requests for another {}s, using the cached copy of {}",
+                host, seconds, url
+            ),
+            Message::MemoryBudgetExceeded { size, max_memory } => format!(
+                "the composite filesystem holds {} bytes, more than the --max-memory budget of {} bytes; \
+                 only cached upstream filesystems can be moved to disk",
+                size, max_memory
+            ),
+            Message::WaitingForCacheLock { path } => format!(
+                "Waiting for another run to finish writing {}",
+                path.display()
+            ),
+            Message::StaleCacheLock { path } => format!(
+                "Removed stale cache lock {} left by a run that did not finish",
+                path.display()
+            ),
+            Message::ResumedFromCheckpoint { what } => {
+                format!("Resuming from the {} saved by a previous run", what)
+            }
+            Message::OverridesApplied { count } => format!(
+                "Overriding {} file(s) from {}",
+                count,
+                crate::defaults::OVERRIDES_DIR
+            ),
+            Message::RenamedUpstream { from, to } => format!(
+                "{} Removed {}: renamed upstream to {}",
+                icon(Icon::Move),
+                from.display(),
+                to.display()
+            ),
+            Message::ResumeTip => format!(
+                "{} Re-run with --resume to continue from the last completed phase",
+                icon(Icon::Tip)
+            ),
+            Message::UpToDate => {
+                format!(
+                    "{} Already up to date: nothing changed since the last apply",
+                    icon(Icon::Ok)
+                )
+            }
+            Message::PlanSaved { path } => format!(
+                "{} Saved the plan to {}; apply it with `common-repo apply --from-plan {}`",
+                icon(Icon::Package),
+                path.display(),
+                path.display()
+            ),
+            Message::ApplyingPlan { path } => {
+                format!("{} Applying the plan saved in {}", icon(Icon::Package), path.display())
+            }
+            Message::ApplyHeader => format!("{} Common Repository Apply", icon(Icon::Scan)),
+            Message::ApplyDryRun => {
+                format!("{} DRY RUN MODE - No changes will be made", icon(Icon::Scan))
+            }
+            Message::ApplySucceeded { seconds } => {
+                format!("{} Applied successfully in {:.2}s", icon(Icon::Ok), seconds)
+            }
+            Message::ApplyFailed => format!("{} Apply failed", icon(Icon::Error)),
+            Message::SlowOperations => format!("{} Slow operations:", icon(Icon::Slow)),
+            Message::WouldOverwriteUntracked { count, files } => format!(
+                "{}  Would overwrite {} file(s) not tracked by git (use --force-untracked):\n{}",
+                icon(Icon::Warning),
+                count,
+                files
+            ),
+            Message::ApplyingToSandbox { path } => format!(
+                "{} Applying into a sandbox at {}; it is removed afterwards",
+                icon(Icon::Sandbox),
+                path.display()
+            ),
+            Message::SandboxRunning { command } => {
+                format!("{} Running `{}` in the sandbox", icon(Icon::Sandbox), command)
+            }
+            Message::SandboxPassed { command } => {
+                format!("{} `{}` passed in the sandbox", icon(Icon::Ok), command)
+            }
+            Message::SandboxFailed { command, status } => {
+                format!("`{}` failed in the sandbox ({})", command, status)
+            }
+            Message::RequiredVarsUnset { count } => format!(
+                "{} Upstreams require {} template variable(s) this configuration does not set",
+                icon(Icon::Note),
+                count
+            ),
+            Message::SaveRequiredVars { path } => {
+                format!("Save these values to {} under template-vars?", path.display())
+            }
+            Message::RequiredVarsSaved { path } => {
+                format!("{} Saved the values to {}", icon(Icon::Note), path.display())
+            }
+            Message::ProtectHookInstalled { path } => {
+                format!("{} Managed files are guarded by {}", icon(Icon::Lock), path.display())
+            }
+            Message::ProtectHookForeign { path } => format!(
+                "{} is not common-repo's: managed files are not guarded by a pre-commit hook",
+                path.display()
+            ),
+            Message::SweepingRename {
+                from,
+                to,
+                count,
+                description,
+            } => {
+                let mut line = format!(
+                    "{}  rename '{}' -> '{}' moves {} file(s)",
+                    icon(Icon::Warning),
+                    from,
+                    to,
+                    count
+                );
+                if let Some(description) = description {
+                    line.push_str(&format!("  # {}", description));
+                }
+                line
+            }
+            Message::SweepingRenameMove { from, to } => {
+                format!("    {} → {}", from.display(), to.display())
+            }
+            Message::SweepingRenameMore { count } => format!("    ... and {} more", count),
+            Message::ConfirmSweepingRenames => "\nApply these renames? (y/N): ".to_string(),
+            Message::ApplyCancelled => "Apply cancelled.".to_string(),
+            Message::SweepingRenamesRefused { count } => format!(
+                "{} rename mapping(s) look too broad to apply without confirmation; nothing was written\n  hint: check the mappings above, then re-run with --yes",
+                count
+            ),
+            Message::AmbiguousRepo {
+                url,
+                ref_,
+                candidates,
+            } => format!(
+                "Multiple cloned repos match {}@{} ({} candidates); using first match",
+                url, ref_, candidates
+            ),
+            Message::UnknownOperation { name, suggestion } => match suggestion {
+                Some(similar) => format!(
+                    "Unknown operation '{}' skipped (did you mean '{}'?)",
+                    name, similar
+                ),
+                None => format!(
+                    "Unknown operation '{}' skipped; it may need a newer common-repo",
+                    name
+                ),
+            },
+            Message::UnmatchedDisable { id, repo } => format!(
+                "disable: no operation with id '{}' in {}; nothing was disabled",
+                id, repo
+            ),
+            Message::DuplicateConfigFiles {
+                location,
+                used,
+                ignored,
+            } => format!(
+                "{} has both {} and {} with different content; reading {} and ignoring {}",
+                location, used, ignored, used, ignored
+            ),
+            Message::RepoNotCloned { url, ref_ } => format!(
+                "Repo reference not found in cloned repos, skipping: {}@{}",
+                url, ref_
+            ),
+            Message::TreeChildNotCloned { key } => {
+                format!("Tree child not found in cloned repos, skipping: {}", key)
+            }
+            Message::EmptyUpstream {
+                repo,
+                include,
+                exclude,
+            } => {
+                let mut message = format!(
+                    "{} contributes no files: include {} kept nothing",
+                    repo, include
+                );
+                if !exclude.is_empty() {
+                    message.push_str(&format!(" after exclude {}", exclude));
+                }
+                message.push_str("; check the patterns for a bad glob");
+                message
+            }
+            Message::UpstreamSecretIgnored { name } => format!(
+                "an upstream reads template variable '{}' from a secret source, which only \
+                 your own configuration may do; it was left undefined",
+                name
+            ),
+            Message::InaccessiblePath { error } => {
+                format!("Skipping inaccessible path during local FS load: {}", error)
+            }
+            Message::ReadOnlySkipped { path } => {
+                format!("Skipped read-only file {}: it was not updated", path)
+            }
+            Message::OutsidePathScope { path } => format!(
+                "Not writing {}: it is outside the allow-paths or deny-paths of the configuration",
+                path
+            ),
+            Message::AutoMergeSkipped { path } => format!(
+                "Auto-merge skipped, file not found on either side: {}. \
+                 Was the file possibly renamed or excluded by a preceding operation?",
+                path
+            ),
+            Message::MergeOverwriteValue {
+                source,
+                dest,
+                path,
+                from,
+                to,
+            } => format!(
+                "{} -> {}: Overwriting value at path '{}': {} -> {}",
+                source, dest, path, from, to
+            ),
+            Message::MergeTypeMismatch {
+                source,
+                dest,
+                path,
+                from,
+                to,
+            } => format!(
+                "{} -> {}: Type mismatch at path '{}': replacing {} with {}",
+                source, dest, path, from, to
+            ),
+            Message::MergeReplaceArray {
+                source,
+                dest,
+                path,
+                old_len,
+                new_len,
+            } => format!(
+                "{} -> {}: Replacing array at path '{}' (old size: {}, new size: {})",
+                source, dest, path, old_len, new_len
+            ),
+            Message::MergeOverwriteScalar {
+                source,
+                dest,
+                path,
+                from,
+                to,
+            } => format!(
+                "{} -> {}: Overwriting scalar at path '{}': {} -> {}",
+                source, dest, path, from, to
+            ),
+            Message::WarningAnnotationTitle { phase } => {
+                format!("common-repo {} warning", phase)
+            }
+            Message::UpdateAnnotationTitle { breaking: true } => {
+                "Breaking update available".to_string()
+            }
+            Message::UpdateAnnotationTitle { breaking: false } => "Update available".to_string(),
+            Message::UpdateAnnotation {
+                url,
+                current,
+                latest,
+            } => format!("{} can be updated from {} to {}", url, current, latest),
+            Message::DriftAnnotationTitle => "Configuration drift".to_string(),
+            Message::DriftAnnotation { change } => format!(
+                "This file would be {} by `common-repo apply`",
+                change
+            ),
+            Message::ErrorAnnotationTitle { code: Some(code) } => {
+                format!("common-repo error {}", code)
+            }
+            Message::ErrorAnnotationTitle { code: None } => "common-repo error".to_string(),
+            Message::UntrackedAnnotation => {
+                "`common-repo apply` would overwrite this file, which git does not track"
+                    .to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Message<'_> {
+    /// Renders with the process-wide output configuration.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&output::global()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_follows_color_setting() {
+        let message = Message::Valid;
+        assert_eq!(
+            message.render(&OutputConfig::with_color()),
+            "✅ Configuration is valid"
+        );
+        assert_eq!(
+            message.render(&OutputConfig::without_color()),
+            "[OK] Configuration is valid"
+        );
+    }
+
+    #[test]
+    fn test_apply_messages_have_plain_icons_without_color() {
+        let config = OutputConfig::without_color();
+        assert_eq!(
+            Message::ApplyHeader.render(&config),
+            "[SCAN] Common Repository Apply"
+        );
+        assert_eq!(
+            Message::ApplySucceeded { seconds: 1.5 }.render(&config),
+            "[OK] Applied successfully in 1.50s"
+        );
+        assert_eq!(
+            Message::ApplySucceeded { seconds: 1.5 }.render(&OutputConfig::with_color()),
+            "✅ Applied successfully in 1.50s"
+        );
+        assert!(Message::BreakingChangesAdvice
+            .render(&config)
+            .starts_with("[WARN]  Some repositories have breaking changes."));
+    }
+
+    #[test]
+    fn test_render_with_parameters() {
+        let config = OutputConfig::without_color();
+        assert_eq!(
+            Message::RepoUpdated {
+                url: "https://github.com/org/repo",
+                occurrences: 2,
+                from: "v1.0.0",
+                to: "v1.1.0",
+            }
+            .render(&config),
+            "[OK] Updated https://github.com/org/repo (2 occurrences): v1.0.0 → v1.1.0"
+        );
+        assert_eq!(
+            Message::UpdatableCount {
+                count: 3,
+                filtered_out: 0
+            }
+            .render(&config),
+            "3 repositories can be updated\n"
+        );
+        assert_eq!(
+            Message::UpdatableCount {
+                count: 3,
+                filtered_out: 1
+            }
+            .render(&config),
+            "3 repositories can be updated (1 filtered out)\n"
+        );
+    }
+}
@@ -0,0 +1,627 @@
+//! # Configuration Migration
+//!
+//! Rewrites `.common-repo.yaml` files written for older releases so they use
+//! the current schema. This backs the `migrate-config` command.
+//!
+//! Rewrites are made on the text, one line at a time, instead of through a
+//! serde round trip. Comments, key order, quoting and blank lines are kept;
+//! only the old shapes themselves change.
+//!
+//! ## Recognized Shapes
+//!
+//! | Old shape | Current shape |
+//! |-----------|---------------|
+//! | `append: true` in a `yaml`, `json` or `toml` operation | `array_mode: append` |
+//! | `append: false` in a `yaml`, `json` or `toml` operation | `array_mode: replace` |
+//! | `include: "pattern"` (also `exclude`, `template`) | `include: ["pattern"]` |
+//!
+//! `append` was removed from the structured merge operators in 0.31 and is
+//! ignored by the parser, so configs still using it silently lost their
+//! append behavior. A single pattern given as a string is rejected by the
+//! parser, which expects a list.
+//!
+//! ## Forms
+//!
+//! [`to_document`] and [`to_list`] convert between a bare list of operations
+//! and a versioned document with `apiVersion` and `operations` (see
+//! [`crate::config::API_VERSION`]). They also work on the text: the
+//! operations are indented or unindented, and comments are kept.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::migrate;
+//!
+//! let old = "- yaml:\n    auto-merge: config.yaml\n    append: true # keep upstream items\n";
+//! let migration = migrate::migrate(old);
+//!
+//! assert_eq!(migration.changes.len(), 1);
+//! assert_eq!(
+//!     migration.content,
+//!     "- yaml:\n    auto-merge: config.yaml\n    array_mode: append # keep upstream items\n"
+//! );
+//! ```
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::config::{is_document, operations_text, API_VERSION};
+use crate::error::{Error, Result};
+
+/// Operators whose `append` key was replaced by `array_mode`.
+const ARRAY_MODE_OPERATORS: &[&str] = &["yaml", "json", "toml"];
+
+/// Operators that take a list of patterns.
+const PATTERN_OPERATORS: &[&str] = &["include", "exclude", "template"];
+
+/// One rewrite made by [`migrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// 1-based line number in the original content.
+    pub line: usize,
+    /// What was rewritten, for display.
+    pub description: String,
+}
+
+/// Result of [`migrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The rewritten content. Equal to the input when `changes` is empty.
+    pub content: String,
+    /// Every rewrite, in line order.
+    pub changes: Vec<Change>,
+}
+
+impl Migration {
+    /// Whether the input used any old shapes.
+    pub fn is_needed(&self) -> bool {
+        !self.changes.is_empty()
+    }
+}
+
+/// A `key: value` line, split into its parts.
+struct KeyLine<'a> {
+    /// Indentation and any `- ` sequence markers before the key.
+    prefix: &'a str,
+    /// Column of the first `-` marker when the line starts a sequence item.
+    item_column: Option<usize>,
+    /// Column where the key starts.
+    column: usize,
+    key: &'a str,
+    /// Everything after `key:`, including whitespace and any comment.
+    rest: &'a str,
+}
+
+impl<'a> KeyLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        static KEY: OnceLock<Regex> = OnceLock::new();
+        let key_re = KEY.get_or_init(|| {
+            Regex::new(r"^((\s*)(?:-\s+)*)([A-Za-z_][A-Za-z0-9_-]*):(\s.*)?$").unwrap()
+        });
+        let captures = key_re.captures(line)?;
+        let prefix = captures.get(1).unwrap().as_str();
+        let indent = captures.get(2).unwrap().as_str().len();
+        Some(KeyLine {
+            prefix,
+            item_column: (prefix.len() > indent).then_some(indent),
+            column: prefix.len(),
+            key: captures.get(3).unwrap().as_str(),
+            rest: captures.get(4).map_or("", |m| m.as_str()),
+        })
+    }
+
+    /// Whether this line ends the block opened by a key at `column`.
+    fn closes(&self, column: usize) -> bool {
+        match self.item_column {
+            Some(item_column) => column > item_column,
+            None => column >= self.column,
+        }
+    }
+
+    /// The value with any trailing comment removed, trimmed.
+    fn value(&self) -> &'a str {
+        split_comment(self.rest).0.trim()
+    }
+}
+
+/// Split `text` into value and comment. The comment keeps its leading
+/// whitespace so it can be re-attached unchanged.
+fn split_comment(text: &str) -> (&str, &str) {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => {
+                let value = &text[..index];
+                let trimmed = value.trim_end();
+                return (trimmed, &text[trimmed.len()..]);
+            }
+            None => {}
+        }
+        previous = c;
+    }
+    (text, "")
+}
+
+/// `append: <bool>` inside a structured merge operator.
+fn rewrite_append(line: &KeyLine<'_>, parent: Option<&str>) -> Option<(String, String)> {
+    let operator = parent.filter(|p| ARRAY_MODE_OPERATORS.contains(p))?;
+    if line.key != "append" {
+        return None;
+    }
+    let value = line.value();
+    let mode = match value {
+        "true" => "append",
+        "false" => "replace",
+        _ => return None,
+    };
+    let rest = line.rest.replacen(value, mode, 1);
+    Some((
+        format!("{}array_mode:{}", line.prefix, rest),
+        format!(
+            "`append: {}` in a {} operation is now `array_mode: {}`",
+            value, operator, mode
+        ),
+    ))
+}
+
+/// `append: <bool>` inside a flow mapping such as `- yaml: {..., append: true}`.
+fn rewrite_flow_append(line: &KeyLine<'_>) -> Option<(String, String)> {
+    static APPEND: OnceLock<Regex> = OnceLock::new();
+    if !ARRAY_MODE_OPERATORS.contains(&line.key) || !line.value().starts_with('{') {
+        return None;
+    }
+    let append_re =
+        APPEND.get_or_init(|| Regex::new(r"\bappend(\s*):(\s*)(true|false)\b").unwrap());
+    let captures = append_re.captures(line.rest)?;
+    let value = captures.get(3).unwrap().as_str();
+    let mode = if value == "true" { "append" } else { "replace" };
+    let rest = append_re.replace(line.rest, format!("array_mode$1:${{2}}{}", mode));
+    Some((
+        format!("{}{}:{}", line.prefix, line.key, rest),
+        format!(
+            "`append: {}` in a {} operation is now `array_mode: {}`",
+            value, line.key, mode
+        ),
+    ))
+}
+
+/// A single pattern given as a string instead of a list.
+fn rewrite_scalar_patterns(line: &KeyLine<'_>) -> Option<(String, String)> {
+    if line.item_column.is_none() || !PATTERN_OPERATORS.contains(&line.key) {
+        return None;
+    }
+    let (value, comment) = split_comment(line.rest);
+    let value = value.trim();
+    if value.is_empty() || value.starts_with(['[', '{', '|', '>', '&', '*', '!']) {
+        return None;
+    }
+    Some((
+        format!("{}{}: [{}]{}", line.prefix, line.key, value, comment),
+        format!("{} pattern {} is now a list", line.key, value),
+    ))
+}
+
+/// Rewrite every old shape in `content` to the current schema.
+///
+/// Content that uses no old shapes is returned unchanged. The result is not
+/// validated; callers should parse it before writing it out.
+pub fn migrate(content: &str) -> Migration {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines = Vec::new();
+    let mut changes = Vec::new();
+    // Keys whose value is the indented block we are inside, outermost first.
+    let mut blocks: Vec<(usize, &str)> = Vec::new();
+    // Column of a key whose value is a `|` or `>` block scalar.
+    let mut block_scalar: Option<usize> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(column) = block_scalar {
+            if line.trim().is_empty() || indent > column {
+                lines.push(line.to_string());
+                continue;
+            }
+            block_scalar = None;
+        }
+
+        let Some(key_line) = KeyLine::parse(line) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        while blocks
+            .last()
+            .is_some_and(|(column, _)| key_line.closes(*column))
+        {
+            blocks.pop();
+        }
+        let parent = blocks.last().map(|(_, key)| *key);
+
+        let rewrite = rewrite_append(&key_line, parent)
+            .or_else(|| rewrite_flow_append(&key_line))
+            .or_else(|| rewrite_scalar_patterns(&key_line));
+        match rewrite {
+            Some((rewritten, description)) => {
+                lines.push(rewritten);
+                changes.push(Change {
+                    line: index + 1,
+                    description,
+                });
+            }
+            None => lines.push(line.to_string()),
+        }
+
+        let value = key_line.value();
+        if value.is_empty() {
+            blocks.push((key_line.column, key_line.key));
+        } else if value.starts_with(['|', '>']) {
+            block_scalar = Some(key_line.column);
+        }
+    }
+
+    if changes.is_empty() {
+        return Migration {
+            content: content.to_string(),
+            changes,
+        };
+    }
+    let mut migrated = lines.join(newline);
+    if content.ends_with('\n') {
+        migrated.push_str(newline);
+    }
+    Migration {
+        content: migrated,
+        changes,
+    }
+}
+
+/// The forms a configuration can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    /// A bare list of operations.
+    List,
+    /// A document with an `apiVersion` and its `operations`.
+    Document,
+}
+
+impl Form {
+    /// The form `content` is written in.
+    pub fn of(content: &str) -> Self {
+        if is_document(content) {
+            Form::Document
+        } else {
+            Form::List
+        }
+    }
+}
+
+/// `migration` with its content converted to `form`, recorded as one more
+/// change. A migration already in `form` is returned as it is.
+pub fn convert(mut migration: Migration, form: Form) -> Result<Migration> {
+    if Form::of(&migration.content) == form {
+        return Ok(migration);
+    }
+    let (content, description) = match form {
+        Form::List => (
+            to_list(&migration.content)?,
+            "the document is now a list of operations".to_string(),
+        ),
+        Form::Document => (
+            to_document(&migration.content)?,
+            format!(
+                "the list of operations is now a document with apiVersion: {}",
+                API_VERSION
+            ),
+        ),
+    };
+    migration.content = content;
+    migration.changes.insert(
+        0,
+        Change {
+            line: 1,
+            description,
+        },
+    );
+    Ok(migration)
+}
+
+/// `content` as a document with `apiVersion` and `operations`.
+///
+/// Comments before the first operation stay at the top; the operations are
+/// indented under `operations:`. A document is returned unchanged.
+///
+/// ```
+/// use common_repo::migrate;
+///
+/// let list = "# Shared CI\n- include: ['**']\n";
+/// assert_eq!(
+///     migrate::to_document(list).unwrap(),
+///     "# Shared CI\napiVersion: common-repo/v1\noperations:\n  - include: ['**']\n"
+/// );
+/// assert_eq!(migrate::to_list(&migrate::to_document(list).unwrap()).unwrap(), list);
+/// ```
+pub fn to_document(content: &str) -> Result<String> {
+    if Form::of(content) == Form::Document {
+        return Ok(content.to_string());
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    let header = lines
+        .iter()
+        .take_while(|line| {
+            let trimmed = line.trim();
+            trimmed.is_empty() || trimmed.starts_with('#')
+        })
+        .count();
+    let mut converted: Vec<String> = lines[..header].iter().map(|l| l.to_string()).collect();
+    converted.push(format!("apiVersion: {}", API_VERSION));
+    converted.push("operations:".to_string());
+    for line in &lines[header..] {
+        if line.trim().is_empty() {
+            converted.push(String::new());
+        } else {
+            converted.push(format!("  {}", line));
+        }
+    }
+    finish(content, converted)
+}
+
+/// `content` as a bare list of operations.
+///
+/// The `apiVersion` line is dropped and the operations are unindented.
+/// Comments outside `operations` are kept where they are. A list is
+/// returned unchanged.
+pub fn to_list(content: &str) -> Result<String> {
+    if Form::of(content) == Form::List {
+        return Ok(content.to_string());
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    let at_top = |line: &str| !line.is_empty() && !line.starts_with([' ', '\t']);
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with("operations:"))
+        .ok_or_else(|| unconvertible("it has no top-level `operations:` line"))?;
+    // The operations block ends at the next top-level key.
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| at_top(line) && !line.starts_with(['#', '-']))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    let block = &lines[start + 1..end];
+    let indent = block
+        .iter()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut converted = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index == start {
+            let inline = split_comment(&line["operations:".len()..]).0.trim();
+            if !inline.is_empty() {
+                converted.push(inline.to_string());
+            }
+        } else if index > start && index < end {
+            let leading = line.len() - line.trim_start().len();
+            converted.push(line[leading.min(indent)..].to_string());
+        } else if !line.starts_with("apiVersion:") {
+            converted.push(line.to_string());
+        }
+    }
+    finish(content, converted)
+}
+
+/// Join `lines` as `content` was joined, and check that they hold the same
+/// operations as `content`.
+fn finish(content: &str, lines: Vec<String>) -> Result<String> {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut converted = lines.join(newline);
+    if content.ends_with('\n') {
+        converted.push_str(newline);
+    }
+    let operations = |text: &str| -> Result<serde_yaml::Value> {
+        let value = serde_yaml::from_str(&operations_text(text)?).map_err(Error::Yaml)?;
+        Ok(match value {
+            serde_yaml::Value::Null => serde_yaml::Value::Sequence(Vec::new()),
+            value => value,
+        })
+    };
+    if operations(content)? != operations(&converted)? {
+        return Err(unconvertible(
+            "its layout could not be converted line by line",
+        ));
+    }
+    Ok(converted)
+}
+
+fn unconvertible(reason: &str) -> Error {
+    Error::ConfigParse {
+        message: format!("The configuration cannot be converted: {}", reason),
+        hint: Some("Convert it by hand; nothing was written".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_config_is_unchanged() {
+        let content = "\
+# Shared CI setup
+- repo:
+    url: https://github.com/example/repo
+    ref: v1.0.0
+- include: [\"**/*\"]
+- ini:
+    source: a.ini
+    dest: b.ini
+    append: true
+- yaml:
+    source: a.yaml
+    dest: b.yaml
+    array_mode: append
+";
+        let migration = migrate(content);
+        assert!(!migration.is_needed());
+        assert_eq!(migration.content, content);
+    }
+
+    #[test]
+    fn test_append_becomes_array_mode() {
+        let content = "\
+- json:
+    source: a.json
+    dest: b.json
+    append: false   # upstream wins
+- repo:
+    url: ../upstream
+    with:
+      - toml:
+          auto-merge: Cargo.toml
+          append: true
+- markdown:
+    auto-merge: README.md
+    append: true
+";
+        let migration = migrate(content);
+
+        assert_eq!(
+            migration.content,
+            "\
+- json:
+    source: a.json
+    dest: b.json
+    array_mode: replace   # upstream wins
+- repo:
+    url: ../upstream
+    with:
+      - toml:
+          auto-merge: Cargo.toml
+          array_mode: append
+- markdown:
+    auto-merge: README.md
+    append: true
+"
+        );
+        let lines: Vec<usize> = migration.changes.iter().map(|c| c.line).collect();
+        assert_eq!(lines, vec![4, 10]);
+        crate::config::parse(&migration.content).unwrap();
+    }
+
+    #[test]
+    fn test_flow_mapping_append() {
+        let migration = migrate("- yaml: {source: a.yaml, dest: b.yaml, append: true}\n");
+        assert_eq!(
+            migration.content,
+            "- yaml: {source: a.yaml, dest: b.yaml, array_mode: append}\n"
+        );
+    }
+
+    #[test]
+    fn test_scalar_pattern_becomes_list() {
+        let content = "\
+- include: \"src/**\" # sources
+- exclude: '*.tmp'
+- template:
+    - \"*.tpl\"
+";
+        let migration = migrate(content);
+        assert_eq!(
+            migration.content,
+            "\
+- include: [\"src/**\"] # sources
+- exclude: ['*.tmp']
+- template:
+    - \"*.tpl\"
+"
+        );
+        assert_eq!(migration.changes.len(), 2);
+        crate::config::parse(&migration.content).unwrap();
+    }
+
+    #[test]
+    fn test_block_scalars_are_left_alone() {
+        let content = "\
+- template-vars:
+    notes: |
+      - yaml:
+          append: true
+- yaml:
+    auto-merge: a.yaml
+";
+        assert!(!migrate(content).is_needed());
+    }
+
+    #[test]
+    fn test_split_comment_respects_quotes() {
+        assert_eq!(split_comment("\"a # b\" # c"), ("\"a # b\"", " # c"));
+        assert_eq!(split_comment("a#b"), ("a#b", ""));
+    }
+
+    #[test]
+    fn test_forms_round_trip_with_comments() {
+        let list = "\
+# Shared CI setup
+- repo:
+    url: https://github.com/example/repo
+    ref: v1.0.0 # pinned
+
+- template-vars:
+    notes: |
+      kept as is
+";
+        let document = to_document(list).unwrap();
+        assert_eq!(
+            document,
+            "\
+# Shared CI setup
+apiVersion: common-repo/v1
+operations:
+  - repo:
+      url: https://github.com/example/repo
+      ref: v1.0.0 # pinned
+
+  - template-vars:
+      notes: |
+        kept as is
+"
+        );
+        assert_eq!(
+            crate::config::parse(&document).unwrap(),
+            crate::config::parse(list).unwrap()
+        );
+        assert_eq!(to_list(&document).unwrap(), list);
+        assert_eq!(to_document(&document).unwrap(), document);
+        assert_eq!(to_list(list).unwrap(), list);
+    }
+
+    #[test]
+    fn test_to_list_reads_unindented_and_flow_operations() {
+        let document = "apiVersion: common-repo/v1\noperations:\n- include: ['**']\n";
+        assert_eq!(to_list(document).unwrap(), "- include: ['**']\n");
+
+        let document = "operations: [{include: ['**']}]\napiVersion: common-repo/v1\n";
+        assert_eq!(to_list(document).unwrap(), "[{include: ['**']}]\n");
+    }
+
+    #[test]
+    fn test_convert_records_a_change() {
+        let migration = convert(migrate("- include: ['**']\n"), Form::Document).unwrap();
+        assert_eq!(Form::of(&migration.content), Form::Document);
+        assert_eq!(migration.changes.len(), 1);
+
+        let unchanged = convert(migrate("- include: ['**']\n"), Form::List).unwrap();
+        assert!(!unchanged.is_needed());
+    }
+}
@@ -0,0 +1,352 @@
+//! # Apply Notifications
+//!
+//! Webhooks configured in the user configuration (see
+//! [`crate::user_config`]) are sent a report at the end of every `apply`
+//! that writes, so that platform teams can follow propagation across a
+//! fleet of repositories without scraping CI logs.
+//!
+//! The report says whether the apply succeeded, where and by whom it ran,
+//! the commit of every upstream, the managed files it changed, how many
+//! warnings it had, and the error it failed with. A webhook is sent it as
+//! JSON ([`WebhookFormat::Json`]), or as a message Slack and compatible
+//! chat services accept as an incoming webhook ([`WebhookFormat::Slack`]),
+//! for every apply or only for those that changed files or failed (see
+//! [`NotifyOn`]).
+//!
+//! Reports are POSTed with `curl`, like [`crate::http`] downloads, with a
+//! short timeout. A webhook that cannot be reached is reported as a
+//! warning and never fails the apply. Its URL is often a secret, so it is
+//! not printed, only its host; `url-env` keeps it out of the file too.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::reporting::history::UpstreamCommit;
+
+/// How long a webhook may take to answer, in seconds.
+const TIMEOUT_SECS: &str = "10";
+
+/// Most files listed in a Slack message; the rest are counted.
+const SLACK_MAX_FILES: usize = 20;
+
+/// Where to send reports, and which.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Webhook {
+    /// The URL to POST reports to.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The environment variable holding the URL, in place of `url`.
+    #[serde(default)]
+    pub url_env: Option<String>,
+    /// The body of the reports.
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Which applies to report.
+    #[serde(default)]
+    pub on: NotifyOn,
+}
+
+/// The body of the reports sent to a webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookFormat {
+    /// The [`ApplyReport`] as JSON.
+    #[default]
+    Json,
+    /// A Slack incoming-webhook message, `{"text": ...}`.
+    Slack,
+}
+
+/// Which applies are reported to a webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyOn {
+    /// Every apply.
+    #[default]
+    Always,
+    /// Applies that changed managed files, and failures.
+    Changes,
+    /// Failures only.
+    Failures,
+}
+
+/// How an apply ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplyStatus {
+    /// It wrote its output.
+    Success,
+    /// It failed with an error.
+    Failure,
+}
+
+/// What a webhook is told about an apply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApplyReport {
+    /// How the apply ended.
+    pub status: ApplyStatus,
+    /// The output directory.
+    pub directory: PathBuf,
+    /// When the apply finished, in RFC 3339 format, UTC.
+    pub time: String,
+    /// Who ran it, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// The named pipeline applied, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    /// Every upstream fetched, in the order they were fetched.
+    pub upstreams: Vec<UpstreamCommit>,
+    /// The managed files added, changed or removed, sorted by path.
+    pub files: Vec<PathBuf>,
+    /// How many warnings the pipeline reported.
+    pub warnings: usize,
+    /// The error the apply failed with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Webhook {
+    /// Check that the webhook names exactly one of `url` and `url-env`.
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.url, &self.url_env) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err("a webhook needs exactly one of url and url-env".to_string()),
+        }
+    }
+
+    /// The URL to send reports to, or `None` when `url-env` names an
+    /// environment variable that is not set.
+    pub fn resolve_url(&self) -> Option<String> {
+        match (&self.url, &self.url_env) {
+            (Some(url), _) => Some(url.clone()),
+            (None, Some(name)) => std::env::var(name).ok().filter(|url| !url.is_empty()),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether `report` is reported to this webhook.
+    pub fn wants(&self, report: &ApplyReport) -> bool {
+        match self.on {
+            NotifyOn::Always => true,
+            NotifyOn::Changes => report.status == ApplyStatus::Failure || !report.files.is_empty(),
+            NotifyOn::Failures => report.status == ApplyStatus::Failure,
+        }
+    }
+}
+
+impl ApplyReport {
+    /// The body of the report for a webhook of `format`.
+    pub fn body(&self, format: WebhookFormat) -> String {
+        match format {
+            WebhookFormat::Json => serde_json::to_string(self),
+            WebhookFormat::Slack => serde_json::to_string(&serde_json::json!({
+                "text": self.slack_text()
+            })),
+        }
+        .expect("a report is serializable")
+    }
+
+    /// The report as a Slack message in mrkdwn.
+    fn slack_text(&self) -> String {
+        let by = self
+            .user
+            .as_ref()
+            .map(|user| format!(" by {}", user))
+            .unwrap_or_default();
+        let mut text = match self.status {
+            ApplyStatus::Success => format!(
+                ":white_check_mark: common-repo apply in `{}`{}: {} file(s) changed",
+                self.directory.display(),
+                by,
+                self.files.len()
+            ),
+            ApplyStatus::Failure => format!(
+                ":x: common-repo apply in `{}`{} failed",
+                self.directory.display(),
+                by
+            ),
+        };
+        if let Some(pipeline) = &self.pipeline {
+            text.push_str(&format!(" (pipeline `{}`)", pipeline));
+        }
+        if self.warnings > 0 {
+            text.push_str(&format!(", {} warning(s)", self.warnings));
+        }
+        if let Some(error) = &self.error {
+            text.push_str(&format!("\n```{}```", error));
+        }
+        for upstream in &self.upstreams {
+            text.push_str(&format!("\n• {} @ {}", upstream.url, upstream.ref_));
+            if let Some(commit) = &upstream.commit {
+                text.push_str(&format!(" (`{}`)", commit.get(..12).unwrap_or(commit)));
+            }
+        }
+        for file in self.files.iter().take(SLACK_MAX_FILES) {
+            text.push_str(&format!("\n  `{}`", file.display()));
+        }
+        if self.files.len() > SLACK_MAX_FILES {
+            text.push_str(&format!(
+                "\n  …and {} more",
+                self.files.len() - SLACK_MAX_FILES
+            ));
+        }
+        text
+    }
+}
+
+/// Send `report` to each of `webhooks` that wants it, warning about those
+/// that cannot be reached.
+pub fn send(webhooks: &[Webhook], report: &ApplyReport) {
+    for webhook in webhooks.iter().filter(|webhook| webhook.wants(report)) {
+        let Some(url) = webhook.resolve_url() else {
+            log::debug!(
+                "webhook skipped: ${} is not set",
+                webhook.url_env.as_deref().unwrap_or_default()
+            );
+            continue;
+        };
+        if let Err(error) = post(&url, report.body(webhook.format).as_bytes()) {
+            log::warn!(
+                "{}",
+                Message::WebhookFailed {
+                    host: &crate::http::host(&url),
+                    error: &error,
+                }
+            );
+        }
+    }
+}
+
+/// POST `body` as JSON to `url`. The error never contains the URL.
+fn post(url: &str, body: &[u8]) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--proto",
+            "=https,http",
+            "--max-time",
+            TIMEOUT_SECS,
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            "--output",
+            if cfg!(windows) { "NUL" } else { "/dev/null" },
+            "--",
+            url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(body)
+            .map_err(|e| format!("could not send the report: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("could not run curl: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(stderr
+        .trim()
+        .trim_start_matches("curl: ")
+        .replace(url, "<url>"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(status: ApplyStatus, files: &[&str]) -> ApplyReport {
+        ApplyReport {
+            status,
+            directory: PathBuf::from("/src/billing"),
+            time: "2026-10-17T09:12:44Z".to_string(),
+            user: Some("Ada <ada@example.com>".to_string()),
+            pipeline: None,
+            upstreams: vec![UpstreamCommit {
+                url: "https://github.com/acme/ci".to_string(),
+                ref_: "v1".to_string(),
+                commit: Some("9c1e2f3a4b5c6d7e".to_string()),
+            }],
+            files: files.iter().map(PathBuf::from).collect(),
+            warnings: 0,
+            error: (status == ApplyStatus::Failure).then(|| "boom".to_string()),
+        }
+    }
+
+    fn webhook(on: NotifyOn) -> Webhook {
+        Webhook {
+            url: Some("https://example.com/hook".to_string()),
+            url_env: None,
+            format: WebhookFormat::Json,
+            on,
+        }
+    }
+
+    #[test]
+    fn test_webhook_wants() {
+        let changed = report(ApplyStatus::Success, &["ci.yml"]);
+        let unchanged = report(ApplyStatus::Success, &[]);
+        let failed = report(ApplyStatus::Failure, &[]);
+        assert!(webhook(NotifyOn::Always).wants(&unchanged));
+        assert!(webhook(NotifyOn::Changes).wants(&changed));
+        assert!(!webhook(NotifyOn::Changes).wants(&unchanged));
+        assert!(webhook(NotifyOn::Changes).wants(&failed));
+        assert!(!webhook(NotifyOn::Failures).wants(&changed));
+        assert!(webhook(NotifyOn::Failures).wants(&failed));
+    }
+
+    #[test]
+    fn test_report_bodies() {
+        let json: serde_json::Value = serde_json::from_str(
+            &report(ApplyStatus::Success, &["ci.yml"]).body(WebhookFormat::Json),
+        )
+        .unwrap();
+        assert_eq!(json["status"], "success");
+        assert_eq!(json["files"], serde_json::json!(["ci.yml"]));
+        assert_eq!(json["upstreams"][0]["commit"], "9c1e2f3a4b5c6d7e");
+        assert!(json.get("error").is_none());
+
+        let slack: serde_json::Value =
+            serde_json::from_str(&report(ApplyStatus::Failure, &[]).body(WebhookFormat::Slack))
+                .unwrap();
+        let text = slack["text"].as_str().unwrap();
+        assert!(
+            text.starts_with(":x: common-repo apply in `/src/billing` by Ada"),
+            "{}",
+            text
+        );
+        assert!(text.contains("```boom```"), "{}", text);
+        assert!(
+            text.contains("https://github.com/acme/ci @ v1 (`9c1e2f3a4b5c`)"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_webhook_validate() {
+        assert!(webhook(NotifyOn::Always).validate().is_ok());
+        let mut both = webhook(NotifyOn::Always);
+        both.url_env = Some("HOOK".to_string());
+        assert!(both.validate().is_err());
+        both.url = None;
+        assert!(both.validate().is_ok());
+        both.url_env = None;
+        assert!(both.validate().is_err());
+    }
+}
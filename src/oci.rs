@@ -0,0 +1,322 @@
+//! # OCI Artifact Sources
+//!
+//! Fetches shared files published to a container registry as an OCI artifact,
+//! for `repo:` URLs of the form `oci://ghcr.io/org/common-config`. Like
+//! [`crate::git`], this is a thin wrapper around a system tool, here the
+//! [ORAS](https://oras.land) CLI, so registry logins made with `oras login`
+//! or `docker login` apply unchanged.
+//!
+//! ## References
+//!
+//! The `ref` of an OCI repo is a tag (`1.2.0`), a digest
+//! (`sha256:<hex>`), or both (`1.2.0@sha256:<hex>`). It can also be written
+//! at the end of the URL, as in `oci://ghcr.io/org/common-config:1.2.0`; see
+//! [`split_reference`].
+//!
+//! ## Integrity
+//!
+//! When a digest is given the artifact is pulled by digest, so the registry
+//! cannot serve different content for it. With a tag as well, the tag is
+//! first resolved and must still point at that digest; a moved tag fails
+//! with [`Error::DigestMismatch`] instead of pulling content that was not
+//! pinned.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::oci;
+//!
+//! assert_eq!(
+//!     oci::split_reference("oci://ghcr.io/org/common-config:1.2.0"),
+//!     Some(("oci://ghcr.io/org/common-config", "1.2.0"))
+//! );
+//!
+//! let reference = oci::Reference::parse("oci://localhost:5000/org/config", "1.2.0").unwrap();
+//! assert_eq!(reference.repository, "localhost:5000/org/config");
+//! assert_eq!(reference.tag.as_deref(), Some("1.2.0"));
+//! assert_eq!(reference.digest, None);
+//! ```
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{Error, Result};
+
+/// URL scheme that selects an OCI artifact source.
+pub const SCHEME: &str = "oci://";
+
+/// Returns true when `url` names an OCI artifact rather than a git
+/// repository.
+pub fn is_oci_url(url: &str) -> bool {
+    url.starts_with(SCHEME)
+}
+
+/// Split a tag or digest written at the end of an OCI URL off the URL.
+///
+/// Returns `None` for URLs that are not OCI URLs or carry no reference. Only
+/// the last path segment is inspected, so a registry port such as
+/// `localhost:5000` is not mistaken for a tag.
+pub fn split_reference(url: &str) -> Option<(&str, &str)> {
+    let rest = url.strip_prefix(SCHEME)?;
+    let name_start = SCHEME.len() + rest.rfind('/')? + 1;
+    let name = &url[name_start..];
+    let split = match (name.find(':'), name.find('@')) {
+        (Some(colon), Some(at)) => colon.min(at),
+        (Some(colon), None) => colon,
+        (None, Some(at)) => at,
+        (None, None) => return None,
+    };
+    let (base, reference) = url.split_at(name_start + split);
+    Some((base, &reference[1..]))
+}
+
+/// A parsed OCI artifact reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Registry host and repository path, without the scheme.
+    pub repository: String,
+    /// Tag to resolve, if any.
+    pub tag: Option<String>,
+    /// Pinned manifest digest (`<algorithm>:<hex>`), if any.
+    pub digest: Option<String>,
+}
+
+impl Reference {
+    /// Parse an `oci://` URL and its `ref`.
+    ///
+    /// `ref_name` is a tag, a digest, or `<tag>@<digest>`.
+    pub fn parse(url: &str, ref_name: &str) -> Result<Self> {
+        let invalid = |message: String| Error::ConfigParse {
+            message,
+            hint: Some(
+                "Use 'url: oci://<registry>/<repository>' with 'ref: <tag>', \
+                 'ref: sha256:<digest>' or 'ref: <tag>@sha256:<digest>'"
+                    .to_string(),
+            ),
+        };
+
+        let repository = url
+            .strip_prefix(SCHEME)
+            .filter(|rest| rest.contains('/') && !rest.ends_with('/'))
+            .ok_or_else(|| invalid(format!("Invalid OCI repository URL '{}'", url)))?;
+        if split_reference(url).is_some() {
+            return Err(invalid(format!(
+                "OCI URL '{}' has a reference in it as well as a ref",
+                url
+            )));
+        }
+
+        let (tag, digest) = match ref_name.split_once('@') {
+            Some((tag, digest)) => (Some(tag), Some(digest)),
+            None if is_digest(ref_name) => (None, Some(ref_name)),
+            None => (Some(ref_name), None),
+        };
+        if let Some(tag) = tag.filter(|tag| !is_tag(tag)) {
+            return Err(invalid(format!("Invalid OCI tag '{}' for {}", tag, url)));
+        }
+        if let Some(digest) = digest.filter(|digest| !is_digest(digest)) {
+            return Err(invalid(format!(
+                "Invalid OCI digest '{}' for {}",
+                digest, url
+            )));
+        }
+
+        Ok(Reference {
+            repository: repository.to_string(),
+            tag: tag.map(str::to_string),
+            digest: digest.map(|digest| digest.to_ascii_lowercase()),
+        })
+    }
+
+    /// The reference to pull: by digest when one is pinned, by tag
+    /// otherwise.
+    pub fn pull_target(&self) -> String {
+        match (&self.digest, &self.tag) {
+            (Some(digest), _) => format!("{}@{}", self.repository, digest),
+            (None, Some(tag)) => format!("{}:{}", self.repository, tag),
+            (None, None) => self.repository.clone(),
+        }
+    }
+}
+
+/// Whether `tag` is a valid OCI tag.
+fn is_tag(tag: &str) -> bool {
+    let mut chars = tag.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && tag.len() <= 128
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Whether `digest` is a `sha256` or `sha512` digest.
+fn is_digest(digest: &str) -> bool {
+    let Some((algorithm, hex)) = digest.split_once(':') else {
+        return false;
+    };
+    let length = match algorithm {
+        "sha256" => 64,
+        "sha512" => 128,
+        _ => return false,
+    };
+    hex.len() == length && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Run `oras` with `args`, returning its standard output.
+fn oras(reference: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("oras")
+        .args(args)
+        .output()
+        .map_err(|e| Error::OciPull {
+            reference: reference.to_string(),
+            message: e.to_string(),
+            hint: Some("Ensure the oras CLI is installed and accessible".to_string()),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim().trim_start_matches("Error: ").to_string();
+        let hint = if message.contains("401") || message.contains("unauthorized") {
+            Some("Log in to the registry with 'oras login'".to_string())
+        } else if message.contains("not found") {
+            Some("Verify the repository URL and ref (tag or digest) are correct".to_string())
+        } else {
+            None
+        };
+        return Err(Error::OciPull {
+            reference: reference.to_string(),
+            message,
+            hint,
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Pull the artifact at `url` and `ref_name` into `target_dir`.
+///
+/// When the reference has both a tag and a digest, the tag must resolve to
+/// that digest.
+pub fn pull(url: &str, ref_name: &str, target_dir: &Path) -> Result<()> {
+    let reference = Reference::parse(url, ref_name)?;
+
+    if let (Some(tag), Some(expected)) = (&reference.tag, &reference.digest) {
+        let tagged = format!("{}:{}", reference.repository, tag);
+        let actual = oras(&tagged, &["resolve", &tagged])?.trim().to_string();
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::DigestMismatch {
+                reference: tagged,
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    if target_dir.exists() {
+        fs::remove_dir_all(target_dir)?;
+    }
+    fs::create_dir_all(target_dir)?;
+
+    let target = reference.pull_target();
+    let output_dir = target_dir.to_string_lossy();
+    oras(&target, &["pull", "--output", &output_dir, &target])?;
+    Ok(())
+}
+
+/// The manifest digest the artifact at `url` and `ref_name` points to.
+///
+/// A pinned digest is returned as is; a tag is resolved with the registry.
+pub fn resolve(url: &str, ref_name: &str) -> Result<String> {
+    let reference = Reference::parse(url, ref_name)?;
+    if let Some(digest) = reference.digest {
+        return Ok(digest);
+    }
+    let target = reference.pull_target();
+    Ok(oras(&target, &["resolve", &target])?
+        .trim()
+        .to_ascii_lowercase())
+}
+
+/// List the tags of the repository at `url`.
+pub fn list_tags(url: &str) -> Result<Vec<String>> {
+    let base = split_reference(url).map_or(url, |(base, _)| base);
+    let repository = base.strip_prefix(SCHEME).unwrap_or(base);
+    let output = oras(repository, &["repo", "tags", repository])?;
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:6c3c624b58dbbcd3c0dd82b4c53f04194d1247c6eebdaab7c610cf7d66709b3b";
+
+    #[test]
+    fn test_split_reference() {
+        assert_eq!(
+            split_reference("oci://ghcr.io/org/config:1.2.0"),
+            Some(("oci://ghcr.io/org/config", "1.2.0"))
+        );
+        let pinned = format!("oci://ghcr.io/org/config:1.2.0@{}", DIGEST);
+        assert_eq!(
+            split_reference(&pinned),
+            Some((
+                "oci://ghcr.io/org/config",
+                &pinned["oci://ghcr.io/org/config:".len()..]
+            ))
+        );
+        let by_digest = format!("oci://localhost:5000/config@{}", DIGEST);
+        assert_eq!(
+            split_reference(&by_digest),
+            Some(("oci://localhost:5000/config", DIGEST))
+        );
+        assert_eq!(split_reference("oci://localhost:5000/org/config"), None);
+        assert_eq!(split_reference("https://github.com/org/config"), None);
+    }
+
+    #[test]
+    fn test_parse_reference() {
+        let reference =
+            Reference::parse("oci://ghcr.io/org/config", &format!("1.2.0@{}", DIGEST)).unwrap();
+        assert_eq!(reference.tag.as_deref(), Some("1.2.0"));
+        assert_eq!(reference.digest.as_deref(), Some(DIGEST));
+        assert_eq!(
+            reference.pull_target(),
+            format!("ghcr.io/org/config@{}", DIGEST)
+        );
+
+        let reference = Reference::parse("oci://ghcr.io/org/config", DIGEST).unwrap();
+        assert_eq!(reference.tag, None);
+
+        let reference = Reference::parse("oci://ghcr.io/org/config", "v1").unwrap();
+        assert_eq!(reference.pull_target(), "ghcr.io/org/config:v1");
+    }
+
+    #[test]
+    fn test_parse_reference_rejects_invalid() {
+        for (url, ref_name) in [
+            ("oci://ghcr.io", "1.0"),
+            ("oci://ghcr.io/org/config:1.0", "1.0"),
+            ("oci://ghcr.io/org/config", "-bad"),
+            ("oci://ghcr.io/org/config", "1.0@sha256:abc"),
+            (
+                "oci://ghcr.io/org/config",
+                "md5:0123456789abcdef0123456789abcdef",
+            ),
+        ] {
+            let err = Reference::parse(url, ref_name).unwrap_err();
+            assert!(
+                matches!(err, Error::ConfigParse { .. }),
+                "{} {}",
+                url,
+                ref_name
+            );
+        }
+    }
+}
@@ -26,11 +26,25 @@
 //! - **`template_vars`**: Collects variables for use in template processing.
 //! - **`tools`**: Validates that required command-line tools are installed and
 //!   meet version constraints.
-
-use crate::config::{ExcludeOp, IncludeOp, Operation, RenameOp, RepoOp};
+//! - **`file`**: Downloads a single file over HTTPS, optionally pinned to a
+//!   SHA-256 digest.
+//! - **`patch`**: Applies a consumer-side unified diff to a file in the
+//!   composed output.
+//! - **`header`**: Prepends a license or copyright header to files, as a
+//!   comment in the syntax of each file's type.
+//! - **`format`**: Normalizes indentation, line endings, final newlines and
+//!   trailing whitespace of propagated files to the output's `.editorconfig`.
+//! - **`yaml_format`**: Rewrites YAML files in a canonical layout, with sorted
+//!   keys, a fixed indentation width and a quote style.
+//! - **`assert`**: Checks that the composed output has, lacks or contains
+//!   what a configuration expects of it.
+//! - **`links`**: Rewrites relative Markdown links of included and renamed
+//!   files, following renames or pointing them at the upstream.
+
+use crate::config::{ExcludeOp, IncludeOp, LinkRewrite, Operation, RenameOp, RepoOp};
 use crate::error::Result;
 use crate::filesystem::MemoryFS;
-use crate::path::regex_rename;
+use crate::path::{regex_rename_with, PatternSet};
 use crate::repository::RepositoryManager;
 use log::trace;
 use std::path::Path;
@@ -38,6 +52,8 @@ use std::path::Path;
 /// Include operator - adds files matching glob patterns to the filesystem
 pub(crate) mod include {
     use super::*;
+    use crate::filesystem::FileOrigin;
+    use std::sync::Arc;
 
     /// Applies the `include` operation to a filesystem.
     ///
@@ -50,7 +66,10 @@ pub(crate) mod include {
     /// by multiple `include` operations, the last one wins because
     /// `MemoryFS::add_file` unconditionally replaces any prior entry — so the
     /// final tag at any path is the tag from the last `include::apply` call
-    /// that produced it.
+    /// that produced it. Its `origin` is set to the path it was included
+    /// from. With `rewrite-links: upstream`, Markdown files are tagged so
+    /// their links are pointed at the upstream once it is known (see
+    /// [`super::links::point_at_upstream`]).
     ///
     /// # Arguments
     ///
@@ -61,31 +80,44 @@ pub(crate) mod include {
     /// # Returns
     /// Result indicating success or failure
     pub(crate) fn apply(op: &IncludeOp, source: &MemoryFS, target: &mut MemoryFS) -> Result<()> {
-        for pattern in &op.patterns {
-            let matching_files = source.list_files_glob(pattern)?;
-            trace!(
-                "include: pattern={} matched {} files",
-                pattern,
-                matching_files.len(),
-            );
+        let patterns = PatternSet::cached(&op.patterns)?;
+        let matching_files = source.list_files_matching(&patterns);
+        trace!(
+            "include: patterns={:?} matched {} files",
+            op.patterns,
+            matching_files.len(),
+        );
 
-            for path in matching_files {
-                if let Some(file) = source.get_file(&path) {
-                    let mut file = file.clone();
-                    file.if_exists = op.if_exists;
-                    let already = target.exists(&path);
-                    trace!(
-                        "include: + {} ({} bytes){}",
-                        path.display(),
-                        file.content.len(),
-                        if already {
-                            " [overwrites existing]"
-                        } else {
-                            ""
-                        },
-                    );
-                    target.add_file(&path, file)?;
+        for path in matching_files {
+            if let Some(file) = source.get_file(&path) {
+                let mut file = file.clone();
+                file.if_exists = op.if_exists;
+                file.origin = Some(Arc::new(FileOrigin {
+                    source: path.clone(),
+                    ..FileOrigin::default()
+                }));
+                if op.rewrite_links == Some(LinkRewrite::Upstream) && links::is_markdown(&path) {
+                    file.upstream_links = true;
                 }
+                let already = target.exists(&path);
+                trace!(
+                    "include: + {} ({} bytes){}",
+                    path.display(),
+                    file.content.len(),
+                    if already {
+                        " [overwrites existing]"
+                    } else {
+                        ""
+                    },
+                );
+                target.add_file(&path, file)?;
+            }
+        }
+
+        for path in source.list_directories_matching(&patterns) {
+            if let Some(dir) = source.get_directory(&path) {
+                trace!("include: + {}/ (directory)", path.display());
+                target.add_directory(&path, dir.clone())?;
             }
         }
 
@@ -110,18 +142,22 @@ pub(crate) mod exclude {
     /// # Returns
     /// Result indicating success or failure
     pub(crate) fn apply(op: &ExcludeOp, target: &mut MemoryFS) -> Result<()> {
-        for pattern in &op.patterns {
-            let matching_files = target.list_files_glob(pattern)?;
-            trace!(
-                "exclude: pattern={} matched {} files",
-                pattern,
-                matching_files.len(),
-            );
+        let patterns = PatternSet::cached(&op.patterns)?;
+        let matching_files = target.list_files_matching(&patterns);
+        trace!(
+            "exclude: patterns={:?} matched {} files",
+            op.patterns,
+            matching_files.len(),
+        );
 
-            for path in matching_files {
-                trace!("exclude: - {}", path.display());
-                target.remove_file(&path)?;
-            }
+        for path in matching_files {
+            trace!("exclude: - {}", path.display());
+            target.remove_file(&path)?;
+        }
+
+        for path in target.list_directories_matching(&patterns) {
+            trace!("exclude: - {}/ (directory)", path.display());
+            target.remove_directory(&path)?;
         }
 
         Ok(())
@@ -131,6 +167,9 @@ pub(crate) mod exclude {
 /// Rename operator - renames files using regex patterns
 pub(crate) mod rename {
     use super::*;
+    use crate::error::Error;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
 
     /// Applies the `rename` operation to a filesystem.
     ///
@@ -138,6 +177,11 @@ pub(crate) mod rename {
     /// of regex-based mappings. Each mapping defines a `from` pattern and a `to`
     /// replacement string, which can include capture groups.
     ///
+    /// With `rewrite-links: relative`, the relative Markdown links of the
+    /// filesystem are then updated to follow the renames; with
+    /// `rewrite-links: upstream`, renamed Markdown files are tagged so their
+    /// links are pointed at the upstream.
+    ///
     /// # Arguments
     ///
     /// * `op` - The rename operation configuration
@@ -146,10 +190,13 @@ pub(crate) mod rename {
     /// # Returns
     /// Result indicating success or failure
     pub(crate) fn apply(op: &RenameOp, target: &mut MemoryFS) -> Result<()> {
+        // Each renamed path with the path it had before this operation
+        let mut previous: HashMap<PathBuf, PathBuf> = HashMap::new();
         for mapping in &op.mappings {
             let current_files: Vec<_> = target.list_files();
             let from_pattern = &mapping.from;
             let to_pattern = &mapping.to;
+            let from_regex = regex::Regex::new(from_pattern).map_err(Error::Regex)?;
 
             let mut files_to_rename = Vec::new();
 
@@ -157,7 +204,7 @@ pub(crate) mod rename {
                 let path_str = path.to_string_lossy();
 
                 // Check if the file path matches the regex pattern
-                if let Some(new_name) = regex_rename(from_pattern, to_pattern, &path_str)? {
+                if let Some(new_name) = regex_rename_with(&from_regex, to_pattern, &path_str) {
                     // Only rename if the name actually changed
                     if new_name != path_str {
                         files_to_rename.push((path.clone(), Path::new(&new_name).to_path_buf()));
@@ -165,6 +212,8 @@ pub(crate) mod rename {
                 }
             }
 
+            crate::reporting::renames::record(mapping, &files_to_rename);
+
             // Perform the renames
             for (old_path, new_path) in files_to_rename {
                 trace!(
@@ -175,9 +224,45 @@ pub(crate) mod rename {
                     to_pattern,
                 );
                 target.rename_file(&old_path, &new_path)?;
+                let before = previous.remove(&old_path).unwrap_or(old_path);
+                previous.insert(new_path, before);
+            }
+
+            let mut dirs_to_rename = Vec::new();
+            for path in target.list_directories() {
+                let path_str = path.to_string_lossy();
+                if let Some(new_name) = regex_rename_with(&from_regex, to_pattern, &path_str) {
+                    if new_name != path_str {
+                        dirs_to_rename.push((path.clone(), Path::new(&new_name).to_path_buf()));
+                    }
+                }
+            }
+            for (old_path, new_path) in dirs_to_rename {
+                trace!(
+                    "rename: {}/ -> {}/ (directory)",
+                    old_path.display(),
+                    new_path.display(),
+                );
+                target.rename_directory(&old_path, &new_path)?;
             }
         }
 
+        let moves: Vec<(PathBuf, PathBuf)> = previous
+            .into_iter()
+            .filter(|(new_path, old_path)| new_path != old_path)
+            .map(|(new_path, old_path)| (old_path, new_path))
+            .collect();
+        match op.rewrite_links {
+            Some(LinkRewrite::Relative) => links::follow_renames(target, &moves),
+            Some(LinkRewrite::Upstream) => {
+                for (_, new_path) in &moves {
+                    if let Some(file) = target.get_file_mut(new_path) {
+                        file.upstream_links |= links::is_markdown(new_path);
+                    }
+                }
+            }
+            None => {}
+        }
         Ok(())
     }
 }
@@ -240,11 +325,13 @@ pub(crate) mod repo {
                     // include operator which copies files from source to target.
 
                     // Collect all files that match any of the include patterns
-                    let mut files_to_keep = std::collections::HashSet::new();
-                    for pattern in &include.patterns {
-                        let matching_files = fs.list_files_glob(pattern)?;
-                        files_to_keep.extend(matching_files);
-                    }
+                    let patterns = PatternSet::cached(&include.patterns)?;
+                    let files_to_keep: std::collections::HashSet<_> =
+                        fs.list_files_matching(&patterns).into_iter().collect();
+                    let dirs_to_keep: std::collections::HashSet<_> = fs
+                        .list_directories_matching(&patterns)
+                        .into_iter()
+                        .collect();
 
                     // Remove all files that don't match any include pattern
                     let all_files = fs.list_files();
@@ -253,6 +340,11 @@ pub(crate) mod repo {
                             fs.remove_file(&path)?;
                         }
                     }
+                    for path in fs.list_directories() {
+                        if !dirs_to_keep.contains(&path) {
+                            fs.remove_directory(&path)?;
+                        }
+                    }
                 }
                 Operation::Exclude { exclude } => {
                     super::exclude::apply(exclude, fs)?;
@@ -297,6 +389,39 @@ pub(crate) mod repo {
                         message: "Self operations not supported in 'with:' clauses".to_string(),
                     });
                 }
+                Operation::File { file } => {
+                    super::file::apply(file, fs)?;
+                }
+                Operation::Header { header } => {
+                    super::header::apply(header, fs)?;
+                }
+                Operation::YamlFormat { yaml_format } => {
+                    super::yaml_format::apply(yaml_format, fs)?;
+                }
+                Operation::Extract { extract } => {
+                    crate::merge::apply_extract_operation(fs, extract)?;
+                }
+                // Checked against the consumer's composed output
+                Operation::Assert { .. } => {}
+                Operation::Patch { .. } => {
+                    return Err(crate::error::Error::Operator {
+                        operator: "patch".to_string(),
+                        message: "Patch operations not supported in 'with:' clauses".to_string(),
+                    });
+                }
+                Operation::Vendor { .. } => {
+                    return Err(crate::error::Error::Operator {
+                        operator: "vendor".to_string(),
+                        message: "Vendor operations not supported in 'with:' clauses".to_string(),
+                    });
+                }
+                Operation::Format { .. } => {
+                    return Err(crate::error::Error::Operator {
+                        operator: "format".to_string(),
+                        message: "Format operations not supported in 'with:' clauses".to_string(),
+                    });
+                }
+                Operation::Unknown(unknown) => unknown.report(),
             }
         }
 
@@ -330,6 +455,7 @@ mod tests {
             let op = IncludeOp {
                 patterns: vec!["*.rs".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
 
             include::apply(&op, &source, &mut target).unwrap();
@@ -356,6 +482,7 @@ mod tests {
             let op = IncludeOp {
                 patterns: vec!["src/*.rs".to_string(), "README.md".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
 
             include::apply(&op, &source, &mut target).unwrap();
@@ -364,6 +491,29 @@ mod tests {
             assert!(!target.exists("tests/test.rs"));
             assert!(target.exists("README.md"));
         }
+
+        #[test]
+        fn test_include_copies_matching_directories() {
+            use crate::filesystem::Directory;
+
+            let mut source = MemoryFS::new();
+            let mut target = MemoryFS::new();
+            source
+                .add_directory("db/migrations", Directory::new())
+                .unwrap();
+            source.add_directory("tmp", Directory::new()).unwrap();
+
+            let op = IncludeOp {
+                patterns: vec!["db/**".to_string()],
+                if_exists: IfExists::Overwrite,
+                rewrite_links: None,
+            };
+
+            include::apply(&op, &source, &mut target).unwrap();
+
+            assert!(target.has_directory("db/migrations"));
+            assert!(!target.has_directory("tmp"));
+        }
     }
 
     mod include_apply_tests {
@@ -380,6 +530,7 @@ mod tests {
             let op = IncludeOp {
                 patterns: vec!["foo.txt".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
             let mut target = MemoryFS::new();
             include::apply(&op, &source, &mut target).unwrap();
@@ -396,6 +547,7 @@ mod tests {
             let op = IncludeOp {
                 patterns: vec!["foo.txt".to_string()],
                 if_exists: IfExists::Preserve,
+                rewrite_links: None,
             };
             let mut target = MemoryFS::new();
             include::apply(&op, &source, &mut target).unwrap();
@@ -412,10 +564,12 @@ mod tests {
             let op_first = IncludeOp {
                 patterns: vec!["foo.txt".to_string()],
                 if_exists: IfExists::Preserve,
+                rewrite_links: None,
             };
             let op_second = IncludeOp {
                 patterns: vec!["foo.txt".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
             let mut target = MemoryFS::new();
             include::apply(&op_first, &source, &mut target).unwrap();
@@ -480,11 +634,47 @@ mod tests {
             assert!(target.exists("README.md"));
             assert!(!target.exists("CHANGELOG.md"));
         }
+
+        #[test]
+        fn test_exclude_removes_matching_directories() {
+            use crate::filesystem::Directory;
+
+            let mut target = MemoryFS::new();
+            target.add_directory("logs", Directory::new()).unwrap();
+            target.add_directory("keep", Directory::new()).unwrap();
+
+            let op = ExcludeOp {
+                patterns: vec!["logs".to_string()],
+            };
+
+            exclude::apply(&op, &mut target).unwrap();
+
+            assert!(!target.has_directory("logs"));
+            assert!(target.has_directory("keep"));
+        }
     }
 
     mod rename_tests {
         use super::*;
 
+        #[test]
+        fn test_rename_rejects_target_outside_root() {
+            let mut target = MemoryFS::new();
+            target.add_file_string("config.yml", "a: 1").unwrap();
+
+            let op = RenameOp {
+                mappings: vec![RenameMapping {
+                    from: r"^(.*)$".to_string(),
+                    to: "../../$1".to_string(),
+                }],
+                rewrite_links: None,
+            };
+
+            let err = rename::apply(&op, &mut target).unwrap_err();
+            assert!(matches!(err, crate::error::Error::Path { .. }));
+            assert!(target.exists("config.yml"));
+        }
+
         #[test]
         fn test_rename_simple_pattern() {
             let mut target = MemoryFS::new();
@@ -499,6 +689,7 @@ mod tests {
                     from: r"(\w+)\.rs".to_string(),
                     to: "$1.backup".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -522,6 +713,7 @@ mod tests {
                     from: r"(\w+)\.js".to_string(),
                     to: "$1.backup".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -544,6 +736,7 @@ mod tests {
                     from: r"(\w+)/(\w+)\.rs".to_string(),
                     to: "$2_$1.rs".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -570,6 +763,7 @@ mod tests {
                         to: "$1_js.js".to_string(),
                     },
                 ],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -596,6 +790,7 @@ mod tests {
                         to: "vendor/$1".to_string(),
                     },
                 ],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -632,6 +827,7 @@ mod tests {
                     from: r"^(.+)\.bak$".to_string(),
                     to: "$1".to_string(),
                 }],
+                rewrite_links: None,
             };
             rename::apply(&op, &mut fs).unwrap();
 
@@ -792,6 +988,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![], // No with clause
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -832,6 +1033,11 @@ mod tests {
                         patterns: vec!["*.rs".to_string()],
                     },
                 }],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -879,9 +1085,15 @@ mod tests {
                                 from: r"(\w+)\.js".to_string(),
                                 to: "$1_renamed.js".to_string(),
                             }],
+                            rewrite_links: None,
                         },
                     },
                 ],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -905,8 +1117,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             }];
 
             repo::apply_with_clause(&operations, &mut fs).unwrap();
@@ -950,6 +1164,7 @@ mod tests {
                         from: r"(\w+)\.rs".to_string(),
                         to: "$1_backup.rs".to_string(),
                     }],
+                    rewrite_links: None,
                 },
             }];
 
@@ -972,6 +1187,11 @@ mod tests {
                     path: None,
                     r#ref: Some("main".to_string()),
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             }];
 
@@ -1032,6 +1252,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: Some("uv".to_string()),
                 with: vec![], // No with clause
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -1076,6 +1301,11 @@ mod tests {
                         patterns: vec!["*test*".to_string()],
                     },
                 }],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -1108,6 +1338,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![], // No with clause
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             };
 
             // Apply the repo operation
@@ -1140,6 +1375,7 @@ mod tests {
             let operations = vec![Operation::Template {
                 template: crate::config::TemplateOp {
                     patterns: vec!["template.txt".to_string()],
+                    files: Default::default(),
                 },
             }];
 
@@ -1164,6 +1400,7 @@ mod tests {
                 Operation::TemplateVars {
                     template_vars: crate::config::TemplateVars {
                         vars: std::collections::HashMap::new(),
+                        secrets: Default::default(),
                     },
                 },
                 Operation::Yaml {
@@ -1249,8 +1486,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/*.rs".to_string(), "tests/*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             }];
 
             repo::apply_with_clause(&operations, &mut fs).unwrap();
@@ -1279,8 +1518,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/*".to_string(), "template.txt".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 // Then rename src/ to rust/
                 Operation::Rename {
@@ -1289,12 +1530,14 @@ mod tests {
                             from: r"src/(.*)".to_string(),
                             to: "rust/$1".to_string(),
                         }],
+                        rewrite_links: None,
                     },
                 },
                 // Mark template for processing
                 Operation::Template {
                     template: crate::config::TemplateOp {
                         patterns: vec!["template.txt".to_string()],
+                        files: Default::default(),
                     },
                 },
             ];
@@ -1325,8 +1568,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             }];
 
             repo::apply_with_clause(&operations, &mut fs).unwrap();
@@ -1347,8 +1592,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["**/*".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             }];
 
             repo::apply_with_clause(&operations, &mut fs).unwrap();
@@ -1380,6 +1627,7 @@ mod tests {
                         to: "renamed2.txt".to_string(),
                     },
                 ],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -1407,6 +1655,7 @@ mod tests {
                         to: "final.txt".to_string(),
                     },
                 ],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -1428,6 +1677,7 @@ mod tests {
                     from: r"[invalid".to_string(), // Invalid regex - unclosed bracket
                     to: "renamed.txt".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             let result = rename::apply(&op, &mut target);
@@ -1450,6 +1700,7 @@ mod tests {
                     from: "".to_string(),
                     to: "renamed.txt".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             // Empty pattern might match everything or nothing depending on regex behavior
@@ -1471,6 +1722,7 @@ mod tests {
                     from: r"nonexistent\.txt".to_string(),
                     to: "renamed.txt".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -1494,6 +1746,7 @@ mod tests {
                     from: r"old\.txt".to_string(),
                     to: "existing.txt".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -1504,7 +1757,7 @@ mod tests {
             // Content should be from old.txt (last write wins)
             let file = target.get_file("existing.txt").unwrap();
             assert_eq!(
-                String::from_utf8(file.content.clone()).unwrap(),
+                String::from_utf8(file.content.to_vec()).unwrap(),
                 "old content"
             );
         }
@@ -1525,6 +1778,7 @@ mod tests {
                     from: r"src/(\w+)\.rs".to_string(),
                     to: "rust/$1.rs".to_string(),
                 }],
+                rewrite_links: None,
             };
 
             rename::apply(&op, &mut target).unwrap();
@@ -1549,6 +1803,8 @@ pub(crate) mod template {
     /// This function iterates through the files in the `MemoryFS` that match the
     /// provided glob patterns. If a file's content contains the `__COMMON_REPO__`
     /// sentinel prefix, it is marked as a template for later processing.
+    /// Files declared in `files` are marked whatever their content; their
+    /// checksums are checked by `verify-upstream`, not here.
     ///
     /// # Arguments
     ///
@@ -1558,25 +1814,40 @@ pub(crate) mod template {
     /// # Returns
     /// Result indicating success or failure
     pub(crate) fn mark(op: &crate::config::TemplateOp, fs: &mut MemoryFS) -> Result<()> {
-        for pattern in &op.patterns {
-            let matching_files = fs.list_files_glob(pattern)?;
-
-            for path in matching_files {
-                if let Some(file) = fs.get_file_mut(&path) {
-                    // Check if the file contains template variables
-                    if let Ok(content) = String::from_utf8(file.content.clone()) {
-                        // Check for __COMMON_REPO__ sentinel prefix
-                        if content.contains("__COMMON_REPO__") {
-                            file.is_template = true;
-                        }
+        let patterns = PatternSet::cached(&op.patterns)?;
+        for path in fs.list_files_matching(&patterns) {
+            if let Some(file) = fs.get_file_mut(&path) {
+                // Check if the file contains template variables
+                if let Ok(content) = String::from_utf8(file.content.to_vec()) {
+                    // Check for __COMMON_REPO__ sentinel prefix
+                    if content.contains("__COMMON_REPO__") {
+                        file.is_template = true;
                     }
                 }
             }
         }
+        for path in op.files.keys() {
+            if let Some(file) = fs.get_file_mut(path) {
+                file.is_template = true;
+            }
+        }
 
         Ok(())
     }
 
+    /// A `__COMMON_REPO__VAR__` sentinel, capturing the variable name.
+    static SENTINEL: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+        regex::Regex::new(r"__COMMON_REPO__([A-Za-z_][A-Za-z0-9_]*?)__").unwrap()
+    });
+
+    /// The names of the variables `content` references, in order, with
+    /// repeats.
+    pub(crate) fn variables(content: &str) -> impl Iterator<Item = &str> {
+        SENTINEL
+            .captures_iter(content)
+            .map(|capture| capture.get(1).unwrap().as_str())
+    }
+
     /// Process templates with variable substitution
     ///
     /// This function finds all files that have been marked as templates and
@@ -1607,7 +1878,7 @@ pub(crate) mod template {
             if let Some(file) = fs.get_file_mut(&path) {
                 // Convert content to string for processing
                 let content =
-                    String::from_utf8(file.content.clone()).map_err(|e| Error::Template {
+                    String::from_utf8(file.content.to_vec()).map_err(|e| Error::Template {
                         message: format!(
                             "Invalid UTF-8 in template file {}: {}",
                             path.display(),
@@ -1620,8 +1891,9 @@ pub(crate) mod template {
                 let processed_content = substitute_variables(&content, vars)?;
 
                 // Update the file content
-                file.content = processed_content.into_bytes();
+                file.content = processed_content.into();
                 file.is_template = false; // Mark as processed
+                file.record("template");
             }
         }
 
@@ -1641,18 +1913,9 @@ pub(crate) mod template {
     /// # Returns
     /// Result containing processed content
     fn substitute_variables(content: &str, vars: &HashMap<String, String>) -> Result<String> {
-        use regex::Regex;
-
-        let re = Regex::new(r"__COMMON_REPO__([A-Za-z_][A-Za-z0-9_]*?)__").map_err(|e| {
-            Error::Template {
-                message: format!("Invalid regex pattern: {}", e),
-                variable: None,
-            }
-        })?;
-
         let mut result = content.to_string();
 
-        for capture in re.captures_iter(content) {
+        for capture in SENTINEL.captures_iter(content) {
             let var_name = capture.get(1).unwrap().as_str();
 
             let replacement = if let Some(value) = vars.get(var_name) {
@@ -1682,7 +1945,8 @@ pub(crate) mod template_vars {
     ///
     /// This function merges the variables defined in the `TemplateVars` operation
     /// into an existing `HashMap`. If a variable already exists in the context,
-    /// its value will be overwritten.
+    /// its value will be overwritten. Secret variables are read from their
+    /// sources, so this is only for the consumer's own configuration.
     ///
     /// # Arguments
     ///
@@ -1695,11 +1959,33 @@ pub(crate) mod template_vars {
         op: &crate::config::TemplateVars,
         context: &mut HashMap<String, String>,
     ) -> Result<()> {
+        collect_public(op, context)?;
+        for (key, source) in &op.secrets {
+            context.insert(key.clone(), crate::secrets::read(key, source)?);
+        }
+        Ok(())
+    }
+
+    /// Like [`collect`], but leaves out the secret variables, for upstream
+    /// configurations.
+    pub(crate) fn collect_public(
+        op: &crate::config::TemplateVars,
+        context: &mut HashMap<String, String>,
+    ) -> Result<()> {
+        check_names(op)?;
+        for (key, value) in &op.vars {
+            context.insert(key.clone(), value.clone());
+        }
+        Ok(())
+    }
+
+    /// Check the names of the operation's variables, secret ones included.
+    pub(crate) fn check_names(op: &crate::config::TemplateVars) -> Result<()> {
         use regex::Regex;
 
         let valid_name = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("valid regex");
 
-        for (key, value) in &op.vars {
+        for key in op.vars.keys().chain(op.secrets.keys()) {
             if !valid_name.is_match(key) {
                 return Err(crate::error::Error::Template {
                     message: format!(
@@ -1720,7 +2006,6 @@ pub(crate) mod template_vars {
                     variable: Some(key.clone()),
                 });
             }
-            context.insert(key.clone(), value.clone());
         }
 
         Ok(())
@@ -1744,6 +2029,7 @@ mod template_tests {
 
         let op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
 
         template::mark(&op, &mut fs).unwrap();
@@ -1767,6 +2053,7 @@ mod template_tests {
 
         let mark_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         template::mark(&mark_op, &mut fs).unwrap();
 
@@ -1776,7 +2063,7 @@ mod template_tests {
         template::process(&mut fs, &vars).unwrap();
 
         let file = fs.get_file("template.txt").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         assert_eq!(content, "Hello World!");
         assert!(!file.is_template);
     }
@@ -1789,6 +2076,7 @@ mod template_tests {
 
         let mark_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         template::mark(&mark_op, &mut fs).unwrap();
 
@@ -1817,6 +2105,7 @@ mod template_tests {
                 vars.insert("existing".to_string(), "updated_value".to_string());
                 vars
             },
+            secrets: Default::default(),
         };
 
         template_vars::collect(&op, &mut context).unwrap();
@@ -1859,6 +2148,7 @@ mod template_tests {
 
         let mark_op = crate::config::TemplateOp {
             patterns: vec!["*.sh".to_string()],
+            files: Default::default(),
         };
         template::mark(&mark_op, &mut fs).unwrap();
 
@@ -1868,7 +2158,7 @@ mod template_tests {
         template::process(&mut fs, &vars).unwrap();
 
         let file = fs.get_file("script.sh").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         assert_eq!(content, "echo ${PATH}\nproject: myproject\n");
     }
 
@@ -1883,6 +2173,7 @@ mod template_tests {
 
         let mark_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         template::mark(&mark_op, &mut fs).unwrap();
 
@@ -1894,7 +2185,7 @@ mod template_tests {
         template::process(&mut fs, &vars).unwrap();
 
         let file = fs.get_file("mixed.txt").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         assert_eq!(content, "new: World\nold: ${LEGACY_VAR}\n");
     }
 
@@ -1906,6 +2197,7 @@ mod template_tests {
 
         let mark_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         template::mark(&mark_op, &mut fs).unwrap();
 
@@ -1915,7 +2207,7 @@ mod template_tests {
         template::process(&mut fs, &vars).unwrap();
 
         let file = fs.get_file("template.txt").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         assert_eq!(content, "value: replacedBAR__");
     }
 
@@ -1928,6 +2220,7 @@ mod template_tests {
                 vars.insert("FOO__BAR".to_string(), "value".to_string());
                 vars
             },
+            secrets: Default::default(),
         };
 
         let result = template_vars::collect(&op, &mut context);
@@ -1943,6 +2236,7 @@ mod template_tests {
                 vars.insert("my-var".to_string(), "value".to_string());
                 vars
             },
+            secrets: Default::default(),
         };
 
         let result = template_vars::collect(&op, &mut context);
@@ -1961,6 +2255,7 @@ mod template_tests {
                 vars.insert("Mixed_Case_123".to_string(), "d".to_string());
                 vars
             },
+            secrets: Default::default(),
         };
 
         template_vars::collect(&op, &mut context).unwrap();
@@ -1976,6 +2271,7 @@ mod template_tests {
 
         let op = crate::config::TemplateOp {
             patterns: vec!["greet.txt".to_string()],
+            files: Default::default(),
         };
         template::mark(&op, &mut fs).unwrap();
 
@@ -1988,6 +2284,23 @@ mod template_tests {
         assert!(marked.is_template, "file should be marked as a template");
     }
 
+    #[test]
+    fn template_mark_declared_files_whatever_their_content() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("plain.txt", "no placeholders").unwrap();
+        fs.add_file_string("other.txt", "__COMMON_REPO__NAME__")
+            .unwrap();
+
+        let op = crate::config::TemplateOp {
+            patterns: Vec::new(),
+            files: [("plain.txt".to_string(), "sha256:x".to_string())].into(),
+        };
+        template::mark(&op, &mut fs).unwrap();
+
+        assert!(fs.get_file("plain.txt").unwrap().is_template);
+        assert!(!fs.get_file("other.txt").unwrap().is_template);
+    }
+
     #[test]
     fn template_process_preserves_if_exists_tag_through_expansion() {
         let mut fs = MemoryFS::new();
@@ -2029,7 +2342,7 @@ pub(crate) mod tools {
     }
 
     /// Check if a tool exists and meets version requirements
-    fn check_tool(tool: &Tool) -> Result<()> {
+    pub(crate) fn check_tool(tool: &Tool) -> Result<()> {
         // Check if tool exists by running it with --version or -V
         let output = Command::new(&tool.name).arg("--version").output();
 
@@ -2279,3 +2592,2078 @@ pub(crate) mod tools {
         }
     }
 }
+
+/// File operator - downloads a single file into the filesystem
+pub(crate) mod file {
+    use crate::config::FileOp;
+    use crate::error::Result;
+    use crate::filesystem::MemoryFS;
+    use crate::http;
+
+    /// Applies the `file` operation: downloads `op.url`, checks it against
+    /// `op.sha256` when set, and writes it to its destination path, replacing
+    /// any file already there.
+    pub(crate) fn apply(op: &FileOp, fs: &mut MemoryFS) -> Result<()> {
+        let dest = op.dest_path()?;
+        let content = http::download(&op.url)?;
+        if let Some(sha256) = &op.sha256 {
+            http::verify_sha256(&op.url, &content, sha256)?;
+        }
+        log::debug!("file: {} -> {} ({} bytes)", op.url, dest, content.len());
+        fs.add_file_content(dest, content)
+    }
+}
+
+/// Vendor operator - copies a repository's files under a directory
+pub(crate) mod vendor {
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+
+    use serde::Serialize;
+
+    use crate::config::VendorOp;
+    use crate::error::{Error, Result};
+    use crate::filesystem::MemoryFS;
+    use crate::http::sha256_hex;
+    use crate::repository::RepositoryManager;
+
+    /// Comment at the top of the provenance file.
+    const HEADER: &str = "# Generated by common-repo from the vendor operation. Do not edit.\n\
+        # To change the vendored version, update its ref (see `common-repo update\n\
+        # --update-vendored`) and run `common-repo apply`.\n";
+
+    /// Contents of the provenance file written next to the vendored files.
+    #[derive(Serialize)]
+    struct Provenance<'a> {
+        url: &'a str,
+        #[serde(rename = "ref")]
+        ref_: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<&'a str>,
+        /// Each vendored file, relative to the vendored directory, with the
+        /// SHA-256 of its content.
+        files: BTreeMap<String, String>,
+    }
+
+    /// Applies the `vendor` operation: fetches `op.url` at `op.ref` and
+    /// places all of its files under the vendored directory, replacing
+    /// whatever was there, along with a provenance file.
+    pub(crate) fn apply(
+        op: &VendorOp,
+        repo_manager: &RepositoryManager,
+        fs: &mut MemoryFS,
+    ) -> Result<()> {
+        let dest = PathBuf::from(op.dest_dir()?);
+        let vendored =
+            repo_manager.fetch_repository_with_path(&op.url, &op.r#ref, op.path.as_deref())?;
+
+        for path in fs.list_files() {
+            if path.starts_with(&dest) {
+                fs.remove_file(&path)?;
+            }
+        }
+
+        let mut files = BTreeMap::new();
+        for (path, file) in vendored.files() {
+            files.insert(
+                path.to_string_lossy().replace('\\', "/"),
+                sha256_hex(&file.content),
+            );
+            fs.add_file(dest.join(path), file.clone())?;
+        }
+        log::debug!(
+            "vendor: {}@{} -> {} ({} files)",
+            op.url,
+            op.r#ref,
+            dest.display(),
+            files.len()
+        );
+
+        let provenance = Provenance {
+            url: &op.url,
+            ref_: &op.r#ref,
+            path: op.path.as_deref(),
+            files,
+        };
+        let yaml = serde_yaml::to_string(&provenance).map_err(|e| Error::Operator {
+            operator: "vendor".to_string(),
+            message: format!("Failed to write provenance for {}: {}", op.url, e),
+        })?;
+        fs.add_file_string(
+            dest.join(Path::new(VendorOp::PROVENANCE_FILE)),
+            &format!("{}{}", HEADER, yaml),
+        )
+    }
+}
+
+/// Patch operator - applies a consumer-side unified diff to a composed file
+pub(crate) mod patch {
+    use crate::config::PatchOp;
+    use crate::error::{Error, Result};
+    use crate::filesystem::MemoryFS;
+
+    /// One `@@ -a,b +c,d @@` section of a unified diff.
+    #[derive(Debug, Default, Clone)]
+    struct Hunk {
+        /// 1-based line of the original file the hunk starts at. For a hunk
+        /// that only adds lines, the line the additions follow.
+        old_start: usize,
+        /// Lines the hunk expects to find: context and removals.
+        old: Vec<String>,
+        /// Lines that replace them: context and additions.
+        new: Vec<String>,
+        /// The old side ends without a trailing newline.
+        old_no_newline: bool,
+        /// The new side ends without a trailing newline.
+        new_no_newline: bool,
+    }
+
+    impl Hunk {
+        fn reversed(&self) -> Hunk {
+            Hunk {
+                old_start: self.old_start,
+                old: self.new.clone(),
+                new: self.old.clone(),
+                old_no_newline: self.new_no_newline,
+                new_no_newline: self.old_no_newline,
+            }
+        }
+    }
+
+    /// Applies the `patch` operation: applies `diff` to `op.file` in `fs`.
+    ///
+    /// Hunks must match the file exactly, but may have moved. A patch that is
+    /// already applied (its reverse applies cleanly) leaves the file alone,
+    /// so running the pipeline again over its own output is a no-op.
+    pub(crate) fn apply(op: &PatchOp, diff: &str, fs: &mut MemoryFS) -> Result<()> {
+        let fail = |message: String| Error::Patch {
+            file: op.file.clone(),
+            patch: op.patch.clone(),
+            message,
+        };
+        let hunks = parse(diff).map_err(fail)?;
+        let file = fs
+            .get_file_mut(&op.file)
+            .ok_or_else(|| fail("the file is not in the output".to_string()))?;
+        let original = std::str::from_utf8(&file.content)
+            .map_err(|_| fail("the file is not valid UTF-8".to_string()))?;
+
+        let patched = match apply_hunks(original, &hunks) {
+            Ok(patched) => patched,
+            Err(message) => {
+                let reversed: Vec<Hunk> = hunks.iter().map(Hunk::reversed).collect();
+                if apply_hunks(original, &reversed).is_ok() {
+                    log::debug!("patch {} is already applied to {}", op.patch, op.file);
+                    return Ok(());
+                }
+                return Err(fail(message));
+            }
+        };
+        file.content = patched.into();
+        Ok(())
+    }
+
+    /// Parse a single-file unified diff into its hunks.
+    ///
+    /// File headers (`---`, `+++`, `diff --git`, `index`) are skipped; the
+    /// target is named by the operation, not the diff.
+    fn parse(diff: &str) -> std::result::Result<Vec<Hunk>, String> {
+        let mut hunks: Vec<Hunk> = Vec::new();
+        let mut files = 0;
+        // Lines of the current hunk still expected on the old and new side.
+        let mut remaining = (0usize, 0usize);
+        let mut last_kind = ' ';
+
+        for line in diff.lines() {
+            if line.starts_with('\\') {
+                // "\ No newline at end of file" applies to the line before it
+                if let Some(hunk) = hunks.last_mut() {
+                    match last_kind {
+                        '-' => hunk.old_no_newline = true,
+                        '+' => hunk.new_no_newline = true,
+                        _ => {
+                            hunk.old_no_newline = true;
+                            hunk.new_no_newline = true;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if remaining == (0, 0) {
+                if line.starts_with("--- ") {
+                    files += 1;
+                    if files > 1 {
+                        return Err("the patch changes more than one file".to_string());
+                    }
+                } else if let Some(header) = line.strip_prefix("@@ ") {
+                    let (old_start, old_len, new_len) = parse_header(header)
+                        .ok_or_else(|| format!("malformed hunk header '{}'", line))?;
+                    hunks.push(Hunk {
+                        old_start,
+                        ..Hunk::default()
+                    });
+                    remaining = (old_len, new_len);
+                }
+                continue;
+            }
+
+            let number = hunks.len();
+            let hunk = hunks.last_mut().expect("inside a hunk");
+            // Some editors strip the single space of an empty context line
+            let (kind, text) = match line.chars().next() {
+                None => (' ', ""),
+                Some(c @ (' ' | '-' | '+')) => (c, &line[1..]),
+                Some(_) => return Err(format!("unexpected line in hunk {}: '{}'", number, line)),
+            };
+            let too_long = || format!("hunk {} is longer than its header says", number);
+            if kind != '+' {
+                remaining.0 = remaining.0.checked_sub(1).ok_or_else(too_long)?;
+                hunk.old.push(text.to_string());
+            }
+            if kind != '-' {
+                remaining.1 = remaining.1.checked_sub(1).ok_or_else(too_long)?;
+                hunk.new.push(text.to_string());
+            }
+            last_kind = kind;
+        }
+
+        if remaining != (0, 0) {
+            return Err(format!("the patch ends inside hunk {}", hunks.len()));
+        }
+        if hunks.is_empty() {
+            return Err("the patch contains no hunks".to_string());
+        }
+        Ok(hunks)
+    }
+
+    /// Parse `-a,b +c,d @@ ...` into the old start and both lengths.
+    fn parse_header(header: &str) -> Option<(usize, usize, usize)> {
+        let range = |range: &str| -> Option<(usize, usize)> {
+            match range.split_once(',') {
+                Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+                None => Some((range.parse().ok()?, 1)),
+            }
+        };
+        let mut parts = header.split_whitespace();
+        let (old_start, old_len) = range(parts.next()?.strip_prefix('-')?)?;
+        let (_, new_len) = range(parts.next()?.strip_prefix('+')?)?;
+        Some((old_start, old_len, new_len))
+    }
+
+    /// Apply parsed hunks to `original`, keeping its line endings.
+    fn apply_hunks(original: &str, hunks: &[Hunk]) -> std::result::Result<String, String> {
+        let newline = if original.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let lines: Vec<&str> = original.lines().collect();
+        let mut trailing_newline = original.is_empty() || original.ends_with('\n');
+        let mut result: Vec<&str> = Vec::with_capacity(lines.len());
+        // Index of the first original line not yet copied to the result
+        let mut next = 0;
+        // How far the previous hunk was from where its header put it
+        let mut offset: isize = 0;
+
+        for (index, hunk) in hunks.iter().enumerate() {
+            let declared = if hunk.old.is_empty() {
+                hunk.old_start
+            } else {
+                hunk.old_start.saturating_sub(1)
+            };
+            let expected = (declared as isize + offset).max(next as isize) as usize;
+            let position = find(&lines, &hunk.old, expected, next).ok_or_else(|| {
+                format!(
+                    "hunk {} (original line {}) does not match the file",
+                    index + 1,
+                    hunk.old_start
+                )
+            })?;
+
+            result.extend_from_slice(&lines[next..position]);
+            result.extend(hunk.new.iter().map(String::as_str));
+            next = position + hunk.old.len();
+            offset = position as isize - declared as isize;
+
+            if next == lines.len() {
+                if hunk.new_no_newline {
+                    trailing_newline = false;
+                } else if hunk.old_no_newline {
+                    trailing_newline = true;
+                }
+            }
+        }
+        result.extend_from_slice(&lines[next..]);
+
+        let mut patched = result.join(newline);
+        if trailing_newline && !result.is_empty() {
+            patched.push_str(newline);
+        }
+        Ok(patched)
+    }
+
+    /// Find where `old` occurs in `lines` at or after `start`, preferring
+    /// the occurrence closest to `expected`.
+    fn find(lines: &[&str], old: &[String], expected: usize, start: usize) -> Option<usize> {
+        if old.is_empty() {
+            return Some(expected.min(lines.len()));
+        }
+        let last = lines.len().checked_sub(old.len())?;
+        let matches_at = |position: usize| {
+            lines[position..position + old.len()]
+                .iter()
+                .zip(old)
+                .all(|(line, expected)| *line == expected.as_str())
+        };
+        (start..=last)
+            .filter(|&position| matches_at(position))
+            .min_by_key(|&position| position.abs_diff(expected))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn op() -> PatchOp {
+            PatchOp {
+                file: "README.md".to_string(),
+                patch: "patches/readme.diff".to_string(),
+            }
+        }
+
+        fn fs_with(content: &str) -> MemoryFS {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("README.md", content).unwrap();
+            fs
+        }
+
+        fn content(fs: &MemoryFS) -> String {
+            String::from_utf8(fs.get_file("README.md").unwrap().content.to_vec()).unwrap()
+        }
+
+        const DIFF: &str = "\
+--- a/README.md
++++ b/README.md
+@@ -1,3 +1,3 @@
+ # Project
+-Built by upstream.
++Built by the platform team.
+ 
+";
+
+        #[test]
+        fn test_apply_replaces_lines() {
+            let mut fs = fs_with("# Project\nBuilt by upstream.\n\nMore text.\n");
+            apply(&op(), DIFF, &mut fs).unwrap();
+            assert_eq!(
+                content(&fs),
+                "# Project\nBuilt by the platform team.\n\nMore text.\n"
+            );
+        }
+
+        #[test]
+        fn test_apply_finds_moved_hunk_and_is_idempotent() {
+            let mut fs = fs_with("Intro\nadded upstream\n# Project\nBuilt by upstream.\n\n");
+            apply(&op(), DIFF, &mut fs).unwrap();
+            let once = content(&fs);
+            assert!(once.contains("Built by the platform team."));
+
+            apply(&op(), DIFF, &mut fs).unwrap();
+            assert_eq!(content(&fs), once);
+        }
+
+        #[test]
+        fn test_apply_reports_hunk_that_no_longer_matches() {
+            let mut fs = fs_with("# Project\nRewritten upstream.\n\n");
+            let err = apply(&op(), DIFF, &mut fs).unwrap_err();
+            assert_eq!(err.code(), "E2006");
+            assert!(err.to_string().contains("hunk 1 (original line 1)"));
+            assert_eq!(content(&fs), "# Project\nRewritten upstream.\n\n");
+        }
+
+        #[test]
+        fn test_apply_missing_file() {
+            let err = apply(&op(), DIFF, &mut MemoryFS::new()).unwrap_err();
+            assert!(err.to_string().contains("not in the output"));
+        }
+
+        #[test]
+        fn test_no_newline_marker_and_crlf() {
+            let diff = "\
+@@ -1,2 +1,2 @@
+ a
+-b
++c
+\\ No newline at end of file
+";
+            let mut fs = fs_with("a\r\nb\r\n");
+            apply(&op(), diff, &mut fs).unwrap();
+            assert_eq!(content(&fs), "a\r\nc");
+        }
+
+        #[test]
+        fn test_parse_rejects_bad_diffs() {
+            assert!(parse("").unwrap_err().contains("no hunks"));
+            assert!(parse("@@ -1,2 +1,2 @@\n a\n")
+                .unwrap_err()
+                .contains("ends inside hunk 1"));
+            assert!(parse("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n--- a/y\n")
+                .unwrap_err()
+                .contains("more than one file"));
+            assert!(parse("@@ nonsense @@\n").is_err());
+        }
+    }
+}
+
+/// Header operator - prepends a license or copyright header to files
+pub(crate) mod header {
+    use crate::config::HeaderOp;
+    use crate::error::Result;
+    use crate::filesystem::MemoryFS;
+    use crate::path::PatternSet;
+    use std::path::Path;
+
+    /// How a file type writes comments.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum CommentStyle {
+        /// Every line starts with the marker, e.g. `//` or `#`.
+        Line(&'static str),
+        /// The header is wrapped in an opening and closing marker, each line
+        /// starting with the prefix.
+        Block {
+            open: &'static str,
+            prefix: &'static str,
+            close: &'static str,
+        },
+    }
+
+    /// Markers stripped from the leading comment of a file when looking for
+    /// an existing header, whatever comment syntax it was written in.
+    /// Longer markers come first so `///` is not read as `//` plus `/`.
+    const MARKERS: &[&str] = &[
+        "<!--", "-->", "///", "//!", "//", "/*", "*/", "*", ";;", "--", "#", "%", "\"\"\"",
+    ];
+
+    /// The comment style for `path`, by extension or well-known file name.
+    fn style_for(path: &Path) -> Option<CommentStyle> {
+        let name = path.file_name()?.to_str()?;
+        match name {
+            "Dockerfile" | "Makefile" | "Rakefile" | "Gemfile" | "Jenkinsfile" | "Vagrantfile"
+            | "CMakeLists.txt" | ".gitignore" | ".dockerignore" | ".editorconfig" => {
+                return Some(CommentStyle::Line("#"))
+            }
+            _ => {}
+        }
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let style = match extension.as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "cs" | "go" | "java"
+            | "kt" | "kts" | "scala" | "swift" | "dart" | "js" | "mjs" | "cjs" | "jsx" | "ts"
+            | "mts" | "cts" | "tsx" | "groovy" | "gradle" | "proto" | "zig" | "php" => {
+                CommentStyle::Line("//")
+            }
+            "sh" | "bash" | "zsh" | "fish" | "py" | "pyi" | "rb" | "pl" | "pm" | "r" | "yaml"
+            | "yml" | "toml" | "tf" | "hcl" | "nix" | "ex" | "exs" | "ps1" | "cmake"
+            | "dockerfile" | "mk" | "conf" | "cfg" => CommentStyle::Line("#"),
+            "sql" | "lua" | "hs" | "elm" => CommentStyle::Line("--"),
+            "lisp" | "el" | "clj" | "cljs" | "scm" => CommentStyle::Line(";;"),
+            "tex" | "erl" | "hrl" => CommentStyle::Line("%"),
+            "css" | "scss" | "less" => CommentStyle::Block {
+                open: "/*",
+                prefix: " * ",
+                close: " */",
+            },
+            "html" | "htm" | "xml" | "svg" | "vue" | "md" | "markdown" => CommentStyle::Block {
+                open: "<!--",
+                prefix: "  ",
+                close: "-->",
+            },
+            _ => return None,
+        };
+        Some(style)
+    }
+
+    /// Render the header text as a comment in `style`, ending with a blank
+    /// line that separates it from the file's content.
+    fn render(text: &[&str], style: CommentStyle, newline: &str) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        match style {
+            CommentStyle::Line(marker) => {
+                for line in text {
+                    lines.push(if line.is_empty() {
+                        marker.to_string()
+                    } else {
+                        format!("{} {}", marker, line)
+                    });
+                }
+            }
+            CommentStyle::Block {
+                open,
+                prefix,
+                close,
+            } => {
+                lines.push(open.to_string());
+                for line in text {
+                    lines.push(format!("{}{}", prefix, line).trim_end().to_string());
+                }
+                lines.push(close.to_string());
+            }
+        }
+        let mut header = lines.join(newline);
+        header.push_str(newline);
+        header.push_str(newline);
+        header
+    }
+
+    /// Length of the lines that must stay before the header: a `#!`
+    /// shebang or an `<?xml ...?>` declaration.
+    fn preamble_len(content: &str) -> usize {
+        if content.starts_with("#!") || content.starts_with("<?xml") {
+            content.find('\n').map_or(content.len(), |end| end + 1)
+        } else {
+            0
+        }
+    }
+
+    /// The text of the comment at the start of `content`, one entry per
+    /// non-blank line, with comment markers removed.
+    fn leading_comment(content: &str) -> Vec<String> {
+        let mut text = Vec::new();
+        for line in content.lines() {
+            let mut rest = line.trim();
+            if rest.is_empty() {
+                continue;
+            }
+            let mut is_comment = false;
+            while let Some(marker) = MARKERS.iter().find(|marker| rest.starts_with(**marker)) {
+                rest = rest[marker.len()..].trim_start();
+                is_comment = true;
+            }
+            // A closing marker at the end of a one-line block comment
+            for marker in ["-->", "*/"] {
+                rest = rest.strip_suffix(marker).unwrap_or(rest).trim_end();
+            }
+            if !is_comment {
+                break;
+            }
+            if !rest.is_empty() {
+                text.push(rest.to_string());
+            }
+        }
+        text
+    }
+
+    /// Whether the leading comment of `content` already contains the
+    /// header, in any comment syntax.
+    fn has_header(content: &str, text: &[&str]) -> bool {
+        let wanted: Vec<&str> = text
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        if wanted.is_empty() {
+            return true;
+        }
+        let comment = leading_comment(content);
+        comment
+            .windows(wanted.len())
+            .any(|window| window.iter().zip(&wanted).all(|(a, b)| a == b))
+    }
+
+    /// Applies the `header` operation: prepends `op.template`, as a comment
+    /// in the syntax of each file's type, to the files matching
+    /// `op.patterns`.
+    ///
+    /// Files that already start with the header, written in any comment
+    /// syntax, are left alone, so applying the operation again is a no-op.
+    /// A shebang or XML declaration stays on the first line. Files whose type
+    /// has no known comment syntax, and files that are not UTF-8, are
+    /// skipped.
+    pub(crate) fn apply(op: &HeaderOp, fs: &mut MemoryFS) -> Result<()> {
+        let text: Vec<&str> = op.template.trim_end().lines().map(str::trim_end).collect();
+        let patterns = PatternSet::cached(&op.patterns)?;
+        for path in fs.list_files_matching(&patterns) {
+            let Some(style) = style_for(&path) else {
+                log::debug!("header: no comment syntax for {}", path.display());
+                continue;
+            };
+            let Some(file) = fs.get_file_mut(&path) else {
+                continue;
+            };
+            let Ok(content) = std::str::from_utf8(&file.content) else {
+                log::debug!("header: {} is not UTF-8", path.display());
+                continue;
+            };
+            if has_header(&content[preamble_len(content)..], &text) {
+                continue;
+            }
+            let updated = insert(content, &text, style);
+            log::trace!("header: + {}", path.display());
+            file.content = updated.into();
+        }
+        Ok(())
+    }
+
+    /// `content` with `text` inserted as a comment in `style`, after any
+    /// shebang or XML declaration.
+    fn insert(content: &str, text: &[&str], style: CommentStyle) -> String {
+        let (preamble, body) = content.split_at(preamble_len(content));
+        let newline = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let mut updated = preamble.to_string();
+        if !preamble.is_empty() && !preamble.ends_with('\n') {
+            updated.push_str(newline);
+        }
+        updated.push_str(&render(text, style, newline));
+        updated.push_str(body);
+        updated
+    }
+
+    /// `content` with `text` prepended as a comment in the syntax of
+    /// `path`'s type, the way [`apply`] writes a header, or `None` when the
+    /// type has no known comment syntax.
+    pub(crate) fn prepend(path: &Path, content: &str, text: &[&str]) -> Option<String> {
+        Some(insert(content, text, style_for(path)?))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const TEMPLATE: &str = "Copyright 2026 Example Corp.\nSPDX-License-Identifier: MIT\n";
+
+        fn op(patterns: &[&str]) -> HeaderOp {
+            HeaderOp {
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+                template: TEMPLATE.to_string(),
+            }
+        }
+
+        fn content(fs: &MemoryFS, path: &str) -> String {
+            String::from_utf8(fs.get_file(path).unwrap().content.to_vec()).unwrap()
+        }
+
+        #[test]
+        fn test_apply_uses_comment_syntax_per_extension() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("src/lib.rs", "pub fn f() {}\n").unwrap();
+            fs.add_file_string("style.css", "a {}\n").unwrap();
+            fs.add_file_string("notes.txt", "plain\n").unwrap();
+            apply(&op(&["**/*"]), &mut fs).unwrap();
+
+            assert_eq!(
+                content(&fs, "src/lib.rs"),
+                "// Copyright 2026 Example Corp.\n// SPDX-License-Identifier: MIT\n\npub fn f() {}\n"
+            );
+            assert_eq!(
+                content(&fs, "style.css"),
+                "/*\n * Copyright 2026 Example Corp.\n * SPDX-License-Identifier: MIT\n */\n\na {}\n"
+            );
+            assert_eq!(content(&fs, "notes.txt"), "plain\n");
+        }
+
+        #[test]
+        fn test_apply_keeps_shebang_and_is_idempotent() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("run.sh", "#!/bin/sh\necho hi\n")
+                .unwrap();
+            apply(&op(&["*.sh"]), &mut fs).unwrap();
+            let once = content(&fs, "run.sh");
+            assert_eq!(
+                once,
+                "#!/bin/sh\n# Copyright 2026 Example Corp.\n# SPDX-License-Identifier: MIT\n\necho hi\n"
+            );
+
+            apply(&op(&["*.sh"]), &mut fs).unwrap();
+            assert_eq!(content(&fs, "run.sh"), once);
+        }
+
+        #[test]
+        fn test_apply_detects_header_in_other_comment_syntax() {
+            let mut fs = MemoryFS::new();
+            let existing = "/*\n * Copyright 2026 Example Corp.\n * SPDX-License-Identifier: MIT\n */\nint x;\n";
+            fs.add_file_string("main.c", existing).unwrap();
+            apply(&op(&["*.c"]), &mut fs).unwrap();
+            assert_eq!(content(&fs, "main.c"), existing);
+        }
+
+        #[test]
+        fn test_apply_only_matches_patterns() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("src/a.py", "x = 1\n").unwrap();
+            fs.add_file_string("vendor/b.py", "y = 2\n").unwrap();
+            apply(&op(&["src/**/*.py"]), &mut fs).unwrap();
+            assert!(content(&fs, "src/a.py").starts_with("# Copyright"));
+            assert_eq!(content(&fs, "vendor/b.py"), "y = 2\n");
+        }
+    }
+}
+
+/// # Format Operator
+///
+/// Applies the rules of the output's `.editorconfig` to propagated text
+/// files, so files composed from upstreams follow the consumer's
+/// conventions.
+pub(crate) mod format {
+    use crate::config::FormatOp;
+    use crate::error::Result;
+    use crate::filesystem::MemoryFS;
+    use regex::Regex;
+    use std::path::{Path, PathBuf};
+
+    /// Largest `{n1..n2}` range expanded into its numbers; wider ranges
+    /// match any integer.
+    const MAX_RANGE: i64 = 256;
+
+    /// The `indent_style` property.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum IndentStyle {
+        Space,
+        Tab,
+    }
+
+    /// The properties that apply to one file. `None` leaves that aspect of
+    /// the file as it is.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    struct Properties {
+        indent_style: Option<IndentStyle>,
+        indent_size: Option<usize>,
+        tab_width: Option<usize>,
+        end_of_line: Option<&'static str>,
+        insert_final_newline: Option<bool>,
+        trim_trailing_whitespace: Option<bool>,
+    }
+
+    impl Properties {
+        /// Set property `key` from `value`. `unset` and values that are not
+        /// understood clear the property.
+        fn set(&mut self, key: &str, value: &str) {
+            let value = value.to_ascii_lowercase();
+            let flag = match value.as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            };
+            match key {
+                "indent_style" => {
+                    self.indent_style = match value.as_str() {
+                        "space" => Some(IndentStyle::Space),
+                        "tab" => Some(IndentStyle::Tab),
+                        _ => None,
+                    }
+                }
+                "indent_size" => self.indent_size = value.parse().ok().filter(|size| *size > 0),
+                "tab_width" => self.tab_width = value.parse().ok().filter(|size| *size > 0),
+                "end_of_line" => {
+                    self.end_of_line = match value.as_str() {
+                        "lf" => Some("\n"),
+                        "crlf" => Some("\r\n"),
+                        "cr" => Some("\r"),
+                        _ => None,
+                    }
+                }
+                "insert_final_newline" => self.insert_final_newline = flag,
+                "trim_trailing_whitespace" => self.trim_trailing_whitespace = flag,
+                _ => {}
+            }
+        }
+
+        /// Columns of one indentation level: `indent_size`, else
+        /// `tab_width`, else 4.
+        fn indent_width(&self) -> usize {
+            self.indent_size.or(self.tab_width).unwrap_or(4)
+        }
+
+        /// Columns a tab advances: `tab_width`, else `indent_size`, else 4.
+        fn tab_columns(&self) -> usize {
+            self.tab_width.or(self.indent_size).unwrap_or(4)
+        }
+    }
+
+    /// A parsed `.editorconfig`: sections in file order, each a path
+    /// pattern and the properties it sets.
+    #[derive(Debug)]
+    pub(crate) struct EditorConfig {
+        sections: Vec<(Regex, Vec<(String, String)>)>,
+    }
+
+    impl EditorConfig {
+        /// Parse `.editorconfig` content. Sections whose glob cannot be
+        /// compiled are skipped.
+        pub(crate) fn parse(content: &str) -> Self {
+            let mut sections = Vec::new();
+            let mut current: Option<(Regex, Vec<(String, String)>)> = None;
+            let mut skipping = false;
+            for line in content.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                    continue;
+                }
+                if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    sections.extend(current.take());
+                    match glob_to_regex(glob) {
+                        Ok(regex) => {
+                            current = Some((regex, Vec::new()));
+                            skipping = false;
+                        }
+                        Err(e) => {
+                            log::debug!("format: skipping section [{}]: {}", glob, e);
+                            skipping = true;
+                        }
+                    }
+                    continue;
+                }
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                // Properties before the first section (e.g. `root`) apply to
+                // no files
+                if let (Some((_, properties)), false) = (current.as_mut(), skipping) {
+                    properties.push((key.trim().to_ascii_lowercase(), value.trim().to_string()));
+                }
+            }
+            sections.extend(current);
+            Self { sections }
+        }
+
+        /// The properties for `path`, relative to the directory of the
+        /// `.editorconfig`. Later sections override earlier ones.
+        fn properties(&self, path: &Path) -> Properties {
+            let path = path.to_string_lossy().replace('\\', "/");
+            let mut properties = Properties::default();
+            for (regex, entries) in &self.sections {
+                if regex.is_match(&path) {
+                    for (key, value) in entries {
+                        properties.set(key, value);
+                    }
+                }
+            }
+            properties
+        }
+    }
+
+    /// Translate an EditorConfig section glob into an anchored regex.
+    ///
+    /// `*` matches within a path segment, `**` across segments, `?` one
+    /// character, `[...]` and `[!...]` a character class, `{a,b}` either
+    /// alternative and `{n1..n2}` an integer in the range. A glob without a
+    /// `/` matches the file name in any directory; one with a `/` is
+    /// relative to the `.editorconfig`.
+    fn glob_to_regex(glob: &str) -> std::result::Result<Regex, regex::Error> {
+        let chars: Vec<char> = glob.chars().collect();
+        // Paths are matched with a leading `/`
+        let mut regex = String::from("^");
+        if glob.contains('/') {
+            if !glob.starts_with('/') {
+                regex.push('/');
+            }
+        } else {
+            regex.push_str("(?:.*/)?");
+        }
+        let mut depth = 0usize;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+                    regex.push_str(&regex::escape(&chars[i].to_string()));
+                }
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    i += 1;
+                    regex.push_str(".*");
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                    Some(len) => {
+                        let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                        let (negated, class) = match class.strip_prefix('!') {
+                            Some(rest) => (true, rest.to_string()),
+                            None => (false, class),
+                        };
+                        regex.push('[');
+                        if negated {
+                            regex.push('^');
+                        }
+                        regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                        regex.push(']');
+                        i += len + 1;
+                    }
+                    None => regex.push_str("\\["),
+                },
+                '{' => {
+                    let close = chars[i + 1..].iter().position(|&c| c == '}');
+                    let body: Option<String> =
+                        close.map(|len| chars[i + 1..i + 1 + len].iter().collect());
+                    if let Some(alternatives) = body.as_deref().and_then(range_alternatives) {
+                        regex.push_str(&alternatives);
+                        i += close.unwrap_or(0) + 1;
+                    } else if body.is_some_and(|body| body.contains(',')) {
+                        regex.push_str("(?:");
+                        depth += 1;
+                    } else {
+                        regex.push_str("\\{");
+                    }
+                }
+                ',' if depth > 0 => regex.push('|'),
+                '}' if depth > 0 => {
+                    regex.push(')');
+                    depth -= 1;
+                }
+                _ => regex.push_str(&regex::escape(&c.to_string())),
+            }
+            i += 1;
+        }
+        regex.push('$');
+        Regex::new(&regex)
+    }
+
+    /// The regex for a `{n1..n2}` range body, or `None` if `body` is not a
+    /// range.
+    fn range_alternatives(body: &str) -> Option<String> {
+        let (start, end) = body.split_once("..")?;
+        let (start, end): (i64, i64) = (start.parse().ok()?, end.parse().ok()?);
+        let (low, high) = (start.min(end), start.max(end));
+        if high - low > MAX_RANGE {
+            return Some("-?[0-9]+".to_string());
+        }
+        let numbers: Vec<String> = (low..=high).map(|n| n.to_string()).collect();
+        Some(format!("(?:{})", numbers.join("|")))
+    }
+
+    /// Re-indent the leading whitespace of `line` in `style`.
+    fn reindent(line: &str, style: IndentStyle, properties: &Properties) -> String {
+        let body = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - body.len()];
+        let tab_columns = properties.tab_columns();
+        let columns: usize = indent
+            .chars()
+            .map(|c| if c == '\t' { tab_columns } else { 1 })
+            .sum();
+        let indent = match style {
+            IndentStyle::Space => " ".repeat(columns),
+            IndentStyle::Tab => {
+                // Whole levels become tabs; a partial level, such as the
+                // alignment inside a block comment, stays as spaces
+                let width = properties.indent_width();
+                let mut indent = "\t".repeat(columns / width);
+                indent.push_str(&" ".repeat(columns % width));
+                indent
+            }
+        };
+        format!("{}{}", indent, body)
+    }
+
+    /// Format `content` with `properties`.
+    fn format_text(content: &str, properties: &Properties) -> String {
+        let newline = properties.end_of_line.unwrap_or_else(|| {
+            if content.contains("\r\n") {
+                "\r\n"
+            } else if content.contains('\r') && !content.contains('\n') {
+                "\r"
+            } else {
+                "\n"
+            }
+        });
+        let normalized = content.replace("\r\n", "\n").replace('\r', "\n");
+        let had_final_newline = normalized.ends_with('\n');
+        let body = normalized.strip_suffix('\n').unwrap_or(&normalized);
+
+        let mut lines: Vec<String> = Vec::new();
+        for line in body.split('\n') {
+            let mut line = line.to_string();
+            if properties.trim_trailing_whitespace == Some(true) {
+                line.truncate(line.trim_end().len());
+            }
+            if let Some(style) = properties.indent_style {
+                line = reindent(&line, style, properties);
+            }
+            lines.push(line);
+        }
+        let mut formatted = lines.join(newline);
+        if properties.insert_final_newline == Some(false) {
+            while formatted.ends_with(newline) {
+                formatted.truncate(formatted.len() - newline.len());
+            }
+        } else if (had_final_newline || properties.insert_final_newline == Some(true))
+            && !formatted.is_empty()
+        {
+            formatted.push_str(newline);
+        }
+        formatted
+    }
+
+    /// Applies the `format` operation to the files at `paths`.
+    ///
+    /// The rules come from the `.editorconfig` at the root of `fs`, which is
+    /// the one the output will have, or else the one in `working_dir`.
+    /// Without either, nothing is formatted. Only the root file is read;
+    /// `.editorconfig` files in subdirectories are not. Files that are not
+    /// UTF-8 text are skipped. Indentation changes only between tabs and
+    /// spaces: the indentation width of a file is kept.
+    pub(crate) fn apply(
+        op: FormatOp,
+        fs: &mut MemoryFS,
+        paths: &[PathBuf],
+        working_dir: &Path,
+    ) -> Result<()> {
+        let FormatOp::EditorConfig = op;
+        let content = match fs.get_file(".editorconfig") {
+            Some(file) => String::from_utf8_lossy(&file.content).into_owned(),
+            None => match std::fs::read_to_string(working_dir.join(".editorconfig")) {
+                Ok(content) => content,
+                Err(_) => {
+                    log::debug!("format: no .editorconfig, nothing to format");
+                    return Ok(());
+                }
+            },
+        };
+        let config = EditorConfig::parse(&content);
+
+        for path in paths {
+            let properties = config.properties(&Path::new("/").join(path));
+            if properties == Properties::default() {
+                continue;
+            }
+            let Some(file) = fs.get_file_mut(path) else {
+                continue;
+            };
+            let Ok(text) = std::str::from_utf8(&file.content) else {
+                log::debug!("format: {} is not UTF-8", path.display());
+                continue;
+            };
+            if text.contains('\0') {
+                continue;
+            }
+            let formatted = format_text(text, &properties);
+            if formatted != text {
+                log::trace!("format: ~ {}", path.display());
+                file.content = formatted.into();
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const EDITORCONFIG: &str = "root = true\n\n[*]\nend_of_line = lf\ninsert_final_newline = true\ntrim_trailing_whitespace = true\n\n[*.{yml,yaml}]\nindent_style = space\nindent_size = 2\n\n[Makefile]\nindent_style = tab\n\n[docs/**.md]\ntrim_trailing_whitespace = false\n";
+
+        fn properties(path: &str) -> Properties {
+            EditorConfig::parse(EDITORCONFIG).properties(Path::new(path))
+        }
+
+        #[test]
+        fn test_sections_match_editorconfig_globs() {
+            assert_eq!(properties("/ci.yml").indent_size, Some(2));
+            assert_eq!(
+                properties("/.github/workflows/ci.yaml").indent_size,
+                Some(2)
+            );
+            assert_eq!(
+                properties("/src/Makefile").indent_style,
+                Some(IndentStyle::Tab)
+            );
+            assert_eq!(
+                properties("/README.md").trim_trailing_whitespace,
+                Some(true)
+            );
+            assert_eq!(
+                properties("/docs/guide/a.md").trim_trailing_whitespace,
+                Some(false)
+            );
+            assert_eq!(properties("/README.md").indent_style, None);
+
+            let regex = glob_to_regex("file{1..3}.[!a-c]xt").unwrap();
+            assert!(regex.is_match("/dir/file2.txt"));
+            assert!(!regex.is_match("/dir/file4.txt"));
+            assert!(!regex.is_match("/dir/file2.axt"));
+        }
+
+        #[test]
+        fn test_format_text_applies_properties() {
+            let text = "a:\r\n\tb: 1  \r\n\t\tc: 2";
+            assert_eq!(
+                format_text(text, &properties("/ci.yml")),
+                "a:\n  b: 1\n    c: 2\n"
+            );
+            assert_eq!(
+                format_text("all:\n    cc -o a a.c\n", &properties("/Makefile")),
+                "all:\n\tcc -o a a.c\n"
+            );
+
+            let no_final_newline = Properties {
+                insert_final_newline: Some(false),
+                ..Properties::default()
+            };
+            assert_eq!(format_text("x\r\ny\r\n\r\n", &no_final_newline), "x\r\ny");
+        }
+
+        #[test]
+        fn test_apply_only_formats_listed_paths() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string(".editorconfig", EDITORCONFIG).unwrap();
+            fs.add_file_string("upstream.yml", "a:\n\tb: 1 ").unwrap();
+            fs.add_file_string("local.yml", "a:\n\tb: 1 ").unwrap();
+            fs.add_file(
+                "logo.png",
+                crate::filesystem::File::new(vec![0x89, b'P', 0, b'\t', b' ']),
+            )
+            .unwrap();
+            let paths = [PathBuf::from("upstream.yml"), PathBuf::from("logo.png")];
+            apply(
+                FormatOp::EditorConfig,
+                &mut fs,
+                &paths,
+                Path::new("/nonexistent"),
+            )
+            .unwrap();
+
+            let content = |path: &str| fs.get_file(path).unwrap().content.to_vec();
+            assert_eq!(content("upstream.yml"), b"a:\n  b: 1\n");
+            assert_eq!(content("local.yml"), b"a:\n\tb: 1 ");
+            assert_eq!(content("logo.png"), vec![0x89, b'P', 0, b'\t', b' ']);
+        }
+    }
+}
+
+/// # YAML Format Operator
+///
+/// Rewrites YAML files in a canonical layout: keys sorted at the configured
+/// paths, block style with a fixed indentation width, and one quote style
+/// for strings that cannot be written plain.
+pub(crate) mod yaml_format {
+    use crate::config::{QuoteStyle, YamlFormatOp};
+    use crate::error::{Error, Result};
+    use crate::filesystem::MemoryFS;
+    use crate::merge::{parse_path, PathSegment};
+    use crate::path::PatternSet;
+    use serde::Deserialize;
+    use serde_yaml::{Mapping, Value};
+
+    /// Plain scalars that YAML 1.1 parsers, still common in CI tooling,
+    /// read as booleans.
+    const YAML_1_1_BOOLEANS: &[&str] = &["y", "yes", "n", "no", "on", "off"];
+
+    /// Characters that cannot start a plain scalar.
+    const INDICATORS: &[char] = &[
+        '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@',
+        '`',
+    ];
+
+    /// Sort the keys of the mappings at `path`.
+    fn sort_at(value: &mut Value, path: &[PathSegment]) {
+        let value = match value {
+            Value::Tagged(tagged) => &mut tagged.value,
+            value => value,
+        };
+        let Some((segment, rest)) = path.split_first() else {
+            if let Value::Mapping(map) = value {
+                let mut entries: Vec<(Value, Value)> = std::mem::take(map).into_iter().collect();
+                entries.sort_by_cached_key(|(key, _)| match key {
+                    Value::String(key) => key.clone(),
+                    key => serde_yaml::to_string(key).unwrap_or_default(),
+                });
+                *map = entries.into_iter().collect();
+            }
+            return;
+        };
+        match (segment, value) {
+            (PathSegment::Key(key), Value::Mapping(map)) if key == "*" => {
+                map.values_mut().for_each(|child| sort_at(child, rest));
+            }
+            (PathSegment::Key(key), Value::Sequence(items)) if key == "*" => {
+                items.iter_mut().for_each(|child| sort_at(child, rest));
+            }
+            (PathSegment::Key(key), Value::Mapping(map)) => {
+                if let Some(child) = map.get_mut(key.as_str()) {
+                    sort_at(child, rest);
+                }
+            }
+            (PathSegment::Index(index), Value::Sequence(items)) => {
+                if let Some(child) = items.get_mut(*index) {
+                    sort_at(child, rest);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `s` can be written as a plain scalar and read back as the
+    /// same string. Values also avoid YAML 1.1 booleans; keys do not, so
+    /// a workflow's `on:` stays unquoted.
+    fn is_plain(s: &str, key: bool) -> bool {
+        let Some(first) = s.chars().next() else {
+            return false;
+        };
+        let starts_with_indicator = INDICATORS.contains(&first)
+            && !(matches!(first, '-' | '?' | ':')
+                && s[1..].chars().next().is_some_and(|c| !c.is_whitespace()));
+        !starts_with_indicator
+            && s.trim() == s
+            && !s.chars().any(|c| c.is_control())
+            && !s.contains(": ")
+            && !s.contains(" #")
+            && !s.ends_with(':')
+            && (key || !YAML_1_1_BOOLEANS.contains(&s.to_ascii_lowercase().as_str()))
+            && serde_yaml::from_str::<Value>(s).ok() == Some(Value::String(s.to_string()))
+    }
+
+    /// `s` in double quotes, with escapes.
+    fn double_quoted(s: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in s.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Writes values in block style.
+    struct Emitter {
+        indent: usize,
+        quote: QuoteStyle,
+    }
+
+    impl Emitter {
+        /// A string on one line: plain if possible, else quoted.
+        fn inline_string(&self, s: &str, key: bool) -> String {
+            if is_plain(s, key) {
+                s.to_string()
+            } else if self.quote == QuoteStyle::Single && !s.chars().any(|c| c.is_control()) {
+                format!("'{}'", s.replace('\'', "''"))
+            } else {
+                double_quoted(s)
+            }
+        }
+
+        /// A mapping key, which must be a scalar.
+        fn key(&self, key: &Value) -> std::result::Result<String, String> {
+            match key {
+                Value::String(s) => Ok(self.inline_string(s, true)),
+                Value::Null | Value::Bool(_) | Value::Number(_) => self.scalar(key),
+                _ => Err("only scalar mapping keys are supported".to_string()),
+            }
+        }
+
+        /// A scalar, or an empty collection, on one line.
+        fn scalar(&self, value: &Value) -> std::result::Result<String, String> {
+            Ok(match value {
+                Value::Null => "null".to_string(),
+                Value::Bool(b) => b.to_string(),
+                Value::Number(n) => n.to_string(),
+                Value::String(s) => self.inline_string(s, false),
+                Value::Sequence(_) => "[]".to_string(),
+                Value::Mapping(_) => "{}".to_string(),
+                Value::Tagged(tagged) => format!("{} {}", tagged.tag, self.scalar(&tagged.value)?),
+            })
+        }
+
+        /// Write a scalar after a `key:` or `-` indicator, as a literal block
+        /// indented to `block_col` when it spans lines.
+        fn write_scalar(
+            &self,
+            value: &Value,
+            block_col: usize,
+            out: &mut String,
+        ) -> std::result::Result<(), String> {
+            if let Value::String(s) = value {
+                let body = s.trim_end_matches('\n');
+                let trailing = s.len() - body.len();
+                let literal = body.contains('\n')
+                    && !body.starts_with([' ', '\n'])
+                    && !body
+                        .chars()
+                        .any(|c| c.is_control() && c != '\n' && c != '\t');
+                if literal {
+                    let chomp = match trailing {
+                        0 => "-",
+                        1 => "",
+                        _ => "+",
+                    };
+                    out.push_str(&format!(" |{}\n", chomp));
+                    for line in body.split('\n') {
+                        if !line.is_empty() {
+                            out.push_str(&" ".repeat(block_col));
+                            out.push_str(line);
+                        }
+                        out.push('\n');
+                    }
+                    out.push_str(&"\n".repeat(trailing.saturating_sub(1)));
+                    return Ok(());
+                }
+            }
+            out.push(' ');
+            out.push_str(&self.scalar(value)?);
+            out.push('\n');
+            Ok(())
+        }
+
+        /// Write `value` after a `key:` or `-` indicator at column `col`.
+        /// Collections under a key start on the next line, indented one
+        /// level; collections in a sequence item start on the same line.
+        fn write_node(
+            &self,
+            value: &Value,
+            col: usize,
+            item: bool,
+            out: &mut String,
+        ) -> std::result::Result<(), String> {
+            let (child_col, inline) = if item {
+                (col + 2, true)
+            } else {
+                (col + self.indent, false)
+            };
+            match value {
+                Value::Tagged(tagged)
+                    if matches!(&tagged.value, Value::Mapping(m) if !m.is_empty())
+                        || matches!(&tagged.value, Value::Sequence(s) if !s.is_empty()) =>
+                {
+                    out.push_str(&format!(" {}\n", tagged.tag));
+                    match &tagged.value {
+                        Value::Mapping(map) => self.write_mapping(map, child_col, false, out),
+                        Value::Sequence(items) => self.write_sequence(items, child_col, false, out),
+                        _ => unreachable!("matched a collection"),
+                    }
+                }
+                Value::Mapping(map) if !map.is_empty() => {
+                    out.push(if inline { ' ' } else { '\n' });
+                    self.write_mapping(map, child_col, inline, out)
+                }
+                Value::Sequence(items) if !items.is_empty() => {
+                    out.push(if inline { ' ' } else { '\n' });
+                    self.write_sequence(items, child_col, inline, out)
+                }
+                value => self.write_scalar(value, col + self.indent, out),
+            }
+        }
+
+        /// Write a mapping with its keys at column `col`. With `started`,
+        /// the first key goes where the output already is.
+        fn write_mapping(
+            &self,
+            map: &Mapping,
+            col: usize,
+            started: bool,
+            out: &mut String,
+        ) -> std::result::Result<(), String> {
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 || !started {
+                    out.push_str(&" ".repeat(col));
+                }
+                out.push_str(&self.key(key)?);
+                out.push(':');
+                self.write_node(value, col, false, out)?;
+            }
+            Ok(())
+        }
+
+        /// Write a sequence with its `-` indicators at column `col`. With
+        /// `started`, the first item goes where the output already is.
+        fn write_sequence(
+            &self,
+            items: &[Value],
+            col: usize,
+            started: bool,
+            out: &mut String,
+        ) -> std::result::Result<(), String> {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 || !started {
+                    out.push_str(&" ".repeat(col));
+                }
+                out.push('-');
+                self.write_node(item, col, true, out)?;
+            }
+            Ok(())
+        }
+
+        /// Write one document.
+        fn write_document(
+            &self,
+            value: &Value,
+            out: &mut String,
+        ) -> std::result::Result<(), String> {
+            match value {
+                Value::Null => Ok(()),
+                Value::Mapping(map) if !map.is_empty() => self.write_mapping(map, 0, false, out),
+                Value::Sequence(items) if !items.is_empty() => {
+                    self.write_sequence(items, 0, false, out)
+                }
+                value => {
+                    let mut scalar = String::new();
+                    self.write_scalar(value, self.indent, &mut scalar)?;
+                    out.push_str(scalar.trim_start_matches(' '));
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Format YAML `content`, which may hold several documents.
+    fn format_yaml(
+        content: &str,
+        op: &YamlFormatOp,
+        sort_paths: &[Vec<PathSegment>],
+    ) -> std::result::Result<String, String> {
+        let mut documents = Vec::new();
+        for document in serde_yaml::Deserializer::from_str(content) {
+            let mut value = Value::deserialize(document).map_err(|e| e.to_string())?;
+            for path in sort_paths {
+                sort_at(&mut value, path);
+            }
+            documents.push(value);
+        }
+        let emitter = Emitter {
+            indent: op.indent,
+            quote: op.quote,
+        };
+        let explicit_start = content.trim_start().starts_with("---") || documents.len() > 1;
+        let mut out = String::new();
+        for document in &documents {
+            if explicit_start {
+                out.push_str("---\n");
+            }
+            emitter.write_document(document, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Applies the `yaml-format` operation to the files matching
+    /// `op.patterns`.
+    ///
+    /// Files are parsed and written again, so comments and anchors are not
+    /// kept, as with the `yaml` merge operator.
+    pub(crate) fn apply(op: &YamlFormatOp, fs: &mut MemoryFS) -> Result<()> {
+        if op.indent == 0 {
+            return Err(Error::Operator {
+                operator: "yaml-format".to_string(),
+                message: "indent must be at least 1".to_string(),
+            });
+        }
+        let sort_paths: Vec<Vec<PathSegment>> =
+            op.sort_keys.iter().map(|path| parse_path(path)).collect();
+        let patterns = PatternSet::cached(&op.patterns)?;
+        for path in fs.list_files_matching(&patterns) {
+            let Some(file) = fs.get_file_mut(&path) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&file.content).into_owned();
+            let formatted =
+                format_yaml(&content, op, &sort_paths).map_err(|message| Error::Merge {
+                    operation: "yaml-format".to_string(),
+                    message: format!("{}: {}", path.display(), message),
+                })?;
+            if formatted != content {
+                log::trace!("yaml-format: ~ {}", path.display());
+                file.content = formatted.into();
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn op(sort_keys: &[&str], indent: usize, quote: QuoteStyle) -> YamlFormatOp {
+            YamlFormatOp {
+                patterns: vec!["**/*.yml".to_string()],
+                sort_keys: sort_keys.iter().map(|p| p.to_string()).collect(),
+                indent,
+                quote,
+            }
+        }
+
+        fn format(content: &str, op: &YamlFormatOp) -> String {
+            let sort_paths: Vec<_> = op.sort_keys.iter().map(|p| parse_path(p)).collect();
+            format_yaml(content, op, &sort_paths).unwrap()
+        }
+
+        #[test]
+        fn test_sorts_keys_only_at_configured_paths() {
+            let content = "on: push\njobs:\n  test:\n    steps: []\n    env: {B: 1, A: 2}\n    name: t\nenv: {z: 1, a: 2}\n";
+            let formatted = format(content, &op(&["jobs.*", "env"], 2, QuoteStyle::Single));
+            assert_eq!(
+                formatted,
+                "on: push\njobs:\n  test:\n    env:\n      B: 1\n      A: 2\n    name: t\n    steps: []\nenv:\n  a: 2\n  z: 1\n"
+            );
+        }
+
+        #[test]
+        fn test_indent_and_nested_collections() {
+            let content = "a:\n- x: 1\n  y:\n  - 2\n  - - 3\n- []\nb: |\n  line one\n  line two\n";
+            assert_eq!(
+                format(content, &op(&[], 4, QuoteStyle::Single)),
+                "a:\n    - x: 1\n      y:\n          - 2\n          - - 3\n    - []\nb: |\n    line one\n    line two\n"
+            );
+        }
+
+        #[test]
+        fn test_quotes_only_strings_that_need_it() {
+            let content = "a: 'plain'\nb: \"true\"\nc: 'yes'\nd: \"it's: here\"\ne: \"tab\\there\"\nf: --flag\ng: ''\n";
+            assert_eq!(
+                format(content, &op(&[], 2, QuoteStyle::Single)),
+                "a: plain\nb: 'true'\nc: 'yes'\nd: 'it''s: here'\ne: \"tab\\there\"\nf: --flag\ng: ''\n"
+            );
+            assert_eq!(
+                format(content, &op(&[], 2, QuoteStyle::Double)),
+                "a: plain\nb: \"true\"\nc: \"yes\"\nd: \"it's: here\"\ne: \"tab\\there\"\nf: --flag\ng: \"\"\n"
+            );
+        }
+
+        #[test]
+        fn test_output_reads_back_as_input_and_is_stable() {
+            let content = "---\nname: CI\non:\n  push:\n    branches: [main]\nvalues: [1, 2.5, null, ~, '0x10', \"a\\nb\\n\\n\"]\n---\n- !tag {k: v}\n";
+            let op = op(&[""], 2, QuoteStyle::Single);
+            let once = format(content, &op);
+            assert_eq!(format(&once, &op), once);
+
+            let docs = |s: &str| -> Vec<Value> {
+                serde_yaml::Deserializer::from_str(s)
+                    .map(|d| Value::deserialize(d).unwrap())
+                    .collect()
+            };
+            let mut expected = docs(content);
+            sort_at(&mut expected[0], &[]);
+            assert_eq!(docs(&once), expected);
+        }
+
+        #[test]
+        fn test_apply_rejects_invalid_yaml() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("bad.yml", "a: [1\n").unwrap();
+            let err = apply(&op(&[], 2, QuoteStyle::Single), &mut fs).unwrap_err();
+            assert!(err.to_string().contains("bad.yml"));
+        }
+    }
+}
+
+/// # Assert Operator
+///
+/// Checks the composed output against what a configuration expects of it:
+/// files that must exist, must not exist, or must have certain content.
+pub(crate) mod assert {
+    use crate::config::AssertOp;
+    use crate::error::{Error, Result};
+    use crate::filesystem::MemoryFS;
+    use crate::path::PatternSet;
+
+    /// Each way the files in `fs` fail the assertion `op`, if any.
+    ///
+    /// With `absent`, every file matching `op.path` is a failure. Otherwise
+    /// at least one file must match, and every match must contain
+    /// `op.contains` and match the `op.matches` regex.
+    pub(crate) fn check(op: &AssertOp, fs: &MemoryFS) -> Result<Vec<String>> {
+        let patterns = PatternSet::cached(std::slice::from_ref(&op.path))?;
+        let mut paths = fs.list_files_matching(&patterns);
+        paths.sort();
+        if op.absent {
+            return Ok(paths
+                .iter()
+                .map(|path| format!("{} must not exist", path.display()))
+                .collect());
+        }
+        if paths.is_empty() {
+            return Ok(vec![format!("no file matches {}", op.path)]);
+        }
+        let regex = op.matches.as_deref().map(regex::Regex::new).transpose()?;
+        let mut failures = Vec::new();
+        for path in &paths {
+            let Some(file) = fs.get_file(path) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&file.content);
+            if let Some(text) = &op.contains {
+                if !content.contains(text.as_str()) {
+                    failures.push(format!("{} does not contain {:?}", path.display(), text));
+                }
+            }
+            if let Some(regex) = &regex {
+                if !regex.is_match(&content) {
+                    failures.push(format!("{} does not match /{}/", path.display(), regex));
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Check every assertion in `assertions` against `fs`, each with where
+    /// it was declared, reporting every failure in a single
+    /// [`Error::AssertionsFailed`].
+    pub(crate) fn check_all(assertions: &[(String, AssertOp)], fs: &MemoryFS) -> Result<()> {
+        let mut failures = Vec::new();
+        for (origin, op) in assertions {
+            for failure in check(op, fs)? {
+                failures.push(match &op.message {
+                    Some(message) => format!("{} (asserted by {}: {})", failure, origin, message),
+                    None => format!("{} (asserted by {})", failure, origin),
+                });
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        Err(Error::AssertionsFailed { failures })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn op(path: &str) -> AssertOp {
+            AssertOp {
+                path: path.to_string(),
+                contains: None,
+                matches: None,
+                absent: false,
+                message: None,
+            }
+        }
+
+        fn fs() -> MemoryFS {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string("LICENSE", "Apache License\nVersion 2.0\n")
+                .unwrap();
+            fs.add_file_string("ci/old.yml", "jobs: {}\n").unwrap();
+            fs
+        }
+
+        #[test]
+        fn test_check_presence_and_content() {
+            let fs = fs();
+            assert!(check(&op("LICENSE"), &fs).unwrap().is_empty());
+            assert_eq!(
+                check(&op("NOTICE"), &fs).unwrap(),
+                vec!["no file matches NOTICE"]
+            );
+
+            let mut license = op("LICENSE");
+            license.contains = Some("Apache".to_string());
+            license.matches = Some(r"Version \d\.\d".to_string());
+            assert!(check(&license, &fs).unwrap().is_empty());
+            license.contains = Some("MIT".to_string());
+            license.matches = Some("^MIT".to_string());
+            assert_eq!(
+                check(&license, &fs).unwrap(),
+                vec![
+                    "LICENSE does not contain \"MIT\"",
+                    "LICENSE does not match /^MIT/"
+                ]
+            );
+        }
+
+        #[test]
+        fn test_check_absent() {
+            let fs = fs();
+            let mut absent = op("ci/*.yml");
+            absent.absent = true;
+            assert_eq!(
+                check(&absent, &fs).unwrap(),
+                vec!["ci/old.yml must not exist"]
+            );
+            absent.path = ".travis.yml".to_string();
+            assert!(check(&absent, &fs).unwrap().is_empty());
+        }
+
+        #[test]
+        fn test_check_all_reports_every_failure() {
+            let fs = fs();
+            let mut explained = op("NOTICE");
+            explained.message = Some("attribution is required".to_string());
+            let assertions = vec![
+                ("the configuration".to_string(), op("LICENSE")),
+                ("./policy".to_string(), explained),
+                ("./policy".to_string(), op("SECURITY.md")),
+            ];
+            let Err(Error::AssertionsFailed { failures }) = check_all(&assertions, &fs) else {
+                panic!("expected failed assertions");
+            };
+            assert_eq!(
+                failures,
+                vec![
+                    "no file matches NOTICE (asserted by ./policy: attribution is required)",
+                    "no file matches SECURITY.md (asserted by ./policy)",
+                ]
+            );
+        }
+    }
+}
+
+/// # Markdown Links
+///
+/// Rewrites the relative links and image references of Markdown files that
+/// `include` and `rename` move away from what they point at, either
+/// following the renames or pointing them at the upstream repository.
+pub(crate) mod links {
+    use crate::filesystem::{File, MemoryFS};
+    use regex::Regex;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::LazyLock;
+
+    /// Inline links and images: `[text](target "title")`, `![alt](<target>)`.
+    static INLINE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(!?)\[[^\]]*\]\(\s*(<[^>]*>|[^)\s]+)").unwrap());
+
+    /// Link reference definitions: `[id]: target`.
+    static DEFINITION: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^ {0,3}\[[^\]]+\]:\s*(<[^>]*>|\S+)").unwrap());
+
+    /// Whether `path` is a Markdown file, by extension.
+    pub(crate) fn is_markdown(path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                matches!(extension.to_ascii_lowercase().as_str(), "md" | "markdown")
+            })
+    }
+
+    /// Replace the link targets of `content` with what `rewrite` returns
+    /// for them, given the path part of each relative target and whether it
+    /// is an image. Fragments and queries are kept, and fenced code blocks
+    /// are left alone.
+    fn rewrite(content: &str, mut rewrite: impl FnMut(&str, bool) -> Option<String>) -> String {
+        let mut out = String::with_capacity(content.len());
+        let mut fence: Option<&str> = None;
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            match fence {
+                Some(marker) => {
+                    if trimmed.starts_with(marker) {
+                        fence = None;
+                    }
+                    out.push_str(line);
+                    continue;
+                }
+                None => {
+                    if let Some(marker) = ["```", "~~~"]
+                        .into_iter()
+                        .find(|marker| trimmed.starts_with(marker))
+                    {
+                        fence = Some(marker);
+                        out.push_str(line);
+                        continue;
+                    }
+                }
+            }
+            let targets: Vec<_> = match DEFINITION.captures(line) {
+                Some(caps) => vec![(caps.get(1).unwrap(), false)],
+                None => INLINE
+                    .captures_iter(line)
+                    .map(|caps| (caps.get(2).unwrap(), !caps[1].is_empty()))
+                    .collect(),
+            };
+            let mut last = 0;
+            for (target, image) in targets {
+                if let Some(new) = replace(target.as_str(), image, &mut rewrite) {
+                    out.push_str(&line[last..target.start()]);
+                    out.push_str(&new);
+                    last = target.end();
+                }
+            }
+            out.push_str(&line[last..]);
+        }
+        out
+    }
+
+    /// The new text of one link target, if `rewrite` changes it.
+    fn replace(
+        target: &str,
+        image: bool,
+        rewrite: &mut impl FnMut(&str, bool) -> Option<String>,
+    ) -> Option<String> {
+        if let Some(inner) = target.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            return replace(inner, image, rewrite).map(|new| format!("<{}>", new));
+        }
+        let split = target.find(['#', '?']).unwrap_or(target.len());
+        let (path, suffix) = target.split_at(split);
+        if !is_relative(path) {
+            return None;
+        }
+        rewrite(path, image).map(|new| format!("{}{}", new, suffix))
+    }
+
+    /// Whether a link path is relative to the file it is in: not empty,
+    /// not rooted and without a scheme such as `https:` or `mailto:`.
+    fn is_relative(path: &str) -> bool {
+        static SCHEME: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").unwrap());
+        !path.is_empty() && !path.starts_with('/') && !SCHEME.is_match(path)
+    }
+
+    /// The directory of `path` as `/`-separated components.
+    fn parent(path: &Path) -> Vec<String> {
+        path.parent()
+            .map(|dir| {
+                dir.components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The repository path `target` points at from `dir`, or `None` when it
+    /// leaves the repository. A trailing `/` is kept.
+    fn resolve(dir: &[String], target: &str) -> Option<String> {
+        let mut parts: Vec<&str> = dir.iter().map(String::as_str).collect();
+        for part in target.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop()?;
+                }
+                _ => parts.push(part),
+            }
+        }
+        let mut resolved = parts.join("/");
+        if target.ends_with('/') && !resolved.is_empty() {
+            resolved.push('/');
+        }
+        Some(resolved)
+    }
+
+    /// A relative link from `dir` to the repository path `to`.
+    fn relative(dir: &[String], to: &str) -> String {
+        let parts: Vec<&str> = to.split('/').collect();
+        let common = dir
+            .iter()
+            .zip(&parts)
+            .take_while(|(a, b)| a.as_str() == **b)
+            .count();
+        let mut link = "../".repeat(dir.len() - common);
+        link.push_str(&parts[common..].join("/"));
+        if link.is_empty() {
+            link.push_str("./");
+        }
+        link
+    }
+
+    /// Update the relative links of the Markdown files in `fs` after the
+    /// files in `moves` were renamed from their first path to their second,
+    /// so each link points at the same file from where it is now. Files
+    /// whose links are pointed at the upstream are left alone.
+    pub(crate) fn follow_renames(fs: &mut MemoryFS, moves: &[(PathBuf, PathBuf)]) {
+        if moves.is_empty() {
+            return;
+        }
+        let key = |path: &Path| path.to_string_lossy().replace('\\', "/");
+        let renamed: HashMap<String, String> = moves
+            .iter()
+            .map(|(old, new)| (key(old), key(new)))
+            .collect();
+        let previous: HashMap<&Path, &Path> = moves
+            .iter()
+            .map(|(old, new)| (new.as_path(), old.as_path()))
+            .collect();
+        for path in fs.list_files() {
+            if !is_markdown(&path) {
+                continue;
+            }
+            let Some(file) = fs.get_file_mut(&path) else {
+                continue;
+            };
+            let Ok(content) = std::str::from_utf8(&file.content) else {
+                continue;
+            };
+            if file.upstream_links {
+                continue;
+            }
+            let was = parent(previous.get(path.as_path()).copied().unwrap_or(&path));
+            let now = parent(&path);
+            let rewritten = rewrite(content, |target, _| {
+                let resolved = resolve(&was, target)?;
+                let destination = renamed.get(&resolved).unwrap_or(&resolved);
+                let link = relative(&now, destination);
+                (link != target).then_some(link)
+            });
+            if rewritten != content {
+                log::trace!("rewrite-links: ~ {}", path.display());
+                file.content = rewritten.into();
+                file.record("rewrite-links");
+            }
+        }
+    }
+
+    /// The web address of the repository at `url`, for hosts that serve
+    /// files under `/blob/<ref>/` and `/raw/<ref>/`, such as GitHub and
+    /// GitLab. `None` for local paths and OCI artifacts.
+    pub(crate) fn web_url(url: &str) -> Option<String> {
+        let address = if let Some(rest) = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+        {
+            rest.to_string()
+        } else if let Some(rest) = url.strip_prefix("ssh://") {
+            let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+            rest.to_string()
+        } else if let Some((user_host, path)) = url.split_once(':') {
+            let (_, host) = user_host.split_once('@')?;
+            if host.contains('/') {
+                return None;
+            }
+            format!("{}/{}", host, path)
+        } else {
+            return None;
+        };
+        let address = address.trim_end_matches('/');
+        let address = address.strip_suffix(".git").unwrap_or(address);
+        address
+            .contains('/')
+            .then(|| format!("https://{}", address))
+    }
+
+    /// Point the relative links of `file`, which was included from the
+    /// repository at `url` and `ref_`, at the files in that repository:
+    /// links at `/blob/<ref>/` and images at `/raw/<ref>/`. Links are
+    /// resolved against the path the file has in the repository, however
+    /// it was renamed since. Left as is when `url` has no web address.
+    pub(crate) fn point_at_upstream(file: &mut File, url: &str, ref_: &str) {
+        let Some(base) = web_url(url) else {
+            log::debug!(
+                "rewrite-links: {} has no web address, links left as is",
+                url
+            );
+            return;
+        };
+        let Some(source) = file.origin.as_ref().map(|origin| origin.source.clone()) else {
+            return;
+        };
+        let Ok(content) = std::str::from_utf8(&file.content) else {
+            return;
+        };
+        let dir = parent(&source);
+        let rewritten = rewrite(content, |target, image| {
+            let resolved = resolve(&dir, target)?;
+            let kind = if image { "raw" } else { "blob" };
+            Some(format!("{}/{}/{}/{}", base, kind, ref_, resolved))
+        });
+        if rewritten != content {
+            log::trace!("rewrite-links: {} -> {}", source.display(), base);
+            file.content = rewritten.into();
+            file.record("rewrite-links");
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::filesystem::FileOrigin;
+        use std::sync::Arc;
+
+        fn content(fs: &MemoryFS, path: &str) -> String {
+            String::from_utf8(fs.get_file(path).unwrap().content.to_vec()).unwrap()
+        }
+
+        #[test]
+        fn test_follow_renames() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string(
+                "handbook/guide.md",
+                "See [setup](setup.md#install), ![logo](../img/logo.png) and \
+                 [site](https://example.com).\n\n[ref]: ../README.md\n\n\
+                 ```md\n[kept](setup.md)\n```\n",
+            )
+            .unwrap();
+            fs.add_file_string("handbook/install.md", "# Install\n")
+                .unwrap();
+            fs.add_file_string("README.md", "Read [the guide](docs/guide.md).\n")
+                .unwrap();
+            let moves = vec![
+                (
+                    PathBuf::from("docs/guide.md"),
+                    PathBuf::from("handbook/guide.md"),
+                ),
+                (
+                    PathBuf::from("docs/setup.md"),
+                    PathBuf::from("handbook/install.md"),
+                ),
+            ];
+            follow_renames(&mut fs, &moves);
+            assert_eq!(
+                content(&fs, "handbook/guide.md"),
+                "See [setup](install.md#install), ![logo](../img/logo.png) and \
+                 [site](https://example.com).\n\n[ref]: ../README.md\n\n\
+                 ```md\n[kept](setup.md)\n```\n"
+            );
+            assert_eq!(
+                content(&fs, "README.md"),
+                "Read [the guide](handbook/guide.md).\n"
+            );
+        }
+
+        #[test]
+        fn test_follow_renames_to_another_depth() {
+            let mut fs = MemoryFS::new();
+            fs.add_file_string(
+                "CONTRIBUTING.md",
+                "See [the policy](SECURITY.md) and [docs](docs/).\n",
+            )
+            .unwrap();
+            let moves = vec![(
+                PathBuf::from(".github/CONTRIBUTING.md"),
+                PathBuf::from("CONTRIBUTING.md"),
+            )];
+            follow_renames(&mut fs, &moves);
+            assert_eq!(
+                content(&fs, "CONTRIBUTING.md"),
+                "See [the policy](.github/SECURITY.md) and [docs](.github/docs/).\n"
+            );
+        }
+
+        #[test]
+        fn test_point_at_upstream() {
+            let mut file = File::from_string(
+                "[Setup](setup.md#install), ![Logo](<../img/logo.png>), [top](#top), \
+                 [out](../../x.md)\n",
+            );
+            file.origin = Some(Arc::new(FileOrigin {
+                source: PathBuf::from("docs/guide.md"),
+                ..FileOrigin::default()
+            }));
+            point_at_upstream(&mut file, "git@github.com:org/docs.git", "v1.2.0");
+            assert_eq!(
+                String::from_utf8(file.content.to_vec()).unwrap(),
+                "[Setup](https://github.com/org/docs/blob/v1.2.0/docs/setup.md#install), \
+                 ![Logo](<https://github.com/org/docs/raw/v1.2.0/img/logo.png>), [top](#top), \
+                 [out](../../x.md)\n"
+            );
+
+            let mut local = File::from_string("[Setup](setup.md)\n");
+            point_at_upstream(&mut local, "../docs", "HEAD");
+            assert_eq!(local.content, b"[Setup](setup.md)\n");
+        }
+
+        #[test]
+        fn test_web_url() {
+            for url in [
+                "https://github.com/org/docs",
+                "https://github.com/org/docs.git",
+                "git@github.com:org/docs.git",
+                "ssh://git@github.com/org/docs",
+            ] {
+                assert_eq!(
+                    web_url(url).as_deref(),
+                    Some("https://github.com/org/docs"),
+                    "{}",
+                    url
+                );
+            }
+            assert_eq!(web_url("../docs"), None);
+            assert_eq!(web_url("/srv/docs"), None);
+            assert_eq!(web_url("oci://ghcr.io/org/docs"), None);
+        }
+    }
+}
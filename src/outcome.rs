@@ -0,0 +1,299 @@
+//! # Run Outcomes
+//!
+//! How a command ended, as one of a few [`Outcome`]s that map to the exit
+//! codes in [`crate::exit_codes`], so that scripts can branch on what
+//! happened instead of parsing output.
+//!
+//! Commands that run the pipeline [`record`] what they found as they go:
+//! warnings, drift, how many files they changed. When the command returns,
+//! the CLI ends with the exit code of the most severe outcome recorded and
+//! prints a [`Summary`] block. A command that fails is classified by its
+//! error instead (see [`Outcome::of_error`]); commands that want a failure
+//! classified otherwise return a [`Failure`].
+//!
+//! ```
+//! use common_repo::outcome::{Outcome, Summary};
+//!
+//! let mut summary = Summary::new("diff");
+//! summary.record(Outcome::Warnings);
+//! summary.record(Outcome::Drift);
+//! summary.record(Outcome::Clean);
+//! assert_eq!(summary.outcome, Outcome::Drift);
+//! assert_eq!(summary.outcome.exit_code(), 2);
+//! ```
+
+use std::fmt;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::exit_codes;
+
+/// How a command ended, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// Everything went as configured.
+    Clean,
+    /// Done, with warnings.
+    Warnings,
+    /// Local files differ from what the configuration produces.
+    Drift,
+    /// Local files changed since the plan being applied was computed.
+    StalePlan,
+    /// Files could not be merged or written as configured.
+    Conflicts,
+    /// An upstream could not be fetched.
+    FetchFailed,
+    /// The configuration is missing or invalid.
+    ConfigError,
+    /// Any other error.
+    Error,
+}
+
+impl Outcome {
+    /// The exit code for this outcome.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Clean => exit_codes::SUCCESS,
+            Outcome::Warnings => exit_codes::WARNINGS,
+            Outcome::Drift | Outcome::StalePlan => exit_codes::DRIFT,
+            Outcome::Conflicts => exit_codes::CONFLICTS,
+            Outcome::FetchFailed => exit_codes::FETCH_FAILED,
+            Outcome::ConfigError => exit_codes::CONFIG_ERROR,
+            Outcome::Error => exit_codes::ERROR,
+        }
+    }
+
+    /// The outcome's name, as in the JSON summary.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Clean => "clean",
+            Outcome::Warnings => "warnings",
+            Outcome::Drift => "drift",
+            Outcome::StalePlan => "stale-plan",
+            Outcome::Conflicts => "conflicts",
+            Outcome::FetchFailed => "fetch-failed",
+            Outcome::ConfigError => "config-error",
+            Outcome::Error => "error",
+        }
+    }
+
+    /// The outcome of a command that failed with `error`.
+    pub fn of_error(error: &Error) -> Self {
+        match error {
+            Error::ConfigParse { .. }
+            | Error::Yaml(_)
+            | Error::UrlParse(_)
+            | Error::Semver(_)
+            | Error::VersionRequirement { .. }
+            | Error::CycleDetected { .. }
+            | Error::RequirementsNotMet { .. } => Outcome::ConfigError,
+            Error::GitClone { .. }
+            | Error::Network { .. }
+            | Error::LocalPathNotFound { .. }
+            | Error::LocalPathNotDirectory { .. }
+            | Error::Download { .. }
+            | Error::ChecksumMismatch { .. }
+            | Error::OciPull { .. }
+            | Error::DigestMismatch { .. }
+            | Error::SignatureVerification { .. } => Outcome::FetchFailed,
+            Error::Merge { .. }
+            | Error::MergeTypeMismatch { .. }
+            | Error::MergeConflict { .. }
+            | Error::SymlinkedOutput { .. }
+            | Error::ReadOnlyOutput { .. }
+            | Error::AssertionsFailed { .. } => Outcome::Conflicts,
+            Error::LockedFilesModified { .. } => Outcome::Drift,
+            Error::StalePlan { .. } => Outcome::StalePlan,
+            _ => Outcome::Error,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Outcome::Clean => "clean",
+            Outcome::Warnings => "completed with warnings",
+            Outcome::Drift => "drift detected",
+            Outcome::StalePlan => "stale plan",
+            Outcome::Conflicts => "conflicts",
+            Outcome::FetchFailed => "fetch failed",
+            Outcome::ConfigError => "configuration error",
+            Outcome::Error => "error",
+        })
+    }
+}
+
+/// A failure that ends a command with `outcome` rather than the one its
+/// cause would get.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct Failure {
+    /// How the command ended.
+    pub outcome: Outcome,
+    /// What went wrong.
+    pub message: String,
+}
+
+impl Failure {
+    /// A failure with `outcome`, described by `message`.
+    pub fn new(outcome: Outcome, message: impl Into<String>) -> Self {
+        Self {
+            outcome,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a command found, printed when it ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Summary {
+    /// The command that ran.
+    pub command: &'static str,
+    /// The most severe outcome recorded.
+    pub outcome: Outcome,
+    /// The exit code of `outcome`.
+    pub exit_code: i32,
+    /// How many warnings were reported.
+    pub warnings: usize,
+    /// How many files the command changed or found changed, when it
+    /// counts them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<usize>,
+    /// The code of the error the command failed with, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+}
+
+impl Summary {
+    /// A clean run of `command`.
+    pub fn new(command: &'static str) -> Self {
+        Self {
+            command,
+            outcome: Outcome::Clean,
+            exit_code: exit_codes::SUCCESS,
+            warnings: 0,
+            files: None,
+            error_code: None,
+        }
+    }
+
+    /// Record `outcome`, keeping the most severe one.
+    pub fn record(&mut self, outcome: Outcome) {
+        self.outcome = self.outcome.max(outcome);
+        self.exit_code = self.outcome.exit_code();
+    }
+
+    /// The summary as a block of `key: value` lines.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Summary\n  command:   {}\n  outcome:   {}\n  exit code: {}\n  warnings:  {}\n",
+            self.command, self.outcome, self.exit_code, self.warnings
+        );
+        if let Some(files) = self.files {
+            text.push_str(&format!("  files:     {}\n", files));
+        }
+        if let Some(code) = self.error_code {
+            text.push_str(&format!("  error:     {}\n", code));
+        }
+        text
+    }
+
+    /// The summary as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a summary is serializable")
+    }
+}
+
+/// The summary of the command running in this process.
+static CURRENT: Mutex<Option<Summary>> = Mutex::new(None);
+
+/// Start the summary of `command`. Commands that never call this end
+/// without one.
+pub fn begin(command: &'static str) {
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Summary::new(command));
+}
+
+/// Record `outcome` for the running command.
+pub fn record(outcome: Outcome) {
+    update(|summary| summary.record(outcome));
+}
+
+/// Record that the running command reported `count` warnings.
+pub fn record_warnings(count: usize) {
+    if count > 0 {
+        update(|summary| {
+            summary.warnings += count;
+            summary.record(Outcome::Warnings);
+        });
+    }
+}
+
+/// Record that the running command changed, or found changed, `count`
+/// files.
+pub fn record_files(count: usize) {
+    update(|summary| summary.files = Some(summary.files.unwrap_or(0) + count));
+}
+
+/// The summary of the command, if it started one, ending it.
+pub fn finish() -> Option<Summary> {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+fn update(f: impl FnOnce(&mut Summary)) {
+    if let Some(summary) = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        f(summary);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_of_error() {
+        let clone = Error::GitClone {
+            url: "https://example.com/repo".to_string(),
+            r#ref: "main".to_string(),
+            message: "not found".to_string(),
+            hint: None,
+        };
+        assert_eq!(Outcome::of_error(&clone), Outcome::FetchFailed);
+        let parse = Error::ConfigParse {
+            message: "bad".to_string(),
+            hint: None,
+        };
+        assert_eq!(Outcome::of_error(&parse), Outcome::ConfigError);
+        let conflict = Error::ReadOnlyOutput {
+            paths: vec!["a".to_string()],
+        };
+        assert_eq!(Outcome::of_error(&conflict), Outcome::Conflicts);
+        let stale = Error::StalePlan {
+            paths: vec!["a".to_string()],
+        };
+        assert_eq!(Outcome::of_error(&stale), Outcome::StalePlan);
+        assert_eq!(Outcome::StalePlan.exit_code(), 2);
+        let io = Error::Io(std::io::Error::other("disk"));
+        assert_eq!(Outcome::of_error(&io), Outcome::Error);
+    }
+
+    #[test]
+    fn test_summary_text_and_json() {
+        let mut summary = Summary::new("apply");
+        summary.warnings = 2;
+        summary.files = Some(3);
+        summary.record(Outcome::Warnings);
+        assert_eq!(
+            summary.to_text(),
+            "Summary\n  command:   apply\n  outcome:   completed with warnings\n  exit code: 1\n  warnings:  2\n  files:     3\n"
+        );
+        assert_eq!(
+            summary.to_json(),
+            r#"{"command":"apply","outcome":"warnings","exit-code":1,"warnings":2,"files":3}"#
+        );
+    }
+}
@@ -4,10 +4,21 @@
 //! including color and emoji support based on terminal capabilities and
 //! user preferences.
 //!
+//! It is also the single place status and warning messages are written from.
+//! Text comes from the [`crate::messages`] catalog and goes out through
+//! [`status`], [`progress`], [`warning`] or [`error`], which honor the
+//! process-wide configuration installed with [`set_global`]:
+//!
+//! - In quiet mode only the error and annotation channels are written.
+//! - A sink installed with [`set_sink`] receives every message instead of
+//!   stdout/stderr, so library users can capture or discard output.
+//!
 //! ## Respecting User Preferences
 //!
 //! The module respects the following environment variables and flags:
 //! - `--color=never|always|auto` - CLI flag for color control
+//! - `--no-color` - Same as `--color=never`
+//! - `--quiet` - Suppresses status and warning output
 //! - `NO_COLOR` - Disables colors when set (per https://no-color.org/)
 //! - `CLICOLOR=0` - Disables colors
 //! - `CLICOLOR_FORCE=1` - Forces colors even in non-TTY
@@ -25,12 +36,17 @@
 //! ```
 
 use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::RwLock;
 
 /// Output configuration for controlling colors and emojis.
 #[derive(Debug, Clone)]
 pub struct OutputConfig {
     /// Whether colors and emojis should be used in output.
     pub use_color: bool,
+    /// Whether status and warning output is suppressed.
+    pub quiet: bool,
 }
 
 impl OutputConfig {
@@ -56,7 +72,16 @@ impl OutputConfig {
             _ => Self::detect_color_support(),
         };
 
-        Self { use_color }
+        Self {
+            use_color,
+            quiet: false,
+        }
+    }
+
+    /// Return this configuration with quiet mode set.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
     }
 
     /// Detect whether color output is supported based on environment.
@@ -89,13 +114,19 @@ impl OutputConfig {
     /// Create a configuration with colors always enabled.
     #[cfg(test)]
     pub fn with_color() -> Self {
-        Self { use_color: true }
+        Self {
+            use_color: true,
+            quiet: false,
+        }
     }
 
     /// Create a configuration with colors always disabled.
     #[cfg(test)]
     pub fn without_color() -> Self {
-        Self { use_color: false }
+        Self {
+            use_color: false,
+            quiet: false,
+        }
     }
 }
 
@@ -128,6 +159,113 @@ pub fn emoji<'a>(config: &OutputConfig, emoji_str: &'a str, plain: &'a str) -> &
     }
 }
 
+/// The stream a message is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Progress and result summaries, written to stdout.
+    Status,
+    /// Non-fatal problems, written to stderr.
+    Warning,
+    /// Failures, written to stderr. Never suppressed by quiet mode.
+    Error,
+    /// GitHub Actions workflow commands (see [`crate::github`]), written to
+    /// stdout. Never suppressed by quiet mode.
+    Annotation,
+    /// The summary block that ends a command (see [`crate::outcome`]),
+    /// written to stderr so that it never mixes with the command's output.
+    Summary,
+}
+
+/// Receiver for messages in place of stdout/stderr.
+///
+/// Called with each complete line. Partial lines written by [`progress`]
+/// are passed without a trailing newline.
+pub type Sink = Box<dyn Fn(Channel, &str) + Send + Sync>;
+
+static GLOBAL: RwLock<Option<OutputConfig>> = RwLock::new(None);
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+/// Install the process-wide output configuration.
+pub fn set_global(config: OutputConfig) {
+    *GLOBAL.write().unwrap_or_else(|e| e.into_inner()) = Some(config);
+}
+
+/// The process-wide output configuration.
+///
+/// Falls back to [`OutputConfig::default`] when none has been installed.
+pub fn global() -> OutputConfig {
+    GLOBAL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Route all messages to `sink`, or back to stdout/stderr with `None`.
+pub fn set_sink(sink: Option<Sink>) {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+
+fn emit(channel: Channel, message: &dyn fmt::Display, newline: bool) {
+    if matches!(
+        channel,
+        Channel::Status | Channel::Warning | Channel::Summary
+    ) && global().quiet
+    {
+        return;
+    }
+    let message = crate::secrets::mask(&message.to_string()).into_owned();
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = sink.as_ref() {
+        sink(channel, &message);
+        return;
+    }
+    match (channel, newline) {
+        (Channel::Status | Channel::Annotation, true) => println!("{}", message),
+        (Channel::Status | Channel::Annotation, false) => {
+            print!("{}", message);
+            let _ = io::stdout().flush();
+        }
+        (_, true) => eprintln!("{}", message),
+        (_, false) => eprint!("{}", message),
+    }
+}
+
+/// Write a status line.
+pub fn status(message: impl fmt::Display) {
+    emit(Channel::Status, &message, true);
+}
+
+/// Write the start of a status line, to be finished by [`status`].
+pub fn progress(message: impl fmt::Display) {
+    emit(Channel::Status, &message, false);
+}
+
+/// Write an empty status line.
+pub fn blank_line() {
+    status("");
+}
+
+/// Write a warning line.
+pub fn warning(message: impl fmt::Display) {
+    emit(Channel::Warning, &message, true);
+}
+
+/// Write an error line.
+pub fn error(message: impl fmt::Display) {
+    emit(Channel::Error, &message, true);
+}
+
+/// Write the summary block that ends a command.
+pub fn summary(message: impl fmt::Display) {
+    emit(Channel::Summary, &message, true);
+}
+
+/// Write a workflow command line.
+pub fn annotation(message: impl fmt::Display) {
+    emit(Channel::Annotation, &message, true);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,4 +293,36 @@ mod tests {
         let config = OutputConfig::without_color();
         assert_eq!(emoji(&config, "🔍", "[SCAN]"), "[SCAN]");
     }
+
+    #[test]
+    fn test_sink_captures_messages_and_quiet_keeps_errors() {
+        use std::sync::{Arc, Mutex};
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink_captured = Arc::clone(&captured);
+        set_sink(Some(Box::new(move |channel, message| {
+            if message.starts_with("sink test") {
+                sink_captured
+                    .lock()
+                    .unwrap()
+                    .push((channel, message.to_string()));
+            }
+        })));
+
+        status("sink test: status");
+        set_global(OutputConfig::without_color().with_quiet(true));
+        status("sink test: quiet status");
+        warning("sink test: quiet warning");
+        error("sink test: quiet error");
+        set_global(OutputConfig::without_color());
+        set_sink(None);
+
+        assert_eq!(
+            *captured.lock().unwrap(),
+            vec![
+                (Channel::Status, "sink test: status".to_string()),
+                (Channel::Error, "sink test: quiet error".to_string()),
+            ]
+        );
+    }
 }
@@ -7,8 +7,12 @@
 //!
 //! ## Key Functions
 //!
-//! - **`glob_match`**: A simple wrapper around the `glob` crate to check if a
-//!   given path matches a glob pattern.
+//! - **`glob_match`**: Checks if a given path matches a glob pattern.
+//!
+//! - **`PatternSet`**: Glob patterns compiled once into a single matcher, so
+//!   matching a list of patterns against many paths does not recompile or
+//!   re-walk each pattern per path. Operators share the compiled sets of
+//!   their patterns through [`PatternSet::cached`].
 //!
 //! - **`regex_rename`**: A powerful function for renaming files using regular
 //!   expressions with capture groups. It supports a `$1`, `$2`, etc., syntax for
@@ -20,11 +24,168 @@
 //!
 //! - **`strip_url_scheme`**: Removes the scheme (e.g., `https://`) from a URL,
 //!   returning the remainder. Used for normalizing URLs in pattern matching.
+//!
+//! - **`validate_relative_path`**: Rejects absolute paths and `..` sequences
+//!   that would escape the root. Every path stored in a `MemoryFS` goes
+//!   through this check so that nothing can be written outside the output
+//!   directory.
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::sync::{Arc, LazyLock, Mutex};
 
 use crate::error::{Error, Result};
 use glob::Pattern;
 use regex::Regex;
 
+/// Compiled pattern sets, by their patterns, shared across operations and
+/// repositories.
+static PATTERN_SETS: LazyLock<Mutex<HashMap<Vec<String>, Arc<PatternSet>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A list of glob patterns compiled into one matcher.
+///
+/// A path matches the set when it matches any of the patterns. Patterns use
+/// the syntax of the `glob` crate: `*` and `?` also match `/`, `**` matches
+/// any number of directories, and `[...]` is a character class. Braces
+/// match themselves, as they do in the `glob` crate.
+///
+/// # Examples
+///
+/// ```
+/// use common_repo::path::PatternSet;
+/// use std::path::Path;
+///
+/// let set = PatternSet::new(&["src/**/*.rs", "*.md"]).unwrap();
+/// assert!(set.is_match(Path::new("src/lib.rs")));
+/// assert!(set.is_match(Path::new("README.md")));
+/// assert!(!set.is_match(Path::new("Cargo.toml")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    set: globset::GlobSet,
+}
+
+impl PatternSet {
+    /// Compiles `patterns` into one matcher.
+    ///
+    /// Returns [`Error::Glob`] for the first pattern that is not a valid
+    /// glob.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            // Validated with the `glob` crate so that the patterns accepted,
+            // and the errors for the rest, are the same as `validate` reports.
+            Pattern::new(pattern).map_err(Error::Glob)?;
+            let glob = globset::GlobBuilder::new(&literal_braces(pattern))
+                .literal_separator(false)
+                .backslash_escape(false)
+                .build()
+                .map_err(|e| Error::Path {
+                    message: format!("invalid glob pattern '{}': {}", pattern, e),
+                })?;
+            builder.add(glob);
+        }
+        let set = builder.build().map_err(|e| Error::Path {
+            message: format!("invalid glob patterns: {}", e),
+        })?;
+        Ok(Self { set })
+    }
+
+    /// The compiled set of `patterns`, compiled on first use and shared by
+    /// every later caller with the same patterns.
+    pub fn cached(patterns: &[String]) -> Result<Arc<Self>> {
+        let mut sets = PATTERN_SETS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(set) = sets.get(patterns) {
+            return Ok(Arc::clone(set));
+        }
+        let set = Arc::new(Self::new(patterns)?);
+        sets.insert(patterns.to_vec(), Arc::clone(&set));
+        Ok(set)
+    }
+
+    /// Whether `path` matches any of the patterns.
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.set.is_match(path.as_ref())
+    }
+}
+
+/// `pattern`, a valid `glob` pattern, with the braces outside character
+/// classes wrapped in classes of their own, so that `globset` matches them
+/// literally as `glob` does instead of as alternatives.
+fn literal_braces(pattern: &str) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => {
+                out.push('[');
+                out.push(c);
+                out.push(']');
+            }
+            '[' => {
+                // Copy the class through its closing `]`. A `]` right after
+                // the opening `[` or `[!` is a member, not the end.
+                out.push(c);
+                if chars.peek() == Some(&'!') {
+                    out.extend(chars.next());
+                }
+                if chars.peek() == Some(&']') {
+                    out.extend(chars.next());
+                }
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Checks that a path is relative and stays inside its root.
+///
+/// Absolute paths (including Windows drive and UNC prefixes) are rejected,
+/// as is any `..` component that climbs above the starting directory.
+/// `..` that stays inside the root (e.g. `a/../b`) and `.` components are
+/// accepted.
+///
+/// # Examples
+///
+/// ```
+/// use common_repo::path::validate_relative_path;
+/// use std::path::Path;
+///
+/// assert!(validate_relative_path(Path::new("src/main.rs")).is_ok());
+/// assert!(validate_relative_path(Path::new("a/../b.txt")).is_ok());
+/// assert!(validate_relative_path(Path::new("../outside.txt")).is_err());
+/// assert!(validate_relative_path(Path::new("/etc/passwd")).is_err());
+/// ```
+pub fn validate_relative_path(path: &Path) -> Result<()> {
+    let mut depth: usize = 0;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => {
+                return Err(Error::Path {
+                    message: format!("Absolute path not allowed: {}", path.display()),
+                });
+            }
+            Component::ParentDir => {
+                depth = depth.checked_sub(1).ok_or_else(|| Error::Path {
+                    message: format!("Path escapes the output root: {}", path.display()),
+                })?;
+            }
+            Component::CurDir => {}
+            Component::Normal(_) => depth += 1,
+        }
+    }
+    Ok(())
+}
+
 /// Strips the URL scheme (e.g., `https://`, `git://`) from a URL.
 ///
 /// Returns everything after `://` if present, or the original string if no
@@ -93,6 +254,12 @@ pub fn glob_match(pattern: &str, path: &str) -> Result<bool> {
 /// ```
 pub fn regex_rename(pattern: &str, replacement: &str, path: &str) -> Result<Option<String>> {
     let regex = Regex::new(pattern).map_err(Error::Regex)?;
+    Ok(regex_rename_with(&regex, replacement, path))
+}
+
+/// Like [`regex_rename`], with the pattern already compiled, for renaming
+/// many paths with one pattern.
+pub fn regex_rename_with(regex: &Regex, replacement: &str, path: &str) -> Option<String> {
     if let Some(captures) = regex.captures(path) {
         // Use captures to expand $1, $2, etc. in the replacement string
         let mut expanded_replacement = String::new();
@@ -121,9 +288,9 @@ pub fn regex_rename(pattern: &str, replacement: &str, path: &str) -> Result<Opti
         result.push_str(&expanded_replacement);
         result.push_str(&path[matched_range.end..]);
 
-        Ok(Some(result))
+        Some(result)
     } else {
-        Ok(None)
+        None
     }
 }
 
@@ -311,6 +478,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_relative_path() {
+        use std::path::Path;
+
+        assert!(validate_relative_path(Path::new("file.txt")).is_ok());
+        assert!(validate_relative_path(Path::new("./dir/file.txt")).is_ok());
+        assert!(validate_relative_path(Path::new("dir/../file.txt")).is_ok());
+
+        assert!(validate_relative_path(Path::new("/abs/file.txt")).is_err());
+        assert!(validate_relative_path(Path::new("../file.txt")).is_err());
+        assert!(validate_relative_path(Path::new("dir/../../file.txt")).is_err());
+        assert!(validate_relative_path(Path::new("a/./../..")).is_err());
+
+        let err = validate_relative_path(Path::new("../x")).unwrap_err();
+        assert!(err.to_string().contains("escapes the output root"));
+    }
+
     #[test]
     fn test_glob_match_errors() {
         // Test invalid glob patterns
@@ -318,6 +502,66 @@ mod tests {
         assert!(glob_match("**", "test").is_ok()); // Valid glob
     }
 
+    #[test]
+    fn test_pattern_set_matches_like_glob() {
+        let set = PatternSet::new(&[
+            "src/**/*.rs",
+            "*.md",
+            "[a-c].txt",
+            "docs/{guide,api}/*",
+            "[{]x[}]",
+            "[]{}]y",
+        ])
+        .unwrap();
+        assert!(set.is_match("src/main.rs"));
+        assert!(set.is_match("src/a/b/lib.rs"));
+        // `*` also matches `/`, as in the glob crate's default options
+        assert!(set.is_match("docs/README.md"));
+        assert!(set.is_match("b.txt"));
+        assert!(!set.is_match("d.txt"));
+        // Braces are literal, as in the glob crate
+        assert!(set.is_match("docs/{guide,api}/index.html"));
+        assert!(!set.is_match("docs/api/index.html"));
+        assert!(set.is_match("{x}"));
+        assert!(set.is_match("}y"));
+        assert!(set.is_match("]y"));
+        assert!(!set.is_match("Cargo.toml"));
+        for path in ["docs/{guide,api}/index.html", "docs/api/index.html", "{x}"] {
+            assert_eq!(
+                set.is_match(path),
+                ["docs/{guide,api}/*", "[{]x[}]"]
+                    .iter()
+                    .any(|pattern| glob_match(pattern, path).unwrap()),
+                "{}",
+                path
+            );
+        }
+
+        assert!(matches!(
+            PatternSet::new(&["ok", "*[invalid"]),
+            Err(Error::Glob(_))
+        ));
+    }
+
+    #[test]
+    fn test_pattern_set_cached_is_shared() {
+        let patterns = vec!["cached/**".to_string()];
+        let a = PatternSet::cached(&patterns).unwrap();
+        let b = PatternSet::cached(&patterns).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(a.is_match("cached/x"));
+    }
+
+    #[test]
+    fn test_regex_rename_with_compiled_pattern() {
+        let regex = Regex::new(r"(\w+)\.rs").unwrap();
+        assert_eq!(
+            regex_rename_with(&regex, "$1.bak", "main.rs"),
+            Some("main.bak".to_string())
+        );
+        assert_eq!(regex_rename_with(&regex, "$1.bak", "main.js"), None);
+    }
+
     #[test]
     fn test_strip_url_scheme() {
         // Standard schemes
@@ -0,0 +1,289 @@
+//! Checkpoints for resuming a failed pull.
+//!
+//! A pull saves the output of its expensive phases to a run directory under
+//! the cache root, `runs/<key>/`, where the key hashes the working directory
+//! and the configuration. Each pipeline block (the source block, then each
+//! `self:` block) has its own checkpoints:
+//!
+//! - `block-<n>/tree.json`: the [`RepoTree`] from Phase 1, so discovery and
+//!   cloning are not repeated.
+//! - `block-<n>/composite.json`: a manifest of the final filesystem, just
+//!   before Phase 6 writes it, with the paths of the files it propagated.
+//!   File contents are stored once, by SHA-256, in `blobs/`.
+//!
+//! With `--resume` the latest checkpoint of a block is loaded instead of
+//! running the phases that produced it. A checkpoint that cannot be read is
+//! ignored and the phases run again. The run directory is removed once a
+//! pull completes.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{RepoNode, RepoTree};
+use crate::config::{normalize_include_if_exists, Schema};
+use crate::error::Result;
+use crate::filesystem::{Directory, File, MemoryFS};
+use crate::http::sha256_hex;
+
+/// A file in a composite manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestFile {
+    path: PathBuf,
+    sha256: String,
+    permissions: u32,
+}
+
+/// A directory in a composite manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestDirectory {
+    path: PathBuf,
+    permissions: u32,
+}
+
+/// The filesystem saved by [`RunDir::save_composite`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: Vec<ManifestFile>,
+    directories: Vec<ManifestDirectory>,
+    #[serde(default)]
+    propagated: Vec<PathBuf>,
+}
+
+/// The checkpoint directory of one pull.
+#[derive(Debug, Clone)]
+pub struct RunDir {
+    dir: PathBuf,
+    resume: bool,
+}
+
+impl RunDir {
+    /// The run directory for pulling `config` into `working_dir`.
+    ///
+    /// When `resume` is false, checkpoints are saved but never loaded.
+    pub fn new(cache_root: &Path, working_dir: &Path, config: &Schema, resume: bool) -> Self {
+        let working_dir = working_dir
+            .canonicalize()
+            .unwrap_or_else(|_| working_dir.to_path_buf());
+        let config = serde_json::to_string(config).unwrap_or_default();
+        let key = sha256_hex(format!("{}\n{}", working_dir.display(), config).as_bytes());
+        Self {
+            dir: cache_root.join("runs").join(&key[..16]),
+            resume,
+        }
+    }
+
+    /// The directory the checkpoints are written to.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether a previous run left checkpoints behind.
+    pub fn exists(&self) -> bool {
+        self.dir.is_dir()
+    }
+
+    fn block_dir(&self, block: usize) -> PathBuf {
+        self.dir.join(format!("block-{}", block))
+    }
+
+    /// The saved repository tree of `block`, when resuming.
+    pub fn tree(&self, block: usize) -> Option<RepoTree> {
+        let mut tree: RepoTree = self.load(&self.block_dir(block).join("tree.json"))?;
+        normalize(&mut tree.root);
+        Some(tree)
+    }
+
+    /// Save the repository tree of `block`.
+    pub fn save_tree(&self, block: usize, tree: &RepoTree) {
+        self.save(&self.block_dir(block).join("tree.json"), tree);
+    }
+
+    /// The saved final filesystem of `block` and the paths of its
+    /// propagated files, when resuming.
+    pub fn composite(&self, block: usize) -> Option<(MemoryFS, Vec<PathBuf>)> {
+        let manifest: Manifest = self.load(&self.block_dir(block).join("composite.json"))?;
+        let mut fs = MemoryFS::new();
+        for entry in manifest.files {
+            let content = fs::read(self.dir.join("blobs").join(&entry.sha256)).ok()?;
+            if sha256_hex(&content) != entry.sha256 {
+                return None;
+            }
+            let mut file = File::new(content);
+            file.permissions = entry.permissions;
+            fs.add_file(&entry.path, file).ok()?;
+        }
+        for entry in manifest.directories {
+            let mut directory = Directory::new();
+            directory.permissions = entry.permissions;
+            fs.add_directory(&entry.path, directory).ok()?;
+        }
+        Some((fs, manifest.propagated))
+    }
+
+    /// Save the final filesystem of `block` and the paths of its propagated
+    /// files.
+    pub fn save_composite(&self, block: usize, composite: &MemoryFS, propagated: &[PathBuf]) {
+        if let Err(e) = self.write_composite(block, composite, propagated) {
+            debug!("failed to save checkpoint in {}: {}", self.dir.display(), e);
+        }
+    }
+
+    fn write_composite(
+        &self,
+        block: usize,
+        composite: &MemoryFS,
+        propagated: &[PathBuf],
+    ) -> Result<()> {
+        let blobs = self.dir.join("blobs");
+        fs::create_dir_all(&blobs)?;
+        let mut manifest = Manifest {
+            propagated: propagated.to_vec(),
+            ..Manifest::default()
+        };
+        for (path, file) in composite.files() {
+            let sha256 = sha256_hex(&file.content);
+            let blob = blobs.join(&sha256);
+            if !blob.exists() {
+                fs::write(&blob, &file.content)?;
+            }
+            manifest.files.push(ManifestFile {
+                path: path.clone(),
+                sha256,
+                permissions: file.permissions,
+            });
+        }
+        for (path, directory) in composite.directories() {
+            manifest.directories.push(ManifestDirectory {
+                path: path.clone(),
+                permissions: directory.permissions,
+            });
+        }
+        self.save(&self.block_dir(block).join("composite.json"), &manifest);
+        Ok(())
+    }
+
+    /// Remove the run directory after a successful pull.
+    pub fn finish(&self) {
+        if self.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                debug!("failed to remove {}: {}", self.dir.display(), e);
+            }
+        }
+    }
+
+    fn load<T: for<'de> Deserialize<'de>>(&self, path: &Path) -> Option<T> {
+        if !self.resume {
+            return None;
+        }
+        let content = fs::read(path).ok()?;
+        match serde_json::from_slice(&content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                debug!("ignoring unreadable checkpoint {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Write `value` to `path`. Checkpoints are an optimization, so a
+    /// failure to save one does not fail the pull.
+    fn save<T: Serialize>(&self, path: &Path, value: &T) {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|()| serde_json::to_vec(value).map_err(|e| e.to_string()))
+            .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            debug!("failed to save checkpoint {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Restore the `include` fields that are not part of the serialized form.
+fn normalize(node: &mut RepoNode) {
+    normalize_include_if_exists(&mut node.operations);
+    for child in &mut node.children {
+        normalize(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{IfExists, IncludeOp, Operation};
+    use tempfile::TempDir;
+
+    fn config() -> Schema {
+        vec![Operation::Include {
+            include: IncludeOp {
+                patterns: vec!["**".to_string()],
+                if_exists: IfExists::Preserve,
+                rewrite_links: None,
+            },
+            if_exists: IfExists::Preserve,
+            rewrite_links: None,
+        }]
+    }
+
+    #[test]
+    fn test_tree_round_trip() {
+        let cache = TempDir::new().unwrap();
+        let mut root = RepoNode::new("local".to_string(), String::new(), vec![]);
+        root.children.push(RepoNode::new(
+            "https://github.com/org/shared".to_string(),
+            "v1.0.0".to_string(),
+            config(),
+        ));
+        let tree = RepoTree::new(root);
+
+        let run = RunDir::new(cache.path(), cache.path(), &config(), false);
+        run.save_tree(0, &tree);
+        assert!(run.tree(0).is_none(), "checkpoints load only when resuming");
+
+        let run = RunDir::new(cache.path(), cache.path(), &config(), true);
+        let loaded = run.tree(0).unwrap();
+        assert_eq!(loaded.root, tree.root);
+        assert_eq!(loaded.all_repos, tree.all_repos);
+        assert!(run.tree(1).is_none());
+    }
+
+    #[test]
+    fn test_composite_round_trip() {
+        let cache = TempDir::new().unwrap();
+        let mut composite = MemoryFS::new();
+        composite.add_file_string("a.txt", "same\n").unwrap();
+        let mut script = File::new(b"same\n".to_vec());
+        script.permissions = 0o755;
+        composite.add_file("bin/run", script).unwrap();
+        composite.add_directory("empty", Directory::new()).unwrap();
+
+        let run = RunDir::new(cache.path(), cache.path(), &config(), true);
+        run.save_composite(0, &composite, &[PathBuf::from("a.txt")]);
+        assert_eq!(fs::read_dir(run.path().join("blobs")).unwrap().count(), 1);
+
+        let (loaded, propagated) = run.composite(0).unwrap();
+        assert_eq!(propagated, vec![PathBuf::from("a.txt")]);
+        assert_eq!(loaded.get_file("a.txt").unwrap().content, b"same\n");
+        assert_eq!(loaded.get_file("bin/run").unwrap().permissions, 0o755);
+        assert!(loaded
+            .directories()
+            .any(|(path, _)| path == Path::new("empty")));
+
+        run.finish();
+        assert!(!run.exists());
+        assert!(run.composite(0).is_none());
+    }
+
+    #[test]
+    fn test_run_dir_depends_on_config() {
+        let cache = TempDir::new().unwrap();
+        let run = RunDir::new(cache.path(), cache.path(), &config(), true);
+        let other = RunDir::new(cache.path(), cache.path(), &vec![], true);
+        assert_ne!(run.path(), other.path());
+        assert!(run.path().starts_with(cache.path().join("runs")));
+    }
+}
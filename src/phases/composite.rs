@@ -40,12 +40,14 @@
 //! This phase produces a `MemoryFS` and a `Vec<Operation>` of deferred merge
 //! operations, ready for the final local merge in the next phase.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 use super::{IntermediateFS, OperationOrder};
+use crate::attributes::{union, Attributes, MergeDriver};
 use crate::config::Operation;
 use crate::error::{Error, Result};
-use crate::filesystem::MemoryFS;
+use crate::filesystem::{Directory, File, MemoryFS};
 
 /// Executes Phase 4 of the pipeline (batch mode).
 ///
@@ -73,7 +75,10 @@ pub fn execute(
 
     // Process templates in each intermediate filesystem
     let mut processed_fss = HashMap::new();
-    for (repo_key, intermediate_fs) in intermediate_fss {
+    let mut repo_keys: Vec<&String> = intermediate_fss.keys().collect();
+    repo_keys.sort();
+    for repo_key in repo_keys {
+        let intermediate_fs = &intermediate_fss[repo_key];
         let mut processed_fs = intermediate_fs.fs.clone();
         crate::operators::template::process(&mut processed_fs, &all_template_vars)?;
         processed_fss.insert(repo_key.clone(), processed_fs);
@@ -141,8 +146,8 @@ fn merge_filesystem(target_fs: &mut MemoryFS, source_fs: &MemoryFS) -> Result<()
 /// Only collects non-explicitly-deferred ops. Used by the batch pipeline
 /// (Phase 4) where `defer: true` ops are reserved for Phase 5.
 #[allow(dead_code)]
-fn collect_auto_merge_targets(ops: &[Operation]) -> HashMap<String, Operation> {
-    let mut targets = HashMap::new();
+fn collect_auto_merge_targets(ops: &[Operation]) -> BTreeMap<String, Operation> {
+    let mut targets = BTreeMap::new();
     for op in ops {
         // Only collect non-explicitly-deferred auto-merge ops for Phase 4 inter-repo merging.
         // Ops with explicit `defer: true` are reserved for Phase 5 (consumer local file merge).
@@ -159,8 +164,8 @@ fn collect_auto_merge_targets(ops: &[Operation]) -> HashMap<String, Operation> {
 ///
 /// Used by the sequential pipeline where there is no separate Phase 4/5
 /// split — all auto-merge conflict resolution happens during integration.
-fn collect_all_auto_merge_targets(ops: &[Operation]) -> HashMap<String, Operation> {
-    let mut targets = HashMap::new();
+fn collect_all_auto_merge_targets(ops: &[Operation]) -> BTreeMap<String, Operation> {
+    let mut targets = BTreeMap::new();
     for op in ops {
         if let Some(path) = get_auto_merge_path(op) {
             targets.insert(path.to_string(), op.clone());
@@ -212,7 +217,8 @@ pub(crate) fn make_explicit_merge_op(op: &Operation, source: &str, dest: &str) -
                 } else {
                     yaml.array_mode
                 },
-                position: yaml.position,
+                array_key: yaml.array_key.clone(),
+                position: yaml.position.clone(),
                 defer: None,
                 auto_merge: None,
             },
@@ -294,6 +300,32 @@ pub(crate) fn make_explicit_merge_op(op: &Operation, source: &str, dest: &str) -
     }
 }
 
+/// The merge a `.gitattributes` format driver asks for, with the options
+/// an auto-merge declaration would have by default.
+fn attributes_merge_op(driver: MergeDriver) -> Option<Operation> {
+    Some(match driver {
+        MergeDriver::Yaml => Operation::Yaml {
+            yaml: crate::config::YamlMergeOp::new(),
+        },
+        MergeDriver::Json => Operation::Json {
+            json: crate::config::JsonMergeOp::new(),
+        },
+        MergeDriver::Toml => Operation::Toml {
+            toml: crate::config::TomlMergeOp::new(),
+        },
+        MergeDriver::Ini => Operation::Ini {
+            ini: crate::config::IniMergeOp::new(),
+        },
+        MergeDriver::Markdown => Operation::Markdown {
+            markdown: crate::config::MarkdownMergeOp::new(),
+        },
+        MergeDriver::Xml => Operation::Xml {
+            xml: crate::config::XmlMergeOp::new(),
+        },
+        MergeDriver::Union | MergeDriver::Binary => return None,
+    })
+}
+
 /// Overlay composite files onto a target FS, using format-aware auto-merge
 /// for paths that have auto-merge declarations.
 ///
@@ -305,7 +337,7 @@ pub(crate) fn make_explicit_merge_op(op: &Operation, source: &str, dest: &str) -
 pub(crate) fn merge_composite_with_auto_merge(
     target_fs: &mut MemoryFS,
     source_fs: &MemoryFS,
-    auto_merge_targets: &HashMap<String, Operation>,
+    auto_merge_targets: &BTreeMap<String, Operation>,
 ) -> Result<()> {
     merge_filesystem_with_auto_merge(target_fs, source_fs, auto_merge_targets)
 }
@@ -317,35 +349,109 @@ pub(crate) fn merge_composite_with_auto_merge(
 /// - If the file exists AND has an auto-merge declaration: perform format-aware
 ///   merge to accumulate content from both repos
 /// - If the file exists but NO auto-merge: overwrite (last-write-wins, normal behaviour)
+///
+/// Explicit directory entries are carried over with last-write-wins semantics.
 fn merge_filesystem_with_auto_merge(
     target_fs: &mut MemoryFS,
     source_fs: &MemoryFS,
-    auto_merge_targets: &HashMap<String, Operation>,
+    auto_merge_targets: &BTreeMap<String, Operation>,
 ) -> Result<()> {
-    for (path, file) in source_fs.files() {
+    let attributes = Attributes::between(target_fs, source_fs);
+    merge_entries_with_auto_merge(
+        target_fs,
+        &attributes,
+        source_fs
+            .files()
+            .map(|(path, file)| (path.clone(), file.clone())),
+        source_fs
+            .directories()
+            .map(|(path, dir)| (path.clone(), dir.clone())),
+        auto_merge_targets,
+    )
+}
+
+/// Merge files and directory entries one at a time into a target
+/// filesystem, with the same rules as [`merge_filesystem_with_auto_merge`].
+///
+/// The `.gitattributes` hints in `attributes` take part in each conflict: a
+/// path marked binary or `-merge` is overwritten even if an auto-merge is
+/// declared for it, `merge=union` keeps the lines of both text versions, and
+/// a format driver such as `merge=yaml` merges the path like an auto-merge
+/// with default options.
+///
+/// Entries are moved rather than copied, so a filesystem consumed through
+/// [`MemoryFS::into_parts`] never exists twice in memory.
+fn merge_entries_with_auto_merge(
+    target_fs: &mut MemoryFS,
+    attributes: &Attributes,
+    files: impl IntoIterator<Item = (PathBuf, File)>,
+    directories: impl IntoIterator<Item = (PathBuf, Directory)>,
+    auto_merge_targets: &BTreeMap<String, Operation>,
+) -> Result<()> {
+    for (path, file) in files {
         let path_str = path.to_string_lossy();
-        if target_fs.exists(path) {
-            if let Some(merge_op) = auto_merge_targets.get(path_str.as_ref()) {
-                // Auto-merge conflict: merge instead of overwrite.
-                // Stage the incoming file under a temp name, execute the merge,
-                // then clean up.
-                let temp_path = format!(".__common_repo_auto_merge_temp__{}", path_str);
-                target_fs.add_file(&temp_path, file.clone())?;
-
-                let explicit_op = make_explicit_merge_op(merge_op, &temp_path, &path_str);
-                execute_merge_operation(target_fs, &explicit_op)?;
-
-                // Clean up the temporary file
-                target_fs.remove_file(&temp_path)?;
-            } else {
-                // No auto-merge declaration: last-write-wins
-                target_fs.add_file(path, file.clone())?;
+        let Some(existing) = target_fs.get_file(&path) else {
+            // No conflict: just add the file
+            target_fs.add_file(&path, file)?;
+            continue;
+        };
+        let driver = attributes.merge_driver(&path);
+        if driver == Some(MergeDriver::Binary) || attributes.text(&path) == Some(false) {
+            log::debug!("{} is binary in .gitattributes, not merging", path_str);
+            target_fs.add_file(&path, file)?;
+            continue;
+        }
+        if driver == Some(MergeDriver::Union) {
+            let merged = match (
+                std::str::from_utf8(&existing.content),
+                std::str::from_utf8(&file.content),
+            ) {
+                (Ok(dest), Ok(source)) if attributes.is_text(&path, &existing.content) => {
+                    Some(union(dest, source))
+                }
+                _ => None,
+            };
+            match merged {
+                Some(merged) => {
+                    let mut merged = File {
+                        content: merged.into(),
+                        ..file
+                    };
+                    merged.record("merge");
+                    target_fs.add_file(&path, merged)?
+                }
+                None => target_fs.add_file(&path, file)?,
+            }
+            continue;
+        }
+        let declared = auto_merge_targets.get(path_str.as_ref()).cloned();
+        if let Some(merge_op) = declared.or_else(|| driver.and_then(attributes_merge_op)) {
+            // Auto-merge conflict: merge instead of overwrite.
+            // Stage the incoming file under a temp name, execute the merge,
+            // then clean up.
+            let temp_path = format!(".__common_repo_auto_merge_temp__{}", path_str);
+            let incoming_origin = file.origin.clone();
+            target_fs.add_file(&temp_path, file)?;
+
+            let explicit_op = make_explicit_merge_op(&merge_op, &temp_path, &path_str);
+            execute_merge_operation(target_fs, &explicit_op)?;
+
+            // Clean up the temporary file
+            target_fs.remove_file(&temp_path)?;
+            if let Some(merged) = target_fs.get_file_mut(&path) {
+                if merged.origin.is_none() {
+                    merged.origin = incoming_origin;
+                }
+                merged.record("merge");
             }
         } else {
-            // No conflict: just add the file
-            target_fs.add_file(path, file.clone())?;
+            // No auto-merge declaration: last-write-wins
+            target_fs.add_file(&path, file)?;
         }
     }
+    for (path, dir) in directories {
+        target_fs.add_directory(path, dir)?;
+    }
     Ok(())
 }
 
@@ -379,12 +485,18 @@ pub(crate) fn integrate_sub_composite(
     parent_fs: &mut MemoryFS,
     sub_composite: &IntermediateFS,
 ) -> Result<Vec<Operation>> {
-    let mut targets = HashMap::new();
-    integrate_sub_composite_with_targets(parent_fs, sub_composite, &mut targets)
+    let mut targets = BTreeMap::new();
+    integrate_fs_with_targets(
+        parent_fs,
+        sub_composite.fs.clone(),
+        &sub_composite.merge_operations,
+        &mut targets,
+    )
 }
 
-/// Variant of [`integrate_sub_composite`] that maintains an accumulated set of
-/// auto-merge targets across multiple sequential integrations.
+/// Integrate a sub-composite's filesystem, taken by value, into the parent
+/// composite, maintaining an accumulated set of auto-merge targets across
+/// multiple sequential integrations.
 ///
 /// In the sequential pipeline, repos are integrated one at a time. If repo A
 /// declares auto-merge for a file and repo B also provides that file later,
@@ -395,20 +507,33 @@ pub(crate) fn integrate_sub_composite(
 /// Auto-merge ops with `defer: true` are included in the targets (unlike the
 /// batch pipeline) because there is no separate Phase 5 — conflict resolution
 /// happens entirely during integration.
-pub(crate) fn integrate_sub_composite_with_targets(
+///
+/// Each file is moved into the parent as it goes instead of copying the
+/// whole sub-composite first, so that a sub-composite and its copy are never
+/// both held in memory.
+pub(crate) fn integrate_fs_with_targets(
     parent_fs: &mut MemoryFS,
-    sub_composite: &IntermediateFS,
-    accumulated_targets: &mut HashMap<String, Operation>,
+    fs: MemoryFS,
+    merge_operations: &[Operation],
+    accumulated_targets: &mut BTreeMap<String, Operation>,
 ) -> Result<Vec<Operation>> {
     // 1. Add this sub-composite's auto-merge targets (including deferred) to the
     //    accumulated set so that future integrations can also trigger merges for
     //    these files.
-    let new_targets = collect_all_auto_merge_targets(&sub_composite.merge_operations);
+    let new_targets = collect_all_auto_merge_targets(merge_operations);
     accumulated_targets.extend(new_targets);
 
     // 2. Merge sub-composite files into parent (with auto-merge awareness).
     //    Uses the accumulated targets from ALL prior + current integrations.
-    merge_filesystem_with_auto_merge(parent_fs, &sub_composite.fs, accumulated_targets)?;
+    let attributes = Attributes::between(parent_fs, &fs);
+    let (files, directories) = fs.into_parts();
+    merge_entries_with_auto_merge(
+        parent_fs,
+        &attributes,
+        files,
+        directories,
+        accumulated_targets,
+    )?;
 
     // 3. Execute deferred merges where dest exists in parent; collect rest.
     //    Auto-merge ops are skipped here because they were already handled
@@ -416,7 +541,7 @@ pub(crate) fn integrate_sub_composite_with_targets(
     //    temp name and merged format-aware). Only explicit source/dest deferred
     //    ops need processing.
     let mut residual_ops = Vec::new();
-    for op in &sub_composite.merge_operations {
+    for op in merge_operations {
         if !is_explicitly_deferred(op) {
             // Non-deferred ops were already handled during file merge above
             // (auto-merge conflicts). Skip them here.
@@ -450,6 +575,10 @@ pub(crate) fn integrate_sub_composite_with_targets(
 /// Made `pub(crate)` so Phase 5 can execute deferred merge operations
 /// after local files are available.
 pub(crate) fn execute_merge_operation(fs: &mut MemoryFS, operation: &Operation) -> Result<()> {
+    crate::reporting::timings::time(operation, fs, |fs| dispatch_merge_operation(fs, operation))
+}
+
+fn dispatch_merge_operation(fs: &mut MemoryFS, operation: &Operation) -> Result<()> {
     match operation {
         Operation::Yaml { yaml } => crate::merge::yaml::apply_yaml_merge_operation(fs, yaml),
         Operation::Json { json } => crate::merge::json::apply_json_merge_operation(fs, json),
@@ -470,7 +599,7 @@ pub(crate) fn execute_merge_operation(fs: &mut MemoryFS, operation: &Operation)
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use super::{execute, IntermediateFS, OperationOrder};
     use crate::error::Error;
@@ -550,7 +679,10 @@ mod tests {
         // Last filesystem should win
         assert_eq!(composite.len(), 1);
         let file = composite.get_file("common.txt").unwrap();
-        assert_eq!(String::from_utf8(file.content.clone()).unwrap(), "version2");
+        assert_eq!(
+            String::from_utf8(file.content.to_vec()).unwrap(),
+            "version2"
+        );
     }
 
     #[test]
@@ -631,6 +763,7 @@ mod tests {
         // Mark template.txt as a template
         let template_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         crate::operators::template::mark(&template_op, &mut fs1).unwrap();
 
@@ -676,14 +809,14 @@ mod tests {
         // Template should be processed with merged variables (later repos override)
         assert!(composite.exists("template.txt"));
         let template_file = composite.get_file("template.txt").unwrap();
-        let content = String::from_utf8(template_file.content.clone()).unwrap();
+        let content = String::from_utf8(template_file.content.to_vec()).unwrap();
         assert_eq!(content, "Hello Bob from repo1!"); // NAME overridden, REPO from first repo
         assert!(!template_file.is_template); // Should be unmarked after processing
 
         // Non-template file should be unchanged
         assert!(composite.exists("config.txt"));
         let config_file = composite.get_file("config.txt").unwrap();
-        let config_content = String::from_utf8(config_file.content.clone()).unwrap();
+        let config_content = String::from_utf8(config_file.content.to_vec()).unwrap();
         assert_eq!(config_content, "Config file");
     }
 
@@ -701,6 +834,7 @@ mod tests {
         // Mark templates
         let template_op = crate::config::TemplateOp {
             patterns: vec!["*.txt".to_string()],
+            files: Default::default(),
         };
         crate::operators::template::mark(&template_op, &mut fs1).unwrap();
         crate::operators::template::mark(&template_op, &mut fs2).unwrap();
@@ -741,11 +875,11 @@ mod tests {
 
         // Both templates should be processed
         let greeting_file = composite.get_file("greeting.txt").unwrap();
-        let greeting_content = String::from_utf8(greeting_file.content.clone()).unwrap();
+        let greeting_content = String::from_utf8(greeting_file.content.to_vec()).unwrap();
         assert_eq!(greeting_content, "Hello Alice!");
 
         let version_file = composite.get_file("version.txt").unwrap();
-        let version_content = String::from_utf8(version_file.content.clone()).unwrap();
+        let version_content = String::from_utf8(version_file.content.to_vec()).unwrap();
         assert_eq!(version_content, "Version: 2.1.0");
 
         // Both should be unmarked as templates
@@ -799,7 +933,7 @@ mod tests {
         // package.json should still have original content (not merged)
         assert!(composite.exists("package.json"));
         let package_file = composite.get_file("package.json").unwrap();
-        let content = String::from_utf8(package_file.content.clone()).unwrap();
+        let content = String::from_utf8(package_file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
         assert_eq!(json["name"], "test-package");
         assert!(json.get("newKey").is_none());
@@ -867,7 +1001,7 @@ port = 8080
         // config.ini should still have original content (not merged with fragment)
         assert!(composite.exists("config.ini"));
         let config_file = composite.get_file("config.ini").unwrap();
-        let content = String::from_utf8(config_file.content.clone()).unwrap();
+        let content = String::from_utf8(config_file.content.to_vec()).unwrap();
         assert!(content.contains("[database]"));
         assert!(content.contains("host = localhost"));
         // Fragment values should NOT be merged yet
@@ -909,7 +1043,7 @@ port = 8080
 
             // Verify the merge happened
             let content = fs.get_file("config.yaml").unwrap();
-            let content_str = String::from_utf8(content.content.clone()).unwrap();
+            let content_str = String::from_utf8(content.content.to_vec()).unwrap();
             assert!(content_str.contains("new_key"));
             assert!(content_str.contains("existing_key"));
         }
@@ -936,7 +1070,7 @@ port = 8080
 
             // Verify the merge happened
             let content = fs.get_file("config.toml").unwrap();
-            let content_str = String::from_utf8(content.content.clone()).unwrap();
+            let content_str = String::from_utf8(content.content.to_vec()).unwrap();
             assert!(content_str.contains("section"));
             assert!(content_str.contains("value"));
         }
@@ -970,7 +1104,7 @@ port = 8080
 
             // Verify the merge happened
             let content = fs.get_file("README.md").unwrap();
-            let content_str = String::from_utf8(content.content.clone()).unwrap();
+            let content_str = String::from_utf8(content.content.to_vec()).unwrap();
             assert!(content_str.contains("Features"));
             assert!(content_str.contains("New content"));
         }
@@ -985,8 +1119,10 @@ port = 8080
                 include: IncludeOp {
                     patterns: vec!["**/*".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
 
             let result = execute_merge_operation(&mut fs, &operation);
@@ -1064,7 +1200,7 @@ port = 8080
 
             // Verify the content was merged
             let content = fs.get_file("nonexistent.yaml").unwrap();
-            let content_str = String::from_utf8(content.content.clone()).unwrap();
+            let content_str = String::from_utf8(content.content.to_vec()).unwrap();
             assert!(content_str.contains("new"));
         }
     }
@@ -1137,7 +1273,7 @@ port = 8080
             // Source should overwrite target (last-write-wins)
             let file = target.get_file("common.txt").unwrap();
             assert_eq!(
-                String::from_utf8(file.content.clone()).unwrap(),
+                String::from_utf8(file.content.to_vec()).unwrap(),
                 "source version"
             );
         }
@@ -1219,7 +1355,7 @@ port = 8080
 
         // package.json should NOT have been merged (still original content)
         let package_file = composite.get_file("package.json").unwrap();
-        let content = String::from_utf8(package_file.content.clone()).unwrap();
+        let content = String::from_utf8(package_file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
         assert_eq!(json["name"], "test-package");
         assert!(json.get("newKey").is_none()); // NOT merged
@@ -1239,6 +1375,7 @@ port = 8080
         // Mark as template (upstream repo declared template:)
         let template_op = crate::config::TemplateOp {
             patterns: vec!["workflow.yaml".to_string()],
+            files: Default::default(),
         };
         crate::operators::template::mark(&template_op, &mut fs1).unwrap();
 
@@ -1289,7 +1426,7 @@ port = 8080
         let (composite, _deferred_ops) = execute(&order, &intermediate_fss).unwrap();
 
         let file = composite.get_file("workflow.yaml").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
 
         // Upstream defaults preserved for non-overridden vars
         assert!(content.contains("CHRISTMAS_ISLAND_APP_ID"));
@@ -1379,7 +1516,7 @@ port = 8080
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
 
@@ -1430,7 +1567,7 @@ port = 8080
             let (composite, _) = execute(&order, &intermediate_fss).unwrap();
 
             let content =
-                String::from_utf8(composite.get_file("shared.txt").unwrap().content.clone())
+                String::from_utf8(composite.get_file("shared.txt").unwrap().content.to_vec())
                     .unwrap();
             assert_eq!(content, "from-repo-b");
         }
@@ -1471,7 +1608,7 @@ port = 8080
             let (composite, _) = execute(&order, &intermediate_fss).unwrap();
 
             let content =
-                String::from_utf8(composite.get_file("config.yaml").unwrap().content.clone())
+                String::from_utf8(composite.get_file("config.yaml").unwrap().content.to_vec())
                     .unwrap();
 
             assert!(content.contains("repo-a"), "Missing repo-a: {}", content);
@@ -1507,7 +1644,7 @@ port = 8080
             let (composite, _) = execute(&order, &intermediate_fss).unwrap();
 
             let content =
-                String::from_utf8(composite.get_file("config.yaml").unwrap().content.clone())
+                String::from_utf8(composite.get_file("config.yaml").unwrap().content.to_vec())
                     .unwrap();
             assert!(content.contains("first"));
         }
@@ -1569,7 +1706,7 @@ port = 8080
             let (composite, _) = execute(&order, &intermediate_fss).unwrap();
 
             let content =
-                String::from_utf8(composite.get_file("config.xml").unwrap().content.clone())
+                String::from_utf8(composite.get_file("config.xml").unwrap().content.to_vec())
                     .unwrap();
             // With AppendUnique upgrade, all unique plugins from both repos should
             // be present. "fmt" appears in both but should only appear once.
@@ -1614,7 +1751,7 @@ port = 8080
             assert!(parent.exists("existing.txt"));
             assert!(parent.exists("new_file.txt"));
             assert_eq!(
-                String::from_utf8(parent.get_file("new_file.txt").unwrap().content.clone())
+                String::from_utf8(parent.get_file("new_file.txt").unwrap().content.to_vec())
                     .unwrap(),
                 "sub content"
             );
@@ -1640,7 +1777,7 @@ port = 8080
 
             // Sub-composite wins (last-write-wins)
             assert_eq!(
-                String::from_utf8(parent.get_file("shared.txt").unwrap().content.clone()).unwrap(),
+                String::from_utf8(parent.get_file("shared.txt").unwrap().content.to_vec()).unwrap(),
                 "sub version"
             );
             assert!(residual.is_empty());
@@ -1684,7 +1821,7 @@ port = 8080
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
             assert!(content.contains("hooks-a"), "parent hooks-a preserved");
@@ -1734,7 +1871,7 @@ port = 8080
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
             assert!(content.contains("local-hooks"), "local content preserved");
@@ -1821,7 +1958,8 @@ port = 8080
 
             // dest-a.yaml was merged (source-a merged into it)
             let content_a =
-                String::from_utf8(parent.get_file("dest-a.yaml").unwrap().content.clone()).unwrap();
+                String::from_utf8(parent.get_file("dest-a.yaml").unwrap().content.to_vec())
+                    .unwrap();
             assert!(content_a.contains("merged"), "source-a merged into dest-a");
 
             // dest-b.yaml still doesn't exist; its op is residual
@@ -1839,10 +1977,10 @@ port = 8080
         #[test]
         fn test_sequential_integration_auto_merge_with_defer_true() {
             use crate::config::{ArrayMergeMode, Operation, YamlMergeOp};
-            use crate::phases::composite::integrate_sub_composite_with_targets;
+            use crate::phases::composite::integrate_fs_with_targets;
 
             let mut parent = MemoryFS::new();
-            let mut accumulated = HashMap::new();
+            let mut accumulated = BTreeMap::new();
 
             // --- First repo: conventional-commits (auto-merge, no explicit defer) ---
             let yaml_a =
@@ -1862,9 +2000,13 @@ port = 8080
                     .array_mode(ArrayMergeMode::AppendUnique),
             });
 
-            let residual_a =
-                integrate_sub_composite_with_targets(&mut parent, &ifs_a, &mut accumulated)
-                    .unwrap();
+            let residual_a = integrate_fs_with_targets(
+                &mut parent,
+                ifs_a.fs.clone(),
+                &ifs_a.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
             assert!(residual_a.is_empty());
 
             // --- Second repo: pre-commit (auto-merge with defer: true) ---
@@ -1887,9 +2029,13 @@ port = 8080
                 },
             });
 
-            let residual_b =
-                integrate_sub_composite_with_targets(&mut parent, &ifs_b, &mut accumulated)
-                    .unwrap();
+            let residual_b = integrate_fs_with_targets(
+                &mut parent,
+                ifs_b.fs.clone(),
+                &ifs_b.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
             assert!(residual_b.is_empty());
 
             // --- Verify: both repos' hooks must be present ---
@@ -1898,7 +2044,7 @@ port = 8080
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
             assert!(
@@ -1919,10 +2065,10 @@ port = 8080
         #[test]
         fn test_sequential_integration_defer_true_first() {
             use crate::config::{ArrayMergeMode, Operation, YamlMergeOp};
-            use crate::phases::composite::integrate_sub_composite_with_targets;
+            use crate::phases::composite::integrate_fs_with_targets;
 
             let mut parent = MemoryFS::new();
-            let mut accumulated = HashMap::new();
+            let mut accumulated = BTreeMap::new();
 
             // --- First repo: pre-commit (defer: true) ---
             let yaml_a = "repos:\n  - repo: builtin\n    hooks:\n      - id: trailing-whitespace\n";
@@ -1943,9 +2089,13 @@ port = 8080
                 },
             });
 
-            let residual_a =
-                integrate_sub_composite_with_targets(&mut parent, &ifs_a, &mut accumulated)
-                    .unwrap();
+            let residual_a = integrate_fs_with_targets(
+                &mut parent,
+                ifs_a.fs.clone(),
+                &ifs_a.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
             assert!(residual_a.is_empty());
 
             // --- Second repo: conventional-commits (no explicit defer) ---
@@ -1965,9 +2115,13 @@ port = 8080
                     .array_mode(ArrayMergeMode::AppendUnique),
             });
 
-            let residual_b =
-                integrate_sub_composite_with_targets(&mut parent, &ifs_b, &mut accumulated)
-                    .unwrap();
+            let residual_b = integrate_fs_with_targets(
+                &mut parent,
+                ifs_b.fs.clone(),
+                &ifs_b.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
             assert!(residual_b.is_empty());
 
             let content = String::from_utf8(
@@ -1975,7 +2129,7 @@ port = 8080
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
             assert!(
@@ -1995,10 +2149,10 @@ port = 8080
         #[test]
         fn test_sequential_integration_three_repos_accumulated() {
             use crate::config::{ArrayMergeMode, Operation, YamlMergeOp};
-            use crate::phases::composite::integrate_sub_composite_with_targets;
+            use crate::phases::composite::integrate_fs_with_targets;
 
             let mut parent = MemoryFS::new();
-            let mut accumulated = HashMap::new();
+            let mut accumulated = BTreeMap::new();
 
             let make_yaml = |hook_id: &str| -> String {
                 format!(
@@ -2025,27 +2179,148 @@ port = 8080
 
             // Repo 1: no defer
             let ifs1 = make_ifs("hook-alpha", "https://a.git", None);
-            integrate_sub_composite_with_targets(&mut parent, &ifs1, &mut accumulated).unwrap();
+            integrate_fs_with_targets(
+                &mut parent,
+                ifs1.fs.clone(),
+                &ifs1.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
 
             // Repo 2: defer: true
             let ifs2 = make_ifs("hook-beta", "https://b.git", Some(true));
-            integrate_sub_composite_with_targets(&mut parent, &ifs2, &mut accumulated).unwrap();
+            integrate_fs_with_targets(
+                &mut parent,
+                ifs2.fs.clone(),
+                &ifs2.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
 
             // Repo 3: no defer
             let ifs3 = make_ifs("hook-gamma", "https://c.git", None);
-            integrate_sub_composite_with_targets(&mut parent, &ifs3, &mut accumulated).unwrap();
+            integrate_fs_with_targets(
+                &mut parent,
+                ifs3.fs.clone(),
+                &ifs3.merge_operations,
+                &mut accumulated,
+            )
+            .unwrap();
 
             let content = String::from_utf8(
                 parent
                     .get_file(".pre-commit-config.yaml")
                     .unwrap()
                     .content
-                    .clone(),
+                    .to_vec(),
             )
             .unwrap();
             assert!(content.contains("hook-alpha"), "repo 1 missing:\n{content}");
             assert!(content.contains("hook-beta"), "repo 2 missing:\n{content}");
             assert!(content.contains("hook-gamma"), "repo 3 missing:\n{content}");
         }
+
+        fn integrate_with_attributes(
+            parent: &mut MemoryFS,
+            path: &str,
+            incoming: &[u8],
+            attributes: &str,
+            merge_operations: Vec<crate::config::Operation>,
+        ) {
+            let mut sub_fs = MemoryFS::new();
+            sub_fs.add_file_content(path, incoming.to_vec()).unwrap();
+            sub_fs
+                .add_file_string(crate::attributes::ATTRIBUTES_FILENAME, attributes)
+                .unwrap();
+            let mut sub_ifs = IntermediateFS::new(
+                sub_fs,
+                "https://github.com/sub.git".to_string(),
+                "main".to_string(),
+            );
+            sub_ifs.merge_operations = merge_operations;
+            integrate_sub_composite(parent, &sub_ifs).unwrap();
+        }
+
+        fn content_of(fs: &MemoryFS, path: &str) -> String {
+            String::from_utf8(fs.get_file(path).unwrap().content.to_vec()).unwrap()
+        }
+
+        #[test]
+        fn test_attributes_format_driver_merges_without_auto_merge() {
+            let mut parent = MemoryFS::new();
+            parent
+                .add_file_string("icons/logo.svg", "<svg><g id=\"a\"/></svg>")
+                .unwrap();
+            integrate_with_attributes(
+                &mut parent,
+                "icons/logo.svg",
+                b"<svg><rect id=\"b\"/></svg>",
+                "*.svg text merge=xml\n",
+                Vec::new(),
+            );
+            let content = content_of(&parent, "icons/logo.svg");
+            assert!(content.contains("id=\"a\""), "parent kept:\n{content}");
+            assert!(content.contains("id=\"b\""), "incoming merged:\n{content}");
+        }
+
+        #[test]
+        fn test_attributes_binary_overrides_auto_merge() {
+            use crate::config::{Operation, YamlMergeOp};
+
+            let mut parent = MemoryFS::new();
+            parent.add_file_string("data.yaml", "a: 1\n").unwrap();
+            integrate_with_attributes(
+                &mut parent,
+                "data.yaml",
+                b"b: 2\n",
+                "data.yaml binary\n",
+                vec![Operation::Yaml {
+                    yaml: YamlMergeOp::new().auto_merge("data.yaml"),
+                }],
+            );
+            assert_eq!(content_of(&parent, "data.yaml"), "b: 2\n");
+        }
+
+        #[test]
+        fn test_attributes_binary_skips_auto_merge_of_non_utf8_file() {
+            use crate::config::{JsonMergeOp, Operation};
+
+            let mut parent = MemoryFS::new();
+            parent
+                .add_file_content("blob.json", vec![0xff, 0x00])
+                .unwrap();
+            integrate_with_attributes(
+                &mut parent,
+                "blob.json",
+                &[0xfe, 0x01],
+                "*.json -text\n",
+                vec![Operation::Json {
+                    json: JsonMergeOp::new().auto_merge("blob.json"),
+                }],
+            );
+            assert_eq!(
+                parent.get_file("blob.json").unwrap().content.to_vec(),
+                vec![0xfe, 0x01]
+            );
+        }
+
+        #[test]
+        fn test_attributes_union_driver_keeps_lines_of_both() {
+            let mut parent = MemoryFS::new();
+            parent
+                .add_file_string("CODEOWNERS", "* @a\n/docs @b\n")
+                .unwrap();
+            integrate_with_attributes(
+                &mut parent,
+                "CODEOWNERS",
+                b"* @a\n/src @c\n",
+                "CODEOWNERS merge=union\n",
+                Vec::new(),
+            );
+            assert_eq!(
+                content_of(&parent, "CODEOWNERS"),
+                "* @a\n/docs @b\n/src @c\n"
+            );
+        }
     }
 }
@@ -0,0 +1,199 @@
+//! Per-run settings for the pull pipeline.
+//!
+//! A [`RunContext`] holds everything one pull needs besides its
+//! configuration: where to fetch repositories from and cache them, the
+//! working directory, where to write, checkpoints, variable overrides and
+//! the stages each block runs. The orchestrator passes it to every phase,
+//! so a new setting is a new field rather than a new parameter on each
+//! phase function.
+//!
+//! ```no_run
+//! use std::path::{Path, PathBuf};
+//!
+//! use common_repo::cache::RepoCache;
+//! use common_repo::diagnostics::Diagnostics;
+//! use common_repo::phases::context::RunContext;
+//! use common_repo::phases::orchestrator;
+//! use common_repo::repository::RepositoryManager;
+//!
+//! let config = common_repo::config::from_file(Path::new(".common-repo.yaml"))?;
+//! let repo_manager = RepositoryManager::new(PathBuf::from("/tmp/common-repo-cache"));
+//! let cache = RepoCache::new();
+//! let ctx = RunContext::new(&repo_manager, &cache, Path::new("."))
+//!     .output(Path::new("."))
+//!     .var("project_name", "example");
+//! orchestrator::execute_pull_with_context(&config, &ctx, &mut Diagnostics::new())?;
+//! # Ok::<(), common_repo::error::Error>(())
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::checkpoint::RunDir;
+use super::orchestrator::{default_stages, Stage};
+use super::plan::Plan;
+use super::write::{ReadOnlyPolicy, WriteOptions};
+use crate::cache::RepoCache;
+use crate::config::PathScope;
+use crate::repository::RepositoryManager;
+
+/// The settings of one pull.
+pub struct RunContext<'a> {
+    /// Fetches and caches repositories, with whatever credentials git is
+    /// configured with.
+    pub repo_manager: &'a RepositoryManager,
+    /// In-memory cache of processed repositories.
+    pub cache: &'a RepoCache,
+    /// The consumer's working directory, which local files are read from.
+    pub working_dir: &'a Path,
+    /// Where the output is written, or `None` to only compute it.
+    pub output_path: Option<&'a Path>,
+    /// Where checkpoints are saved and resumed from (see
+    /// [`super::checkpoint`]).
+    pub run: Option<&'a RunDir>,
+    /// Template variables that take precedence over every `template-vars`
+    /// operation, upstream or consumer.
+    pub vars: HashMap<String, String>,
+    /// The stages each pipeline block runs, [`default_stages`] unless
+    /// replaced.
+    pub stages: Vec<Box<dyn Stage>>,
+    /// The named pipeline being applied, whose apply manifest is kept apart
+    /// from those of other pipelines into the same directory, or `None` for
+    /// the default configuration.
+    pub pipeline: Option<&'a str>,
+    /// Write through symlinks in the output directory to their targets
+    /// instead of refusing to.
+    pub follow_symlinks: bool,
+    /// What to do with read-only files in the output directory that would
+    /// change.
+    pub read_only: ReadOnlyPolicy,
+    /// Write files that came from an upstream read-only.
+    pub protect: bool,
+    /// Where the source block's output may be written; composite files
+    /// outside it are left out.
+    pub scope: PathScope,
+    /// A saved plan whose filesystem the source block writes instead of
+    /// composing one (see [`super::plan`]).
+    pub plan: Option<&'a Plan>,
+    /// A subdirectory of the working directory to place the output of the
+    /// source block under. Its local files are read from there, and the
+    /// output paths, and so the apply manifest, start with it.
+    pub into: Option<&'a Path>,
+    /// Fail on operations this version does not know instead of skipping
+    /// them.
+    pub strict: bool,
+}
+
+impl<'a> RunContext<'a> {
+    /// A context that computes the output of pulling into `working_dir`
+    /// without writing it, using the default stages.
+    pub fn new(
+        repo_manager: &'a RepositoryManager,
+        cache: &'a RepoCache,
+        working_dir: &'a Path,
+    ) -> Self {
+        Self {
+            repo_manager,
+            cache,
+            working_dir,
+            output_path: None,
+            run: None,
+            vars: HashMap::new(),
+            stages: default_stages(),
+            pipeline: None,
+            follow_symlinks: false,
+            read_only: ReadOnlyPolicy::default(),
+            protect: false,
+            scope: PathScope::default(),
+            plan: None,
+            into: None,
+            strict: false,
+        }
+    }
+
+    /// Write the output to `path`.
+    pub fn output(mut self, path: &'a Path) -> Self {
+        self.output_path = Some(path);
+        self
+    }
+
+    /// Save checkpoints to `run`, and resume from them if it was created
+    /// for resuming.
+    pub fn checkpoints(mut self, run: &'a RunDir) -> Self {
+        self.run = Some(run);
+        self
+    }
+
+    /// Override the template variable `name`.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Apply the named pipeline `name`.
+    pub fn pipeline(mut self, name: &'a str) -> Self {
+        self.pipeline = Some(name);
+        self
+    }
+
+    /// Write through symlinks in the output directory when `follow` is set.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Handle read-only files in the output directory as `policy` says.
+    pub fn read_only(mut self, policy: ReadOnlyPolicy) -> Self {
+        self.read_only = policy;
+        self
+    }
+
+    /// Write files that came from an upstream read-only when `protect` is
+    /// set.
+    pub fn protect(mut self, protect: bool) -> Self {
+        self.protect = protect;
+        self
+    }
+
+    /// Leave composite files that `scope` does not allow out of the source
+    /// block's output.
+    pub fn scope(mut self, scope: PathScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Write the filesystem of `plan` for the source block instead of
+    /// composing it.
+    pub fn plan(mut self, plan: &'a Plan) -> Self {
+        self.plan = Some(plan);
+        self
+    }
+
+    /// Place the output of the source block under the subdirectory `dir`
+    /// of the working directory.
+    pub fn into(mut self, dir: &'a Path) -> Self {
+        self.into = Some(dir);
+        self
+    }
+
+    /// Fail on operations this version does not know when `strict` is set.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Run `stages` for each block instead of the default stages.
+    pub fn stages(mut self, stages: Vec<Box<dyn Stage>>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// How the output is written.
+    pub(crate) fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            follow_symlinks: self.follow_symlinks,
+            read_only: self.read_only,
+            protect: self.protect,
+        }
+    }
+}
@@ -35,14 +35,16 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-use log::warn;
 use rayon::prelude::*;
 
+use super::context::RunContext;
 use super::{RepoNode, RepoTree};
 use crate::cache::RepoCache;
-use crate::config::{Operation, Schema};
-use crate::defaults::{ALT_CONFIG_FILENAME, DEFAULT_CONFIG_FILENAME};
-use crate::error::{Error, Result};
+use crate::config::{OnFetchError, Operation, RepoOp, Schema, Upstream};
+use crate::defaults::{ConfigFiles, ALT_CONFIG_FILENAME, DEFAULT_CONFIG_FILENAME};
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
+use crate::messages::Message;
 use crate::repository::RepositoryManager;
 
 /// A deduplicated key used in cycle-detection and the discovery visited set.
@@ -60,14 +62,11 @@ enum VisitKey {
 ///
 /// This function orchestrates the discovery and cloning process by calling
 /// `discover_repos` to build the repository tree and then `clone_parallel`
-/// to fetch all the repositories.
-pub fn execute(
-    config: &Schema,
-    repo_manager: &RepositoryManager,
-    cache: &RepoCache,
-) -> Result<RepoTree> {
-    let tree = discover_repos(config, repo_manager)?;
-    clone_parallel(&tree, repo_manager, cache)?;
+/// to fetch all the repositories. Relative local-path references in `config`
+/// are resolved against the context's working directory.
+pub fn execute(config: &Schema, ctx: &RunContext) -> Result<RepoTree> {
+    let tree = discover_repos_with_parent(config, ctx.working_dir, ctx.repo_manager)?;
+    clone_parallel(&tree, ctx.repo_manager, ctx.cache)?;
     Ok(tree)
 }
 
@@ -83,13 +82,15 @@ pub fn discover_repos(config: &Schema, repo_manager: &RepositoryManager) -> Resu
 ///
 /// Like [`discover_repos`] but allows the caller to supply the directory that
 /// the config was read from. Relative local-path references in `config` are
-/// resolved against `parent_dir`.
+/// resolved against `parent_dir`, and ref aliases are looked up in their
+/// `ref-index:` (see [`crate::ref_index`]).
 pub(crate) fn discover_repos_with_parent(
     config: &Schema,
     parent_dir: &Path,
     repo_manager: &RepositoryManager,
 ) -> Result<RepoTree> {
-    let root_node = process_config_to_node(config, parent_dir)?;
+    let config = crate::ref_index::resolve(config, parent_dir, repo_manager)?;
+    let root_node = process_config_to_node(&config, parent_dir)?;
     let root_node =
         discover_inherited_configs(root_node, parent_dir, repo_manager, &mut HashSet::new())?;
     let tree = RepoTree::new(root_node.clone());
@@ -142,8 +143,13 @@ fn discover_inherited_configs(
             parent_dir.to_path_buf()
         };
 
-        match fetch_and_parse_config(&child.url, &child.ref_, repo_manager) {
-            Ok(inherited_config) => {
+        match fetch_and_parse_config(&child, repo_manager) {
+            Ok(Upstream {
+                schema: inherited_config,
+                requires,
+                locked,
+                ..
+            }) => {
                 let upstream_filtering_ops = extract_upstream_operations(&inherited_config);
                 let deferred_ops = extract_deferred_operations(&inherited_config);
 
@@ -165,12 +171,37 @@ fn discover_inherited_configs(
                     original_url: child.original_url.clone(),
                     children: Vec::new(),
                     operations: combined_operations,
+                    disable: child.disable.clone(),
+                    verify: child.verify.clone(),
+                    on_fetch_error: child.on_fetch_error,
+                    requires,
+                    locked,
+                    partial: child.partial.clone(),
+                    description: child.description.clone(),
                 };
                 for inherited_child in inherited_node.children {
                     combined_node.add_child(inherited_child);
                 }
                 new_children.push(combined_node);
             }
+            // An upstream that needs a newer common-repo must not be
+            // silently treated as a plain file tree.
+            Err(e @ Error::VersionRequirement { .. }) => return Err(e),
+            // Nor may an upstream whose signature did not verify.
+            Err(e @ Error::SignatureVerification { .. }) => return Err(e),
+            // An unreachable upstream marked `on-fetch-error: skip` is left
+            // out along with everything it inherits from.
+            Err(e) if e.is_fetch_failure() && child.on_fetch_error == OnFetchError::Skip => {
+                diagnostics::warn(
+                    ErrorPhase::Discovery,
+                    Message::SkippedUpstream {
+                        url: &child.url,
+                        ref_: &child.ref_,
+                        error: &e.to_string(),
+                    }
+                    .to_string(),
+                );
+            }
             Err(_) => {
                 // If we can't fetch/parse the config, just use the original child as-is
                 // This allows repositories without .common-repo.yaml files to still work
@@ -186,44 +217,122 @@ fn discover_inherited_configs(
 }
 
 /// Fetch a repository and parse its .common-repo.yaml file
-fn fetch_and_parse_config(
-    url: &str,
-    ref_: &str,
+///
+/// When the node has `verify:` signers, the fetched tag or commit is
+/// checked before anything in it is read. Operations whose `id:` is in the
+/// node's `disable` list are left out. Ids that match no operation, and the
+/// upstream's deprecation notices, are reported as warnings. Ref aliases in
+/// the upstream's `repo:` operations are resolved through their index.
+///
+/// A commit found to have no configuration file is recorded in the disk
+/// cache, and later runs skip loading the repository to look again.
+pub(crate) fn fetch_and_parse_config(
+    node: &RepoNode,
     repo_manager: &RepositoryManager,
-) -> Result<Schema> {
+) -> Result<Upstream> {
+    let (url, ref_, disable) = (node.url.as_str(), node.ref_.as_str(), &node.disable);
+
+    if repo_manager.is_known_without_config(url, ref_, &node.partial) {
+        log::debug!(
+            "{}@{} is known to have no {}, not looking again",
+            url,
+            ref_,
+            DEFAULT_CONFIG_FILENAME
+        );
+        if let Some(verify) = &node.verify {
+            repo_manager.verify_repository_partial(url, ref_, &node.partial, verify)?;
+        }
+        return Err(missing_config());
+    }
+
     // Fetch the repository
-    let fs = repo_manager.fetch_repository(url, ref_)?;
+    let fs = repo_manager.fetch_repository_partial(url, ref_, &node.partial)?;
+    if let Some(verify) = &node.verify {
+        repo_manager.verify_repository_partial(url, ref_, &node.partial, verify)?;
+    }
 
-    // Try to read .common-repo.yaml
-    let config_content = match fs.get_file(DEFAULT_CONFIG_FILENAME) {
-        Some(file) => file.content.clone(),
+    let origin = if ref_.is_empty() {
+        url.to_string()
+    } else {
+        format!("{}@{}", url, ref_)
+    };
+
+    // Read the configuration under the most preferred name it has
+    let config_files = ConfigFiles::load();
+    let (config_name, config_content) = match config_files.find(&origin, |name| {
+        fs.get_file(name).map(|file| file.content.to_vec())
+    }) {
+        Some(found) => found,
         None => {
-            // Try .commonrepo.yaml as fallback
-            match fs.get_file(ALT_CONFIG_FILENAME) {
-                Some(file) => file.content.clone(),
-                None => {
-                    return Err(Error::ConfigParse {
-                        message: format!(
-                            "No {} or {} found in repository",
-                            DEFAULT_CONFIG_FILENAME, ALT_CONFIG_FILENAME
-                        ),
-                        hint: Some(format!(
-                            "Create a {} file in the repository root",
-                            DEFAULT_CONFIG_FILENAME
-                        )),
-                    });
-                }
+            match repo_manager.record_without_config(url, ref_, &node.partial) {
+                Ok(()) => log::debug!(
+                    "{}@{} has no {}, recorded in the cache",
+                    url,
+                    ref_,
+                    DEFAULT_CONFIG_FILENAME
+                ),
+                Err(e) => log::debug!("failed to record that {} has no config: {}", url, e),
             }
+            return Err(missing_config());
         }
     };
 
     // Parse the YAML content
     let yaml_str = String::from_utf8(config_content).map_err(|_| Error::ConfigParse {
-        message: format!("Invalid UTF-8 in {}", DEFAULT_CONFIG_FILENAME),
+        message: format!("Invalid UTF-8 in {}", config_name),
         hint: Some("Ensure the file is saved with UTF-8 encoding".to_string()),
     })?;
 
-    crate::config::parse(&yaml_str)
+    let mut upstream = crate::config::parse_upstream(&yaml_str, disable).map_err(|e| match e {
+        Error::VersionRequirement {
+            required, current, ..
+        } => Error::VersionRequirement {
+            required,
+            current,
+            origin: Some(origin.clone()),
+        },
+        other => other,
+    })?;
+    let base_dir = if node.is_local() {
+        PathBuf::from(url)
+    } else {
+        std::env::current_dir()?
+    };
+    upstream.schema = crate::ref_index::resolve(&upstream.schema, &base_dir, repo_manager)?;
+    for id in &upstream.unmatched {
+        diagnostics::warn(
+            ErrorPhase::Config,
+            Message::UnmatchedDisable { id, repo: &origin }.to_string(),
+        );
+    }
+    for deprecation in &upstream.deprecations {
+        diagnostics::warn(
+            ErrorPhase::Discovery,
+            Message::UpstreamDeprecated {
+                repo: &origin,
+                operation: deprecation.operation.as_deref(),
+                message: &deprecation.message,
+                replacement_url: deprecation.replacement_url.as_deref(),
+            }
+            .to_string(),
+        );
+    }
+    Ok(upstream)
+}
+
+/// The error for a repository without a configuration file, which
+/// discovery treats as a plain file tree.
+fn missing_config() -> Error {
+    Error::ConfigParse {
+        message: format!(
+            "No {} or {} found in repository",
+            DEFAULT_CONFIG_FILENAME, ALT_CONFIG_FILENAME
+        ),
+        hint: Some(format!(
+            "Create a {} file in the repository root",
+            DEFAULT_CONFIG_FILENAME
+        )),
+    }
 }
 
 /// Extract deferred operations from an upstream repository's config
@@ -240,9 +349,11 @@ fn extract_deferred_operations(config: &Schema) -> Vec<Operation> {
 
 /// Extract upstream operations from an upstream repository's config
 ///
-/// Upstream operations are include, exclude, rename, template, and template-vars
-/// operations that define how an upstream repo exposes files to consumers.
+/// Upstream operations are include, exclude, rename, extract, template, and
+/// template-vars operations that define how an upstream repo exposes files to consumers.
 /// These are applied BEFORE deferred operations and consumer's with: clause.
+/// Assert operations are carried along too, to be checked against the
+/// consumer's output, and unknown operations, to be reported or refused.
 fn extract_upstream_operations(config: &Schema) -> Vec<Operation> {
     config
         .iter()
@@ -252,8 +363,11 @@ fn extract_upstream_operations(config: &Schema) -> Vec<Operation> {
                 Operation::Include { .. }
                     | Operation::Exclude { .. }
                     | Operation::Rename { .. }
+                    | Operation::Extract { .. }
                     | Operation::Template { .. }
                     | Operation::TemplateVars { .. }
+                    | Operation::Assert { .. }
+                    | Operation::Unknown(_)
             )
         })
         .cloned()
@@ -333,6 +447,9 @@ fn process_config_to_node(config: &Schema, defining_config_dir: &Path) -> Result
         }
 
         let child_operations = repo_op.with.clone();
+        let description = crate::config::description(&Operation::Repo {
+            repo: repo_op.clone(),
+        });
 
         let child_node = if repo_op.is_local() {
             // Resolve the URL against defining_config_dir
@@ -357,13 +474,27 @@ fn process_config_to_node(config: &Schema, defining_config_dir: &Path) -> Result
                 original_url: Some(repo_op.url.clone()),
                 children: Vec::new(),
                 operations: child_operations,
+                disable: repo_op.disable.clone(),
+                verify: None,
+                on_fetch_error: repo_op.on_fetch_error.unwrap_or_default(),
+                requires: None,
+                locked: Vec::new(),
+                partial: Vec::new(),
+                description,
             }
         } else {
-            RepoNode::new(
+            let partial = partial_patterns(&repo_op)?;
+            let mut node = RepoNode::new(
                 repo_op.url,
                 repo_op.r#ref.unwrap_or_default(),
                 child_operations,
-            )
+            );
+            node.disable = repo_op.disable.clone();
+            node.verify = repo_op.verify.clone();
+            node.on_fetch_error = repo_op.on_fetch_error.unwrap_or_default();
+            node.description = description;
+            node.partial = partial;
+            node
         };
 
         root_node.add_child(child_node);
@@ -372,15 +503,50 @@ fn process_config_to_node(config: &Schema, defining_config_dir: &Path) -> Result
     Ok(root_node)
 }
 
+/// The patterns of the `include` operations in the `with:` of a `repo:`
+/// operation marked `partial: true`, or none for a full clone.
+///
+/// A partial clone needs an `include` to know what to download.
+fn partial_patterns(repo_op: &RepoOp) -> Result<Vec<String>> {
+    if !repo_op.partial {
+        return Ok(Vec::new());
+    }
+    let patterns: Vec<String> = repo_op
+        .with
+        .iter()
+        .filter_map(|op| match op {
+            Operation::Include { include, .. } => Some(include.patterns.clone()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    if patterns.is_empty() {
+        return Err(Error::ConfigParse {
+            message: format!(
+                "Repo {} is marked partial: true but has no include in its with:",
+                repo_op.url
+            ),
+            hint: Some(
+                "Add '- include: [...]' to 'with:' naming the files to download".to_string(),
+            ),
+        });
+    }
+    Ok(patterns)
+}
+
 /// Clone all repositories in the tree in parallel
 ///
 /// Uses breadth-first ordering to maximize parallelism - all repos at depth N
 /// are cloned before moving to depth N+1.
 ///
 /// Network Failure Behavior:
-/// - If clone fails but cache exists, continue with cached version and warn
+/// - If clone fails but cache exists, continue with cached version and warn,
+///   unless the repository is marked `on-fetch-error: fail`
 /// - If clone fails and no cache exists, abort with error
 ///
+/// Repositories marked `on-fetch-error: skip` that could not be fetched were
+/// already left out of the tree during discovery.
+///
 /// Clones all repositories at the same depth level in parallel using rayon.
 /// This is the default behavior - no CLI flag needed to enable it.
 pub fn clone_parallel(
@@ -393,34 +559,30 @@ pub fn clone_parallel(
 
     while !current_level.is_empty() {
         // Collect all repos at current depth level that need cloning
-        let repos_to_clone: Vec<(&str, &str)> = current_level
+        let repos_to_clone: Vec<&RepoNode> = current_level
             .iter()
-            .filter_map(|node| {
-                if node.url != "local" {
-                    Some((node.url.as_str(), node.ref_.as_str()))
-                } else {
-                    None
-                }
-            })
+            .copied()
+            .filter(|node| node.url != "local")
             .collect();
 
         // Clone all repos at current depth level in parallel
         // Collect errors from all parallel operations
         let errors: Mutex<Vec<Error>> = Mutex::new(Vec::new());
+        // Cache fallbacks are reported after the parallel section so they
+        // reach the diagnostics collector on the calling thread.
+        let fallbacks: Mutex<Vec<(&str, &str)>> = Mutex::new(Vec::new());
 
-        repos_to_clone.par_iter().for_each(|(url, ref_)| {
+        repos_to_clone.par_iter().for_each(|node| {
+            let (url, ref_) = (node.url.as_str(), node.ref_.as_str());
             // Try to fetch the repository
-            if let Err(e) = repo_manager.fetch_repository(url, ref_) {
+            if let Err(e) = repo_manager.fetch_repository_partial(url, ref_, &node.partial) {
                 // Check if this is a network-related error and if we have a cached version
-                let is_network_error =
-                    matches!(e, Error::GitClone { .. }) || matches!(e, Error::Network { .. });
-
-                if is_network_error && repo_manager.is_cached(url, ref_) {
+                if e.is_fetch_failure()
+                    && node.on_fetch_error != OnFetchError::Fail
+                    && repo_manager.is_cached_partial(url, ref_, &node.partial)
+                {
                     // Fall back to cached version with warning
-                    warn!(
-                        "Network fetch failed for {}@{}, falling back to cached version",
-                        url, ref_
-                    );
+                    fallbacks.lock().unwrap().push((url, ref_));
                     // Continue - the repository is already cached and will be used
                 } else {
                     // Either not a network error, or no cache available - collect the error
@@ -429,6 +591,13 @@ pub fn clone_parallel(
             }
         });
 
+        for (url, ref_) in fallbacks.into_inner().unwrap() {
+            diagnostics::warn(
+                ErrorPhase::Discovery,
+                Message::CachedFallback { url, ref_ }.to_string(),
+            );
+        }
+
         // Return the first error if any occurred
         let collected_errors = errors.into_inner().unwrap();
         if let Some(first_error) = collected_errors.into_iter().next() {
@@ -605,8 +774,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Exclude {
                 exclude: ExcludeOp {
@@ -632,6 +803,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -653,8 +829,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Repo {
                 repo: RepoOp {
@@ -666,6 +844,11 @@ mod tests {
                             patterns: vec!["tests/**".to_string()],
                         },
                     }],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
             Operation::Exclude {
@@ -679,6 +862,11 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
         ];
@@ -699,6 +887,24 @@ mod tests {
         assert_eq!(node.children[1].url, "https://github.com/example/repo2");
     }
 
+    #[test]
+    fn test_process_config_to_node_partial_repo() {
+        let config = crate::config::parse(
+            "- repo:\n    url: https://github.com/example/monorepo\n    ref: v1\n    partial: true\n    with:\n      - include: ['ci/**']\n      - exclude: ['ci/old/**']\n      - include: [LICENSE]\n",
+        )
+        .unwrap();
+        let cwd = std::env::current_dir().unwrap();
+        let node = process_config_to_node(&config, &cwd).unwrap();
+        assert_eq!(node.children[0].partial, ["ci/**", "LICENSE"]);
+
+        let config = crate::config::parse(
+            "- repo:\n    url: https://github.com/example/monorepo\n    ref: v1\n    partial: true\n",
+        )
+        .unwrap();
+        let error = process_config_to_node(&config, &cwd).unwrap_err();
+        assert!(error.to_string().contains("has no include in its with:"));
+    }
+
     #[test]
     fn test_process_config_to_node_local_url_error() {
         // Using "local" as a repo URL should trigger a cycle detection error
@@ -708,6 +914,11 @@ mod tests {
                 r#ref: Some("HEAD".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1192,8 +1403,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*.rs".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Exclude {
                 exclude: ExcludeOp {
@@ -1215,6 +1428,36 @@ mod tests {
         assert_eq!(tree.root.operations.len(), 2);
     }
 
+    #[test]
+    fn test_discover_repos_skips_unreachable_upstream_marked_skip() {
+        let config = crate::config::parse(
+            "- repo:\n    url: https://github.com/example/required\n    ref: main\n\
+             - repo:\n    url: https://github.com/example/optional\n    ref: main\n    \
+             on-fetch-error: skip\n",
+        )
+        .unwrap();
+
+        let git_ops = Box::new(MockGitOperations::with_network_error(
+            "Connection refused".to_string(),
+        ));
+        let cache_ops = Box::new(MockCacheOperations::new());
+        let repo_manager = RepositoryManager::with_operations(git_ops, cache_ops);
+
+        let (tree, warnings) = diagnostics::collect(|| discover_repos(&config, &repo_manager));
+        let tree = tree.unwrap();
+
+        // The required upstream stays, so cloning it fails the run later.
+        let urls: Vec<_> = tree.root.children.iter().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, ["https://github.com/example/required"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings
+            .iter()
+            .next()
+            .unwrap()
+            .to_string()
+            .contains("Skipping https://github.com/example/optional@main"));
+    }
+
     // ========================================================================
     // Tests for extract_deferred_operations
     // ========================================================================
@@ -1239,8 +1482,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["*.rs".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Exclude {
                     exclude: ExcludeOp {
@@ -1267,8 +1512,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["*.rs".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Yaml {
                     yaml: YamlMergeOp {
@@ -1303,8 +1550,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["**/*".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 // First deferred op
                 Operation::Yaml {
@@ -1399,8 +1648,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["*.md".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Repo {
                     repo: RepoOp {
@@ -1408,6 +1659,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1435,6 +1691,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1451,7 +1712,10 @@ mod tests {
         #[test]
         fn test_extracts_rename_operations() {
             let config = vec![Operation::Rename {
-                rename: RenameOp { mappings: vec![] },
+                rename: RenameOp {
+                    mappings: vec![],
+                    rewrite_links: None,
+                },
             }];
             let result = extract_upstream_operations(&config);
             assert_eq!(result.len(), 1);
@@ -1465,8 +1729,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Exclude {
                     exclude: ExcludeOp {
@@ -1474,7 +1740,10 @@ mod tests {
                     },
                 },
                 Operation::Rename {
-                    rename: RenameOp { mappings: vec![] },
+                    rename: RenameOp {
+                        mappings: vec![],
+                        rewrite_links: None,
+                    },
                 },
                 // Non-filtering operations should be ignored
                 Operation::Repo {
@@ -1483,6 +1752,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1497,8 +1771,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["first".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Exclude {
                     exclude: ExcludeOp {
@@ -1509,8 +1785,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["third".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
             ];
             let result = extract_upstream_operations(&config);
@@ -1569,6 +1847,7 @@ mod tests {
                 Operation::Template {
                     template: TemplateOp {
                         patterns: vec!["*.yaml".to_string()],
+                        files: Default::default(),
                     },
                 },
                 Operation::Repo {
@@ -1577,6 +1856,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1601,6 +1885,7 @@ mod tests {
                             m.insert("APP_KEY".to_string(), "MY_APP_KEY".to_string());
                             m
                         },
+                        secrets: Default::default(),
                     },
                 },
                 Operation::Repo {
@@ -1609,6 +1894,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1636,12 +1926,15 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Template {
                     template: TemplateOp {
                         patterns: vec!["*.yaml".to_string()],
+                        files: Default::default(),
                     },
                 },
                 Operation::TemplateVars {
@@ -1651,6 +1944,7 @@ mod tests {
                             m.insert("KEY".to_string(), "VALUE".to_string());
                             m
                         },
+                        secrets: Default::default(),
                     },
                 },
                 Operation::Exclude {
@@ -1659,7 +1953,10 @@ mod tests {
                     },
                 },
                 Operation::Rename {
-                    rename: RenameOp { mappings: vec![] },
+                    rename: RenameOp {
+                        mappings: vec![],
+                        rewrite_links: None,
+                    },
                 },
                 // Non-upstream operations should be ignored
                 Operation::Repo {
@@ -1668,6 +1965,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ];
@@ -1690,8 +1992,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Self_ {
                 self_: SelfOp {
@@ -1699,8 +2003,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["**/*".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     }],
                 },
             },
@@ -1722,6 +2028,11 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
             Operation::Self_ {
@@ -1730,8 +2041,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["**/*".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     }],
                 },
             },
@@ -1762,6 +2075,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1785,6 +2103,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         let err = process_config_to_node(&config, tmp.path()).unwrap_err();
@@ -1806,6 +2129,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         let err = process_config_to_node(&config, tmp.path()).unwrap_err();
@@ -1824,6 +2152,11 @@ mod tests {
                 r#ref: None,
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
         // parent_dir here is irrelevant for an absolute-path URL
@@ -9,7 +9,9 @@
 //!
 //! 1.  **Load Local Files**: Load all files from the working directory into a
 //!     new `MemoryFS`. Specific build/tool directories and config files are
-//!     skipped. Dotfiles are loaded, since common-repo manages them.
+//!     skipped. Dotfiles are loaded, since common-repo manages them. Files
+//!     the last apply propagated and that are unchanged since are not local
+//!     input and are skipped too (see [`super::manifest`]).
 //!
 //! 2.  **Apply Local Template Operations**: Template marking and variable
 //!     substitution are applied to local files.
@@ -30,6 +32,18 @@
 //!     - An include before a merge filters the FS before the merge runs
 //!     - Operations interleave naturally: include, merge, exclude, rename, etc.
 //!
+//! Files in the consumer's overrides directory (see
+//! [`crate::defaults::OVERRIDES_DIR`]) are layered on top of the result, so
+//! they replace whatever upstreams and merges produced at their paths (see
+//! [`load_overrides`]).
+//!
+//! Neither overrides nor local edits may change a file an upstream marked
+//! `locked: true`; [`check_locked`] fails the phase when one does.
+//!
+//! Composite files outside the consumer's `allow-paths:` and `deny-paths:`
+//! are left out before the combination, so nothing an upstream produces is
+//! written outside them (see [`enforce_scope`]).
+//!
 //! This phase produces the final, fully merged `MemoryFS`, which is an exact
 //! representation of what the output directory should look like.
 //!
@@ -40,15 +54,17 @@
 //! exists on disk (as a regular file, directory, or symlink). Entries tagged
 //! `error` cause the pipeline to fail with a list of all conflicting paths.
 
-use std::collections::HashMap;
-use std::path::Path;
-
-use log::warn;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
-use crate::config::{Operation, Schema};
-use crate::defaults::{ALT_CONFIG_FILENAME, DEFAULT_CONFIG_FILENAME};
-use crate::error::{Error, Result};
+use crate::config::{Operation, PathScope, Schema};
+use crate::defaults::{ConfigFiles, OVERRIDES_DIR};
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
 use crate::filesystem::{File, MemoryFS};
+use crate::messages::Message;
+
+use super::manifest::ApplyManifest;
 
 /// Executes Phase 5 of the pipeline (batch mode).
 ///
@@ -137,10 +153,13 @@ pub(crate) fn load_local_fs(working_dir: &Path) -> Result<MemoryFS> {
         ".aws",          // AWS credentials
         ".docker",       // Docker config and credentials
         ".kube",         // Kubernetes config
+        ".common-repo",  // Project cache and named pipeline configurations
         "bin",           // Binary output (broader than spec's explicit list)
         "obj",           // Object files (broader than spec's explicit list)
     ];
 
+    let config_files = ConfigFiles::load();
+
     // Use walkdir to recursively find all files, filtering directories early
     for entry in walkdir::WalkDir::new(working_dir)
         .into_iter()
@@ -175,7 +194,13 @@ pub(crate) fn load_local_fs(working_dir: &Path) -> Result<MemoryFS> {
         .filter_map(|e| match e {
             Ok(entry) => Some(entry),
             Err(err) => {
-                warn!("Skipping inaccessible path during local FS load: {}", err);
+                diagnostics::warn(
+                    ErrorPhase::Output,
+                    Message::InaccessiblePath {
+                        error: &err.to_string(),
+                    }
+                    .to_string(),
+                );
                 None
             }
         })
@@ -190,12 +215,8 @@ pub(crate) fn load_local_fs(working_dir: &Path) -> Result<MemoryFS> {
                 message: format!("Failed to make path relative: {}", file_path.display()),
             })?;
 
-        // Skip .common-repo.yaml config file
-        if relative_path
-            .to_str()
-            .map(|s| s == DEFAULT_CONFIG_FILENAME || s == ALT_CONFIG_FILENAME)
-            .unwrap_or(false)
-        {
+        // Skip the project's own config file
+        if config_files.is_config_file(relative_path) {
             continue;
         }
 
@@ -206,6 +227,160 @@ pub(crate) fn load_local_fs(working_dir: &Path) -> Result<MemoryFS> {
     Ok(local_fs)
 }
 
+/// Load the files of the overrides directory under `working_dir`, at their
+/// paths relative to it.
+///
+/// None of the skip rules of [`load_local_fs`] apply: every regular file in
+/// the directory is an explicit replacement, dotfiles and files under
+/// `build/` or `.github/` alike. Symbolic links are not followed. A missing
+/// directory has no overrides.
+pub(crate) fn load_overrides(working_dir: &Path) -> Result<MemoryFS> {
+    let root = working_dir.join(OVERRIDES_DIR);
+    let mut overrides = MemoryFS::new();
+    if !root.is_dir() {
+        return Ok(overrides);
+    }
+    for entry in walkdir::WalkDir::new(&root) {
+        let entry = entry.map_err(|e| Error::Filesystem {
+            message: format!("Failed to read {}: {}", OVERRIDES_DIR, e),
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry.path().strip_prefix(&root).map_err(|_| Error::Path {
+            message: format!("Failed to make path relative: {}", entry.path().display()),
+        })?;
+        overrides.add_file(relative_path, File::from_path(entry.path())?)?;
+    }
+    Ok(overrides)
+}
+
+/// Load the local input files from the working directory.
+///
+/// Like [`load_local_fs`], but leaves out files that the last apply
+/// propagated from upstream and that are unchanged since (see
+/// [`super::manifest`]). Those are earlier output rather than local input,
+/// so treating them as local would keep upstream changes from flowing
+/// through. Files edited after the apply are kept.
+pub(crate) fn load_local_inputs(
+    working_dir: &Path,
+    manifest: Option<&ApplyManifest>,
+) -> Result<MemoryFS> {
+    let mut local_fs = load_local_fs(working_dir)?;
+    let Some(manifest) = manifest.filter(|manifest| !manifest.is_empty()) else {
+        return Ok(local_fs);
+    };
+    let propagated: Vec<PathBuf> = local_fs
+        .files()
+        .filter(|(path, file)| manifest.is_propagated(path, file))
+        .map(|(path, _)| path.clone())
+        .collect();
+    for path in propagated {
+        log::debug!(
+            "local input: skipping {} (unchanged since the last apply wrote it)",
+            path.display()
+        );
+        local_fs.remove_file(&path)?;
+    }
+    Ok(local_fs)
+}
+
+/// Fail when files an upstream locked were modified locally.
+///
+/// `locked` lists, for each repository URL, the patterns of its `include`
+/// operations marked `locked: true`. A composite file is locked when it was
+/// included from that repository by a path one of them matches. It was
+/// modified when `overrides` replaces it, or when `local` has a copy with
+/// different content. Since `local` is loaded by [`load_local_inputs`], a
+/// copy left unchanged since the last apply propagated it is not local
+/// input, and an upstream change to a locked file flows through.
+///
+/// Every modified file is reported in a single
+/// [`Error::LockedFilesModified`].
+pub(crate) fn check_locked(
+    composite: &MemoryFS,
+    local: &MemoryFS,
+    overrides: &MemoryFS,
+    locked: &[(String, Vec<String>)],
+) -> Result<()> {
+    if locked.is_empty() {
+        return Ok(());
+    }
+    let mut paths = Vec::new();
+    for (url, patterns) in locked {
+        let patterns = crate::path::PatternSet::cached(patterns)?;
+        for (path, file) in composite.files() {
+            let Some(origin) = &file.origin else {
+                continue;
+            };
+            if origin.repo.as_deref() != Some(url.as_str()) || !patterns.is_match(&origin.source) {
+                continue;
+            }
+            let edited = local
+                .get_file(path)
+                .is_some_and(|copy| copy.content != file.content);
+            if edited || overrides.exists(path) {
+                paths.push(format!("{} (locked by {})", path.display(), url));
+            }
+        }
+    }
+    if paths.is_empty() {
+        return Ok(());
+    }
+    paths.sort();
+    Err(Error::LockedFilesModified { paths })
+}
+
+/// Leave the composite files that `scope` does not allow out, along with
+/// the auto-merges into such paths, warning about each.
+///
+/// A composite file with the same content as the local file at its path
+/// writes nothing, so it is left alone.
+pub(crate) fn enforce_scope(
+    composite: &mut MemoryFS,
+    local: &MemoryFS,
+    auto_merge_targets: &mut BTreeMap<String, Operation>,
+    scope: &PathScope,
+) -> Result<()> {
+    if scope.is_empty() {
+        return Ok(());
+    }
+    let mut outside = Vec::new();
+    for (path, file) in composite.files() {
+        let unchanged = local
+            .get_file(path)
+            .is_some_and(|copy| copy.content == file.content);
+        if !unchanged && !scope.allows(path)? {
+            outside.push(path.clone());
+        }
+    }
+    for path in &outside {
+        composite.remove_file(path)?;
+    }
+    let mut targets = Vec::new();
+    for target in auto_merge_targets.keys() {
+        if !scope.allows(Path::new(target))? {
+            targets.push(target.clone());
+        }
+    }
+    for target in &targets {
+        auto_merge_targets.remove(target);
+        outside.push(PathBuf::from(target));
+    }
+    outside.sort();
+    outside.dedup();
+    for path in &outside {
+        diagnostics::warn(
+            ErrorPhase::Merge,
+            Message::OutsidePathScope {
+                path: &path.display().to_string(),
+            }
+            .to_string(),
+        );
+    }
+    Ok(())
+}
+
 /// Merge composite files over local files (composite wins for shared paths)
 #[allow(dead_code)]
 fn merge_composite_over_local(final_fs: &mut MemoryFS, composite_fs: &MemoryFS) -> Result<()> {
@@ -278,6 +453,20 @@ fn apply_consumer_operations(final_fs: &mut MemoryFS, local_config: &Schema) ->
             Operation::Rename { rename } => {
                 operators::rename::apply(rename, final_fs)?;
             }
+            Operation::File { file } => {
+                operators::file::apply(file, final_fs)?;
+            }
+            // Needs the repository manager; applied by the compose stage
+            Operation::Vendor { .. } => {}
+            Operation::Header { header } => {
+                operators::header::apply(header, final_fs)?;
+            }
+            Operation::YamlFormat { yaml_format } => {
+                operators::yaml_format::apply(yaml_format, final_fs)?;
+            }
+            Operation::Extract { extract } => {
+                crate::merge::apply_extract_operation(final_fs, extract)?;
+            }
             // Merge operations — delegate to the shared dispatcher
             Operation::Yaml { .. }
             | Operation::Json { .. }
@@ -296,6 +485,10 @@ fn apply_consumer_operations(final_fs: &mut MemoryFS, local_config: &Schema) ->
             Operation::TemplateVars { .. } => {}
             Operation::Tools { .. } => {}
             Operation::Self_ { .. } => {}
+            // Formatting, patches and assertions run after this phase, on
+            // the combined output
+            Operation::Format { .. } | Operation::Patch { .. } | Operation::Assert { .. } => {}
+            Operation::Unknown(unknown) => unknown.report(),
         }
     }
     Ok(())
@@ -332,14 +525,10 @@ pub(crate) fn filter_if_exists(
     // Suppress unused-variable warning: `local` is kept for API consistency
     // and future content-comparison use.
     let _ = local;
-    // Sort paths so that the `Error` arm reports deterministic output and the
-    // per-file debug log lines for `Preserve` skips appear in stable order
-    // regardless of `MemoryFS`'s internal HashMap iteration order.
-    let paths: Vec<std::path::PathBuf> = {
-        let mut p = composite.list_files();
-        p.sort();
-        p
-    };
+    // `list_files` is sorted, so the `Error` arm reports deterministic output
+    // and the per-file debug log lines for `Preserve` skips appear in stable
+    // order.
+    let paths: Vec<std::path::PathBuf> = composite.list_files();
     let mut error_paths: Vec<String> = Vec::new();
     for path in paths {
         let Some(file) = composite.get_file(&path) else {
@@ -594,6 +783,98 @@ mod tests {
     use crate::config::{ExcludeOp, IfExists, IncludeOp, JsonMergeOp, RenameMapping, RenameOp};
     use tempfile::TempDir;
 
+    #[test]
+    fn test_check_locked_reports_edits_and_overrides() {
+        use crate::filesystem::FileOrigin;
+        use std::sync::Arc;
+
+        let upstream = |source: &str| {
+            let mut file = File::from_string("upstream");
+            file.origin = Some(Arc::new(FileOrigin {
+                repo: Some("https://github.com/org/policies".to_string()),
+                source: PathBuf::from(source),
+                ..FileOrigin::default()
+            }));
+            file
+        };
+        let mut composite = MemoryFS::new();
+        composite
+            .add_file("SECURITY.md", upstream("SECURITY.md"))
+            .unwrap();
+        // Renamed on the way; still locked by its upstream path
+        composite
+            .add_file("docs/CODEOWNERS", upstream("CODEOWNERS"))
+            .unwrap();
+        composite
+            .add_file("README.md", upstream("README.md"))
+            .unwrap();
+        let locked = vec![(
+            "https://github.com/org/policies".to_string(),
+            vec!["SECURITY.md".to_string(), "CODEOWNERS".to_string()],
+        )];
+
+        let mut local = MemoryFS::new();
+        local.add_file_string("SECURITY.md", "upstream").unwrap();
+        local.add_file_string("README.md", "edited").unwrap();
+        check_locked(&composite, &local, &MemoryFS::new(), &locked).unwrap();
+
+        local.add_file_string("docs/CODEOWNERS", "edited").unwrap();
+        let mut overrides = MemoryFS::new();
+        overrides.add_file_string("SECURITY.md", "ours").unwrap();
+        let err = check_locked(&composite, &local, &overrides, &locked).unwrap_err();
+        let Error::LockedFilesModified { paths } = err else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(
+            paths,
+            [
+                "SECURITY.md (locked by https://github.com/org/policies)",
+                "docs/CODEOWNERS (locked by https://github.com/org/policies)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enforce_scope_leaves_out_files_outside() {
+        let mut composite = MemoryFS::new();
+        composite
+            .add_file_string(".github/workflows/ci.yml", "ci")
+            .unwrap();
+        composite.add_file_string("Makefile", "upstream").unwrap();
+        composite.add_file_string("README.md", "same").unwrap();
+        composite
+            .add_file_string(".github/CODEOWNERS", "* @org")
+            .unwrap();
+        let mut local = MemoryFS::new();
+        local.add_file_string("README.md", "same").unwrap();
+        let mut targets = BTreeMap::new();
+        for target in [".github/labels.yml", "package.json"] {
+            targets.insert(
+                target.to_string(),
+                Operation::Exclude {
+                    exclude: crate::config::ExcludeOp { patterns: vec![] },
+                },
+            );
+        }
+        let scope = PathScope {
+            allow: vec![".github/**".to_string()],
+            deny: vec![".github/CODEOWNERS".to_string()],
+        };
+
+        enforce_scope(&mut composite, &local, &mut targets, &scope).unwrap();
+        let mut paths = composite.list_files();
+        paths.sort();
+        // A file with the local content writes nothing, so it stays
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from(".github/workflows/ci.yml"),
+                PathBuf::from("README.md")
+            ]
+        );
+        assert_eq!(targets.keys().collect::<Vec<_>>(), [".github/labels.yml"]);
+    }
+
     #[test]
     fn test_phase5_execute_merge_local_files() {
         let temp_dir = TempDir::new().unwrap();
@@ -636,7 +917,7 @@ mod tests {
 
         let file = final_fs.get_file("common.txt").unwrap();
         assert_eq!(
-            String::from_utf8(file.content.clone()).unwrap(),
+            String::from_utf8(file.content.to_vec()).unwrap(),
             "composite version"
         );
     }
@@ -662,7 +943,7 @@ mod tests {
         assert!(final_fs.exists("local_only.txt"));
         let local_file = final_fs.get_file("local_only.txt").unwrap();
         assert_eq!(
-            String::from_utf8(local_file.content.clone()).unwrap(),
+            String::from_utf8(local_file.content.to_vec()).unwrap(),
             "my local file"
         );
     }
@@ -696,7 +977,7 @@ mod tests {
         let final_fs = execute(&composite_fs, &local_config, working_dir, &deferred_ops).unwrap();
 
         let package_file = final_fs.get_file("package.json").unwrap();
-        let content = String::from_utf8(package_file.content.clone()).unwrap();
+        let content = String::from_utf8(package_file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
 
         assert_eq!(json["name"], "my-app");
@@ -739,7 +1020,7 @@ mod tests {
         let final_fs = execute(&composite_fs, &local_config, working_dir, &deferred_ops).unwrap();
 
         let file = final_fs.get_file("ci-config.json").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
 
         // Composite version wins (new-runner, 60)
@@ -780,7 +1061,7 @@ mod tests {
         let final_fs = execute(&composite_fs, &local_config, working_dir, &[]).unwrap();
 
         let file = final_fs.get_file("output.json").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
         assert_eq!(json["base"], true);
         assert_eq!(json["added"], true);
@@ -907,6 +1188,32 @@ mod tests {
         assert!(local_fs.exists("real.txt"));
     }
 
+    #[test]
+    fn test_phase5_load_overrides_ignores_skip_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let working_dir = temp_dir.path();
+        assert!(load_overrides(working_dir).unwrap().is_empty());
+
+        let overrides = working_dir.join(OVERRIDES_DIR);
+        std::fs::create_dir_all(overrides.join(".vscode")).unwrap();
+        std::fs::create_dir_all(overrides.join("build")).unwrap();
+        std::fs::write(overrides.join(".vscode/settings.json"), b"{}").unwrap();
+        std::fs::write(overrides.join("build/config.mk"), b"mine").unwrap();
+        std::fs::write(overrides.join(".DS_Store"), b"metadata").unwrap();
+
+        let overrides = load_overrides(working_dir).unwrap();
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(
+            overrides.get_file("build/config.mk").unwrap().content,
+            b"mine"
+        );
+        assert!(overrides.exists(".vscode/settings.json"));
+
+        // The overrides directory itself is not local input.
+        let local_fs = load_local_fs(working_dir).unwrap();
+        assert!(local_fs.is_empty());
+    }
+
     #[test]
     #[cfg(unix)]
     fn test_phase5_load_local_fs_preserves_executable_permissions() {
@@ -1032,8 +1339,10 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["*.txt".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         }];
 
         let final_fs = execute(&composite_fs, &local_config, working_dir, &[]).unwrap();
@@ -1059,6 +1368,7 @@ mod tests {
                     from: r"old_name\.txt".to_string(),
                     to: "new_name.txt".to_string(),
                 }],
+                rewrite_links: None,
             },
         }];
 
@@ -1116,7 +1426,7 @@ mod tests {
         let final_fs = execute(&composite_fs, &local_config, working_dir, &deferred_ops).unwrap();
 
         let file = final_fs.get_file("config.json").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
 
         // Base preserved
@@ -1194,7 +1504,7 @@ mod tests {
         let final_fs = execute(&composite_fs, &local_config, working_dir, &deferred_ops).unwrap();
 
         let file = final_fs.get_file("settings.json").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
 
         // Source wins for conflicting keys (SourceAlwaysWins invariant)
@@ -1224,8 +1534,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             // Step 2: Merge fragment into config (both survived the include)
             Operation::Json {
@@ -1249,7 +1561,7 @@ mod tests {
         assert!(!final_fs.exists("root.txt"));
         // Merge happened: config.json has merged content
         let file = final_fs.get_file("src/config.json").unwrap();
-        let content = String::from_utf8(file.content.clone()).unwrap();
+        let content = String::from_utf8(file.content.to_vec()).unwrap();
         let json: serde_json::Value = serde_json::from_str(&content).unwrap();
         assert_eq!(json["base"], true);
         assert_eq!(json["extra"], true);
@@ -0,0 +1,408 @@
+//! The apply manifest: which files the last apply propagated from upstream.
+//!
+//! Phase 6 writes the whole final filesystem, so on the next run the files
+//! an apply wrote are back in the working directory and would be read as
+//! local files. Local files are the base that upstream content is merged
+//! into, so a file that was only ever upstream content would keep the
+//! previous upstream's keys, or survive `if-exists` checks, forever.
+//!
+//! After a source block writes its output, the manifest records each file
+//! that came from the composite rather than from a local file, with the
+//! SHA-256 of the content written. When the next run loads local files (see
+//! [`super::local_merge::load_local_inputs`]), a file that is listed and
+//! still has that content is left out, so upstream changes flow through.
+//! A listed file that was edited since is a genuine local file again.
+//!
+//! The manifest also lets an apply follow a file the upstream renamed
+//! between refs: a file the last apply propagated that is no longer in the
+//! output, and whose content now arrives at a new path, was moved (see
+//! [`ApplyManifest::renames`]). Its old copy is removed when it is still
+//! unchanged on disk, instead of being left behind as a stale duplicate.
+//! Empty files are listed like any other, but have no content to follow,
+//! so they are never taken for renames.
+//!
+//! The permission bits written are recorded too, so that a change of mode
+//! alone, such as a script made executable upstream, is one of the
+//! [`ApplyManifest::changes`] of an apply.
+//!
+//! Manifests are stored under the cache root in `manifests/`, one per output
+//! directory and named pipeline (see [`crate::defaults::PIPELINES_DIR`]). A missing or unreadable manifest lists nothing, which is the
+//! behavior without one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::filesystem::{File, MemoryFS};
+use crate::http::sha256_hex;
+use crate::messages::Message;
+
+/// The files an apply into one directory propagated from upstream.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyManifest {
+    /// SHA-256 of the content written, by path relative to the directory.
+    files: BTreeMap<PathBuf, String>,
+    /// Permission bits written, by path. Manifests saved before modes were
+    /// recorded have none.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    modes: BTreeMap<PathBuf, u32>,
+}
+
+impl ApplyManifest {
+    /// An empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the manifest for applying `pipeline`, or the default
+    /// configuration, into `dir` is stored.
+    pub fn path(cache_root: &Path, dir: &Path, pipeline: Option<&str>) -> PathBuf {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let key = sha256_hex(dir.display().to_string().as_bytes());
+        let name = match pipeline {
+            Some(pipeline) => format!("{}-{}.json", &key[..16], pipeline),
+            None => format!("{}.json", &key[..16]),
+        };
+        cache_root.join("manifests").join(name)
+    }
+
+    /// The manifest of the last apply of `pipeline` into `dir`, or an empty
+    /// one.
+    pub fn load(cache_root: &Path, dir: &Path, pipeline: Option<&str>) -> Self {
+        let path = Self::path(cache_root, dir, pipeline);
+        let Ok(content) = fs::read(&path) else {
+            return Self::new();
+        };
+        serde_json::from_slice(&content).unwrap_or_else(|e| {
+            debug!("ignoring unreadable manifest {}: {}", path.display(), e);
+            Self::new()
+        })
+    }
+
+    /// Save the manifest for applying `pipeline` into `dir`. The manifest
+    /// only refines what counts as local, so a failure to save it does not
+    /// fail the apply.
+    pub fn save(&self, cache_root: &Path, dir: &Path, pipeline: Option<&str>) {
+        let path = Self::path(cache_root, dir, pipeline);
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|()| serde_json::to_vec_pretty(self).map_err(|e| e.to_string()))
+            .and_then(|content| fs::write(&path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            debug!("failed to save manifest {}: {}", path.display(), e);
+        }
+    }
+
+    /// Record that `file` was propagated to `path`.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, file: &File) {
+        let path = path.into();
+        self.modes.insert(path.clone(), file.permissions & 0o777);
+        self.files.insert(path, sha256_hex(&file.content));
+    }
+
+    /// Whether `file`, found at `path`, is unchanged since an apply
+    /// propagated it.
+    pub fn is_propagated(&self, path: &Path, file: &File) -> bool {
+        self.files
+            .get(path)
+            .is_some_and(|sha256| *sha256 == sha256_hex(&file.content))
+    }
+
+    /// The files this manifest lists that `next` moved: each old path with
+    /// the new path of the same content, sorted by old path.
+    ///
+    /// A file moved when `next` no longer lists its path and lists its
+    /// content at a path this manifest does not. Each new path is matched
+    /// to at most one old path. Empty files are all alike, so none is
+    /// matched.
+    pub fn renames(&self, next: &ApplyManifest) -> Vec<(PathBuf, PathBuf)> {
+        let empty = sha256_hex(b"");
+        let mut added: BTreeMap<&str, Vec<&PathBuf>> = BTreeMap::new();
+        for (path, sha256) in &next.files {
+            if !self.files.contains_key(path) && *sha256 != empty {
+                added.entry(sha256.as_str()).or_default().push(path);
+            }
+        }
+        let mut renames = Vec::new();
+        for (path, sha256) in &self.files {
+            if next.files.contains_key(path) {
+                continue;
+            }
+            if let Some(candidates) = added.get_mut(sha256.as_str()) {
+                if !candidates.is_empty() {
+                    renames.push((path.clone(), candidates.remove(0).clone()));
+                }
+            }
+        }
+        renames
+    }
+
+    /// The files that differ between this manifest and `next`: listed in
+    /// only one of them, or with different content or permission bits,
+    /// sorted by path. A file without a recorded mode in either manifest is
+    /// compared by content alone.
+    pub fn changes(&self, next: &ApplyManifest) -> Vec<PathBuf> {
+        let mode_changed = |path: &PathBuf| match (self.modes.get(path), next.modes.get(path)) {
+            (Some(old), Some(new)) => old != new,
+            _ => false,
+        };
+        let mut changed: Vec<PathBuf> = next
+            .files
+            .iter()
+            .filter(|(path, sha256)| self.files.get(*path) != Some(*sha256) || mode_changed(path))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !next.files.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        changed
+    }
+
+    /// The files listed under `dir`, with paths relative to it, for an
+    /// apply that placed its output under `dir`.
+    pub fn within(&self, dir: &Path) -> Self {
+        fn strip<T: Clone>(entries: &BTreeMap<PathBuf, T>, dir: &Path) -> BTreeMap<PathBuf, T> {
+            entries
+                .iter()
+                .filter_map(|(path, value)| {
+                    let path = path.strip_prefix(dir).ok()?;
+                    Some((path.to_path_buf(), value.clone()))
+                })
+                .collect()
+        }
+        Self {
+            files: strip(&self.files, dir),
+            modes: strip(&self.modes, dir),
+        }
+    }
+
+    /// The paths listed, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Number of files listed.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files are listed.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Remove from `dir` the old copies of the files that the apply recorded in
+/// `next` moved since the apply recorded in `previous`.
+///
+/// An old copy is only removed when nothing in `final_fs` is written to its
+/// path and it is unchanged on disk since it was propagated; one edited
+/// since is a local file and is kept. Directories left empty are removed
+/// too.
+pub(crate) fn remove_renamed(
+    previous: &ApplyManifest,
+    next: &ApplyManifest,
+    final_fs: &MemoryFS,
+    dir: &Path,
+) -> Result<()> {
+    for (from, to) in previous.renames(next) {
+        let disk_path = dir.join(&from);
+        if final_fs.exists(&from) || !disk_path.is_file() {
+            continue;
+        }
+        if !previous.is_propagated(&from, &File::from_path(&disk_path)?) {
+            debug!(
+                "keeping {}: edited since the last apply, renamed upstream to {}",
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+        fs::remove_file(&disk_path).map_err(|e| Error::Filesystem {
+            message: format!("Failed to remove '{}': {}", disk_path.display(), e),
+        })?;
+        info!(
+            "{}",
+            Message::RenamedUpstream {
+                from: &from,
+                to: &to
+            }
+        );
+        let mut parent = disk_path.parent();
+        while let Some(path) = parent.filter(|path| *path != dir) {
+            if fs::remove_dir(path).is_err() {
+                break;
+            }
+            parent = path.parent();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_round_trip_and_edited_files() {
+        let cache = TempDir::new().unwrap();
+        let dir = TempDir::new().unwrap();
+
+        let mut manifest = ApplyManifest::new();
+        manifest.insert("ci.yml", &File::from_string("upstream"));
+        manifest.save(cache.path(), dir.path(), None);
+
+        let loaded = ApplyManifest::load(cache.path(), dir.path(), None);
+        assert_eq!(loaded, manifest);
+        assert!(loaded.is_propagated(Path::new("ci.yml"), &File::from_string("upstream")));
+        assert!(!loaded.is_propagated(Path::new("ci.yml"), &File::from_string("edited")));
+        assert!(!loaded.is_propagated(Path::new("other.yml"), &File::from_string("upstream")));
+    }
+
+    #[test]
+    fn test_within_strips_the_subdirectory() {
+        let mut manifest = ApplyManifest::new();
+        manifest.insert("infra/shared/ci.yml", &File::from_string("upstream"));
+        manifest.insert("README.md", &File::from_string("upstream"));
+
+        let within = manifest.within(Path::new("infra/shared"));
+        assert_eq!(within.len(), 1);
+        assert!(within.is_propagated(Path::new("ci.yml"), &File::from_string("upstream")));
+    }
+
+    #[test]
+    fn test_renames_match_moved_content() {
+        let manifest = |files: &[(&str, &str)]| {
+            let mut manifest = ApplyManifest::new();
+            for (path, content) in files {
+                manifest.insert(*path, &File::from_string(content));
+            }
+            manifest
+        };
+        let previous = manifest(&[
+            ("ci.yml", "ci"),
+            ("docs/old.md", "guide"),
+            ("gone.txt", "removed"),
+            ("lint.yml", "lint"),
+            ("old/.gitkeep", ""),
+        ]);
+        let next = manifest(&[
+            (".github/ci.yml", "ci"),
+            ("docs/new.md", "guide"),
+            ("lint.yml", "lint"),
+            ("copy.yml", "lint"),
+            ("new/.gitkeep", ""),
+        ]);
+        assert_eq!(
+            previous.renames(&next),
+            vec![
+                (PathBuf::from("ci.yml"), PathBuf::from(".github/ci.yml")),
+                (PathBuf::from("docs/old.md"), PathBuf::from("docs/new.md")),
+            ]
+        );
+        assert!(next.renames(&next).is_empty());
+    }
+
+    #[test]
+    fn test_changes_list_added_removed_and_edited_files() {
+        let mut previous = ApplyManifest::new();
+        previous.insert("ci.yml", &File::from_string("ci"));
+        previous.insert("gone.txt", &File::from_string("removed"));
+        previous.insert("lint.yml", &File::from_string("lint"));
+        let mut next = ApplyManifest::new();
+        next.insert("ci.yml", &File::from_string("ci v2"));
+        next.insert("lint.yml", &File::from_string("lint"));
+        next.insert("new.md", &File::from_string("new"));
+
+        assert_eq!(
+            previous.changes(&next),
+            vec![
+                PathBuf::from("ci.yml"),
+                PathBuf::from("gone.txt"),
+                PathBuf::from("new.md"),
+            ]
+        );
+        assert!(next.changes(&next).is_empty());
+
+        let mut executable = File::from_string("lint");
+        executable.permissions = 0o755;
+        let mut chmodded = next.clone();
+        chmodded.insert("lint.yml", &executable);
+        assert_eq!(next.changes(&chmodded), vec![PathBuf::from("lint.yml")]);
+
+        // Manifests saved before modes were recorded compare content only.
+        let legacy: ApplyManifest =
+            serde_json::from_str(r#"{"files": {"lint.yml": "x"}}"#).unwrap();
+        assert!(legacy.modes.is_empty());
+        let mut same = ApplyManifest::new();
+        same.files
+            .insert(PathBuf::from("lint.yml"), "x".to_string());
+        same.modes.insert(PathBuf::from("lint.yml"), 0o755);
+        assert!(legacy.changes(&same).is_empty());
+    }
+
+    #[test]
+    fn test_remove_renamed_keeps_edited_copies() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("old/a.md"), "a").unwrap();
+        fs::write(dir.path().join("b.md"), "edited").unwrap();
+        let mut previous = ApplyManifest::new();
+        previous.insert("old/a.md", &File::from_string("a"));
+        previous.insert("b.md", &File::from_string("b"));
+        let mut final_fs = MemoryFS::new();
+        final_fs.add_file_string("new/a.md", "a").unwrap();
+        final_fs.add_file_string("new/b.md", "b").unwrap();
+        let mut next = ApplyManifest::new();
+        for (path, file) in final_fs.files() {
+            next.insert(path, file);
+        }
+
+        remove_renamed(&previous, &next, &final_fs, dir.path()).unwrap();
+        assert!(!dir.path().join("old").exists());
+        assert!(dir.path().join("b.md").exists());
+    }
+
+    #[test]
+    fn test_missing_manifest_is_empty() {
+        let cache = TempDir::new().unwrap();
+        let dir = TempDir::new().unwrap();
+        assert!(ApplyManifest::load(cache.path(), dir.path(), None).is_empty());
+
+        fs::create_dir_all(cache.path().join("manifests")).unwrap();
+        fs::write(
+            ApplyManifest::path(cache.path(), dir.path(), None),
+            "not json",
+        )
+        .unwrap();
+        assert!(ApplyManifest::load(cache.path(), dir.path(), None).is_empty());
+    }
+
+    #[test]
+    fn test_pipelines_have_separate_manifests() {
+        let cache = TempDir::new().unwrap();
+        let dir = TempDir::new().unwrap();
+
+        let mut manifest = ApplyManifest::new();
+        manifest.insert("ci.yml", &File::from_string("upstream"));
+        manifest.save(cache.path(), dir.path(), Some("ci"));
+
+        assert_eq!(
+            ApplyManifest::load(cache.path(), dir.path(), Some("ci")),
+            manifest
+        );
+        assert!(ApplyManifest::load(cache.path(), dir.path(), Some("docs")).is_empty());
+        assert!(ApplyManifest::load(cache.path(), dir.path(), None).is_empty());
+    }
+}
@@ -20,6 +20,7 @@ use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
 use log::warn;
+use serde::{Deserialize, Serialize};
 
 use crate::config::Operation;
 use crate::filesystem::MemoryFS;
@@ -61,10 +62,14 @@ pub(crate) fn ops_fingerprint(operations: &[Operation]) -> Option<String> {
 }
 
 // Phase modules - internal implementations
+pub mod checkpoint;
 pub(crate) mod composite;
+pub mod context;
 pub(crate) mod discovery;
 pub(crate) mod local_merge;
+pub mod manifest;
 pub(crate) mod ordering;
+pub mod plan;
 pub(crate) mod processing;
 pub(crate) mod write;
 
@@ -86,8 +91,95 @@ pub(crate) use write as phase6;
 /// This is re-exported for CLI command use (tree, validate).
 pub use discovery::discover_repos;
 
+/// How the output is written to disk; see [`context::RunContext`].
+pub use write::{ReadOnlyPolicy, WriteOptions};
+
+/// Discover the inheritance tree of `config` and fetch every repository in
+/// it into the disk cache, without processing or writing anything.
+///
+/// This is Phase 1 on its own, used by `cache warm` to pre-warm a cache.
+/// Relative local paths in `config` resolve against the context's working
+/// directory.
+pub fn prefetch(
+    config: &crate::config::Schema,
+    ctx: &context::RunContext,
+) -> crate::error::Result<RepoTree> {
+    phase1::execute(config, ctx)
+}
+
+/// The template variables that upstreams of `config` require and that
+/// neither `config` nor the context sets, each listed once.
+///
+/// Discovers the inheritance tree as [`prefetch`] does, so that `apply` can
+/// ask for the variables before the pipeline fails on them. Only the
+/// source operations are checked; `self:` blocks are checked when they run.
+pub fn unset_required_vars(
+    config: &crate::config::Schema,
+    ctx: &context::RunContext,
+) -> crate::error::Result<Vec<crate::config::RequiredVar>> {
+    let (_, source_config) = orchestrator::partition_self_operations(config);
+    let tree = phase1::execute(&source_config, ctx)?;
+    let mut seen = std::collections::HashSet::new();
+    Ok(phase2::unset_vars(&tree, &source_config, ctx)
+        .into_iter()
+        .filter(|(_, var)| seen.insert(var.name.as_str()))
+        .map(|(_, var)| var.clone())
+        .collect())
+}
+
+/// Fail if `config` or one of its upstreams has an operation this version
+/// does not know and the context is strict, or one whose name looks like a
+/// misspelled known operation.
+///
+/// Discovers the inheritance tree as [`prefetch`] does, so that `check
+/// --strict` finds what `apply --strict` would refuse without running the
+/// pipeline.
+pub fn check_unknown_operations(
+    config: &crate::config::Schema,
+    ctx: &context::RunContext,
+) -> crate::error::Result<()> {
+    let tree = phase1::execute(config, ctx)?;
+    phase2::check_unknown_operations(&tree, config, ctx.strict)
+}
+
+/// Write every file and directory of `fs` under `output_path`, as Phase 6
+/// does for the output of a pull, refusing to write through symlinks.
+///
+/// Used by `export-template` to write a composite somewhere other than the
+/// output directory.
+pub fn write_fs(
+    fs: &crate::filesystem::MemoryFS,
+    output_path: &std::path::Path,
+) -> crate::error::Result<()> {
+    phase6::execute(fs, output_path, WriteOptions::default())
+}
+
+/// Write every file and directory of `fs` as a tar archive to `writer`,
+/// instead of to disk.
+///
+/// Used by `apply --output-format tar`.
+pub fn write_tar(
+    fs: &crate::filesystem::MemoryFS,
+    writer: impl std::io::Write,
+) -> crate::error::Result<()> {
+    phase6::write_tar(fs, writer)
+}
+
+/// Fetch the repository `url` at `ref_` and parse its configuration as an
+/// upstream, without discovering the repositories it inherits from.
+///
+/// Used by `describe` to show what an upstream offers before adopting it.
+pub fn fetch_upstream(
+    url: &str,
+    ref_: &str,
+    repo_manager: &crate::repository::RepositoryManager,
+) -> crate::error::Result<crate::config::Upstream> {
+    let node = RepoNode::new(url.to_string(), ref_.to_string(), Vec::new());
+    phase1::fetch_and_parse_config(&node, repo_manager)
+}
+
 /// Repository tree node representing inheritance hierarchy
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RepoNode {
     /// Repository URL. For local nodes after discovery, this is the canonical
     /// absolute path produced by `fs::canonicalize`.
@@ -101,6 +193,33 @@ pub struct RepoNode {
     pub children: Vec<RepoNode>,
     /// Operations to apply to this repository
     pub operations: Vec<Operation>,
+    /// Ids of this repository's own operations that the inheriting config
+    /// disabled with `repo: { disable: [...] }`.
+    pub disable: Vec<String>,
+    /// Trusted signers from the inheriting config's `repo: { verify: ... }`.
+    pub verify: Option<crate::config::VerifyOp>,
+    /// What to do when the repository cannot be fetched, from the inheriting
+    /// config.
+    #[serde(default)]
+    pub on_fetch_error: crate::config::OnFetchError,
+    /// What this repository's own config requires of its consumers.
+    #[serde(default)]
+    pub requires: Option<crate::config::ConsumerRequirements>,
+    /// Patterns of this repository's own `include` operations marked
+    /// `locked: true`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locked: Vec<String>,
+    /// Patterns of the `include` operations in the `with:` of a `repo:`
+    /// operation marked `partial: true`, which limit what is downloaded of
+    /// this repository (see
+    /// [`RepositoryManager::fetch_repository_partial`](crate::repository::RepositoryManager::fetch_repository_partial)).
+    /// Empty for a full clone.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partial: Vec<String>,
+    /// The `description:` of the `repo:` operation that added this
+    /// repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 impl RepoNode {
@@ -111,6 +230,13 @@ impl RepoNode {
             original_url: None,
             children: Vec::new(),
             operations,
+            disable: Vec::new(),
+            verify: None,
+            on_fetch_error: Default::default(),
+            requires: None,
+            locked: Vec::new(),
+            partial: Vec::new(),
+            description: None,
         }
     }
 
@@ -137,7 +263,7 @@ impl RepoNode {
 }
 
 /// Repository dependency tree for inheritance tracking
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RepoTree {
     /// Root repository (the one being pulled)
     pub root: RepoNode,
@@ -246,6 +372,16 @@ impl ClonedRepo {
     pub fn node_key(&self) -> String {
         compute_repo_key(&self.url, &self.ref_, &self.operations)
     }
+
+    /// Whether processing this repo needs nothing but its own clone: it has
+    /// no `repo:` operations and no tree children to integrate.
+    pub(crate) fn is_independent(&self) -> bool {
+        self.children_keys.is_empty()
+            && !self
+                .operations
+                .iter()
+                .any(|op| matches!(op, Operation::Repo { .. }))
+    }
 }
 
 /// Intermediate filesystem wrapper with metadata
@@ -500,6 +636,13 @@ mod phase_tests {
                 original_url: Some("./foo".to_string()),
                 operations: vec![],
                 children: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: Default::default(),
+                requires: None,
+                locked: Vec::new(),
+                partial: Vec::new(),
+                description: None,
             };
             assert!(node.is_local());
         }
@@ -24,17 +24,32 @@
 //! [`execute_pull`] partitions the config into source and `self:` operations,
 //! runs the sequential pipeline for sources, then runs the sequential
 //! pipeline for each `self:` block.
-
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
-
-use log::{debug, trace, warn};
-
+//!
+//! Each block runs as a list of [`Stage`]s over a shared [`BlockState`]:
+//! [`DISCOVER`], [`COMPOSE`], [`LOCAL_MERGE`], [`FINALIZE`] and [`WRITE`].
+//! Library users can insert their own stages, such as a policy check on the
+//! composite before it meets local files, and run the list with
+//! [`execute_pull_with_context`]; a [`RunContext`] carries it along with the
+//! other settings of the pull.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::{debug, info, trace};
+
+use super::checkpoint::RunDir;
+use super::context::RunContext;
+use super::manifest::{self, ApplyManifest};
+use super::plan::Plan;
 use super::{phase1, phase2, phase4, phase5, phase6, ClonedRepo, IntermediateFS};
 use crate::cache::RepoCache;
-use crate::config::{IncludeOp, Operation, RepoOp, Schema, SelfOp};
-use crate::error::Result;
+use crate::config::{AssertOp, IncludeOp, Operation, RepoOp, Schema, SelfOp};
+use crate::diagnostics::{self, Diagnostics};
+use crate::error::{Error, ErrorPhase, Result};
 use crate::filesystem::MemoryFS;
+use crate::messages::Message;
+use crate::reporting::timings;
 use crate::repository::RepositoryManager;
 
 /// Apply an include operation with the auto-merge × include rule.
@@ -53,9 +68,9 @@ fn apply_include_with_auto_merge(
     include: &IncludeOp,
     source_fs: &MemoryFS,
     fs: &mut MemoryFS,
-    auto_merge_targets: &HashMap<String, Operation>,
+    auto_merge_targets: &BTreeMap<String, Operation>,
 ) -> Result<()> {
-    let mut snapshots: HashMap<String, crate::filesystem::File> = HashMap::new();
+    let mut snapshots: BTreeMap<String, crate::filesystem::File> = BTreeMap::new();
     for path in auto_merge_targets.keys() {
         if let Some(file) = fs.get_file(path) {
             snapshots.insert(path.clone(), file.clone());
@@ -94,7 +109,7 @@ fn apply_include_with_auto_merge(
 /// For local repos: the config carries the original relative/absolute spelling
 /// (e.g. `../upstream`) while the cloned repo's `url` is the canonical abs
 /// path set by discovery. We match via `original_url` instead.
-fn match_cloned_repo_to_op(cloned: &ClonedRepo, repo: &RepoOp) -> bool {
+pub(crate) fn match_cloned_repo_to_op(cloned: &ClonedRepo, repo: &RepoOp) -> bool {
     if repo.is_local() {
         cloned.original_url.as_deref() == Some(repo.url.as_str())
     } else {
@@ -168,20 +183,98 @@ fn resolve_repo_inline_inner(
     cache: &RepoCache,
     visited: &mut HashSet<String>,
 ) -> Result<IntermediateFS> {
-    let has_repo_ops = cloned
-        .operations
-        .iter()
-        .any(|op| matches!(op, Operation::Repo { .. }));
-
     // Fast path: no nested repo: ops and no tree children → delegate to
     // process_cloned_repo which uses the in-process cache for deduplication.
-    if !has_repo_ops && cloned.children_keys.is_empty() {
-        return phase2::process_cloned_repo(cloned, cache);
+    let mut result = if cloned.is_independent() {
+        phase2::process_cloned_repo(cloned, cache)?
+    } else {
+        // Slow path: operations contain repo: references or this node has tree
+        // children (repos discovered from the upstream .common-repo.yaml).
+        // Process sequentially so repo: ops fire at their declaration position.
+        timings::within(format!("{}@{}", cloned.url, cloned.ref_), || {
+            resolve_dependent_repo(cloned, cloned_repos, cache, visited)
+        })?
+    };
+    warn_if_empty(cloned, &result.fs);
+    stamp_origins(cloned, &mut result.fs);
+    Ok(result)
+}
+
+/// Record `cloned` as the repository of the files it included itself, and
+/// point the links of those tagged with `rewrite-links: upstream` at it.
+/// Files from nested repositories were stamped when those were resolved.
+fn stamp_origins(cloned: &ClonedRepo, fs: &mut MemoryFS) {
+    for path in fs.list_files() {
+        let Some(file) = fs.get_file_mut(&path) else {
+            continue;
+        };
+        if let Some(origin) = &mut file.origin {
+            if origin.repo.is_none() {
+                let origin = Arc::make_mut(origin);
+                origin.repo = Some(cloned.url.clone());
+                origin.ref_ = cloned.ref_.clone();
+            }
+        }
+        if file.upstream_links {
+            file.upstream_links = false;
+            crate::operators::links::point_at_upstream(file, &cloned.url, &cloned.ref_);
+        }
+    }
+}
+
+/// Warn when a repo that has files and `include` operations contributes
+/// none of them, which is usually a glob that matches nothing or an
+/// `exclude` that matches too much. A repo without `include` operations,
+/// for example one that only defines template variables, is expected to
+/// contribute nothing.
+fn warn_if_empty(cloned: &ClonedRepo, fs: &MemoryFS) {
+    if !fs.is_empty() || cloned.fs.is_empty() {
+        return;
+    }
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+    for operation in &cloned.operations {
+        match operation {
+            Operation::Include { include: op, .. } => include.extend(op.patterns.iter()),
+            Operation::Exclude { exclude: op } => exclude.extend(op.patterns.iter()),
+            _ => {}
+        }
+    }
+    if include.is_empty() {
+        return;
     }
+    let quoted = |patterns: &[&String]| {
+        patterns
+            .iter()
+            .map(|pattern| format!("'{}'", pattern))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let url = cloned.original_url.as_deref().unwrap_or(&cloned.url);
+    let repo = if cloned.ref_.is_empty() {
+        url.to_string()
+    } else {
+        format!("{}@{}", url, cloned.ref_)
+    };
+    diagnostics::warn(
+        ErrorPhase::Processing,
+        Message::EmptyUpstream {
+            repo: &repo,
+            include: &quoted(&include),
+            exclude: &quoted(&exclude),
+        }
+        .to_string(),
+    );
+}
 
-    // Slow path: operations contain repo: references or this node has tree
-    // children (repos discovered from the upstream .common-repo.yaml).
-    // Process sequentially so repo: ops fire at their declaration position.
+/// The slow path of [`resolve_repo_inline_inner`], for a repo that depends
+/// on others.
+fn resolve_dependent_repo(
+    cloned: &ClonedRepo,
+    cloned_repos: &HashMap<String, ClonedRepo>,
+    cache: &RepoCache,
+    visited: &mut HashSet<String>,
+) -> Result<IntermediateFS> {
     let mut template_vars = phase2::collect_template_vars(&cloned.operations)?;
     let mut merge_operations = phase2::collect_merge_operations(&cloned.operations);
 
@@ -193,7 +286,7 @@ fn resolve_repo_inline_inner(
     // Track auto-merge targets across nested repos so cross-upstream
     // auto-merges accumulate correctly (e.g., when a chained upstream
     // also declares auto-merge for the same file).
-    let mut accumulated_auto_merge_targets = HashMap::new();
+    let mut accumulated_auto_merge_targets = BTreeMap::new();
 
     for operation in &cloned.operations {
         match operation {
@@ -220,43 +313,44 @@ fn resolve_repo_inline_inner(
                     .collect();
 
                 if candidates.len() > 1 {
-                    warn!(
-                        "Multiple cloned repos match nested {}@{} ({} candidates); using first",
-                        repo.url,
-                        repo.r#ref.as_deref().unwrap_or(""),
-                        candidates.len()
+                    diagnostics::warn(
+                        ErrorPhase::Processing,
+                        Message::AmbiguousRepo {
+                            url: &repo.url,
+                            ref_: repo.r#ref.as_deref().unwrap_or(""),
+                            candidates: candidates.len(),
+                        }
+                        .to_string(),
                     );
                 }
 
                 if let Some(nested_cloned) = candidates.into_iter().next() {
-                    let nested_result =
+                    let mut nested_result =
                         resolve_repo_inline_inner(nested_cloned, cloned_repos, cache, visited)?;
 
                     // Use auto-merge-aware integration so that chained
                     // repos with auto-merge declarations accumulate
                     // content instead of overwriting via last-write-wins.
-                    let nested_intermediate = IntermediateFS::new_with_vars_and_merges(
-                        nested_result.fs.clone(),
-                        nested_result.upstream_url.clone(),
-                        nested_result.upstream_ref.clone(),
-                        HashMap::new(), // template_vars handled below
-                        nested_result.merge_operations.clone(),
-                    );
-                    let residual = phase4::integrate_sub_composite_with_targets(
+                    let residual = phase4::integrate_fs_with_targets(
                         &mut fs,
-                        &nested_intermediate,
+                        std::mem::take(&mut nested_result.fs),
+                        &nested_result.merge_operations,
                         &mut accumulated_auto_merge_targets,
                     )?;
+                    cache.enforce_max_memory(&fs)?;
 
                     merge_operations.extend(nested_result.merge_operations);
                     merge_operations.extend(residual);
 
                     template_vars.extend(nested_result.template_vars);
                 } else {
-                    warn!(
-                        "Nested repo: reference not found in cloned repos, skipping: {}@{}",
-                        repo.url,
-                        repo.r#ref.as_deref().unwrap_or("")
+                    diagnostics::warn(
+                        ErrorPhase::Processing,
+                        Message::RepoNotCloned {
+                            url: &repo.url,
+                            ref_: repo.r#ref.as_deref().unwrap_or(""),
+                        }
+                        .to_string(),
                     );
                 }
             }
@@ -268,12 +362,14 @@ fn resolve_repo_inline_inner(
                 // paths that already exist in the composite trigger the
                 // auto-merge × include rule (format-aware merge instead of
                 // overwrite).
-                apply_include_with_auto_merge(
-                    include,
-                    &cloned.fs,
-                    &mut fs,
-                    &accumulated_auto_merge_targets,
-                )?;
+                timings::time(operation, &mut fs, |fs| {
+                    apply_include_with_auto_merge(
+                        include,
+                        &cloned.fs,
+                        fs,
+                        &accumulated_auto_merge_targets,
+                    )
+                })?;
             }
             other => {
                 phase2::apply_operation(&mut fs, &cloned.fs, other)?;
@@ -297,30 +393,25 @@ fn resolve_repo_inline_inner(
                 continue;
             }
 
-            let child_result =
+            let mut child_result =
                 resolve_repo_inline_inner(child_cloned, cloned_repos, cache, visited)?;
 
             // Same auto-merge-aware integration for tree children
-            let child_intermediate = IntermediateFS::new_with_vars_and_merges(
-                child_result.fs.clone(),
-                child_result.upstream_url.clone(),
-                child_result.upstream_ref.clone(),
-                HashMap::new(),
-                child_result.merge_operations.clone(),
-            );
-            let residual = phase4::integrate_sub_composite_with_targets(
+            let residual = phase4::integrate_fs_with_targets(
                 &mut fs,
-                &child_intermediate,
+                std::mem::take(&mut child_result.fs),
+                &child_result.merge_operations,
                 &mut accumulated_auto_merge_targets,
             )?;
+            cache.enforce_max_memory(&fs)?;
 
             merge_operations.extend(child_result.merge_operations);
             merge_operations.extend(residual);
             template_vars.extend(child_result.template_vars);
         } else {
-            warn!(
-                "Tree child not found in cloned repos, skipping: {}",
-                child_key
+            diagnostics::warn(
+                ErrorPhase::Processing,
+                Message::TreeChildNotCloned { key: child_key }.to_string(),
             );
         }
     }
@@ -345,7 +436,8 @@ fn resolve_repo_inline_inner(
 ///
 /// - **`SourceBlock`** runs a Phase 5 merge after the sequential pass to
 ///   combine the composite with local files (composite wins for shared
-///   paths).
+///   paths). Its source FS leaves out files the last apply propagated (see
+///   [`super::manifest`]), and writing its output saves a new manifest.
 /// - **`SelfBlock`** skips Phase 5 because files enter the composite
 ///   only through explicit `include` operators or upstream `repo:`
 ///   integrations.
@@ -355,321 +447,625 @@ enum PipelineMode {
     SourceBlock,
 }
 
-/// Execute a sequential pipeline where operations fire in declaration order.
+/// Name of the stage that discovers and clones repositories and loads the
+/// local source FS.
+pub const DISCOVER: &str = "discover";
+/// Name of the stage that walks the operations to build the composite.
+pub const COMPOSE: &str = "compose";
+/// Name of the stage that combines the composite with local files.
+pub const LOCAL_MERGE: &str = "local-merge";
+/// Name of the stage that applies consumer headers, formatting and patches.
+pub const FINALIZE: &str = "finalize";
+/// Name of the stage that writes the output to disk.
+pub const WRITE: &str = "write";
+
+/// One step of the pipeline a source or `self:` block runs.
 ///
-/// This function walks the config's operations sequentially. When a `repo:`
-/// operation is encountered, it resolves inline at that position using
-/// [`resolve_repo_inline`] and [`phase4::integrate_sub_composite`].
+/// Each block runs its stages in order over a shared [`BlockState`].
+/// [`default_stages`] is the pipeline [`execute_pull`] runs; library users
+/// can insert their own stages into that list, such as a policy check
+/// between [`COMPOSE`] and [`LOCAL_MERGE`], and run it with
+/// [`RunContext::stages`]:
 ///
-/// Both `self:` and source blocks use this pipeline. Both start with an
-/// empty composite and load a read-only source FS from the local working
-/// directory; `include` operators pull from this source FS additively.
-/// See [`PipelineMode`] for full details.
-fn execute_sequential_pipeline(
-    config: &Schema,
-    repo_manager: &RepositoryManager,
-    cache: &RepoCache,
-    working_dir: &Path,
-    output_path: Option<&Path>,
-    mode: PipelineMode,
-) -> Result<MemoryFS> {
-    // Phase 1: Discover and clone repos eagerly
-    let repo_tree = phase1::execute(config, repo_manager, cache)?;
-
-    // Build cloned_repos map for on-demand resolution
-    let cloned_repos = phase2::clone_tree_repos(&repo_tree, repo_manager)?;
-
-    // Source FS: the read-only input from which include operators pull
-    // files. Per the operators spec, this is the local working directory
-    // on disk for both self and source blocks. include operators pull
-    // matching files from this read-only source into the composite without
-    // touching files already there.
-    let mut source_fs = Some(phase5::load_local_fs(working_dir)?);
-
-    // Both modes start with an empty composite. Self blocks populate it
-    // via include (pulling from source_fs). Source blocks populate it via
-    // repo: integrations and additive include operators.
-    let mut fs = MemoryFS::new();
+/// ```
+/// use common_repo::error::Result;
+/// use common_repo::phases::context::RunContext;
+/// use common_repo::phases::orchestrator::{self, BlockState, Stage};
+///
+/// struct NoSecrets;
+///
+/// impl Stage for NoSecrets {
+///     fn name(&self) -> &str {
+///         "no-secrets"
+///     }
+///
+///     fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+///         state.fs.remove_file(".env").ok();
+///         Ok(())
+///     }
+/// }
+///
+/// let mut stages = orchestrator::default_stages();
+/// let at = stages
+///     .iter()
+///     .position(|stage| stage.name() == orchestrator::LOCAL_MERGE)
+///     .unwrap();
+/// stages.insert(at, Box::new(NoSecrets));
+/// # let (repo_manager, cache) = (
+/// #     common_repo::repository::RepositoryManager::new(std::env::temp_dir()),
+/// #     common_repo::cache::RepoCache::new(),
+/// # );
+/// let ctx = RunContext::new(&repo_manager, &cache, std::path::Path::new(".")).stages(stages);
+/// ```
+pub trait Stage {
+    /// A short name for logs and for finding the stage in a list.
+    fn name(&self) -> &str;
+
+    /// Run the stage. An error stops the block and fails the pull.
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()>;
+}
 
-    let mut all_template_vars = HashMap::new();
-    let mut residual_deferred_ops: Vec<Operation> = Vec::new();
+/// The state the stages of one block share.
+pub struct BlockState<'a> {
+    /// The block's operations.
+    pub config: &'a Schema,
+    /// The settings of the pull.
+    pub ctx: &'a RunContext<'a>,
+    /// The filesystem being built: the composite until [`LOCAL_MERGE`]
+    /// combines it with local files, then the output.
+    pub fs: MemoryFS,
+    /// The read-only local files `include` pulls from. Loaded by
+    /// [`DISCOVER`] and taken by [`LOCAL_MERGE`].
+    pub source_fs: Option<MemoryFS>,
+    mode: PipelineMode,
+    // Index of the block, for checkpoints
+    block: usize,
+    cloned_repos: HashMap<String, ClonedRepo>,
+    template_vars: HashMap<String, String>,
+    residual_deferred_ops: Vec<Operation>,
     // Accumulate auto-merge targets across all repo integrations so that a
     // later repo can trigger format-aware merge for a file declared by an
     // earlier repo (or vice versa, when the later repo has defer: true).
-    let mut accumulated_auto_merge_targets = HashMap::new();
+    auto_merge_targets: BTreeMap<String, Operation>,
     // In source mode, snapshot upstream file content for auto-merge targets
     // at the time each repo: fires. These snapshots survive subsequent
     // consumer operations (e.g., exclude) and are used during Phase 5 to
     // merge upstream content into local files that were not in the composite
     // during the sequential pass.
-    let mut auto_merge_snapshots: HashMap<String, crate::filesystem::File> = HashMap::new();
+    auto_merge_snapshots: BTreeMap<String, crate::filesystem::File>,
+    // Composite paths, which `format` normalizes
+    composed: Vec<PathBuf>,
+    // Composite paths with no local input, listed in the manifest
+    propagated: Vec<PathBuf>,
+    // In source mode, the URLs of the repositories that lock files, with
+    // the patterns they lock
+    locked: Vec<(String, Vec<String>)>,
+    // In source mode, the `assert` operations of the upstreams, with their
+    // URLs
+    assertions: Vec<(String, AssertOp)>,
+    // Whether `fs` is a final filesystem restored from the checkpoint
+    resumed: bool,
+    // Whether `fs` is already placed under the subdirectory of the output
+    placed: bool,
+    // In source mode, hashes of the local files phase 5 merged, which a
+    // plan records
+    local_inputs: Option<BTreeMap<PathBuf, String>>,
+}
 
-    debug!(
-        "pipeline start: mode={:?}, working_dir={}, ops={}, source_fs_files={}",
-        mode,
-        working_dir.display(),
-        config.len(),
-        source_fs.as_ref().map(|s| s.len()).unwrap_or(0),
-    );
+impl<'a> BlockState<'a> {
+    /// Whether this is a `self:` block rather than the source block.
+    pub fn is_self_block(&self) -> bool {
+        self.mode == PipelineMode::SelfBlock
+    }
 
-    // Sequential pass: walk operations in declaration order
-    for operation in config {
-        match operation {
-            Operation::Include { include, .. } => {
-                // include is additive — pull matching files from the
-                // read-only source FS into the composite. The source FS
-                // is never modified, so include-after-exclude can re-add
-                // previously excluded files. Tagged paths (auto-merge)
-                // that already exist in the composite are merged with
-                // the incoming content instead of being overwritten.
-                let source = source_fs
-                    .as_ref()
-                    .expect("source_fs is loaded at function entry for both pipeline modes");
-                debug!(
-                    "op include: patterns={:?}, source_fs_files={}, composite_before={}",
-                    include.patterns,
-                    source.len(),
-                    fs.len(),
-                );
-                apply_include_with_auto_merge(
-                    include,
-                    source,
-                    &mut fs,
-                    &accumulated_auto_merge_targets,
-                )?;
-                trace!("op include: composite_after={}", fs.len());
+    /// The checkpoint directory and index of this block, when saving
+    /// checkpoints.
+    fn checkpoint(&self) -> Option<(&RunDir, usize)> {
+        self.ctx.run.map(|run| (run, self.block))
+    }
+
+    /// The subdirectory of the working directory the output of this block
+    /// is placed under, if any. Only the source block is placed.
+    fn placed_under(&self) -> Option<&'a Path> {
+        self.ctx
+            .into
+            .filter(|_| self.mode == PipelineMode::SourceBlock)
+    }
+
+    /// The directory this block reads local files from: the working
+    /// directory, or the subdirectory its output is placed under.
+    fn local_dir(&self) -> PathBuf {
+        match self.placed_under() {
+            Some(into) => self.ctx.working_dir.join(into),
+            None => self.ctx.working_dir.to_path_buf(),
+        }
+    }
+
+    /// Whether the subdirectory the output is placed under does not exist
+    /// yet, as on the first apply with `--into`. The block then has no local
+    /// files and no overrides.
+    fn local_dir_missing(&self) -> bool {
+        self.placed_under().is_some() && !self.local_dir().is_dir()
+    }
+
+    /// The local input files of the block. In source mode, files the last
+    /// apply propagated are earlier output, not input, and are left out.
+    fn load_local_inputs(&self) -> Result<MemoryFS> {
+        if self.local_dir_missing() {
+            return Ok(MemoryFS::new());
+        }
+        let ctx = self.ctx;
+        // The manifest lists the output paths, which start with the
+        // subdirectory the output is placed under.
+        let manifest = match (self.mode, ctx.repo_manager.cache_root()) {
+            (PipelineMode::SourceBlock, Some(cache_root)) => {
+                let manifest = ApplyManifest::load(cache_root, ctx.working_dir, ctx.pipeline);
+                Some(match self.placed_under() {
+                    Some(into) => manifest.within(into),
+                    None => manifest,
+                })
             }
-            Operation::Exclude { exclude } => {
-                debug!(
-                    "op exclude: patterns={:?}, composite_before={}",
-                    exclude.patterns,
-                    fs.len(),
+            _ => None,
+        };
+        phase5::load_local_inputs(&self.local_dir(), manifest.as_ref())
+    }
+}
+
+/// The stages [`execute_pull`] runs for each block, in order:
+/// [`DISCOVER`], [`COMPOSE`], [`LOCAL_MERGE`], [`FINALIZE`] and [`WRITE`].
+pub fn default_stages() -> Vec<Box<dyn Stage>> {
+    vec![
+        Box::new(DiscoverStage),
+        Box::new(ComposeStage),
+        Box::new(LocalMergeStage),
+        Box::new(FinalizeStage),
+        Box::new(WriteStage),
+    ]
+}
+
+/// Phase 1: discover and clone repositories, and load the source FS.
+pub struct DiscoverStage;
+
+impl Stage for DiscoverStage {
+    fn name(&self) -> &str {
+        DISCOVER
+    }
+
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+        let (config, ctx) = (state.config, state.ctx);
+        let repo_tree = match state.checkpoint().and_then(|(run, block)| run.tree(block)) {
+            Some(repo_tree) => {
+                info!(
+                    "{}",
+                    Message::ResumedFromCheckpoint {
+                        what: "repository tree"
+                    }
                 );
-                crate::operators::exclude::apply(exclude, &mut fs)?;
-                trace!("op exclude: composite_after={}", fs.len());
+                repo_tree
             }
-            Operation::Rename { rename } => {
-                debug!(
-                    "op rename: mappings={}, composite_before={}",
-                    rename.mappings.len(),
-                    fs.len(),
-                );
-                crate::operators::rename::apply(rename, &mut fs)?;
-                trace!("op rename: composite_after={}", fs.len());
+            None => {
+                let repo_tree = phase1::execute(config, ctx)?;
+                if let Some((run, block)) = state.checkpoint() {
+                    run.save_tree(block, &repo_tree);
+                }
+                repo_tree
             }
-            Operation::Repo { repo } => {
-                debug!(
-                    "op repo: url={}, ref={}, composite_before={}",
-                    repo.url,
-                    repo.r#ref.as_deref().unwrap_or(""),
-                    fs.len(),
-                );
-                // Phase 1 enriches each repo node with upstream filtering +
-                // deferred ops, so the cloned_repos key differs from the raw
-                // (url, ref, with) on the Operation::Repo. Look up via
-                // match_cloned_repo_to_op, which handles both git repos
-                // (by url+ref) and local repos (by original_url). If multiple
-                // candidates match, take the first and warn.
-                let candidates: Vec<_> = cloned_repos
-                    .values()
-                    .filter(|c| match_cloned_repo_to_op(c, repo))
-                    .collect();
+        };
 
-                if candidates.len() > 1 {
-                    warn!(
-                        "Multiple cloned repos match {}@{} ({} candidates); using first match",
-                        repo.url,
-                        repo.r#ref.as_deref().unwrap_or(""),
-                        candidates.len()
-                    );
-                }
+        // Fail fast when the consumer lacks what an upstream requires, or
+        // an operation would be skipped that should not be
+        phase2::check_requirements(&repo_tree, config, ctx)?;
+        phase2::check_unknown_operations(&repo_tree, config, ctx.strict)?;
+        if state.mode == PipelineMode::SourceBlock {
+            state.locked = phase2::locked_patterns(&repo_tree);
+            state.assertions = phase2::upstream_assertions(&repo_tree);
+        }
 
-                let cloned = candidates.into_iter().next();
+        // Build cloned_repos map for on-demand resolution
+        state.cloned_repos = phase2::clone_tree_repos(&repo_tree, ctx.repo_manager)?;
+        phase2::warm_cache(&state.cloned_repos, ctx.cache);
+
+        // Source FS: the read-only input from which include operators pull
+        // files. Per the operators spec, this is the local working directory
+        // on disk for both self and source blocks. include operators pull
+        // matching files from this read-only source into the composite without
+        // touching files already there.
+        state.source_fs = Some(state.load_local_inputs()?);
+        Ok(())
+    }
+}
+
+/// The sequential pass: operations fire in declaration order, each `repo:`
+/// resolving inline, followed by residual deferred merges and templates.
+pub struct ComposeStage;
+
+impl Stage for ComposeStage {
+    fn name(&self) -> &str {
+        COMPOSE
+    }
+
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+        let (config, ctx, mode) = (state.config, state.ctx, state.mode);
+        let (local_dir, cache) = (state.local_dir(), ctx.cache);
+        let working_dir = local_dir.as_path();
+        let BlockState {
+            fs,
+            source_fs,
+            cloned_repos,
+            template_vars: all_template_vars,
+            residual_deferred_ops,
+            auto_merge_targets: accumulated_auto_merge_targets,
+            auto_merge_snapshots,
+            ..
+        } = state;
+
+        debug!(
+            "pipeline start: mode={:?}, working_dir={}, ops={}, source_fs_files={}",
+            mode,
+            working_dir.display(),
+            config.len(),
+            source_fs.as_ref().map(|s| s.len()).unwrap_or(0),
+        );
 
-                if let Some(cloned) = cloned {
-                    let sub_composite = resolve_repo_inline(cloned, &cloned_repos, cache)?;
+        // Sequential pass: walk operations in declaration order
+        for operation in config {
+            match operation {
+                Operation::Include { include, .. } => {
+                    // include is additive — pull matching files from the
+                    // read-only source FS into the composite. The source FS
+                    // is never modified, so include-after-exclude can re-add
+                    // previously excluded files. Tagged paths (auto-merge)
+                    // that already exist in the composite are merged with
+                    // the incoming content instead of being overwritten.
+                    let source = source_fs
+                        .as_ref()
+                        .expect("source_fs is loaded by the discover stage");
+                    debug!(
+                        "op include: patterns={:?}, source_fs_files={}, composite_before={}",
+                        include.patterns,
+                        source.len(),
+                        fs.len(),
+                    );
+                    timings::time(operation, fs, |fs| {
+                        apply_include_with_auto_merge(
+                            include,
+                            source,
+                            fs,
+                            accumulated_auto_merge_targets,
+                        )
+                    })?;
+                    trace!("op include: composite_after={}", fs.len());
+                }
+                Operation::Exclude { exclude } => {
                     debug!(
-                        "op repo: sub_composite resolved for {} — files={}, merge_ops={}, template_vars={}",
+                        "op exclude: patterns={:?}, composite_before={}",
+                        exclude.patterns,
+                        fs.len(),
+                    );
+                    timings::time(operation, fs, |fs| {
+                        crate::operators::exclude::apply(exclude, fs)
+                    })?;
+                    trace!("op exclude: composite_after={}", fs.len());
+                }
+                Operation::Rename { rename } => {
+                    debug!(
+                        "op rename: mappings={}, composite_before={}",
+                        rename.mappings.len(),
+                        fs.len(),
+                    );
+                    timings::time(operation, fs, |fs| {
+                        crate::operators::rename::apply(rename, fs)
+                    })?;
+                    trace!("op rename: composite_after={}", fs.len());
+                }
+                Operation::Extract { extract } => {
+                    debug!(
+                        "op extract: source={}, dest={}, composite_before={}",
+                        extract.source,
+                        extract.dest,
+                        fs.len(),
+                    );
+                    timings::time(operation, fs, |fs| {
+                        crate::merge::apply_extract_operation(fs, extract)
+                    })?;
+                }
+                Operation::Repo { repo } => {
+                    debug!(
+                        "op repo: url={}, ref={}, composite_before={}",
                         repo.url,
-                        sub_composite.fs.len(),
-                        sub_composite.merge_operations.len(),
-                        sub_composite.template_vars.len(),
+                        repo.r#ref.as_deref().unwrap_or(""),
+                        fs.len(),
                     );
+                    // Phase 1 enriches each repo node with upstream filtering +
+                    // deferred ops, so the cloned_repos key differs from the raw
+                    // (url, ref, with) on the Operation::Repo. Look up via
+                    // match_cloned_repo_to_op, which handles both git repos
+                    // (by url+ref) and local repos (by original_url). If multiple
+                    // candidates match, take the first and warn.
+                    let candidates: Vec<_> = cloned_repos
+                        .values()
+                        .filter(|c| match_cloned_repo_to_op(c, repo))
+                        .collect();
+
+                    if candidates.len() > 1 {
+                        diagnostics::warn(
+                            ErrorPhase::Processing,
+                            Message::AmbiguousRepo {
+                                url: &repo.url,
+                                ref_: repo.r#ref.as_deref().unwrap_or(""),
+                                candidates: candidates.len(),
+                            }
+                            .to_string(),
+                        );
+                    }
+
+                    let cloned = candidates.into_iter().next();
+
+                    if let Some(cloned) = cloned {
+                        let mut sub_composite = resolve_repo_inline(cloned, cloned_repos, cache)?;
+                        debug!(
+                                "op repo: sub_composite resolved for {} — files={}, merge_ops={}, template_vars={}",
+                                repo.url,
+                                sub_composite.fs.len(),
+                                sub_composite.merge_operations.len(),
+                                sub_composite.template_vars.len(),
+                            );
 
-                    // In source mode, snapshot auto-merge source files BEFORE
-                    // integration. These snapshots survive subsequent consumer
-                    // ops (exclude, rename) and are used during Phase 5 to
-                    // merge upstream content into local files.
-                    if mode == PipelineMode::SourceBlock {
-                        for op in &sub_composite.merge_operations {
-                            if let Some(path) = phase4::get_auto_merge_path(op) {
-                                if let Some(file) = sub_composite.fs.get_file(path) {
-                                    trace!(
-                                        "op repo: snapshot auto-merge target {} ({} bytes) from {}",
-                                        path,
-                                        file.content.len(),
-                                        repo.url,
-                                    );
-                                    auto_merge_snapshots.insert(path.to_string(), file.clone());
+                        // In source mode, snapshot auto-merge source files BEFORE
+                        // integration. These snapshots survive subsequent consumer
+                        // ops (exclude, rename) and are used during Phase 5 to
+                        // merge upstream content into local files.
+                        if mode == PipelineMode::SourceBlock {
+                            for op in &sub_composite.merge_operations {
+                                if let Some(path) = phase4::get_auto_merge_path(op) {
+                                    if let Some(file) = sub_composite.fs.get_file(path) {
+                                        trace!(
+                                                "op repo: snapshot auto-merge target {} ({} bytes) from {}",
+                                                path,
+                                                file.content.len(),
+                                                repo.url,
+                                            );
+                                        auto_merge_snapshots.insert(path.to_string(), file.clone());
+                                    }
                                 }
                             }
                         }
-                    }
 
-                    let residual = phase4::integrate_sub_composite_with_targets(
-                        &mut fs,
-                        &sub_composite,
-                        &mut accumulated_auto_merge_targets,
-                    )?;
+                        let residual = phase4::integrate_fs_with_targets(
+                            fs,
+                            std::mem::take(&mut sub_composite.fs),
+                            &sub_composite.merge_operations,
+                            accumulated_auto_merge_targets,
+                        )?;
+                        // Processed upstreams kept for reuse go to disk once the
+                        // composite and they no longer fit in --max-memory.
+                        cache.enforce_max_memory(fs)?;
+                        debug!(
+                            "op repo: integrated {}; composite_after={}, deferred_residual={}",
+                            repo.url,
+                            fs.len(),
+                            residual.len(),
+                        );
+                        residual_deferred_ops.extend(residual);
+
+                        // Upstream template vars fill in defaults but do not
+                        // overwrite consumer-level vars already set by a preceding
+                        // template-vars operation. This matches the old batch
+                        // pipeline where Phase 4 processed repos in post-order
+                        // (children before parents, local root last) and the local
+                        // root's consumer vars were the final write.
+                        for (key, value) in sub_composite.template_vars {
+                            all_template_vars.entry(key).or_insert(value);
+                        }
+                    } else {
+                        diagnostics::warn(
+                            ErrorPhase::Processing,
+                            Message::RepoNotCloned {
+                                url: &repo.url,
+                                ref_: repo.r#ref.as_deref().unwrap_or(""),
+                            }
+                            .to_string(),
+                        );
+                    }
+                }
+                Operation::Template { template } => {
+                    timings::time(operation, fs, |fs| {
+                        crate::operators::template::mark(template, fs)
+                    })?;
+                }
+                Operation::TemplateVars { template_vars } => {
+                    crate::operators::template_vars::collect(template_vars, all_template_vars)?;
+                }
+                Operation::Yaml { .. }
+                | Operation::Json { .. }
+                | Operation::Toml { .. }
+                | Operation::Ini { .. }
+                | Operation::Markdown { .. }
+                | Operation::Xml { .. } => {
+                    let kind = match operation {
+                        Operation::Yaml { .. } => "yaml",
+                        Operation::Json { .. } => "json",
+                        Operation::Toml { .. } => "toml",
+                        Operation::Ini { .. } => "ini",
+                        Operation::Markdown { .. } => "markdown",
+                        Operation::Xml { .. } => "xml",
+                        _ => "unknown",
+                    };
                     debug!(
-                        "op repo: integrated {}; composite_after={}, deferred_residual={}",
-                        repo.url,
+                        "op merge: kind={}, source={:?}, dest={:?}, composite_before={}",
+                        kind,
+                        operation.merge_effective_source(),
+                        operation.merge_effective_dest(),
                         fs.len(),
-                        residual.len(),
                     );
-                    residual_deferred_ops.extend(residual);
-
-                    // Upstream template vars fill in defaults but do not
-                    // overwrite consumer-level vars already set by a preceding
-                    // template-vars operation. This matches the old batch
-                    // pipeline where Phase 4 processed repos in post-order
-                    // (children before parents, local root last) and the local
-                    // root's consumer vars were the final write.
-                    for (key, value) in sub_composite.template_vars {
-                        all_template_vars.entry(key).or_insert(value);
+                    // Merge source/dest may reference local files not yet in the
+                    // composite. For source blocks, local files enter in Phase 5.
+                    // For self blocks, the composite starts empty and only contains
+                    // files pulled by include or integrated by repo:. In both cases,
+                    // load from disk when the file is missing.
+                    for path in [
+                        operation.merge_effective_source(),
+                        operation.merge_effective_dest(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    {
+                        if !fs.exists(path) {
+                            let disk_path = working_dir.join(path);
+                            if disk_path.exists() {
+                                let file = crate::filesystem::File::from_path(&disk_path)?;
+                                trace!(
+                                    "op merge: pre-load from disk {} ({} bytes)",
+                                    path,
+                                    file.content.len(),
+                                );
+                                fs.add_file(path, file)?;
+                            }
+                        }
                     }
-                } else {
-                    warn!(
-                        "Repo reference not found in cloned repos, skipping: {}@{}",
-                        repo.url,
-                        repo.r#ref.as_deref().unwrap_or("")
+                    phase4::execute_merge_operation(fs, operation)?;
+                }
+                Operation::Tools { tools } => {
+                    crate::operators::tools::apply(tools)?;
+                }
+                Operation::File { file } => {
+                    debug!("op file: url={}, composite_before={}", file.url, fs.len());
+                    timings::time(operation, fs, |fs| crate::operators::file::apply(file, fs))?;
+                }
+                Operation::Vendor { vendor } => {
+                    debug!(
+                        "op vendor: url={}, ref={}, composite_before={}",
+                        vendor.url,
+                        vendor.r#ref,
+                        fs.len()
                     );
+                    timings::time(operation, fs, |fs| {
+                        crate::operators::vendor::apply(vendor, ctx.repo_manager, fs)
+                    })?;
+                }
+                Operation::Self_ { .. } => {}
+                // Applied after Phase 5, once the output is fully composed
+                Operation::Header { .. }
+                | Operation::YamlFormat { .. }
+                | Operation::Format { .. }
+                | Operation::Patch { .. }
+                | Operation::Assert { .. } => {}
+                Operation::Unknown(unknown) => {
+                    debug!("op unknown: name={}", unknown.name);
+                    unknown.report();
                 }
             }
-            Operation::Template { template } => {
-                crate::operators::template::mark(template, &mut fs)?;
-            }
-            Operation::TemplateVars { template_vars } => {
-                crate::operators::template_vars::collect(template_vars, &mut all_template_vars)?;
-            }
-            Operation::Yaml { .. }
-            | Operation::Json { .. }
-            | Operation::Toml { .. }
-            | Operation::Ini { .. }
-            | Operation::Markdown { .. }
-            | Operation::Xml { .. } => {
-                let kind = match operation {
-                    Operation::Yaml { .. } => "yaml",
-                    Operation::Json { .. } => "json",
-                    Operation::Toml { .. } => "toml",
-                    Operation::Ini { .. } => "ini",
-                    Operation::Markdown { .. } => "markdown",
-                    Operation::Xml { .. } => "xml",
-                    _ => "unknown",
-                };
-                debug!(
-                    "op merge: kind={}, source={:?}, dest={:?}, composite_before={}",
-                    kind,
-                    operation.merge_effective_source(),
-                    operation.merge_effective_dest(),
-                    fs.len(),
-                );
-                // Merge source/dest may reference local files not yet in the
-                // composite. For source blocks, local files enter in Phase 5.
-                // For self blocks, the composite starts empty and only contains
-                // files pulled by include or integrated by repo:. In both cases,
-                // load from disk when the file is missing.
-                for path in [
-                    operation.merge_effective_source(),
-                    operation.merge_effective_dest(),
-                ]
+        }
+
+        // Execute residual deferred merges (dest wasn't in the FS during integration).
+        // These run before the filter pass so merge results carry the default
+        // Overwrite tag and are not inadvertently dropped.
+        for op in residual_deferred_ops.iter() {
+            // Load merge source/dest from disk if not in composite (same
+            // rationale as the sequential-pass merge handling above).
+            for path in [op.merge_effective_source(), op.merge_effective_dest()]
                 .into_iter()
                 .flatten()
-                {
-                    if !fs.exists(path) {
-                        let disk_path = working_dir.join(path);
-                        if disk_path.exists() {
-                            let file = crate::filesystem::File::from_path(&disk_path)?;
-                            trace!(
-                                "op merge: pre-load from disk {} ({} bytes)",
-                                path,
-                                file.content.len(),
-                            );
-                            fs.add_file(path, file)?;
-                        }
+            {
+                if !fs.exists(path) {
+                    let disk_path = working_dir.join(path);
+                    if disk_path.exists() {
+                        fs.add_file(path, crate::filesystem::File::from_path(&disk_path)?)?;
                     }
                 }
-                phase4::execute_merge_operation(&mut fs, operation)?;
-            }
-            Operation::Tools { tools } => {
-                crate::operators::tools::apply(tools)?;
             }
-            Operation::Self_ { .. } => {}
+            phase4::execute_merge_operation(fs, op)?;
         }
+
+        // Process templates with all collected variables.
+        // Runs before the filter pass so template-expanded files carry the
+        // correct if_exists tag when the filter inspects them.
+        // Variables the caller set take precedence over every declaration.
+        all_template_vars.extend(ctx.vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        crate::operators::template::process(fs, all_template_vars)?;
+        Ok(())
     }
+}
 
-    // Execute residual deferred merges (dest wasn't in the FS during integration).
-    // These run before the filter pass so merge results carry the default
-    // Overwrite tag and are not inadvertently dropped.
-    for op in &residual_deferred_ops {
-        // Load merge source/dest from disk if not in composite (same
-        // rationale as the sequential-pass merge handling above).
-        for path in [op.merge_effective_source(), op.merge_effective_dest()]
-            .into_iter()
-            .flatten()
-        {
-            if !fs.exists(path) {
-                let disk_path = working_dir.join(path);
-                if disk_path.exists() {
-                    fs.add_file(path, crate::filesystem::File::from_path(&disk_path)?)?;
-                }
-            }
-        }
-        phase4::execute_merge_operation(&mut fs, op)?;
+/// Phase 5: drop composite files whose `if-exists` keeps the local file, and
+/// for source blocks, combine the composite with local files.
+pub struct LocalMergeStage;
+
+impl Stage for LocalMergeStage {
+    fn name(&self) -> &str {
+        LOCAL_MERGE
     }
 
-    // Process templates with all collected variables.
-    // Runs before the filter pass so template-expanded files carry the
-    // correct if_exists tag when the filter inspects them.
-    crate::operators::template::process(&mut fs, &all_template_vars)?;
-
-    // The source FS loaded at function entry is the local working directory
-    // on disk for both pipeline modes. Reuse it for the filter pass and the
-    // source-mode overlay rather than re-reading from disk.
-    let local_fs_for_filter = source_fs
-        .take()
-        .expect("source_fs is loaded at function entry for both pipeline modes");
-
-    // Filter pass: drop composite entries whose if_exists tag says to
-    // preserve or error-on-conflict when the local file already exists.
-    phase5::filter_if_exists(&mut fs, &local_fs_for_filter, working_dir)?;
-
-    // Source blocks: Phase 5 — combine composite with local files.
-    // Local files that are not in the composite are preserved. Composite
-    // files win for shared paths (CompositePrecedence invariant). Auto-merge
-    // targets use format-aware merging instead of overwriting.
-    if mode == PipelineMode::SourceBlock {
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+        // The source FS loaded by the discover stage is the local working
+        // directory on disk for both pipeline modes. Reuse it for the filter
+        // pass and the source-mode overlay rather than re-reading from disk.
+        let local_fs_for_filter = state
+            .source_fs
+            .take()
+            .expect("source_fs is loaded by the discover stage");
+
+        // Filter pass: drop composite entries whose if_exists tag says to
+        // preserve or error-on-conflict when the local file already exists.
+        let local_dir = state.local_dir();
+        // Overrides only apply to source blocks. Neither they nor local
+        // edits may replace files an upstream locked.
+        let local_dir_missing = state.local_dir_missing();
+        let overrides = match state.mode {
+            PipelineMode::SourceBlock if !local_dir_missing => phase5::load_overrides(&local_dir)?,
+            _ => MemoryFS::new(),
+        };
+        phase5::check_locked(&state.fs, &local_fs_for_filter, &overrides, &state.locked)?;
+        // Only the consumer's own `self:` blocks may write anywhere.
+        if state.mode == PipelineMode::SourceBlock {
+            phase5::enforce_scope(
+                &mut state.fs,
+                &local_fs_for_filter,
+                &mut state.auto_merge_targets,
+                &state.ctx.scope,
+            )?;
+        }
+        if !local_dir_missing {
+            phase5::filter_if_exists(&mut state.fs, &local_fs_for_filter, &local_dir)?;
+        }
+
+        // Source blocks: Phase 5 — combine composite with local files.
+        // Local files that are not in the composite are preserved. Composite
+        // files win for shared paths (CompositePrecedence invariant). Auto-merge
+        // targets use format-aware merging instead of overwriting.
+        // Composite files are the ones `format` normalizes. Those with no local
+        // input at their path are propagated; the manifest of this apply lists
+        // them once their final content is known.
+        state.composed = state.fs.files().map(|(path, _)| path.clone()).collect();
+        if state.mode == PipelineMode::SelfBlock {
+            return Ok(());
+        }
+        state.propagated = state
+            .composed
+            .iter()
+            .filter(|path| !local_fs_for_filter.exists(path))
+            .cloned()
+            .collect();
+        state.local_inputs = Some(Plan::local_hashes(&local_fs_for_filter, &overrides));
+
+        let fs = &state.fs;
         let mut combined = local_fs_for_filter;
         // Overlay composite on top of local files, with auto-merge awareness
-        phase4::merge_composite_with_auto_merge(
-            &mut combined,
-            &fs,
-            &accumulated_auto_merge_targets,
-        )?;
+        phase4::merge_composite_with_auto_merge(&mut combined, fs, &state.auto_merge_targets)?;
 
         // Apply auto-merge snapshots for upstream files that were removed
         // from the composite by consumer operations (e.g., exclude) after
         // the repo: fired. The snapshots preserve the upstream's original
         // file content so it can merge into local files even though the
         // composite no longer contains it.
-        for (path, auto_merge_op) in &accumulated_auto_merge_targets {
+        for (path, auto_merge_op) in &state.auto_merge_targets {
             // Skip paths still in the composite — handled by the overlay.
             if fs.exists(path) {
                 continue;
             }
             // Merge snapshot into local file if both exist
             if combined.exists(path) {
-                if let Some(snapshot_file) = auto_merge_snapshots.get(path) {
+                if let Some(snapshot_file) = state.auto_merge_snapshots.get(path) {
                     let temp_path = format!(".__common_repo_auto_merge_snapshot__{}", path);
                     combined.add_file(&temp_path, snapshot_file.clone())?;
                     let explicit_op =
@@ -680,15 +1076,276 @@ fn execute_sequential_pipeline(
             }
         }
 
-        fs = combined;
+        // Overrides replace everything else at their paths. They are local
+        // files rather than composite output, so `format` leaves them alone
+        // and the manifest does not list them.
+        if !overrides.is_empty() {
+            info!(
+                "{}",
+                Message::OverridesApplied {
+                    count: overrides.len()
+                }
+            );
+            for (path, file) in overrides.files() {
+                debug!("override: {}", path.display());
+                combined.add_file(path, file.clone())?;
+            }
+            state.composed.retain(|path| !overrides.exists(path));
+            state.propagated.retain(|path| !overrides.exists(path));
+        }
+
+        state.fs = combined;
+        Ok(())
+    }
+}
+
+/// Consumer headers, YAML formatting, formatting and patches, then the
+/// assertions of the block and its upstreams.
+pub struct FinalizeStage;
+
+impl Stage for FinalizeStage {
+    fn name(&self) -> &str {
+        FINALIZE
+    }
+
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+        let (config, working_dir) = (state.config, state.ctx.working_dir);
+        let fs = &mut state.fs;
+        // Consumer headers, formatting and patches go last so they apply to the
+        // final content of a file, whichever upstream or merge produced it.
+        // Headers come first so they are formatted too, and patches last, so a
+        // patch is written against the output as it is otherwise written.
+        for operation in config {
+            if let Operation::Header { header } = operation {
+                debug!("op header: patterns={:?}", header.patterns);
+                crate::operators::header::apply(header, fs)?;
+            }
+        }
+        for operation in config {
+            if let Operation::YamlFormat { yaml_format } = operation {
+                debug!("op yaml-format: patterns={:?}", yaml_format.patterns);
+                crate::operators::yaml_format::apply(yaml_format, fs)?;
+            }
+        }
+        for operation in config {
+            if let Operation::Format { format } = operation {
+                debug!(
+                    "op format: style={:?}, files={}",
+                    format,
+                    state.composed.len()
+                );
+                crate::operators::format::apply(*format, fs, &state.composed, working_dir)?;
+            }
+        }
+        for operation in config {
+            if let Operation::Patch { patch } = operation {
+                // The patch is read from the consumer repository, never
+                // from outside it
+                crate::path::validate_relative_path(Path::new(&patch.patch)).map_err(|e| {
+                    Error::Operator {
+                        operator: "patch".to_string(),
+                        message: format!(
+                            "the patch {} is outside the repository: {}",
+                            patch.patch, e
+                        ),
+                    }
+                })?;
+                let diff =
+                    std::fs::read_to_string(working_dir.join(&patch.patch)).map_err(|e| {
+                        Error::Patch {
+                            file: patch.file.clone(),
+                            patch: patch.patch.clone(),
+                            message: format!("the patch could not be read: {}", e),
+                        }
+                    })?;
+                debug!("op patch: file={}, patch={}", patch.file, patch.patch);
+                crate::operators::patch::apply(patch, &diff, fs)?;
+            }
+        }
+        // Assertions hold for the output as it will be written.
+        let mut assertions = state.assertions.clone();
+        for operation in config {
+            if let Operation::Assert { assert } = operation {
+                assertions.push(("the configuration".to_string(), assert.clone()));
+            }
+        }
+        crate::operators::assert::check_all(&assertions, fs)
+    }
+}
+
+/// Phase 6: write the output to disk, when there is an output path.
+pub struct WriteStage;
+
+impl Stage for WriteStage {
+    fn name(&self) -> &str {
+        WRITE
+    }
+
+    fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+        let Some(output) = state.ctx.output_path else {
+            return Ok(());
+        };
+        // Saved only when writing: without a write, a self: block reads
+        // local files that an earlier block has not updated yet. A restored
+        // filesystem was already saved.
+        if let Some((run, block)) = state.checkpoint().filter(|_| !state.resumed) {
+            run.save_composite(block, &state.fs, &state.propagated);
+        }
+        phase6::execute(&state.fs, output, state.ctx.write_options())?;
+        if let (PipelineMode::SourceBlock, Some(cache_root)) =
+            (state.mode, state.ctx.repo_manager.cache_root())
+        {
+            let mut manifest = ApplyManifest::new();
+            for path in &state.propagated {
+                if let Some(file) = state.fs.get_file(path) {
+                    manifest.insert(path, file);
+                }
+            }
+            // Files the upstream renamed since the last apply would
+            // otherwise stay behind at their old paths.
+            let previous = ApplyManifest::load(cache_root, output, state.ctx.pipeline);
+            manifest::remove_renamed(&previous, &manifest, &state.fs, output)?;
+            manifest.save(cache_root, output, state.ctx.pipeline);
+        }
+        Ok(())
+    }
+}
+
+/// Execute a sequential pipeline where operations fire in declaration order.
+///
+/// This function runs the context's stages over one block; the default
+/// [`COMPOSE`] stage walks the config's operations sequentially. When a
+/// `repo:` operation is encountered, it resolves inline at that position
+/// using [`resolve_repo_inline`] and [`phase4::integrate_sub_composite`].
+///
+/// Both `self:` and source blocks use this pipeline. Both start with an
+/// empty composite and load a read-only source FS from the local working
+/// directory; `include` operators pull from this source FS additively.
+/// See [`PipelineMode`] for full details.
+///
+/// A block whose final filesystem was saved as a checkpoint, or a source
+/// block with a plan to apply, only runs the stages from [`WRITE`] on.
+fn execute_sequential_pipeline(
+    config: &Schema,
+    ctx: &RunContext,
+    mode: PipelineMode,
+    block: usize,
+) -> Result<MemoryFS> {
+    run_block(config, ctx, mode, block).map(|state| state.fs)
+}
+
+/// Run one block as [`execute_sequential_pipeline`] does, and return its
+/// final state.
+fn run_block<'a>(
+    config: &'a Schema,
+    ctx: &'a RunContext<'a>,
+    mode: PipelineMode,
+    block: usize,
+) -> Result<BlockState<'a>> {
+    let mut state = BlockState {
+        config,
+        ctx,
+        fs: MemoryFS::new(),
+        source_fs: None,
+        mode,
+        block,
+        cloned_repos: HashMap::new(),
+        template_vars: HashMap::new(),
+        residual_deferred_ops: Vec::new(),
+        auto_merge_targets: BTreeMap::new(),
+        auto_merge_snapshots: BTreeMap::new(),
+        composed: Vec::new(),
+        propagated: Vec::new(),
+        locked: Vec::new(),
+        assertions: Vec::new(),
+        resumed: false,
+        placed: false,
+        local_inputs: None,
+    };
+
+    let mut stages = &ctx.stages[..];
+    let write = stages.iter().position(|stage| stage.name() == WRITE);
+    if let (PipelineMode::SourceBlock, Some(plan)) = (mode, ctx.plan) {
+        // The plan holds local files as they were when it was computed, so
+        // it may only be written over the same ones.
+        if plan.local.is_some() {
+            let local = state.load_local_inputs()?;
+            let overrides = if state.local_dir_missing() {
+                MemoryFS::new()
+            } else {
+                phase5::load_overrides(&state.local_dir())?
+            };
+            plan.check_local(&Plan::local_hashes(&local, &overrides))?;
+        }
+        if plan.into.is_some() {
+            if plan.into.as_deref() != ctx.into {
+                return Err(Error::ConfigParse {
+                    message: format!(
+                        "The plan was computed for --into {}",
+                        plan.into.as_deref().unwrap_or(Path::new("")).display()
+                    ),
+                    hint: Some("apply it with the same --into".to_string()),
+                });
+            }
+            state.placed = true;
+        }
+        state.fs = plan.fs.clone();
+        state.propagated = plan.propagated.clone();
+        stages = &stages[write.unwrap_or(stages.len())..];
+    } else if let Some((fs, propagated)) = state
+        .checkpoint()
+        .and_then(|(run, block)| run.composite(block))
+    {
+        info!(
+            "{}",
+            Message::ResumedFromCheckpoint {
+                what: "final filesystem"
+            }
+        );
+        state.fs = fs;
+        state.propagated = propagated;
+        state.resumed = true;
+        stages = &stages[write.unwrap_or(stages.len())..];
     }
 
-    // Phase 6: Write to disk (if output path provided)
-    if let Some(output) = output_path {
-        phase6::execute(&fs, output)?;
+    // The output is placed under its subdirectory once it is final, before
+    // it is written; a restored filesystem was saved placed.
+    let write = stages.iter().position(|stage| stage.name() == WRITE);
+    for (index, stage) in stages.iter().enumerate() {
+        if Some(index) == write {
+            place_output(&mut state)?;
+        }
+        debug!("stage {}: start", stage.name());
+        stage.run(&mut state)?;
     }
+    if write.is_none() {
+        place_output(&mut state)?;
+    }
+    Ok(state)
+}
 
-    Ok(fs)
+/// Move the output of a block, and the list of its propagated files, under
+/// the subdirectory it is placed under, if any.
+fn place_output(state: &mut BlockState<'_>) -> Result<()> {
+    let Some(into) = state
+        .placed_under()
+        .filter(|_| !state.resumed && !state.placed)
+    else {
+        return Ok(());
+    };
+    debug!("placing the output under {}", into.display());
+    let mut placed = MemoryFS::new();
+    for (path, file) in state.fs.files() {
+        placed.add_file(into.join(path), file.clone())?;
+    }
+    for (path, directory) in state.fs.directories() {
+        placed.add_directory(into.join(path), directory.clone())?;
+    }
+    state.fs = placed;
+    for path in &mut state.propagated {
+        *path = into.join(&*path);
+    }
+    Ok(())
 }
 
 /// Execute the complete pull operation.
@@ -701,40 +1358,105 @@ fn execute_sequential_pipeline(
 ///
 /// If `output_path` is `None`, returns the final MemoryFS without writing to disk.
 /// If `output_path` is `Some(path)`, writes to disk and returns the MemoryFS.
+///
+/// Non-fatal problems found along the way are appended to `diagnostics`,
+/// including those from a run that ends in an error.
 pub fn execute_pull(
     config: &Schema,
     repo_manager: &RepositoryManager,
     cache: &RepoCache,
     working_dir: &Path,
     output_path: Option<&Path>,
+    diagnostics: &mut Diagnostics,
 ) -> Result<MemoryFS> {
-    // Partition self: operations from source operations
-    let (self_ops, source_config) = partition_self_operations(config);
-
-    // Run the source pipeline using the sequential model so operations
-    // execute in YAML declaration order (same code path as self: blocks).
-    let final_fs = execute_sequential_pipeline(
-        &source_config,
+    execute_pull_resumable(
+        config,
         repo_manager,
         cache,
         working_dir,
         output_path,
-        PipelineMode::SourceBlock,
-    )?;
+        None,
+        diagnostics,
+    )
+}
+
+/// Execute the complete pull operation, saving checkpoints to `run`.
+///
+/// Like [`execute_pull`], but each pipeline block saves its repository tree
+/// and final filesystem to `run` (see [`super::checkpoint`]), and loads them
+/// instead of recomputing them when `run` was created for resuming. The run
+/// directory is removed after a successful write to `output_path`.
+pub fn execute_pull_resumable(
+    config: &Schema,
+    repo_manager: &RepositoryManager,
+    cache: &RepoCache,
+    working_dir: &Path,
+    output_path: Option<&Path>,
+    run: Option<&RunDir>,
+    diagnostics: &mut Diagnostics,
+) -> Result<MemoryFS> {
+    let mut ctx = RunContext::new(repo_manager, cache, working_dir);
+    ctx.output_path = output_path;
+    ctx.run = run;
+    execute_pull_with_context(config, &ctx, diagnostics)
+}
+
+/// Execute the complete pull operation with the settings in `ctx`.
+///
+/// [`execute_pull`] and [`execute_pull_resumable`] are shorthands for a
+/// context with default stages and no variable overrides. The checkpoint
+/// directory, if any, is removed after a successful write.
+pub fn execute_pull_with_context(
+    config: &Schema,
+    ctx: &RunContext,
+    diagnostics: &mut Diagnostics,
+) -> Result<MemoryFS> {
+    execute_plan(config, ctx, diagnostics).map(|plan| plan.fs)
+}
+
+/// Execute the complete pull operation with the settings in `ctx`, and
+/// return its output as a [`Plan`] that `ctx.plan` can apply later.
+///
+/// The plan holds the final filesystem of the source block; `self:` blocks
+/// are not part of it.
+pub fn execute_plan(
+    config: &Schema,
+    ctx: &RunContext,
+    diagnostics: &mut Diagnostics,
+) -> Result<Plan> {
+    let (result, collected) = diagnostics::collect(|| execute_pull_inner(config, ctx));
+    diagnostics.extend(collected);
+    if let (Ok(_), Some(_), Some(run)) = (&result, ctx.output_path, ctx.run) {
+        run.finish();
+    }
+    result
+}
+
+fn execute_pull_inner(config: &Schema, ctx: &RunContext) -> Result<Plan> {
+    // Resolve ref aliases up front, so every phase sees the refs discovery
+    // fetched
+    let resolved = crate::ref_index::resolve(config, ctx.working_dir, ctx.repo_manager)?;
+
+    // Partition self: operations from source operations
+    let (self_ops, source_config) = partition_self_operations(&resolved);
+
+    // Run the source pipeline using the sequential model so operations
+    // execute in YAML declaration order (same code path as self: blocks).
+    let source = run_block(&source_config, ctx, PipelineMode::SourceBlock, 0)?;
+    let plan = Plan {
+        config: Plan::config_hash(config),
+        fs: source.fs,
+        propagated: source.propagated,
+        local: source.local_inputs,
+        into: ctx.into.map(Path::to_path_buf),
+    };
 
     // Run self: pipelines using the same sequential execution model.
-    for self_op in &self_ops {
-        execute_sequential_pipeline(
-            &self_op.operations,
-            repo_manager,
-            cache,
-            working_dir,
-            output_path,
-            PipelineMode::SelfBlock,
-        )?;
+    for (index, self_op) in self_ops.iter().enumerate() {
+        execute_sequential_pipeline(&self_op.operations, ctx, PipelineMode::SelfBlock, index + 1)?;
     }
 
-    Ok(final_fs)
+    Ok(plan)
 }
 
 #[cfg(test)]
@@ -753,8 +1475,10 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["**".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         }
     }
 
@@ -765,8 +1489,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Self_ {
                 self_: SelfOp {
@@ -774,8 +1500,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["**/*".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     }],
                 },
             },
@@ -793,8 +1521,10 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["**/*".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         }];
 
         let (self_ops, source_ops) = partition_self_operations(&config);
@@ -883,7 +1613,7 @@ mod tests {
         // config.yaml: composite wins (upstream updated version)
         let config = final_fs.get_file("config.yaml").unwrap();
         assert_eq!(
-            String::from_utf8(config.content.clone()).unwrap(),
+            String::from_utf8(config.content.to_vec()).unwrap(),
             "new: value"
         );
 
@@ -893,7 +1623,7 @@ mod tests {
         // package.json: local base with fragment merged in
         let pkg = final_fs.get_file("package.json").unwrap();
         let json: serde_json::Value =
-            serde_json::from_str(&String::from_utf8(pkg.content.clone()).unwrap()).unwrap();
+            serde_json::from_str(std::str::from_utf8(&pkg.content).unwrap()).unwrap();
         assert_eq!(json["name"], "my-app");
         assert_eq!(json["version"], "1.0.0");
         assert_eq!(json["scripts"]["test"], "jest");
@@ -908,8 +1638,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["a/**".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     }],
                 },
             },
@@ -917,8 +1649,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Self_ {
                 self_: SelfOp {
@@ -926,8 +1660,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["b/**".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     }],
                 },
             },
@@ -976,6 +1712,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![include_all_op()],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -992,7 +1733,7 @@ mod tests {
         // Child's file was integrated via recursive resolution
         assert!(result.fs.exists("child.txt"));
         assert_eq!(
-            String::from_utf8(result.fs.get_file("child.txt").unwrap().content.clone()).unwrap(),
+            String::from_utf8(result.fs.get_file("child.txt").unwrap().content.to_vec()).unwrap(),
             "from child"
         );
     }
@@ -1033,6 +1774,11 @@ mod tests {
                         r#ref: Some("v1".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1058,8 +1804,18 @@ mod tests {
                                 r#ref: Some("v1".to_string()),
                                 path: None,
                                 with: vec![],
+                                disable: vec![],
+                                verify: None,
+                                on_fetch_error: None,
+                                partial: false,
+                                ref_index: None,
                             },
                         }],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1165,7 +1921,10 @@ mod tests {
             "https://github.com/test/repo.git".to_string(),
             "main".to_string(),
             vec![Operation::TemplateVars {
-                template_vars: TemplateVars { vars },
+                template_vars: TemplateVars {
+                    vars,
+                    secrets: Default::default(),
+                },
             }],
         );
 
@@ -1198,8 +1957,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             }],
         );
 
@@ -1242,6 +2003,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1302,8 +2068,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 // Third: repo: integrates child files into the FS.
                 Operation::Repo {
@@ -1312,6 +2080,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
                 // Fourth: exclude removes remove.txt (which came from child).
@@ -1370,8 +2143,18 @@ mod tests {
                                 r#ref: Some("main".to_string()),
                                 path: None,
                                 with: vec![],
+                                disable: vec![],
+                                verify: None,
+                                on_fetch_error: None,
+                                partial: false,
+                                ref_index: None,
                             },
                         }],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1392,6 +2175,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1454,6 +2242,11 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![merge_op],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             }],
         );
@@ -1490,11 +2283,9 @@ mod tests {
 
         let result = execute_sequential_pipeline(
             &config,
-            &repo_manager,
-            &cache,
-            working_dir,
-            None,
+            &RunContext::new(&repo_manager, &cache, working_dir),
             PipelineMode::SelfBlock,
+            0,
         )
         .unwrap();
 
@@ -1527,17 +2318,17 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["a.txt".to_string()],
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             if_exists: IfExists::Overwrite,
+            rewrite_links: None,
         }];
 
         let result = execute_sequential_pipeline(
             &config,
-            &repo_manager,
-            &cache,
-            working_dir,
-            None,
+            &RunContext::new(&repo_manager, &cache, working_dir),
             PipelineMode::SelfBlock,
+            0,
         )
         .unwrap();
 
@@ -1551,25 +2342,27 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["a.txt".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Include {
                 include: IncludeOp {
                     patterns: vec!["b.txt".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
         ];
 
         let result2 = execute_sequential_pipeline(
             &config_additive,
-            &repo_manager,
-            &cache,
-            working_dir,
-            None,
+            &RunContext::new(&repo_manager, &cache, working_dir),
             PipelineMode::SelfBlock,
+            0,
         )
         .unwrap();
 
@@ -1610,8 +2403,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["*".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Exclude {
                 exclude: crate::config::ExcludeOp {
@@ -1622,18 +2417,18 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["a.txt".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
         ];
 
         let result = execute_sequential_pipeline(
             &config,
-            &repo_manager,
-            &cache,
-            working_dir,
-            None,
+            &RunContext::new(&repo_manager, &cache, working_dir),
             PipelineMode::SelfBlock,
+            0,
         )
         .unwrap();
 
@@ -1659,6 +2454,7 @@ mod tests {
         let child_tv_op = Operation::TemplateVars {
             template_vars: TemplateVars {
                 vars: child_vars.clone(),
+                secrets: Default::default(),
             },
         };
 
@@ -1681,7 +2477,10 @@ mod tests {
             "main".to_string(),
             vec![
                 Operation::TemplateVars {
-                    template_vars: TemplateVars { vars: parent_vars },
+                    template_vars: TemplateVars {
+                        vars: parent_vars,
+                        secrets: Default::default(),
+                    },
                 },
                 Operation::Repo {
                     repo: RepoOp {
@@ -1689,6 +2488,11 @@ mod tests {
                         r#ref: Some("main".to_string()),
                         path: None,
                         with: vec![child_tv_op],
+                        disable: vec![],
+                        verify: None,
+                        on_fetch_error: None,
+                        partial: false,
+                        ref_index: None,
                     },
                 },
             ],
@@ -1733,8 +2537,10 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["a.txt".to_string()],
                 if_exists: IfExists::Preserve,
+                rewrite_links: None,
             },
             if_exists: IfExists::Preserve,
+            rewrite_links: None,
         }];
 
         let repo_manager = RepositoryManager::new(working.to_path_buf());
@@ -1744,11 +2550,9 @@ mod tests {
         // super::* in this test module).
         execute_sequential_pipeline(
             &config,
-            &repo_manager,
-            &cache,
-            working,
-            Some(output),
+            &RunContext::new(&repo_manager, &cache, working).output(output),
             PipelineMode::SelfBlock,
+            0,
         )
         .unwrap();
 
@@ -1761,4 +2565,171 @@ mod tests {
             "a.txt should not be written when if_exists: Preserve and file exists locally"
         );
     }
+
+    #[test]
+    fn execute_pull_with_context_runs_custom_stage_before_local_merge() {
+        use crate::cache::RepoCache;
+        use crate::config::{IfExists, IncludeOp, Operation};
+        use crate::repository::RepositoryManager;
+        use std::fs;
+        use tempfile::TempDir;
+
+        /// Rejects a composite that would propagate `secret.txt`.
+        struct NoSecrets;
+
+        impl Stage for NoSecrets {
+            fn name(&self) -> &str {
+                "no-secrets"
+            }
+
+            fn run(&self, state: &mut BlockState<'_>) -> Result<()> {
+                assert!(state.source_fs.is_some());
+                if state.fs.exists("secret.txt") {
+                    return Err(Error::Operator {
+                        operator: "no-secrets".to_string(),
+                        message: "secret.txt is in the composite".to_string(),
+                    });
+                }
+                Ok(())
+            }
+        }
+
+        let working_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        let working = working_dir.path();
+        let output = output_dir.path();
+        fs::write(working.join("a.txt"), b"a").unwrap();
+        fs::write(working.join("secret.txt"), b"s").unwrap();
+
+        let include = |pattern: &str| Operation::Include {
+            include: IncludeOp {
+                patterns: vec![pattern.to_string()],
+                if_exists: IfExists::Overwrite,
+                rewrite_links: None,
+            },
+            if_exists: IfExists::Overwrite,
+            rewrite_links: None,
+        };
+        let repo_manager = RepositoryManager::new(working.to_path_buf());
+        let cache = RepoCache::new();
+
+        let mut stages = default_stages();
+        let at = stages
+            .iter()
+            .position(|stage| stage.name() == LOCAL_MERGE)
+            .unwrap();
+        stages.insert(at, Box::new(NoSecrets));
+        let ctx = RunContext::new(&repo_manager, &cache, working)
+            .output(output)
+            .stages(stages);
+
+        let pull =
+            |config: &Schema| execute_pull_with_context(config, &ctx, &mut Diagnostics::new());
+
+        let err = pull(&vec![include("*.txt")]).unwrap_err();
+        assert!(matches!(err, Error::Operator { ref operator, .. } if operator == "no-secrets"));
+        // The stage stopped the block before anything was written.
+        assert!(!output.join("a.txt").exists());
+
+        pull(&vec![include("a.txt")]).unwrap();
+        assert_eq!(fs::read(output.join("a.txt")).unwrap(), b"a");
+    }
+
+    #[test]
+    fn execute_pull_with_context_overrides_template_vars() {
+        use crate::cache::RepoCache;
+        use crate::config::{IfExists, IncludeOp, Operation, TemplateOp, TemplateVars};
+        use crate::repository::RepositoryManager;
+        use std::fs;
+        use tempfile::TempDir;
+
+        let working_dir = TempDir::new().unwrap();
+        let working = working_dir.path();
+        fs::write(
+            working.join("a.txt"),
+            "__COMMON_REPO__NAME__ __COMMON_REPO__OWNER__",
+        )
+        .unwrap();
+
+        let config: Vec<Operation> = vec![
+            Operation::Include {
+                include: IncludeOp {
+                    patterns: vec!["a.txt".to_string()],
+                    if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
+                },
+                if_exists: IfExists::Overwrite,
+                rewrite_links: None,
+            },
+            Operation::Template {
+                template: TemplateOp {
+                    patterns: vec!["a.txt".to_string()],
+                    files: Default::default(),
+                },
+            },
+            Operation::TemplateVars {
+                template_vars: TemplateVars {
+                    vars: HashMap::from([
+                        ("NAME".to_string(), "declared".to_string()),
+                        ("OWNER".to_string(), "team".to_string()),
+                    ]),
+                    secrets: Default::default(),
+                },
+            },
+        ];
+
+        let repo_manager = RepositoryManager::new(working.to_path_buf());
+        let cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &cache, working).var("NAME", "override");
+
+        let result = execute_pull_with_context(&config, &ctx, &mut Diagnostics::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(result.get_file("a.txt").unwrap().content.to_vec()).unwrap(),
+            "override team"
+        );
+    }
+
+    #[test]
+    fn execute_pull_warns_about_upstream_that_contributes_nothing() {
+        use crate::cache::RepoCache;
+        use crate::repository::RepositoryManager;
+        use std::fs;
+        use tempfile::TempDir;
+
+        let working_dir = TempDir::new().unwrap();
+        let working = working_dir.path();
+        for (name, config) in [
+            (
+                "typo",
+                "- include: ['*.md']\n- exclude: ['docs/**']\n- include: ['src/*.rss']\n",
+            ),
+            ("vars", "- template-vars:\n    owner: team\n"),
+            ("ok", "- include: ['**/*']\n"),
+        ] {
+            let upstream = working.join(name);
+            fs::create_dir_all(upstream.join("src")).unwrap();
+            fs::write(upstream.join(".common-repo.yaml"), config).unwrap();
+            fs::write(upstream.join("src/lib.rs"), name).unwrap();
+        }
+        let config = crate::config::parse(
+            "- repo: {url: ./typo}\n- repo: {url: ./vars}\n- repo: {url: ./ok}\n",
+        )
+        .unwrap();
+
+        let repo_manager = RepositoryManager::new(working.join("cache"));
+        let cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &cache, working);
+        let mut diagnostics = Diagnostics::new();
+        let result = execute_pull_with_context(&config, &ctx, &mut diagnostics).unwrap();
+        assert!(result.exists("src/lib.rs"));
+
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "./typo contributes no files: include '*.md', 'src/*.rss' kept nothing \
+                 after exclude 'docs/**'; check the patterns for a bad glob"
+            ]
+        );
+    }
 }
@@ -0,0 +1,409 @@
+//! Plans that pin the output of a pull between review and apply.
+//!
+//! `diff --save-plan FILE` saves the final filesystem of the source block as
+//! a [`Plan`], and `apply --from-plan FILE` writes that filesystem instead of
+//! composing it again. What was reviewed is then what is applied, even if an
+//! upstream branch moved in between. `self:` blocks, which `diff` does not
+//! show, still run when the plan is applied.
+//!
+//! A plan file is a tar archive of `plan.json`, a manifest of the files with
+//! their permissions and origins, and of `blobs/`, which holds each file's
+//! content once by SHA-256. The manifest records a hash of the configuration
+//! the plan was computed from, and applying the plan with another
+//! configuration fails. It also records a hash of each local file the plan
+//! was merged with, so that applying it after one of them was edited fails
+//! instead of writing back the content it had at `diff` time.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Schema;
+use crate::defaults::OVERRIDES_DIR;
+use crate::error::{Error, Result};
+use crate::filesystem::{Directory, File, FileOrigin, MemoryFS};
+use crate::http::sha256_hex;
+
+/// The format version of plan files.
+pub const PLAN_VERSION: u32 = 2;
+
+/// The name of the manifest in a plan file.
+const MANIFEST: &str = "plan.json";
+
+/// The output of a pull, saved for applying later.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    /// Hash of the configuration the plan was computed from.
+    pub config: String,
+    /// The final filesystem of the source block.
+    pub fs: MemoryFS,
+    /// Files of `fs` that came from upstreams with no local input, which the
+    /// apply manifest lists.
+    pub propagated: Vec<PathBuf>,
+    /// Hashes of the local input files `fs` was merged with, by path (see
+    /// [`Plan::local_hashes`]), or `None` when the source block did not
+    /// merge local files.
+    pub local: Option<BTreeMap<PathBuf, String>>,
+    /// The subdirectory `fs` and `propagated` are already placed under (see
+    /// [`super::context::RunContext::into`]), if any.
+    pub into: Option<PathBuf>,
+}
+
+/// A file in a plan manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct PlanFile {
+    path: PathBuf,
+    sha256: String,
+    permissions: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<FileOrigin>,
+}
+
+/// A directory in a plan manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct PlanDirectory {
+    path: PathBuf,
+    permissions: u32,
+}
+
+/// The `plan.json` of a plan file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    config: String,
+    files: Vec<PlanFile>,
+    directories: Vec<PlanDirectory>,
+    propagated: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    local: Option<BTreeMap<PathBuf, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    into: Option<PathBuf>,
+}
+
+impl Plan {
+    /// The hash a plan records of `config`.
+    pub fn config_hash(config: &Schema) -> String {
+        sha256_hex(serde_json::to_string(config).unwrap_or_default().as_bytes())
+    }
+
+    /// Check that the plan was computed from `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigParse` if it was computed from another
+    /// configuration.
+    pub fn check(&self, config: &Schema) -> Result<()> {
+        if self.config == Self::config_hash(config) {
+            return Ok(());
+        }
+        Err(Error::ConfigParse {
+            message: "The plan was computed from a different configuration".to_string(),
+            hint: Some("run `common-repo diff --save-plan` again".to_string()),
+        })
+    }
+
+    /// The hashes a plan records of the local input files in `local` and of
+    /// the files in `overrides`, which are listed under the overrides
+    /// directory.
+    pub fn local_hashes(local: &MemoryFS, overrides: &MemoryFS) -> BTreeMap<PathBuf, String> {
+        let overrides_dir = Path::new(OVERRIDES_DIR);
+        local
+            .files()
+            .map(|(path, file)| (path.clone(), sha256_hex(&file.content)))
+            .chain(
+                overrides
+                    .files()
+                    .map(|(path, file)| (overrides_dir.join(path), sha256_hex(&file.content))),
+            )
+            .collect()
+    }
+
+    /// Check that the local input files are those the plan was merged with,
+    /// given their hashes in `local`.
+    ///
+    /// Only the paths the plan reads or writes count: those of the files it
+    /// was merged with, and those of its files, directly or under the
+    /// overrides directory. A file added elsewhere does not make it stale.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::StalePlan` naming the files that were added, changed
+    /// or removed since.
+    pub fn check_local(&self, local: &BTreeMap<PathBuf, String>) -> Result<()> {
+        let Some(planned) = &self.local else {
+            return Ok(());
+        };
+        // The local paths are relative to the subdirectory the files of the
+        // plan are placed under.
+        let into = self.into.as_deref().unwrap_or(Path::new(""));
+        let overrides_dir = Path::new(OVERRIDES_DIR);
+        let written = |path: &Path| {
+            let path = path.strip_prefix(overrides_dir).unwrap_or(path);
+            self.fs.exists(into.join(path))
+        };
+        let mut paths: Vec<String> = planned
+            .keys()
+            .chain(local.keys())
+            .filter(|path| planned.contains_key(*path) || written(path))
+            .filter(|path| planned.get(*path) != local.get(*path))
+            .map(|path| path.display().to_string())
+            .collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+        paths.sort();
+        paths.dedup();
+        Err(Error::StalePlan { paths })
+    }
+
+    /// Save the plan to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Filesystem` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let write_error = |e: std::io::Error| Error::Filesystem {
+            message: format!("Failed to write plan '{}': {}", path.display(), e),
+        };
+        let mut manifest = Manifest {
+            version: PLAN_VERSION,
+            config: self.config.clone(),
+            files: Vec::new(),
+            directories: Vec::new(),
+            propagated: self.propagated.clone(),
+            local: self.local.clone(),
+            into: self.into.clone(),
+        };
+        let mut blobs = HashMap::new();
+        for (path, file) in self.fs.files() {
+            let sha256 = sha256_hex(&file.content);
+            manifest.files.push(PlanFile {
+                path: path.clone(),
+                sha256: sha256.clone(),
+                permissions: file.permissions,
+                origin: file.origin.as_deref().cloned(),
+            });
+            blobs.entry(sha256).or_insert(&file.content[..]);
+        }
+        for (path, directory) in self.fs.directories() {
+            manifest.directories.push(PlanDirectory {
+                path: path.clone(),
+                permissions: directory.permissions,
+            });
+        }
+        let manifest = serde_json::to_vec(&manifest).map_err(|e| Error::Serialization {
+            message: e.to_string(),
+        })?;
+
+        let mut builder = tar::Builder::new(std::fs::File::create(path).map_err(write_error)?);
+        let mut append = |name: &str, content: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, name, content)
+        };
+        append(MANIFEST, &manifest).map_err(write_error)?;
+        let mut blobs: Vec<_> = blobs.into_iter().collect();
+        blobs.sort();
+        for (sha256, content) in blobs {
+            append(&format!("blobs/{}", sha256), content).map_err(write_error)?;
+        }
+        builder.into_inner().map_err(write_error)?;
+        Ok(())
+    }
+
+    /// Load the plan saved to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Filesystem` if the file cannot be read or is not a
+    /// plan of this version.
+    pub fn load(path: &Path) -> Result<Plan> {
+        let invalid = |message: String| Error::Filesystem {
+            message: format!("Failed to read plan '{}': {}", path.display(), message),
+        };
+        let file = std::fs::File::open(path).map_err(|e| invalid(e.to_string()))?;
+        let mut archive = tar::Archive::new(file);
+        let mut manifest = None;
+        let mut blobs = HashMap::new();
+        for entry in archive.entries().map_err(|e| invalid(e.to_string()))? {
+            let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+            let name = entry
+                .path()
+                .map_err(|e| invalid(e.to_string()))?
+                .to_string_lossy()
+                .into_owned();
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| invalid(e.to_string()))?;
+            if name == MANIFEST {
+                manifest = Some(content);
+            } else if let Some(sha256) = name.strip_prefix("blobs/") {
+                blobs.insert(sha256.to_string(), content);
+            }
+        }
+        let manifest = manifest.ok_or_else(|| invalid(format!("it has no {}", MANIFEST)))?;
+        let manifest: Manifest =
+            serde_json::from_slice(&manifest).map_err(|e| invalid(e.to_string()))?;
+        if manifest.version != PLAN_VERSION {
+            return Err(invalid(format!(
+                "it has version {}, but this version of common-repo reads version {}",
+                manifest.version, PLAN_VERSION
+            )));
+        }
+
+        let mut fs = MemoryFS::new();
+        for entry in manifest.files {
+            let content = blobs
+                .get(&entry.sha256)
+                .filter(|content| sha256_hex(content) == entry.sha256)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "the content of {} is missing",
+                        entry.path.display()
+                    ))
+                })?;
+            let mut file = File::new(content.clone());
+            file.permissions = entry.permissions;
+            file.origin = entry.origin.map(Arc::new);
+            fs.add_file(&entry.path, file)?;
+        }
+        for entry in manifest.directories {
+            let mut directory = Directory::new();
+            directory.permissions = entry.permissions;
+            fs.add_directory(&entry.path, directory)?;
+        }
+        Ok(Plan {
+            config: manifest.config,
+            fs,
+            propagated: manifest.propagated,
+            local: manifest.local,
+            into: manifest.into,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{IfExists, IncludeOp, Operation};
+    use tempfile::TempDir;
+
+    fn config() -> Schema {
+        vec![Operation::Include {
+            include: IncludeOp {
+                patterns: vec!["**".to_string()],
+                if_exists: IfExists::Overwrite,
+                rewrite_links: None,
+            },
+            if_exists: IfExists::Overwrite,
+            rewrite_links: None,
+        }]
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("README.md", "same\n").unwrap();
+        let mut script = File::new(b"same\n".to_vec());
+        script.permissions = 0o755;
+        script.origin = Some(Arc::new(FileOrigin {
+            repo: Some("https://github.com/acme/ci".to_string()),
+            ref_: "v1".to_string(),
+            source: PathBuf::from("run.sh"),
+            operations: vec!["template".to_string()],
+        }));
+        fs.add_file("bin/run", script).unwrap();
+        fs.add_directory("empty", Directory::new()).unwrap();
+        let plan = Plan {
+            config: Plan::config_hash(&config()),
+            fs,
+            propagated: vec![PathBuf::from("bin/run")],
+            local: Some(BTreeMap::new()),
+            into: Some(PathBuf::from("services/api")),
+        };
+
+        let path = dir.path().join("plan.bin");
+        plan.save(&path).unwrap();
+        let loaded = Plan::load(&path).unwrap();
+        assert_eq!(loaded.fs.get_file("README.md").unwrap().content, b"same\n");
+        let run = loaded.fs.get_file("bin/run").unwrap();
+        assert_eq!(run.permissions, 0o755);
+        assert_eq!(run.origin, plan.fs.get_file("bin/run").unwrap().origin);
+        assert!(loaded
+            .fs
+            .directories()
+            .any(|(path, _)| path == Path::new("empty")));
+        assert_eq!(loaded.propagated, plan.propagated);
+        assert_eq!(loaded.local, plan.local);
+        assert_eq!(loaded.into, plan.into);
+
+        assert!(loaded.check(&config()).is_ok());
+        let err = loaded.check(&vec![]).unwrap_err().to_string();
+        assert!(
+            err.contains("different configuration"),
+            "Error was: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_check_local_names_changed_files() {
+        let mut local = MemoryFS::new();
+        local.add_file_string("README.md", "mine\n").unwrap();
+        local.add_file_string("src/lib.rs", "\n").unwrap();
+        let mut overrides = MemoryFS::new();
+        overrides.add_file_string("ci.yml", "on: push\n").unwrap();
+        let mut fs = local.clone();
+        fs.add_file_string("ci.yml", "on: push\n").unwrap();
+        fs.add_file_string("CONTRIBUTING.md", "upstream\n").unwrap();
+        let plan = Plan {
+            config: Plan::config_hash(&config()),
+            fs,
+            propagated: vec![],
+            local: Some(Plan::local_hashes(&local, &overrides)),
+            into: None,
+        };
+        assert!(plan
+            .check_local(&Plan::local_hashes(&local, &overrides))
+            .is_ok());
+
+        local.add_file_string("README.md", "edited\n").unwrap();
+        local.add_file_string("CONTRIBUTING.md", "mine\n").unwrap();
+        local.add_file_string("NEW.md", "\n").unwrap();
+        let err = plan
+            .check_local(&Plan::local_hashes(&local, &MemoryFS::new()))
+            .unwrap_err();
+        assert_eq!(err.code(), "E4005");
+        let err = err.to_string();
+        assert!(
+            err.contains(".common-repo/overrides/ci.yml, CONTRIBUTING.md, README.md"),
+            "Error was: {}",
+            err
+        );
+        // Neither read nor written by the plan
+        assert!(!err.contains("NEW.md"), "Error was: {}", err);
+        assert!(!err.contains("src/lib.rs"), "Error was: {}", err);
+
+        let unrecorded = Plan {
+            local: None,
+            ..plan
+        };
+        assert!(unrecorded.check_local(&BTreeMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_load_rejects_other_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("plan.bin");
+        std::fs::write(&path, "not a plan").unwrap();
+        assert!(Plan::load(&path).is_err());
+        assert!(Plan::load(&dir.path().join("missing.bin")).is_err());
+    }
+}
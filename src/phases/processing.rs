@@ -9,6 +9,11 @@
 //! converts one `ClonedRepo` into an `IntermediateFS`, using the in-process
 //! [`RepoCache`] to reuse work when the same clone and operations appear again.
 //!
+//! Repositories are independent of each other until composition, so clones are
+//! read in parallel, and [`warm_cache`] processes every repository that does
+//! not integrate others in parallel before the sequential pass. The sequential
+//! pass then only does the work that depends on declaration order.
+//!
 //! [`execute`] is still the batch entry point for the source pipeline: it takes
 //! the tree and returns the keyed intermediate map as before, but now builds
 //! `ClonedRepo` values with [`clone_tree_repos`] and processes each repository
@@ -16,28 +21,42 @@
 //! `self:` pipeline can call [`process_cloned_repo`] on demand without a
 //! separate code path.
 //!
+//! Before any of that, [`check_requirements`] checks what the upstreams
+//! require of the consumer, so a misconfigured consumer fails with every
+//! problem listed instead of producing broken output, and
+//! [`check_unknown_operations`] refuses misspelled operations, or under
+//! `--strict` any operation this version does not know.
+//!
 //! For each repository, operations from the `with:` clause run in order on a
 //! copy of the raw filesystem. Template variables and deferred merge operations
 //! are collected into each `IntermediateFS` for Phase 4 and Phase 5.
 
-use std::collections::HashMap;
-
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+use rayon::prelude::*;
+
+use super::context::RunContext;
 use super::{ClonedRepo, IntermediateFS, RepoNode, RepoTree};
 use crate::cache::{CacheKey, RepoCache};
-use crate::config::Operation;
-use crate::defaults::{ALT_CONFIG_FILENAME, DEFAULT_CONFIG_FILENAME};
+use crate::config::{AssertOp, Operation, RequiredVar, UnknownOp};
+use crate::defaults::ConfigFiles;
+use crate::diagnostics::{self, Diagnostics};
 use crate::error::{Error, Result};
 use crate::filesystem::MemoryFS;
 use crate::operators;
+use crate::reporting::renames::{self, RenameTrace};
+use crate::reporting::timings::{self, Timings};
 use crate::repository::RepositoryManager;
 
 /// Walk a `RepoTree` and produce a map of `ClonedRepo` instances.
 ///
 /// Each non-local node is fetched from the repository cache (already cloned in
 /// Phase 1) and bundled with its operations into a `ClonedRepo`. Config files
-/// are stripped so the raw FS is ready for on-demand processing.
+/// are stripped so the raw FS is ready for on-demand processing. Nodes that
+/// use the same repository at the same ref with different operations share
+/// one fetch. Repositories are fetched in parallel; if several fail, the
+/// error of the first in traversal order is returned.
 ///
 /// The returned map is keyed by `RepoNode::node_key()`, the same key used
 /// by [`execute`] and the Phase 3 operation order.
@@ -45,44 +64,125 @@ pub(crate) fn clone_tree_repos(
     tree: &RepoTree,
     repo_manager: &RepositoryManager,
 ) -> Result<HashMap<String, ClonedRepo>> {
-    let mut cloned_repos = HashMap::new();
-    clone_tree_repos_recursive(&tree.root, repo_manager, &mut cloned_repos)?;
-    Ok(cloned_repos)
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+
+    // Each distinct fetch once, in traversal order
+    let mut fetches: Vec<FetchKey> = Vec::new();
+    for (_, node) in &nodes {
+        let fetch = fetch_key(node);
+        if !fetches.contains(&fetch) {
+            fetches.push(fetch);
+        }
+    }
+    let fetched: Vec<Result<MemoryFS>> = fetches
+        .par_iter()
+        .map(|(url, ref_, partial)| {
+            let mut fs = repo_manager.fetch_repository_partial(url, ref_, partial)?;
+            remove_source_config_files(&mut fs);
+            Ok(fs)
+        })
+        .collect();
+    let fetched: HashMap<FetchKey, MemoryFS> = fetches
+        .into_iter()
+        .zip(fetched.into_iter().collect::<Result<Vec<_>>>()?)
+        .collect();
+
+    Ok(nodes
+        .into_iter()
+        .map(|(key, node)| {
+            let fs = fetched[&fetch_key(node)].clone();
+            (key, cloned_repo(node, fs))
+        })
+        .collect())
 }
 
-/// Recursively walk the tree and fetch each non-local repo into a ClonedRepo.
-fn clone_tree_repos_recursive(
-    node: &RepoNode,
-    repo_manager: &RepositoryManager,
-    cloned_repos: &mut HashMap<String, ClonedRepo>,
-) -> Result<()> {
-    // Process children first (same traversal order as process_repo_recursive)
+/// What is fetched for a node: its URL, ref and partial patterns.
+type FetchKey<'a> = (&'a str, &'a str, &'a [String]);
+
+fn fetch_key(node: &RepoNode) -> FetchKey<'_> {
+    (&node.url, &node.ref_, &node.partial)
+}
+
+/// Recursively walk the tree and collect each non-local node once, children
+/// before their parent.
+fn collect_tree_nodes<'a>(
+    node: &'a RepoNode,
+    seen: &mut HashSet<String>,
+    nodes: &mut Vec<(String, &'a RepoNode)>,
+) {
     for child in &node.children {
-        clone_tree_repos_recursive(child, repo_manager, cloned_repos)?;
+        collect_tree_nodes(child, seen, nodes);
     }
 
-    // Skip local root and already-fetched repos
+    // Skip local root and repos already collected
     let key = node.node_key();
-    if node.url == "local" || cloned_repos.contains_key(&key) {
-        return Ok(());
+    if node.url == "local" || !seen.insert(key.clone()) {
+        return;
     }
+    nodes.push((key, node));
+}
 
-    let mut fs = repo_manager.fetch_repository_with_path(&node.url, &node.ref_, None)?;
-    remove_source_config_files(&mut fs);
-
-    let children_keys: Vec<String> = node.children.iter().map(|c| c.node_key()).collect();
+/// Bundle one node's fetched clone `fs` into a `ClonedRepo`.
+fn cloned_repo(node: &RepoNode, fs: MemoryFS) -> ClonedRepo {
     let mut cloned = ClonedRepo::new(
         fs,
         node.url.clone(),
         node.ref_.clone(),
         node.operations.clone(),
     );
-    cloned.children_keys = children_keys;
+    cloned.children_keys = node.children.iter().map(|c| c.node_key()).collect();
     cloned.original_url = node.original_url.clone();
+    cloned
+}
 
-    cloned_repos.insert(key, cloned);
+/// Process every independent repo in `cloned_repos` in parallel, ahead of
+/// the sequential pass.
+///
+/// A repo without `repo:` operations or tree children (see
+/// [`ClonedRepo::is_independent`]) is processed from its own clone, so these
+/// repos do not wait on each other. The results land in `cache`, where
+/// [`process_cloned_repo`] finds them when the sequential pass reaches each
+/// repo in declaration order. Repos that depend on others are still
+/// processed there, after their dependencies.
+///
+/// Diagnostics, renames and operation timings (see [`crate::reporting`])
+/// are reported on the calling thread, in key order. A repo that fails is left
+/// out of the cache, so the sequential pass reports the error where it
+/// would have without this step.
+pub(crate) fn warm_cache(cloned_repos: &HashMap<String, ClonedRepo>, cache: &RepoCache) {
+    let mut independent: Vec<(&String, &ClonedRepo)> = cloned_repos
+        .iter()
+        .filter(|(_, cloned)| {
+            cloned.is_independent() && !crate::repository::is_local_url(&cloned.url)
+        })
+        .collect();
+    independent.sort_by_key(|(key, _)| *key);
+
+    let reports: Vec<(Diagnostics, RenameTrace, Timings)> = independent
+        .par_iter()
+        .map(|(_, cloned)| {
+            let (((result, reported), renames), timings) = timings::trace(|| {
+                renames::trace(|| diagnostics::collect(|| process_cloned_repo(cloned, cache)))
+            });
+            match result {
+                Ok(_) => (reported, renames, timings),
+                Err(_) => (
+                    Diagnostics::new(),
+                    RenameTrace::default(),
+                    Timings::default(),
+                ),
+            }
+        })
+        .collect();
 
-    Ok(())
+    for (reported, trace, timed) in reports {
+        for diagnostic in &reported {
+            diagnostics::warn(diagnostic.phase, diagnostic.message.clone());
+        }
+        renames::replay(trace);
+        timings::replay(timed);
+    }
 }
 
 /// Process a single `ClonedRepo` into an `IntermediateFS`.
@@ -95,6 +195,12 @@ fn clone_tree_repos_recursive(
 /// every repo in a tree, it processes one repo at a time so the sequential
 /// pass can invoke it when a `repo:` operation fires.
 pub fn process_cloned_repo(cloned: &ClonedRepo, cache: &RepoCache) -> Result<IntermediateFS> {
+    timings::within(format!("{}@{}", cloned.url, cloned.ref_), || {
+        process_cloned_repo_inner(cloned, cache)
+    })
+}
+
+fn process_cloned_repo_inner(cloned: &ClonedRepo, cache: &RepoCache) -> Result<IntermediateFS> {
     let template_vars = collect_template_vars(&cloned.operations)?;
     let merge_operations = collect_merge_operations(&cloned.operations);
 
@@ -164,6 +270,205 @@ fn cache_key_for_cloned(cloned: &ClonedRepo) -> Result<Option<CacheKey>> {
     )))
 }
 
+/// The repositories in `tree` that lock files, each URL with the patterns
+/// of its `include` operations marked `locked: true`.
+pub(crate) fn locked_patterns(tree: &RepoTree) -> Vec<(String, Vec<String>)> {
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+    nodes
+        .into_iter()
+        .filter(|(_, node)| !node.locked.is_empty())
+        .map(|(_, node)| (node.url.clone(), node.locked.clone()))
+        .collect()
+}
+
+/// The `assert` operations of the repositories in `tree`, each with the URL
+/// of the repository that declares it.
+pub(crate) fn upstream_assertions(tree: &RepoTree) -> Vec<(String, AssertOp)> {
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+    let mut assertions = Vec::new();
+    for (_, node) in nodes {
+        for operation in &node.operations {
+            if let Operation::Assert { assert } = operation {
+                assertions.push((node.url.clone(), assert.clone()));
+            }
+        }
+    }
+    assertions
+}
+
+/// Check the operations of `config`, including those of its `self:`
+/// blocks, and of the repositories in `tree` for operations this version
+/// does not know.
+///
+/// An unknown operation whose name is close to a known one is most likely
+/// a typo and fails even without `strict`; with `strict` every unknown
+/// operation fails. The rest are left for the pipeline to skip and report.
+pub(crate) fn check_unknown_operations(
+    tree: &RepoTree,
+    config: &[Operation],
+    strict: bool,
+) -> Result<()> {
+    fn consumer_unknowns<'a>(
+        config: &'a [Operation],
+        unknowns: &mut Vec<(Option<&'a str>, &'a UnknownOp)>,
+    ) {
+        for operation in config {
+            match operation {
+                Operation::Unknown(unknown) => unknowns.push((None, unknown)),
+                Operation::Self_ { self_ } => consumer_unknowns(&self_.operations, unknowns),
+                _ => {}
+            }
+        }
+    }
+
+    let mut unknowns = Vec::new();
+    consumer_unknowns(config, &mut unknowns);
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+    for (_, node) in &nodes {
+        for operation in &node.operations {
+            if let Operation::Unknown(unknown) = operation {
+                unknowns.push((Some(node.url.as_str()), unknown));
+            }
+        }
+    }
+
+    let failing: Vec<String> = unknowns
+        .into_iter()
+        .filter(|(_, unknown)| strict || unknown.suggestion().is_some())
+        .map(|(repo, unknown)| {
+            let mut problem = format!("'{}'", unknown.name);
+            if let Some(repo) = repo {
+                problem.push_str(&format!(" in {}", repo));
+            }
+            if let Some(similar) = unknown.suggestion() {
+                problem.push_str(&format!(" (did you mean '{}'?)", similar));
+            }
+            problem
+        })
+        .collect();
+    if failing.is_empty() {
+        return Ok(());
+    }
+    Err(Error::ConfigParse {
+        message: format!("Unknown operations: {}", failing.join(", ")),
+        hint: Some(if strict {
+            "fix or remove them, or upgrade common-repo if they come from a newer release"
+                .to_string()
+        } else {
+            "fix the misspelled operation names".to_string()
+        }),
+    })
+}
+
+/// Check what the upstreams in `tree` require of the consumer with
+/// `requires:` blocks (see [`crate::config::ConsumerRequirements`]) against
+/// `config` and the run's context.
+///
+/// Variables count as set when a `template-vars` operation in `config`, or
+/// in the `with:` of one of its repos, sets them, or when the context
+/// overrides them. Files are looked up in the working directory. Every unmet
+/// requirement is reported in a single [`Error::RequirementsNotMet`].
+pub(crate) fn check_requirements(
+    tree: &RepoTree,
+    config: &[Operation],
+    ctx: &RunContext,
+) -> Result<()> {
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+    if nodes.iter().all(|(_, node)| node.requires.is_none()) {
+        return Ok(());
+    }
+
+    let mut problems: Vec<String> = unset_vars(tree, config, ctx)
+        .into_iter()
+        .map(|(repo, var)| {
+            format!(
+                "{} requires template variable '{}'; set it with a template-vars operation",
+                repo, var.name
+            )
+        })
+        .collect();
+    for (_, node) in nodes {
+        let Some(requires) = &node.requires else {
+            continue;
+        };
+        let repo = node.original_url.as_deref().unwrap_or(&node.url);
+        for file in &requires.files {
+            if !ctx.working_dir.join(file).exists() {
+                problems.push(format!(
+                    "{} requires file '{}' in {}",
+                    repo,
+                    file,
+                    ctx.working_dir.display()
+                ));
+            }
+        }
+        for tool in &requires.tools {
+            if let Err(e) = operators::tools::check_tool(tool) {
+                let message = match e {
+                    Error::ToolValidation { message, .. } => message,
+                    other => other.to_string(),
+                };
+                problems.push(format!("{} requires {}: {}", repo, tool.name, message));
+            }
+        }
+    }
+    // A repository inherited through several paths is checked once per path.
+    let mut seen = HashSet::new();
+    problems.retain(|problem| seen.insert(problem.clone()));
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::RequirementsNotMet { problems })
+    }
+}
+
+/// The variables upstreams in `tree` require that neither `config` nor
+/// the run's context sets, with the repository requiring each, in tree
+/// order. A variable required by several repositories is listed for each.
+pub(crate) fn unset_vars<'t>(
+    tree: &'t RepoTree,
+    config: &[Operation],
+    ctx: &RunContext,
+) -> Vec<(&'t str, &'t RequiredVar)> {
+    let mut nodes = Vec::new();
+    collect_tree_nodes(&tree.root, &mut HashSet::new(), &mut nodes);
+
+    let mut vars_set: HashSet<&str> = ctx.vars.keys().map(String::as_str).collect();
+    collect_set_vars(config, &mut vars_set);
+
+    let mut unset = Vec::new();
+    for (_, node) in nodes {
+        let Some(requires) = &node.requires else {
+            continue;
+        };
+        let repo = node.original_url.as_deref().unwrap_or(&node.url);
+        for var in &requires.vars {
+            if !vars_set.contains(var.name.as_str()) {
+                unset.push((repo, var));
+            }
+        }
+    }
+    unset
+}
+
+/// Add the variables `template-vars` operations in `operations`, and in the
+/// `with:` of their repos, set.
+fn collect_set_vars<'a>(operations: &'a [Operation], vars: &mut HashSet<&'a str>) {
+    for operation in operations {
+        match operation {
+            Operation::TemplateVars { template_vars } => {
+                vars.extend(template_vars.vars.keys().map(String::as_str));
+            }
+            Operation::Repo { repo } => collect_set_vars(&repo.with, vars),
+            _ => {}
+        }
+    }
+}
+
 /// Executes Phase 2 of the pipeline.
 ///
 /// This function takes the `RepoTree` from Phase 1 and processes each
@@ -183,6 +488,7 @@ pub fn execute(
     // Clone all non-local repos into raw ClonedRepo structs
     let cloned_repos = clone_tree_repos(tree, repo_manager)?;
 
+    warm_cache(&cloned_repos, cache);
     let mut intermediate_fss = HashMap::new();
 
     // Process each cloned repo into an IntermediateFS
@@ -220,7 +526,8 @@ fn process_single_repo(
 
     if let Some(cache_key) = cache_key_for_node(node)? {
         let fs = cache.get_or_process(cache_key, || -> Result<MemoryFS> {
-            let mut fs = repo_manager.fetch_repository(&node.url, &node.ref_)?;
+            let mut fs =
+                repo_manager.fetch_repository_partial(&node.url, &node.ref_, &node.partial)?;
             // Remove upstream repo's config files so they don't get copied to consumers
             remove_source_config_files(&mut fs);
             // Source FS for include operators is the cleaned working
@@ -264,7 +571,15 @@ pub(crate) fn collect_template_vars(operations: &[Operation]) -> Result<HashMap<
 
     for operation in operations {
         if let Operation::TemplateVars { template_vars } = operation {
-            template_vars::collect(template_vars, &mut vars)?;
+            // An upstream must not run commands or read files on the
+            // consumer's machine
+            for name in template_vars.secrets.keys() {
+                diagnostics::warn(
+                    crate::error::ErrorPhase::Processing,
+                    crate::messages::Message::UpstreamSecretIgnored { name }.to_string(),
+                );
+            }
+            template_vars::collect_public(template_vars, &mut vars)?;
         }
     }
 
@@ -310,14 +625,14 @@ fn cache_key_for_node(node: &RepoNode) -> Result<Option<CacheKey>> {
 
 /// Remove upstream repository's config files from the filesystem
 ///
-/// Config files (.common-repo.yaml and .commonrepo.yaml) should never be
-/// copied from upstream repos to consumers. This function removes them after
-/// fetching an upstream repository but before applying any operations.
+/// Config files (.common-repo.yaml, .commonrepo.yaml, and the `config-file:`
+/// of the user configuration) should never be copied from upstream repos to
+/// consumers. This function removes them after fetching an upstream
+/// repository but before applying any operations.
 fn remove_source_config_files(fs: &mut MemoryFS) {
-    // Remove primary config filename
-    let _ = fs.remove_file(Path::new(DEFAULT_CONFIG_FILENAME));
-    // Remove alternate config filename
-    let _ = fs.remove_file(Path::new(ALT_CONFIG_FILENAME));
+    for name in ConfigFiles::load().names() {
+        let _ = fs.remove_file(Path::new(name));
+    }
 }
 
 /// Apply a single operation to a filesystem
@@ -332,10 +647,16 @@ pub(crate) fn apply_operation(
             // from the read-only source FS into the composite. Files
             // already in the composite that do not match the patterns are
             // left alone.
-            operators::include::apply(include, source_fs, fs)
+            timings::time(operation, fs, |fs| {
+                operators::include::apply(include, source_fs, fs)
+            })
+        }
+        Operation::Exclude { exclude } => {
+            timings::time(operation, fs, |fs| operators::exclude::apply(exclude, fs))
+        }
+        Operation::Rename { rename } => {
+            timings::time(operation, fs, |fs| operators::rename::apply(rename, fs))
         }
-        Operation::Exclude { exclude } => operators::exclude::apply(exclude, fs),
-        Operation::Rename { rename } => operators::rename::apply(rename, fs),
         Operation::Repo { repo: _ } => {
             // Repo operations should have been processed in Phase 1
             // They create new repositories, not modify existing ones
@@ -343,7 +664,7 @@ pub(crate) fn apply_operation(
         }
         Operation::Template { template } => {
             use crate::operators::template;
-            template::mark(template, fs)
+            timings::time(operation, fs, |fs| template::mark(template, fs))
         }
         Operation::TemplateVars { template_vars: _ } => {
             // Template variables are collected separately in collect_template_vars()
@@ -351,6 +672,20 @@ pub(crate) fn apply_operation(
             Ok(())
         }
         Operation::Tools { tools } => operators::tools::apply(tools),
+        Operation::File { file } => {
+            timings::time(operation, fs, |fs| operators::file::apply(file, fs))
+        }
+        Operation::Header { header } => {
+            timings::time(operation, fs, |fs| operators::header::apply(header, fs))
+        }
+        Operation::YamlFormat { yaml_format } => timings::time(operation, fs, |fs| {
+            operators::yaml_format::apply(yaml_format, fs)
+        }),
+        Operation::Extract { extract } => timings::time(operation, fs, |fs| {
+            crate::merge::apply_extract_operation(fs, extract)
+        }),
+        // Checked against the consumer's composed output
+        Operation::Assert { .. } => Ok(()),
         // Merge operations are collected separately and executed in Phase 4
         Operation::Yaml { yaml: _ } => {
             // Collected in collect_merge_operations() and executed in Phase 4
@@ -380,6 +715,36 @@ pub(crate) fn apply_operation(
             // Self operations run in an isolated pipeline, handled separately
             Ok(())
         }
+        Operation::Vendor { .. } => {
+            // Vendored files belong to the consumer; an upstream that needs
+            // shared code should inherit it with a `repo` operation
+            Err(Error::Operator {
+                operator: "vendor".to_string(),
+                message: "Vendor operations are only supported in the consumer's configuration"
+                    .to_string(),
+            })
+        }
+        Operation::Patch { .. } => {
+            // Patches apply to the consumer's composed output; an upstream
+            // repository has no output of its own to patch
+            Err(Error::Operator {
+                operator: "patch".to_string(),
+                message: "Patch operations not supported in 'with:' clauses".to_string(),
+            })
+        }
+        Operation::Format { .. } => {
+            // Formatting follows the consumer's conventions, which an
+            // upstream repository does not know
+            Err(Error::Operator {
+                operator: "format".to_string(),
+                message: "Format operations not supported in 'with:' clauses".to_string(),
+            })
+        }
+        Operation::Unknown(unknown) => {
+            // Written for a newer release; skip it rather than fail
+            unknown.report();
+            Ok(())
+        }
     }
 }
 
@@ -477,7 +842,7 @@ mod tests {
 
             if let Some(repo_url) = self.get_repo_key(cache_path) {
                 if let Some(config_content) = self.repo_configs.get(&repo_url) {
-                    fs.add_file_string(DEFAULT_CONFIG_FILENAME, config_content)?;
+                    fs.add_file_string(crate::defaults::DEFAULT_CONFIG_FILENAME, config_content)?;
                 }
             }
 
@@ -580,8 +945,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             },
             Operation::Exclude {
                 exclude: ExcludeOp {
@@ -644,6 +1011,7 @@ mod tests {
                     vars.insert("OWNER".to_string(), "org-a".to_string());
                     vars
                 },
+                secrets: Default::default(),
             },
         }];
 
@@ -654,6 +1022,7 @@ mod tests {
                     vars.insert("OWNER".to_string(), "org-b".to_string());
                     vars
                 },
+                secrets: Default::default(),
             },
         }];
 
@@ -688,6 +1057,155 @@ mod tests {
         assert_eq!(fs2.template_vars.get("OWNER"), Some(&"org-b".to_string()));
     }
 
+    #[test]
+    fn test_warm_cache_processes_only_independent_repos() {
+        let include_all = Operation::Include {
+            include: IncludeOp {
+                patterns: vec!["**".to_string()],
+                if_exists: IfExists::Overwrite,
+                rewrite_links: None,
+            },
+            if_exists: IfExists::Overwrite,
+            rewrite_links: None,
+        };
+        let mut source = MemoryFS::new();
+        source.add_file_string("README.md", "upstream").unwrap();
+
+        let mut cloned_repos = HashMap::new();
+        for name in ["a", "b", "c"] {
+            let cloned = ClonedRepo::new(
+                source.clone(),
+                format!("https://example.com/{}.git", name),
+                "main".to_string(),
+                vec![include_all.clone()],
+            );
+            cloned_repos.insert(cloned.node_key(), cloned);
+        }
+        // A repo that integrates another waits for the sequential pass.
+        let mut parent = ClonedRepo::new(
+            source,
+            "https://example.com/parent.git".to_string(),
+            "main".to_string(),
+            vec![include_all],
+        );
+        parent.children_keys = vec!["https://example.com/a.git@main".to_string()];
+        cloned_repos.insert(parent.node_key(), parent);
+
+        let cache = RepoCache::new();
+        warm_cache(&cloned_repos, &cache);
+        assert_eq!(cache.len().unwrap(), 3);
+
+        // The sequential pass reuses the warmed result.
+        let a = cloned_repos
+            .values()
+            .find(|c| c.url == "https://example.com/a.git")
+            .unwrap();
+        let processed = process_cloned_repo(a, &cache).unwrap();
+        assert!(processed.fs.exists("README.md"));
+        assert_eq!(cache.len().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_check_unknown_operations_refuses_typos_and_under_strict() {
+        let unknown = |name: &str| {
+            Operation::Unknown(crate::config::UnknownOp {
+                name: name.to_string(),
+                value: serde_yaml::Value::Null,
+            })
+        };
+        let upstream = RepoNode::new(
+            "https://example.com/template.git".to_string(),
+            "v1".to_string(),
+            vec![unknown("archive")],
+        );
+        let mut root = RepoNode::new("local".to_string(), "HEAD".to_string(), vec![]);
+        root.add_child(upstream);
+        let tree = RepoTree::new(root);
+
+        // Operations from a newer release are skipped unless strict
+        check_unknown_operations(&tree, &[unknown("not-an-operation")], false).unwrap();
+        let Err(Error::ConfigParse { message, .. }) =
+            check_unknown_operations(&tree, &[unknown("not-an-operation")], true)
+        else {
+            panic!("expected unknown operations to fail under strict");
+        };
+        assert_eq!(
+            message,
+            "Unknown operations: 'not-an-operation', 'archive' in https://example.com/template.git"
+        );
+
+        // A misspelled operation fails either way
+        let Err(Error::ConfigParse { message, .. }) =
+            check_unknown_operations(&tree, &[unknown("exlude")], false)
+        else {
+            panic!("expected a misspelled operation to fail");
+        };
+        assert_eq!(
+            message,
+            "Unknown operations: 'exlude' (did you mean 'exclude'?)"
+        );
+    }
+
+    #[test]
+    fn test_check_requirements_lists_every_unmet_requirement() {
+        use crate::config::{ConsumerRequirements, Tool};
+
+        let required = |name: &str| RequiredVar {
+            name: name.to_string(),
+            description: None,
+            default: None,
+        };
+        let mut upstream = RepoNode::new(
+            "https://example.com/template.git".to_string(),
+            "v1".to_string(),
+            vec![],
+        );
+        upstream.requires = Some(ConsumerRequirements {
+            vars: vec![required("project_name"), required("owner")],
+            files: vec!["Cargo.toml".to_string(), "LICENSE".to_string()],
+            tools: vec![Tool {
+                name: "common-repo-no-such-tool".to_string(),
+                version: "*".to_string(),
+            }],
+        });
+        let mut root = RepoNode::new("local".to_string(), "HEAD".to_string(), vec![]);
+        root.add_child(upstream);
+        let tree = RepoTree::new(root);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        let config = vec![Operation::TemplateVars {
+            template_vars: TemplateVars {
+                vars: HashMap::from([("project_name".to_string(), "demo".to_string())]),
+                secrets: Default::default(),
+            },
+        }];
+        let repo_manager = RepositoryManager::new(dir.path().join("cache"));
+        let cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &cache, dir.path());
+
+        let Err(Error::RequirementsNotMet { problems }) = check_requirements(&tree, &config, &ctx)
+        else {
+            panic!("expected unmet requirements");
+        };
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].contains("template variable 'owner'"));
+        assert!(problems[1].contains("file 'LICENSE'"));
+        assert!(problems[2].contains("common-repo-no-such-tool"));
+        assert_eq!(
+            unset_vars(&tree, &config, &ctx),
+            [("https://example.com/template.git", &required("owner"))]
+        );
+
+        // Context overrides count as set; files and tools still do not.
+        let ctx = ctx.var("owner", "org");
+        let Err(Error::RequirementsNotMet { problems }) = check_requirements(&tree, &config, &ctx)
+        else {
+            panic!("expected unmet requirements");
+        };
+        assert_eq!(problems.len(), 2);
+    }
+
     #[test]
     fn test_recursive_discovery() {
         let mock_git = RecursiveMockGitOps::new();
@@ -707,9 +1225,16 @@ mod tests {
                     include: crate::config::IncludeOp {
                         patterns: vec!["*.md".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 }],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -791,6 +1316,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -826,6 +1356,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -899,6 +1434,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -959,6 +1499,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1006,6 +1551,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1057,6 +1607,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1087,6 +1642,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1119,6 +1679,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: Some("subdir".to_string()),
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1148,6 +1713,11 @@ mod tests {
                 r#ref: Some("main".to_string()),
                 path: None,
                 with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
             },
         }];
 
@@ -1186,6 +1756,13 @@ mod tests {
                 original_url: Some("./relative".to_string()),
                 children: Vec::new(),
                 operations: Vec::new(),
+                disable: Vec::new(),
+                verify: None,
+                on_fetch_error: Default::default(),
+                requires: None,
+                locked: Vec::new(),
+                partial: Vec::new(),
+                description: None,
             };
 
             let result = cache_key_for_node(&node).expect("should not error");
@@ -1305,8 +1882,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["src/**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Rename {
                     rename: RenameOp {
@@ -1314,6 +1893,7 @@ mod tests {
                             from: "old".to_string(),
                             to: "new".to_string(),
                         }],
+                        rewrite_links: None,
                     },
                 },
             ];
@@ -1331,6 +1911,7 @@ mod tests {
             let operations = vec![Operation::Template {
                 template: TemplateOp {
                     patterns: vec!["**/*.tmpl".to_string()],
+                    files: Default::default(),
                 },
             }];
             let node = RepoNode::new(
@@ -1375,7 +1956,10 @@ mod tests {
             vars.insert("VERSION".to_string(), "1.0.0".to_string());
 
             let operations = vec![Operation::TemplateVars {
-                template_vars: TemplateVars { vars },
+                template_vars: TemplateVars {
+                    vars,
+                    secrets: Default::default(),
+                },
             }];
             let result = collect_template_vars(&operations).expect("should not error");
             assert_eq!(result.len(), 2);
@@ -1393,10 +1977,16 @@ mod tests {
 
             let operations = vec![
                 Operation::TemplateVars {
-                    template_vars: TemplateVars { vars: vars1 },
+                    template_vars: TemplateVars {
+                        vars: vars1,
+                        secrets: Default::default(),
+                    },
                 },
                 Operation::TemplateVars {
-                    template_vars: TemplateVars { vars: vars2 },
+                    template_vars: TemplateVars {
+                        vars: vars2,
+                        secrets: Default::default(),
+                    },
                 },
             ];
             let result = collect_template_vars(&operations).expect("should not error");
@@ -1417,7 +2007,10 @@ mod tests {
                     },
                 },
                 Operation::TemplateVars {
-                    template_vars: TemplateVars { vars },
+                    template_vars: TemplateVars {
+                        vars,
+                        secrets: Default::default(),
+                    },
                 },
             ];
             let result = collect_template_vars(&operations).expect("should not error");
@@ -1442,12 +2035,16 @@ mod tests {
             let operations = vec![
                 // Upstream operations come first (from extract_upstream_operations)
                 Operation::TemplateVars {
-                    template_vars: TemplateVars { vars: source_vars },
+                    template_vars: TemplateVars {
+                        vars: source_vars,
+                        secrets: Default::default(),
+                    },
                 },
                 // Consumer with: operations come last
                 Operation::TemplateVars {
                     template_vars: TemplateVars {
                         vars: consumer_vars,
+                        secrets: Default::default(),
                     },
                 },
             ];
@@ -1737,8 +2334,10 @@ mod tests {
                 include: IncludeOp {
                     patterns: vec!["src/**".to_string()],
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: IfExists::Overwrite,
+                rewrite_links: None,
             };
             apply_operation(&mut fs, &source_fs, &operation).expect("should not error");
 
@@ -1778,6 +2377,7 @@ mod tests {
                         from: r"^README\.md$".to_string(),
                         to: "GUIDE.md".to_string(),
                     }],
+                    rewrite_links: None,
                 },
             };
             apply_operation(&mut fs, &MemoryFS::new(), &operation).expect("should not error");
@@ -1796,6 +2396,11 @@ mod tests {
                     r#ref: Some("main".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             };
             apply_operation(&mut fs, &MemoryFS::new(), &operation).expect("should not error");
@@ -1810,7 +2415,10 @@ mod tests {
             let mut vars = HashMap::new();
             vars.insert("KEY".to_string(), "value".to_string());
             let operation = Operation::TemplateVars {
-                template_vars: TemplateVars { vars },
+                template_vars: TemplateVars {
+                    vars,
+                    secrets: Default::default(),
+                },
             };
             apply_operation(&mut fs, &MemoryFS::new(), &operation).expect("should not error");
             assert_eq!(original_count, fs.list_files().len());
@@ -1822,6 +2430,7 @@ mod tests {
             let operation = Operation::Template {
                 template: TemplateOp {
                     patterns: vec!["*.md".to_string()],
+                    files: Default::default(),
                 },
             };
             apply_operation(&mut fs, &MemoryFS::new(), &operation).expect("should not error");
@@ -2006,7 +2615,10 @@ mod tests {
             vars.insert("NAME".to_string(), "test-project".to_string());
 
             let operations = vec![Operation::TemplateVars {
-                template_vars: TemplateVars { vars },
+                template_vars: TemplateVars {
+                    vars,
+                    secrets: Default::default(),
+                },
             }];
             let node = RepoNode::new(
                 "https://github.com/example/repo.git".to_string(),
@@ -2365,6 +2977,66 @@ mod tests {
             assert!(cloned.fs.exists("temp.tmp"));
         }
 
+        #[test]
+        fn fetches_a_repository_used_with_different_operations_once() {
+            struct CountingCacheOps {
+                loads: Arc<Mutex<usize>>,
+            }
+
+            impl CacheOperations for CountingCacheOps {
+                fn exists(&self, _cache_path: &Path) -> bool {
+                    true
+                }
+
+                fn get_cache_path(&self, _url: &str, _ref_name: &str) -> PathBuf {
+                    PathBuf::from("/mock/cache/path")
+                }
+
+                fn load_from_cache(&self, _cache_path: &Path) -> Result<MemoryFS> {
+                    *self.loads.lock().unwrap() += 1;
+                    let mut fs = MemoryFS::new();
+                    fs.add_file_string("keep.txt", "important").unwrap();
+                    Ok(fs)
+                }
+
+                fn save_to_cache(&self, _cache_path: &Path, _fs: &MemoryFS) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            let loads = Arc::new(Mutex::new(0));
+            let repo_manager = RepositoryManager::with_operations(
+                Box::new(MockGitOps::new(
+                    Arc::new(Mutex::new(0)),
+                    Arc::new(Mutex::new(true)),
+                )),
+                Box::new(CountingCacheOps {
+                    loads: loads.clone(),
+                }),
+            );
+
+            let exclude = |pattern: &str| {
+                vec![Operation::Exclude {
+                    exclude: ExcludeOp {
+                        patterns: vec![pattern.to_string()],
+                    },
+                }]
+            };
+            let url = "https://example.com/repo.git".to_string();
+            let tree = build_tree_with_children(vec![
+                RepoNode::new(url.clone(), "main".to_string(), exclude("*.tmp")),
+                RepoNode::new(url.clone(), "main".to_string(), exclude("*.bak")),
+                RepoNode::new(url, "v2".to_string(), exclude("*.tmp")),
+            ]);
+
+            let cloned_repos = clone_tree_repos(&tree, &repo_manager).expect("clone_tree_repos");
+            assert_eq!(cloned_repos.len(), 3);
+            assert!(cloned_repos
+                .values()
+                .all(|cloned| cloned.fs.exists("keep.txt")));
+            assert_eq!(*loads.lock().unwrap(), 2, "one fetch per url@ref");
+        }
+
         #[test]
         fn strips_config_files_from_cloned_repo() {
             let clone_calls = Arc::new(Mutex::new(0));
@@ -2422,6 +3094,13 @@ mod tests {
                 original_url: Some("./wherever".to_string()),
                 children: Vec::new(),
                 operations: Vec::new(),
+                disable: Vec::new(),
+                verify: None,
+                on_fetch_error: Default::default(),
+                requires: None,
+                locked: Vec::new(),
+                partial: Vec::new(),
+                description: None,
             };
             let mut root = RepoNode::new("local".to_string(), "HEAD".to_string(), vec![]);
             root.add_child(local_child);
@@ -2487,8 +3166,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["**".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     Operation::Exclude {
                         exclude: ExcludeOp {
@@ -2528,8 +3209,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["README.md".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     // Second include pulls src/** files. Additive: README.md
                     // — already in the composite — is left alone.
@@ -2537,8 +3220,10 @@ mod tests {
                         include: IncludeOp {
                             patterns: vec!["src/**".to_string()],
                             if_exists: IfExists::Overwrite,
+                            rewrite_links: None,
                         },
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                 ],
             );
@@ -2569,6 +3254,7 @@ mod tests {
                             vars.insert("OWNER".to_string(), "my-org".to_string());
                             vars
                         },
+                        secrets: Default::default(),
                     },
                 }],
             );
@@ -2661,8 +3347,10 @@ mod tests {
                     include: IncludeOp {
                         patterns: vec!["**".to_string()],
                         if_exists: IfExists::Overwrite,
+                        rewrite_links: None,
                     },
                     if_exists: IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 Operation::Exclude {
                     exclude: ExcludeOp {
@@ -2736,6 +3424,7 @@ mod tests {
                         vars.insert("NAME".to_string(), "test-project".to_string());
                         vars
                     },
+                    secrets: Default::default(),
                 },
             }];
 
@@ -2800,8 +3489,10 @@ mod tests {
             include: IncludeOp {
                 patterns: vec!["foo.txt".to_string()],
                 if_exists: IfExists::Preserve,
+                rewrite_links: None,
             },
             if_exists: IfExists::Preserve,
+            rewrite_links: None,
         };
 
         apply_operation(&mut fs, &source_fs, &op).unwrap();
@@ -7,73 +7,508 @@
 //!
 //! 1.  **Iterate Files**: The process iterates through all files in the MemoryFS.
 //!
-//! 2.  **Create Directories**: For each file, creates any necessary parent
-//!     directories recursively.
+//! 2.  **Create Directories**: Collects the parent directories of all files
+//!     and creates each distinct one once, deepest first.
 //!
-//! 3.  **Write Content**: Writes the file content to disk.
+//! 3.  **Write Content**: Writes the file content to disk. Files are written
+//!     in parallel on rayon's thread pool, which has one thread per CPU.
 //!
 //! 4.  **Set Permissions**: On Unix-like systems, sets file permissions to match
 //!     the stored permissions (e.g., executable bit for scripts).
 //!
+//! 5.  **Create Explicit Directories**: Directory entries recorded in the
+//!     MemoryFS (typically empty directories) are created with their stored
+//!     permissions.
+//!
+//! Before anything is written, every destination is checked to be a name
+//! the host can store: valid UTF-8, free of control characters, and within
+//! the OS limits on name and path length once joined to the output path.
+//! All the paths that fail are listed in one [`Error::UnsafeOutputPaths`],
+//! instead of the write stopping partway with an OS error.
+//!
+//! Every destination is also checked for a symlink
+//! at the path itself or at any of its parent directories. Writing through
+//! one would change the link's target, which may be outside the output
+//! directory, so the write fails with [`Error::SymlinkedOutput`] unless the
+//! caller opts in to following symlinks.
+//!
+//! Destinations that already exist as read-only files are also found up
+//! front. One whose content would not change is left alone; the others are
+//! handled as [`ReadOnlyPolicy`] says: all of them listed in one
+//! [`Error::ReadOnlyOutput`], each skipped with a warning, or each made
+//! writable for the write and read-only again afterwards.
+//!
+//! With [`WriteOptions::protect`], files that came from an upstream are
+//! written read-only, and overwritten even when they already are, so that
+//! a managed file is always read-only and up to date after a write.
+//!
 //! This phase produces files on the host filesystem, completing the pull operation.
+//! [`write_tar`] produces the same files as a tar archive instead.
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use rayon::prelude::*;
+
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
+use crate::filesystem::{File, MemoryFS};
+use crate::messages::Message;
+use crate::path::validate_relative_path;
+
+/// The longest file or directory name, in bytes, that common filesystems
+/// accept.
+const MAX_NAME_LEN: usize = 255;
+
+/// The longest full path, in characters, that the host accepts. On Windows
+/// this is `MAX_PATH`, which counts the output directory too.
+#[cfg(windows)]
+const MAX_PATH_LEN: usize = 260;
+#[cfg(not(windows))]
+const MAX_PATH_LEN: usize = 4096;
+
+/// What to do with a destination that exists as a read-only file and
+/// would change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadOnlyPolicy {
+    /// Fail before writing anything, listing every such file (default).
+    #[default]
+    Error,
+    /// Leave the file as it is, with a warning.
+    Skip,
+    /// Make the file writable, write it, and make it read-only again.
+    Overwrite,
+}
 
-use crate::error::{Error, Result};
-use crate::filesystem::MemoryFS;
+/// How [`execute`] treats what already exists under the output path.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Write through symlinks to their targets instead of refusing to.
+    pub follow_symlinks: bool,
+    /// What to do with read-only files that would change.
+    pub read_only: ReadOnlyPolicy,
+    /// Write files that came from an upstream read-only, overwriting them
+    /// whatever `read_only` says.
+    pub protect: bool,
+}
 
 /// Execute Phase 6: Write final filesystem to disk
 ///
 /// Writes all files from the MemoryFS to the host filesystem at the specified output path.
 /// Creates all necessary directories recursively and preserves file permissions where possible.
-pub fn execute(final_fs: &MemoryFS, output_path: &Path) -> Result<()> {
+///
+/// Every path is re-checked with [`validate_relative_path`] before anything
+/// is written, so an absolute or `..`-escaping entry fails the whole write
+/// instead of landing outside `output_path`.
+///
+/// Unless [`WriteOptions::follow_symlinks`] is set, a destination that is a
+/// symlink, or that has one among its parent directories under
+/// `output_path`, also fails the whole write. Read-only destinations are
+/// handled as [`WriteOptions::read_only`] says.
+pub fn execute(final_fs: &MemoryFS, output_path: &Path, options: WriteOptions) -> Result<()> {
+    for path in final_fs
+        .files()
+        .map(|(p, _)| p)
+        .chain(final_fs.directories().map(|(p, _)| p))
+    {
+        validate_relative_path(path)?;
+    }
+
+    let unsafe_paths: Vec<String> = final_fs
+        .files()
+        .map(|(p, _)| p)
+        .chain(final_fs.directories().map(|(p, _)| p))
+        .filter_map(|path| {
+            path_problem(path, output_path)
+                .map(|problem| format!("{}: {}", path.to_string_lossy(), problem))
+        })
+        .collect();
+    if !unsafe_paths.is_empty() {
+        return Err(Error::UnsafeOutputPaths {
+            paths: unsafe_paths,
+        });
+    }
+
+    if !options.follow_symlinks {
+        let links = symlinked_destinations(final_fs, output_path);
+        if !links.is_empty() {
+            return Err(Error::SymlinkedOutput {
+                paths: links
+                    .iter()
+                    .map(|link| {
+                        let target = fs::read_link(output_path.join(link))
+                            .map(|target| target.display().to_string())
+                            .unwrap_or_else(|_| "?".to_string());
+                        format!("{} -> {}", link.display(), target)
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    // The original permissions of read-only files being overwritten, and
+    // the read-only files left as they are.
+    let mut restore = HashMap::new();
+    let mut skip = HashSet::new();
+    let mut conflicts = Vec::new();
     for (relative_path, file) in final_fs.files() {
-        // Construct full output path
         let full_path = output_path.join(relative_path);
-
-        // Create parent directories if needed
-        if let Some(parent) = full_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| Error::Filesystem {
-                message: format!("Failed to create directory '{}': {}", parent.display(), e),
-            })?;
+        let Ok(metadata) = fs::metadata(&full_path) else {
+            continue;
+        };
+        if !metadata.is_file() || !metadata.permissions().readonly() {
+            continue;
+        }
+        if fs::read(&full_path).is_ok_and(|content| content == file.content) {
+            debug!("unchanged read-only file: {}", relative_path.display());
+            skip.insert(relative_path.as_path());
+            continue;
+        }
+        if options.protect && file.is_managed() {
+            restore.insert(relative_path.as_path(), metadata.permissions());
+            continue;
         }
+        match options.read_only {
+            ReadOnlyPolicy::Error => conflicts.push(relative_path.display().to_string()),
+            ReadOnlyPolicy::Skip => {
+                diagnostics::warn(
+                    ErrorPhase::Output,
+                    Message::ReadOnlySkipped {
+                        path: &relative_path.display().to_string(),
+                    }
+                    .to_string(),
+                );
+                skip.insert(relative_path.as_path());
+            }
+            ReadOnlyPolicy::Overwrite => {
+                restore.insert(relative_path.as_path(), metadata.permissions());
+            }
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(Error::ReadOnlyOutput { paths: conflicts });
+    }
 
-        // Write file content
-        fs::write(&full_path, &file.content).map_err(|e| Error::Filesystem {
-            message: format!("Failed to write file '{}': {}", full_path.display(), e),
+    let to_write: Vec<(&PathBuf, &File)> = final_fs
+        .files()
+        .filter(|(relative_path, _)| !skip.contains(relative_path.as_path()))
+        .collect();
+
+    // Each distinct parent is created once, deepest first so that its
+    // ancestors come along and need no call of their own.
+    let parents: BTreeSet<&Path> = to_write
+        .iter()
+        .filter_map(|(relative_path, _)| relative_path.parent())
+        .collect();
+    let deepest: Vec<&Path> = parents
+        .iter()
+        .filter(|parent| {
+            parents
+                .range::<Path, _>((Bound::Excluded(**parent), Bound::Unbounded))
+                .next()
+                .is_none_or(|next| !next.starts_with(parent))
+        })
+        .copied()
+        .collect();
+    deepest.par_iter().try_for_each(|parent| {
+        let full_path = output_path.join(parent);
+        fs::create_dir_all(&full_path).map_err(|e| Error::Filesystem {
+            message: format!(
+                "Failed to create directory '{}': {}",
+                full_path.display(),
+                e
+            ),
+        })
+    })?;
+
+    to_write.par_iter().try_for_each(|(relative_path, file)| {
+        write_file(
+            &output_path.join(relative_path),
+            file,
+            restore.get(relative_path.as_path()),
+            options.protect && file.is_managed(),
+        )
+    })?;
+
+    for (relative_path, dir) in final_fs.directories() {
+        let full_path = output_path.join(relative_path);
+        fs::create_dir_all(&full_path).map_err(|e| Error::Filesystem {
+            message: format!(
+                "Failed to create directory '{}': {}",
+                full_path.display(),
+                e
+            ),
         })?;
 
-        // Set permissions on Unix-like systems
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let perms = fs::Permissions::from_mode(file.permissions);
-            if let Err(e) = fs::set_permissions(&full_path, perms) {
-                // Log warning but don't fail - permissions are best-effort
-                // On some systems (e.g., certain mount points), setting permissions may fail
-                return Err(Error::Filesystem {
-                    message: format!(
-                        "Failed to set permissions on '{}': {}",
-                        full_path.display(),
-                        e
-                    ),
-                });
+            let perms = fs::Permissions::from_mode(dir.permissions);
+            fs::set_permissions(&full_path, perms).map_err(|e| Error::Filesystem {
+                message: format!(
+                    "Failed to set permissions on '{}': {}",
+                    full_path.display(),
+                    e
+                ),
+            })?;
+        }
+        #[cfg(not(unix))]
+        let _ = dir;
+    }
+
+    Ok(())
+}
+
+/// Writes every file and directory of `final_fs` to `writer` as a tar
+/// archive, in path order, with their permissions and modification times.
+///
+/// Paths are checked as [`execute`] checks them, so the archive unpacks to
+/// the same files a write to disk would produce.
+pub fn write_tar(final_fs: &MemoryFS, writer: impl std::io::Write) -> Result<()> {
+    let tar_error = |path: &Path, e: std::io::Error| Error::Filesystem {
+        message: format!("Failed to add '{}' to the archive: {}", path.display(), e),
+    };
+
+    let mut entries: Vec<(&PathBuf, Option<&File>, u32)> = final_fs
+        .files()
+        .map(|(path, file)| (path, Some(file), file.permissions))
+        .chain(
+            final_fs
+                .directories()
+                .map(|(path, dir)| (path, None, dir.permissions)),
+        )
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut builder = tar::Builder::new(writer);
+    for (path, file, permissions) in entries {
+        validate_relative_path(path)?;
+        if let Some(problem) = path_problem(path, Path::new("")) {
+            return Err(Error::UnsafeOutputPaths {
+                paths: vec![format!("{}: {}", path.to_string_lossy(), problem)],
+            });
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(permissions & 0o7777);
+        match file {
+            Some(file) => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_size(file.content.len() as u64);
+                header.set_mtime(
+                    file.modified_time
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                );
+                builder
+                    .append_data(&mut header, path, &file.content[..])
+                    .map_err(|e| tar_error(path, e))?;
+            }
+            None => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_size(0);
+                builder
+                    .append_data(&mut header, path, std::io::empty())
+                    .map_err(|e| tar_error(path, e))?;
             }
         }
     }
+    builder
+        .into_inner()
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| Error::Filesystem {
+            message: format!("Failed to finish the archive: {}", e),
+        })
+}
+
+/// Writes `file` to `full_path`, whose parent exists. `original` holds the
+/// permissions of a read-only file being overwritten, which it keeps. A
+/// `read_only` file is made read-only after it is written.
+fn write_file(
+    full_path: &Path,
+    file: &File,
+    original: Option<&fs::Permissions>,
+    read_only: bool,
+) -> Result<()> {
+    if let Some(original) = original {
+        let mut writable = original.clone();
+        #[allow(clippy::permissions_set_readonly_false)]
+        writable.set_readonly(false);
+        fs::set_permissions(full_path, writable).map_err(|e| Error::Filesystem {
+            message: format!("Failed to make '{}' writable: {}", full_path.display(), e),
+        })?;
+    }
 
+    fs::write(full_path, &file.content).map_err(|e| Error::Filesystem {
+        message: format!("Failed to write file '{}': {}", full_path.display(), e),
+    })?;
+
+    // A read-only file stays read-only.
+    if let Some(original) = original {
+        return fs::set_permissions(full_path, original.clone()).map_err(|e| Error::Filesystem {
+            message: format!(
+                "Failed to restore permissions on '{}': {}",
+                full_path.display(),
+                e
+            ),
+        });
+    }
+
+    // Set permissions on Unix-like systems
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = fs::Permissions::from_mode(file.permissions);
+        fs::set_permissions(full_path, perms).map_err(|e| Error::Filesystem {
+            message: format!(
+                "Failed to set permissions on '{}': {}",
+                full_path.display(),
+                e
+            ),
+        })?;
+    }
+    if read_only {
+        let mut permissions = fs::metadata(full_path)
+            .map_err(|e| Error::Filesystem {
+                message: format!("Failed to read '{}': {}", full_path.display(), e),
+            })?
+            .permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(full_path, permissions).map_err(|e| Error::Filesystem {
+            message: format!("Failed to make '{}' read-only: {}", full_path.display(), e),
+        })?;
+    }
     Ok(())
 }
 
+/// Why the host could not store `path` under `output_path`, if it could
+/// not.
+fn path_problem(path: &Path, output_path: &Path) -> Option<String> {
+    let Some(text) = path.to_str() else {
+        return Some("not valid UTF-8".to_string());
+    };
+    if let Some(c) = text.chars().find(|c| c.is_control()) {
+        return Some(format!("contains control character {:?}", c));
+    }
+    if let Some(name) = text
+        .split(['/', '\\'])
+        .find(|name| name.len() > MAX_NAME_LEN)
+    {
+        return Some(format!(
+            "name '{}...' is {} bytes long, over the limit of {}",
+            name.chars().take(20).collect::<String>(),
+            name.len(),
+            MAX_NAME_LEN
+        ));
+    }
+    let full_len = output_path.join(path).to_string_lossy().chars().count();
+    if full_len > MAX_PATH_LEN {
+        return Some(format!(
+            "{} characters long with the output directory, over the limit of {}",
+            full_len, MAX_PATH_LEN
+        ));
+    }
+    None
+}
+
+/// The symlinks under `output_path` that writing `final_fs` would go
+/// through, relative to `output_path`.
+fn symlinked_destinations(final_fs: &MemoryFS, output_path: &Path) -> BTreeSet<PathBuf> {
+    let mut checked = HashSet::new();
+    let mut links = BTreeSet::new();
+    for path in final_fs
+        .files()
+        .map(|(p, _)| p)
+        .chain(final_fs.directories().map(|(p, _)| p))
+    {
+        let mut prefix = PathBuf::new();
+        for component in path.components() {
+            prefix.push(component);
+            if links.contains(&prefix) {
+                break;
+            }
+            if !checked.insert(prefix.clone()) {
+                continue;
+            }
+            match fs::symlink_metadata(output_path.join(&prefix)) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    links.insert(prefix.clone());
+                    break;
+                }
+                Ok(_) => {}
+                // Nothing exists here yet, so nothing below it does either.
+                Err(_) => break,
+            }
+        }
+    }
+    links
+}
+
 #[cfg(test)]
 mod tests {
-    use super::execute;
+    use super::{execute, ReadOnlyPolicy, WriteOptions};
     use crate::filesystem::{File, MemoryFS};
     use std::fs;
     use std::os::unix::fs::PermissionsExt;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_phase6_write_many_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut fs = MemoryFS::new();
+        for i in 0..500 {
+            fs.add_file_string(format!("d{}/e{}/f{}.txt", i % 7, i % 3, i), &i.to_string())
+                .unwrap();
+        }
+        fs.add_file_string("d1-top.txt", "top").unwrap();
+
+        execute(&fs, temp_dir.path(), WriteOptions::default()).unwrap();
+
+        for i in 0..500 {
+            let path = temp_dir
+                .path()
+                .join(format!("d{}/e{}/f{}.txt", i % 7, i % 3, i));
+            assert_eq!(std::fs::read_to_string(path).unwrap(), i.to_string());
+        }
+        assert!(temp_dir.path().join("d1-top.txt").is_file());
+    }
+
+    #[test]
+    fn test_phase6_reports_unsafe_paths_before_writing() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("ok.txt", "ok").unwrap();
+        fs.add_file_string("tab\there.txt", "x").unwrap();
+        fs.add_file_string(format!("{}.txt", "a".repeat(300)), "x")
+            .unwrap();
+        fs.add_file(
+            OsStr::from_bytes(b"bad\xffname.txt"),
+            File::from_string("x"),
+        )
+        .unwrap();
+
+        let err = execute(&fs, temp_dir.path(), WriteOptions::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("tab\there.txt: contains control character '\\t'"),
+            "{err}"
+        );
+        assert!(err.contains("bytes long, over the limit of 255"), "{err}");
+        assert!(
+            err.contains("bad\u{fffd}name.txt: not valid UTF-8"),
+            "{err}"
+        );
+        assert!(!err.contains("ok.txt"), "{err}");
+        assert!(!temp_dir.path().join("ok.txt").exists());
+    }
+
     #[test]
     fn test_phase6_write_single_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -82,7 +517,7 @@ mod tests {
         let mut fs = MemoryFS::new();
         fs.add_file_string("test.txt", "Hello, world!").unwrap();
 
-        execute(&fs, output_path).unwrap();
+        execute(&fs, output_path, WriteOptions::default()).unwrap();
 
         let file_path = output_path.join("test.txt");
         assert!(file_path.exists());
@@ -101,7 +536,7 @@ mod tests {
         fs.add_file_string("src/main.rs", "fn main() {}").unwrap();
         fs.add_file_string("README.md", "# Project").unwrap();
 
-        execute(&fs, output_path).unwrap();
+        execute(&fs, output_path, WriteOptions::default()).unwrap();
 
         // Verify nested file exists
         let nested_path = output_path.join("src/utils/helper.rs");
@@ -128,7 +563,7 @@ mod tests {
         fs.add_file_string("file2.txt", "Content 2").unwrap();
         fs.add_file_string("file3.txt", "Content 3").unwrap();
 
-        execute(&fs, output_path).unwrap();
+        execute(&fs, output_path, WriteOptions::default()).unwrap();
 
         assert_eq!(
             fs::read_to_string(output_path.join("file1.txt")).unwrap(),
@@ -155,7 +590,7 @@ mod tests {
             .add_file_content("binary.bin", binary_data.clone())
             .unwrap();
 
-        execute(&memfs, output_path).unwrap();
+        execute(&memfs, output_path, WriteOptions::default()).unwrap();
 
         let file_path = output_path.join("binary.bin");
         assert!(file_path.exists());
@@ -174,7 +609,7 @@ mod tests {
         file.permissions = 0o755; // Executable permissions
         memfs.add_file("script.sh", file).unwrap();
 
-        execute(&memfs, output_path).unwrap();
+        execute(&memfs, output_path, WriteOptions::default()).unwrap();
 
         let file_path = output_path.join("script.sh");
         assert!(file_path.exists());
@@ -187,6 +622,27 @@ mod tests {
         assert_eq!(mode & 0o777, 0o755);
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_phase6_creates_empty_directories() {
+        use crate::filesystem::Directory;
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path();
+
+        let mut memfs = MemoryFS::new();
+        memfs
+            .add_directory("db/migrations", Directory { permissions: 0o700 })
+            .unwrap();
+
+        execute(&memfs, output_path, WriteOptions::default()).unwrap();
+
+        let dir_path = output_path.join("db/migrations");
+        assert!(dir_path.is_dir());
+        let mode = fs::metadata(&dir_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
     #[test]
     fn test_phase6_empty_filesystem() {
         let temp_dir = TempDir::new().unwrap();
@@ -195,7 +651,7 @@ mod tests {
         let memfs = MemoryFS::new();
 
         // Should not error on empty filesystem
-        execute(&memfs, output_path).unwrap();
+        execute(&memfs, output_path, WriteOptions::default()).unwrap();
 
         // Directory should exist but be empty
         assert!(output_path.exists());
@@ -224,7 +680,7 @@ mod tests {
 
         // Load → write round-trip
         let loaded = load_local_fs(source_dir.path()).unwrap();
-        execute(&loaded, output_dir.path()).unwrap();
+        execute(&loaded, output_dir.path(), WriteOptions::default()).unwrap();
 
         // Executable bit must survive the round-trip
         let written_script = output_dir.path().join("install.sh");
@@ -251,10 +707,152 @@ mod tests {
             .add_file_string("existing.txt", "new content")
             .unwrap();
 
-        execute(&memfs, output_path).unwrap();
+        execute(&memfs, output_path, WriteOptions::default()).unwrap();
 
         // File should be overwritten
         let content = fs::read_to_string(&existing_path).unwrap();
         assert_eq!(content, "new content");
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_phase6_refuses_to_write_through_symlinks() {
+        use crate::error::Error;
+        use std::os::unix::fs::symlink;
+
+        let outside = TempDir::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path();
+        fs::write(outside.path().join("target.md"), "keep").unwrap();
+        symlink(
+            outside.path().join("target.md"),
+            output_path.join("AGENTS.md"),
+        )
+        .unwrap();
+        symlink(outside.path(), output_path.join("docs")).unwrap();
+
+        let mut memfs = MemoryFS::new();
+        memfs.add_file_string("AGENTS.md", "upstream").unwrap();
+        memfs.add_file_string("docs/a.md", "a").unwrap();
+        memfs.add_file_string("docs/b.md", "b").unwrap();
+        memfs.add_file_string("README.md", "readme").unwrap();
+
+        let Err(Error::SymlinkedOutput { paths }) =
+            execute(&memfs, output_path, WriteOptions::default())
+        else {
+            panic!("expected the write to be refused");
+        };
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].starts_with("AGENTS.md -> "), "{:?}", paths);
+        assert!(paths[1].starts_with("docs -> "), "{:?}", paths);
+        assert!(!output_path.join("README.md").exists());
+        assert_eq!(
+            fs::read_to_string(outside.path().join("target.md")).unwrap(),
+            "keep"
+        );
+
+        let options = WriteOptions {
+            follow_symlinks: true,
+            ..WriteOptions::default()
+        };
+        execute(&memfs, output_path, options).unwrap();
+        assert_eq!(
+            fs::read_to_string(outside.path().join("target.md")).unwrap(),
+            "upstream"
+        );
+        assert!(outside.path().join("a.md").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_phase6_read_only_policies() {
+        use crate::error::Error;
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path();
+        for (name, content) in [("locked.txt", "old"), ("same.txt", "same")] {
+            let path = output_path.join(name);
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+        }
+
+        let mut memfs = MemoryFS::new();
+        memfs.add_file_string("locked.txt", "new").unwrap();
+        memfs.add_file_string("same.txt", "same").unwrap();
+        memfs.add_file_string("other.txt", "other").unwrap();
+
+        let Err(Error::ReadOnlyOutput { paths }) =
+            execute(&memfs, output_path, WriteOptions::default())
+        else {
+            panic!("expected the write to be refused");
+        };
+        assert_eq!(paths, vec!["locked.txt".to_string()]);
+        assert!(!output_path.join("other.txt").exists());
+
+        let with = |read_only| WriteOptions {
+            read_only,
+            ..WriteOptions::default()
+        };
+        let (result, warnings) = crate::diagnostics::collect(|| {
+            execute(&memfs, output_path, with(ReadOnlyPolicy::Skip))
+        });
+        result.unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            fs::read_to_string(output_path.join("locked.txt")).unwrap(),
+            "old"
+        );
+        assert!(output_path.join("other.txt").exists());
+
+        execute(&memfs, output_path, with(ReadOnlyPolicy::Overwrite)).unwrap();
+        let locked = output_path.join("locked.txt");
+        assert_eq!(fs::read_to_string(&locked).unwrap(), "new");
+        let mode = fs::metadata(&locked).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o444);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_phase6_protect_writes_managed_files_read_only() {
+        use crate::filesystem::{File, FileOrigin};
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path();
+        let managed = |content: &str| {
+            let mut file = File::from_string(content);
+            file.origin = Some(Arc::new(FileOrigin {
+                repo: Some("../upstream".to_string()),
+                ..FileOrigin::default()
+            }));
+            file
+        };
+        let protect = WriteOptions {
+            protect: true,
+            ..WriteOptions::default()
+        };
+
+        let mut memfs = MemoryFS::new();
+        memfs.add_file("ci.yml", managed("v1")).unwrap();
+        memfs.add_file_string("local.txt", "local").unwrap();
+        execute(&memfs, output_path, protect).unwrap();
+        let mode = |name: &str| {
+            fs::metadata(output_path.join(name))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        assert_eq!(mode("ci.yml"), 0o444);
+        assert_eq!(mode("local.txt"), 0o644);
+
+        // A later write updates the read-only file instead of refusing to
+        memfs.add_file("ci.yml", managed("v2")).unwrap();
+        execute(&memfs, output_path, protect).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_path.join("ci.yml")).unwrap(),
+            "v2"
+        );
+        assert_eq!(mode("ci.yml"), 0o444);
+    }
 }
@@ -0,0 +1,142 @@
+//! # Promoting Local Changes Upstream
+//!
+//! A fix made to a managed file in a consumer usually belongs in the
+//! upstream the file came from, so that every consumer gets it. [`promote`]
+//! carries such a change back: it diffs the content the configuration
+//! produces for the file against the consumer's edited copy, and applies
+//! the difference to the file's source in the upstream, which the file's
+//! origin names (see [`crate::filesystem::FileOrigin`]). Renames are
+//! followed that way, and the parts of the source the consumer did not
+//! change are left alone.
+//!
+//! Hunks must match the source the way the `patch` operation matches them.
+//! When they do not and the source is a template, the values its variables
+//! were given are turned back into `__COMMON_REPO__NAME__` sentinels in
+//! both versions first, so a change to a line with a substituted value
+//! applies too. Values on the lines the consumer added become sentinels
+//! as well. Changes to content that a merge or formatting produced cannot
+//! be carried back.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::config::PatchOp;
+use crate::filesystem::MemoryFS;
+use crate::operators::{patch, template};
+
+/// `source` with the change from `produced` to `local` applied, or `None`
+/// when the change does not apply to it.
+///
+/// `vars` are the values the source's template variables were given.
+/// `source` is returned as it is when the two versions are equal or it
+/// already has the change.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use common_repo::promote::promote;
+///
+/// let source = "# __COMMON_REPO__NAME__\n\nRun make.\n";
+/// let produced = "# billing\n\nRun make.\n";
+/// let local = "# billing\n\nRun make test.\n";
+/// let vars = HashMap::from([("NAME".to_string(), "billing".to_string())]);
+/// assert_eq!(
+///     promote(source, produced, local, &vars).unwrap(),
+///     "# __COMMON_REPO__NAME__\n\nRun make test.\n"
+/// );
+/// ```
+pub fn promote(
+    source: &str,
+    produced: &str,
+    local: &str,
+    vars: &HashMap<String, String>,
+) -> Option<String> {
+    if let Some(promoted) = apply_change(source, produced, local) {
+        return Some(promoted);
+    }
+    let names: BTreeSet<&str> = template::variables(source).collect();
+    let mut values: Vec<(&str, &str)> = names
+        .into_iter()
+        .filter_map(|name| {
+            let value = vars.get(name).filter(|value| !value.is_empty())?;
+            Some((name, value.as_str()))
+        })
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    // Longer values first, so that one containing another is replaced whole
+    values.sort_by_key(|(_, value)| Reverse(value.len()));
+    let untemplate = |text: &str| {
+        values.iter().fold(text.to_string(), |text, (name, value)| {
+            text.replace(value, &format!("__COMMON_REPO__{}__", name))
+        })
+    };
+    apply_change(source, &untemplate(produced), &untemplate(local))
+}
+
+/// `source` with the diff from `old` to `new` applied, as the `patch`
+/// operation applies it.
+fn apply_change(source: &str, old: &str, new: &str) -> Option<String> {
+    const PATH: &str = "file";
+    let single = |content: &str| {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string(PATH, content).ok().map(|()| fs)
+    };
+    let diff = single(old)?.diff(&single(new)?);
+    let Some(entry) = diff.iter().next() else {
+        return Some(source.to_string());
+    };
+    let unified = entry.unified_diff(3)?;
+    let mut fs = single(source)?;
+    let op = PatchOp {
+        file: PATH.to_string(),
+        patch: "local change".to_string(),
+    };
+    patch::apply(&op, &unified, &mut fs).ok()?;
+    String::from_utf8(fs.get_file(PATH)?.content.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_promote_applies_change_to_source() {
+        let source = "a\nb\nc\nd\ne\nf\n";
+        let produced = "a\nb\nc\nd\ne\nf\nadded by a merge\n";
+        let local = "a\nB\nc\nd\ne\nf\nadded by a merge\n";
+        let vars = HashMap::new();
+        assert_eq!(
+            promote(source, produced, local, &vars).unwrap(),
+            "a\nB\nc\nd\ne\nf\n"
+        );
+        assert_eq!(promote(source, produced, produced, &vars).unwrap(), source);
+        // The change is already upstream
+        assert_eq!(promote(local, produced, local, &vars).unwrap(), local);
+    }
+
+    #[test]
+    fn test_promote_reverses_templating() {
+        let source = "name: __COMMON_REPO__NAME__\nowner: __COMMON_REPO__OWNER__\nlevel: 1\n";
+        let produced = "name: billing-api\nowner: billing\nlevel: 1\n";
+        let local = "name: billing-api\nowner: billing\nlevel: 2\n";
+        let vars = HashMap::from([
+            ("NAME".to_string(), "billing-api".to_string()),
+            ("OWNER".to_string(), "billing".to_string()),
+        ]);
+        assert_eq!(
+            promote(source, produced, local, &vars).unwrap(),
+            "name: __COMMON_REPO__NAME__\nowner: __COMMON_REPO__OWNER__\nlevel: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_promote_fails_on_content_not_in_source() {
+        let source = "a\n";
+        let produced = "a\nmerged: 1\n";
+        let local = "a\nmerged: 2\n";
+        assert!(promote(source, produced, local, &HashMap::new()).is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! # Managed File Protection
+//!
+//! A consumer that opts in with `- protect: pre-commit` (see
+//! [`crate::config::Protection`]) gets a git pre-commit hook that rejects
+//! commits changing files managed by common-repo, so that changes are made
+//! upstream instead.
+//!
+//! The hook is written by every `apply`, with the SHA-256 of each managed
+//! file as apply wrote it. A commit whose staged version of a managed file
+//! has another hash, or that deletes one, is rejected; committing what
+//! apply wrote passes. The hook only needs `git` and `sha256sum` or
+//! `shasum`, and `git commit --no-verify` skips it.
+//!
+//! A pre-commit hook that common-repo did not write is never replaced, and
+//! [`remove_hook`] only removes one that it did.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Error, Result};
+use crate::filesystem::MemoryFS;
+use crate::http::sha256_hex;
+
+/// The line that marks a hook as written by common-repo.
+pub const HOOK_MARKER: &str = "# Generated by common-repo apply; rewritten on every apply.";
+
+/// What [`install_hook`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// The hook was written to this path.
+    Installed(PathBuf),
+    /// Another pre-commit hook is at this path, and was left alone.
+    Foreign(PathBuf),
+    /// The directory is not in a git work tree.
+    NotGit,
+}
+
+/// The pre-commit hook guarding the managed files of `final_fs`, whose
+/// paths are prefixed with `prefix` to make them relative to the top of
+/// the work tree.
+pub fn pre_commit_hook(final_fs: &MemoryFS, prefix: &str) -> String {
+    let mut hook = format!(
+        "#!/bin/sh\n\
+         {}\n\
+         # Rejects commits that change files managed by common-repo.\n\
+         \n\
+         expected() {{\n    case \"$1\" in\n",
+        HOOK_MARKER
+    );
+    for (path, file) in final_fs.files() {
+        if !file.is_managed() {
+            continue;
+        }
+        let path = format!("{}{}", prefix, path.to_string_lossy().replace('\\', "/"));
+        hook.push_str(&format!(
+            "    {}) echo {} ;;\n",
+            shell_quote(&path),
+            sha256_hex(&file.content)
+        ));
+    }
+    hook.push_str(
+        r#"    *) return 1 ;;
+    esac
+}
+
+if command -v sha256sum >/dev/null 2>&1; then
+    hash() { sha256sum | cut -d ' ' -f 1; }
+else
+    hash() { shasum -a 256 | cut -d ' ' -f 1; }
+fi
+
+rejected=$(git -c core.quotePath=false diff --cached --name-only --no-renames |
+    while IFS= read -r path; do
+        want=$(expected "$path") || continue
+        if git cat-file -e ":$path" 2>/dev/null; then
+            have=$(git cat-file blob ":$path" | hash)
+        else
+            have=deleted
+        fi
+        [ "$have" = "$want" ] || echo "  $path"
+    done)
+
+if [ -n "$rejected" ]; then
+    echo "common-repo: these files are managed by common-repo; change them upstream instead:" >&2
+    echo "$rejected" >&2
+    echo "Run 'common-repo apply' to restore them, or commit with --no-verify." >&2
+    exit 1
+fi
+"#,
+    );
+    hook
+}
+
+/// Write the pre-commit hook guarding the managed files of `final_fs`
+/// into the git repository containing `dir`.
+///
+/// # Errors
+///
+/// Returns `Error::Filesystem` if the hook cannot be written.
+pub fn install_hook(dir: &Path, final_fs: &MemoryFS) -> Result<HookOutcome> {
+    let Some(path) = hook_path(dir) else {
+        return Ok(HookOutcome::NotGit);
+    };
+    if path.exists() && !is_ours(&path) {
+        return Ok(HookOutcome::Foreign(path));
+    }
+    let prefix = git(dir, &["rev-parse", "--show-prefix"]).unwrap_or_default();
+    let write_error = |e: std::io::Error| Error::Filesystem {
+        message: format!("Failed to write '{}': {}", path.display(), e),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(write_error)?;
+    }
+    std::fs::write(&path, pre_commit_hook(final_fs, &prefix)).map_err(write_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .map_err(write_error)?;
+    }
+    Ok(HookOutcome::Installed(path))
+}
+
+/// Remove the pre-commit hook of the git repository containing `dir` if
+/// common-repo wrote it. Returns whether there was one to remove.
+///
+/// # Errors
+///
+/// Returns `Error::Filesystem` if the hook cannot be removed.
+pub fn remove_hook(dir: &Path) -> Result<bool> {
+    let Some(path) = hook_path(dir).filter(|path| is_ours(path)) else {
+        return Ok(false);
+    };
+    std::fs::remove_file(&path).map_err(|e| Error::Filesystem {
+        message: format!("Failed to remove '{}': {}", path.display(), e),
+    })?;
+    Ok(true)
+}
+
+/// The path of the pre-commit hook of the git repository containing
+/// `dir`, or `None` outside one.
+fn hook_path(dir: &Path) -> Option<PathBuf> {
+    let path = PathBuf::from(git(dir, &["rev-parse", "--git-path", "hooks/pre-commit"])?);
+    Some(if path.is_absolute() {
+        path
+    } else {
+        dir.join(path)
+    })
+}
+
+/// Whether the hook at `path` was written by common-repo.
+fn is_ours(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|content| content.contains(HOOK_MARKER))
+}
+
+/// The trimmed output of `git -C dir args`, or `None` if it fails.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `value` quoted for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{File, FileOrigin};
+    use std::sync::Arc;
+
+    fn managed(content: &str) -> File {
+        let mut file = File::from_string(content);
+        file.origin = Some(Arc::new(FileOrigin {
+            repo: Some("../upstream".to_string()),
+            ..FileOrigin::default()
+        }));
+        file
+    }
+
+    #[test]
+    fn test_hook_lists_only_managed_files() {
+        let mut fs = MemoryFS::new();
+        fs.add_file("ci's.yml", managed("on: push\n")).unwrap();
+        fs.add_file_string("main.rs", "fn main() {}\n").unwrap();
+        let hook = pre_commit_hook(&fs, "app/");
+        assert!(hook.starts_with("#!/bin/sh\n"));
+        assert!(hook.contains(HOOK_MARKER));
+        assert!(hook.contains(&format!(
+            "    'app/ci'\\''s.yml') echo {} ;;\n",
+            sha256_hex(b"on: push\n")
+        )));
+        assert!(!hook.contains("main.rs"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_rejects_changes_to_managed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        run(&["init", "-q"]);
+        let mut fs = MemoryFS::new();
+        fs.add_file("ci.yml", managed("on: push\n")).unwrap();
+        std::fs::write(dir.path().join("ci.yml"), "on: push\n").unwrap();
+
+        let HookOutcome::Installed(path) = install_hook(dir.path(), &fs).unwrap() else {
+            panic!("expected the hook to be installed");
+        };
+        run(&["add", "ci.yml"]);
+        assert!(run(&["commit", "-q", "-m", "apply"]).status.success());
+
+        std::fs::write(dir.path().join("ci.yml"), "on: pull_request\n").unwrap();
+        run(&["add", "ci.yml"]);
+        let rejected = run(&["commit", "-q", "-m", "edit"]);
+        assert!(!rejected.status.success());
+        assert!(String::from_utf8_lossy(&rejected.stderr).contains("  ci.yml"));
+
+        assert!(remove_hook(dir.path()).unwrap());
+        assert!(!path.exists());
+        std::fs::write(&path, "#!/bin/sh\nexit 0\n").unwrap();
+        assert_eq!(
+            install_hook(dir.path(), &fs).unwrap(),
+            HookOutcome::Foreign(path.clone())
+        );
+        assert!(!remove_hook(dir.path()).unwrap());
+    }
+}
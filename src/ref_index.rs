@@ -0,0 +1,302 @@
+//! # Ref Aliases
+//!
+//! A `repo:` operation with a `ref-index:` (its own or the config's
+//! `- ref-index:` entry) looks its `ref` up in an index file published in a
+//! repository, usually one per organization:
+//!
+//! ```yaml
+//! https://github.com/acme/ci-config:
+//!   stable: v2.3.1
+//!   next: v3.0.0-rc.1
+//! ```
+//!
+//! With this index, `ref: stable` fetches `v2.3.1`. Promoting a new version
+//! to every consumer is a commit to the index instead of a pull request to
+//! each of them. The index is keyed by the upstream URL exactly as the
+//! `repo:` operation writes it, and a ref it does not list is used as
+//! written. Local upstreams have no ref and are never looked up.
+//!
+//! Each index is fetched once per run, bypassing the cache so a promotion
+//! takes effect on the next run. When it cannot be fetched, the cached copy
+//! is used with a warning. Relative local paths to an index are resolved
+//! against the directory of the configuration that names it.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use crate::config::{Operation, RefIndex, Schema};
+use crate::diagnostics;
+use crate::error::{Error, ErrorPhase, Result};
+use crate::messages::Message;
+use crate::repository::{is_local_url, RepositoryManager};
+
+/// The index file read when a `ref-index:` has no `path`.
+pub const DEFAULT_INDEX_FILE: &str = "index.yaml";
+
+/// The ref of the index read when a `ref-index:` has no `ref`.
+pub const DEFAULT_INDEX_REF: &str = "main";
+
+/// The aliases of each upstream URL.
+type Aliases = BTreeMap<String, BTreeMap<String, String>>;
+
+/// An index repository, its ref and the path of the index file in it.
+/// Local URLs are canonical.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IndexLocation {
+    /// Repository URL, or the canonical path of a local index.
+    pub url: String,
+    /// The ref of the index, empty for local paths.
+    pub ref_: String,
+    /// Path of the index file in the repository.
+    pub path: String,
+}
+
+impl IndexLocation {
+    /// Where `index` is, resolving a relative local path against `base_dir`.
+    fn of(index: &RefIndex, base_dir: &Path) -> Result<Self> {
+        let path = index
+            .path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INDEX_FILE.to_string());
+        if !is_local_url(&index.url) {
+            return Ok(Self {
+                url: index.url.clone(),
+                ref_: index
+                    .r#ref
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_INDEX_REF.to_string()),
+                path,
+            });
+        }
+        let candidate = if index.url.starts_with('/') {
+            PathBuf::from(&index.url)
+        } else {
+            base_dir.join(&index.url)
+        };
+        let canonical =
+            std::fs::canonicalize(&candidate).map_err(|e| Error::LocalPathNotFound {
+                original: index.url.clone(),
+                attempted: candidate,
+                source: e,
+            })?;
+        Ok(Self {
+            url: canonical.to_string_lossy().into_owned(),
+            ref_: String::new(),
+            path,
+        })
+    }
+
+    fn origin(&self) -> String {
+        if self.ref_.is_empty() {
+            format!("{}/{}", self.url, self.path)
+        } else {
+            format!("{}@{}:{}", self.url, self.ref_, self.path)
+        }
+    }
+}
+
+/// The indexes read so far in this process.
+static LOADED: LazyLock<Mutex<BTreeMap<IndexLocation, Aliases>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Every index read so far in this process, so that the fingerprint of an
+/// apply changes when one of them does.
+pub fn loaded() -> Vec<IndexLocation> {
+    LOADED
+        .lock()
+        .map(|loaded| loaded.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// `config` with the ref of each `repo:` operation that is an alias in its
+/// `ref-index:` replaced by the ref it maps to.
+///
+/// `base_dir` is the directory of the configuration, which relative local
+/// paths to an index are resolved against.
+pub fn resolve(
+    config: &Schema,
+    base_dir: &Path,
+    repo_manager: &RepositoryManager,
+) -> Result<Schema> {
+    let mut config = config.clone();
+    resolve_in(&mut config, base_dir, repo_manager)?;
+    Ok(config)
+}
+
+fn resolve_in(
+    operations: &mut Schema,
+    base_dir: &Path,
+    repo_manager: &RepositoryManager,
+) -> Result<()> {
+    for operation in operations {
+        match operation {
+            Operation::Repo { repo } => {
+                resolve_in(&mut repo.with, base_dir, repo_manager)?;
+                if repo.is_local() {
+                    continue;
+                }
+                let (Some(index), Some(alias)) = (&repo.ref_index, &repo.r#ref) else {
+                    continue;
+                };
+                let location = IndexLocation::of(index, base_dir)?;
+                let aliases = load(&location, repo_manager)?;
+                let Some(target) = aliases.get(&repo.url).and_then(|refs| refs.get(alias)) else {
+                    continue;
+                };
+                log::debug!(
+                    "{}",
+                    Message::ResolvedRefAlias {
+                        url: &repo.url,
+                        alias,
+                        ref_: target,
+                        index: &location.origin(),
+                    }
+                );
+                repo.r#ref = Some(target.clone());
+            }
+            Operation::Self_ { self_ } => {
+                resolve_in(&mut self_.operations, base_dir, repo_manager)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The aliases in the index at `location`, fetching it the first time.
+fn load(location: &IndexLocation, repo_manager: &RepositoryManager) -> Result<Aliases> {
+    if let Some(aliases) = LOADED
+        .lock()
+        .ok()
+        .and_then(|loaded| loaded.get(location).cloned())
+    {
+        return Ok(aliases);
+    }
+
+    let origin = location.origin();
+    let fs = match repo_manager.fetch_repository_fresh(&location.url, &location.ref_) {
+        Ok(fs) => fs,
+        Err(e) if e.is_fetch_failure() => {
+            let Ok(fs) = repo_manager.fetch_repository(&location.url, &location.ref_) else {
+                return Err(e);
+            };
+            diagnostics::warn(
+                ErrorPhase::Discovery,
+                Message::StaleRefIndex {
+                    index: &origin,
+                    error: &e.to_string(),
+                }
+                .to_string(),
+            );
+            fs
+        }
+        Err(e) => return Err(e),
+    };
+    let file = fs
+        .get_file(&location.path)
+        .ok_or_else(|| Error::ConfigParse {
+            message: format!("Ref index {} not found", origin),
+            hint: Some("Check the `url`, `ref` and `path` of the `ref-index:` entry".to_string()),
+        })?;
+    let aliases = parse(&file.content).map_err(|e| Error::ConfigParse {
+        message: format!("Invalid ref index {}: {}", origin, e),
+        hint: Some("The index maps each upstream URL to a mapping of aliases to refs".to_string()),
+    })?;
+
+    if let Ok(mut loaded) = LOADED.lock() {
+        loaded.insert(location.clone(), aliases.clone());
+    }
+    Ok(aliases)
+}
+
+fn parse(content: &[u8]) -> std::result::Result<Aliases, serde_yaml::Error> {
+    if content.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Aliases::new());
+    }
+    serde_yaml::from_slice(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoOp;
+    use tempfile::TempDir;
+
+    fn repo_op(url: &str, ref_: &str, index: &Path) -> Operation {
+        Operation::Repo {
+            repo: RepoOp {
+                url: url.to_string(),
+                r#ref: Some(ref_.to_string()),
+                path: None,
+                with: vec![],
+                disable: vec![],
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: Some(RefIndex {
+                    url: index.to_string_lossy().into_owned(),
+                    r#ref: None,
+                    path: None,
+                }),
+            },
+        }
+    }
+
+    fn ref_of(operation: &Operation) -> Option<&str> {
+        match operation {
+            Operation::Repo { repo } => repo.r#ref.as_deref(),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_replaces_aliases() {
+        let index = TempDir::new().unwrap();
+        std::fs::write(
+            index.path().join(DEFAULT_INDEX_FILE),
+            "https://github.com/acme/ci-config:\n  stable: v2.3.1\n",
+        )
+        .unwrap();
+        let config = vec![
+            repo_op("https://github.com/acme/ci-config", "stable", index.path()),
+            repo_op("https://github.com/acme/ci-config", "v1.0.0", index.path()),
+            repo_op("https://github.com/acme/other", "stable", index.path()),
+        ];
+        let manager = RepositoryManager::new(index.path().join("cache"));
+
+        let resolved = resolve(&config, index.path(), &manager).unwrap();
+        assert_eq!(ref_of(&resolved[0]), Some("v2.3.1"));
+        assert_eq!(ref_of(&resolved[1]), Some("v1.0.0"));
+        assert_eq!(ref_of(&resolved[2]), Some("stable"));
+    }
+
+    #[test]
+    fn test_resolve_reports_missing_index_file() {
+        let index = TempDir::new().unwrap();
+        let config = vec![repo_op(
+            "https://github.com/acme/ci-config",
+            "stable",
+            index.path(),
+        )];
+        let manager = RepositoryManager::new(index.path().join("cache"));
+
+        let err = resolve(&config, index.path(), &manager).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_reports_malformed_index() {
+        let index = TempDir::new().unwrap();
+        std::fs::write(index.path().join(DEFAULT_INDEX_FILE), "- stable\n").unwrap();
+        let config = vec![repo_op(
+            "https://github.com/acme/ci-config",
+            "stable",
+            index.path(),
+        )];
+        let manager = RepositoryManager::new(index.path().join("cache"));
+
+        let err = resolve(&config, index.path(), &manager).unwrap_err();
+        assert!(err.to_string().contains("Invalid ref index"), "{}", err);
+    }
+}
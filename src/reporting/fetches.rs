@@ -0,0 +1,147 @@
+//! # Repository Fetches
+//!
+//! Which repositories a run fetched, whether each came from the on-disk
+//! cache or was cloned, and how long it took. [`RepositoryManager`] records
+//! every fetch; `apply` saves them to `last-run.json` in the cache root
+//! after each run, and `info --cache` shows them, to help find out why a run
+//! was slow or used stale content.
+//!
+//! [`RepositoryManager`]: crate::repository::RepositoryManager
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// Where the last run's fetches are saved, relative to the cache root.
+pub const LAST_RUN_FILE: &str = "last-run.json";
+
+/// Where a fetched repository came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchOutcome {
+    /// The on-disk cache had it.
+    Cached,
+    /// It was cloned or downloaded into the cache.
+    Cloned,
+    /// It is a local directory, which is never cached.
+    Local,
+}
+
+/// One fetch of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fetch {
+    /// URL or path of the repository.
+    pub url: String,
+    /// The ref fetched.
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    /// The sub-path loaded, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Where it came from.
+    pub outcome: FetchOutcome,
+    /// The commit the cache entry holds, for a git repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// How long the fetch took, including loading the files, in
+    /// milliseconds.
+    pub millis: u64,
+}
+
+impl Fetch {
+    /// How long the fetch took.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+}
+
+/// The fetches of the last run, as saved in [`LAST_RUN_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRun {
+    /// When the run finished, in seconds since the Unix epoch.
+    pub finished: u64,
+    /// The fetches, in the order they finished.
+    pub fetches: Vec<Fetch>,
+}
+
+impl LastRun {
+    /// A run that finished now with `fetches`.
+    pub fn new(fetches: Vec<Fetch>) -> Self {
+        let finished = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self { finished, fetches }
+    }
+
+    /// How many fetches `outcome` describes.
+    pub fn count(&self, outcome: FetchOutcome) -> usize {
+        self.fetches
+            .iter()
+            .filter(|fetch| fetch.outcome == outcome)
+            .count()
+    }
+
+    /// When the run finished.
+    pub fn finished_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.finished)
+    }
+
+    fn path(cache_root: &Path) -> PathBuf {
+        cache_root.join(LAST_RUN_FILE)
+    }
+
+    /// Save the run to the cache root. The fetches are only for
+    /// troubleshooting, so a failure to save them is logged and ignored.
+    pub fn save(&self, cache_root: &Path) {
+        let path = Self::path(cache_root);
+        let result = std::fs::create_dir_all(cache_root)
+            .map_err(|e| e.to_string())
+            .and_then(|()| serde_json::to_vec_pretty(self).map_err(|e| e.to_string()))
+            .and_then(|content| std::fs::write(&path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            debug!("failed to save {}: {}", path.display(), e);
+        }
+    }
+
+    /// The run saved in the cache root, if one was saved and can be read.
+    pub fn load(cache_root: &Path) -> Option<Self> {
+        let content = std::fs::read(Self::path(cache_root)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(url: &str, outcome: FetchOutcome, millis: u64) -> Fetch {
+        Fetch {
+            url: url.to_string(),
+            ref_: "main".to_string(),
+            path: None,
+            outcome,
+            commit: None,
+            millis,
+        }
+    }
+
+    #[test]
+    fn test_last_run_round_trip() {
+        let cache = tempfile::tempdir().unwrap();
+        assert!(LastRun::load(cache.path()).is_none());
+
+        let run = LastRun::new(vec![
+            fetch("https://github.com/acme/ci", FetchOutcome::Cloned, 1200),
+            fetch("https://github.com/acme/base", FetchOutcome::Cached, 4),
+            fetch("../local", FetchOutcome::Local, 1),
+        ]);
+        run.save(cache.path());
+        let loaded = LastRun::load(cache.path()).unwrap();
+        assert_eq!(loaded, run);
+        assert_eq!(loaded.count(FetchOutcome::Cached), 1);
+        assert_eq!(loaded.count(FetchOutcome::Cloned), 1);
+        assert_eq!(loaded.fetches[0].elapsed(), Duration::from_millis(1200));
+    }
+}
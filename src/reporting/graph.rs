@@ -0,0 +1,241 @@
+//! # Inheritance Graph Export
+//!
+//! Renders a discovered [`RepoTree`] as a Graphviz DOT or Mermaid graph,
+//! for `common-repo tree --format dot|mermaid`.
+//!
+//! Each repository is one node, labelled with its URL and the ref it is
+//! pinned to, and each edge points from a configuration to a repository it
+//! inherits from. A repository inherited along several paths at the same
+//! ref appears once. When the same URL is pinned to different refs in the
+//! tree, every node for it is marked as a conflict and drawn in red.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::phases::{RepoNode, RepoTree};
+//! use common_repo::reporting::graph;
+//!
+//! let mut root = RepoNode::new("local".into(), "HEAD".into(), vec![]);
+//! root.add_child(RepoNode::new("https://github.com/org/base".into(), "v1.0.0".into(), vec![]));
+//! let tree = RepoTree::new(root);
+//!
+//! let dot = graph::to_dot(&tree, None);
+//! assert!(dot.contains("n0 -> n1;"));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::phases::{RepoNode, RepoTree};
+
+/// A repository in the graph.
+struct Node {
+    label: String,
+    conflict: bool,
+}
+
+/// The repositories of a tree, deduplicated by URL and ref, with the
+/// inheritance edges between them.
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    fn build(tree: &RepoTree, max_depth: Option<usize>) -> Self {
+        let mut builder = Builder::default();
+        builder.id_of(&tree.root);
+        builder.visit(&tree.root, max_depth.unwrap_or(usize::MAX), 0);
+
+        // A URL pinned to more than one ref is a conflict.
+        let mut refs: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (url, ref_) in &builder.keys {
+            if url != "local" {
+                refs.entry(url).or_default().insert(ref_);
+            }
+        }
+        let nodes = builder
+            .keys
+            .iter()
+            .zip(builder.labels)
+            .map(|((url, _), label)| Node {
+                label,
+                conflict: refs.get(url.as_str()).is_some_and(|refs| refs.len() > 1),
+            })
+            .collect();
+        Graph {
+            nodes,
+            edges: builder.edges,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    /// `(url, ref)` of each node, in the order they were first reached.
+    keys: Vec<(String, String)>,
+    labels: Vec<String>,
+    index: HashMap<(String, String), usize>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Builder {
+    fn visit(&mut self, node: &RepoNode, max_depth: usize, depth: usize) {
+        if depth >= max_depth {
+            return;
+        }
+        let id = self.id_of(node);
+        for child in &node.children {
+            let edge = (id, self.id_of(child));
+            if !self.edges.contains(&edge) {
+                self.edges.push(edge);
+            }
+            self.visit(child, max_depth, depth + 1);
+        }
+    }
+
+    fn id_of(&mut self, node: &RepoNode) -> usize {
+        let key = (node.url.clone(), node.ref_.clone());
+        if let Some(&id) = self.index.get(&key) {
+            return id;
+        }
+        let id = self.keys.len();
+        self.labels.push(label(node));
+        self.keys.push(key.clone());
+        self.index.insert(key, id);
+        id
+    }
+}
+
+fn label(node: &RepoNode) -> String {
+    let url = node.original_url.as_deref().unwrap_or(&node.url);
+    if node.ref_.is_empty() {
+        url.to_string()
+    } else {
+        format!("{} @ {}", url, node.ref_)
+    }
+}
+
+/// Render `tree` as a Graphviz DOT digraph.
+///
+/// `max_depth` limits how many levels of inheritance are drawn, as with
+/// `tree --depth`; `None` draws the whole tree.
+pub fn to_dot(tree: &RepoTree, max_depth: Option<usize>) -> String {
+    let graph = Graph::build(tree, max_depth);
+    let mut out = String::from("digraph common_repo {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (id, node) in graph.nodes.iter().enumerate() {
+        let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+        if node.conflict {
+            out.push_str(&format!(
+                "    n{} [label=\"{}\", color=red, fontcolor=red];\n",
+                id, label
+            ));
+        } else {
+            out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+        }
+    }
+    for (from, to) in &graph.edges {
+        out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render `tree` as a Mermaid flowchart.
+///
+/// `max_depth` works as for [`to_dot`].
+pub fn to_mermaid(tree: &RepoTree, max_depth: Option<usize>) -> String {
+    let graph = Graph::build(tree, max_depth);
+    let mut out = String::from("graph LR\n");
+    for (id, node) in graph.nodes.iter().enumerate() {
+        out.push_str(&format!(
+            "    n{}[\"{}\"]\n",
+            id,
+            node.label.replace('"', "#quot;")
+        ));
+    }
+    for (from, to) in &graph.edges {
+        out.push_str(&format!("    n{} --> n{}\n", from, to));
+    }
+    let conflicts: Vec<String> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.conflict)
+        .map(|(id, _)| format!("n{}", id))
+        .collect();
+    if !conflicts.is_empty() {
+        out.push_str("    classDef conflict stroke:#d00,color:#d00\n");
+        out.push_str(&format!("    class {} conflict\n", conflicts.join(",")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(url: &str, ref_: &str, children: Vec<RepoNode>) -> RepoNode {
+        let mut node = RepoNode::new(url.to_string(), ref_.to_string(), vec![]);
+        node.children = children;
+        node
+    }
+
+    /// local inherits a@v1 and b@v1; b inherits a@v2 and c@v1, which a@v1
+    /// also inherits.
+    fn conflicting_tree() -> RepoTree {
+        let c = repo("https://example.com/c", "v1", vec![]);
+        let a1 = repo("https://example.com/a", "v1", vec![c.clone()]);
+        let a2 = repo("https://example.com/a", "v2", vec![]);
+        let b = repo("https://example.com/b", "v1", vec![a2, c]);
+        RepoTree::new(repo("local", "HEAD", vec![a1, b]))
+    }
+
+    #[test]
+    fn test_to_dot_marks_conflicting_refs() {
+        assert_eq!(
+            to_dot(&conflicting_tree(), None),
+            "digraph common_repo {
+    rankdir=LR;
+    node [shape=box];
+    n0 [label=\"local @ HEAD\"];
+    n1 [label=\"https://example.com/a @ v1\", color=red, fontcolor=red];
+    n2 [label=\"https://example.com/c @ v1\"];
+    n3 [label=\"https://example.com/b @ v1\"];
+    n4 [label=\"https://example.com/a @ v2\", color=red, fontcolor=red];
+    n0 -> n1;
+    n1 -> n2;
+    n0 -> n3;
+    n3 -> n4;
+    n3 -> n2;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid_marks_conflicting_refs() {
+        let mermaid = to_mermaid(&conflicting_tree(), None);
+        assert!(mermaid.starts_with("graph LR\n    n0[\"local @ HEAD\"]\n"));
+        assert!(mermaid.contains("    n3 --> n2\n"));
+        assert!(mermaid.ends_with("    class n1,n4 conflict\n"));
+    }
+
+    #[test]
+    fn test_max_depth_limits_graph() {
+        let dot = to_dot(&conflicting_tree(), Some(1));
+        assert!(dot.contains("n0 -> n2;"));
+        assert!(!dot.contains("https://example.com/c"));
+        assert!(!dot.contains("@ v2"));
+        assert!(!dot.contains("color=red"));
+
+        let mermaid = to_mermaid(&conflicting_tree(), Some(0));
+        assert_eq!(mermaid, "graph LR\n    n0[\"local @ HEAD\"]\n");
+    }
+
+    #[test]
+    fn test_labels_are_escaped() {
+        let tree = RepoTree::new(repo("local", "HEAD", vec![repo("./a \"b\"", "", vec![])]));
+        assert!(to_dot(&tree, None).contains("n1 [label=\"./a \\\"b\\\"\"];"));
+        assert!(to_mermaid(&tree, None).contains("n1[\"./a #quot;b#quot;\"]"));
+    }
+}
@@ -0,0 +1,230 @@
+//! # Apply History
+//!
+//! A local audit trail of who propagated what and when. Every successful
+//! `apply` that writes to disk appends one line to
+//! `.common-repo/history.jsonl` in the output directory, and
+//! `common-repo history` shows them.
+//!
+//! Each entry records when the apply ran and who ran it (the git identity
+//! of the output directory, or the login name without one; left out when
+//! neither is known), the SHA-256 of the configuration, the commit of every
+//! upstream fetched, and the managed files the apply added, changed or
+//! removed. The configuration hash and the upstream commits together pin
+//! down exactly what was applied.
+//!
+//! There is no hash of the resolved refs: there is no lock file to hash,
+//! and the entry lists every upstream's commit in full, which a hash would
+//! only repeat. Upstreams without a commit, such as local directories and
+//! downloaded files, are recorded by URL and ref alone.
+//!
+//! ```json
+//! {"time":"2026-10-17T09:12:44Z","user":"Ada <ada@example.com>","config":"5f0c…","upstreams":[{"url":"https://github.com/acme/ci-templates","ref":"v2.1.0","commit":"9c1e…"}],"files":[".github/workflows/ci.yml"]}
+//! ```
+//!
+//! The file is only ever appended to, so it can be committed and reviewed
+//! like any other file, and merges of it rarely conflict.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::reporting::fetches::Fetch;
+
+/// Where the history is kept, relative to the output directory.
+pub const HISTORY_FILE: &str = ".common-repo/history.jsonl";
+
+/// One successful apply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the apply finished, in RFC 3339 format, UTC.
+    pub time: String,
+    /// Who ran it, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// The named pipeline applied, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    /// SHA-256 of the configuration.
+    pub config: String,
+    /// Every upstream fetched, in the order they were fetched.
+    #[serde(default)]
+    pub upstreams: Vec<UpstreamCommit>,
+    /// The managed files added, changed or removed, sorted by path.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+}
+
+/// An upstream and the commit it was applied at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamCommit {
+    /// URL or path of the repository.
+    pub url: String,
+    /// The ref as written in the configuration.
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    /// The commit the ref pointed to, for a git repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl HistoryEntry {
+    /// An entry for an apply that just finished, run by `user` with the
+    /// configuration whose SHA-256 is `config`.
+    pub fn now(user: Option<String>, config: impl Into<String>) -> Self {
+        Self {
+            time: current_time(),
+            user,
+            pipeline: None,
+            config: config.into(),
+            upstreams: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+}
+
+/// Who is applying into `dir`: the git identity there, or the login name
+/// when git has none. `None` when neither is known.
+pub fn current_user(dir: &Path) -> Option<String> {
+    crate::git::user_identity(dir)
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .filter(|user| !user.is_empty())
+}
+
+/// The distinct upstreams of `fetches`, in the order they were fetched.
+pub fn upstream_commits(fetches: Vec<Fetch>) -> Vec<UpstreamCommit> {
+    let mut upstreams = Vec::new();
+    for fetch in fetches {
+        let upstream = UpstreamCommit {
+            url: fetch.url,
+            ref_: fetch.ref_,
+            commit: fetch.commit,
+        };
+        if !upstreams.contains(&upstream) {
+            upstreams.push(upstream);
+        }
+    }
+    upstreams
+}
+
+/// Append `entry` to the history of the output directory `dir`.
+pub fn append(dir: &Path, entry: &HistoryEntry) -> Result<()> {
+    let path = dir.join(HISTORY_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry).expect("a history entry is serializable");
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// The history of the output directory `dir`, oldest first. A directory
+/// without one has an empty history.
+pub fn load(dir: &Path) -> Result<Vec<HistoryEntry>> {
+    let path = dir.join(HISTORY_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| Error::Filesystem {
+                message: format!("{}:{}: not a history entry: {}", path.display(), i + 1, e),
+            })
+        })
+        .collect()
+}
+
+/// The current time as an RFC 3339 timestamp in UTC.
+pub fn current_time() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    timestamp(secs)
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 timestamp in UTC, such as
+/// `2026-10-17T09:12:44Z`.
+pub fn timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(1_792_228_364), "2026-10-17T09:12:44Z");
+    }
+
+    #[test]
+    fn test_history_appends_entries() {
+        let temp = TempDir::new().unwrap();
+        assert!(load(temp.path()).unwrap().is_empty());
+
+        let mut first = HistoryEntry::now(Some("Ada <ada@example.com>".to_string()), "abc");
+        first.upstreams.push(UpstreamCommit {
+            url: "https://github.com/acme/ci".to_string(),
+            ref_: "v1".to_string(),
+            commit: Some("0123".to_string()),
+        });
+        first.files.push(PathBuf::from("ci.yml"));
+        let mut second = HistoryEntry::now(None, "def");
+        second.pipeline = Some("docs".to_string());
+        append(temp.path(), &first).unwrap();
+        append(temp.path(), &second).unwrap();
+
+        assert_eq!(load(temp.path()).unwrap(), vec![first, second]);
+        let content = fs::read_to_string(temp.path().join(HISTORY_FILE)).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert!(lines[0].contains(r#""user":"Ada <ada@example.com>""#));
+        assert!(!lines[1].contains("user"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_history_rejects_malformed_line() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(HISTORY_FILE);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{\"time\":\"x\"}\nnot json\n").unwrap();
+        let err = load(temp.path()).unwrap_err().to_string();
+        assert!(err.contains(":1:"), "{}", err);
+    }
+}
@@ -0,0 +1,205 @@
+//! # Managed File Map
+//!
+//! Which upstream file each managed file came from, written to
+//! `.common-repo/map.json` on every apply so that editor plugins can tell
+//! a user that a file is managed by common-repo and should be edited
+//! upstream instead.
+//!
+//! The map is keyed by destination path. Each entry names the repository
+//! and ref the file was included from, its path there, and the operations
+//! that changed it on the way: `rename` when the path differs, `template`
+//! when variables were substituted, and `merge` when it was merged with
+//! another version of the file. Files that did not come from an upstream,
+//! such as the consumer's own files, are not in the map. When `apply --into`
+//! placed the output under a subdirectory, the paths start with it, and
+//! placing a file there does not count as a rename.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "files": {
+//!     ".github/workflows/ci.yml": {
+//!       "repo": "https://github.com/acme/ci-templates",
+//!       "ref": "v2.1.0",
+//!       "source": "workflows/ci.yml",
+//!       "operations": ["rename", "template"]
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::filesystem::MemoryFS;
+
+/// Where the map is written, relative to the output directory.
+pub const MAP_FILE: &str = ".common-repo/map.json";
+
+/// The format version of the map. It changes only when an existing field
+/// changes meaning or is removed.
+pub const MAP_VERSION: u32 = 1;
+
+/// The upstream of every managed file in an output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileMap {
+    /// The format version, [`MAP_VERSION`].
+    pub version: u32,
+    /// The managed files, by destination path.
+    pub files: BTreeMap<PathBuf, ManagedFile>,
+}
+
+/// Where one managed file came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManagedFile {
+    /// URL or path of the repository the file was included from.
+    pub repo: String,
+    /// The ref of that repository, if it has one.
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_: Option<String>,
+    /// The file's path in that repository.
+    pub source: PathBuf,
+    /// What was done to the file on the way.
+    pub operations: Vec<String>,
+}
+
+impl FileMap {
+    /// The map of the files of `output` that came from an upstream.
+    pub fn of(output: &MemoryFS) -> Self {
+        Self::placed(output, Path::new(""))
+    }
+
+    /// The map of the files of `output`, which was placed under the
+    /// subdirectory `dir`, that came from an upstream.
+    pub fn placed(output: &MemoryFS, dir: &Path) -> Self {
+        let mut files = BTreeMap::new();
+        for (path, file) in output.files() {
+            let Some(origin) = &file.origin else {
+                continue;
+            };
+            let Some(repo) = &origin.repo else {
+                continue;
+            };
+            let mut operations = Vec::new();
+            if path.strip_prefix(dir).unwrap_or(path) != origin.source {
+                operations.push("rename".to_string());
+            }
+            operations.extend(origin.operations.iter().cloned());
+            files.insert(
+                path.clone(),
+                ManagedFile {
+                    repo: repo.clone(),
+                    ref_: Some(origin.ref_.clone()).filter(|r| !r.is_empty()),
+                    source: origin.source.clone(),
+                    operations,
+                },
+            );
+        }
+        FileMap {
+            version: MAP_VERSION,
+            files,
+        }
+    }
+
+    /// The map as pretty-printed JSON, with a trailing newline.
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("a file map is serializable");
+        json.push('\n');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{File, FileOrigin};
+    use std::sync::Arc;
+
+    fn from(repo: Option<&str>, source: &str, operations: &[&str]) -> File {
+        let mut file = File::from_string("content");
+        file.origin = Some(Arc::new(FileOrigin {
+            repo: repo.map(str::to_string),
+            ref_: "v1".to_string(),
+            source: PathBuf::from(source),
+            operations: operations.iter().map(|op| op.to_string()).collect(),
+        }));
+        file
+    }
+
+    #[test]
+    fn test_map_lists_only_files_from_upstreams() {
+        let mut output = MemoryFS::new();
+        output
+            .add_file(
+                ".github/workflows/ci.yml",
+                from(Some("../ci"), "ci.yml", &["template"]),
+            )
+            .unwrap();
+        output
+            .add_file("LICENSE", from(Some("../base"), "LICENSE", &[]))
+            .unwrap();
+        output
+            .add_file("unstamped.txt", from(None, "unstamped.txt", &[]))
+            .unwrap();
+        output
+            .add_file_string("src/main.rs", "fn main() {}")
+            .unwrap();
+
+        let map = FileMap::of(&output);
+        assert_eq!(map.files.len(), 2);
+        let ci = &map.files[&PathBuf::from(".github/workflows/ci.yml")];
+        assert_eq!(ci.repo, "../ci");
+        assert_eq!(ci.source, PathBuf::from("ci.yml"));
+        assert_eq!(ci.operations, ["rename", "template"]);
+        assert!(map.files[&PathBuf::from("LICENSE")].operations.is_empty());
+    }
+
+    #[test]
+    fn test_placed_output_is_not_renamed() {
+        let mut output = MemoryFS::new();
+        output
+            .add_file("infra/shared/ci.yml", from(Some("../ci"), "ci.yml", &[]))
+            .unwrap();
+        output
+            .add_file(
+                "infra/shared/lint.yml",
+                from(Some("../ci"), "ci.yml", &["template"]),
+            )
+            .unwrap();
+
+        let map = FileMap::placed(&output, Path::new("infra/shared"));
+        assert!(map.files[&PathBuf::from("infra/shared/ci.yml")]
+            .operations
+            .is_empty());
+        assert_eq!(
+            map.files[&PathBuf::from("infra/shared/lint.yml")].operations,
+            ["rename", "template"]
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut output = MemoryFS::new();
+        let mut file = from(Some("https://github.com/acme/base"), "LICENSE", &[]);
+        Arc::make_mut(file.origin.as_mut().unwrap()).ref_ = String::new();
+        output.add_file("LICENSE", file).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&FileMap::of(&output).to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "files": {
+                    "LICENSE": {
+                        "repo": "https://github.com/acme/base",
+                        "source": "LICENSE",
+                        "operations": []
+                    }
+                }
+            })
+        );
+    }
+}
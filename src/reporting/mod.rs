@@ -0,0 +1,31 @@
+//! # Reporting
+//!
+//! Renderers that turn what the pipeline discovered into documents for
+//! people and other tools, such as diagrams for documentation and
+//! architecture reviews.
+//!
+//! - **`fetches`**: Which repositories a run fetched, from the cache or
+//!   not, and how long each took
+//! - **`graph`**: The repository inheritance tree as a Graphviz DOT or
+//!   Mermaid graph
+//! - **`history`**: Who applied which upstream commits, and what changed
+//! - **`map`**: Which upstream file each managed file came from
+//! - **`provenance`**: Where the final content of a file came from
+//! - **`renames`**: Where each renamed file ends up after every `rename`
+//!   operation
+//! - **`shadow`**: How the output of newer upstream refs differs from the
+//!   live files
+//! - **`stats`**: How much of the final output each upstream accounts for
+//! - **`timings`**: How long each operation took and how many files it left
+//! - **`vars`**: Where template variables are defined and used
+
+pub mod fetches;
+pub mod graph;
+pub mod history;
+pub mod map;
+pub mod provenance;
+pub mod renames;
+pub mod shadow;
+pub mod stats;
+pub mod timings;
+pub mod vars;
@@ -0,0 +1,105 @@
+//! # File Provenance
+//!
+//! Where the final content of a file came from, for
+//! `common-repo render --provenance`.
+//!
+//! The origin is worked out from three versions of the file: the composite
+//! the upstreams produced, the consumer's local file, and the final output.
+//! A final file equal to one of the first two came from it; anything else
+//! was combined from both, or written by the consumer's own operations.
+//! A file in the overrides directory is reported as such, whatever its
+//! content.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::operators::header;
+
+/// Where the final content of a file came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The upstream composite, unchanged.
+    Upstream,
+    /// The consumer's local file, unchanged.
+    Local,
+    /// Upstream content merged with the local file.
+    Merged,
+    /// The consumer's operations, from neither side as it was.
+    Consumer,
+    /// The consumer's overrides directory.
+    Override,
+}
+
+impl Origin {
+    /// The origin of `output`, given the composite and local versions of
+    /// the file, if there were any.
+    pub fn of(composite: Option<&[u8]>, local: Option<&[u8]>, output: &[u8]) -> Self {
+        match (composite, local) {
+            (Some(composite), _) if composite == output => Origin::Upstream,
+            (_, Some(local)) if local == output => Origin::Local,
+            (Some(_), Some(_)) => Origin::Merged,
+            _ => Origin::Consumer,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::Upstream => "upstream",
+            Origin::Local => "local file",
+            Origin::Merged => "upstream merged with the local file",
+            Origin::Consumer => "consumer operations",
+            Origin::Override => "override in .common-repo/overrides",
+        })
+    }
+}
+
+/// The provenance comment lines for `path`.
+pub fn describe(path: &Path, origin: Origin) -> Vec<String> {
+    vec![
+        format!("common-repo render: {}", path.display()),
+        format!("origin: {}", origin),
+    ]
+}
+
+/// `content` with the provenance of `path` prepended as a comment, placed
+/// the way the `header` operation places one, or `None` when the file is
+/// not UTF-8 or its type has no known comment syntax.
+pub fn annotate(path: &Path, content: &[u8], origin: Origin) -> Option<String> {
+    let content = std::str::from_utf8(content).ok()?;
+    let lines = describe(path, origin);
+    let text: Vec<&str> = lines.iter().map(String::as_str).collect();
+    header::prepend(path, content, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_of() {
+        assert_eq!(Origin::of(Some(b"a"), None, b"a"), Origin::Upstream);
+        assert_eq!(Origin::of(Some(b"a"), Some(b"b"), b"a"), Origin::Upstream);
+        assert_eq!(Origin::of(Some(b"a"), Some(b"b"), b"b"), Origin::Local);
+        assert_eq!(Origin::of(Some(b"a"), Some(b"b"), b"ab"), Origin::Merged);
+        assert_eq!(Origin::of(None, None, b"c"), Origin::Consumer);
+        assert_eq!(Origin::of(Some(b"a"), None, b"A"), Origin::Consumer);
+    }
+
+    #[test]
+    fn test_annotate_uses_comment_syntax() {
+        let yaml = annotate(Path::new("ci.yml"), b"a: 1\n", Origin::Merged).unwrap();
+        assert_eq!(
+            yaml,
+            "# common-repo render: ci.yml\n\
+             # origin: upstream merged with the local file\n\
+             \n\
+             a: 1\n"
+        );
+        let script = annotate(Path::new("run.sh"), b"#!/bin/sh\necho\n", Origin::Local).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n# common-repo render: run.sh\n"));
+        assert!(annotate(Path::new("notes.txt"), b"x\n", Origin::Local).is_none());
+        assert!(annotate(Path::new("a.yml"), &[0xff], Origin::Local).is_none());
+    }
+}
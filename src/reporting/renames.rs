@@ -0,0 +1,352 @@
+//! # Rename Plan
+//!
+//! Where each renamed file ends up once every `rename` operation has run,
+//! for `common-repo plan --renames`.
+//!
+//! While a pipeline runs inside [`trace`], each rename mapping reports the
+//! files it moved, or that it matched nothing, to a collector for the
+//! current thread, in the same way as [`crate::diagnostics`]. The moves are
+//! chained by path into one [`RenamedFile`] per source file, so a file
+//! renamed by one mapping and then again by a later one shows the whole
+//! chain. Mappings that never matched a file are listed as unmatched.
+//!
+//! Work handed to other threads must pass its trace back to the calling
+//! thread with [`replay`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::RenameMapping;
+
+/// One rename mapping applied once, with the files it moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Event {
+    mapping: RenameMapping,
+    /// The `description:` of the rename operation.
+    description: Option<String>,
+    moves: Vec<(PathBuf, PathBuf)>,
+}
+
+/// The renames recorded by [`trace`], in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameTrace {
+    events: Vec<Event>,
+}
+
+/// One step of a file's rename chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameStep {
+    /// The mapping that moved the file.
+    pub mapping: RenameMapping,
+    /// The `description:` of the rename operation the mapping belongs to.
+    pub description: Option<String>,
+    /// Where the mapping moved it.
+    pub path: PathBuf,
+}
+
+/// A file that was renamed at least once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedFile {
+    /// The path the file had before its first rename.
+    pub source: PathBuf,
+    /// Each rename that moved the file, in order.
+    pub steps: Vec<RenameStep>,
+}
+
+impl RenamedFile {
+    /// The path the file has after all renames.
+    pub fn destination(&self) -> &Path {
+        &self.steps.last().expect("a renamed file has a step").path
+    }
+
+    /// Whether a later mapping renamed the file again after an earlier one
+    /// already had, which is often not what the later mapping meant.
+    pub fn is_chained(&self) -> bool {
+        self.steps.len() > 1
+    }
+}
+
+/// A rename mapping that never matched a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmatchedMapping {
+    /// The mapping.
+    pub mapping: RenameMapping,
+    /// The `description:` of the rename operation the mapping belongs to.
+    pub description: Option<String>,
+}
+
+/// A rename mapping that moved many files at once, or whose pattern
+/// matches any path, which is usually a regex that is too greedy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepingRename {
+    /// The mapping.
+    pub mapping: RenameMapping,
+    /// The `description:` of the rename operation the mapping belongs to.
+    pub description: Option<String>,
+    /// Every file the mapping moved, in the order it moved them.
+    pub moves: Vec<(PathBuf, PathBuf)>,
+}
+
+/// The renamed files of a trace and the mappings that matched nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenamePlan {
+    /// Every renamed file, sorted by source path.
+    pub files: Vec<RenamedFile>,
+    /// Mappings that never matched a file, in the order they first ran.
+    pub unmatched: Vec<UnmatchedMapping>,
+}
+
+impl RenameTrace {
+    /// Whether no rename mapping ran.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The mappings among `mappings` that moved files and either moved
+    /// more than `threshold` of them or have a pattern that matches any path.
+    /// A mapping that ran more than once, for example once per upstream, is
+    /// listed once with all of its moves.
+    pub fn sweeping(&self, mappings: &[&RenameMapping], threshold: usize) -> Vec<SweepingRename> {
+        let mut sweeping: Vec<SweepingRename> = Vec::new();
+        for event in &self.events {
+            if event.moves.is_empty() || !mappings.contains(&&event.mapping) {
+                continue;
+            }
+            match sweeping.iter_mut().find(|s| s.mapping == event.mapping) {
+                Some(existing) => existing.moves.extend(event.moves.iter().cloned()),
+                None => sweeping.push(SweepingRename {
+                    mapping: event.mapping.clone(),
+                    description: event.description.clone(),
+                    moves: event.moves.clone(),
+                }),
+            }
+        }
+        sweeping.retain(|s| s.moves.len() > threshold || matches_any_path(&s.mapping.from));
+        sweeping
+    }
+
+    /// Chain the recorded moves into a plan.
+    pub fn plan(&self) -> RenamePlan {
+        // Index into `files` of the chain that currently ends at each path.
+        let mut current: HashMap<PathBuf, usize> = HashMap::new();
+        let mut files: Vec<RenamedFile> = Vec::new();
+        let mut matched: Vec<&RenameMapping> = Vec::new();
+        for event in &self.events {
+            if !event.moves.is_empty() {
+                matched.push(&event.mapping);
+            }
+            let mut moved = Vec::with_capacity(event.moves.len());
+            for (from, to) in &event.moves {
+                let index = current.remove(from).unwrap_or_else(|| {
+                    files.push(RenamedFile {
+                        source: from.clone(),
+                        steps: Vec::new(),
+                    });
+                    files.len() - 1
+                });
+                files[index].steps.push(RenameStep {
+                    mapping: event.mapping.clone(),
+                    description: event.description.clone(),
+                    path: to.clone(),
+                });
+                moved.push((to.clone(), index));
+            }
+            current.extend(moved);
+        }
+        files.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let mut unmatched: Vec<UnmatchedMapping> = Vec::new();
+        for event in &self.events {
+            if !matched.contains(&&event.mapping)
+                && !unmatched.iter().any(|u| u.mapping == event.mapping)
+            {
+                unmatched.push(UnmatchedMapping {
+                    mapping: event.mapping.clone(),
+                    description: event.description.clone(),
+                });
+            }
+        }
+        RenamePlan { files, unmatched }
+    }
+}
+
+/// Whether the rename pattern `pattern` matches every path, as `.*` or an
+/// unanchored pattern that can match nothing does.
+pub fn matches_any_path(pattern: &str) -> bool {
+    regex::Regex::new(pattern).is_ok_and(|regex| regex.is_match("") && regex.is_match("a/b.c"))
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<Vec<Event>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Pops the collector frame even if the closure panics.
+struct Frame;
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| {
+            active.borrow_mut().pop();
+        });
+    }
+}
+
+/// Run `f`, recording every rename it makes on this thread.
+///
+/// Calls nest: renames go to the innermost collector only.
+pub fn trace<T>(f: impl FnOnce() -> T) -> (T, RenameTrace) {
+    ACTIVE.with(|active| active.borrow_mut().push(Vec::new()));
+    let frame = Frame;
+    let value = f();
+    let events = ACTIVE.with(|active| std::mem::take(active.borrow_mut().last_mut().unwrap()));
+    drop(frame);
+    (value, RenameTrace { events })
+}
+
+/// Record that `mapping` moved the files in `moves`, if a collector is
+/// active on this thread.
+pub(crate) fn record(mapping: &RenameMapping, moves: &[(PathBuf, PathBuf)]) {
+    push(Event {
+        mapping: mapping.clone(),
+        description: crate::diagnostics::current_description(),
+        moves: moves.to_vec(),
+    });
+}
+
+/// Record the renames of a trace taken on another thread.
+pub(crate) fn replay(trace: RenameTrace) {
+    for event in trace.events {
+        push(event);
+    }
+}
+
+fn push(event: Event) {
+    ACTIVE.with(|active| {
+        if let Some(frame) = active.borrow_mut().last_mut() {
+            frame.push(event);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(from: &str, to: &str) -> RenameMapping {
+        RenameMapping {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    fn moves(pairs: &[(&str, &str)]) -> Vec<(PathBuf, PathBuf)> {
+        pairs
+            .iter()
+            .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_chains_moves_and_lists_unmatched() {
+        let ((), trace) = trace(|| {
+            record(
+                &mapping("^src/", "lib/"),
+                &moves(&[("src/a.rs", "lib/a.rs")]),
+            );
+            record(&mapping("^old/", "new/"), &[]);
+            record(
+                &mapping(r"\.rs$", ".txt"),
+                &moves(&[("lib/a.rs", "lib/a.txt"), ("b.rs", "b.txt")]),
+            );
+        });
+        let plan = trace.plan();
+
+        assert_eq!(plan.files.len(), 2);
+        assert_eq!(plan.files[0].source, Path::new("b.rs"));
+        assert!(!plan.files[0].is_chained());
+        assert_eq!(plan.files[1].source, Path::new("src/a.rs"));
+        assert_eq!(plan.files[1].destination(), Path::new("lib/a.txt"));
+        assert!(plan.files[1].is_chained());
+        assert_eq!(
+            plan.unmatched,
+            vec![UnmatchedMapping {
+                mapping: mapping("^old/", "new/"),
+                description: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_record_keeps_the_operation_description() {
+        let ((), trace) = trace(|| {
+            crate::diagnostics::described(Some("Publish the docs".to_string()), || {
+                record(&mapping("^docs/", "site/"), &moves(&[("docs/a", "site/a")]));
+                record(&mapping("^old/", "new/"), &[]);
+            });
+        });
+        let plan = trace.plan();
+
+        assert_eq!(
+            plan.files[0].steps[0].description.as_deref(),
+            Some("Publish the docs")
+        );
+        assert_eq!(
+            plan.unmatched[0].description.as_deref(),
+            Some("Publish the docs")
+        );
+    }
+
+    #[test]
+    fn test_sweeping_lists_large_and_match_all_mappings() {
+        let many: Vec<(String, String)> = (0..5)
+            .map(|i| (format!("src/{i}.rs"), format!("lib/{i}.rs")))
+            .collect();
+        let many: Vec<(&str, &str)> = many.iter().map(|(a, b)| (a.as_str(), b.as_str())).collect();
+        let greedy = mapping("(.*)", "out/$1");
+        let large = mapping("^src/", "lib/");
+        let small = mapping("^docs/", "site/");
+        let upstream = mapping(".*", "x");
+        let ((), trace) = trace(|| {
+            record(&large, &moves(&many[..3]));
+            record(&large, &moves(&many[3..]));
+            record(&small, &moves(&[("docs/a", "site/a")]));
+            record(&greedy, &moves(&[("a", "out/a")]));
+            record(&upstream, &moves(&[("b", "x")]));
+        });
+
+        let sweeping = trace.sweeping(&[&large, &small, &greedy], 4);
+        assert_eq!(sweeping.len(), 2);
+        assert_eq!(sweeping[0].mapping, large);
+        assert_eq!(sweeping[0].moves.len(), 5);
+        assert_eq!(sweeping[1].mapping, greedy);
+    }
+
+    #[test]
+    fn test_matches_any_path() {
+        assert!(matches_any_path(".*"));
+        assert!(matches_any_path("^(.*)$"));
+        assert!(matches_any_path("x?"));
+        assert!(!matches_any_path("^src/(.*)"));
+        assert!(!matches_any_path("^$"));
+        assert!(!matches_any_path("("));
+    }
+
+    #[test]
+    fn test_record_without_collector_is_dropped() {
+        record(&mapping("a", "b"), &moves(&[("a", "b")]));
+        let ((), inner) = trace(|| {});
+        assert!(inner.is_empty());
+    }
+
+    #[test]
+    fn test_replay_into_outer_collector() {
+        let ((), outer) = trace(|| {
+            let handle = std::thread::spawn(|| {
+                trace(|| record(&mapping("a", "b"), &moves(&[("a", "b")]))).1
+            });
+            replay(handle.join().unwrap());
+        });
+        assert_eq!(outer.plan().files[0].destination(), Path::new("b"));
+    }
+}
@@ -0,0 +1,231 @@
+//! # Shadow Reports
+//!
+//! `apply --shadow-dir <dir>` composes the output of newer upstream refs
+//! into a directory beside the live files instead of over them, so that a
+//! major template upgrade can be reviewed and staged over days without
+//! touching the working tree. The report compares the two directories:
+//! which managed files the upgrade would add, change or remove, and which
+//! it leaves as they are.
+//!
+//! The report is printed, and saved as Markdown to
+//! `.common-repo/shadow-report.md` in the shadow directory, next to the
+//! files it describes:
+//!
+//! ```markdown
+//! ## Shadow apply
+//!
+//! | Upstream | Live | Shadow |
+//! | --- | --- | --- |
+//! | https://github.com/acme/ci-templates | v1.4.0 | v2.0.0 |
+//!
+//! 1 added, 1 changed, 0 removed, 12 unchanged.
+//!
+//! | File | Change |
+//! | --- | --- |
+//! | .github/workflows/release.yml | added |
+//! | .github/workflows/ci.yml | changed |
+//! ```
+//!
+//! A changed file can be compared with `diff <live> <shadow-dir>/<file>`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::filesystem::{File, MemoryFS};
+use crate::github::summary_table;
+use crate::phases::manifest::ApplyManifest;
+
+/// Where the report is saved, relative to the shadow directory.
+pub const REPORT_FILE: &str = ".common-repo/shadow-report.md";
+
+/// An upstream composed at a different ref in the shadow directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefBump {
+    /// URL of the repository.
+    pub url: String,
+    /// The ref the configuration has.
+    pub from: String,
+    /// The ref the shadow directory was composed with.
+    pub to: String,
+}
+
+/// How the shadow directory differs from the live one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShadowReport {
+    /// The upstreams composed at a different ref.
+    pub bumps: Vec<RefBump>,
+    /// Files only in the shadow directory, sorted.
+    pub added: Vec<PathBuf>,
+    /// Files in both, with different content or permission bits, sorted.
+    pub changed: Vec<PathBuf>,
+    /// Files the last apply into the live directory propagated that the
+    /// shadow output no longer has, sorted.
+    pub removed: Vec<PathBuf>,
+    /// How many files are the same in both.
+    pub unchanged: usize,
+}
+
+impl ShadowReport {
+    /// Compare `shadow_fs`, the files written to the shadow directory, with
+    /// the files at the same paths under `live_dir`. `previous` lists the
+    /// files the last apply into `live_dir` propagated.
+    pub fn compare(
+        bumps: Vec<RefBump>,
+        shadow_fs: &MemoryFS,
+        live_dir: &Path,
+        previous: &ApplyManifest,
+    ) -> Self {
+        let mut report = Self {
+            bumps,
+            ..Self::default()
+        };
+        for (path, file) in shadow_fs.files() {
+            match File::from_path(&live_dir.join(path)) {
+                Ok(live)
+                    if live.content == file.content
+                        && live.permissions & 0o777 == file.permissions & 0o777 =>
+                {
+                    report.unchanged += 1
+                }
+                Ok(_) => report.changed.push(path.clone()),
+                Err(_) => report.added.push(path.clone()),
+            }
+        }
+        report.removed = previous
+            .paths()
+            .filter(|path| !shadow_fs.exists(path) && live_dir.join(path).is_file())
+            .map(Path::to_path_buf)
+            .collect();
+        report.added.sort();
+        report.changed.sort();
+        report
+    }
+
+    /// The one-line count of files added, changed, removed and unchanged.
+    pub fn counts(&self) -> String {
+        format!(
+            "{} added, {} changed, {} removed, {} unchanged.",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+
+    /// The report as a Markdown document.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("## Shadow apply\n\n");
+        if !self.bumps.is_empty() {
+            let rows: Vec<Vec<String>> = self
+                .bumps
+                .iter()
+                .map(|bump| vec![bump.url.clone(), bump.from.clone(), bump.to.clone()])
+                .collect();
+            markdown.push_str(&summary_table(&["Upstream", "Live", "Shadow"], &rows));
+            markdown.push('\n');
+        }
+        markdown.push_str(&self.counts());
+        markdown.push('\n');
+        let rows: Vec<Vec<String>> = [
+            (&self.added, "added"),
+            (&self.changed, "changed"),
+            (&self.removed, "removed"),
+        ]
+        .into_iter()
+        .flat_map(|(paths, change)| {
+            paths
+                .iter()
+                .map(move |path| vec![path.display().to_string(), change.to_string()])
+        })
+        .collect();
+        if !rows.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&summary_table(&["File", "Change"], &rows));
+        }
+        markdown
+    }
+
+    /// Save the report to [`REPORT_FILE`] in `shadow_dir`.
+    pub fn save(&self, shadow_dir: &Path) -> Result<()> {
+        let path = shadow_dir.join(REPORT_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_markdown())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compare_and_render() {
+        let live = TempDir::new().unwrap();
+        fs::write(live.path().join("ci.yml"), "on: push\n").unwrap();
+        fs::write(live.path().join("same.txt"), "same\n").unwrap();
+        fs::write(live.path().join("old.yml"), "old\n").unwrap();
+        let mut previous = ApplyManifest::new();
+        for path in ["ci.yml", "same.txt", "old.yml"] {
+            previous.insert(path, &File::from_string(""));
+        }
+        let mut shadow = MemoryFS::new();
+        shadow
+            .add_file_string("ci.yml", "on: pull_request\n")
+            .unwrap();
+        // The same content and mode as the live file.
+        shadow
+            .add_file(
+                "same.txt",
+                File::from_path(&live.path().join("same.txt")).unwrap(),
+            )
+            .unwrap();
+        shadow.add_file_string("release.yml", "on: tag\n").unwrap();
+
+        let bumps = vec![RefBump {
+            url: "https://github.com/acme/ci".to_string(),
+            from: "v1.0.0".to_string(),
+            to: "v2.0.0".to_string(),
+        }];
+        let report = ShadowReport::compare(bumps, &shadow, live.path(), &previous);
+        assert_eq!(report.added, vec![PathBuf::from("release.yml")]);
+        assert_eq!(report.changed, vec![PathBuf::from("ci.yml")]);
+        assert_eq!(report.removed, vec![PathBuf::from("old.yml")]);
+        assert_eq!(report.unchanged, 1);
+
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.contains("| https://github.com/acme/ci | v1.0.0 | v2.0.0 |"),
+            "{}",
+            markdown
+        );
+        assert!(
+            markdown.contains("1 added, 1 changed, 1 removed, 1 unchanged."),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("| old.yml | removed |"), "{}", markdown);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compare_reports_mode_changes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let live = TempDir::new().unwrap();
+        let script = live.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        let mut executable = File::from_string("#!/bin/sh\n");
+        executable.permissions = 0o755;
+        let mut shadow = MemoryFS::new();
+        shadow.add_file("run.sh", executable).unwrap();
+
+        let report = ShadowReport::compare(Vec::new(), &shadow, live.path(), &ApplyManifest::new());
+        assert_eq!(report.changed, vec![PathBuf::from("run.sh")]);
+        assert_eq!(report.unchanged, 0);
+    }
+}
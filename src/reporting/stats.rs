@@ -0,0 +1,99 @@
+//! # Upstream Statistics
+//!
+//! How much of the final output each upstream accounts for, for
+//! `common-repo info --stats`.
+//!
+//! An upstream's files are the composite it produces on its own. Each of
+//! them is counted against the final output: a file with the same content
+//! there was contributed by the upstream, a file with other content was
+//! overridden by a later upstream, a local file or the consumer's own
+//! operations, and a file missing from the output was dropped, for example
+//! by an `exclude`.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::filesystem::MemoryFS;
+//! use common_repo::reporting::stats::UpstreamStats;
+//!
+//! let mut upstream = MemoryFS::new();
+//! upstream.add_file_string("README.md", "upstream").unwrap();
+//! upstream.add_file_string("ci.yml", "on: push").unwrap();
+//! let mut output = MemoryFS::new();
+//! output.add_file_string("README.md", "local").unwrap();
+//! output.add_file_string("ci.yml", "on: push").unwrap();
+//!
+//! let stats = UpstreamStats::of(&upstream, &output, 3);
+//! assert_eq!(stats.contributed, 1);
+//! assert_eq!(stats.bytes, 8);
+//! assert_eq!(stats.overridden, 1);
+//! ```
+
+use std::path::PathBuf;
+
+use crate::filesystem::MemoryFS;
+
+/// What one upstream accounts for in the final output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpstreamStats {
+    /// Files in the output with the upstream's content.
+    pub contributed: usize,
+    /// Total size of the contributed files, in bytes.
+    pub bytes: u64,
+    /// The largest contributed files with their sizes, largest first.
+    pub largest: Vec<(PathBuf, u64)>,
+    /// Files of the upstream whose content in the output is different.
+    pub overridden: usize,
+    /// Files of the upstream that are not in the output.
+    pub dropped: usize,
+}
+
+impl UpstreamStats {
+    /// Count the files of `upstream` against `output`, keeping the
+    /// `largest` biggest contributed files.
+    pub fn of(upstream: &MemoryFS, output: &MemoryFS, largest: usize) -> Self {
+        let mut stats = UpstreamStats::default();
+        let mut sizes = Vec::new();
+        for (path, file) in upstream.files() {
+            match output.get_file(path) {
+                Some(final_file) if final_file.content == file.content => {
+                    let size = file.content.len() as u64;
+                    stats.contributed += 1;
+                    stats.bytes += size;
+                    sizes.push((path.clone(), size));
+                }
+                Some(_) => stats.overridden += 1,
+                None => stats.dropped += 1,
+            }
+        }
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes.truncate(largest);
+        stats.largest = sizes;
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_largest_files_sorted_and_limited() {
+        let mut upstream = MemoryFS::new();
+        upstream.add_file_string("a", "1").unwrap();
+        upstream.add_file_string("b", "333").unwrap();
+        upstream.add_file_string("c", "22").unwrap();
+        upstream.add_file_string("d", "gone").unwrap();
+        let mut output = upstream.clone();
+        output.remove_file("d").unwrap();
+
+        let stats = UpstreamStats::of(&upstream, &output, 2);
+        assert_eq!(stats.contributed, 3);
+        assert_eq!(stats.bytes, 6);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(
+            stats.largest,
+            vec![(PathBuf::from("b"), 3), (PathBuf::from("c"), 2)]
+        );
+    }
+}
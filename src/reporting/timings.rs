@@ -0,0 +1,283 @@
+//! # Operation Timings
+//!
+//! How long each operation took and how many files it left, for `--verbose`
+//! output and the summary at the end of `apply`.
+//!
+//! Every operation run through [`time`] is logged at debug level with its
+//! duration and the file count before and after it. While a pipeline runs
+//! inside [`trace`], the timings are also collected for the current thread,
+//! in the same way as [`crate::reporting::renames`], so the slowest
+//! operations can be reported once the run is over. Operations run inside
+//! [`within`] are attributed to that upstream.
+//!
+//! Work handed to other threads must pass its timings back to the calling
+//! thread with [`replay`].
+
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use crate::config::Operation;
+use crate::error::Result;
+use crate::filesystem::MemoryFS;
+
+/// Operations that take at least this long are reported as slow.
+pub const SLOW_OPERATION: Duration = Duration::from_millis(500);
+
+/// One operation run once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationTiming {
+    /// The operation with its patterns or paths, e.g. `exclude *.tmp`.
+    pub operation: String,
+    /// The upstream the operation belongs to, or `None` for the consumer's
+    /// own configuration.
+    pub source: Option<String>,
+    /// How long the operation took.
+    pub elapsed: Duration,
+    /// Files in the filesystem before the operation.
+    pub files_before: usize,
+    /// Files in the filesystem after the operation.
+    pub files_after: usize,
+}
+
+impl fmt::Display for OperationTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {:.1}ms, {} -> {} files",
+            self.operation,
+            self.source.as_deref().unwrap_or("local config"),
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.files_before,
+            self.files_after
+        )
+    }
+}
+
+/// The operations recorded by [`trace`], in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timings {
+    operations: Vec<OperationTiming>,
+}
+
+impl Timings {
+    /// Whether no operation was timed.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Every timed operation, in the order it ran.
+    pub fn operations(&self) -> &[OperationTiming] {
+        &self.operations
+    }
+
+    /// Add the operations of another trace after these.
+    pub fn extend(&mut self, other: Timings) {
+        self.operations.extend(other.operations);
+    }
+
+    /// The `count` slowest operations, slowest first.
+    pub fn slowest(&self, count: usize) -> Vec<&OperationTiming> {
+        let mut operations: Vec<&OperationTiming> = self.operations.iter().collect();
+        operations.sort_by_key(|timing| std::cmp::Reverse(timing.elapsed));
+        operations.truncate(count);
+        operations
+    }
+
+    /// The operations that took at least [`SLOW_OPERATION`], slowest first.
+    pub fn slow(&self) -> Vec<&OperationTiming> {
+        let mut operations = self.slowest(self.operations.len());
+        operations.retain(|timing| timing.elapsed >= SLOW_OPERATION);
+        operations
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Vec<Vec<OperationTiming>>> = const { RefCell::new(Vec::new()) };
+    static SOURCES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Pops the collector frame even if the closure panics.
+struct Frame;
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| {
+            active.borrow_mut().pop();
+        });
+    }
+}
+
+/// Pops the source even if the closure panics.
+struct Source;
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        SOURCES.with(|sources| {
+            sources.borrow_mut().pop();
+        });
+    }
+}
+
+/// Run `f`, recording every operation it times on this thread.
+///
+/// Calls nest: timings go to the innermost collector only.
+pub fn trace<T>(f: impl FnOnce() -> T) -> (T, Timings) {
+    ACTIVE.with(|active| active.borrow_mut().push(Vec::new()));
+    let frame = Frame;
+    let value = f();
+    let operations = ACTIVE.with(|active| std::mem::take(active.borrow_mut().last_mut().unwrap()));
+    drop(frame);
+    (value, Timings { operations })
+}
+
+/// Run `f`, attributing the operations it times to `source`.
+pub(crate) fn within<T>(source: String, f: impl FnOnce() -> T) -> T {
+    SOURCES.with(|sources| sources.borrow_mut().push(source));
+    let _source = Source;
+    f()
+}
+
+/// Run the operation `f` on `fs`, logging and recording how long it took
+/// and how many files it left.
+pub(crate) fn time<T>(
+    operation: &Operation,
+    fs: &mut MemoryFS,
+    f: impl FnOnce(&mut MemoryFS) -> Result<T>,
+) -> Result<T> {
+    let files_before = fs.len();
+    let start = Instant::now();
+    let result = crate::diagnostics::described(crate::config::description(operation), || f(fs));
+    let timing = OperationTiming {
+        operation: describe(operation),
+        source: SOURCES.with(|sources| sources.borrow().last().cloned()),
+        elapsed: start.elapsed(),
+        files_before,
+        files_after: fs.len(),
+    };
+    debug!("op timing: {}", timing);
+    record(timing);
+    result
+}
+
+/// Record the timings of a trace taken on another thread.
+pub(crate) fn replay(timings: Timings) {
+    for timing in timings.operations {
+        record(timing);
+    }
+}
+
+fn record(timing: OperationTiming) {
+    ACTIVE.with(|active| {
+        if let Some(frame) = active.borrow_mut().last_mut() {
+            frame.push(timing);
+        }
+    });
+}
+
+/// The operation's kind with what it applies to.
+fn describe(operation: &Operation) -> String {
+    let detail = match operation {
+        Operation::Include { include, .. } => include.patterns.join(", "),
+        Operation::Exclude { exclude } => exclude.patterns.join(", "),
+        Operation::Template { template } => template.targets().join(", "),
+        Operation::Rename { rename } => rename
+            .mappings
+            .iter()
+            .map(|mapping| format!("'{}' -> '{}'", mapping.from, mapping.to))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Operation::File { file } => file.url.clone(),
+        Operation::Vendor { vendor } => format!("{}@{}", vendor.url, vendor.r#ref),
+        Operation::Extract { extract } => format!("{} -> {}", extract.source, extract.dest),
+        Operation::Assert { assert } => assert.path.clone(),
+        _ => match (
+            operation.merge_effective_source(),
+            operation.merge_effective_dest(),
+        ) {
+            (Some(source), Some(dest)) if source != dest => format!("{} -> {}", source, dest),
+            (Some(path), _) | (None, Some(path)) => path.to_string(),
+            (None, None) => String::new(),
+        },
+    };
+    if detail.is_empty() {
+        operation.kind().to_string()
+    } else {
+        format!("{} {}", operation.kind(), detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exclude(pattern: &str) -> Operation {
+        crate::config::parse(&format!("- exclude: [\"{}\"]\n", pattern))
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_time_records_files_and_source() {
+        let mut fs = MemoryFS::new();
+        fs.add_file_string("a.tmp", "a").unwrap();
+        fs.add_file_string("b.rs", "b").unwrap();
+        let operation = exclude("*.tmp");
+
+        let (result, timings) = trace(|| {
+            within("https://example.com/up@v1".to_string(), || {
+                time(&operation, &mut fs, |fs| fs.remove_file("a.tmp"))
+            })
+        });
+        result.unwrap();
+
+        let timing = &timings.operations()[0];
+        assert_eq!(timing.operation, "exclude *.tmp");
+        assert_eq!(timing.source.as_deref(), Some("https://example.com/up@v1"));
+        assert_eq!((timing.files_before, timing.files_after), (2, 1));
+    }
+
+    #[test]
+    fn test_slowest_and_slow() {
+        let timing = |operation: &str, millis: u64| OperationTiming {
+            operation: operation.to_string(),
+            source: None,
+            elapsed: Duration::from_millis(millis),
+            files_before: 0,
+            files_after: 0,
+        };
+        let timings = Timings {
+            operations: vec![
+                timing("include a", 10),
+                timing("rename b", 900),
+                timing("exclude c", 600),
+            ],
+        };
+
+        let slowest: Vec<&str> = timings
+            .slowest(2)
+            .iter()
+            .map(|timing| timing.operation.as_str())
+            .collect();
+        assert_eq!(slowest, vec!["rename b", "exclude c"]);
+        assert_eq!(timings.slow().len(), 2);
+        assert_eq!(
+            timings.slowest(1)[0].to_string(),
+            "rename b (local config): 900.0ms, 0 -> 0 files"
+        );
+    }
+
+    #[test]
+    fn test_replay_into_outer_collector() {
+        let ((), outer) = trace(|| {
+            let handle = std::thread::spawn(|| {
+                let mut fs = MemoryFS::new();
+                trace(|| time(&exclude("x"), &mut fs, |_| Ok(()))).1
+            });
+            replay(handle.join().unwrap());
+        });
+        assert_eq!(outer.operations().len(), 1);
+    }
+}
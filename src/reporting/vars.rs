@@ -0,0 +1,391 @@
+//! # Template Variable Audit
+//!
+//! Where each template variable is defined and where it is used, for
+//! `common-repo check --vars`, and which definition gives it its value, for
+//! `common-repo info --vars`.
+//!
+//! Definitions come from the `template-vars` operations of the consumer's
+//! configuration and of every repository it inherits from. Uses come from
+//! the files each repository marks as templates, scanned for
+//! `__COMMON_REPO__NAME__` sentinels, and from the local files and upstream
+//! files the consumer's own `template` operations mark. A variable used but
+//! never defined fails the run when the file is rendered; one defined but
+//! never used is usually a leftover or a misspelling of one that is.
+//!
+//! [`resolve`] follows the precedence the pipeline uses. Within an
+//! upstream, the repositories it inherits from override its own
+//! definitions. In the consumer's configuration, a `repo:` operation only
+//! fills in variables that are not set yet, while a `template-vars`
+//! operation overrides what came before it. Variables set on the
+//! [`RunContext`] override everything.
+//!
+//! Only the source block of the configuration is audited; `self:` blocks
+//! are left out.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::config::{Operation, Schema};
+use crate::error::Result;
+use crate::filesystem::MemoryFS;
+use crate::operators::{template, template_vars};
+use crate::phases::context::RunContext;
+use crate::phases::orchestrator::{match_cloned_repo_to_op, partition_self_operations};
+use crate::phases::{phase1, phase2, phase5, ClonedRepo};
+use crate::ref_index;
+
+/// Label of the consumer's own configuration in an audit.
+pub const LOCAL_SOURCE: &str = "local config";
+
+/// Label of the variables set on the [`RunContext`] in a resolution.
+pub const OVERRIDE_SOURCE: &str = "override";
+
+/// One place that gives a template variable a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarDefinition {
+    /// The repository that defines it, [`LOCAL_SOURCE`] or
+    /// [`OVERRIDE_SOURCE`].
+    pub source: String,
+    /// The value it gives.
+    pub value: String,
+}
+
+/// Every definition of each template variable, from lowest to highest
+/// precedence. Templates get the value of the last one.
+pub type VarResolution = BTreeMap<String, Vec<VarDefinition>>;
+
+/// Where the template variables of a configuration are defined and used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarAudit {
+    /// Each variable with the repositories that define it, or
+    /// [`LOCAL_SOURCE`].
+    pub defined: BTreeMap<String, BTreeSet<String>>,
+    /// Each variable with the template files that use it.
+    pub used: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+impl VarAudit {
+    /// Variables that are defined but used by no template.
+    pub fn unused(&self) -> Vec<&str> {
+        self.defined
+            .keys()
+            .filter(|name| !self.used.contains_key(*name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Variables that templates use but nothing defines.
+    pub fn undefined(&self) -> Vec<&str> {
+        self.used
+            .keys()
+            .filter(|name| !self.defined.contains_key(*name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Record the variables used by the template files of `fs`.
+    fn scan(&mut self, fs: &MemoryFS) {
+        for (path, file) in fs.files() {
+            if !file.is_template {
+                continue;
+            }
+            let Ok(content) = std::str::from_utf8(&file.content) else {
+                continue;
+            };
+            for name in template::variables(content) {
+                self.used
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(path.clone());
+            }
+        }
+    }
+
+    fn define(&mut self, operations: &[Operation], source: &str) -> Result<()> {
+        for operation in operations {
+            let Operation::TemplateVars { template_vars: op } = operation else {
+                continue;
+            };
+            template_vars::check_names(op)?;
+            for name in op.vars.keys().chain(op.secrets.keys()) {
+                self.defined
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(source.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Audit the template variables of `config`, fetching the repositories it
+/// inherits from.
+pub fn audit(config: &Schema, ctx: &RunContext) -> Result<VarAudit> {
+    let config = ref_index::resolve(config, ctx.working_dir, ctx.repo_manager)?;
+    let (_, config) = partition_self_operations(&config);
+    let consumer_marks: Vec<&Operation> = config
+        .iter()
+        .filter(|op| matches!(op, Operation::Template { .. }))
+        .collect();
+    let mark = |fs: &mut MemoryFS| -> Result<()> {
+        for op in &consumer_marks {
+            if let Operation::Template { template } = op {
+                template::mark(template, fs)?;
+            }
+        }
+        Ok(())
+    };
+
+    let mut audit = VarAudit::default();
+    audit.define(&config, LOCAL_SOURCE)?;
+    let mut local_fs = phase5::load_local_fs(ctx.working_dir)?;
+    mark(&mut local_fs)?;
+    audit.scan(&local_fs);
+
+    let tree = phase1::execute(&config, ctx)?;
+    let cloned_repos = phase2::clone_tree_repos(&tree, ctx.repo_manager)?;
+    let mut keys: Vec<&String> = cloned_repos.keys().collect();
+    keys.sort();
+    for key in keys {
+        let cloned = &cloned_repos[key];
+        audit.define(&cloned.operations, &source_label(cloned))?;
+        let mut fs = phase2::process_cloned_repo(cloned, ctx.cache)?.fs;
+        mark(&mut fs)?;
+        audit.scan(&fs);
+    }
+    Ok(audit)
+}
+
+/// Resolve the template variables of `config`, fetching the repositories
+/// it inherits from.
+pub fn resolve(config: &Schema, ctx: &RunContext) -> Result<VarResolution> {
+    let config = ref_index::resolve(config, ctx.working_dir, ctx.repo_manager)?;
+    let (_, config) = partition_self_operations(&config);
+    let tree = phase1::execute(&config, ctx)?;
+    let cloned_repos = phase2::clone_tree_repos(&tree, ctx.repo_manager)?;
+
+    let mut resolution = VarResolution::new();
+    for operation in &config {
+        match operation {
+            Operation::Repo { repo } => {
+                let Some(cloned) = cloned_repos
+                    .values()
+                    .find(|cloned| match_cloned_repo_to_op(cloned, repo))
+                else {
+                    continue;
+                };
+                let mut visited = HashSet::from([format!("{}@{}", cloned.url, cloned.ref_)]);
+                let upstream = resolve_upstream(cloned, &cloned_repos, &mut visited)?;
+                for (name, definitions) in upstream {
+                    // Upstream values only fill in variables not set yet.
+                    let existing = resolution.entry(name).or_default();
+                    existing.splice(0..0, definitions);
+                }
+            }
+            Operation::TemplateVars { .. } => {
+                override_with(
+                    &mut resolution,
+                    define(std::slice::from_ref(operation), LOCAL_SOURCE)?,
+                );
+            }
+            _ => {}
+        }
+    }
+    let mut overrides: Vec<_> = ctx.vars.iter().collect();
+    overrides.sort();
+    for (name, value) in overrides {
+        resolution
+            .entry(name.clone())
+            .or_default()
+            .push(VarDefinition {
+                source: OVERRIDE_SOURCE.to_string(),
+                value: value.clone(),
+            });
+    }
+    Ok(resolution)
+}
+
+/// The definitions of an upstream and the repositories it inherits from,
+/// in the order the pipeline resolves them.
+fn resolve_upstream(
+    cloned: &ClonedRepo,
+    cloned_repos: &HashMap<String, ClonedRepo>,
+    visited: &mut HashSet<String>,
+) -> Result<VarResolution> {
+    let mut resolution = define(&cloned.operations, &source_label(cloned))?;
+    for operation in &cloned.operations {
+        let Operation::Repo { repo } = operation else {
+            continue;
+        };
+        if !visited.insert(format!(
+            "{}@{}",
+            repo.url,
+            repo.r#ref.as_deref().unwrap_or("")
+        )) {
+            continue;
+        }
+        if let Some(nested) = cloned_repos
+            .values()
+            .find(|cloned| match_cloned_repo_to_op(cloned, repo))
+        {
+            override_with(
+                &mut resolution,
+                resolve_upstream(nested, cloned_repos, visited)?,
+            );
+        }
+    }
+    for key in &cloned.children_keys {
+        let Some(child) = cloned_repos.get(key) else {
+            continue;
+        };
+        if visited.insert(format!("{}@{}", child.url, child.ref_)) {
+            override_with(
+                &mut resolution,
+                resolve_upstream(child, cloned_repos, visited)?,
+            );
+        }
+    }
+    Ok(resolution)
+}
+
+/// The definitions of the `template-vars` operations among `operations`.
+fn define(operations: &[Operation], source: &str) -> Result<VarResolution> {
+    let mut resolution = VarResolution::new();
+    for operation in operations {
+        let Operation::TemplateVars { template_vars: op } = operation else {
+            continue;
+        };
+        // Rejects the names the pipeline rejects.
+        template_vars::check_names(op)?;
+        for (name, value) in &op.vars {
+            resolution
+                .entry(name.clone())
+                .or_default()
+                .push(VarDefinition {
+                    source: source.to_string(),
+                    value: value.clone(),
+                });
+        }
+        // Secret values are not read for a report; upstreams' are ignored
+        if source == LOCAL_SOURCE {
+            for name in op.secrets.keys() {
+                resolution
+                    .entry(name.clone())
+                    .or_default()
+                    .push(VarDefinition {
+                        source: source.to_string(),
+                        value: crate::secrets::MASK.to_string(),
+                    });
+            }
+        }
+    }
+    Ok(resolution)
+}
+
+/// Add `later` to `resolution` with higher precedence.
+fn override_with(resolution: &mut VarResolution, later: VarResolution) {
+    for (name, definitions) in later {
+        resolution.entry(name).or_default().extend(definitions);
+    }
+}
+
+/// How a repository is labelled in an audit.
+fn source_label(cloned: &ClonedRepo) -> String {
+    let url = cloned.original_url.as_deref().unwrap_or(&cloned.url);
+    if cloned.ref_.is_empty() {
+        url.to_string()
+    } else {
+        format!("{} @ {}", url, cloned.ref_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::RepoCache;
+    use crate::repository::RepositoryManager;
+
+    #[test]
+    fn test_audit_local_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("README.md"),
+            "# __COMMON_REPO__name__ by __COMMON_REPO__ownr__\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.md"), "__COMMON_REPO__other__").unwrap();
+        let config = crate::config::parse(
+            "- template-vars:\n    name: demo\n    owner: me\n- template: [README.md]\n",
+        )
+        .unwrap();
+        let repo_manager = RepositoryManager::new(dir.path().join("cache"));
+        let cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &cache, dir.path());
+
+        let audit = audit(&config, &ctx).unwrap();
+        assert_eq!(audit.unused(), vec!["owner"]);
+        assert_eq!(audit.undefined(), vec!["ownr"]);
+        assert_eq!(
+            audit.defined["name"],
+            BTreeSet::from([LOCAL_SOURCE.to_string()])
+        );
+        assert_eq!(
+            audit.used["name"],
+            BTreeSet::from([PathBuf::from("README.md")])
+        );
+    }
+
+    #[test]
+    fn test_resolve_follows_pipeline_precedence() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let upstream = dir.path().join("up");
+        std::fs::create_dir(&upstream).unwrap();
+        std::fs::write(
+            upstream.join(".common-repo.yaml"),
+            "- include: ['**/*']\n- template: [t.txt]\n- template-vars:\n    owner: base\n    name: up\n",
+        )
+        .unwrap();
+        std::fs::write(
+            upstream.join("t.txt"),
+            "__COMMON_REPO__owner__ __COMMON_REPO__name__ __COMMON_REPO__region__",
+        )
+        .unwrap();
+        let config = crate::config::parse(
+            "- template-vars:\n    owner: early\n- repo:\n    url: ./up\n- template-vars:\n    name: mine\n",
+        )
+        .unwrap();
+        let repo_manager = RepositoryManager::new(dir.path().join("cache"));
+        let cache = RepoCache::new();
+        let ctx = RunContext::new(&repo_manager, &cache, dir.path()).var("region", "eu");
+
+        let resolution = resolve(&config, &ctx).unwrap();
+        let definitions = |name: &str| -> Vec<(String, String)> {
+            resolution[name]
+                .iter()
+                .map(|d| (d.source.clone(), d.value.clone()))
+                .collect()
+        };
+        let pair = |source: &str, value: &str| (source.to_string(), value.to_string());
+        assert_eq!(
+            definitions("owner"),
+            vec![pair("./up", "base"), pair(LOCAL_SOURCE, "early")]
+        );
+        assert_eq!(
+            definitions("name"),
+            vec![pair("./up", "up"), pair(LOCAL_SOURCE, "mine")]
+        );
+        assert_eq!(definitions("region"), vec![pair(OVERRIDE_SOURCE, "eu")]);
+
+        // The last definition is the value the pipeline substitutes.
+        let output = crate::phases::orchestrator::execute_pull_with_context(
+            &config,
+            &ctx,
+            &mut crate::diagnostics::Diagnostics::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            output.get_file("t.txt").unwrap().content.as_ref(),
+            b"early mine eu"
+        );
+    }
+}
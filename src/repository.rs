@@ -12,7 +12,9 @@
 //! and cache operations. This is achieved through two key traits:
 //!
 //! - **`GitOperations`**: Defines the interface for Git-related actions, such as
-//!   cloning a repository and listing its tags.
+//!   cloning a repository and listing its tags. `oci://` URLs are served by a
+//!   second implementation, `DefaultOciOperations`, which pulls OCI artifacts
+//!   through the same interface.
 //!
 //! - **`CacheOperations`**: Defines the interface for cache-related actions, such
 //!   as checking for the existence of a cached item, generating cache paths,
@@ -25,9 +27,14 @@
 //! replaced with mock implementations to simulate various scenarios without
 //! performing real Git operations or touching the filesystem.
 
+use crate::cache::EntryLock;
+use crate::config::VerifyOp;
 use crate::error::Result;
 use crate::filesystem::MemoryFS;
+use crate::reporting::fetches::{Fetch, FetchOutcome};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
 
 /// Trait for git operations - allows mocking in tests
 pub trait GitOperations: Send + Sync {
@@ -37,8 +44,44 @@ pub trait GitOperations: Send + Sync {
     /// space.
     fn clone_shallow(&self, url: &str, ref_name: &str, target_dir: &Path) -> Result<()>;
 
+    /// Clones a repository like [`clone_shallow`](Self::clone_shallow), but
+    /// only needs the files matching the `include` glob patterns, and the
+    /// repository's configuration file.
+    ///
+    /// The default clones everything, for sources that cannot fetch files
+    /// selectively.
+    fn clone_partial(
+        &self,
+        url: &str,
+        ref_name: &str,
+        target_dir: &Path,
+        _include: &[String],
+    ) -> Result<()> {
+        self.clone_shallow(url, ref_name, target_dir)
+    }
+
     /// Retrieves a list of all tags from a remote repository.
     fn list_tags(&self, url: &str) -> Result<Vec<String>>;
+
+    /// Checks that the reference cloned into `repo_dir` is signed by one of
+    /// the signers in `verify`.
+    ///
+    /// The default fails, so a source without signatures is never taken as
+    /// verified.
+    fn verify_signature(
+        &self,
+        _repo_dir: &Path,
+        url: &str,
+        ref_name: &str,
+        _verify: &VerifyOp,
+    ) -> Result<()> {
+        Err(crate::error::Error::SignatureVerification {
+            url: url.to_string(),
+            r#ref: ref_name.to_string(),
+            message: "signatures are not supported for this source".to_string(),
+            hint: None,
+        })
+    }
 }
 
 /// A trait that defines the interface for cache operations.
@@ -86,9 +129,45 @@ impl GitOperations for DefaultGitOperations {
         crate::git::clone_shallow(url, ref_name, target_dir)
     }
 
+    fn clone_partial(
+        &self,
+        url: &str,
+        ref_name: &str,
+        target_dir: &Path,
+        include: &[String],
+    ) -> Result<()> {
+        crate::git::clone_partial(url, ref_name, target_dir, include)
+    }
+
     fn list_tags(&self, url: &str) -> Result<Vec<String>> {
         crate::git::list_tags(url)
     }
+
+    fn verify_signature(
+        &self,
+        repo_dir: &Path,
+        url: &str,
+        ref_name: &str,
+        verify: &VerifyOp,
+    ) -> Result<()> {
+        crate::git::verify_signature(repo_dir, url, ref_name, verify)
+    }
+}
+
+/// Fetches `oci://` URLs as OCI artifacts using the system's `oras` command.
+///
+/// The "clone" is an artifact pull and the tags are the registry
+/// repository's tags; see [`crate::oci`].
+pub struct DefaultOciOperations;
+
+impl GitOperations for DefaultOciOperations {
+    fn clone_shallow(&self, url: &str, ref_name: &str, target_dir: &Path) -> Result<()> {
+        crate::oci::pull(url, ref_name, target_dir)
+    }
+
+    fn list_tags(&self, url: &str) -> Result<Vec<String>> {
+        crate::oci::list_tags(url)
+    }
 }
 
 /// The default implementation of `CacheOperations`, which interacts with the
@@ -140,29 +219,79 @@ pub(crate) fn is_local_url(url: &str) -> bool {
 /// high-level API for fetching repositories.
 pub struct RepositoryManager {
     git_ops: Box<dyn GitOperations>,
+    oci_ops: Box<dyn GitOperations>,
     cache_ops: Box<dyn CacheOperations>,
+    cache_root: Option<PathBuf>,
+    fetches: Mutex<Vec<Fetch>>,
 }
 
 impl RepositoryManager {
     /// Creates a new `RepositoryManager` with the default Git and cache
     /// operations, using the specified `cache_root` for the on-disk cache.
+    ///
+    /// `cache_root` also becomes the cache for single-file downloads; see
+    /// [`crate::http::set_cache_dir`].
     pub fn new(cache_root: PathBuf) -> Self {
+        crate::http::set_cache_dir(cache_root.clone());
         Self {
             git_ops: Box::new(DefaultGitOperations),
-            cache_ops: Box::new(DefaultCacheOperations::new(cache_root)),
+            oci_ops: Box::new(DefaultOciOperations),
+            cache_ops: Box::new(DefaultCacheOperations::new(cache_root.clone())),
+            cache_root: Some(cache_root),
+            fetches: Mutex::default(),
         }
     }
 
+    /// The on-disk cache root, or `None` for a manager built from custom
+    /// operations.
+    pub fn cache_root(&self) -> Option<&Path> {
+        self.cache_root.as_deref()
+    }
+
     /// Creates a `RepositoryManager` with custom `GitOperations` and
     /// `CacheOperations` implementations.
     ///
-    /// This is primarily used for testing to inject mock operations.
-    #[cfg(test)]
+    /// This is primarily used for testing to inject mock operations, such
+    /// as the fake upstreams of the `test_support` feature.
+    #[cfg(any(test, feature = "test_support"))]
     pub fn with_operations(
         git_ops: Box<dyn GitOperations>,
         cache_ops: Box<dyn CacheOperations>,
     ) -> Self {
-        Self { git_ops, cache_ops }
+        Self {
+            git_ops,
+            oci_ops: Box::new(DefaultOciOperations),
+            cache_ops,
+            cache_root: None,
+            fetches: Mutex::default(),
+        }
+    }
+
+    /// Creates a `RepositoryManager` with custom `GitOperations` for git and
+    /// `oci://` URLs.
+    #[cfg(test)]
+    pub fn with_oci_operations(
+        git_ops: Box<dyn GitOperations>,
+        oci_ops: Box<dyn GitOperations>,
+        cache_ops: Box<dyn CacheOperations>,
+    ) -> Self {
+        Self {
+            git_ops,
+            oci_ops,
+            cache_ops,
+            cache_root: None,
+            fetches: Mutex::default(),
+        }
+    }
+
+    /// The operations that fetch `url`: OCI for `oci://` URLs, git
+    /// otherwise.
+    fn fetcher(&self, url: &str) -> &dyn GitOperations {
+        if crate::oci::is_oci_url(url) {
+            self.oci_ops.as_ref()
+        } else {
+            self.git_ops.as_ref()
+        }
     }
 
     /// Fetches a repository, using the cache if a valid entry is available.
@@ -190,20 +319,31 @@ impl RepositoryManager {
         ref_name: &str,
         path: Option<&str>,
     ) -> Result<MemoryFS> {
+        let start = Instant::now();
         if is_local_url(url) {
-            return load_local_path(url, path);
+            let fs = load_local_path(url, path)?;
+            self.record(url, ref_name, path, FetchOutcome::Local, None, start);
+            return Ok(fs);
         }
 
         let cache_path = self.cache_ops.get_cache_path_with_path(url, ref_name, path);
+        let _lock = self.lock_entry(&cache_path)?;
 
         // Check if already cached
-        if !self.cache_ops.exists(&cache_path) {
+        let outcome = if self.cache_ops.exists(&cache_path) {
+            FetchOutcome::Cached
+        } else {
             // Clone to cache
-            self.git_ops.clone_shallow(url, ref_name, &cache_path)?;
-        }
+            self.clone_into_cache(url, ref_name, &cache_path)?;
+            FetchOutcome::Cloned
+        };
 
         // Load from cache with path filtering
-        self.cache_ops.load_from_cache_with_path(&cache_path, path)
+        let fs = self
+            .cache_ops
+            .load_from_cache_with_path(&cache_path, path)?;
+        self.record(url, ref_name, path, outcome, Some(&cache_path), start);
+        Ok(fs)
     }
 
     /// Fetches a repository, bypassing any existing cache entries.
@@ -225,17 +365,244 @@ impl RepositoryManager {
         ref_name: &str,
         path: Option<&str>,
     ) -> Result<MemoryFS> {
+        let start = Instant::now();
         if is_local_url(url) {
-            return load_local_path(url, path);
+            let fs = load_local_path(url, path)?;
+            self.record(url, ref_name, path, FetchOutcome::Local, None, start);
+            return Ok(fs);
         }
 
         let cache_path = self.cache_ops.get_cache_path_with_path(url, ref_name, path);
+        let _lock = self.lock_entry(&cache_path)?;
 
         // Always clone fresh
-        self.git_ops.clone_shallow(url, ref_name, &cache_path)?;
+        self.clone_into_cache(url, ref_name, &cache_path)?;
 
         // Load from cache with path filtering
-        self.cache_ops.load_from_cache_with_path(&cache_path, path)
+        let fs = self
+            .cache_ops
+            .load_from_cache_with_path(&cache_path, path)?;
+        self.record(
+            url,
+            ref_name,
+            path,
+            FetchOutcome::Cloned,
+            Some(&cache_path),
+            start,
+        );
+        Ok(fs)
+    }
+
+    /// Fetches a repository like [`fetch_repository`](Self::fetch_repository),
+    /// as a partial clone of only the files the `include` glob patterns can
+    /// match, for a large upstream of which little is used.
+    ///
+    /// The partial clone is cached apart from the full one, under a key that
+    /// includes the patterns. The files it holds are a superset of those
+    /// that match (see [`crate::git::sparse_patterns`]). With no patterns,
+    /// the whole repository is fetched.
+    pub fn fetch_repository_partial(
+        &self,
+        url: &str,
+        ref_name: &str,
+        include: &[String],
+    ) -> Result<MemoryFS> {
+        if include.is_empty() || is_local_url(url) {
+            return self.fetch_repository(url, ref_name);
+        }
+
+        let start = Instant::now();
+        let cache_path = self.partial_cache_path(url, ref_name, include);
+        let _lock = self.lock_entry(&cache_path)?;
+        let outcome = if self.cache_ops.exists(&cache_path) {
+            FetchOutcome::Cached
+        } else {
+            self.clone_partial_into_cache(url, ref_name, &cache_path, include)?;
+            FetchOutcome::Cloned
+        };
+        let fs = self.cache_ops.load_from_cache(&cache_path)?;
+        self.record(url, ref_name, None, outcome, Some(&cache_path), start);
+        Ok(fs)
+    }
+
+    /// Fetches a repository like [`fetch_repository`](Self::fetch_repository)
+    /// and returns the directory its files can be read from in place: the
+    /// on-disk cache entry, or the directory itself for a local path.
+    ///
+    /// `diff --repo` and `check --repo` read a remote consumer repository
+    /// this way, without a checkout of their own. Managers built from custom
+    /// operations may not put the entry on disk.
+    pub fn fetch_repository_dir(&self, url: &str, ref_name: &str) -> Result<PathBuf> {
+        if is_local_url(url) {
+            let candidate = PathBuf::from(url);
+            return std::fs::canonicalize(&candidate).map_err(|e| {
+                crate::error::Error::LocalPathNotFound {
+                    original: url.to_string(),
+                    attempted: candidate,
+                    source: e,
+                }
+            });
+        }
+
+        let start = Instant::now();
+        let cache_path = self.cache_ops.get_cache_path(url, ref_name);
+        let _lock = self.lock_entry(&cache_path)?;
+        let outcome = if self.cache_ops.exists(&cache_path) {
+            FetchOutcome::Cached
+        } else {
+            self.clone_into_cache(url, ref_name, &cache_path)?;
+            FetchOutcome::Cloned
+        };
+        self.record(url, ref_name, None, outcome, Some(&cache_path), start);
+        Ok(cache_path)
+    }
+
+    /// Locks the on-disk cache entry at `cache_path` against other runs
+    /// sharing the cache root. Managers built from custom operations have no
+    /// on-disk cache to lock.
+    fn lock_entry(&self, cache_path: &Path) -> Result<Option<EntryLock>> {
+        match self.cache_root {
+            Some(_) => EntryLock::acquire(cache_path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Clones `url` at `ref_name` as the cache entry at `cache_path`.
+    ///
+    /// With an on-disk cache, the clone goes to a staging directory that is
+    /// renamed into place once complete, so a failed or interrupted clone
+    /// leaves any previous entry intact. The caller holds the entry's lock.
+    fn clone_into_cache(&self, url: &str, ref_name: &str, cache_path: &Path) -> Result<()> {
+        self.clone_partial_into_cache(url, ref_name, cache_path, &[])
+    }
+
+    /// Clones `url` at `ref_name` as the cache entry at `cache_path`, as a
+    /// partial clone when there are `include` patterns.
+    fn clone_partial_into_cache(
+        &self,
+        url: &str,
+        ref_name: &str,
+        cache_path: &Path,
+        include: &[String],
+    ) -> Result<()> {
+        let clone = |target_dir: &Path| {
+            let fetcher = self.fetcher(url);
+            if include.is_empty() {
+                fetcher.clone_shallow(url, ref_name, target_dir)
+            } else {
+                fetcher.clone_partial(url, ref_name, target_dir, include)
+            }
+        };
+        if self.cache_root.is_none() {
+            return clone(cache_path);
+        }
+
+        let staged = crate::cache::staging_path(cache_path)?;
+        let result = clone(&staged).and_then(|()| crate::cache::commit_staged(&staged, cache_path));
+        if result.is_err() && staged.exists() {
+            let _ = std::fs::remove_dir_all(&staged);
+        }
+        result
+    }
+
+    /// The on-disk cache entry of a repository with an optional sub-path,
+    /// whether or not it is cached, or `None` for a local path.
+    pub fn cache_path(&self, url: &str, ref_name: &str, path: Option<&str>) -> Option<PathBuf> {
+        if is_local_url(url) {
+            return None;
+        }
+        Some(self.cache_ops.get_cache_path_with_path(url, ref_name, path))
+    }
+
+    /// The on-disk cache entry of a partial clone with the `include`
+    /// patterns, or of the full clone when there are none.
+    fn partial_cache_path(&self, url: &str, ref_name: &str, include: &[String]) -> PathBuf {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let cache_path = self.cache_ops.get_cache_path(url, ref_name);
+        if include.is_empty() {
+            return cache_path;
+        }
+        let mut hasher = DefaultHasher::new();
+        include.hash(&mut hasher);
+        let mut name = cache_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!("-partial-{:x}", hasher.finish()));
+        cache_path.with_file_name(name)
+    }
+
+    /// The commit the on-disk cache entry of a repository holds, for
+    /// results cached per commit. `None` for a local path, an entry that is
+    /// not on disk, or a source without commits.
+    fn cached_commit(&self, url: &str, ref_name: &str, include: &[String]) -> Option<String> {
+        if is_local_url(url) || self.cache_root.is_none() {
+            return None;
+        }
+        crate::git::head_commit(&self.partial_cache_path(url, ref_name, include))
+    }
+
+    /// Whether an earlier run found no configuration file in the commit
+    /// that the cached clone of a repository (partial with `include`
+    /// patterns, as for
+    /// [`fetch_repository_partial`](Self::fetch_repository_partial)) holds.
+    pub fn is_known_without_config(&self, url: &str, ref_name: &str, include: &[String]) -> bool {
+        match (&self.cache_root, self.cached_commit(url, ref_name, include)) {
+            (Some(root), Some(commit)) => crate::cache::is_known_without_config(root, &commit),
+            _ => false,
+        }
+    }
+
+    /// Records that the commit the cached clone of a repository holds has
+    /// no configuration file, so that
+    /// [`is_known_without_config`](Self::is_known_without_config) holds on
+    /// later runs. Does nothing without an on-disk cache or a commit.
+    pub fn record_without_config(
+        &self,
+        url: &str,
+        ref_name: &str,
+        include: &[String],
+    ) -> Result<()> {
+        match (&self.cache_root, self.cached_commit(url, ref_name, include)) {
+            (Some(root), Some(commit)) => crate::cache::record_without_config(root, &commit),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every repository fetched so far, in the order the fetches finished.
+    pub fn fetches(&self) -> Vec<Fetch> {
+        self.fetches
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Record a fetch that started at `start` and just finished, into the
+    /// cache entry at `cache_path` if it has one.
+    fn record(
+        &self,
+        url: &str,
+        ref_name: &str,
+        path: Option<&str>,
+        outcome: FetchOutcome,
+        cache_path: Option<&Path>,
+        start: Instant,
+    ) {
+        let commit = match self.cache_root {
+            Some(_) => cache_path.and_then(crate::git::head_commit),
+            None => None,
+        };
+        let fetch = Fetch {
+            url: url.to_string(),
+            ref_: ref_name.to_string(),
+            path: path.map(str::to_string),
+            outcome,
+            commit,
+            millis: start.elapsed().as_millis() as u64,
+        };
+        self.fetches
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(fetch);
     }
 
     /// Checks if a repository is present in the on-disk cache.
@@ -253,9 +620,44 @@ impl RepositoryManager {
         self.cache_ops.exists(&cache_path)
     }
 
+    /// Checks if the partial clone of a repository with the `include`
+    /// patterns is present in the on-disk cache.
+    pub fn is_cached_partial(&self, url: &str, ref_name: &str, include: &[String]) -> bool {
+        if is_local_url(url) {
+            return false;
+        }
+        let cache_path = self.partial_cache_path(url, ref_name, include);
+        self.cache_ops.exists(&cache_path)
+    }
+
+    /// Checks that a fetched repository's tag or commit is signed by one of
+    /// the signers in `verify`.
+    ///
+    /// The repository must already be in the cache, as it is after
+    /// `fetch_repository`.
+    pub fn verify_repository(&self, url: &str, ref_name: &str, verify: &VerifyOp) -> Result<()> {
+        self.verify_repository_partial(url, ref_name, &[], verify)
+    }
+
+    /// Checks the signature like
+    /// [`verify_repository`](Self::verify_repository), in the partial clone
+    /// with the `include` patterns that
+    /// [`fetch_repository_partial`](Self::fetch_repository_partial) made.
+    pub fn verify_repository_partial(
+        &self,
+        url: &str,
+        ref_name: &str,
+        include: &[String],
+        verify: &VerifyOp,
+    ) -> Result<()> {
+        let cache_path = self.partial_cache_path(url, ref_name, include);
+        self.fetcher(url)
+            .verify_signature(&cache_path, url, ref_name, verify)
+    }
+
     /// Retrieves a list of all available tags for a remote repository.
     pub fn list_repository_tags(&self, url: &str) -> Result<Vec<String>> {
-        self.git_ops.list_tags(url)
+        self.fetcher(url).list_tags(url)
     }
 }
 
@@ -343,6 +745,16 @@ mod tests {
         fn list_tags(&self, _url: &str) -> Result<Vec<String>> {
             Ok(self.tags.clone())
         }
+
+        fn verify_signature(
+            &self,
+            _repo_dir: &Path,
+            _url: &str,
+            _ref_name: &str,
+            _verify: &VerifyOp,
+        ) -> Result<()> {
+            Ok(())
+        }
     }
 
     /// Mock cache operations for testing
@@ -473,6 +885,90 @@ mod tests {
         assert_eq!(calls[0].1, "main");
     }
 
+    #[test]
+    fn test_fetch_repository_dir_returns_cache_entry() {
+        let git_ops = Box::new(MockGitOperations::new());
+        let clone_calls = git_ops.clone_calls.clone();
+        let cache_ops = Box::new(MockCacheOperations::new());
+
+        let manager = RepositoryManager::with_operations(git_ops, cache_ops);
+
+        let dir = manager
+            .fetch_repository_dir("https://github.com/test/repo", "main")
+            .unwrap();
+        let calls = clone_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].2, dir);
+    }
+
+    #[test]
+    fn test_fetch_repository_partial_has_own_cache_entry() {
+        let git_ops = Box::new(MockGitOperations::new());
+        let clone_calls = git_ops.clone_calls.clone();
+        let cache_ops = Box::new(MockCacheOperations::new());
+
+        let manager = RepositoryManager::with_operations(git_ops, cache_ops);
+        let url = "https://github.com/test/repo";
+        let include = ["docs/**".to_string()];
+
+        manager
+            .fetch_repository_partial(url, "main", &include)
+            .unwrap();
+        manager.fetch_repository_partial(url, "main", &[]).unwrap();
+
+        let calls = clone_calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        let full = manager.cache_path(url, "main", None).unwrap();
+        assert_eq!(calls[1].2, full);
+        assert_ne!(calls[0].2, full);
+        assert!(calls[0].2.to_string_lossy().contains("-partial-"));
+        assert_eq!(
+            calls[0].2,
+            manager.partial_cache_path(url, "main", &include)
+        );
+    }
+
+    #[test]
+    fn test_fetch_oci_repository_uses_oci_operations() {
+        let git_ops = Box::new(MockGitOperations::new());
+        let git_calls = git_ops.clone_calls.clone();
+        let oci_ops = Box::new(MockGitOperations::new());
+        let oci_calls = oci_ops.clone_calls.clone();
+        let cache_ops = Box::new(MockCacheOperations::new());
+
+        let manager = RepositoryManager::with_oci_operations(git_ops, oci_ops, cache_ops);
+
+        manager
+            .fetch_repository("oci://ghcr.io/org/common-config", "1.2.0")
+            .unwrap();
+
+        assert!(git_calls.lock().unwrap().is_empty());
+        let calls = oci_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, "oci://ghcr.io/org/common-config");
+        assert_eq!(calls[0].1, "1.2.0");
+    }
+
+    #[test]
+    fn test_verify_oci_repository_is_unsupported() {
+        let manager = RepositoryManager::with_operations(
+            Box::new(MockGitOperations::new()),
+            Box::new(MockCacheOperations::new()),
+        );
+        let verify = VerifyOp {
+            gpg: vec!["1111222233334444555566667777888899990000".to_string()],
+            sigstore: vec![],
+        };
+
+        manager
+            .verify_repository("https://github.com/test/repo", "v1.0.0", &verify)
+            .unwrap();
+        let err = manager
+            .verify_repository("oci://ghcr.io/org/config", "1.0.0", &verify)
+            .unwrap_err();
+        assert_eq!(err.code(), "E1012");
+    }
+
     #[test]
     fn test_fetch_repository_already_cached() {
         let git_ops = Box::new(MockGitOperations::new());
@@ -492,6 +988,34 @@ mod tests {
         assert_eq!(calls.len(), 0);
     }
 
+    #[test]
+    fn test_fetches_are_recorded() {
+        let cache_path = PathBuf::from("/mock/cache/https---github.com-test-repo-main");
+        let manager = RepositoryManager::with_operations(
+            Box::new(MockGitOperations::new()),
+            Box::new(MockCacheOperations::with_cached(vec![cache_path.clone()])),
+        );
+
+        manager
+            .fetch_repository("https://github.com/test/repo", "main")
+            .unwrap();
+        manager
+            .fetch_repository_with_path("https://github.com/test/other", "v1", Some("ci"))
+            .unwrap();
+
+        let fetches = manager.fetches();
+        assert_eq!(fetches.len(), 2);
+        assert_eq!(fetches[0].outcome, FetchOutcome::Cached);
+        assert_eq!(fetches[1].outcome, FetchOutcome::Cloned);
+        assert_eq!(fetches[1].ref_, "v1");
+        assert_eq!(fetches[1].path.as_deref(), Some("ci"));
+        assert_eq!(
+            manager.cache_path("https://github.com/test/repo", "main", None),
+            Some(cache_path)
+        );
+        assert_eq!(manager.cache_path("./local", "", None), None);
+    }
+
     #[test]
     fn test_fetch_repository_fresh_always_clones() {
         let git_ops = Box::new(MockGitOperations::new());
@@ -706,6 +1230,67 @@ mod tests {
         assert!(fs.exists("hello.txt"));
     }
 
+    #[test]
+    fn concurrent_fetches_share_one_complete_cache_entry() {
+        use std::process::Command;
+        use tempfile::TempDir;
+
+        let upstream = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(upstream.path())
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test"]);
+        std::fs::write(upstream.path().join("hello.txt"), b"world").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+        let url = format!("file://{}", upstream.path().display());
+
+        let cache_root = TempDir::new().unwrap();
+        let manager = RepositoryManager::new(cache_root.path().to_path_buf());
+        std::thread::scope(|scope| {
+            let fetches: Vec<_> = (0..4)
+                .map(|i| {
+                    let (manager, url) = (&manager, &url);
+                    scope.spawn(move || {
+                        if i % 2 == 0 {
+                            manager.fetch_repository(url, "main")
+                        } else {
+                            manager.fetch_repository_fresh(url, "main")
+                        }
+                    })
+                })
+                .collect();
+            for fetch in fetches {
+                let fs = fetch.join().unwrap().unwrap();
+                assert!(fs.exists("hello.txt"));
+            }
+        });
+
+        // Only the finished entry remains, next to empty lock and staging
+        // directories.
+        let mut names: Vec<_> = std::fs::read_dir(cache_root.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3, "unexpected cache contents: {names:?}");
+        assert_eq!(names[..2], [".locks", ".staging"]);
+        for dir in &names[..2] {
+            assert!(std::fs::read_dir(cache_root.path().join(dir))
+                .unwrap()
+                .next()
+                .is_none());
+        }
+    }
+
     #[test]
     fn fetch_repository_with_path_local_missing_dir_is_local_path_not_found() {
         let cache_root = tempfile::TempDir::new().unwrap();
@@ -0,0 +1,277 @@
+//! # Secret Template Variables
+//!
+//! A `template-vars` entry can read its value from a secret source instead
+//! of giving it inline (see [`SecretSource`]):
+//!
+//! - `from_env_file`: a dotenv file of `NAME=value` lines.
+//! - `from_sops`: a SOPS-encrypted YAML or JSON file, decrypted by running
+//!   `sops --decrypt`.
+//! - `from_command`: the output of a shell command, such as
+//!   `vault kv get -field=token secret/ci`.
+//!
+//! Sources are read only from the consumer's own configuration: an upstream
+//! could otherwise run commands on every machine that inherits from it. A
+//! configuration downloaded with `apply --config-url` counts as the
+//! consumer's own only when `--config-sha256` pins its content.
+//! Relative file paths are resolved from the working directory.
+//!
+//! Every value read is registered as sensitive. [`mask`] replaces sensitive
+//! values with [`MASK`], and is applied to everything written through
+//! [`crate::output`], to `diff --patch` and to variable reports, so that a
+//! token templated into a file does not end up in a CI log. Values shorter
+//! than [`MIN_MASKED_LEN`] characters are not masked, as masking them would
+//! garble unrelated output.
+//!
+//! ## Example
+//!
+//! ```
+//! use common_repo::secrets;
+//!
+//! secrets::register("s3cr3t-t0ken");
+//! assert_eq!(secrets::mask("token: s3cr3t-t0ken"), "token: ********");
+//! ```
+
+use std::borrow::Cow;
+use std::process::Command;
+use std::sync::RwLock;
+
+use crate::config::SecretSource;
+use crate::error::{Error, Result};
+use crate::merge::{parse_path, PathSegment};
+
+/// What sensitive values are replaced with.
+pub const MASK: &str = "********";
+
+/// The length below which a value is not masked.
+pub const MIN_MASKED_LEN: usize = 4;
+
+/// The values read from secret sources in this process.
+static SENSITIVE: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Mark `value` as sensitive, so that [`mask`] hides it.
+pub fn register(value: &str) {
+    if value.chars().count() < MIN_MASKED_LEN {
+        return;
+    }
+    let mut sensitive = SENSITIVE.write().unwrap_or_else(|e| e.into_inner());
+    if !sensitive.iter().any(|known| known == value) {
+        sensitive.push(value.to_string());
+        // Longest first, so a value containing another is masked whole
+        sensitive.sort_by_key(|known| std::cmp::Reverse(known.len()));
+    }
+}
+
+/// `text` with every sensitive value replaced by [`MASK`].
+pub fn mask(text: &str) -> Cow<'_, str> {
+    let sensitive = SENSITIVE.read().unwrap_or_else(|e| e.into_inner());
+    let mut masked = Cow::Borrowed(text);
+    for value in sensitive.iter() {
+        if masked.contains(value.as_str()) {
+            masked = Cow::Owned(masked.replace(value.as_str(), MASK));
+        }
+    }
+    masked
+}
+
+/// Read the value of the secret variable `name` from `source`, and register
+/// it as sensitive.
+///
+/// # Errors
+///
+/// Returns `Error::Template` naming the variable if the source cannot be
+/// read or does not hold the value.
+pub fn read(name: &str, source: &SecretSource) -> Result<String> {
+    let key = source.key.as_deref().unwrap_or(name);
+    let value = if let Some(path) = &source.from_env_file {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| fail(name, format!("could not read {}: {}", path, e)))?;
+        env_file_value(&content, key)
+            .ok_or_else(|| fail(name, format!("{} does not set {}", path, key)))?
+    } else if let Some(path) = &source.from_sops {
+        let decrypted = run(name, Command::new("sops").args(["--decrypt", path]))?;
+        sops_value(&decrypted, key)
+            .map_err(|message| fail(name, format!("{}: {}", path, message)))?
+    } else if let Some(command) = &source.from_command {
+        let mut output = run(name, &mut shell(command))?;
+        while output.ends_with('\n') || output.ends_with('\r') {
+            output.pop();
+        }
+        output
+    } else {
+        return Err(fail(name, "no secret source is set".to_string()));
+    };
+    register(&value);
+    Ok(value)
+}
+
+fn fail(name: &str, message: String) -> Error {
+    Error::Template {
+        message: format!("Secret template variable '{}': {}", name, message),
+        variable: Some(name.to_string()),
+    }
+}
+
+/// A command running `command` in the platform's shell. Also used to run
+/// the command of `apply --run`.
+pub fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+/// Run `command` and return its standard output. Its standard error is left
+/// out of the error, as it may echo the secret.
+fn run(name: &str, command: &mut Command) -> Result<String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| fail(name, format!("could not run {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(fail(
+            name,
+            format!("{} exited with {}", program, output.status),
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        fail(
+            name,
+            format!("{} printed something that is not UTF-8", program),
+        )
+    })
+}
+
+/// The value `key` is set to in dotenv `content`.
+fn env_file_value(content: &str, key: &str) -> Option<String> {
+    content.lines().rev().find_map(|line| {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line.split_once('=')?;
+        if name.trim() != key || line.starts_with('#') {
+            return None;
+        }
+        let value = value.trim();
+        let unquoted = ['"', '\'']
+            .iter()
+            .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote));
+        Some(unquoted.unwrap_or(value).to_string())
+    })
+}
+
+/// The scalar at `key` in decrypted SOPS output, YAML or JSON.
+fn sops_value(decrypted: &str, key: &str) -> std::result::Result<String, String> {
+    let document: serde_yaml::Value = serde_yaml::from_str(decrypted)
+        .map_err(|e| format!("could not parse the decrypted file: {}", e))?;
+    let mut current = &document;
+    for segment in parse_path(key) {
+        let next = match segment {
+            PathSegment::Key(name) => current.get(name.as_str()),
+            PathSegment::Index(index) => current.get(index),
+        };
+        current = next.ok_or_else(|| format!("nothing is at {}", key))?;
+    }
+    match current {
+        serde_yaml::Value::String(value) => Ok(value.clone()),
+        serde_yaml::Value::Number(value) => Ok(value.to_string()),
+        serde_yaml::Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(format!("{} is not a single value", key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_source() -> SecretSource {
+        SecretSource {
+            from_env_file: None,
+            from_sops: None,
+            from_command: None,
+            key: None,
+        }
+    }
+
+    #[test]
+    fn test_mask_replaces_registered_values() {
+        register("hunter2-long");
+        register("hunter2-long-and-longer");
+        register("abc");
+        assert_eq!(
+            mask("a=hunter2-long b=hunter2-long-and-longer c=abc"),
+            "a=******** b=******** c=abc"
+        );
+        assert!(matches!(mask("nothing secret"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_env_file_value() {
+        let content = "# tokens\nexport NPM_TOKEN=\"npm_abc\"\nGH_TOKEN = ghp_1\n#GH_TOKEN=old\n";
+        assert_eq!(
+            env_file_value(content, "NPM_TOKEN").as_deref(),
+            Some("npm_abc")
+        );
+        assert_eq!(
+            env_file_value(content, "GH_TOKEN").as_deref(),
+            Some("ghp_1")
+        );
+        assert_eq!(env_file_value(content, "MISSING"), None);
+    }
+
+    #[test]
+    fn test_sops_value() {
+        let decrypted = "database:\n  password: pa55word\n  port: 5432\nsops:\n  version: 3.8.1\n";
+        assert_eq!(
+            sops_value(decrypted, "database.password").unwrap(),
+            "pa55word"
+        );
+        assert_eq!(sops_value(decrypted, "database.port").unwrap(), "5432");
+        assert!(sops_value(decrypted, "database")
+            .unwrap_err()
+            .contains("not a single value"));
+        assert!(sops_value(decrypted, "api.token").is_err());
+    }
+
+    #[test]
+    fn test_read_from_env_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "API_TOKEN=from-env-file-123\n").unwrap();
+        let source = SecretSource {
+            from_env_file: Some(path.to_string_lossy().into_owned()),
+            key: Some("API_TOKEN".to_string()),
+            ..no_source()
+        };
+        assert_eq!(read("token", &source).unwrap(), "from-env-file-123");
+        assert_eq!(mask("from-env-file-123"), MASK);
+
+        let missing = SecretSource {
+            key: None,
+            ..source
+        };
+        let err = read("token", &missing).unwrap_err();
+        assert!(err.to_string().contains("does not set token"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_from_command() {
+        let source = SecretSource {
+            from_command: Some("printf 'from-command-456\\n'".to_string()),
+            ..no_source()
+        };
+        assert_eq!(read("token", &source).unwrap(), "from-command-456");
+
+        let failing = SecretSource {
+            from_command: Some("echo from-stderr >&2; exit 3".to_string()),
+            ..no_source()
+        };
+        let err = read("token", &failing).unwrap_err().to_string();
+        assert!(err.contains("exited with"));
+        assert!(!err.contains("from-stderr"));
+    }
+}
@@ -18,20 +18,52 @@
 
 use std::path::Path;
 
+use crate::outcome::{Failure, Outcome};
+
 /// Generate an error for when the configuration file is not found.
 ///
 /// Includes hints about:
 /// - Creating a new config file
 /// - Using the -c/--config flag
 /// - Using the COMMON_REPO_CONFIG environment variable
+///
+/// The command ends with [`Outcome::ConfigError`].
 pub fn config_not_found(path: &Path) -> anyhow::Error {
-    anyhow::anyhow!(
-        "Configuration file not found: {path}\n\n\
-         hint: Create a .common-repo.yaml file in your project root\n\
-         hint: Use -c/--config to specify a different path\n\
-         hint: Set COMMON_REPO_CONFIG environment variable",
-        path = path.display()
+    Failure::new(
+        Outcome::ConfigError,
+        format!(
+            "Configuration file not found: {path}\n\n\
+             hint: Create a .common-repo.yaml file in your project root\n\
+             hint: Use -c/--config to specify a different path\n\
+             hint: Set COMMON_REPO_CONFIG environment variable",
+            path = path.display()
+        ),
     )
+    .into()
+}
+
+/// Generate an error for a `--pipeline` that has no configuration.
+///
+/// Lists the pipelines that do exist. The command ends with
+/// [`Outcome::ConfigError`].
+pub fn pipeline_not_found(name: &str, path: &Path, available: &[String]) -> anyhow::Error {
+    let hint = if available.is_empty() {
+        format!(
+            "hint: Create {} to configure the '{}' pipeline",
+            path.display(),
+            name
+        )
+    } else {
+        format!("hint: Available pipelines: {}", available.join(", "))
+    };
+    Failure::new(
+        Outcome::ConfigError,
+        format!(
+            "Pipeline '{name}' not found: {path}\n\n{hint}",
+            path = path.display()
+        ),
+    )
+    .into()
 }
 
 /// Generate an error for when cache clean is called without filters.
@@ -129,7 +161,7 @@ pub fn tool_version_invalid(tool: &str, version: &str, error: &str) -> anyhow::E
 /// Find a similar string from a list of candidates using edit distance.
 ///
 /// Returns Some(candidate) if a close match is found (edit distance <= 2).
-fn find_similar<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+pub(crate) fn find_similar<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
     candidates
         .iter()
         .filter_map(|&candidate| {
@@ -200,6 +232,18 @@ mod tests {
         assert!(message.contains("COMMON_REPO_CONFIG"));
     }
 
+    #[test]
+    fn test_pipeline_not_found_lists_available() {
+        let path = Path::new(".common-repo/release.yaml");
+        let message = pipeline_not_found("release", path, &["ci".to_string(), "docs".to_string()])
+            .to_string();
+        assert!(message.contains("Pipeline 'release' not found: .common-repo/release.yaml"));
+        assert!(message.contains("hint: Available pipelines: ci, docs"));
+
+        let message = pipeline_not_found("release", path, &[]).to_string();
+        assert!(message.contains("hint: Create .common-repo/release.yaml"));
+    }
+
     #[test]
     fn test_cache_clean_no_filter_includes_hints() {
         let error = cache_clean_no_filter();
@@ -0,0 +1,237 @@
+//! # In-Memory Upstreams
+//!
+//! Fakes for crates that embed this library and want to test their own
+//! pipelines without the network, a cache directory or temporary git
+//! repositories. This module is only available with the `test_support`
+//! feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! common-repo = { version = "0.37", features = ["test_support"] }
+//! ```
+//!
+//! A [`FakeUpstream`] is a repository's files held in memory. Registered in
+//! [`FakeUpstreams`] under a URL and ref, it is what a `repo:` operation
+//! with that URL and ref fetches through the [`RepositoryManager`] the
+//! registry builds:
+//!
+//! ```ignore
+//! use common_repo::test_support::{FakeUpstream, FakeUpstreams};
+//!
+//! let upstreams = FakeUpstreams::new().with_upstream(
+//!     "https://github.com/acme/ci-config",
+//!     "v1.0.0",
+//!     FakeUpstream::new()
+//!         .with_config("- include: ['**']\n")
+//!         .with_file(".github/workflows/ci.yml", "on: push\n"),
+//! );
+//! let repo_manager = upstreams.repository_manager();
+//! // Run the pipeline with `repo_manager`, e.g. through
+//! // `common_repo::phases::orchestrator::execute_pull`.
+//! ```
+//!
+//! An upstream registered again under the same URL and ref replaces the
+//! earlier one, also for managers already built, so a test can publish a
+//! new version between runs. The tags of a URL are the refs registered for
+//! it. Fetching a URL and ref that were not registered fails as an
+//! unreachable host would, and signatures never verify. Local paths are
+//! read from disk as usual.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::defaults::DEFAULT_CONFIG_FILENAME;
+use crate::error::{Error, Result};
+use crate::filesystem::{File, MemoryFS};
+use crate::repository::{CacheOperations, GitOperations, RepositoryManager};
+
+/// Where the cache entries of fake upstreams appear to be. Nothing is
+/// written there.
+const FAKE_CACHE_ROOT: &str = "/fake-upstreams";
+
+/// The files of a repository, held in memory.
+#[derive(Debug, Clone, Default)]
+pub struct FakeUpstream {
+    fs: MemoryFS,
+}
+
+impl FakeUpstream {
+    /// An upstream without files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the file `path` with `content`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `path` is not a relative path inside the repository.
+    pub fn with_file(mut self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) -> Self {
+        let path = path.as_ref();
+        if let Err(e) = self.fs.add_file(path, File::new(content.into())) {
+            panic!("invalid fake upstream file {}: {}", path.display(), e);
+        }
+        self
+    }
+
+    /// Add the file `path` with `content` and the unix `mode`, such as
+    /// `0o755` for a script.
+    pub fn with_mode(
+        mut self,
+        path: impl AsRef<Path>,
+        content: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Self {
+        let path = path.as_ref();
+        let mut file = File::new(content.into());
+        file.permissions = mode;
+        if let Err(e) = self.fs.add_file(path, file) {
+            panic!("invalid fake upstream file {}: {}", path.display(), e);
+        }
+        self
+    }
+
+    /// Use `yaml` as the upstream's configuration file.
+    pub fn with_config(self, yaml: &str) -> Self {
+        self.with_file(DEFAULT_CONFIG_FILENAME, yaml)
+    }
+
+    /// The upstream's files.
+    pub fn files(&self) -> &MemoryFS {
+        &self.fs
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    /// The upstreams by URL and ref.
+    upstreams: BTreeMap<(String, String), MemoryFS>,
+    /// The URL and ref each cache entry was "cloned" from.
+    entries: HashMap<PathBuf, (String, String)>,
+}
+
+/// Fake upstreams by URL and ref, and the repository managers that fetch
+/// them.
+#[derive(Clone, Default)]
+pub struct FakeUpstreams {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl FakeUpstreams {
+    /// A registry without upstreams.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `upstream` as the repository at `url` and `ref_name`.
+    pub fn with_upstream(self, url: &str, ref_name: &str, upstream: FakeUpstream) -> Self {
+        self.insert(url, ref_name, upstream);
+        self
+    }
+
+    /// Register `upstream` as the repository at `url` and `ref_name`,
+    /// replacing any upstream registered there before.
+    pub fn insert(&self, url: &str, ref_name: &str, upstream: FakeUpstream) {
+        self.lock()
+            .upstreams
+            .insert((url.to_string(), ref_name.to_string()), upstream.fs);
+    }
+
+    /// A repository manager that fetches the registered upstreams.
+    pub fn repository_manager(&self) -> RepositoryManager {
+        RepositoryManager::with_operations(Box::new(self.clone()), Box::new(self.clone()))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // A test that panicked while holding the lock left nothing half
+        // written that later readers could trip over.
+        self.registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The files of the cache entry at `cache_path`.
+    fn entry(&self, cache_path: &Path) -> Result<MemoryFS> {
+        let registry = self.lock();
+        registry
+            .entries
+            .get(cache_path)
+            .and_then(|key| registry.upstreams.get(key))
+            .cloned()
+            .ok_or_else(|| Error::Cache {
+                message: format!("No fake upstream cached at {}", cache_path.display()),
+            })
+    }
+}
+
+impl GitOperations for FakeUpstreams {
+    fn clone_shallow(&self, url: &str, ref_name: &str, target_dir: &Path) -> Result<()> {
+        let key = (url.to_string(), ref_name.to_string());
+        let mut registry = self.lock();
+        if !registry.upstreams.contains_key(&key) {
+            return Err(Error::GitClone {
+                url: url.to_string(),
+                r#ref: ref_name.to_string(),
+                message: "no fake upstream is registered for this URL and ref".to_string(),
+                hint: Some("Register it with FakeUpstreams::with_upstream".to_string()),
+            });
+        }
+        registry.entries.insert(target_dir.to_path_buf(), key);
+        Ok(())
+    }
+
+    fn list_tags(&self, url: &str) -> Result<Vec<String>> {
+        Ok(self
+            .lock()
+            .upstreams
+            .keys()
+            .filter(|(registered, _)| registered == url)
+            .map(|(_, ref_name)| ref_name.clone())
+            .collect())
+    }
+}
+
+impl CacheOperations for FakeUpstreams {
+    fn exists(&self, cache_path: &Path) -> bool {
+        self.lock().entries.contains_key(cache_path)
+    }
+
+    fn get_cache_path(&self, url: &str, ref_name: &str) -> PathBuf {
+        self.get_cache_path_with_path(url, ref_name, None)
+    }
+
+    fn get_cache_path_with_path(&self, url: &str, ref_name: &str, path: Option<&str>) -> PathBuf {
+        let mut key = format!("{}@{}", url.replace(['/', ':'], "-"), ref_name);
+        if let Some(path) = path {
+            key.push_str("-path-");
+            key.push_str(&path.replace(['/', '.'], "-"));
+        }
+        Path::new(FAKE_CACHE_ROOT).join(key)
+    }
+
+    fn load_from_cache(&self, cache_path: &Path) -> Result<MemoryFS> {
+        self.entry(cache_path)
+    }
+
+    fn load_from_cache_with_path(&self, cache_path: &Path, path: Option<&str>) -> Result<MemoryFS> {
+        let fs = self.entry(cache_path)?;
+        let Some(path) = path else {
+            return Ok(fs);
+        };
+        let prefix = Path::new(path.trim_matches('/'));
+        let mut filtered = MemoryFS::new();
+        for (file_path, file) in fs.files() {
+            if let Ok(relative) = file_path.strip_prefix(prefix) {
+                if !relative.as_os_str().is_empty() {
+                    filtered.add_file(relative, file.clone())?;
+                }
+            }
+        }
+        Ok(filtered)
+    }
+
+    fn save_to_cache(&self, _cache_path: &Path, _fs: &MemoryFS) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+//! # Pipeline Test Harness
+//!
+//! Helpers for testing `.common-repo.yaml` configurations end to end. This
+//! module is only available with the `testing` feature and is intended for
+//! upstream template authors who want to test their own repositories with
+//! this crate as a dev-dependency:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! common-repo = { version = "0.37", features = ["testing"] }
+//! ```
+//!
+//! ## Golden-File Tests
+//!
+//! A *fixture* is a consumer repository checked in as a plain directory
+//! tree: a `.common-repo.yaml` plus whatever local files it needs. Upstreams
+//! are usually sibling fixture directories referenced with local paths
+//! (`url: ../upstream`). A *golden* directory holds the exact files the
+//! pipeline should produce for that fixture.
+//!
+//! ```ignore
+//! #[test]
+//! fn consumer_a() {
+//!     common_repo::assert_pipeline!("fixtures/consumer-a", expected = "golden/consumer-a");
+//! }
+//! ```
+//!
+//! Paths in the macros are relative to the calling crate's
+//! `CARGO_MANIFEST_DIR`. Files are compared by content only. The consumer's
+//! own config file is left out of the comparison on both sides.
+//!
+//! Set `COMMON_REPO_UPDATE_GOLDEN=1` to rewrite the golden directory from the
+//! current pipeline output instead of comparing against it.
+//!
+//! ## Mutation Tests
+//!
+//! A golden test only proves that the current configuration produces the
+//! current output. It cannot tell when an operation has no effect, such as
+//! an `exclude` whose pattern never matches. [`mutants`] derives variants of
+//! a configuration with one operation or pattern removed, and
+//! [`surviving_mutants`] reports those whose output is identical to the
+//! unmodified run.
+//!
+//! ```ignore
+//! #[test]
+//! fn consumer_a_ops_are_live() {
+//!     common_repo::assert_no_surviving_mutants!("fixtures/consumer-a");
+//! }
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use crate::cache::RepoCache;
+use crate::config::{self, Operation, Schema};
+use crate::defaults::{ConfigFiles, DEFAULT_CONFIG_FILENAME};
+use crate::diagnostics::Diagnostics;
+use crate::error::{Error, Result};
+use crate::filesystem::MemoryFS;
+use crate::git::{load_directory_into_memfs, LoadOptions};
+use crate::phases::{orchestrator, phase6};
+use crate::repository::RepositoryManager;
+
+/// Environment variable that switches golden checks into update mode.
+pub const UPDATE_GOLDEN_ENV: &str = "COMMON_REPO_UPDATE_GOLDEN";
+
+/// A consumer fixture ready to run through the pipeline.
+///
+/// Each `Pipeline` owns a private, temporary cache so runs never touch the
+/// user's cache and repeated runs (e.g. for mutants) reuse the same clones.
+pub struct Pipeline {
+    consumer_dir: PathBuf,
+    config: Schema,
+    repo_manager: RepositoryManager,
+    repo_cache: RepoCache,
+    _cache_root: TempDir,
+}
+
+impl Pipeline {
+    /// Load the configuration of the fixture at `consumer_dir`.
+    pub fn load(consumer_dir: impl AsRef<Path>) -> Result<Self> {
+        let consumer_dir = consumer_dir.as_ref().to_path_buf();
+        let config_path = ConfigFiles::load()
+            .find_in_dir(&consumer_dir)
+            .ok_or_else(|| Error::ConfigParse {
+                message: format!("No configuration file found in {}", consumer_dir.display()),
+                hint: Some(format!(
+                    "Fixtures need a {} at their root",
+                    DEFAULT_CONFIG_FILENAME
+                )),
+            })?;
+        let config = config::from_file(&config_path)?;
+        let cache_root = TempDir::new()?;
+
+        Ok(Self {
+            repo_manager: RepositoryManager::new(cache_root.path().to_path_buf()),
+            repo_cache: RepoCache::new(),
+            consumer_dir,
+            config,
+            _cache_root: cache_root,
+        })
+    }
+
+    /// The configuration parsed from the fixture.
+    pub fn config(&self) -> &Schema {
+        &self.config
+    }
+
+    /// Run the fixture's own configuration and return the resulting files.
+    pub fn run(&self) -> Result<MemoryFS> {
+        self.run_with(&self.config)
+    }
+
+    /// Run `config` in place of the fixture's configuration, using the
+    /// fixture directory as the working directory.
+    pub fn run_with(&self, config: &Schema) -> Result<MemoryFS> {
+        let mut fs = orchestrator::execute_pull(
+            config,
+            &self.repo_manager,
+            &self.repo_cache,
+            &self.consumer_dir,
+            None,
+            &mut Diagnostics::new(),
+        )?;
+        strip_config_files(&mut fs);
+        Ok(fs)
+    }
+}
+
+/// Run the pipeline for the fixture at `consumer_dir` without writing
+/// anything to disk.
+pub fn run_pipeline(consumer_dir: impl AsRef<Path>) -> Result<MemoryFS> {
+    Pipeline::load(consumer_dir)?.run()
+}
+
+/// Load a golden directory as a [`MemoryFS`], leaving out config files.
+pub fn load_tree(dir: impl AsRef<Path>) -> Result<MemoryFS> {
+    let mut fs = load_directory_into_memfs(dir.as_ref(), LoadOptions::default())?;
+    strip_config_files(&mut fs);
+    Ok(fs)
+}
+
+fn strip_config_files(fs: &mut MemoryFS) {
+    for name in ConfigFiles::load().names() {
+        let _ = fs.remove_file(name);
+    }
+}
+
+/// Differences between a pipeline result and a golden tree.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Files in the golden tree that the pipeline did not produce.
+    pub missing: Vec<PathBuf>,
+    /// Files the pipeline produced that are not in the golden tree.
+    pub unexpected: Vec<PathBuf>,
+    /// Files present in both whose content differs.
+    pub changed: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    /// Returns `true` if the two trees are identical.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.missing {
+            writeln!(f, "  missing:    {}", path.display())?;
+        }
+        for path in &self.unexpected {
+            writeln!(f, "  unexpected: {}", path.display())?;
+        }
+        for path in &self.changed {
+            writeln!(f, "  changed:    {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the files of `actual` against `expected` by content.
+pub fn compare(actual: &MemoryFS, expected: &MemoryFS) -> TreeDiff {
+    let actual_paths: BTreeSet<_> = actual.list_files().into_iter().collect();
+    let expected_paths: BTreeSet<_> = expected.list_files().into_iter().collect();
+
+    let mut diff = TreeDiff {
+        missing: expected_paths.difference(&actual_paths).cloned().collect(),
+        unexpected: actual_paths.difference(&expected_paths).cloned().collect(),
+        changed: Vec::new(),
+    };
+    for path in actual_paths.intersection(&expected_paths) {
+        let (Some(a), Some(e)) = (actual.get_file(path), expected.get_file(path)) else {
+            continue;
+        };
+        if a.content != e.content {
+            diff.changed.push(path.clone());
+        }
+    }
+    diff
+}
+
+/// Run the fixture at `consumer_dir` and compare it with `golden_dir`.
+///
+/// When [`UPDATE_GOLDEN_ENV`] is set, `golden_dir` is replaced with the
+/// pipeline output and an empty diff is returned.
+pub fn check_golden(
+    consumer_dir: impl AsRef<Path>,
+    golden_dir: impl AsRef<Path>,
+) -> Result<TreeDiff> {
+    let golden_dir = golden_dir.as_ref();
+    let actual = run_pipeline(consumer_dir)?;
+
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if golden_dir.exists() {
+            std::fs::remove_dir_all(golden_dir)?;
+        }
+        std::fs::create_dir_all(golden_dir)?;
+        phase6::execute(&actual, golden_dir, phase6::WriteOptions::default())?;
+        return Ok(TreeDiff::default());
+    }
+
+    if !golden_dir.is_dir() {
+        return Err(Error::Filesystem {
+            message: format!(
+                "Golden directory {} does not exist (set {}=1 to create it)",
+                golden_dir.display(),
+                UPDATE_GOLDEN_ENV
+            ),
+        });
+    }
+    Ok(compare(&actual, &load_tree(golden_dir)?))
+}
+
+/// Panicking form of [`check_golden`] for use in tests.
+pub fn assert_golden(consumer_dir: impl AsRef<Path>, golden_dir: impl AsRef<Path>) {
+    let consumer_dir = consumer_dir.as_ref();
+    let golden_dir = golden_dir.as_ref();
+    match check_golden(consumer_dir, golden_dir) {
+        Ok(diff) if diff.is_empty() => {}
+        Ok(diff) => panic!(
+            "pipeline output for {} does not match {}:\n{}(set {}=1 to update)",
+            consumer_dir.display(),
+            golden_dir.display(),
+            diff,
+            UPDATE_GOLDEN_ENV
+        ),
+        Err(e) => panic!("pipeline failed for {}: {}", consumer_dir.display(), e),
+    }
+}
+
+/// A configuration with a single operation or pattern removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mutant {
+    /// Human-readable description of what was removed.
+    pub description: String,
+    /// The mutated configuration.
+    pub config: Schema,
+}
+
+/// Derive every single-step mutant of `config`.
+///
+/// Each top-level operation yields one mutant with that operation removed.
+/// `include`, `exclude` and `template` operations with more than one
+/// pattern also yield one mutant per pattern with just that pattern
+/// removed.
+pub fn mutants(config: &Schema) -> Vec<Mutant> {
+    let mut out = Vec::new();
+    for (index, op) in config.iter().enumerate() {
+        let label = format!("operation {} ({})", index + 1, op.kind());
+
+        let mut removed = config.clone();
+        removed.remove(index);
+        out.push(Mutant {
+            description: format!("remove {}", label),
+            config: removed,
+        });
+
+        let pattern_count = patterns_mut(&mut op.clone()).map_or(0, |p| p.len());
+        if pattern_count < 2 {
+            continue;
+        }
+        for pattern_index in 0..pattern_count {
+            let mut mutated = config.clone();
+            let patterns =
+                patterns_mut(&mut mutated[index]).expect("operation kind is unchanged by cloning");
+            let pattern = patterns.remove(pattern_index);
+            out.push(Mutant {
+                description: format!("drop pattern {:?} from {}", pattern, label),
+                config: mutated,
+            });
+        }
+    }
+    out
+}
+
+fn patterns_mut(op: &mut Operation) -> Option<&mut Vec<String>> {
+    match op {
+        Operation::Include { include, .. } => Some(&mut include.patterns),
+        Operation::Exclude { exclude } => Some(&mut exclude.patterns),
+        Operation::Template { template } => Some(&mut template.patterns),
+        _ => None,
+    }
+}
+
+/// Run every mutant of the fixture's configuration and return those whose
+/// output is identical to the unmodified run.
+///
+/// A mutant that fails to run counts as detected.
+pub fn surviving_mutants(consumer_dir: impl AsRef<Path>) -> Result<Vec<Mutant>> {
+    let pipeline = Pipeline::load(consumer_dir)?;
+    let baseline = pipeline.run()?;
+
+    Ok(mutants(pipeline.config())
+        .into_iter()
+        .filter(|mutant| match pipeline.run_with(&mutant.config) {
+            Ok(fs) => compare(&fs, &baseline).is_empty(),
+            Err(_) => false,
+        })
+        .collect())
+}
+
+/// Panicking form of [`surviving_mutants`] for use in tests.
+pub fn assert_no_surviving_mutants(consumer_dir: impl AsRef<Path>) {
+    let consumer_dir = consumer_dir.as_ref();
+    let survivors = surviving_mutants(consumer_dir)
+        .unwrap_or_else(|e| panic!("pipeline failed for {}: {}", consumer_dir.display(), e));
+    if !survivors.is_empty() {
+        let list: Vec<_> = survivors
+            .iter()
+            .map(|m| format!("  {}", m.description))
+            .collect();
+        panic!(
+            "{} mutant(s) of {} produce unchanged output:\n{}",
+            survivors.len(),
+            consumer_dir.display(),
+            list.join("\n")
+        );
+    }
+}
+
+/// Assert that a fixture produces the files in a golden directory.
+///
+/// Both paths are relative to the calling crate's `CARGO_MANIFEST_DIR`.
+///
+/// ```ignore
+/// common_repo::assert_pipeline!("fixtures/consumer-a", expected = "golden/consumer-a");
+/// ```
+#[macro_export]
+macro_rules! assert_pipeline {
+    ($consumer:expr, expected = $golden:expr $(,)?) => {{
+        let root = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        $crate::testing::assert_golden(root.join($consumer), root.join($golden));
+    }};
+}
+
+/// Assert that removing any single operation or pattern from a fixture's
+/// configuration changes its output.
+///
+/// The path is relative to the calling crate's `CARGO_MANIFEST_DIR`.
+#[macro_export]
+macro_rules! assert_no_surviving_mutants {
+    ($consumer:expr $(,)?) => {{
+        let root = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        $crate::testing::assert_no_surviving_mutants(root.join($consumer));
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_reports_each_kind_of_difference() {
+        let mut actual = MemoryFS::new();
+        actual.add_file_string("same.txt", "a").unwrap();
+        actual.add_file_string("changed.txt", "new").unwrap();
+        actual.add_file_string("extra.txt", "x").unwrap();
+
+        let mut expected = MemoryFS::new();
+        expected.add_file_string("same.txt", "a").unwrap();
+        expected.add_file_string("changed.txt", "old").unwrap();
+        expected.add_file_string("gone.txt", "y").unwrap();
+
+        let diff = compare(&actual, &expected);
+        assert_eq!(diff.missing, vec![PathBuf::from("gone.txt")]);
+        assert_eq!(diff.unexpected, vec![PathBuf::from("extra.txt")]);
+        assert_eq!(diff.changed, vec![PathBuf::from("changed.txt")]);
+        assert!(!diff.is_empty());
+        assert!(compare(&actual, &actual).is_empty());
+    }
+
+    #[test]
+    fn test_mutants_remove_ops_and_patterns() {
+        let config = config::parse(
+            r#"
+- include: ["*.md", "*.txt"]
+- exclude: ["secret.txt"]
+"#,
+        )
+        .unwrap();
+
+        let descriptions: Vec<_> = mutants(&config)
+            .into_iter()
+            .map(|m| m.description)
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "remove operation 1 (include)",
+                "drop pattern \"*.md\" from operation 1 (include)",
+                "drop pattern \"*.txt\" from operation 1 (include)",
+                "remove operation 2 (exclude)",
+            ]
+        );
+    }
+}
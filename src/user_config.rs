@@ -0,0 +1,138 @@
+//! # User Configuration
+//!
+//! Settings of the person or machine running common-repo rather than of a
+//! project, read from a YAML file outside any repository (see
+//! [`crate::defaults::user_config_path`]). A missing file means no
+//! settings.
+//!
+//! ```yaml
+//! webhooks:
+//!   - url-env: SLACK_WEBHOOK_URL
+//!     format: slack
+//!     on: changes
+//!   - url: https://fleet.example.com/common-repo/applies
+//! ```
+//!
+//! - **`webhooks`**: Where to report the result of every `apply` (see
+//!   [`crate::notify`]).
+//! - **`config-file`**: A name to look for the project configuration under
+//!   before `.common-repo.yaml` and `.commonrepo.yaml` (see
+//!   [`crate::defaults::config_filenames`]).
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::notify::Webhook;
+
+/// The user configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct UserConfig {
+    /// Webhooks to report apply results to.
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// The file name to look for the project configuration under first.
+    #[serde(default, alias = "config_file")]
+    pub config_file: Option<String>,
+}
+
+impl UserConfig {
+    /// The user configuration at [`crate::defaults::user_config_path`].
+    pub fn load() -> Result<Self> {
+        match crate::defaults::user_config_path() {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The user configuration in the file at `path`, or none when there is
+    /// no file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let config: Self = serde_yaml::from_str(&content).map_err(|e| Error::ConfigParse {
+            message: format!("Invalid user configuration {}: {}", path.display(), e),
+            hint: None,
+        })?;
+        for webhook in &config.webhooks {
+            webhook.validate().map_err(|message| Error::ConfigParse {
+                message: format!("Invalid user configuration {}: {}", path.display(), message),
+                hint: None,
+            })?;
+        }
+        if let Some(name) = &config.config_file {
+            if name.is_empty() || name.contains(['/', '\\']) {
+                return Err(Error::ConfigParse {
+                    message: format!(
+                        "Invalid user configuration {}: config-file '{}' is not a file name",
+                        path.display(),
+                        name
+                    ),
+                    hint: Some(
+                        "config-file names a file in the project root, such as .common-repo.yml"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::{NotifyOn, WebhookFormat};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_user_config_from_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.yaml");
+        assert_eq!(UserConfig::from_file(&path).unwrap(), UserConfig::default());
+
+        std::fs::write(
+            &path,
+            "webhooks:\n  - url-env: SLACK_URL\n    format: slack\n    on: failures\n  - url: https://example.com/hook\n",
+        )
+        .unwrap();
+        let config = UserConfig::from_file(&path).unwrap();
+        assert_eq!(config.webhooks.len(), 2);
+        assert_eq!(config.webhooks[0].url_env.as_deref(), Some("SLACK_URL"));
+        assert_eq!(config.webhooks[0].format, WebhookFormat::Slack);
+        assert_eq!(config.webhooks[0].on, NotifyOn::Failures);
+        assert_eq!(config.webhooks[1].format, WebhookFormat::Json);
+        assert_eq!(config.webhooks[1].on, NotifyOn::Always);
+
+        std::fs::write(&path, "webhooks:\n  - format: slack\n").unwrap();
+        let err = UserConfig::from_file(&path).unwrap_err().to_string();
+        assert!(err.contains("url"), "{}", err);
+        std::fs::write(&path, "webhook: []\n").unwrap();
+        assert!(UserConfig::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_user_config_config_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.yaml");
+        std::fs::write(&path, "config-file: .common-repo.yml\n").unwrap();
+        let config = UserConfig::from_file(&path).unwrap();
+        assert_eq!(config.config_file.as_deref(), Some(".common-repo.yml"));
+
+        std::fs::write(&path, "config_file: repo.yaml\n").unwrap();
+        let config = UserConfig::from_file(&path).unwrap();
+        assert_eq!(config.config_file.as_deref(), Some("repo.yaml"));
+
+        std::fs::write(&path, "config-file: config/repo.yaml\n").unwrap();
+        let err = UserConfig::from_file(&path).unwrap_err().to_string();
+        assert!(err.contains("not a file name"), "{}", err);
+    }
+}
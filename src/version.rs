@@ -33,16 +33,64 @@
 //! (with scheme stripped) combined with any optional path. This is used by the
 //! `--filter` flag in the update command.
 //!
+//! ## Version Requirements
+//!
+//! A configuration can declare the oldest release it works with:
+//!
+//! ```yaml
+//! - requires: ">=0.30"
+//! ```
+//!
+//! [`check_requirement`] compares such a constraint with [`CURRENT`] while
+//! the configuration is parsed, so a config that uses newer operations fails
+//! with an upgrade hint instead of a confusing parse error.
+//!
+//! ## Trying Newer Refs
+//!
+//! [`with_refs`] swaps the refs of chosen upstreams in a configuration, so
+//! that `apply --shadow-dir` can compose the output of a newer release
+//! without the configuration file being edited.
+//!
 //! ## `UpdateInfo`
 //!
 //! The results of the update check are returned in a `Vec<UpdateInfo>`, where
 //! each `UpdateInfo` struct contains detailed information about the updates
 //! available for a single repository.
 
+use std::collections::BTreeMap;
+
 use crate::config::{RepoOp, Schema};
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::repository::RepositoryManager;
-use semver::Version;
+use semver::{Version, VersionReq};
+
+/// Version of this build of common-repo.
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+/// Check a `requires:` constraint such as `">=0.30"` against [`CURRENT`].
+///
+/// Returns [`Error::VersionRequirement`] when the constraint is not met and
+/// [`Error::ConfigParse`] when it is not a valid semver requirement.
+pub fn check_requirement(requirement: &str) -> Result<()> {
+    check_requirement_against(requirement, CURRENT)
+}
+
+fn check_requirement_against(requirement: &str, current: &str) -> Result<()> {
+    let req = VersionReq::parse(requirement.trim()).map_err(|e| Error::ConfigParse {
+        message: format!("Invalid requires constraint '{}': {}", requirement, e),
+        hint: Some("Use a semver requirement such as 'requires: \">=0.30\"'".to_string()),
+    })?;
+    let version = Version::parse(current)?;
+    if req.matches(&version) {
+        Ok(())
+    } else {
+        Err(Error::VersionRequirement {
+            required: requirement.trim().to_string(),
+            current: current.to_string(),
+            origin: None,
+        })
+    }
+}
 
 /// Information about available updates for a repository
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -105,14 +153,32 @@ pub fn check_updates_filtered(
     config: &Schema,
     repo_manager: &RepositoryManager,
     filters: &[String],
+) -> Result<FilteredUpdateResult> {
+    check_repos_filtered(collect_inherited_repos(config), repo_manager, filters)
+}
+
+/// Checks the repositories of `vendor` operations for newer versions, with
+/// the same filtering as [`check_updates_filtered`].
+///
+/// Vendored code is pinned on purpose, so these are checked separately from
+/// inherited repositories, for `update --update-vendored`.
+pub fn check_vendored_updates_filtered(
+    config: &Schema,
+    repo_manager: &RepositoryManager,
+    filters: &[String],
+) -> Result<FilteredUpdateResult> {
+    check_repos_filtered(collect_vendored_repos(config), repo_manager, filters)
+}
+
+fn check_repos_filtered(
+    repos: Vec<RepoOp>,
+    repo_manager: &RepositoryManager,
+    filters: &[String],
 ) -> Result<FilteredUpdateResult> {
     let mut results = Vec::new();
     let mut filtered_out_count = 0;
 
-    // Get all inherited repos from the configuration
-    let inherited_repos = collect_inherited_repos(config);
-
-    for repo in inherited_repos {
+    for repo in repos {
         // Apply filter if any patterns specified
         if !filters.is_empty() && !matches_filter(&repo, filters) {
             filtered_out_count += 1;
@@ -129,8 +195,9 @@ pub fn check_updates_filtered(
     })
 }
 
-/// Build the match target string for a repo (url without scheme + path).
-fn build_match_target(repo: &RepoOp) -> String {
+/// Build the match target string for a repo (url without scheme + path),
+/// which `--filter` patterns are matched against.
+pub fn build_match_target(repo: &RepoOp) -> String {
     let base = crate::path::strip_url_scheme(&repo.url);
     match &repo.path {
         Some(path) => format!("{}/{}", base, path.trim_matches('/')),
@@ -286,8 +353,9 @@ fn find_latest_version(tags: &[String]) -> Option<(String, Version)> {
     latest
 }
 
-/// Collect all inherited repositories from a configuration
-fn collect_inherited_repos(config: &Schema) -> Vec<RepoOp> {
+/// Collect all inherited repositories from a configuration, including
+/// those in `with:` clauses and `self:` blocks
+pub fn collect_inherited_repos(config: &Schema) -> Vec<RepoOp> {
     let mut repos = Vec::new();
 
     // Recursively collect repos from operations
@@ -296,6 +364,56 @@ fn collect_inherited_repos(config: &Schema) -> Vec<RepoOp> {
     repos
 }
 
+/// Collect the repositories of all `vendor` operations, as repo operations
+/// without `with:` clauses
+fn collect_vendored_repos(config: &Schema) -> Vec<RepoOp> {
+    let mut repos = Vec::new();
+    for op in config {
+        match op {
+            crate::config::Operation::Vendor { vendor } => repos.push(RepoOp {
+                url: vendor.url.clone(),
+                r#ref: Some(vendor.r#ref.clone()),
+                path: vendor.path.clone(),
+                with: Vec::new(),
+                disable: Vec::new(),
+                verify: None,
+                on_fetch_error: None,
+                partial: false,
+                ref_index: None,
+            }),
+            crate::config::Operation::Self_ { self_ } => {
+                repos.extend(collect_vendored_repos(&self_.operations));
+            }
+            _ => {}
+        }
+    }
+    repos
+}
+
+/// `config` with the `ref` of every `repo` operation whose URL is a key of
+/// `refs` replaced by its value, including those in `with:` clauses and
+/// `self:` blocks.
+pub fn with_refs(config: &Schema, refs: &BTreeMap<String, String>) -> Schema {
+    let mut config = config.clone();
+    set_refs(&mut config, refs);
+    config
+}
+
+fn set_refs(operations: &mut [crate::config::Operation], refs: &BTreeMap<String, String>) {
+    for op in operations {
+        match op {
+            crate::config::Operation::Repo { repo } => {
+                if let Some(new_ref) = refs.get(&repo.url) {
+                    repo.r#ref = Some(new_ref.clone());
+                }
+                set_refs(&mut repo.with, refs);
+            }
+            crate::config::Operation::Self_ { self_ } => set_refs(&mut self_.operations, refs),
+            _ => {}
+        }
+    }
+}
+
 fn collect_repos_from_operations(operations: &[crate::config::Operation], repos: &mut Vec<RepoOp>) {
     for op in operations {
         match op {
@@ -316,6 +434,24 @@ mod tests {
     use super::*;
     use crate::config::RepoOp;
 
+    #[test]
+    fn test_check_requirement() {
+        assert!(check_requirement_against(">=0.30", "0.37.3").is_ok());
+        assert!(check_requirement_against("^0.37", "0.37.3").is_ok());
+
+        let err = check_requirement_against(">=0.30", "0.27.0").unwrap_err();
+        assert!(matches!(err, Error::VersionRequirement { .. }));
+        let message = err.to_string();
+        assert!(message.contains("requires common-repo >=0.30, but this is 0.27.0"));
+        assert!(message.contains("hint: upgrade"));
+
+        let err = check_requirement_against("newest", "0.37.3").unwrap_err();
+        assert!(matches!(err, Error::ConfigParse { .. }));
+
+        // The running build always satisfies its own version.
+        assert!(check_requirement(&format!(">={}", CURRENT)).is_ok());
+    }
+
     #[test]
     fn test_collect_repos_includes_self_block_repos() {
         use crate::config::{Operation, SelfOp};
@@ -327,6 +463,11 @@ mod tests {
                     r#ref: Some("v1.0.0".to_string()),
                     path: None,
                     with: vec![],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
             Operation::Self_ {
@@ -337,6 +478,11 @@ mod tests {
                             r#ref: Some("v2.0.0".to_string()),
                             path: None,
                             with: vec![],
+                            disable: vec![],
+                            verify: None,
+                            on_fetch_error: None,
+                            partial: false,
+                            ref_index: None,
                         },
                     }],
                 },
@@ -425,6 +571,29 @@ mod tests {
         assert!(!compatible);
     }
 
+    #[test]
+    fn test_collect_vendored_repos() {
+        let config = crate::config::parse(
+            r#"
+- repo:
+    url: https://github.com/acme/base.git
+    ref: v1.0.0
+- vendor:
+    url: https://github.com/acme/scripts.git
+    ref: v2.0.0
+    path: bin
+"#,
+        )
+        .unwrap();
+
+        let repos = collect_vendored_repos(&config);
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].url, "https://github.com/acme/scripts.git");
+        assert_eq!(repos[0].r#ref.as_deref(), Some("v2.0.0"));
+        assert_eq!(repos[0].path.as_deref(), Some("bin"));
+        assert_eq!(collect_inherited_repos(&config).len(), 1);
+    }
+
     #[test]
     fn test_collect_inherited_repos() {
         let config: Schema = vec![
@@ -439,16 +608,28 @@ mod tests {
                             path: None,
                             r#ref: Some("main".to_string()),
                             with: vec![],
+                            disable: vec![],
+                            verify: None,
+                            on_fetch_error: None,
+                            partial: false,
+                            ref_index: None,
                         },
                     }],
+                    disable: vec![],
+                    verify: None,
+                    on_fetch_error: None,
+                    partial: false,
+                    ref_index: None,
                 },
             },
             crate::config::Operation::Include {
                 include: crate::config::IncludeOp {
                     patterns: vec!["*.md".to_string()],
                     if_exists: crate::config::IfExists::Overwrite,
+                    rewrite_links: None,
                 },
                 if_exists: crate::config::IfExists::Overwrite,
+                rewrite_links: None,
             },
         ];
 
@@ -465,6 +646,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(build_match_target(&repo), "github.com/org/repo");
     }
@@ -476,6 +662,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: Some("configs/eslint".to_string()),
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(
             build_match_target(&repo),
@@ -491,6 +682,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(build_match_target(&repo), "github.com/org/repo");
 
@@ -500,6 +696,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(build_match_target(&repo), "gitlab.com/org/repo");
 
@@ -509,6 +710,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(build_match_target(&repo), "git@github.com/org/repo");
     }
@@ -520,6 +726,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: Some("/configs/eslint/".to_string()),
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert_eq!(
             build_match_target(&repo),
@@ -534,6 +745,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
 
         // Exact match
@@ -558,6 +774,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
 
         // First pattern matches
@@ -572,6 +793,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert!(matches_filter(
             &repo2,
@@ -584,6 +810,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         assert!(!matches_filter(
             &repo3,
@@ -598,6 +829,11 @@ mod tests {
             r#ref: Some("v1.0.0".to_string()),
             path: Some("configs/eslint".to_string()),
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
 
         // Match full path
@@ -676,6 +912,11 @@ mod tests {
             r#ref: None,
             path: None,
             with: vec![],
+            disable: vec![],
+            verify: None,
+            on_fetch_error: None,
+            partial: false,
+            ref_index: None,
         };
         let info = check_repo_updates(&repo, &manager).unwrap();
         assert_eq!(info.url, "./local");
@@ -686,4 +927,44 @@ mod tests {
         assert!(info.available_versions.is_empty());
         assert_eq!(*calls.lock().unwrap(), 0, "list_tags must not be called");
     }
+
+    #[test]
+    fn test_with_refs_replaces_matching_repo_refs() {
+        let config = crate::config::parse(
+            "- repo:\n    url: https://github.com/acme/ci\n    ref: v1.0.0\n    with:\n      - repo:\n          url: https://github.com/acme/lint\n          ref: v3.0.0\n- repo:\n    url: https://github.com/acme/docs\n    ref: v1.0.0\n",
+        )
+        .unwrap();
+        let refs = BTreeMap::from([
+            (
+                "https://github.com/acme/ci".to_string(),
+                "v2.0.0".to_string(),
+            ),
+            (
+                "https://github.com/acme/lint".to_string(),
+                "v4.0.0".to_string(),
+            ),
+        ]);
+        let refs: Vec<(String, Option<String>)> =
+            collect_inherited_repos(&with_refs(&config, &refs))
+                .into_iter()
+                .map(|repo| (repo.url, repo.r#ref))
+                .collect();
+        assert_eq!(
+            refs,
+            vec![
+                (
+                    "https://github.com/acme/ci".to_string(),
+                    Some("v2.0.0".to_string())
+                ),
+                (
+                    "https://github.com/acme/lint".to_string(),
+                    Some("v4.0.0".to_string())
+                ),
+                (
+                    "https://github.com/acme/docs".to_string(),
+                    Some("v1.0.0".to_string())
+                ),
+            ]
+        );
+    }
 }
@@ -25,7 +25,7 @@ fn test_add_quiet_creates_config() {
         .assert()
         .success()
         .stdout(predicate::str::contains("Fetching tags from"))
-        .stdout(predicate::str::contains("✅ Created .common-repo.yaml"));
+        .stdout(predicate::str::contains("Created .common-repo.yaml"));
 
     // Check that the config file was created with the repo
     let config_file = temp.child(".common-repo.yaml");
@@ -67,7 +67,7 @@ fn test_add_to_existing_config() {
         .assert()
         .success()
         .stdout(predicate::str::contains("Fetching tags from"))
-        .stdout(predicate::str::contains("✅ Added"));
+        .stdout(predicate::str::contains("Added"));
 
     // Verify both repos are in the config
     config_file.assert(predicate::str::contains(
@@ -131,6 +131,41 @@ fn test_apply_verbose() {
         .stderr(predicate::str::contains("📋 Parsing configuration"));
 }
 
+/// Test that --verbose times each operation and lists the slowest ones
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_verbose_operation_timings() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("keep.md").write_str("keep").unwrap();
+    temp.child("drop.tmp").write_str("drop").unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+    config_file
+        .write_str(
+            r#"
+- include: ["**/*"]
+- exclude: ["*.tmp"]
+"#,
+        )
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("apply")
+        .arg("--config")
+        .arg(config_file.path())
+        .arg("--dry-run")
+        .arg("--verbose")
+        .assert()
+        .success()
+        .stderr(
+            predicate::str::is_match(
+                r"op timing: exclude \*\.tmp \(local config\): [0-9.]+ms, \d+ -> \d+ files",
+            )
+            .unwrap(),
+        )
+        .stderr(predicate::str::contains("Slowest operations:"));
+}
+
 /// Test that --force flag is accepted
 #[test]
 #[cfg_attr(not(feature = "integration-tests"), ignore)]
@@ -369,6 +404,543 @@ fn test_apply_env_cache() {
         .stderr(predicate::str::contains("Applied successfully"));
 }
 
+/// Test that a `cache-dir` entry keeps the cache inside the project
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_project_cache_dir() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+    temp.child("README.md").write_str("local").unwrap();
+
+    config_file
+        .write_str(
+            r#"
+- cache-dir: .common-repo/cache
+- include: ["README.md"]
+"#,
+        )
+        .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("common-repo");
+
+    cmd.current_dir(temp.path())
+        .env_remove("COMMON_REPO_CACHE")
+        .env_remove("COMMON_REPO_CACHE_DIR")
+        .arg("apply")
+        .arg("--config")
+        .arg(config_file.path())
+        .assert()
+        .success();
+
+    temp.child(".common-repo/cache/fingerprints")
+        .assert(predicate::path::is_dir());
+}
+
+/// Test that an unreachable upstream marked `on-fetch-error: skip` is left
+/// out with a warning while the rest of the configuration is applied
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_skips_unreachable_upstream() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let missing = temp.child("missing-upstream");
+    let config_file = temp.child("project/.common-repo.yaml");
+    temp.child("project/README.md").write_str("local").unwrap();
+
+    config_file
+        .write_str(&format!(
+            r#"
+- repo:
+    url: "file://{}"
+    ref: main
+    on-fetch-error: skip
+- include: ["README.md"]
+"#,
+            missing.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(temp.child("cache").path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Skipping file://"));
+
+    // Without the policy the unreachable upstream fails the run.
+    config_file
+        .write_str(&format!(
+            "- repo:\n    url: \"file://{}\"\n    ref: main\n",
+            missing.path().display()
+        ))
+        .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(temp.child("cache").path())
+        .assert()
+        .failure();
+}
+
+/// Test that apply refuses to write through a symlink in the output
+/// directory unless --follow-symlinks is given
+#[test]
+#[cfg(unix)]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_refuses_to_write_through_symlinks() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/AGENTS.md")
+        .write_str("upstream")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    temp.child("outside.md").write_str("keep").unwrap();
+    std::os::unix::fs::symlink(
+        temp.child("outside.md").path(),
+        temp.child("project/AGENTS.md").path(),
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(temp.child("cache").path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("AGENTS.md -> "))
+        .stderr(predicate::str::contains("--follow-symlinks"));
+    temp.child("outside.md").assert("keep");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .arg("apply")
+        .arg("--follow-symlinks")
+        .arg("--cache-dir")
+        .arg(temp.child("cache").path())
+        .assert()
+        .success();
+    temp.child("outside.md").assert("upstream");
+}
+
+/// Test that apply refuses to change a read-only file unless --read-only
+/// says how to handle it
+#[test]
+#[cfg(unix)]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_read_only_files() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/settings.toml")
+        .write_str("new")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    let settings = temp.child("project/settings.toml");
+    settings.write_str("old").unwrap();
+    std::fs::set_permissions(settings.path(), std::fs::Permissions::from_mode(0o444)).unwrap();
+
+    let apply = |extra: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(temp.child("project").path())
+            .arg("apply")
+            .args(extra)
+            .arg("--cache-dir")
+            .arg(temp.child("cache").path());
+        cmd
+    };
+
+    apply(&[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("read-only"))
+        .stderr(predicate::str::contains("settings.toml"));
+    settings.assert("old");
+
+    apply(&["--read-only", "skip"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Skipped read-only file settings.toml",
+        ));
+    settings.assert("old");
+
+    apply(&["--read-only", "overwrite", "--force"])
+        .assert()
+        .success();
+    settings.assert("new");
+    let mode = std::fs::metadata(settings.path())
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o444);
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_refuses_to_override_locked_files() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: [SECURITY.md]\n  locked: true\n- include: [README.md]\n")
+        .unwrap();
+    temp.child("upstream/SECURITY.md")
+        .write_str("Report issues to security@example.com\n")
+        .unwrap();
+    temp.child("upstream/README.md")
+        .write_str("# Upstream\n")
+        .unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    let apply = || {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .args(["apply", "--cache-dir"])
+            .arg(temp.child("cache").path());
+        cmd
+    };
+    apply().assert().success();
+    project
+        .child("SECURITY.md")
+        .assert("Report issues to security@example.com\n");
+
+    // Editing an unlocked file is fine; editing a locked one is not.
+    project.child("README.md").write_str("# Ours\n").unwrap();
+    apply().assert().success();
+    project
+        .child("SECURITY.md")
+        .write_str("Report issues to us\n")
+        .unwrap();
+    apply()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Refusing to override locked files",
+        ))
+        .stderr(predicate::str::contains("SECURITY.md (locked by"));
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["check", "--locked", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("SECURITY.md (locked by"));
+    project.child("SECURITY.md").assert("Report issues to us\n");
+
+    // Restoring the file clears the error; an override is refused too.
+    project
+        .child("SECURITY.md")
+        .write_str("Report issues to security@example.com\n")
+        .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["check", "--locked", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No locked file is modified locally",
+        ));
+    project
+        .child(".common-repo/overrides/SECURITY.md")
+        .write_str("Report issues to us\n")
+        .unwrap();
+    apply()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("SECURITY.md (locked by"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_enforces_assertions() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str(
+            "- include: [LICENSE]\n\
+             - assert:\n    path: .travis.yml\n    absent: true\n    message: CI runs on GitHub Actions\n",
+        )
+        .unwrap();
+    temp.child("upstream/LICENSE")
+        .write_str("Apache License\n")
+        .unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str(
+            "- repo:\n    url: ../upstream\n\
+             - assert:\n    path: LICENSE\n    contains: Apache License\n",
+        )
+        .unwrap();
+    project
+        .child(".travis.yml")
+        .write_str("language: rust\n")
+        .unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .args(args)
+            .arg("--cache-dir")
+            .arg(temp.child("cache").path());
+        cmd
+    };
+
+    // The upstream's assertion fails, and nothing is written.
+    run(&["apply"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Assertions failed"))
+        .stderr(predicate::str::contains(
+            ".travis.yml must not exist (asserted by",
+        ))
+        .stderr(predicate::str::contains("CI runs on GitHub Actions"));
+    project.child("LICENSE").assert(predicate::path::missing());
+    run(&["check", "--assertions"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(".travis.yml must not exist"));
+
+    std::fs::remove_file(project.child(".travis.yml").path()).unwrap();
+    run(&["check", "--assertions"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("The output meets every assertion"));
+    run(&["apply"]).assert().success();
+    project.child("LICENSE").assert("Apache License\n");
+
+    // The consumer's own assertions hold against the output, local files
+    // included.
+    project
+        .child(".common-repo.yaml")
+        .write_str(
+            "- repo:\n    url: ../upstream\n\
+             - assert:\n    path: LICENSE\n    contains: Apache License\n\
+             - assert:\n    path: CODEOWNERS\n",
+        )
+        .unwrap();
+    run(&["apply"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "no file matches CODEOWNERS (asserted by the configuration)",
+        ));
+    project
+        .child("CODEOWNERS")
+        .write_str("* @org/team\n")
+        .unwrap();
+    run(&["apply"]).assert().success();
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_overrides_directory() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("upstream: true\n")
+        .unwrap();
+    temp.child("upstream/README.md")
+        .write_str("# Upstream\n")
+        .unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    project
+        .child(".common-repo/overrides/ci.yml")
+        .write_str("mine: true\n")
+        .unwrap();
+    project
+        .child(".common-repo/overrides/.vscode/settings.json")
+        .write_str("{}\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Overriding 2 file(s) from .common-repo/overrides",
+        ));
+
+    project.child("ci.yml").assert("mine: true\n");
+    project.child(".vscode/settings.json").assert("{}\n");
+    project.child("README.md").assert("# Upstream\n");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["render", "--file", "ci.yml", "--provenance", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "origin: override in .common-repo/overrides",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_asks_before_sweeping_rename() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("upstream: true\n")
+        .unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str(
+            "- repo:\n    url: ../upstream\n    with:\n      - rename:\n          - '(.*)': 'shared/$1'\n",
+        )
+        .unwrap();
+
+    // Without a terminal to ask on, nothing is written
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "rename '(.*)' -> 'shared/$1' moves 1 file(s)",
+        ))
+        .stderr(predicate::str::contains("ci.yml → shared/ci.yml"))
+        .stderr(predicate::str::contains("re-run with --yes"));
+    project.child("shared").assert(predicate::path::missing());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--yes", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success();
+    project.child("shared/ci.yml").assert("upstream: true\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_output_format_tar() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci/check.sh")
+        .write_str("#!/bin/sh\n")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["apply", "--output-format", "tar"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--output"));
+
+    let archive = temp.child("out.tar");
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["apply", "--output-format", "tar", "--output"])
+        .arg(archive.path())
+        .arg("--cache-dir")
+        .arg(temp.child("cache").path())
+        .assert()
+        .success();
+
+    let mut entries = Vec::new();
+    let mut tar = tar::Archive::new(std::fs::File::open(archive.path()).unwrap());
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut content).unwrap();
+        entries.push((entry.path().unwrap().display().to_string(), content));
+    }
+    assert!(entries.contains(&("ci/check.sh".to_string(), "#!/bin/sh\n".to_string())));
+    temp.child("project/ci").assert(predicate::path::missing());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_output_format_git_tree() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/settings.toml")
+        .write_str("new")
+        .unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    project.child("settings.toml").write_str("old").unwrap();
+    project.child("kept.txt").write_str("kept").unwrap();
+
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .current_dir(project.path())
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    let head = git(&["rev-parse", "HEAD"]);
+    project.child("scratch.txt").write_str("local").unwrap();
+
+    let output = cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .args(["apply", "--output-format", "git-tree", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let commit = String::from_utf8_lossy(&output)
+        .lines()
+        .find(|line| line.len() == 40)
+        .expect("commit id on stdout")
+        .to_string();
+
+    assert_eq!(git(&["show", &format!("{}:settings.toml", commit)]), "new");
+    assert_eq!(git(&["show", &format!("{}:kept.txt", commit)]), "kept");
+    assert_eq!(git(&["rev-parse", &format!("{}^", commit)]), head);
+    assert_eq!(git(&["rev-parse", "refs/common-repo/apply"]), commit);
+    assert!(!git(&["ls-tree", "--name-only", &commit]).contains("scratch.txt"));
+    assert_eq!(git(&["rev-parse", "HEAD"]), head);
+    project.child("settings.toml").assert("old");
+    assert!(git(&["status", "--porcelain"]).contains("?? scratch.txt"));
+}
+
 /// Test that apply with multiple repo operations shows appropriate error for invalid URLs
 /// This tests that the parallel cloning error handling works correctly
 #[test]
@@ -434,3 +1006,156 @@ fn test_apply_nested_repo_inheritance_invalid() {
         .failure()
         .stderr(predicate::str::contains("clone").or(predicate::str::contains("Git")));
 }
+
+/// Test that allow-paths and deny-paths bound where upstream files are
+/// written, whatever the upstream renames them to
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_keeps_upstream_files_within_allowed_paths() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n- rename:\n    - '^ci\\.yml$': .github/workflows/ci.yml\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+    temp.child("upstream/CODEOWNERS")
+        .write_str("* @upstream\n")
+        .unwrap();
+    temp.child("upstream/Makefile").write_str("all:\n").unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str(
+            "- allow-paths: ['.github/**', CODEOWNERS]\n- deny-paths: [CODEOWNERS]\n- repo:\n    url: ../upstream\n",
+        )
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Not writing Makefile: it is outside the allow-paths or deny-paths",
+        ))
+        .stderr(predicate::str::contains("Not writing CODEOWNERS"));
+    project
+        .child(".github/workflows/ci.yml")
+        .assert("on: push\n");
+    project.child("Makefile").assert(predicate::path::missing());
+    project
+        .child("CODEOWNERS")
+        .assert(predicate::path::missing());
+}
+
+/// `apply` reads the configuration under either file name, warns when a
+/// directory has both with different content, and looks under the
+/// `config-file:` of the user configuration first.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_resolves_config_file_names() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.commonrepo.yaml")
+        .write_str("- include: [LICENSE, NOTICE]\n")
+        .unwrap();
+    temp.child("upstream/LICENSE").write_str("MIT\n").unwrap();
+    temp.child("upstream/NOTICE").write_str("Notice\n").unwrap();
+    let project = temp.child("project");
+    project
+        .child(".commonrepo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    let run = || {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .env("COMMON_REPO_USER_CONFIG", temp.child("user.yaml").path())
+            .args(["apply", "--cache-dir"])
+            .arg(temp.child("cache").path());
+        cmd
+    };
+
+    run().assert().success();
+    project.child("LICENSE").assert("MIT\n");
+    project.child("NOTICE").assert("Notice\n");
+    temp.child("upstream/.commonrepo.yaml")
+        .assert(predicate::path::exists());
+
+    // Both names: the preferred one is read, and the difference is reported.
+    project
+        .child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n    with:\n      - exclude: [NOTICE]\n")
+        .unwrap();
+    std::fs::remove_file(project.child("NOTICE").path()).unwrap();
+    run().assert().success().stderr(predicate::str::contains(
+        "has both .common-repo.yaml and .commonrepo.yaml with different content",
+    ));
+    project.child("NOTICE").assert(predicate::path::missing());
+
+    // The user configuration names a file to look for first.
+    temp.child("user.yaml")
+        .write_str("config-file: repo.yaml\n")
+        .unwrap();
+    project
+        .child("repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    run().assert().success();
+    project.child("NOTICE").assert("Notice\n");
+}
+
+/// Empty files are propagated like any other, and a change of mode alone
+/// upstream is applied and recorded in the history.
+#[cfg(unix)]
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_propagates_empty_files_and_mode_changes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/logs/.gitkeep").touch().unwrap();
+    temp.child("upstream/run.sh")
+        .write_str("#!/bin/sh\n")
+        .unwrap();
+    let script = temp.child("upstream/run.sh");
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o644)).unwrap();
+    let project = temp.child("project");
+    project
+        .child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    let run = |command: &str| {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .args([command, "--cache-dir"])
+            .arg(temp.child("cache").path());
+        cmd
+    };
+    let mode =
+        |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    run("apply").assert().success();
+    project.child("logs/.gitkeep").assert("");
+    assert_eq!(mode(project.child("run.sh").path()), 0o644);
+    run("diff").assert().success();
+
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    run("diff")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("run.sh (mode 644 -> 755)"));
+    run("apply")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Already up to date").not());
+    assert_eq!(mode(project.child("run.sh").path()), 0o755);
+    let history =
+        std::fs::read_to_string(project.child(".common-repo/history.jsonl").path()).unwrap();
+    let last: serde_json::Value = serde_json::from_str(history.lines().last().unwrap()).unwrap();
+    assert_eq!(last["files"], serde_json::json!(["run.sh"]), "{}", history);
+    run("diff").assert().success();
+}
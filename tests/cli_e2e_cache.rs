@@ -2,8 +2,11 @@
 //! These tests invoke the actual CLI binary and validate cache command behavior
 //! from a user's perspective.
 
+mod common;
+
 use assert_cmd::cargo::cargo_bin_cmd;
 use assert_fs::prelude::*;
+use common::init_test_git_repo;
 use predicates::prelude::*;
 
 /// Test that cache --help flag shows help information
@@ -658,3 +661,212 @@ fn test_cache_clean_combined_filters() {
     assert!(entry2.path().exists());
     assert!(entry3.path().exists());
 }
+
+/// Test the air-gapped workflow: export the repositories a configuration
+/// inherits from, import them into another cache, and apply without the
+/// upstream being reachable
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_cache_export_import_applies_offline() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(&upstream, &[("README.md", "# Upstream\n")], Some("v1.0.0")).unwrap();
+    let upstream_url = format!("file://{}", upstream.path().display());
+
+    let consumer = assert_fs::TempDir::new().unwrap();
+    consumer
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: \"{}\"\n    ref: v1.0.0\n    with:\n      - include: [\"**\"]\n",
+            upstream_url
+        ))
+        .unwrap();
+    let online_cache = assert_fs::TempDir::new().unwrap();
+    let offline_cache = assert_fs::TempDir::new().unwrap();
+    let archive = online_cache.path().join("bundle.tar");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("cache")
+        .arg("--cache-dir")
+        .arg(online_cache.path())
+        .arg("export")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported 1 cache entries"));
+
+    // The upstream is gone; only the archive reaches the offline machine.
+    upstream.close().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .arg("cache")
+        .arg("--cache-dir")
+        .arg(offline_cache.path())
+        .arg("import")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 1 cache entries"));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(offline_cache.path())
+        .assert()
+        .success();
+    consumer
+        .child("README.md")
+        .assert(predicate::str::contains("# Upstream"));
+}
+
+/// Test that cache warm fetches the repositories of every configuration
+/// given, so a later apply does not need the upstream
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_cache_warm_prefetches_configs() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(&upstream, &[("README.md", "# Upstream\n")], Some("v1.0.0")).unwrap();
+    let upstream_url = format!("file://{}", upstream.path().display());
+
+    let workspace = assert_fs::TempDir::new().unwrap();
+    for project in ["one", "two"] {
+        workspace
+            .child(project)
+            .child(".common-repo.yaml")
+            .write_str(&format!(
+                "- repo:\n    url: \"{}\"\n    ref: v1.0.0\n    with:\n      - include: [\"**\"]\n",
+                upstream_url
+            ))
+            .unwrap();
+    }
+    let cache = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(workspace.path())
+        .arg("cache")
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .arg("warm")
+        .arg("--config")
+        .arg("one/.common-repo.yaml")
+        .arg("--config")
+        .arg("two/.common-repo.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "1 repositories cached for one/.common-repo.yaml",
+        ))
+        .stdout(predicate::str::contains(
+            "1 repositories cached for two/.common-repo.yaml",
+        ));
+
+    // Warming only fetches; nothing is written into the projects.
+    workspace
+        .child("one")
+        .child("README.md")
+        .assert(predicate::path::missing());
+
+    upstream.close().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(workspace.child("one").path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .assert()
+        .success();
+    workspace
+        .child("one")
+        .child("README.md")
+        .assert(predicate::str::contains("# Upstream"));
+}
+
+/// Test that a `partial: true` upstream is cloned with only the files its
+/// `with:` includes can match
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_partial_repo_caches_only_included_files() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: ['**']\n"),
+            ("ci/lint.yml", "lint: true\n"),
+            ("ci/test.yml", "test: true\n"),
+            ("assets/video.bin", "large\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    let project = assert_fs::TempDir::new().unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    project
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n    partial: true\n    with:\n      - include: ['ci/*.yml']\n      - exclude: [ci/test.yml]\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .arg("apply")
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .assert()
+        .success();
+    project.child("ci/lint.yml").assert("lint: true\n");
+    project
+        .child("ci/test.yml")
+        .assert(predicate::path::missing());
+    project.child("assets").assert(predicate::path::missing());
+
+    let entries: Vec<_> = std::fs::read_dir(cache.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().contains("-partial-"))
+        .collect();
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].join("ci/test.yml").exists());
+    assert!(!entries[0].join("assets/video.bin").exists());
+}
+
+/// Test that an upstream found to have no configuration is not searched
+/// for one again on the next run
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_missing_upstream_config_is_cached() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(&upstream, &[("README.md", "# Plain\n")], Some("v1.0.0")).unwrap();
+    let project = assert_fs::TempDir::new().unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    project
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    let apply = || {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .args(["apply", "--dry-run", "--verbose", "--cache-dir"])
+            .arg(cache.path());
+        cmd
+    };
+
+    apply().assert().success().stderr(predicate::str::contains(
+        "has no .common-repo.yaml, recorded in the cache",
+    ));
+    assert_eq!(
+        std::fs::read_dir(cache.child(".no-config").path())
+            .unwrap()
+            .count(),
+        1
+    );
+
+    apply().assert().success().stderr(predicate::str::contains(
+        "is known to have no .common-repo.yaml, not looking again",
+    ));
+}
@@ -3,8 +3,11 @@
 //! These tests invoke the actual CLI binary and validate its behavior
 //! from a user's perspective.
 
+mod common;
+
 use assert_cmd::cargo::cargo_bin_cmd;
 use assert_fs::prelude::*;
+use common::init_test_git_repo;
 use predicates::prelude::*;
 
 /// Test that --help flag shows help information
@@ -80,6 +83,44 @@ fn test_check_valid_config_no_repos() {
         .stdout(predicate::str::contains("Repositories: 0"));
 }
 
+/// Test that check --templates compares template declarations with the files
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_check_templates_declarations() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+    temp.child("ci.yml")
+        .write_str("name: __COMMON_REPO__project__\n")
+        .unwrap();
+    let declare = |checksum: &str| {
+        config_file
+            .write_str(&format!(
+                "- template:\n    files:\n      ci.yml: {}\n- template-vars:\n    project: demo\n",
+                checksum
+            ))
+            .unwrap();
+    };
+    let check = || {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.args(["check", "--templates", "--config"])
+            .arg(config_file.path());
+        cmd
+    };
+
+    declare("sha256:0000");
+    check().assert().failure().stdout(predicate::str::contains(
+        "placeholders in ci.yml do not match the declaration",
+    ));
+
+    let checksum =
+        common_repo::config::placeholder_checksum(b"name: __COMMON_REPO__project__\n").unwrap();
+    declare(&checksum);
+    check()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Template declarations match"));
+}
+
 /// Test that check succeeds with valid config containing repositories
 #[test]
 #[cfg_attr(not(feature = "integration-tests"), ignore)]
@@ -408,3 +449,79 @@ fn test_check_updates_output_format() {
             || stdout.contains("No repositories found")
     );
 }
+
+/// Test that --repo checks the configuration of a remote consumer repository
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_check_remote_consumer_repo() {
+    let consumer = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &consumer,
+        &[(
+            ".common-repo.yaml",
+            "- include: [\"*.md\"]\n- exclude: [\"*.tmp\"]\n",
+        )],
+        None,
+    )
+    .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let workdir = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(workdir.path())
+        .arg("check")
+        .arg("--repo")
+        .arg(format!("file://{}", consumer.path().display()))
+        .args(["--ref", "main", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Configuration loaded successfully",
+        ))
+        .stdout(predicate::str::contains("Operations: 2"));
+}
+
+/// Test that --vars reports misspelled and unused template variables
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_check_vars_reports_undefined_and_unused() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: [\"**/*\"]\n- template: [\"README.md\"]\n",
+            ),
+            ("README.md", "# __COMMON_REPO__PROJEKT_NAME__\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+    config_file
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: main\n- template-vars:\n    PROJECT_NAME: demo\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .arg("check")
+        .arg("--vars")
+        .arg("--config")
+        .arg(config_file.path())
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("PROJECT_NAME: local config"))
+        .stdout(predicate::str::contains("PROJEKT_NAME (used in README.md)"))
+        .stdout(predicate::str::contains("Defined but never used:"))
+        .stderr(predicate::str::contains(
+            "1 template variable(s) are used but never defined",
+        ));
+}
@@ -113,3 +113,43 @@ fn test_completions_missing_shell_argument() {
         .failure()
         .stderr(predicate::str::contains("required"));
 }
+
+#[test]
+fn test_completions_dynamic_registers_callback() {
+    let mut cmd = cargo_bin_cmd!("common-repo");
+    cmd.args(["completions", "bash", "--dynamic"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("COMPLETE=\"bash\""))
+        .stdout(predicate::str::contains("complete -o nospace"));
+}
+
+#[test]
+fn test_dynamic_completion_of_project_values() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".common-repo/docs.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child(".common-repo/release.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- repo:\n    url: https://github.com/acme/ci\n    ref: v2\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .env("COMPLETE", "fish")
+        .args(["--", "common-repo", "apply", "--pipeline", ""])
+        .assert()
+        .success()
+        .stdout("docs\nrelease\n");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .env("COMPLETE", "fish")
+        .args(["--", "common-repo", "update", "--filter", ""])
+        .assert()
+        .success()
+        .stdout("github.com/acme/ci\tv2\n");
+}
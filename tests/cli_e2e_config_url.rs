@@ -0,0 +1,86 @@
+//! End-to-end tests for `apply --config-url`.
+//!
+//! A throwaway HTTP server on loopback serves the configuration; plain
+//! `http://` is accepted only together with `--config-sha256`.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::thread;
+use tempfile::TempDir;
+
+const CONFIG: &str = "- include: ['*.md']\n";
+const CONFIG_SHA256: &str = "edb5cc4389949271fd9e27b898216196c4e92bac7e07e7ae04cc3b4be056fb53";
+
+/// Serve `body` to the next connection and return its URL.
+fn serve(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        if let Some(stream) = listener.incoming().next() {
+            let mut stream = stream.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    format!("http://{}/.common-repo.yaml", address)
+}
+
+fn apply(dir: &Path, url: &str, sha256: &str) -> assert_cmd::assert::Assert {
+    cargo_bin_cmd!("common-repo")
+        .current_dir(dir)
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(dir.join(".cache"))
+        .arg("--config-url")
+        .arg(url)
+        .arg("--config-sha256")
+        .arg(sha256)
+        .arg("--dry-run")
+        .assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_uses_remote_config() {
+    let url = serve(CONFIG);
+    let tmp = TempDir::new().unwrap();
+
+    // There is no local configuration to fall back on.
+    apply(tmp.path(), &url, CONFIG_SHA256).success();
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_rejects_remote_config_checksum_mismatch() {
+    let url = serve("- include: ['**/*']\n");
+    let tmp = TempDir::new().unwrap();
+
+    apply(tmp.path(), &url, CONFIG_SHA256)
+        .failure()
+        .stderr(predicate::str::contains("Checksum mismatch"))
+        .stderr(predicate::str::contains("E1009"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_rejects_unpinned_http_config_url() {
+    let tmp = TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.path())
+        .arg("apply")
+        .arg("--config-url")
+        .arg("http://127.0.0.1:9/.common-repo.yaml")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("uses http without a sha256"));
+}
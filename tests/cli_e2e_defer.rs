@@ -451,7 +451,7 @@ database:
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     // File should still exist and be valid YAML
     let content = std::fs::read_to_string(yaml_file.path()).unwrap();
@@ -617,7 +617,7 @@ app:
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     // Both files should still be valid
     let yaml_content = std::fs::read_to_string(yaml_file.path()).unwrap();
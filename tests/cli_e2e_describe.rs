@@ -0,0 +1,91 @@
+//! End-to-end tests for the describe command.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+
+const UPSTREAM_CONFIG: &str = r#"- requires:
+    vars: [project_name]
+    tools:
+      - name: git
+        version: "*"
+- docs: https://example.com/adopting
+- template-vars:
+    license: MIT
+- include: ["**/*"]
+- template: ["README.md"]
+"#;
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_describe_upstream_at_latest_tag() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", UPSTREAM_CONFIG),
+            ("README.md", "# __COMMON_REPO__project_name__\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let url = format!("file://{}", upstream.path().display());
+
+    cargo_bin_cmd!("common-repo")
+        .args(["describe", &url, "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Upstream: {} @ v1.0.0",
+            url
+        )))
+        .stdout(predicate::str::contains("  license = MIT"))
+        .stdout(predicate::str::contains("  project_name (required)"))
+        .stdout(predicate::str::contains("  template README.md"))
+        .stdout(predicate::str::contains("Required tools:\n  git *"))
+        .stdout(predicate::str::contains(
+            "Docs:\n  https://example.com/adopting",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_describe_json() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(&upstream, &[(".common-repo.yaml", UPSTREAM_CONFIG)], None).unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let url = format!("file://{}", upstream.path().display());
+
+    let output = cargo_bin_cmd!("common-repo")
+        .args(["describe", &url, "--ref", "main", "--json", "--cache-dir"])
+        .arg(cache.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["ref"], "main");
+    assert_eq!(json["variables"][1]["name"], "project_name");
+    assert_eq!(json["variables"][1]["required"], true);
+    assert_eq!(json["operations"][1]["kind"], "include");
+    assert_eq!(json["docs"][0], "https://example.com/adopting");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_describe_repo_without_config() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(&upstream, &[("README.md", "# Hi\n")], None).unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let url = format!("file://{}", upstream.path().display());
+
+    cargo_bin_cmd!("common-repo")
+        .args(["describe", &url, "--ref", "main", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No .common-repo.yaml"));
+}
@@ -3,8 +3,11 @@
 //! These tests verify the CLI behavior of the `diff` command by invoking
 //! the binary directly and checking its output.
 
+mod common;
+
 use assert_cmd::cargo::cargo_bin_cmd;
 use assert_fs::prelude::*;
+use common::init_test_git_repo;
 use predicates::prelude::*;
 
 #[test]
@@ -291,3 +294,231 @@ fn test_diff_empty_config_no_changes() {
         .success()
         .stdout(predicate::str::contains("No changes detected"));
 }
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_base_compares_against_git_ref() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(
+            r#"
+- include: ["*.txt"]
+"#,
+        )
+        .unwrap();
+    temp.child("kept.txt").write_str("same").unwrap();
+    temp.child("edited.txt").write_str("before").unwrap();
+    git(temp.path(), &["init", "-q", "-b", "main"]);
+    git(temp.path(), &["config", "user.email", "test@example.com"]);
+    git(temp.path(), &["config", "user.name", "Test"]);
+    git(temp.path(), &["add", "."]);
+    git(temp.path(), &["commit", "-q", "-m", "base"]);
+
+    // Working-tree changes on top of main.
+    temp.child("edited.txt").write_str("after").unwrap();
+    temp.child("new.txt").write_str("new").unwrap();
+
+    // The working tree already matches the configuration...
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("diff")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No changes detected"));
+
+    // ...but relative to main two files change.
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["diff", "--base", "main"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("+ new.txt"))
+        .stdout(predicate::str::contains("~ edited.txt"))
+        .stdout(predicate::str::contains("kept.txt").not());
+}
+
+/// Test that a permission change with the same content is reported
+#[test]
+#[cfg(unix)]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_base_reports_mode_changes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"*.sh\"]\n")
+        .unwrap();
+    temp.child("run.sh").write_str("#!/bin/sh\n").unwrap();
+    git(temp.path(), &["init", "-q", "-b", "main"]);
+    git(temp.path(), &["config", "user.email", "test@example.com"]);
+    git(temp.path(), &["config", "user.name", "Test"]);
+    git(temp.path(), &["config", "core.fileMode", "true"]);
+    git(temp.path(), &["add", "."]);
+    git(temp.path(), &["commit", "-q", "-m", "base"]);
+
+    let script = temp.child("run.sh");
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["diff", "--base", "main"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Permissions to change:"))
+        .stdout(predicate::str::contains("* run.sh (mode 644 -> 755)"))
+        .stdout(predicate::str::contains("1 mode change(s)"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_base_unknown_ref() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"*.txt\"]\n")
+        .unwrap();
+    temp.child("a.txt").write_str("a").unwrap();
+    git(temp.path(), &["init", "-q"]);
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["diff", "--base", "no-such-ref"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to read files at 'no-such-ref'",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_patch_shows_unified_diff() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"*.txt\"]\n")
+        .unwrap();
+    temp.child("edited.txt").write_str("one\ntwo\n").unwrap();
+    git(temp.path(), &["init", "-q", "-b", "main"]);
+    git(temp.path(), &["config", "user.email", "test@example.com"]);
+    git(temp.path(), &["config", "user.name", "Test"]);
+    git(temp.path(), &["add", "."]);
+    git(temp.path(), &["commit", "-q", "-m", "base"]);
+    temp.child("edited.txt").write_str("one\n2\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["diff", "--base", "main", "--patch"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "--- a/edited.txt\n+++ b/edited.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_remote_consumer_repo() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: [\"**/*\"]\n"),
+            ("shared.txt", "shared\n"),
+            ("LICENSE", "MIT\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let consumer = assert_fs::TempDir::new().unwrap();
+    let config = format!(
+        "- repo:\n    url: \"file://{}\"\n    ref: main\n",
+        upstream.path().display()
+    );
+    init_test_git_repo(
+        &consumer,
+        &[(".common-repo.yaml", config.as_str()), ("LICENSE", "MIT\n")],
+        None,
+    )
+    .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let workdir = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(workdir.path())
+        .arg("diff")
+        .arg("--repo")
+        .arg(format!("file://{}", consumer.path().display()))
+        .args(["--ref", "main", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Files to add:\n  + shared.txt"))
+        .stdout(predicate::str::contains("LICENSE").not());
+    workdir
+        .child("shared.txt")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_repo_requires_ref() {
+    cargo_bin_cmd!("common-repo")
+        .args(["diff", "--repo", "https://example.com/consumer.git"])
+        .assert()
+        .code(64)
+        .stderr(predicate::str::contains("--ref <REF>"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_diff_ignores_expected_drift() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: [\"**/*\"]\n"),
+            ("README.md", "# Service\n\nShared text.\n"),
+            ("ci.yml", "name: 'ci'\n"),
+            ("notes.txt", "upstream\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            r#"- repo:
+    url: "file://{}"
+    ref: main
+- diff-ignore:
+    lines: ['^\[!\[']
+    rules: [yaml-quoting]
+"#,
+            upstream.path().display()
+        ))
+        .unwrap();
+    temp.child("README.md")
+        .write_str("# Service\n[![build](badge.svg)](ci)\n\nShared text.\n")
+        .unwrap();
+    temp.child("ci.yml").write_str("name: ci\n").unwrap();
+    temp.child("notes.txt").write_str("local\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["diff", "--patch", "--cache-dir"])
+        .arg(temp.path().join(".common-repo/cache"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("  ~ notes.txt"))
+        .stdout(predicate::str::contains("README.md").not())
+        .stdout(predicate::str::contains("ci.yml").not());
+}
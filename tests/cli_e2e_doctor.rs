@@ -0,0 +1,81 @@
+//! End-to-end tests for the `common-repo doctor` command.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+/// A project applied from a local upstream, with its own cache.
+fn project() -> assert_fs::TempDir {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success();
+    temp
+}
+
+/// Test that a healthy project passes every check, and that a file deleted
+/// since the last apply is a warning
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_doctor_checks_environment() {
+    let temp = project();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["doctor", "--offline", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("git: git version"))
+        .stdout(predicate::str::contains("is writable"))
+        .stdout(predicate::str::contains(".common-repo.yaml parses"))
+        .stdout(predicate::str::contains(
+            "all 1 files of the last apply are present",
+        ))
+        .stdout(predicate::str::contains("0 warning(s), 0 error(s)"));
+
+    std::fs::remove_file(temp.child("project/ci.yml").path()).unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["doctor", "--offline", "--json", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "ci.yml from the last apply is missing",
+        ))
+        .stdout(predicate::str::contains("\"status\": \"warning\""));
+}
+
+/// Test that a configuration that does not parse fails with a fix
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_doctor_fails_on_invalid_config() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- repo: {url: [\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["doctor", "--offline", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "fix: run `common-repo validate --config .common-repo.yaml` for details",
+        ))
+        .stderr(predicate::str::contains("Doctor found 1 problem(s)"));
+}
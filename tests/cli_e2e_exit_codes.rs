@@ -4,8 +4,13 @@
 //! the standard conventions documented in [`common_repo::exit_codes`]:
 //!
 //! - Exit code 0: Success
-//! - Exit code 1: General error (or changes detected for `diff` command)
-//! - Exit code 2: Invalid command-line usage (handled by clap)
+//! - Exit code 1: Completed with warnings
+//! - Exit code 2: Drift detected (`diff` found changes)
+//! - Exit code 3: Conflicts
+//! - Exit code 4: Fetch failures
+//! - Exit code 5: Configuration errors
+//! - Exit code 6: Any other error
+//! - Exit code 64: Invalid command-line usage (handled by clap)
 
 use assert_cmd::cargo::cargo_bin_cmd;
 use assert_fs::prelude::*;
@@ -52,7 +57,7 @@ fn test_exit_code_version() {
     cmd.arg("--version").assert().code(0);
 }
 
-/// Exit code 1 is returned for configuration file not found.
+/// Exit code 5 is returned for configuration file not found.
 #[test]
 fn test_exit_code_error_config_not_found() {
     let temp = assert_fs::TempDir::new().unwrap();
@@ -64,11 +69,11 @@ fn test_exit_code_error_config_not_found() {
         .arg("--config")
         .arg("nonexistent.yaml")
         .assert()
-        .code(1)
+        .code(5)
         .stderr(predicate::str::contains("No such file or directory"));
 }
 
-/// Exit code 1 is returned for invalid YAML syntax.
+/// Exit code 5 is returned for invalid YAML syntax.
 #[test]
 fn test_exit_code_error_invalid_yaml() {
     let temp = assert_fs::TempDir::new().unwrap();
@@ -91,32 +96,32 @@ fn test_exit_code_error_invalid_yaml() {
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .code(1);
+        .code(5);
 }
 
-/// Exit code 2 is returned for unknown command-line flags (handled by clap).
+/// Exit code 64 is returned for unknown command-line flags (handled by clap).
 #[test]
 fn test_exit_code_usage_unknown_flag() {
     let mut cmd = cargo_bin_cmd!("common-repo");
 
     cmd.arg("--unknown-flag-that-does-not-exist")
         .assert()
-        .code(2)
+        .code(64)
         .stderr(predicate::str::contains("error:"));
 }
 
-/// Exit code 2 is returned for unknown subcommand.
+/// Exit code 64 is returned for unknown subcommand.
 #[test]
 fn test_exit_code_usage_unknown_subcommand() {
     let mut cmd = cargo_bin_cmd!("common-repo");
 
     cmd.arg("unknown-subcommand-xyz")
         .assert()
-        .code(2)
+        .code(64)
         .stderr(predicate::str::contains("error:"));
 }
 
-/// Exit code 2 is returned when required arguments are missing.
+/// Exit code 64 is returned when required arguments are missing.
 #[test]
 fn test_exit_code_usage_missing_required_arg() {
     let mut cmd = cargo_bin_cmd!("common-repo");
@@ -124,11 +129,11 @@ fn test_exit_code_usage_missing_required_arg() {
     // The 'completions' command requires a SHELL argument
     cmd.arg("completions")
         .assert()
-        .code(2)
+        .code(64)
         .stderr(predicate::str::contains("required"));
 }
 
-/// Exit code 2 is returned for invalid argument values.
+/// Exit code 64 is returned for invalid argument values.
 #[test]
 fn test_exit_code_usage_invalid_arg_value() {
     let mut cmd = cargo_bin_cmd!("common-repo");
@@ -137,7 +142,7 @@ fn test_exit_code_usage_invalid_arg_value() {
     cmd.arg("completions")
         .arg("invalid-shell-name")
         .assert()
-        .code(2)
+        .code(64)
         .stderr(predicate::str::contains("invalid value"));
 }
 
@@ -168,6 +173,69 @@ fn test_exit_code_diff_no_changes() {
         .stdout(predicate::str::contains("No changes"));
 }
 
+/// Exit code 2 is returned by diff when changes are detected, followed by
+/// the summary block.
+#[test]
+fn test_exit_code_diff_changes_detected() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .args(["diff", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("outcome:   drift detected"))
+        .stderr(predicate::str::contains("files:     1"));
+}
+
+/// Exit code 5 is returned by apply when there is no configuration, and the
+/// JSON summary names the outcome and error.
+#[test]
+fn test_exit_code_apply_missing_config_json_summary() {
+    let temp = assert_fs::TempDir::new().unwrap();
+
+    let output = cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["--error-format", "json", "apply"])
+        .assert()
+        .code(5)
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(summary["command"], "apply");
+    assert_eq!(summary["outcome"], "config-error");
+    assert_eq!(summary["exit-code"], 5);
+}
+
+/// Exit code 4 is returned when an upstream cannot be fetched.
+#[test]
+fn test_exit_code_fetch_failed() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- repo:\n    url: ./missing-upstream\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("outcome:   fetch failed"));
+}
+
 /// Subcommand help returns exit code 0.
 #[test]
 fn test_exit_code_subcommand_help() {
@@ -176,7 +244,7 @@ fn test_exit_code_subcommand_help() {
     cmd.arg("apply").arg("--help").assert().code(0);
 }
 
-/// Exit code 2 is returned when --verbose and --quiet are used together.
+/// Exit code 64 is returned when --verbose and --quiet are used together.
 #[test]
 fn test_exit_code_usage_verbose_quiet_conflict() {
     let mut cmd = cargo_bin_cmd!("common-repo");
@@ -185,7 +253,7 @@ fn test_exit_code_usage_verbose_quiet_conflict() {
         .arg("--quiet")
         .arg("validate")
         .assert()
-        .code(2)
+        .code(64)
         .stderr(predicate::str::contains("cannot be used with"));
 }
 
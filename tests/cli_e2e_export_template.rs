@@ -0,0 +1,100 @@
+//! End-to-end tests for the `export-template` command
+//!
+//! These tests invoke the actual CLI binary and validate its behavior
+//! from a user's perspective.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+fn consumer_of_local_upstream(tmp: &TempDir) -> std::path::PathBuf {
+    let upstream = tmp.path().join("upstream");
+    fs::create_dir(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(upstream.join("shared.txt"), b"shared").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- header:\n    patterns: ['*.rs']\n    template: Copyright\n",
+    )
+    .unwrap();
+    fs::write(consumer.join("local.txt"), b"local").unwrap();
+    consumer
+}
+
+/// Test that the composed upstream files are exported without local files
+/// and the flattened configuration inherits from the export
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn export_template_writes_composite_and_flat_config() {
+    let tmp = TempDir::new().unwrap();
+    let consumer = consumer_of_local_upstream(&tmp);
+    let out = tmp.path().join("out");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args([
+            "export-template",
+            "--out",
+            "../out",
+            "--cache-dir",
+            "../cache",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported 1 file(s)"))
+        .stdout(predicate::str::contains(format!(
+            "url: {}",
+            out.canonicalize().unwrap().display()
+        )))
+        .stdout(predicate::str::contains("template: Copyright"));
+
+    assert_eq!(
+        fs::read_to_string(out.join("shared.txt")).unwrap(),
+        "shared"
+    );
+    assert!(out.join(".common-repo.yaml").exists());
+    assert!(!out.join("local.txt").exists());
+}
+
+/// Test that a non-empty output directory is refused without --force
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn export_template_refuses_non_empty_out() {
+    let tmp = TempDir::new().unwrap();
+    let consumer = consumer_of_local_upstream(&tmp);
+    let out = tmp.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::write(out.join("existing.txt"), b"keep").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args([
+            "export-template",
+            "--out",
+            "../out",
+            "--cache-dir",
+            "../cache",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args([
+            "export-template",
+            "--out",
+            "../out",
+            "--cache-dir",
+            "../cache",
+        ])
+        .args(["--force", "--flat-config", "flat.yaml"])
+        .assert()
+        .success();
+    assert!(out.join("shared.txt").exists());
+    assert!(consumer.join("flat.yaml").exists());
+}
@@ -0,0 +1,95 @@
+//! End-to-end tests for GitHub Actions annotations and job summaries.
+//!
+//! Each test sets the variables a GitHub Actions runner provides:
+//! `GITHUB_ACTIONS`, `GITHUB_WORKSPACE` and `GITHUB_STEP_SUMMARY`.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A project inheriting `README.md` from a local upstream.
+fn setup(tmp: &Path) {
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(upstream.join("README.md"), "# Shared\n").unwrap();
+
+    let project = tmp.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join(".common-repo.yaml"),
+        "- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+}
+
+fn command(tmp: &Path, github_actions: bool) -> assert_cmd::Command {
+    let mut cmd = cargo_bin_cmd!("common-repo");
+    cmd.current_dir(tmp.join("project"))
+        .env(
+            "GITHUB_ACTIONS",
+            if github_actions { "true" } else { "false" },
+        )
+        .env("GITHUB_WORKSPACE", tmp.join("project"))
+        .env("GITHUB_STEP_SUMMARY", tmp.join("summary.md"));
+    cmd
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn diff_annotates_drifted_files() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    command(tmp.path(), true)
+        .args(["diff", "--cache-root"])
+        .arg(tmp.path().join("cache"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "::warning file=README.md,title=Configuration drift::This file would be added by `common-repo apply`",
+        ));
+
+    let summary = fs::read_to_string(tmp.path().join("summary.md")).unwrap();
+    assert!(summary.contains("### common-repo diff"), "{summary}");
+    assert!(summary.contains("| `README.md` | added |"), "{summary}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_writes_job_summary_and_annotates_errors() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    command(tmp.path(), true)
+        .args(["apply", "--cache-root"])
+        .arg(tmp.path().join("cache"))
+        .assert()
+        .success();
+    let summary = fs::read_to_string(tmp.path().join("summary.md")).unwrap();
+    assert!(summary.contains("### common-repo apply"), "{summary}");
+    assert!(summary.contains("file(s) written to `.`"), "{summary}");
+
+    command(tmp.path(), true)
+        .args(["apply", "--config", "missing.yaml"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("::error title=common-repo error"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn no_annotations_outside_github_actions() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    command(tmp.path(), false)
+        .args(["diff", "--cache-root"])
+        .arg(tmp.path().join("cache"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("::warning").not());
+    assert!(!tmp.path().join("summary.md").exists());
+}
@@ -0,0 +1,95 @@
+//! End-to-end tests for the history of applies.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+use std::process::Command;
+
+/// Test that each apply that writes records the upstream commit and the
+/// managed files it changed, and that `history` shows them newest first
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_records_history() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: ['**']\n"),
+            ("ci.yml", "on: push\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    let head = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(upstream.path())
+        .output()
+        .unwrap();
+    let head = String::from_utf8(head.stdout).unwrap().trim().to_string();
+    let project = assert_fs::TempDir::new().unwrap();
+    project
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+    // Without a git identity, the user is the login name, if any.
+    let apply = |force: bool, user: Option<&str>| {
+        let mut cmd = cargo_bin_cmd!("common-repo");
+        cmd.current_dir(project.path())
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env_remove("USERNAME")
+            .args(["apply", "--cache-dir"])
+            .arg(cache.path());
+        match user {
+            Some(user) => cmd.env("USER", user),
+            None => cmd.env_remove("USER"),
+        };
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.assert().success();
+    };
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No applies recorded"));
+
+    apply(false, Some("ada"));
+    apply(true, None);
+
+    let output = cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["history", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    // Newest first: the forced apply changed nothing.
+    assert_eq!(entries[0]["files"], serde_json::json!([]));
+    assert_eq!(entries[1]["files"], serde_json::json!(["ci.yml"]));
+    assert_eq!(entries[1]["upstreams"][0]["ref"], "v1.0.0");
+    assert_eq!(entries[1]["upstreams"][0]["commit"], head.as_str());
+    assert_eq!(entries[1]["user"], "ada");
+    assert!(entries[0].get("user").is_none(), "{}", entries[0]);
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["history", "-n", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 file(s) changed"))
+        .stdout(predicate::str::contains(&head[..12]))
+        .stdout(predicate::str::contains("ci.yml").not());
+}
@@ -149,7 +149,7 @@ fn if_exists_consumer_excludes_to_opt_out_of_upstream_auto_merge() {
     );
 
     let mut cmd = cargo_bin_cmd!("common-repo");
-    cmd.current_dir(&consumer).arg("apply").assert().success();
+    cmd.current_dir(&consumer).arg("apply").assert().code(1);
 
     // The snapshot-based auto-merge fires even though foo.yaml was excluded
     // from the composite: the upstream key wins the scalar conflict.
@@ -0,0 +1,70 @@
+//! End-to-end tests for the `import` command
+//!
+//! These tests invoke the actual CLI binary and validate its behavior
+//! from a user's perspective.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+const CRUFT_STATE: &str = r#"{
+  "template": "https://github.com/acme/cookiecutter-lib",
+  "commit": "0123456789abcdef0123456789abcdef01234567",
+  "checkout": null,
+  "context": {
+    "cookiecutter": {
+      "project_slug": "demo",
+      "authors": ["me"],
+      "_template": "https://github.com/acme/cookiecutter-lib"
+    }
+  },
+  "directory": null
+}
+"#;
+
+/// Test that importing cruft state writes a configuration with the template
+/// pinned and the answers as template variables
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_import_cruft_writes_config() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".cruft.json").write_str(CRUFT_STATE).unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["import", "--from", "cruft"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 template variable(s)"))
+        .stderr(predicate::str::contains("'authors'"));
+
+    temp.child(".common-repo.yaml").assert(
+        predicate::str::contains("url: https://github.com/acme/cookiecutter-lib")
+            .and(predicate::str::contains(
+                "ref: 0123456789abcdef0123456789abcdef01234567",
+            ))
+            .and(predicate::str::contains("project_slug: demo")),
+    );
+}
+
+/// Test that --dry-run prints the configuration without writing it
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_import_copier_dry_run() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".copier-answers.yml")
+        .write_str("_commit: v1.0.0\n_src_path: gh:acme/template\nname: demo\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["import", "--from", "copier", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "url: https://github.com/acme/template.git",
+        ));
+
+    temp.child(".common-repo.yaml")
+        .assert(predicate::path::missing());
+}
@@ -3,8 +3,11 @@
 //! These tests invoke the actual CLI binary and validate the behavior of the
 //! `info` subcommand from a user's perspective.
 
+mod common;
+
 use assert_cmd::cargo::cargo_bin_cmd;
 use assert_fs::prelude::*;
+use common::init_test_git_repo;
 use predicates::prelude::*;
 
 /// Test that info --help flag shows help information
@@ -299,3 +302,117 @@ fn test_info_template_vars() {
         .stdout(predicate::str::contains("1 template operations"))
         .stdout(predicate::str::contains("1 template_vars operations"));
 }
+
+/// Test that info --stats shows what each upstream contributes
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_info_stats_per_upstream() {
+    let base = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &base,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: [\"**/*\"]\n- template: [\"README.md\"]\n",
+            ),
+            ("README.md", "# __COMMON_REPO__name__\n"),
+            ("LICENSE", "MIT License, shared\n"),
+            ("scratch.tmp", "x"),
+        ],
+        None,
+    )
+    .unwrap();
+    let extras = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &extras,
+        &[
+            (".common-repo.yaml", "- include: [\"**/*\"]\n"),
+            ("LICENSE", "Apache\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            r#"- template-vars:
+    name: demo
+- repo:
+    url: "file://{}"
+    ref: main
+- repo:
+    url: "file://{}"
+    ref: main
+- exclude: ["*.tmp"]
+"#,
+            base.path().display(),
+            extras.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["info", "--stats", "--cache-dir"])
+        .arg(temp.path().join(".common-repo/cache"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "  • file://{} @ main: 1 files, 7B\n\
+             \x20     1 overridden\n\
+             \x20     1 not in the output\n\
+             \x20     README.md (7B)\n",
+            base.path().display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "  • file://{} @ main: 1 files, 7B\n\
+             \x20     LICENSE (7B)\n",
+            extras.path().display()
+        )));
+}
+
+/// Test that info --vars shows where each template variable gets its value
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_info_vars_definition_sites() {
+    let base = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &base,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: [\"**/*\"]\n- template-vars:\n    owner: infra\n    license: MIT\n",
+            ),
+            ("README.md", "# readme\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            r#"- repo:
+    url: "file://{}"
+    ref: main
+- template-vars:
+    owner: platform
+"#,
+            base.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["info", "--vars", "--cache-dir"])
+        .arg(temp.path().join(".common-repo/cache"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Template variables: 2\n  \
+             license = \"MIT\"\n      \
+             1. file://{0} @ main: \"MIT\"  (used)\n  \
+             owner = \"platform\"\n      \
+             1. file://{0} @ main: \"infra\"\n      \
+             2. local config: \"platform\"  (used)\n",
+            base.path().display()
+        )));
+}
@@ -37,10 +37,11 @@ fn stage_fixture() -> (TempDir, PathBuf) {
     (tmp, staged)
 }
 
-/// Run `common-repo apply` in `dir`; panic if it fails.
+/// Run `common-repo apply` in `dir`; panic if it fails. Exit code 1 is
+/// applied with warnings.
 fn apply_in(dir: &Path) {
     let mut cmd = cargo_bin_cmd!("common-repo");
-    cmd.current_dir(dir).arg("apply").assert().success();
+    cmd.current_dir(dir).arg("apply").assert().code(&[0, 1][..]);
 }
 
 /// Read and parse `dir/merge.yaml` as a `serde_yaml::Value`.
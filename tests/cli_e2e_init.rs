@@ -25,7 +25,7 @@ fn test_init_with_uri_positional_arg() {
         .assert()
         .success()
         .stdout(predicate::str::contains("Fetching tags from"))
-        .stdout(predicate::str::contains("✅ Created .common-repo.yaml"));
+        .stdout(predicate::str::contains("Created .common-repo.yaml"));
 
     // Check that the config file was created with the repo
     let config_file = temp.child(".common-repo.yaml");
@@ -90,7 +90,7 @@ fn test_init_with_uri_force_overwrite() {
         .assert()
         .success()
         .stdout(predicate::str::contains("Fetching tags from"))
-        .stdout(predicate::str::contains("✅ Created .common-repo.yaml"));
+        .stdout(predicate::str::contains("Created .common-repo.yaml"));
 
     // Verify the content was overwritten
     config_file.assert(predicate::str::contains("# common-repo configuration"));
@@ -0,0 +1,80 @@
+//! End-to-end tests for the `lint-upstream` command.
+//!
+//! These tests invoke the actual CLI binary against small upstream trees and
+//! check the reported findings and exit status.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_lint_upstream_clean() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"templates/**\"]\n")
+        .unwrap();
+    temp.child("templates/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+    temp.child("README.md").write_str("# Templates\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("lint-upstream")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 finding(s)"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_lint_upstream_reports_and_disables_rules() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- exclude: [\"internal/**\"]\n")
+        .unwrap();
+    temp.child("LICENSE").write_str("MIT\n").unwrap();
+    temp.child(".github/workflows/ci.yml")
+        .write_str("jobs:\n  test:\n    steps:\n      - uses: actions/checkout@v4\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("lint-upstream")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "include-scope: there is no include",
+        ))
+        .stdout(predicate::str::contains("own-docs: LICENSE:"))
+        .stdout(predicate::str::contains(
+            "unpinned-actions: .github/workflows/ci.yml:4: actions/checkout@v4",
+        ));
+
+    let output = cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args([
+            "lint-upstream",
+            "--json",
+            "--disable",
+            "include-scope,own-docs",
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let findings = report["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0]["rule"], "unpinned-actions");
+    assert_eq!(findings[0]["line"], 4);
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["lint-upstream", "--disable", "include-scope"])
+        .args(["--disable", "own-docs", "--disable", "unpinned-actions"])
+        .assert()
+        .success();
+}
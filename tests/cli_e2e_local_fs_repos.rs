@@ -73,3 +73,993 @@ fn apply_accepts_absolute_local_path() {
     cmd.current_dir(&consumer).arg("apply").assert().success();
     assert!(consumer.join("payload.txt").exists());
 }
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_errors_when_upstream_requires_newer_version() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- requires: \">=999.0\"\n- include: ['**']\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("payload.txt"), b"hello").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- requires: \">=0.1\"\n- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("common-repo");
+    cmd.current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Upstream configuration"))
+        .stderr(predicate::str::contains("requires common-repo >=999.0"))
+        .stderr(predicate::str::contains("E0005"));
+    assert!(!consumer.join("payload.txt").exists());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_skips_upstream_operations_disabled_by_id() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- yaml:\n    auto-merge: config.yaml\n  id: shared-config\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("config.yaml"), b"upstream: true\n").unwrap();
+    fs::write(sibling.join("payload.txt"), b"hello").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(consumer.join("config.yaml"), b"local: true\n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n    disable: [shared-config, no-such-id]\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("common-repo");
+    cmd.current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "no operation with id 'no-such-id'",
+        ));
+
+    assert_eq!(fs::read(consumer.join("payload.txt")).unwrap(), b"hello");
+    // Without the auto-merge the included upstream file replaces the local one
+    assert_eq!(
+        fs::read_to_string(consumer.join("config.yaml")).unwrap(),
+        "upstream: true\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_patches_inherited_file_and_reports_stale_patch() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("README.md"), b"# Project\nOwner: upstream\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(consumer.join("patches")).unwrap();
+    fs::write(
+        consumer.join("patches/readme.diff"),
+        b"--- a/README.md\n+++ b/README.md\n@@ -1,2 +1,2 @@\n # Project\n-Owner: upstream\n+Owner: consumer\n",
+    )
+    .unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- patch:\n    file: README.md\n    patch: patches/readme.diff\n",
+    )
+    .unwrap();
+
+    // Applying twice gives the same result: the patch lands on the fresh
+    // upstream content each time
+    for _ in 0..2 {
+        cargo_bin_cmd!("common-repo")
+            .current_dir(&consumer)
+            .arg("apply")
+            .assert()
+            .success();
+        assert_eq!(
+            fs::read_to_string(consumer.join("README.md")).unwrap(),
+            "# Project\nOwner: consumer\n"
+        );
+    }
+
+    fs::write(
+        sibling.join("README.md"),
+        b"# Project\nMaintainers: upstream\n",
+    )
+    .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Patch patches/readme.diff does not apply to README.md",
+        ))
+        .stderr(predicate::str::contains("E2006"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_refuses_patch_outside_the_repository() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("README.md"), b"# Project\nOwner: upstream\n").unwrap();
+    fs::write(
+        tmp.path().join("readme.diff"),
+        b"--- a/README.md\n+++ b/README.md\n@@ -1,2 +1,2 @@\n # Project\n-Owner: upstream\n+Owner: consumer\n",
+    )
+    .unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- patch:\n    file: README.md\n    patch: ../readme.diff\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the patch ../readme.diff is outside the repository",
+        ));
+    assert!(!consumer.join("README.md").exists());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("validate")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "patch file is outside the repository: ../readme.diff",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_adds_license_header_once() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(sibling.join("src")).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("src/lib.rs"), b"pub fn shared() {}\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(consumer.join("scripts")).unwrap();
+    fs::write(consumer.join("scripts/run.sh"), b"#!/bin/sh\necho run\n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- header:\n    patterns: ['src/**/*.rs', 'scripts/*.sh']\n    template: |\n      Copyright 2026 Example Corp.\n",
+    )
+    .unwrap();
+
+    // Headers land on inherited and local files alike, and only once
+    for _ in 0..2 {
+        cargo_bin_cmd!("common-repo")
+            .current_dir(&consumer)
+            .args(["apply", "--force"])
+            .assert()
+            .success();
+        assert_eq!(
+            fs::read_to_string(consumer.join("src/lib.rs")).unwrap(),
+            "// Copyright 2026 Example Corp.\n\npub fn shared() {}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(consumer.join("scripts/run.sh")).unwrap(),
+            "#!/bin/sh\n# Copyright 2026 Example Corp.\n\necho run\n"
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_formats_inherited_files_with_editorconfig() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("config.yml"), b"a:\r\n\tb: 1   \r\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".editorconfig"),
+        b"root = true\n\n[*]\nend_of_line = lf\ntrim_trailing_whitespace = true\n\n[*.yml]\nindent_style = space\nindent_size = 2\n",
+    )
+    .unwrap();
+    fs::write(consumer.join("local.yml"), b"x:\n\ty: 2   \n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- format: editorconfig\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+
+    // Inherited files follow the consumer's conventions; local files are
+    // left as they are
+    assert_eq!(
+        fs::read_to_string(consumer.join("config.yml")).unwrap(),
+        "a:\n  b: 1\n"
+    );
+    assert_eq!(
+        fs::read_to_string(consumer.join("local.yml")).unwrap(),
+        "x:\n\ty: 2   \n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_writes_merged_yaml_in_canonical_form() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(
+        sibling.join("env.yml"),
+        b"env:\n    ZED: 1\n    ALPHA: 'on'\n",
+    )
+    .unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(consumer.join("extra.yml"), b"env:\n  MIDDLE: \"x\"\n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- yaml:\n    source: extra.yml\n    dest: env.yml\n- yaml-format:\n    patterns: ['env.yml']\n    sort-keys: ['env']\n    quote: double\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(consumer.join("env.yml")).unwrap(),
+        "env:\n  ALPHA: \"on\"\n  MIDDLE: x\n  ZED: 1\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_merges_issue_form_fields_by_id() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(sibling.join(".github/ISSUE_TEMPLATE")).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- yaml:\n    auto-merge: .github/ISSUE_TEMPLATE/bug.yml\n    array_key: id\n",
+    )
+    .unwrap();
+    fs::write(
+        sibling.join(".github/ISSUE_TEMPLATE/bug.yml"),
+        b"name: Bug\nbody:\n  - type: input\n    id: version\n    attributes:\n      label: Version (required)\n  - type: input\n    id: os\n    attributes:\n      label: OS\n",
+    )
+    .unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(consumer.join(".github/ISSUE_TEMPLATE")).unwrap();
+    fs::write(
+        consumer.join(".github/ISSUE_TEMPLATE/bug.yml"),
+        b"name: Bug\nbody:\n  - type: input\n    id: version\n    attributes:\n      label: Version\n  - type: dropdown\n    id: team\n    attributes:\n      label: Team\n",
+    )
+    .unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .code(1);
+
+    // The upstream's fields are updated and added in its order; the
+    // consumer's own field is kept
+    let form: serde_yaml::Value = serde_yaml::from_str(
+        &fs::read_to_string(consumer.join(".github/ISSUE_TEMPLATE/bug.yml")).unwrap(),
+    )
+    .unwrap();
+    let body = form["body"].as_sequence().unwrap();
+    let ids: Vec<&str> = body.iter().map(|f| f["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["version", "os", "team"]);
+    assert_eq!(body[0]["attributes"]["label"], "Version (required)");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_extracts_one_job_of_an_upstream_workflow() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(
+        sibling.join("ci.yml"),
+        b"on: push\njobs:\n  lint:\n    runs-on: ubuntu-latest\n  test:\n    runs-on: macos-latest\n",
+    )
+    .unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n    with:\n      - extract:\n          source: ci.yml\n          dest: lint.yml\n          path: jobs.lint\n          keep-path: true\n      - exclude: ['ci.yml']\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(consumer.join("lint.yml")).unwrap(),
+        "jobs:\n  lint:\n    runs-on: ubuntu-latest\n"
+    );
+    assert!(!consumer.join("ci.yml").exists());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_writes_map_of_managed_files() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(sibling.join("workflows")).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- template: ['workflows/*']\n",
+    )
+    .unwrap();
+    fs::write(
+        sibling.join("workflows/ci.yml"),
+        b"name: __COMMON_REPO__NAME__\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("LICENSE"), b"MIT\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(consumer.join("main.rs"), b"fn main() {}\n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n    with:\n      - rename:\n          - from: '^workflows/(.*)$'\n            to: '.github/workflows/$1'\n- template-vars:\n    NAME: ci\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+
+    let map: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(consumer.join(".common-repo/map.json")).unwrap())
+            .unwrap();
+    assert_eq!(map["version"], 1);
+    let files = map["files"].as_object().unwrap();
+    assert_eq!(files.len(), 2, "{}", map);
+    let ci = &files[".github/workflows/ci.yml"];
+    assert!(ci["repo"].as_str().unwrap().ends_with("upstream"), "{}", ci);
+    assert_eq!(ci["source"], "workflows/ci.yml");
+    assert_eq!(ci["operations"], serde_json::json!(["rename", "template"]));
+    assert_eq!(files["LICENSE"]["operations"], serde_json::json!([]));
+}
+
+#[cfg(unix)]
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_protects_managed_files_read_only_and_with_a_hook() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- protect: [read-only, pre-commit]\n",
+    )
+    .unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(&consumer)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    git(&["init", "-q"]);
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+    let mode = || {
+        fs::metadata(consumer.join("ci.yml"))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o222
+    };
+    assert_eq!(mode(), 0, "ci.yml should be read-only");
+    git(&["add", "-A"]);
+    assert!(git(&["commit", "-q", "-m", "apply"]).status.success());
+
+    // A direct edit is rejected by the hook
+    let ci = consumer.join("ci.yml");
+    fs::set_permissions(&ci, fs::Permissions::from_mode(0o644)).unwrap();
+    fs::write(&ci, b"on: pull_request\n").unwrap();
+    git(&["add", "ci.yml"]);
+    assert!(!git(&["commit", "-q", "-m", "edit"]).status.success());
+
+    // The next apply restores the file and makes it read-only again
+    fs::write(sibling.join("ci.yml"), b"on: [push, pull_request]\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&ci).unwrap(),
+        "on: [push, pull_request]\n"
+    );
+    assert_eq!(mode(), 0, "ci.yml should be read-only again");
+    git(&["add", "ci.yml"]);
+    assert!(git(&["commit", "-q", "-m", "update"]).status.success());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_templates_secret_from_env_file_and_diff_masks_it() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- template: ['npmrc']\n",
+    )
+    .unwrap();
+    fs::write(
+        sibling.join("npmrc"),
+        b"//registry.npmjs.org/:_authToken=__COMMON_REPO__NPM_TOKEN__\n",
+    )
+    .unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(consumer.join(".env"), b"NPM_TOKEN=npm_s3cr3t_value\n").unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- template-vars:\n    NPM_TOKEN: { from_env_file: .env }\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--force"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(consumer.join("npmrc")).unwrap(),
+        "//registry.npmjs.org/:_authToken=npm_s3cr3t_value\n"
+    );
+
+    fs::write(consumer.join("npmrc"), b"edited\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["diff", "--patch"])
+        .assert()
+        .stdout(predicate::str::contains("_authToken=********"))
+        .stdout(predicate::str::contains("npm_s3cr3t_value").not());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_runs_consumer_operations_once_when_checking_before_writing() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- template: ['ci.yml']\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("ci.yml"), b"run: __COMMON_REPO__RUN__\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    // Each read of the secret appends a line outside the working tree and
+    // prints a different value
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- template-vars:\n    RUN: { from_command: \"echo read >> ../reads.log; wc -l < ../reads.log | tr -d ' '\" }\n",
+    )
+    .unwrap();
+
+    // --deny-warnings checks the output before writing it
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--deny-warnings"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("reads.log")).unwrap(),
+        "read\n"
+    );
+    assert_eq!(
+        fs::read_to_string(consumer.join("ci.yml")).unwrap(),
+        "run: 1\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_from_plan_writes_what_diff_saved() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["diff", "--save-plan", "../plan.bin"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("ci.yml"));
+
+    // The upstream moves on between review and apply
+    fs::write(sibling.join("ci.yml"), b"on: pull_request\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--from-plan", "../plan.bin"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(consumer.join("ci.yml")).unwrap(),
+        "on: push\n"
+    );
+    assert!(consumer.join(".common-repo/map.json").exists());
+
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- exclude: ['ci.yml']\n",
+    )
+    .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--from-plan", "../plan.bin"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("different configuration"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_from_plan_refuses_after_local_edits() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+    fs::write(consumer.join("notes.md"), b"draft\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["diff", "--save-plan", "../plan.bin"])
+        .assert()
+        .code(2);
+
+    // An edit between review and apply is not overwritten with the old copy,
+    // while a file the plan neither read nor writes does not matter
+    fs::write(consumer.join("notes.md"), b"final\n").unwrap();
+    fs::write(consumer.join("scratch.txt"), b"unrelated\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--from-plan", "../plan.bin"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Local files changed since the plan was computed: notes.md\n",
+        ))
+        .stderr(predicate::str::contains("E4005"))
+        .stderr(predicate::str::contains("outcome:   stale plan"));
+    assert_eq!(
+        fs::read_to_string(consumer.join("notes.md")).unwrap(),
+        "final\n"
+    );
+    assert!(!consumer.join("ci.yml").exists());
+
+    fs::write(consumer.join("notes.md"), b"draft\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--from-plan", "../plan.bin"])
+        .assert()
+        .success();
+    assert!(consumer.join("ci.yml").exists());
+
+    fs::write(consumer.join("notes.md"), b"final\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["diff", "--save-plan", "../plan.bin"])
+        .assert()
+        .code(0);
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--from-plan", "../plan.bin"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(consumer.join("notes.md")).unwrap(),
+        "final\n"
+    );
+    assert!(consumer.join("ci.yml").exists());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_into_places_output_under_subdirectory() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(consumer.join("infra/shared")).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+    fs::write(consumer.join("README.md"), b"# consumer\n").unwrap();
+    fs::write(consumer.join("infra/shared/notes.txt"), b"mine\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--into", "infra/shared"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(consumer.join("infra/shared/ci.yml")).unwrap(),
+        "on: push\n"
+    );
+    assert!(!consumer.join("ci.yml").exists());
+    assert!(!consumer.join("infra/shared/README.md").exists());
+    assert_eq!(
+        fs::read_to_string(consumer.join("infra/shared/notes.txt")).unwrap(),
+        "mine\n"
+    );
+    let map = fs::read_to_string(consumer.join(".common-repo/map.json")).unwrap();
+    assert!(map.contains("\"infra/shared/ci.yml\""), "map was: {}", map);
+    assert!(!map.contains("rename"), "map was: {}", map);
+
+    // The manifest lists the placed paths, so an upstream rename moves the
+    // placed file instead of leaving a stale copy behind.
+    fs::rename(sibling.join("ci.yml"), sibling.join("build.yml")).unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--into", "infra/shared"])
+        .assert()
+        .success();
+    assert!(consumer.join("infra/shared/build.yml").exists());
+    assert!(!consumer.join("infra/shared/ci.yml").exists());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--into", "../outside"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected a relative path"));
+}
+
+/// The first apply into a subdirectory creates it: a missing directory has
+/// no local files, and is not an inaccessible path to warn about.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_into_creates_missing_subdirectory() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--into", "infra/shared"])
+        .assert()
+        .code(0)
+        .stderr(predicate::str::contains("Warning").not())
+        .stderr(predicate::str::contains("files:     1"));
+    assert_eq!(
+        fs::read_to_string(consumer.join("infra/shared/ci.yml")).unwrap(),
+        "on: push\n"
+    );
+
+    // Replacing a local file counts as a change too, though the manifest
+    // does not list it.
+    fs::write(consumer.join("infra/shared/ci.yml"), b"on: pull_request\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--into", "infra/shared", "--force"])
+        .assert()
+        .code(0)
+        .stderr(predicate::str::contains("files:     1"));
+}
+
+/// Files the last apply wrote but nobody committed yet are generated output,
+/// not local work, so a second apply replaces them without `--force-untracked`.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_twice_without_commit_updates_generated_untracked_files() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("README.md"), b"# v1\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+    let status = std::process::Command::new("git")
+        .current_dir(&consumer)
+        .args(["init", "-q"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(consumer.join("README.md")).unwrap(),
+        "# v1\n"
+    );
+
+    fs::write(sibling.join("README.md"), b"# v2\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Refusing to overwrite").not());
+    assert_eq!(
+        fs::read_to_string(consumer.join("README.md")).unwrap(),
+        "# v2\n"
+    );
+
+    // A hand edit to the generated file makes it local work again
+    fs::write(consumer.join("README.md"), b"# mine\n").unwrap();
+    fs::write(sibling.join("README.md"), b"# v3\n").unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("README.md"));
+    assert_eq!(
+        fs::read_to_string(consumer.join("README.md")).unwrap(),
+        "# mine\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_sandbox_runs_command_on_output_and_leaves_tree_alone() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir_all(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("ci.yml"), b"on: push\n").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir_all(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+    fs::write(consumer.join("local.txt"), b"mine\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args([
+            "apply",
+            "--sandbox",
+            "--run",
+            "grep -q push ci.yml && test -f local.txt",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("passed in the sandbox"));
+    assert!(!consumer.join("ci.yml").exists());
+    assert!(!consumer.join(".common-repo").exists());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--sandbox", "--run", "exit 3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("failed in the sandbox"));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--run", "true"])
+        .assert()
+        .failure();
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_does_not_interpolate_environment_into_upstream_config() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- rename:\n    - \"^payload\\\\.txt$\": \"${CONSUMER_TOKEN}.txt\"\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("payload.txt"), b"hello").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    // The reference is undefined in the upstream's configuration, which is
+    // then not read at all
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .env("CONSUMER_TOKEN", "s3cr3t-token")
+        .args(["--verbose", "apply"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("s3cr3t-token").not())
+        .stderr(predicate::str::contains("s3cr3t-token").not());
+    assert!(!consumer.join("s3cr3t-token.txt").exists());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_strict_refuses_unknown_operations() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(
+        sibling.join(".common-repo.yaml"),
+        b"- include: ['**']\n- not-an-operation:\n    source: third_party\n",
+    )
+    .unwrap();
+    fs::write(sibling.join("payload.txt"), b"hello").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+
+    for command in ["apply", "check"] {
+        cargo_bin_cmd!("common-repo")
+            .current_dir(&consumer)
+            .args([command, "--strict"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("'not-an-operation' in"));
+    }
+    assert!(!consumer.join("payload.txt").exists());
+
+    // Without --strict the upstream's unknown operation is skipped, which
+    // ends with the exit code for warnings
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Unknown operation 'not-an-operation' skipped",
+        ));
+    assert_eq!(fs::read(consumer.join("payload.txt")).unwrap(), b"hello");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_refuses_misspelled_operations() {
+    let tmp = TempDir::new().unwrap();
+    let sibling = tmp.path().join("upstream");
+    fs::create_dir(&sibling).unwrap();
+    fs::write(sibling.join(".common-repo.yaml"), b"- include: ['**']\n").unwrap();
+    fs::write(sibling.join("payload.txt"), b"hello").unwrap();
+
+    let consumer = tmp.path().join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        b"- repo:\n    url: ../upstream\n- exlude: ['payload.txt']\n",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .arg("apply")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("did you mean 'exclude'?"));
+    assert!(!consumer.join("payload.txt").exists());
+}
@@ -0,0 +1,77 @@
+//! End-to-end tests for `apply --max-memory`.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn upstream(tmp: &Path, name: &str, content: &str) {
+    let dir = tmp.join(name);
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(dir.join(format!("{name}.txt")), content).unwrap();
+}
+
+fn consumer(tmp: &Path) -> std::path::PathBuf {
+    upstream(tmp, "first", &"a".repeat(4096));
+    upstream(tmp, "second", &"b".repeat(4096));
+    let consumer = tmp.join("consumer");
+    fs::create_dir(&consumer).unwrap();
+    fs::write(
+        consumer.join(".common-repo.yaml"),
+        "- repo:\n    url: ../first\n- repo:\n    url: ../second\n",
+    )
+    .unwrap();
+    consumer
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_within_memory_budget_writes_all_files() {
+    let tmp = TempDir::new().unwrap();
+    let consumer = consumer(tmp.path());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--max-memory", "1M"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("--max-memory").not());
+
+    assert_eq!(fs::read(consumer.join("first.txt")).unwrap().len(), 4096);
+    assert_eq!(fs::read(consumer.join("second.txt")).unwrap().len(), 4096);
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_warns_when_composite_exceeds_memory_budget() {
+    let tmp = TempDir::new().unwrap();
+    let consumer = consumer(tmp.path());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--max-memory", "4K"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "more than the --max-memory budget of 4096 bytes",
+        ));
+
+    assert_eq!(fs::read(consumer.join("first.txt")).unwrap().len(), 4096);
+    assert_eq!(fs::read(consumer.join("second.txt")).unwrap().len(), 4096);
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_rejects_invalid_memory_budget() {
+    let tmp = TempDir::new().unwrap();
+    let consumer = consumer(tmp.path());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(&consumer)
+        .args(["apply", "--max-memory", "lots"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size 'lots'"));
+}
@@ -0,0 +1,111 @@
+//! End-to-end tests for the `migrate-config` command.
+//!
+//! These tests invoke the actual CLI binary on configs written for older
+//! releases and check the rewritten file and exit status.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+const OLD_CONFIG: &str = "\
+# Shared formatting settings
+- json:
+    source: fragments/settings.json
+    dest: .vscode/settings.json
+    append: true # keep both lists
+- include: \"fragments/**\"
+";
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_migrate_config_check_fails_on_old_shapes() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = temp.child(".common-repo.yaml");
+    config.write_str(OLD_CONFIG).unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("migrate-config")
+        .arg("--check")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("line 5:"))
+        .stdout(predicate::str::contains("line 6:"));
+
+    config.assert(OLD_CONFIG);
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_migrate_config_rewrites_preserving_comments() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = temp.child(".common-repo.yaml");
+    config.write_str(OLD_CONFIG).unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("migrate-config")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 change(s)"));
+
+    config.assert(
+        "\
+# Shared formatting settings
+- json:
+    source: fragments/settings.json
+    dest: .vscode/settings.json
+    array_mode: append # keep both lists
+- include: [\"fragments/**\"]
+",
+    );
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("migrate-config")
+        .arg("--check")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already uses the current schema"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_migrate_config_converts_to_a_document_that_applies() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = temp.child(".common-repo.yaml");
+    config.write_str(OLD_CONFIG).unwrap();
+    temp.child("fragments/settings.json")
+        .write_str("{\"tabs\": [1]}")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["migrate-config", "--to", "document"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 change(s)"));
+
+    config.assert(
+        "\
+# Shared formatting settings
+apiVersion: common-repo/v1
+operations:
+  - json:
+      source: fragments/settings.json
+      dest: .vscode/settings.json
+      array_mode: append # keep both lists
+  - include: [\"fragments/**\"]
+",
+    );
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(temp.path().join(".cache"))
+        .assert()
+        .success();
+    temp.child(".vscode/settings.json")
+        .assert(predicate::str::contains("tabs"));
+}
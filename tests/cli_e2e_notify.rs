@@ -0,0 +1,120 @@
+//! End-to-end tests for the webhooks told about apply results.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Accept one request on `listener`, answer it with `200 OK`, and return
+/// its body.
+fn serve_one(listener: TcpListener) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    })
+}
+
+/// A project inheriting one file from a local upstream.
+fn project() -> assert_fs::TempDir {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("upstream/.common-repo.yaml")
+        .write_str("- include: ['**']\n")
+        .unwrap();
+    temp.child("upstream/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+    temp.child("project/.common-repo.yaml")
+        .write_str("- repo:\n    url: ../upstream\n")
+        .unwrap();
+    temp
+}
+
+/// Test that a webhook in the user configuration is sent a JSON report of
+/// the apply
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_reports_to_webhook() {
+    let temp = project();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = serve_one(listener);
+    temp.child("user.yaml")
+        .write_str("webhooks:\n  - url-env: TEST_WEBHOOK_URL\n    on: changes\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .env("COMMON_REPO_USER_CONFIG", temp.child("user.yaml").path())
+        .env(
+            "TEST_WEBHOOK_URL",
+            format!("http://127.0.0.1:{}/hook", port),
+        )
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success();
+
+    let report: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+    assert_eq!(report["status"], "success");
+    assert_eq!(report["files"], serde_json::json!(["ci.yml"]));
+    assert!(report["upstreams"][0]["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("upstream"));
+    assert_eq!(report["warnings"], 0);
+}
+
+/// Test that a webhook that cannot be reached is a warning, not a failure
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_unreachable_webhook_does_not_fail_apply() {
+    let temp = project();
+    // Bound and dropped, so nothing listens on the port.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    temp.child("user.yaml")
+        .write_str(&format!(
+            "webhooks:\n  - url: http://127.0.0.1:{}/secret-token\n    format: slack\n",
+            port
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.child("project").path())
+        .env("COMMON_REPO_USER_CONFIG", temp.child("user.yaml").path())
+        .args(["apply", "--cache-dir"])
+        .arg(temp.child("cache").path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Could not notify the webhook at 127.0.0.1",
+        ))
+        .stderr(predicate::str::contains("secret-token").not());
+    temp.child("project/ci.yml").assert("on: push\n");
+}
@@ -0,0 +1,104 @@
+//! End-to-end tests for `oci://` repo sources.
+//!
+//! A stand-in `oras` script on `PATH` plays the registry: it logs its
+//! arguments, resolves every tag to `$FAKE_ORAS_DIGEST` and "pulls" by
+//! copying the `$FAKE_ORAS_ARTIFACT` directory.
+
+#![cfg(unix)]
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempfile::TempDir;
+
+const DIGEST: &str = "sha256:6c3c624b58dbbcd3c0dd82b4c53f04194d1247c6eebdaab7c610cf7d66709b3b";
+const OTHER_DIGEST: &str =
+    "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+const FAKE_ORAS: &str = r#"#!/bin/sh
+echo "$@" >> "$FAKE_ORAS_LOG"
+case "$1" in
+  resolve) echo "$FAKE_ORAS_DIGEST" ;;
+  pull) cp -R "$FAKE_ORAS_ARTIFACT/." "$3" ;;
+  *) echo "Error: unsupported" >&2; exit 1 ;;
+esac
+"#;
+
+/// Set up the fake `oras`, an artifact with one file, and a consumer config.
+/// Returns the temp dir holding `bin/`, `artifact/`, `cache/` and `project/`.
+fn setup(config: &str) -> TempDir {
+    let tmp = TempDir::new().unwrap();
+    let bin = tmp.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("oras"), FAKE_ORAS).unwrap();
+    fs::set_permissions(bin.join("oras"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let artifact = tmp.path().join("artifact");
+    fs::create_dir_all(&artifact).unwrap();
+    fs::write(artifact.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(artifact.join(".editorconfig"), "root = true\n").unwrap();
+
+    let project = tmp.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join(".common-repo.yaml"), config).unwrap();
+    tmp
+}
+
+fn apply(tmp: &Path) -> assert_cmd::assert::Assert {
+    let path = format!(
+        "{}:{}",
+        tmp.join("bin").display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.join("project"))
+        .env("PATH", path)
+        .env("FAKE_ORAS_LOG", tmp.join("oras.log"))
+        .env("FAKE_ORAS_ARTIFACT", tmp.join("artifact"))
+        .env("FAKE_ORAS_DIGEST", DIGEST)
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(tmp.join("cache"))
+        .assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_pulls_oci_artifact_by_pinned_digest() {
+    let tmp = setup(&format!(
+        "- repo:\n    url: oci://ghcr.io/org/common-config:1.2.0@{}\n",
+        DIGEST
+    ));
+
+    apply(tmp.path()).success();
+
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("project/.editorconfig")).unwrap(),
+        "root = true\n"
+    );
+    let log = fs::read_to_string(tmp.path().join("oras.log")).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines[0], "resolve ghcr.io/org/common-config:1.2.0");
+    assert!(lines[1].starts_with("pull --output "));
+    assert!(lines[1].ends_with(&format!(" ghcr.io/org/common-config@{}", DIGEST)));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_rejects_moved_oci_tag() {
+    let tmp = setup(&format!(
+        "- repo:\n    url: oci://ghcr.io/org/common-config\n    ref: 1.2.0@{}\n",
+        OTHER_DIGEST
+    ));
+
+    apply(tmp.path())
+        .failure()
+        .stderr(predicate::str::contains("Digest mismatch"))
+        .stderr(predicate::str::contains("E1011"));
+
+    assert!(!tmp.path().join("project/.editorconfig").exists());
+    let log = fs::read_to_string(tmp.path().join("oras.log")).unwrap();
+    assert!(!log.contains("pull"));
+}
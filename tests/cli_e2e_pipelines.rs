@@ -0,0 +1,95 @@
+//! End-to-end tests for named pipelines (`apply --pipeline`)
+//!
+//! These tests invoke the actual CLI binary and validate its behavior
+//! from a user's perspective.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+
+fn pipeline_config(upstream: &assert_fs::TempDir) -> String {
+    format!(
+        "- repo:\n    url: file://{}\n    ref: main\n",
+        upstream.path().display()
+    )
+}
+
+fn upstream(files: &[(&str, &str)]) -> assert_fs::TempDir {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    let mut files = files.to_vec();
+    files.push((".common-repo.yaml", "- include: [\"**/*\"]\n"));
+    init_test_git_repo(&upstream, &files, None).unwrap();
+    upstream
+}
+
+/// Test that each pipeline applies only its own configuration
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_pipelines_independently() {
+    let ci = upstream(&[(".github/workflows/ci.yml", "on: push\n")]);
+    let docs = upstream(&[("docs/guide.md", "# Guide\n")]);
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo/ci.yaml")
+        .write_str(&pipeline_config(&ci))
+        .unwrap();
+    temp.child(".common-repo/docs.yaml")
+        .write_str(&pipeline_config(&docs))
+        .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args([
+            "apply",
+            "--pipeline",
+            "ci",
+            "--force-untracked",
+            "--cache-dir",
+        ])
+        .arg(cache.path())
+        .assert()
+        .success();
+    temp.child(".github/workflows/ci.yml")
+        .assert(predicate::path::exists());
+    temp.child("docs/guide.md")
+        .assert(predicate::path::missing());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args([
+            "apply",
+            "--pipeline",
+            "docs",
+            "--force-untracked",
+            "--cache-dir",
+        ])
+        .arg(cache.path())
+        .assert()
+        .success();
+    temp.child("docs/guide.md").assert("# Guide\n");
+    temp.child(".github/workflows/ci.yml").assert("on: push\n");
+}
+
+/// Test that an unknown pipeline lists the configured ones
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_unknown_pipeline_lists_available() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo/ci.yaml")
+        .write_str("- include: [\"*.md\"]\n")
+        .unwrap();
+    temp.child(".common-repo/docs.yaml")
+        .write_str("- include: [\"*.md\"]\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["apply", "--pipeline", "release"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Pipeline 'release' not found"))
+        .stderr(predicate::str::contains("Available pipelines: ci, docs"));
+}
@@ -0,0 +1,114 @@
+//! End-to-end tests for the plan command.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_plan_renames_across_upstream_and_consumer() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: [\"**/*\"]\n- rename:\n    - from: \"^src/(.*)$\"\n      to: \"lib/$1\"\n",
+            ),
+            ("src/a.rs", "a"),
+            ("README.md", "readme"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            r#"- repo:
+    url: "file://{}"
+    ref: main
+- rename:
+    - from: "^lib/(.*)$"
+      to: "pkg/$1"
+    - from: "^old/(.*)$"
+      to: "new/$1"
+"#,
+            upstream.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["plan", "--renames", "--cache-dir"])
+        .arg(temp.path().join(".common-repo/cache"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  src/a.rs -> pkg/a.rs  (chained)\n\
+             \x20     '^src/(.*)$' -> 'lib/$1': lib/a.rs\n\
+             \x20     '^lib/(.*)$' -> 'pkg/$1': pkg/a.rs\n",
+        ))
+        .stdout(predicate::str::contains(
+            "Unmatched rename patterns:\n  '^old/(.*)$' -> 'new/$1'\n",
+        ))
+        .stdout(predicate::str::contains("README.md").not());
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_plan_renames_show_descriptions() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: [\"**/*\"]\n- rename:\n    - from: \"^src/(.*)$\"\n      to: \"lib/$1\"\n  description: Consumers build from lib/\n",
+            ),
+            ("src/a.rs", "a"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            r#"- repo:
+    url: "file://{}"
+    ref: main
+- rename:
+    - from: "^old/(.*)$"
+      to: "new/$1"
+  description: Kept for checkouts of the 1.x layout
+"#,
+            upstream.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["plan", "--renames", "--cache-dir"])
+        .arg(temp.path().join(".common-repo/cache"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  src/a.rs -> lib/a.rs\n\
+             \x20     '^src/(.*)$' -> 'lib/$1': lib/a.rs  # Consumers build from lib/\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  '^old/(.*)$' -> 'new/$1'  # Kept for checkouts of the 1.x layout\n",
+        ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_plan_requires_a_view() {
+    cargo_bin_cmd!("common-repo")
+        .arg("plan")
+        .assert()
+        .code(64)
+        .stderr(predicate::str::contains("--renames"));
+}
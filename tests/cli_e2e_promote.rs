@@ -0,0 +1,118 @@
+//! End-to-end tests for the promote command.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+
+/// A project that inherits a templated README from a tagged git upstream,
+/// renamed to `docs/README.md`, and has applied it.
+fn setup() -> (assert_fs::TempDir, assert_fs::TempDir) {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (
+                ".common-repo.yaml",
+                "- include: ['**']\n- template: [README.md]\n",
+            ),
+            ("README.md", "# __COMMON_REPO__NAME__\n\nline a\nline b\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    let project = assert_fs::TempDir::new().unwrap();
+    project
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n    with:\n      - rename:\n          - '^README\\.md$': docs/README.md\n- template-vars:\n    NAME: billing\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir"])
+        .arg(project.child("cache").path())
+        .assert()
+        .success();
+    project
+        .child("docs/README.md")
+        .assert("# billing\n\nline a\nline b\n");
+    (upstream, project)
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_promote_prints_patch_against_upstream_source() {
+    let (_upstream, project) = setup();
+    project
+        .child("docs/README.md")
+        .write_str("# billing\n\nline a\nline b fixed\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["promote", "docs/README.md", "--cache-dir"])
+        .arg(project.child("cache").path())
+        .assert()
+        .success()
+        .stdout(
+            "--- a/README.md\n+++ b/README.md\n@@ -1,4 +1,4 @@\n # __COMMON_REPO__NAME__\n \n line a\n-line b\n+line b fixed\n",
+        );
+    // The working directory is left alone.
+    project
+        .child("docs/README.md")
+        .assert("# billing\n\nline a\nline b fixed\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_promote_commits_on_branch_of_upstream_clone() {
+    let (_upstream, project) = setup();
+    project
+        .child("docs/README.md")
+        .write_str("# billing\n\nline a fixed\nline b\n")
+        .unwrap();
+    let clone = project.child("clone");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .env("GIT_COMMITTER_NAME", "Test User")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .args(["promote", "docs/README.md", "--branch", "fix-line-a"])
+        .arg("--clone-dir")
+        .arg(clone.path())
+        .arg("--cache-dir")
+        .arg(project.child("cache").path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Committed the change to README.md on branch fix-line-a",
+        ));
+    clone
+        .child("README.md")
+        .assert("# __COMMON_REPO__NAME__\n\nline a fixed\nline b\n");
+    let head = std::process::Command::new("git")
+        .arg("-C")
+        .arg(clone.path())
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&head.stdout).trim(), "fix-line-a");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_promote_unchanged_file_fails() {
+    let (_upstream, project) = setup();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["promote", "docs/README.md", "--cache-dir"])
+        .arg(project.child("cache").path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("there is nothing to promote"));
+}
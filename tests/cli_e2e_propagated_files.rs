@@ -0,0 +1,104 @@
+//! End-to-end tests for telling files an earlier `apply` wrote apart from
+//! genuine local files.
+//!
+//! An upstream auto-merges `settings.yaml` into the consumer. Once applied,
+//! the file is in the consumer's working directory; it must not then be
+//! treated as local input that upstream changes are merged into, nor left
+//! behind when the upstream renames it.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A project inheriting an auto-merged `settings.yaml` from a local upstream.
+fn setup(tmp: &Path) {
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(
+        upstream.join(".common-repo.yaml"),
+        "- include: ['**']\n- yaml:\n    auto-merge: settings.yaml\n",
+    )
+    .unwrap();
+    fs::write(upstream.join("settings.yaml"), "a: 1\nb: 2\n").unwrap();
+
+    let project = tmp.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join(".common-repo.yaml"),
+        "- repo:\n    url: ../upstream\n",
+    )
+    .unwrap();
+}
+
+fn apply(tmp: &Path) {
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.join("project"))
+        .args(["apply", "--cache-root"])
+        .arg(tmp.join("cache"))
+        .assert()
+        // 1 is applied with warnings, such as merges overwriting values.
+        .code(&[0, 1][..]);
+}
+
+fn settings(tmp: &Path) -> String {
+    fs::read_to_string(tmp.join("project/settings.yaml")).unwrap()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn upstream_removals_reach_previously_applied_files() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path());
+    assert_eq!(settings(tmp.path()), "a: 1\nb: 2\n");
+
+    fs::write(tmp.path().join("upstream/settings.yaml"), "a: 1\n").unwrap();
+    apply(tmp.path());
+    assert_eq!(settings(tmp.path()), "a: 1\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn edited_files_stay_local() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path());
+    fs::write(tmp.path().join("project/settings.yaml"), "a: 1\nc: 3\n").unwrap();
+    fs::write(tmp.path().join("upstream/settings.yaml"), "a: 5\n").unwrap();
+    apply(tmp.path());
+
+    let settings = settings(tmp.path());
+    assert!(settings.contains("a: 5"), "{settings}");
+    assert!(settings.contains("c: 3"), "{settings}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn upstream_renames_move_previously_applied_files() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+    let upstream = tmp.path().join("upstream");
+    fs::create_dir_all(upstream.join("docs")).unwrap();
+    fs::write(upstream.join("docs/guide.md"), "# Guide\n").unwrap();
+
+    apply(tmp.path());
+    assert!(tmp.path().join("project/docs/guide.md").exists());
+
+    fs::create_dir_all(upstream.join("handbook")).unwrap();
+    fs::rename(
+        upstream.join("docs/guide.md"),
+        upstream.join("handbook/guide.md"),
+    )
+    .unwrap();
+    apply(tmp.path());
+
+    let project = tmp.path().join("project");
+    assert_eq!(
+        fs::read_to_string(project.join("handbook/guide.md")).unwrap(),
+        "# Guide\n"
+    );
+    assert!(!project.join("docs").exists());
+}
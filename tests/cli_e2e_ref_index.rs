@@ -0,0 +1,101 @@
+//! End-to-end tests for resolving `ref` aliases through a ref index.
+//!
+//! The upstream is a git repository cloned over `file://` with two tags,
+//! and the index is a local directory, so promoting `stable` is an edit to
+//! the index file.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// Publish `stable: <tag>` for the upstream in the index.
+fn promote(tmp: &Path, tag: &str) {
+    fs::write(
+        tmp.join("index/index.yaml"),
+        format!(
+            "file://{}:\n  stable: {}\n",
+            tmp.join("upstream").display(),
+            tag
+        ),
+    )
+    .unwrap();
+}
+
+fn setup(tmp: &Path) {
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(upstream.join("README.md"), "# Version 1\n").unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Version 1"]);
+    git(&upstream, &["tag", "v1"]);
+    fs::write(upstream.join("README.md"), "# Version 2\n").unwrap();
+    git(&upstream, &["commit", "-q", "-am", "Version 2"]);
+    git(&upstream, &["tag", "v2"]);
+
+    fs::create_dir_all(tmp.join("index")).unwrap();
+    promote(tmp, "v1");
+
+    let project = tmp.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join(".common-repo.yaml"),
+        format!(
+            "- ref-index:\n    url: ../index\n- repo:\n    url: file://{}\n    ref: stable\n",
+            upstream.display()
+        ),
+    )
+    .unwrap();
+}
+
+fn apply(tmp: &Path) -> assert_cmd::assert::Assert {
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.join("project"))
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(tmp.join("cache"))
+        .assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_follows_promotions_in_the_index() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+    let readme = tmp.path().join("project/README.md");
+
+    apply(tmp.path()).success();
+    assert_eq!(fs::read_to_string(&readme).unwrap(), "# Version 1\n");
+
+    promote(tmp.path(), "v2");
+    apply(tmp.path())
+        .success()
+        .stderr(predicate::str::contains("Already up to date").not());
+    assert_eq!(fs::read_to_string(&readme).unwrap(), "# Version 2\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_fails_on_a_missing_index_file() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+    fs::remove_file(tmp.path().join("index/index.yaml")).unwrap();
+
+    apply(tmp.path())
+        .failure()
+        .stderr(predicate::str::contains("Ref index"));
+}
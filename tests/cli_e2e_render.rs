@@ -0,0 +1,84 @@
+//! End-to-end tests for the render command.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_render_prints_file_content() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"*.txt\"]\n")
+        .unwrap();
+    temp.child("notes.txt").write_str("hello\n").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["render", "--file", "notes.txt"])
+        .assert()
+        .success()
+        .stdout("hello\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_render_missing_file_fails() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"*.txt\"]\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["render", "--file", "missing.txt"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.txt is not in the output"))
+        .stderr(predicate::str::contains("common-repo ls"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_render_config_from_stdin_with_provenance() {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: [\"**/*\"]\n"),
+            ("ci.yml", "name: ci\n"),
+        ],
+        None,
+    )
+    .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = format!(
+        "- repo:\n    url: file://{}\n    ref: main\n",
+        upstream.path().display()
+    );
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args([
+            "render",
+            "--config",
+            "-",
+            "--file",
+            "./ci.yml",
+            "--provenance",
+        ])
+        .arg("--cache-dir")
+        .arg(temp.path().join("cache"))
+        .write_stdin(config)
+        .assert()
+        .success()
+        .stdout(
+            "# common-repo render: ci.yml\n\
+             # origin: upstream\n\
+             \n\
+             name: ci\n",
+        );
+}
@@ -0,0 +1,189 @@
+//! End-to-end tests for `apply --resume`.
+//!
+//! The project inherits from an upstream git repository cloned over
+//! `file://` and patches one of its files, so a missing patch fails the run
+//! after discovery and cloning have finished.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+fn setup(tmp: &Path) {
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(upstream.join("README.md"), "# Shared\n").unwrap();
+    git(&upstream, &["init", "-q"]);
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Initial"]);
+    git(&upstream, &["tag", "v1.0.0"]);
+
+    let project = tmp.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join(".common-repo.yaml"),
+        format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n\
+             - patch:\n    file: README.md\n    patch: readme.diff\n",
+            upstream.display()
+        ),
+    )
+    .unwrap();
+}
+
+fn apply(tmp: &Path, resume: bool) -> assert_cmd::assert::Assert {
+    let mut cmd = cargo_bin_cmd!("common-repo");
+    cmd.current_dir(tmp.join("project"))
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(tmp.join("cache"));
+    if resume {
+        cmd.arg("--resume");
+    }
+    cmd.assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_resume_reuses_repository_tree_after_late_failure() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path(), false)
+        .failure()
+        .stderr(predicate::str::contains("E2006"))
+        .stderr(predicate::str::contains("--resume"));
+    assert!(tmp.path().join("cache/runs").read_dir().unwrap().count() > 0);
+
+    fs::write(
+        tmp.path().join("project/readme.diff"),
+        "--- a/README.md\n+++ b/README.md\n@@ -1 +1,2 @@\n # Shared\n+Local notes.\n",
+    )
+    .unwrap();
+    apply(tmp.path(), true)
+        .success()
+        .stderr(predicate::str::contains(
+            "Resuming from the repository tree saved by a previous run",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("project/README.md")).unwrap(),
+        "# Shared\nLocal notes.\n"
+    );
+    assert_eq!(
+        tmp.path().join("cache/runs").read_dir().unwrap().count(),
+        0,
+        "a successful run removes its checkpoints"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_without_resume_ignores_checkpoints() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path(), false).failure();
+    fs::write(
+        tmp.path().join("project/readme.diff"),
+        "--- a/README.md\n+++ b/README.md\n@@ -1 +1,2 @@\n # Shared\n+Local notes.\n",
+    )
+    .unwrap();
+    apply(tmp.path(), false)
+        .success()
+        .stderr(predicate::str::contains("Resuming").not());
+}
+
+/// The manifest files in the cache, joined, for asserting on what the last
+/// apply recorded as propagated.
+fn manifests(tmp: &Path) -> String {
+    fs::read_dir(tmp.join("cache/manifests"))
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_resume_from_final_filesystem_saves_manifest() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+    let project = tmp.path().join("project");
+    fs::write(
+        project.join("readme.diff"),
+        "--- a/README.md\n+++ b/README.md\n@@ -1 +1,2 @@\n # Shared\n+Local notes.\n",
+    )
+    .unwrap();
+    // A directory in the way fails the write after the final filesystem
+    // was saved.
+    fs::create_dir_all(project.join("README.md/blocked")).unwrap();
+    apply(tmp.path(), false).failure();
+    assert!(!tmp.path().join("cache/manifests").exists());
+
+    fs::remove_dir_all(project.join("README.md")).unwrap();
+    apply(tmp.path(), true)
+        .success()
+        .stderr(predicate::str::contains(
+            "Resuming from the final filesystem saved by a previous run",
+        ));
+    assert_eq!(
+        fs::read_to_string(project.join("README.md")).unwrap(),
+        "# Shared\nLocal notes.\n"
+    );
+    assert!(
+        manifests(tmp.path()).contains("README.md"),
+        "manifest: {}",
+        manifests(tmp.path())
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_resume_removes_files_the_upstream_renamed() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+    let upstream = tmp.path().join("upstream");
+    let project = tmp.path().join("project");
+    let config = |tag: &str| {
+        fs::write(
+            project.join(".common-repo.yaml"),
+            format!(
+                "- repo:\n    url: file://{}\n    ref: {}\n",
+                upstream.display(),
+                tag
+            ),
+        )
+        .unwrap();
+    };
+    config("v1.0.0");
+    apply(tmp.path(), false).success();
+
+    git(&upstream, &["mv", "README.md", "GUIDE.md"]);
+    git(&upstream, &["commit", "-q", "-m", "Rename"]);
+    git(&upstream, &["tag", "v2.0.0"]);
+    config("v2.0.0");
+    fs::create_dir_all(project.join("GUIDE.md/blocked")).unwrap();
+    apply(tmp.path(), false).failure();
+    assert!(project.join("README.md").exists());
+
+    fs::remove_dir_all(project.join("GUIDE.md")).unwrap();
+    apply(tmp.path(), true).success();
+    assert_eq!(
+        fs::read_to_string(project.join("GUIDE.md")).unwrap(),
+        "# Shared\n"
+    );
+    assert!(!project.join("README.md").exists());
+}
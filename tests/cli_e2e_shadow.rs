@@ -0,0 +1,137 @@
+//! End-to-end tests for composing newer upstream refs into a shadow
+//! directory with `apply --shadow-dir`.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+use std::process::Command;
+
+/// An upstream tagged v1.0.0, then v2.0.0 with `ci.yml` changed and
+/// `release.yml` added, and a project applied at v1.0.0.
+fn project() -> (assert_fs::TempDir, assert_fs::TempDir) {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            (".common-repo.yaml", "- include: ['**']\n"),
+            ("ci.yml", "on: push\n"),
+            ("lint.yml", "rules: {}\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    upstream
+        .child("ci.yml")
+        .write_str("on: pull_request\n")
+        .unwrap();
+    upstream
+        .child("release.yml")
+        .write_str("on: tag\n")
+        .unwrap();
+    for args in [
+        &["add", "."][..],
+        &["commit", "--no-verify", "-m", "Release 2.0.0"],
+        &["tag", "v2.0.0"],
+    ] {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(upstream.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    let project = assert_fs::TempDir::new().unwrap();
+    project
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: file://{}\n    ref: v1.0.0\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir", ".cache"])
+        .assert()
+        .success();
+    (upstream, project)
+}
+
+/// Test that the latest release is composed into the shadow directory and
+/// compared with the live files, which are left alone
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_shadow_dir_composes_latest_release() {
+    let (_upstream, project) = project();
+    let config = std::fs::read_to_string(project.child(".common-repo.yaml").path()).unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args([
+            "apply",
+            "--cache-dir",
+            ".cache",
+            "--shadow-dir",
+            ".common-repo/shadow",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("at v2.0.0 (live: v1.0.0)"))
+        .stdout(predicate::str::contains(
+            "1 added, 1 changed, 0 removed, 1 unchanged",
+        ));
+
+    project.child("ci.yml").assert("on: push\n");
+    project
+        .child("release.yml")
+        .assert(predicate::path::missing());
+    project.child(".common-repo.yaml").assert(config.as_str());
+    project
+        .child(".common-repo/shadow/ci.yml")
+        .assert("on: pull_request\n");
+    project
+        .child(".common-repo/shadow/release.yml")
+        .assert("on: tag\n");
+    project
+        .child(".common-repo/shadow/.common-repo/shadow-report.md")
+        .assert(predicate::str::contains("| ci.yml | changed |"))
+        .assert(predicate::str::contains("| release.yml | added |"));
+
+    // Live applies still use the configured ref.
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir", ".cache", "--force"])
+        .assert()
+        .success();
+    project.child("ci.yml").assert("on: push\n");
+}
+
+/// Test that --shadow-ref picks the ref, and must name an upstream
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_shadow_ref_selects_ref() {
+    let (upstream, project) = project();
+    let url = format!("file://{}", upstream.path().display());
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--cache-dir", ".cache", "--shadow-dir", "shadow"])
+        .arg("--shadow-ref")
+        .arg(format!("{}=v1.0.0", url))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "0 added, 0 changed, 0 removed, 2 unchanged",
+        ));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(project.path())
+        .args(["apply", "--shadow-dir", "shadow", "--shadow-ref"])
+        .arg("file:///elsewhere=v2.0.0")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no upstream has this URL"));
+}
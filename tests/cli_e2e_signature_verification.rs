@@ -0,0 +1,154 @@
+//! End-to-end tests for `verify:` on repo operations.
+//!
+//! Each test creates a throwaway GPG home with a fresh signing key and an
+//! upstream git repository with a signed tag (`v1.0.0`) and an unsigned one
+//! (`v0.9.0`), cloned over `file://`.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+const UNTRUSTED: &str = "0000111122223333444455556666777788889999";
+
+fn run(dir: &Path, gnupg: &Path, program: &str, args: &[&str]) -> String {
+    let output = Command::new(program)
+        .current_dir(dir)
+        .env("GNUPGHOME", gnupg)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{} {:?}: {}",
+        program,
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Create the key and upstream repo. Returns the key's fingerprint.
+fn setup(tmp: &Path) -> String {
+    let gnupg = tmp.join("gnupg");
+    fs::create_dir_all(&gnupg).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&gnupg, fs::Permissions::from_mode(0o700)).unwrap();
+    }
+    run(
+        tmp,
+        &gnupg,
+        "gpg",
+        &[
+            "--batch",
+            "--pinentry-mode",
+            "loopback",
+            "--passphrase",
+            "",
+            "--quick-gen-key",
+            "Publisher <publisher@example.com>",
+            "ed25519",
+            "sign",
+            "never",
+        ],
+    );
+    let keys = run(tmp, &gnupg, "gpg", &["--list-keys", "--with-colons"]);
+    let fingerprint = keys
+        .lines()
+        .find_map(|line| line.strip_prefix("fpr:"))
+        .and_then(|fields| fields.split(':').nth(8))
+        .unwrap()
+        .to_string();
+
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(upstream.join("ci.yml"), "on: push\n").unwrap();
+    let git = |args: &[&str]| {
+        let mut full = vec![
+            "-c",
+            "user.name=Publisher",
+            "-c",
+            "user.email=publisher@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ];
+        full.extend_from_slice(args);
+        run(&upstream, &gnupg, "git", &full);
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Initial"]);
+    git(&["tag", "v0.9.0"]);
+    git(&[
+        "-c",
+        &format!("user.signingkey={}", fingerprint),
+        "tag",
+        "-s",
+        "v1.0.0",
+        "-m",
+        "v1.0.0",
+    ]);
+
+    fs::create_dir_all(tmp.join("project")).unwrap();
+    fingerprint
+}
+
+fn apply(tmp: &Path, tag: &str, fingerprint: &str) -> assert_cmd::assert::Assert {
+    fs::write(
+        tmp.join("project/.common-repo.yaml"),
+        format!(
+            "- repo:\n    url: file://{}\n    ref: {}\n    verify:\n      gpg: [\"{}\"]\n",
+            tmp.join("upstream").display(),
+            tag,
+            fingerprint
+        ),
+    )
+    .unwrap();
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.join("project"))
+        .env("GNUPGHOME", tmp.join("gnupg"))
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(tmp.join("cache"))
+        .assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_accepts_tag_signed_by_trusted_key() {
+    let tmp = TempDir::new().unwrap();
+    let fingerprint = setup(tmp.path());
+
+    apply(tmp.path(), "v1.0.0", &fingerprint).success();
+
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("project/ci.yml")).unwrap(),
+        "on: push\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_rejects_untrusted_and_unsigned_refs() {
+    let tmp = TempDir::new().unwrap();
+    let fingerprint = setup(tmp.path());
+
+    apply(tmp.path(), "v1.0.0", UNTRUSTED)
+        .failure()
+        .stderr(predicate::str::contains("Signature verification failed"))
+        .stderr(predicate::str::contains("untrusted GPG key"))
+        .stderr(predicate::str::contains("E1012"));
+
+    apply(tmp.path(), "v0.9.0", &fingerprint)
+        .failure()
+        .stderr(predicate::str::contains(
+            "commit has no valid GPG signature",
+        ));
+
+    assert!(!tmp.path().join("project/ci.yml").exists());
+}
@@ -162,7 +162,7 @@ items = ["old1", "old2"]
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     let merged_content = std::fs::read_to_string(dest_file.path()).unwrap();
     let value: toml::Value = merged_content.parse().unwrap();
@@ -401,7 +401,7 @@ serde = "1.0"
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     let merged_content = std::fs::read_to_string(dest_file.path()).unwrap();
 
@@ -416,3 +416,95 @@ serde = "1.0"
     assert_eq!(parsed["name"].as_str(), Some("new-package"));
     assert_eq!(parsed["version"].as_str(), Some("1.0.0"));
 }
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_cli_toml_merge_deny_warnings() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+    let source_file = temp.child("source.toml");
+    let dest_file = temp.child("dest.toml");
+    let warnings_file = temp.child("warnings.json");
+
+    source_file.write_str("server = \"replaced\"\n").unwrap();
+    dest_file
+        .write_str("[server]\nhost = \"localhost\"\n")
+        .unwrap();
+    config_file
+        .write_str(
+            r#"
+- toml:
+    source: source.toml
+    dest: dest.toml
+"#,
+        )
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("apply")
+        .arg("--deny-warnings")
+        .arg("--warnings-json")
+        .arg(warnings_file.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--deny-warnings"));
+
+    // Nothing was written.
+    let dest = std::fs::read_to_string(dest_file.path()).unwrap();
+    assert!(dest.contains("host = \"localhost\""));
+
+    let warnings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(warnings_file.path()).unwrap()).unwrap();
+    assert_eq!(warnings[0]["phase"], "merge");
+
+    // Without --deny-warnings the merge goes ahead and the warning is shown.
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("apply")
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("Warning: merge:"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_cli_toml_merge_warning_shows_description() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let warnings_file = temp.child("warnings.json");
+
+    temp.child("source.toml")
+        .write_str("server = \"replaced\"\n")
+        .unwrap();
+    temp.child("dest.toml")
+        .write_str("[server]\nhost = \"localhost\"\n")
+        .unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(
+            r#"
+- toml:
+    source: source.toml
+    dest: dest.toml
+  description: The platform team owns the server settings
+"#,
+        )
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("apply")
+        .arg("--warnings-json")
+        .arg(warnings_file.path())
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains(
+            "(operation: The platform team owns the server settings)",
+        ));
+
+    let warnings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(warnings_file.path()).unwrap()).unwrap();
+    assert!(warnings[0]["message"]
+        .as_str()
+        .unwrap()
+        .ends_with("(operation: The platform team owns the server settings)"));
+}
@@ -302,3 +302,73 @@ fn test_tree_config_with_with_clauses() {
             "└─ https://github.com/example/templates.git @ v2.0.0",
         ));
 }
+
+/// Test that tree --format dot prints a graph with conflicting refs marked
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_tree_format_dot() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+
+    config_file
+        .write_str(
+            r#"
+- repo:
+    url: https://github.com/example/base-config.git
+    ref: main
+- repo:
+    url: https://github.com/example/base-config.git
+    ref: v2.0.0
+"#,
+        )
+        .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("common-repo");
+
+    cmd.current_dir(temp.path())
+        .arg("tree")
+        .arg("--format")
+        .arg("dot")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph common_repo {"))
+        .stdout(predicate::str::contains("n0 [label=\"local @ HEAD\"];"))
+        .stdout(predicate::str::contains(
+            "n1 [label=\"https://github.com/example/base-config.git @ main\", color=red",
+        ))
+        .stdout(predicate::str::contains("n0 -> n2;"))
+        .stdout(predicate::str::contains("Repository inheritance tree").not());
+}
+
+/// Test that tree --format mermaid prints a Mermaid flowchart
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_tree_format_mermaid() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+
+    config_file
+        .write_str(
+            r#"
+- repo:
+    url: https://github.com/example/templates.git
+    ref: v2.0.0
+"#,
+        )
+        .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("common-repo");
+
+    cmd.current_dir(temp.path())
+        .arg("tree")
+        .arg("--format")
+        .arg("mermaid")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("graph LR\n"))
+        .stdout(predicate::str::contains(
+            "n1[\"https://github.com/example/templates.git @ v2.0.0\"]",
+        ))
+        .stdout(predicate::str::contains("n0 --> n1"))
+        .stdout(predicate::str::contains("classDef").not());
+}
@@ -0,0 +1,107 @@
+//! End-to-end tests for skipping `apply` when nothing changed.
+//!
+//! The project inherits from an upstream git repository cloned over
+//! `file://`, tracking its `main` branch so new upstream commits change the
+//! fingerprint.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+const UP_TO_DATE: &str = "Already up to date";
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+fn setup(tmp: &Path) {
+    let upstream = tmp.join("upstream");
+    fs::create_dir_all(&upstream).unwrap();
+    fs::write(upstream.join(".common-repo.yaml"), "- include: ['**']\n").unwrap();
+    fs::write(upstream.join("README.md"), "# Shared\n").unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Initial"]);
+
+    let project = tmp.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(
+        project.join(".common-repo.yaml"),
+        format!(
+            "- repo:\n    url: file://{}\n    ref: main\n",
+            upstream.display()
+        ),
+    )
+    .unwrap();
+}
+
+fn apply(tmp: &Path, extra: &[&str]) -> assert_cmd::assert::Assert {
+    cargo_bin_cmd!("common-repo")
+        .current_dir(tmp.join("project"))
+        .arg("apply")
+        .arg("--cache-root")
+        .arg(tmp.join("cache"))
+        .args(extra)
+        .assert()
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_twice_skips_second_run() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path(), &[])
+        .success()
+        .stderr(predicate::str::contains(UP_TO_DATE).not());
+    apply(tmp.path(), &[])
+        .success()
+        .stderr(predicate::str::contains(UP_TO_DATE))
+        .stderr(predicate::str::contains("Applied successfully").not());
+    apply(tmp.path(), &["--force"])
+        .success()
+        .stderr(predicate::str::contains("Applied successfully"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_reruns_after_local_change() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path(), &[]).success();
+    fs::write(tmp.path().join("project/README.md"), "# Edited\n").unwrap();
+    apply(tmp.path(), &[])
+        .success()
+        .stderr(predicate::str::contains(UP_TO_DATE).not());
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("project/README.md")).unwrap(),
+        "# Shared\n"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn apply_reruns_after_upstream_commit() {
+    let tmp = TempDir::new().unwrap();
+    setup(tmp.path());
+
+    apply(tmp.path(), &[]).success();
+    let upstream = tmp.path().join("upstream");
+    fs::write(upstream.join("CONTRIBUTING.md"), "# Contributing\n").unwrap();
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "Add contributing guide"]);
+
+    apply(tmp.path(), &[])
+        .success()
+        .stderr(predicate::str::contains(UP_TO_DATE).not());
+}
@@ -1008,3 +1008,182 @@ fn test_consumer_top_level_template_vars_override_upstream_defaults() {
         content
     );
 }
+
+// =============================================================================
+// Upstream deprecation notices
+// =============================================================================
+
+/// Test that an upstream's deprecation notices are reported by apply, in the
+/// output and in the warnings JSON, while the upstream is still applied.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_upstream_deprecation_notices_reported() {
+    let upstream_repo = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream_repo,
+        &[
+            (
+                ".common-repo.yaml",
+                r#"- deprecated:
+    message: Moved to org/templates-v2
+    replacement-url: https://github.com/org/templates-v2
+- include: ["**/*"]
+- exclude: ["legacy/**"]
+  deprecated:
+    message: Legacy files are going away
+"#,
+            ),
+            ("README.md", "# Upstream README\n"),
+        ],
+        None,
+    )
+    .unwrap();
+
+    let consumer = assert_fs::TempDir::new().unwrap();
+    consumer
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: \"file://{}\"\n    ref: main\n",
+            upstream_repo.path().display()
+        ))
+        .unwrap();
+    let warnings = consumer.child("warnings.json");
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("apply")
+        .arg("--warnings-json")
+        .arg(warnings.path())
+        .assert()
+        .code(1)
+        .stderr(
+            predicate::str::contains("DEPRECATED: file://").and(predicate::str::contains(
+                "Moved to org/templates-v2 (replacement: https://github.com/org/templates-v2)",
+            )),
+        )
+        .stderr(predicate::str::contains("Operation 'exclude' of file://"));
+
+    consumer
+        .child("README.md")
+        .assert(predicate::str::contains("# Upstream README"));
+    warnings.assert(predicate::str::contains("Legacy files are going away"));
+}
+
+// =============================================================================
+// Upstream requirements of consumers
+// =============================================================================
+
+/// Test that apply fails before writing anything, listing every unmet
+/// requirement, when the consumer lacks what an upstream's `requires:`
+/// block asks for.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_upstream_requirements_checked_before_processing() {
+    let upstream_repo = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream_repo,
+        &[
+            (
+                ".common-repo.yaml",
+                r#"- requires:
+    vars: [project_name]
+    files: [Cargo.toml]
+- include: ["**/*"]
+"#,
+            ),
+            ("README.md", "# __COMMON_REPO__project_name__\n"),
+        ],
+        None,
+    )
+    .unwrap();
+
+    let consumer = assert_fs::TempDir::new().unwrap();
+    let config = format!(
+        "- repo:\n    url: \"file://{}\"\n    ref: main\n",
+        upstream_repo.path().display()
+    );
+    consumer
+        .child(".common-repo.yaml")
+        .write_str(&config)
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("apply")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Upstream requirements not met"))
+        .stderr(predicate::str::contains("template variable 'project_name'"))
+        .stderr(predicate::str::contains("file 'Cargo.toml'"));
+    consumer
+        .child("README.md")
+        .assert(predicate::path::missing());
+
+    consumer
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- template-vars:\n    project_name: demo\n{}",
+            config
+        ))
+        .unwrap();
+    consumer.child("Cargo.toml").write_str("").unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("apply")
+        .assert()
+        .success();
+    consumer
+        .child("README.md")
+        .assert(predicate::path::exists());
+}
+
+/// Test that a `rename` with `rewrite-links: relative` keeps the relative
+/// Markdown links of the upstream's docs pointing at the renamed files.
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_upstream_rename_rewrites_markdown_links() {
+    let upstream_repo = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream_repo,
+        &[
+            (
+                ".common-repo.yaml",
+                r#"- include: ["**/*"]
+- rename:
+    - "^docs/(.*)$": "handbook/$1"
+  rewrite-links: relative
+"#,
+            ),
+            ("README.md", "Start with [the guide](docs/guide.md).\n"),
+            (
+                "docs/guide.md",
+                "Back to [the README](../README.md), on to [setup](setup.md).\n",
+            ),
+            ("docs/setup.md", "# Setup\n"),
+        ],
+        None,
+    )
+    .unwrap();
+
+    let consumer = assert_fs::TempDir::new().unwrap();
+    consumer
+        .child(".common-repo.yaml")
+        .write_str(&format!(
+            "- repo:\n    url: \"file://{}\"\n    ref: main\n",
+            upstream_repo.path().display()
+        ))
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(consumer.path())
+        .arg("apply")
+        .assert()
+        .success();
+    consumer
+        .child("README.md")
+        .assert("Start with [the guide](handbook/guide.md).\n");
+    consumer
+        .child("handbook/guide.md")
+        .assert("Back to [the README](../README.md), on to [setup](setup.md).\n");
+}
@@ -165,6 +165,45 @@ fn test_validate_empty_tools_strict_mode() {
         .failure();
 }
 
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_validate_unknown_operation_warns_and_fails_strict() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config_file = temp.child(".common-repo.yaml");
+
+    config_file
+        .write_str(
+            r#"
+- include: ["**/*"]
+- not-an-operation:
+    source: third_party
+    lock: vendor.lock
+"#,
+        )
+        .unwrap();
+
+    // Without --strict the operation is reported and validation passes
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("validate")
+        .arg("--config")
+        .arg(config_file.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Operation 1 has unknown type 'not-an-operation'",
+        ));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("validate")
+        .arg("--config")
+        .arg(config_file.path())
+        .arg("--strict")
+        .assert()
+        .failure();
+}
+
 #[test]
 #[cfg_attr(not(feature = "integration-tests"), ignore)]
 fn test_validate_missing_config_file() {
@@ -0,0 +1,99 @@
+//! End-to-end tests for the `vendor:` operation
+//!
+//! These tests invoke the actual CLI binary and validate its behavior
+//! from a user's perspective.
+
+mod common;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use common::init_test_git_repo;
+use predicates::prelude::*;
+use std::process::Command;
+
+fn upstream() -> assert_fs::TempDir {
+    let upstream = assert_fs::TempDir::new().unwrap();
+    init_test_git_repo(
+        &upstream,
+        &[
+            ("bin/lint.sh", "#!/bin/sh\necho lint\n"),
+            ("README.md", "# Scripts\n"),
+        ],
+        Some("v1.0.0"),
+    )
+    .unwrap();
+    upstream
+}
+
+/// Test that apply places every file of the upstream under vendor/<name>
+/// with a provenance file
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_apply_vendor_places_files_with_provenance() {
+    let upstream = upstream();
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str(&format!(
+            "- vendor:\n    url: file://{}\n    ref: v1.0.0\n    name: scripts\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    temp.child("vendor/scripts/stale.sh")
+        .write_str("old\n")
+        .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["apply", "--force-untracked", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .success();
+
+    temp.child("vendor/scripts/bin/lint.sh")
+        .assert("#!/bin/sh\necho lint\n");
+    temp.child("vendor/scripts/README.md").assert("# Scripts\n");
+    temp.child("bin/lint.sh").assert(predicate::path::missing());
+    temp.child("vendor/scripts/.vendored.yaml").assert(
+        predicate::str::contains("ref: v1.0.0")
+            .and(predicate::str::contains("bin/lint.sh:"))
+            .and(predicate::str::contains("# Generated by common-repo")),
+    );
+}
+
+/// Test that update only bumps a vendored ref with --update-vendored
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_update_vendored_refs() {
+    let upstream = upstream();
+    Command::new("git")
+        .args(["tag", "v1.1.0"])
+        .current_dir(upstream.path())
+        .output()
+        .unwrap();
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = temp.child(".common-repo.yaml");
+    config
+        .write_str(&format!(
+            "- vendor:\n    url: file://{}\n    ref: v1.0.0\n",
+            upstream.path().display()
+        ))
+        .unwrap();
+    let cache = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["update", "--yes", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .success();
+    config.assert(predicate::str::contains("ref: v1.0.0"));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .args(["update", "--yes", "--update-vendored", "--cache-dir"])
+        .arg(cache.path())
+        .assert()
+        .success();
+    config.assert(predicate::str::contains("ref: v1.1.0"));
+}
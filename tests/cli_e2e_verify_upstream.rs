@@ -0,0 +1,92 @@
+//! End-to-end tests for the `verify-upstream` command.
+//!
+//! These tests invoke the actual CLI binary against small upstream trees and
+//! check the reported findings and exit status.
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_verify_upstream_clean() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"templates/**\"]\n")
+        .unwrap();
+    temp.child("templates/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("verify-upstream")
+        .arg("--strict")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 error(s), 0 warning(s)"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_verify_upstream_dead_pattern_fails_only_with_strict() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child(".common-repo.yaml")
+        .write_str("- include: [\"templates/**\", \"configs/**\"]\n")
+        .unwrap();
+    temp.child("templates/ci.yml")
+        .write_str("on: push\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("verify-upstream")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: operation 1 (include): pattern \"configs/**\" matches no files",
+        ));
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("verify-upstream")
+        .arg("--strict")
+        .assert()
+        .failure();
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_verify_upstream_config_in_other_directory() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let upstream = temp.child("upstream");
+    upstream
+        .child(".common-repo.yaml")
+        .write_str("- exclude: [\"internal/**\"]\n")
+        .unwrap();
+    upstream
+        .child("internal/notes.md")
+        .write_str("x\n")
+        .unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("verify-upstream")
+        .arg("--config")
+        .arg("upstream/.common-repo.yaml")
+        .arg("--strict")
+        .assert()
+        .success();
+}
+
+#[test]
+#[cfg_attr(not(feature = "integration-tests"), ignore)]
+fn test_verify_upstream_missing_config() {
+    let temp = assert_fs::TempDir::new().unwrap();
+
+    cargo_bin_cmd!("common-repo")
+        .current_dir(temp.path())
+        .arg("verify-upstream")
+        .assert()
+        .failure();
+}
@@ -158,7 +158,7 @@ fn test_cli_yaml_merge_array_replace() {
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     let merged_content = std::fs::read_to_string(dest_file.path()).unwrap();
     assert!(merged_content.contains("new_item1"));
@@ -236,7 +236,7 @@ fn test_cli_yaml_merge_create_dest() {
         .arg("--config")
         .arg(config_file.path())
         .assert()
-        .success();
+        .code(1);
 
     assert!(dest_file.path().exists());
 
@@ -26,7 +26,9 @@
 //! 5. Walk the tempdir and fail if any file outside the expected set was
 //!    produced (catches over-creation).
 //!
-//! `.git/` and `.common-repo-cache/` paths are ignored on both sides.
+//! `.git/` and `.common-repo-cache/` paths are ignored on both sides, as are
+//! the `.common-repo/map.json` apply writes, which holds absolute paths, and
+//! the `.common-repo/history.jsonl`, which holds the time of the apply.
 
 use std::collections::BTreeSet;
 use std::fs;
@@ -37,7 +39,12 @@ use tempfile::TempDir;
 
 const FIXTURE_PLACEHOLDER: &str = "__FIXTURE__";
 const CONFIG_FILE: &str = ".common-repo.yaml";
-const IGNORED_PREFIXES: &[&str] = &[".git/", ".common-repo-cache/"];
+const IGNORED_PREFIXES: &[&str] = &[
+    ".git/",
+    ".common-repo-cache/",
+    ".common-repo/map.json",
+    ".common-repo/history.jsonl",
+];
 
 /// Discover every `<name>.expected/` directory directly under `fixture_root`,
 /// run apply against each, and assert the resulting tempdir matches byte-for-byte.
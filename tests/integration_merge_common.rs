@@ -109,7 +109,8 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
 /// Result of running the common-repo CLI.
 #[derive(Debug)]
 pub struct ApplyResult {
-    /// Whether the command succeeded (exit code 0).
+    /// Whether the command applied the configuration (exit code 0, or 1
+    /// for applied with warnings).
     pub success: bool,
     /// Standard output from the command.
     pub stdout: String,
@@ -138,7 +139,7 @@ pub fn run_apply(dir: &assert_fs::TempDir, config_file: Option<&str>) -> ApplyRe
     let output = cmd.output().expect("Failed to execute common-repo apply");
 
     ApplyResult {
-        success: output.status.success(),
+        success: matches!(output.status.code(), Some(0 | 1)),
         stdout: String::from_utf8_lossy(&output.stdout).to_string(),
         stderr: String::from_utf8_lossy(&output.stderr).to_string(),
     }
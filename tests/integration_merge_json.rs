@@ -309,7 +309,7 @@ fn test_json_merge_append_unique() {
 
     apply_json_merge_operation(&mut fs, &op).unwrap();
 
-    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.clone()).unwrap();
+    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.to_vec()).unwrap();
     let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
     let items = parsed["items"].as_array().unwrap();
 
@@ -341,7 +341,7 @@ fn test_json_merge_append_position_start() {
 
     apply_json_merge_operation(&mut fs, &op).unwrap();
 
-    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.clone()).unwrap();
+    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.to_vec()).unwrap();
     let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
     let items = parsed["items"].as_array().unwrap();
 
@@ -375,7 +375,7 @@ fn test_json_merge_append_unique_position_start() {
 
     apply_json_merge_operation(&mut fs, &op).unwrap();
 
-    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.clone()).unwrap();
+    let result = String::from_utf8(fs.get_file("dest.json").unwrap().content.to_vec()).unwrap();
     let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
     let items = parsed["items"].as_array().unwrap();
 
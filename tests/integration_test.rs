@@ -335,6 +335,7 @@ fn test_basic_inheritance_pipeline() {
         &cache,
         working_dir.as_path(),
         None, // Don't write to disk for this test
+        &mut common_repo::diagnostics::Diagnostics::new(),
     );
 
     match result {
@@ -544,12 +545,16 @@ fn test_repository_sub_path_filtering_integration() {
         .expect("Failed to fetch simulated-repo-1 again");
 
     // Compare file contents
-    let content1 = fs_repo1.get_file("repo1-file.txt").unwrap().content.clone();
+    let content1 = fs_repo1
+        .get_file("repo1-file.txt")
+        .unwrap()
+        .content
+        .to_vec();
     let content1_again = fs_repo1_again
         .get_file("repo1-file.txt")
         .unwrap()
         .content
-        .clone();
+        .to_vec();
     assert_eq!(
         content1, content1_again,
         "File content should be identical across fetches"
@@ -559,12 +564,12 @@ fn test_repository_sub_path_filtering_integration() {
         .get_file(".common-repo.yaml")
         .unwrap()
         .content
-        .clone();
+        .to_vec();
     let config1_again = fs_repo1_again
         .get_file(".common-repo.yaml")
         .unwrap()
         .content
-        .clone();
+        .to_vec();
     assert_eq!(
         config1, config1_again,
         "Config content should be identical across fetches"
@@ -761,12 +766,12 @@ fn test_deep_repository_reference_chain() {
     );
 
     // Verify content integrity for the final level
-    let level4_content = fs_level4.get_file("src/main.rs").unwrap().content.clone();
+    let level4_content = fs_level4.get_file("src/main.rs").unwrap().content.to_vec();
     let level4_cached_content = _fs_level4_cached
         .get_file("src/main.rs")
         .unwrap()
         .content
-        .clone();
+        .to_vec();
     assert_eq!(
         level4_content, level4_cached_content,
         "Content should be identical between fetches"
@@ -188,6 +188,72 @@ fn test_schema_parsing(path: &Path) -> datatest_stable::Result<()> {
                     path.display()
                 );
             }
+            common_repo::config::Operation::File { file } => {
+                assert!(
+                    file.validate().is_ok(),
+                    "File operation {} in {} is invalid",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Vendor { vendor } => {
+                assert!(
+                    vendor.validate().is_ok(),
+                    "Vendor operation {} in {} is invalid",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Patch { patch } => {
+                assert!(
+                    !patch.file.is_empty() && !patch.patch.is_empty(),
+                    "Patch operation {} in {} has an empty file or patch",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Header { header } => {
+                assert!(
+                    !header.patterns.is_empty() && !header.template.is_empty(),
+                    "Header operation {} in {} has no patterns or template",
+                    idx,
+                    path.display()
+                );
+            }
+            // Formatting has a single style and nothing else to check
+            common_repo::config::Operation::Format { .. } => {}
+            common_repo::config::Operation::YamlFormat { yaml_format } => {
+                assert!(
+                    !yaml_format.patterns.is_empty(),
+                    "YAML format operation {} in {} has no patterns",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Extract { extract } => {
+                assert!(
+                    extract.validate().is_ok(),
+                    "Extract operation {} in {} is invalid",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Assert { assert } => {
+                assert!(
+                    assert.validate().is_ok(),
+                    "Assert operation {} in {} is invalid",
+                    idx,
+                    path.display()
+                );
+            }
+            common_repo::config::Operation::Unknown(unknown) => {
+                panic!(
+                    "Operation {} in {} has unknown type '{}'",
+                    idx,
+                    path.display(),
+                    unknown.name
+                );
+            }
         }
     }
 
@@ -0,0 +1,143 @@
+//! Tests for the `test_support` feature's in-memory upstreams, run through
+//! the full pipeline.
+
+use common_repo::cache::RepoCache;
+use common_repo::config;
+use common_repo::diagnostics::Diagnostics;
+use common_repo::filesystem::MemoryFS;
+use common_repo::phases::orchestrator;
+use common_repo::repository::RepositoryManager;
+use common_repo::test_support::{FakeUpstream, FakeUpstreams};
+use tempfile::TempDir;
+
+const CI_CONFIG: &str = "https://github.com/acme/ci-config";
+const BASE: &str = "https://github.com/acme/base";
+const INCLUDE_ALL: &str = "- include: ['**']\n";
+
+fn pull(yaml: &str, repo_manager: &RepositoryManager) -> common_repo::error::Result<MemoryFS> {
+    let working_dir = TempDir::new().unwrap();
+    orchestrator::execute_pull(
+        &config::parse(yaml).unwrap(),
+        repo_manager,
+        &RepoCache::new(),
+        working_dir.path(),
+        None,
+        &mut Diagnostics::new(),
+    )
+}
+
+fn content(fs: &MemoryFS, path: &str) -> String {
+    let file = fs
+        .get_file(path)
+        .unwrap_or_else(|| panic!("{} is missing", path));
+    String::from_utf8(file.content.to_vec()).unwrap()
+}
+
+#[test]
+fn pipeline_fetches_registered_upstreams() {
+    let upstreams = FakeUpstreams::new()
+        .with_upstream(
+            CI_CONFIG,
+            "v1.0.0",
+            FakeUpstream::new()
+                .with_config(&format!(
+                    "- repo:\n    url: {}\n    ref: v2\n{}",
+                    BASE, INCLUDE_ALL
+                ))
+                .with_file(".github/workflows/ci.yml", "on: push\n"),
+        )
+        .with_upstream(
+            BASE,
+            "v2",
+            FakeUpstream::new()
+                .with_config(INCLUDE_ALL)
+                .with_file("LICENSE", "MIT\n"),
+        );
+
+    let fs = pull(
+        &format!("- repo:\n    url: {}\n    ref: v1.0.0\n", CI_CONFIG),
+        &upstreams.repository_manager(),
+    )
+    .unwrap();
+
+    assert_eq!(content(&fs, ".github/workflows/ci.yml"), "on: push\n");
+    assert_eq!(content(&fs, "LICENSE"), "MIT\n");
+}
+
+#[test]
+fn fetches_can_select_a_subdirectory() {
+    let upstreams = FakeUpstreams::new().with_upstream(
+        CI_CONFIG,
+        "v1.0.0",
+        FakeUpstream::new()
+            .with_file("templates/rust/rustfmt.toml", "edition = \"2021\"\n")
+            .with_file("templates/go/.golangci.yml", "linters: {}\n"),
+    );
+
+    let fs = upstreams
+        .repository_manager()
+        .fetch_repository_with_path(CI_CONFIG, "v1.0.0", Some("templates/rust"))
+        .unwrap();
+
+    assert_eq!(content(&fs, "rustfmt.toml"), "edition = \"2021\"\n");
+    assert_eq!(fs.len(), 1);
+}
+
+#[test]
+fn replaced_upstreams_are_seen_by_existing_managers() {
+    let upstreams = FakeUpstreams::new().with_upstream(
+        CI_CONFIG,
+        "main",
+        FakeUpstream::new()
+            .with_config(INCLUDE_ALL)
+            .with_file("README.md", "# One\n"),
+    );
+    let repo_manager = upstreams.repository_manager();
+    let yaml = format!("- repo:\n    url: {}\n    ref: main\n", CI_CONFIG);
+    assert_eq!(
+        content(&pull(&yaml, &repo_manager).unwrap(), "README.md"),
+        "# One\n"
+    );
+
+    upstreams.insert(
+        CI_CONFIG,
+        "main",
+        FakeUpstream::new()
+            .with_config(INCLUDE_ALL)
+            .with_file("README.md", "# Two\n"),
+    );
+    assert_eq!(
+        content(&pull(&yaml, &repo_manager).unwrap(), "README.md"),
+        "# Two\n"
+    );
+}
+
+#[test]
+fn unregistered_upstreams_fail_to_fetch() {
+    let upstreams = FakeUpstreams::new().with_upstream(CI_CONFIG, "v1.0.0", FakeUpstream::new());
+    let repo_manager = upstreams.repository_manager();
+
+    let err = pull(
+        &format!("- repo:\n    url: {}\n    ref: v9.9.9\n", CI_CONFIG),
+        &repo_manager,
+    )
+    .unwrap_err();
+    assert!(err.is_fetch_failure(), "{}", err);
+    assert!(repo_manager.fetch_repository(BASE, "v1").is_err());
+}
+
+#[test]
+fn tags_are_the_registered_refs() {
+    let upstreams = FakeUpstreams::new()
+        .with_upstream(CI_CONFIG, "v1.0.0", FakeUpstream::new())
+        .with_upstream(CI_CONFIG, "v1.1.0", FakeUpstream::new())
+        .with_upstream(BASE, "v2", FakeUpstream::new());
+
+    assert_eq!(
+        upstreams
+            .repository_manager()
+            .list_repository_tags(CI_CONFIG)
+            .unwrap(),
+        ["v1.0.0", "v1.1.0"]
+    );
+}
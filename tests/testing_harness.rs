@@ -0,0 +1,51 @@
+//! Tests for the `testing` feature's golden-file and mutation helpers,
+//! run against the fixtures under `tests/testdata/testing-harness/`.
+
+use common_repo::testing::{self, compare, load_tree, run_pipeline};
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/testdata/testing-harness")
+        .join(name)
+}
+
+#[test]
+fn golden_matches_pipeline_output() {
+    common_repo::assert_pipeline!(
+        "tests/testdata/testing-harness/consumer-a",
+        expected = "tests/testdata/testing-harness/golden/consumer-a"
+    );
+}
+
+#[test]
+fn golden_mismatch_is_reported() {
+    let actual = run_pipeline(fixture("dead-exclude")).unwrap();
+    let golden = load_tree(fixture("golden/consumer-a")).unwrap();
+
+    let diff = compare(&actual, &golden);
+    assert_eq!(
+        diff.missing,
+        vec![
+            PathBuf::from("README.md"),
+            PathBuf::from("docs/CONTRIBUTING.md")
+        ]
+    );
+    assert_eq!(diff.unexpected, vec![PathBuf::from("CONTRIBUTING.md")]);
+    assert_eq!(diff.changed, Vec::<PathBuf>::new());
+}
+
+#[test]
+fn every_operation_affects_output() {
+    common_repo::assert_no_surviving_mutants!("tests/testdata/testing-harness/consumer-a");
+}
+
+#[test]
+fn dead_pattern_survives() {
+    let survivors = testing::surviving_mutants(fixture("dead-exclude")).unwrap();
+    let descriptions: Vec<_> = survivors.into_iter().map(|m| m.description).collect();
+    assert_eq!(
+        descriptions,
+        vec!["drop pattern \"does-not-exist.md\" from operation 2 (exclude)"]
+    );
+}